@@ -10,7 +10,7 @@
 // main.rs
 
 // --- Mock Dependencies in Cargo.toml ---
-// actix-web = "4"
+// actix-web = "4.9" # middleware::from_fn
 // actix-session = { version = "0.7", features = ["cookie-session"] }
 // serde = { version = "1.0", features = ["derive"] }
 // serde_json = "1.0"
@@ -22,8 +22,13 @@
 // rand = "0.8"
 
 use actix_web::{web, App, HttpServer, Responder, HttpResponse, Error};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::middleware::{from_fn, Next};
 use actix_session::{Session, SessionMiddleware, storage::CookieSessionStore};
-use actix_web::cookie::Key;
+use actix_web::cookie::{Cookie, Key, SameSite};
+use rand::distributions::Alphanumeric;
 use rand::Rng;
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
@@ -97,7 +102,50 @@ fn create_jwt(usr: &User) -> Result<String, jsonwebtoken::errors::Error> {
     encode(&Header::default(), &claims, &EncodingKey::from_secret(JWT_SECRET))
 }
 
-// --- 4. HANDLERS ---
+// --- 4. CSRF PROTECTION (double-submit cookie) ---
+// Browsers attach the session cookie to cross-site requests, so unsafe methods must also
+// echo the csrf cookie in a header, which another origin cannot read. Bearer-token API
+// calls carry no ambient credentials and are exempt.
+const CSRF_COOKIE: &str = "csrf_token";
+const CSRF_HEADER: &str = "x-csrf-token";
+
+fn new_csrf_token() -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect()
+}
+
+fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn csrf_guard(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let cookie_token = req.cookie(CSRF_COOKIE).map(|c| c.value().to_string());
+    let is_safe = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let is_bearer = req.headers().get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .map_or(false, |v| v.starts_with("Bearer "));
+
+    if !is_safe && !is_bearer {
+        let header_token = req.headers().get(CSRF_HEADER).and_then(|v| v.to_str().ok());
+        match (cookie_token.as_deref(), header_token) {
+            (Some(c), Some(h)) if tokens_match(c, h) => {}
+            _ => return Err(actix_web::error::ErrorForbidden("Missing or invalid CSRF token")),
+        }
+    }
+
+    let mut res = next.call(req).await?;
+    // Issue a token on the first safe request; clients read the cookie and send it back as a header
+    if is_safe && cookie_token.is_none() {
+        let cookie = Cookie::build(CSRF_COOKIE, new_csrf_token())
+            .path("/")
+            .same_site(SameSite::Strict)
+            .http_only(false)
+            .finish();
+        res.response_mut().add_cookie(&cookie)?;
+    }
+    Ok(res)
+}
+
+// --- 5. HANDLERS ---
 #[derive(Deserialize)]
 struct LoginData {
     email: String,
@@ -126,6 +174,11 @@ async fn login(data: web::Json<LoginData>, session: Session) -> impl Responder {
     HttpResponse::Unauthorized().json("Invalid credentials")
 }
 
+// Cheap GET for clients that need a csrf cookie before their first POST (e.g. login)
+async fn csrf_token() -> impl Responder {
+    HttpResponse::NoContent().finish()
+}
+
 async fn logout(session: Session) -> impl Responder {
     session.purge();
     HttpResponse::Ok().body("Logged out")
@@ -181,7 +234,7 @@ async fn publish_any_post(session: Session) -> Result<HttpResponse, Error> {
     }
 }
 
-// --- 5. MAIN SERVER SETUP ---
+// --- 6. MAIN SERVER SETUP ---
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Use a static key for simplicity in this example, but generate it for production
@@ -191,9 +244,11 @@ async fn main() -> std::io::Result<()> {
 
     HttpServer::new(move || {
         App::new()
+            .wrap(from_fn(csrf_guard))
             .wrap(SessionMiddleware::new(CookieSessionStore::default(), session_key.clone()))
             .service(
                 web::scope("/api")
+                    .route("/csrf", web::get().to(csrf_token))
                     .route("/login", web::post().to(login))
                     .route("/logout", web::post().to(logout))
                     .route("/oauth/google", web::get().to(oauth_redirect))
@@ -0,0 +1,7 @@
+//! Build script for the gRPC API in variation_1.rs: generates the `users.v1`
+//! client/server stubs picked up by `tonic::include_proto!("users.v1")`.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_build::compile_protos("proto/users.proto")?;
+    Ok(())
+}
@@ -3,31 +3,41 @@
 //! handlers (API), services (business logic), and repositories (data access).
 //! It's robust, testable, and scales well for large applications.
 
-use actix_web::{web, App, HttpServer, Responder, HttpResponse, ResponseError};
-use sea_orm::{prelude::*, sea_query::OnConflict, ActiveValue, Database, DatabaseConnection, DbErr, EntityTrait, TransactionTrait};
+use actix_web::{web, App, HttpServer, ResponseError};
+use sea_orm::{Database, DatabaseConnection, DbErr};
 use sea_orm_migration::prelude::*;
-use serde::{Deserialize, Serialize};
-use std::fmt::{Display, Formatter};
 use std::sync::Arc;
-use futures::executor::block_on;
 
 // --- 1. Error Handling ---
 #[derive(Debug, thiserror::Error)]
-enum ApiError {
+pub enum ApiError {
     #[error("Database error: {0}")]
     DbError(#[from] DbErr),
     #[error("Not found: {0}")]
     NotFound(String),
     #[error("Bad request: {0}")]
     BadRequest(String),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Internal error: {0}")]
+    Internal(String),
 }
 
 impl ResponseError for ApiError {
     fn status_code(&self) -> actix_web::http::StatusCode {
         match self {
+            ApiError::DbError(e) if resilience::is_unavailable(e) => actix_web::http::StatusCode::SERVICE_UNAVAILABLE,
             ApiError::DbError(_) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::NotFound(_) => actix_web::http::StatusCode::NOT_FOUND,
             ApiError::BadRequest(_) => actix_web::http::StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => actix_web::http::StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => actix_web::http::StatusCode::FORBIDDEN,
+            ApiError::Conflict(_) => actix_web::http::StatusCode::CONFLICT,
+            ApiError::Internal(_) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
@@ -36,6 +46,7 @@ impl ResponseError for ApiError {
 mod models {
     pub mod user {
         use super::post;
+        use super::profile;
         use super::role;
         use super::user_role;
         use sea_orm::entity::prelude::*;
@@ -59,18 +70,24 @@ mod models {
             Post,
             #[sea_orm(has_many = "user_role::Entity")]
             UserRole,
+            #[sea_orm(has_one = "profile::Entity")]
+            Profile,
         }
 
         impl Related<post::Entity> for Entity {
             fn to() -> RelationDef { Relation::Post.def() }
         }
 
+        impl Related<profile::Entity> for Entity {
+            fn to() -> RelationDef { Relation::Profile.def() }
+        }
+
         impl Related<role::Entity> for Entity {
             fn to() -> RelationDef {
-                Relation::UserRole.def()
+                user_role::Relation::Role.def()
             }
             fn via() -> Option<RelationDef> {
-                Some(user_role::Relation::Role.def().rev())
+                Some(user_role::Relation::User.def().rev())
             }
         }
 
@@ -78,7 +95,7 @@ mod models {
     }
 
     pub mod post {
-        use super::user;
+        use super::{post_tag, tag, user};
         use sea_orm::entity::prelude::*;
         use serde::{Deserialize, Serialize};
 
@@ -111,6 +128,142 @@ mod models {
                 to = "user::Column::Id"
             )]
             User,
+            #[sea_orm(has_many = "post_tag::Entity")]
+            PostTag,
+        }
+
+        impl Related<user::Entity> for Entity {
+            fn to() -> RelationDef { Relation::User.def() }
+        }
+
+        impl Related<tag::Entity> for Entity {
+            fn to() -> RelationDef {
+                post_tag::Relation::Tag.def()
+            }
+            fn via() -> Option<RelationDef> {
+                Some(post_tag::Relation::Post.def().rev())
+            }
+        }
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    pub mod comment {
+        use super::{post, user};
+        use sea_orm::entity::prelude::*;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+        #[sea_orm(table_name = "comments")]
+        pub struct Model {
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub id: Uuid,
+            pub post_id: Uuid,
+            pub author_id: Uuid,
+            // None for top-level comments
+            pub parent_comment_id: Option<Uuid>,
+            #[sea_orm(column_type = "Text")]
+            pub body: String,
+            pub created_at: ChronoDateTimeUtc,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {
+            #[sea_orm(
+                belongs_to = "post::Entity",
+                from = "Column::PostId",
+                to = "post::Column::Id"
+            )]
+            Post,
+            #[sea_orm(
+                belongs_to = "user::Entity",
+                from = "Column::AuthorId",
+                to = "user::Column::Id"
+            )]
+            Author,
+            #[sea_orm(
+                belongs_to = "Entity",
+                from = "Column::ParentCommentId",
+                to = "Column::Id"
+            )]
+            Parent,
+        }
+
+        impl Related<post::Entity> for Entity {
+            fn to() -> RelationDef { Relation::Post.def() }
+        }
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    pub mod tag {
+        use super::{post, post_tag};
+        use sea_orm::entity::prelude::*;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+        #[sea_orm(table_name = "tags")]
+        pub struct Model {
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub id: Uuid,
+            #[sea_orm(unique)]
+            pub name: String,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {
+            #[sea_orm(has_many = "post_tag::Entity")]
+            PostTag,
+        }
+
+        impl Related<post::Entity> for Entity {
+            fn to() -> RelationDef {
+                post_tag::Relation::Post.def()
+            }
+            fn via() -> Option<RelationDef> {
+                Some(post_tag::Relation::Tag.def().rev())
+            }
+        }
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    pub mod post_tag {
+        use super::{post, tag};
+        use sea_orm::entity::prelude::*;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+        #[sea_orm(table_name = "post_tags")]
+        pub struct Model {
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub post_id: Uuid,
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub tag_id: Uuid,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {
+            #[sea_orm(
+                belongs_to = "post::Entity",
+                from = "Column::PostId",
+                to = "post::Column::Id"
+            )]
+            Post,
+            #[sea_orm(
+                belongs_to = "tag::Entity",
+                from = "Column::TagId",
+                to = "tag::Column::Id"
+            )]
+            Tag,
+        }
+
+        impl Related<post::Entity> for Entity {
+            fn to() -> RelationDef { Relation::Post.def() }
+        }
+
+        impl Related<tag::Entity> for Entity {
+            fn to() -> RelationDef { Relation::Tag.def() }
         }
 
         impl ActiveModelBehavior for ActiveModel {}
@@ -179,324 +332,5570 @@ mod models {
             Role,
         }
 
+        impl Related<user::Entity> for Entity {
+            fn to() -> RelationDef { Relation::User.def() }
+        }
+
+        impl Related<role::Entity> for Entity {
+            fn to() -> RelationDef { Relation::Role.def() }
+        }
+
         impl ActiveModelBehavior for ActiveModel {}
     }
 
-    pub mod dtos {
-        use serde::Deserialize;
-        use uuid::Uuid;
+    pub mod profile {
+        use super::user;
+        use sea_orm::entity::prelude::*;
+        use serde::{Deserialize, Serialize};
 
-        #[derive(Deserialize)]
-        pub struct CreateUserDto {
-            pub email: String,
-            pub password: String,
+        #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+        #[sea_orm(table_name = "profiles")]
+        pub struct Model {
+            // One-to-one with users: the user id doubles as the primary key
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub user_id: Uuid,
+            pub display_name: Option<String>,
+            #[sea_orm(column_type = "Text", nullable)]
+            pub bio: Option<String>,
+            pub avatar_url: Option<String>,
+            pub locale: Option<String>,
+            pub timezone: Option<String>,
+            pub updated_at: ChronoDateTimeUtc,
         }
 
-        #[derive(Deserialize)]
-        pub struct UserFilterDto {
-            pub is_active: Option<bool>,
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {
+            #[sea_orm(
+                belongs_to = "user::Entity",
+                from = "Column::UserId",
+                to = "user::Column::Id"
+            )]
+            User,
         }
 
-        #[derive(Deserialize)]
-        pub struct AssignRoleDto {
-            pub role_name: String,
+        impl Related<user::Entity> for Entity {
+            fn to() -> RelationDef { Relation::User.def() }
         }
-    }
-}
 
-// --- 3. Repository Layer (repositories/user_repository.rs) ---
-mod repositories {
-    use super::models::{user, role, user_role, dtos::UserFilterDto};
-    use sea_orm::{prelude::*, ActiveValue, ColumnTrait, Condition, DbConn, DbErr, EntityTrait, QueryFilter, QuerySelect};
+        impl ActiveModelBehavior for ActiveModel {}
+    }
 
-    pub struct UserRepository;
+    pub mod feature_flag {
+        use sea_orm::entity::prelude::*;
+        use serde::{Deserialize, Serialize};
 
-    impl UserRepository {
-        pub async fn find_by_id(db: &DbConn, id: Uuid) -> Result<Option<user::Model>, DbErr> {
-            user::Entity::find_by_id(id).one(db).await
+        #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+        #[sea_orm(table_name = "feature_flags")]
+        pub struct Model {
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub name: String,
+            pub enabled: bool,
+            // 0-100; share of subjects that see the flag when it is enabled
+            pub rollout_percentage: i16,
+            pub description: Option<String>,
+            pub updated_at: ChronoDateTimeUtc,
         }
 
-        pub async fn find_by_email(db: &DbConn, email: &str) -> Result<Option<user::Model>, DbErr> {
-            user::Entity::find().filter(user::Column::Email.eq(email)).one(db).await
-        }
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
 
-        pub async fn find_all_with_filter(db: &DbConn, filter: UserFilterDto) -> Result<Vec<user::Model>, DbErr> {
-            let mut select = user::Entity::find();
-            if let Some(is_active) = filter.is_active {
-                select = select.filter(user::Column::IsActive.eq(is_active));
-            }
-            select.all(db).await
-        }
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    pub mod feature_flag_override {
+        use sea_orm::entity::prelude::*;
+        use serde::{Deserialize, Serialize};
 
-        pub async fn save(db: &DbConn, user_model: user::ActiveModel) -> Result<user::Model, DbErr> {
-            user_model.insert(db).await
+        #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+        #[sea_orm(table_name = "feature_flag_overrides")]
+        pub struct Model {
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub flag_name: String,
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub tenant_id: String,
+            pub enabled: bool,
         }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
+
+        impl ActiveModelBehavior for ActiveModel {}
     }
 
-    pub struct RoleRepository;
+    pub mod tenant_setting {
+        use sea_orm::entity::prelude::*;
+        use serde::{Deserialize, Serialize};
 
-    impl RoleRepository {
-        pub async fn find_by_name(db: &DbConn, name: &str) -> Result<Option<role::Model>, DbErr> {
-            role::Entity::find().filter(role::Column::Name.eq(name)).one(db).await
+        #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+        #[sea_orm(table_name = "tenant_settings")]
+        pub struct Model {
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub tenant_id: String,
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub key: String,
+            #[sea_orm(column_type = "Json")]
+            pub value: Json,
+            pub updated_at: ChronoDateTimeUtc,
         }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
+
+        impl ActiveModelBehavior for ActiveModel {}
     }
 
-    pub struct UserRoleRepository;
+    pub mod saved_search {
+        use sea_orm::entity::prelude::*;
+        use serde::{Deserialize, Serialize};
 
-    impl UserRoleRepository {
-        pub async fn assign_role_to_user(txn: &DatabaseTransaction, user_id: Uuid, role_id: Uuid) -> Result<(), DbErr> {
-            let user_role = user_role::ActiveModel {
-                user_id: ActiveValue::Set(user_id),
-                role_id: ActiveValue::Set(role_id),
-            };
-            user_role::Entity::insert(user_role).exec(txn).await?;
-            Ok(())
+        #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+        #[sea_orm(table_name = "saved_searches")]
+        pub struct Model {
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub id: Uuid,
+            pub user_id: Uuid,
+            pub name: String,
+            // Filterable::RESOURCE of the listing it runs against
+            pub resource: String,
+            // A filters::FilterDocument
+            #[sea_orm(column_type = "Json")]
+            pub document: Json,
+            pub created_at: ChronoDateTimeUtc,
         }
-    }
-}
 
-// --- 4. Service Layer (services/user_service.rs) ---
-mod services {
-    use super::models::{dtos::CreateUserDto, user, role};
-    use super::repositories::{UserRepository, RoleRepository, UserRoleRepository};
-    use super::ApiError;
-    use sea_orm::{prelude::*, ActiveValue, DatabaseConnection, TransactionTrait};
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
 
-    pub struct UserService {
-        db: Arc<DatabaseConnection>,
+        impl ActiveModelBehavior for ActiveModel {}
     }
 
-    impl UserService {
-        pub fn new(db: Arc<DatabaseConnection>) -> Self {
-            Self { db }
+    pub mod dtos {
+        use super::super::filters::FilterDocument;
+        use sea_orm::FromQueryResult;
+        use serde::{Deserialize, Deserializer, Serialize};
+        use uuid::Uuid;
+
+        #[derive(Deserialize)]
+        pub struct CreateUserDto {
+            pub email: String,
+            // Accepted but not read yet: hashing is omitted for brevity
+            #[allow(dead_code)]
+            pub password: String,
         }
 
-        // Demonstrates Transaction and Rollback
-        pub async fn create_user_with_default_role(&self, user_data: CreateUserDto) -> Result<user::Model, ApiError> {
-            let txn = self.db.begin().await?;
+        /// The public view of a user. `password_hash` stays on the server.
+        #[derive(Serialize)]
+        pub struct UserResponse {
+            pub id: Uuid,
+            pub email: String,
+            pub is_active: bool,
+            pub created_at: chrono::DateTime<chrono::Utc>,
+        }
 
-            // Check if user exists
-            if UserRepository::find_by_email(&txn, &user_data.email).await?.is_some() {
-                return Err(ApiError::BadRequest("Email already exists".to_string()));
+        impl From<super::user::Model> for UserResponse {
+            fn from(user: super::user::Model) -> Self {
+                Self {
+                    id: user.id,
+                    email: user.email,
+                    is_active: user.is_active,
+                    created_at: user.created_at,
+                }
             }
+        }
 
-            // Find default role
-            let user_role = RoleRepository::find_by_name(&txn, "USER").await?
-                .ok_or_else(|| ApiError::NotFound("Default role 'USER' not found".to_string()))?;
+        #[derive(Deserialize)]
+        pub struct AssignRoleDto {
+            pub role_name: String,
+        }
 
-            // Create user
-            let new_user = user::ActiveModel {
-                id: ActiveValue::Set(Uuid::new_v4()),
-                email: ActiveValue::Set(user_data.email),
-                password_hash: ActiveValue::Set("...hashed_password...".to_string()), // Hashing omitted for brevity
-                is_active: ActiveValue::Set(true),
-                created_at: ActiveValue::Set(chrono::Utc::now()),
-            };
-            let user = new_user.insert(&txn).await?;
+        #[derive(Deserialize)]
+        pub struct ReplaceRolesDto {
+            pub role_names: Vec<String>,
+        }
 
-            // Assign role
-            UserRoleRepository::assign_role_to_user(&txn, user.id, user_role.id).await?;
+        #[derive(Deserialize)]
+        pub struct CreateRoleDto {
+            pub name: String,
+        }
 
-            txn.commit().await?;
-            Ok(user)
+        #[derive(Deserialize)]
+        pub struct UpdateRoleDto {
+            pub name: Option<String>,
         }
 
-        pub async fn find_user_posts(&self, user_id: Uuid) -> Result<Vec<super::models::post::Model>, ApiError> {
-            let user = UserRepository::find_by_id(&*self.db, user_id).await?
-                .ok_or_else(|| ApiError::NotFound(format!("User with id {} not found", user_id)))?;
-            
-            let posts = user.find_related(super::models::post::Entity).all(&*self.db).await?;
-            Ok(posts)
+        // Distinguishes an absent field (None) from an explicit null (Some(None)) for merge patches
+        fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+        where
+            D: Deserializer<'de>,
+            T: Deserialize<'de>,
+        {
+            Option::<T>::deserialize(deserializer).map(Some)
         }
-    }
-}
 
-// --- 5. Handler Layer (handlers/user_handler.rs) ---
-mod handlers {
-    use super::models::dtos::{CreateUserDto, UserFilterDto, AssignRoleDto};
-    use super::services::UserService;
-    use super::ApiError;
-    use super::repositories::{UserRepository, RoleRepository};
-    use actix_web::{web, HttpResponse, Responder};
-    use sea_orm::{DatabaseConnection, EntityTrait, ModelTrait};
-    use std::sync::Arc;
-    use uuid::Uuid;
+        /// RFC 7386 JSON Merge Patch body for a profile: absent fields are left untouched,
+        /// `null` clears the field, and any other value replaces it.
+        #[derive(Deserialize, Default)]
+        pub struct ProfileMergePatchDto {
+            #[serde(default, deserialize_with = "nullable")]
+            pub display_name: Option<Option<String>>,
+            #[serde(default, deserialize_with = "nullable")]
+            pub bio: Option<Option<String>>,
+            #[serde(default, deserialize_with = "nullable")]
+            pub avatar_url: Option<Option<String>>,
+            #[serde(default, deserialize_with = "nullable")]
+            pub locale: Option<Option<String>>,
+            #[serde(default, deserialize_with = "nullable")]
+            pub timezone: Option<Option<String>>,
+        }
 
-    pub async fn create_user(
-        user_service: web::Data<UserService>,
-        user_data: web::Json<CreateUserDto>,
+        #[derive(Deserialize)]
+        pub struct CreateCommentDto {
+            pub body: String,
+            pub parent_comment_id: Option<Uuid>,
+        }
+
+        #[derive(Deserialize)]
+        pub struct CommentPageQuery {
+            pub page: Option<u64>,
+            pub per_page: Option<u64>,
+            pub max_depth: Option<usize>,
+        }
+
+        #[derive(Serialize)]
+        pub struct CommentNodeDto {
+            pub id: Uuid,
+            pub author_id: Uuid,
+            pub body: String,
+            pub created_at: chrono::DateTime<chrono::Utc>,
+            pub replies: Vec<CommentNodeDto>,
+            // Set when replies exist beyond the requested depth
+            pub has_more_replies: bool,
+        }
+
+        #[derive(Serialize)]
+        pub struct CommentPageDto {
+            pub page: u64,
+            pub per_page: u64,
+            pub total_top_level: u64,
+            pub comments: Vec<CommentNodeDto>,
+        }
+
+        #[derive(Deserialize)]
+        pub struct SetPostTagsDto {
+            pub tags: Vec<String>,
+        }
+
+        #[derive(Deserialize)]
+        pub struct TagSearchQuery {
+            pub prefix: Option<String>,
+            pub limit: Option<u64>,
+        }
+
+        #[derive(Serialize, FromQueryResult)]
+        pub struct TagWithCountDto {
+            pub id: Uuid,
+            pub name: String,
+            pub post_count: i64,
+        }
+
+        #[derive(Serialize)]
+        pub struct TaggedPostsDto {
+            pub tag: String,
+            pub post_count: usize,
+            pub posts: Vec<super::post::Model>,
+        }
+
+        #[derive(Deserialize)]
+        pub struct UpsertFeatureFlagDto {
+            pub enabled: bool,
+            #[serde(default = "full_rollout")]
+            pub rollout_percentage: i16,
+            pub description: Option<String>,
+        }
+
+        fn full_rollout() -> i16 {
+            100
+        }
+
+        #[derive(Deserialize)]
+        pub struct TenantOverrideDto {
+            pub enabled: bool,
+        }
+
+        #[derive(Deserialize)]
+        pub struct TenantSettingDto {
+            pub value: serde_json::Value,
+        }
+
+        #[derive(Deserialize)]
+        pub struct DeleteRoleQuery {
+            // Must be set to remove a role that is still assigned to users
+            #[serde(default)]
+            pub cascade: bool,
+        }
+
+        #[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+        #[serde(rename_all = "lowercase")]
+        pub enum TimeBucket {
+            #[default]
+            Day,
+            // ISO weeks, labelled by their Monday
+            Week,
+        }
+
+        #[derive(Deserialize)]
+        pub struct UserStatsQuery {
+            #[serde(default)]
+            pub group_by: TimeBucket,
+            // e.g. "30d" or "12w"; defaults to 30 days
+            pub range: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        pub struct PostStatsQuery {
+            pub group_by: Option<String>,
+        }
+
+        #[derive(Serialize, FromQueryResult)]
+        pub struct BucketCountDto {
+            pub bucket: String, // YYYY-MM-DD
+            pub count: i64,
+        }
+
+        #[derive(Serialize)]
+        pub struct UserStatsDto {
+            pub group_by: TimeBucket,
+            pub from: chrono::NaiveDate,
+            pub to: chrono::NaiveDate,
+            pub buckets: Vec<BucketCountDto>,
+        }
+
+        #[derive(Serialize, FromQueryResult)]
+        pub struct StatusCountDto {
+            pub status: super::post::PostStatus,
+            pub count: i64,
+        }
+
+        /// Bumped whenever the backup layout changes; imports refuse newer versions.
+        /// Version 2 added profiles, tags, post tags and comments.
+        pub const BACKUP_SCHEMA_VERSION: u32 = 2;
+
+        #[derive(Serialize, Deserialize)]
+        pub struct BackupDto {
+            // Serialized first so tools can check it without parsing the whole file
+            pub schema_version: u32,
+            pub exported_at: chrono::DateTime<chrono::Utc>,
+            pub users: Vec<super::user::Model>,
+            pub roles: Vec<super::role::Model>,
+            pub user_roles: Vec<super::user_role::Model>,
+            pub posts: Vec<super::post::Model>,
+            // Missing from version 1 backups, which load as if these tables were empty
+            #[serde(default)]
+            pub profiles: Vec<super::profile::Model>,
+            #[serde(default)]
+            pub tags: Vec<super::tag::Model>,
+            #[serde(default)]
+            pub post_tags: Vec<super::post_tag::Model>,
+            #[serde(default)]
+            pub comments: Vec<super::comment::Model>,
+        }
+
+        #[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+        #[serde(rename_all = "lowercase")]
+        pub enum ImportMode {
+            // Upsert by primary key and keep rows that are not in the backup
+            #[default]
+            Merge,
+            // Wipe the backed-up tables first
+            Replace,
+        }
+
+        #[derive(Deserialize)]
+        pub struct ImportBackupQuery {
+            #[serde(default)]
+            pub mode: ImportMode,
+        }
+
+        #[derive(Serialize)]
+        pub struct ImportSummaryDto {
+            pub mode: ImportMode,
+            pub schema_version: u32,
+            pub users: usize,
+            pub roles: usize,
+            pub user_roles: usize,
+            pub posts: usize,
+            pub profiles: usize,
+            pub tags: usize,
+            pub post_tags: usize,
+            pub comments: usize,
+        }
+
+        #[derive(Deserialize)]
+        pub struct CreateSavedSearchDto {
+            pub name: String,
+            pub resource: String,
+            #[serde(flatten)]
+            pub search: FilterDocument,
+        }
+
+        #[derive(Serialize)]
+        pub struct SavedSearchDto {
+            pub name: String,
+            pub resource: String,
+            #[serde(flatten)]
+            pub search: FilterDocument,
+            pub created_at: chrono::DateTime<chrono::Utc>,
+        }
+
+        #[derive(Deserialize)]
+        pub struct SavedSearchQuery {
+            pub saved_search: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        pub struct IncludeQuery {
+            // Comma-separated relation paths, e.g. "posts.tags,roles"
+            pub include: Option<String>,
+        }
+
+        /// A user plus whichever relations `?include=` asked for. Relations that were
+        /// not requested are left out, not sent as empty lists.
+        #[derive(Serialize)]
+        pub struct UserWithIncludesDto {
+            #[serde(flatten)]
+            pub user: UserResponse,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub posts: Option<Vec<PostWithIncludesDto>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub roles: Option<Vec<super::role::Model>>,
+            // Some(None) is sent as null: requested, but the user has no profile yet
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub profile: Option<Option<super::profile::Model>>,
+        }
+
+        #[derive(Serialize)]
+        pub struct PostWithIncludesDto {
+            #[serde(flatten)]
+            pub post: super::post::Model,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub tags: Option<Vec<super::tag::Model>>,
+        }
+    }
+}
+
+// --- 3. Database Resilience (db/resilience.rs) ---
+mod resilience {
+    use super::instrumentation::{self, QueryStats};
+    use rand::Rng;
+    use sea_orm::{
+        AccessMode, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, DbErr, ExecResult,
+        IsolationLevel, QueryResult, Statement, StreamTrait, TransactionError, TransactionTrait,
+    };
+    use serde::Serialize;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    pub const CIRCUIT_OPEN: &str = "Database circuit breaker is open";
+
+    /// Failures caused by the connection rather than the statement. Only these are
+    /// retried and counted against the breaker; a constraint violation means the
+    /// database is up and answering.
+    pub fn is_transient(err: &DbErr) -> bool {
+        matches!(err, DbErr::ConnectionAcquire(_) | DbErr::Conn(_))
+    }
+
+    /// Errors that should surface as 503 rather than 500.
+    pub fn is_unavailable(err: &DbErr) -> bool {
+        is_transient(err) || matches!(err, DbErr::Custom(msg) if msg == CIRCUIT_OPEN)
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum BreakerState {
+        Closed,
+        Open,
+        HalfOpen,
+    }
+
+    struct BreakerInner {
+        state: BreakerState,
+        consecutive_failures: u32,
+        changed_at: Instant,
+        probe_in_flight: bool,
+    }
+
+    /// Trips after `failure_threshold` consecutive transient failures and rejects calls
+    /// for `open_for`. After that a single probe is let through; its outcome decides
+    /// whether the breaker closes or opens again.
+    pub struct CircuitBreaker {
+        failure_threshold: u32,
+        open_for: Duration,
+        inner: Mutex<BreakerInner>,
+    }
+
+    impl CircuitBreaker {
+        pub fn new(failure_threshold: u32, open_for: Duration) -> Self {
+            Self {
+                failure_threshold,
+                open_for,
+                inner: Mutex::new(BreakerInner {
+                    state: BreakerState::Closed,
+                    consecutive_failures: 0,
+                    changed_at: Instant::now(),
+                    probe_in_flight: false,
+                }),
+            }
+        }
+
+        fn try_acquire(&self) -> bool {
+            let mut inner = self.inner.lock().unwrap();
+            match inner.state {
+                BreakerState::Closed => true,
+                // A probe whose caller was dropped must not wedge the breaker, so a
+                // new probe is allowed once another `open_for` has passed.
+                BreakerState::Open | BreakerState::HalfOpen => {
+                    let waited = inner.changed_at.elapsed() >= self.open_for;
+                    let may_probe = match inner.state {
+                        BreakerState::Open => waited,
+                        _ => !inner.probe_in_flight || waited,
+                    };
+                    if may_probe {
+                        inner.state = BreakerState::HalfOpen;
+                        inner.changed_at = Instant::now();
+                        inner.probe_in_flight = true;
+                    }
+                    may_probe
+                }
+            }
+        }
+
+        fn record_success(&self) {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.state != BreakerState::Closed {
+                inner.changed_at = Instant::now();
+            }
+            inner.state = BreakerState::Closed;
+            inner.consecutive_failures = 0;
+            inner.probe_in_flight = false;
+        }
+
+        /// Returns true if this failure tripped the breaker.
+        fn record_failure(&self) -> bool {
+            let mut inner = self.inner.lock().unwrap();
+            inner.consecutive_failures += 1;
+            inner.probe_in_flight = false;
+            let should_open = inner.state == BreakerState::HalfOpen || inner.consecutive_failures >= self.failure_threshold;
+            if should_open && inner.state != BreakerState::Open {
+                inner.state = BreakerState::Open;
+                inner.changed_at = Instant::now();
+                return true;
+            }
+            false
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct RetryPolicy {
+        pub max_attempts: u32,
+        pub base_delay: Duration,
+        pub max_delay: Duration,
+    }
+
+    impl Default for RetryPolicy {
+        fn default() -> Self {
+            Self { max_attempts: 3, base_delay: Duration::from_millis(50), max_delay: Duration::from_secs(1) }
+        }
+    }
+
+    impl RetryPolicy {
+        // Full jitter: a random delay up to the exponential ceiling
+        fn delay_for(&self, attempt: u32) -> Duration {
+            let ceiling = self.base_delay.saturating_mul(1 << attempt.min(16)).min(self.max_delay);
+            let millis = rand::thread_rng().gen_range(0..=ceiling.as_millis() as u64);
+            Duration::from_millis(millis)
+        }
+    }
+
+    #[derive(Default)]
+    struct Counters {
+        calls: AtomicU64,
+        failures: AtomicU64,
+        retries: AtomicU64,
+        rejected: AtomicU64,
+        trips: AtomicU64,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct ResilienceSnapshot {
+        pub state: BreakerState,
+        pub consecutive_failures: u32,
+        pub calls: u64,
+        pub failures: u64,
+        pub retries: u64,
+        pub rejected: u64,
+        pub trips: u64,
+    }
+
+    /// A `DatabaseConnection` that retries transient failures and short-circuits while
+    /// the database is down. It implements `ConnectionTrait`, so repositories take it
+    /// like any other connection. Only `begin` is guarded: it hands back a
+    /// `TimedTransaction`, whose statements are timed into the same `QueryStats` but
+    /// never retried. Every statement run outside a transaction is timed too.
+    // With the `fault-injection` feature the injector sits between this layer and the
+    // pool, so retries and the breaker see injected failures exactly like real ones.
+    #[cfg(feature = "fault-injection")]
+    type Inner = super::faults::FaultyConnection;
+    #[cfg(not(feature = "fault-injection"))]
+    type Inner = DatabaseConnection;
+
+    pub struct ResilientConnection {
+        inner: Inner,
+        breaker: CircuitBreaker,
+        retry: RetryPolicy,
+        counters: Counters,
+        stats: Arc<QueryStats>,
+    }
+
+    impl ResilientConnection {
+        pub fn new(inner: DatabaseConnection) -> Self {
+            Self::with_policy(inner, CircuitBreaker::new(5, Duration::from_secs(10)), RetryPolicy::default())
+        }
+
+        pub fn with_policy(inner: DatabaseConnection, breaker: CircuitBreaker, retry: RetryPolicy) -> Self {
+            #[cfg(feature = "fault-injection")]
+            let inner = super::faults::FaultyConnection::new(
+                inner,
+                std::sync::Arc::new(super::faults::FaultInjector::from_env()),
+            );
+            Self::wrap(inner, breaker, retry)
+        }
+
+        /// Like `with_policy`, but with an injector the test keeps a handle on
+        /// instead of one configured from the environment.
+        #[cfg(all(test, feature = "fault-injection"))]
+        pub fn with_faults(
+            inner: DatabaseConnection,
+            breaker: CircuitBreaker,
+            retry: RetryPolicy,
+            injector: std::sync::Arc<super::faults::FaultInjector>,
+        ) -> Self {
+            Self::wrap(super::faults::FaultyConnection::new(inner, injector), breaker, retry)
+        }
+
+        fn wrap(inner: Inner, breaker: CircuitBreaker, retry: RetryPolicy) -> Self {
+            Self {
+                inner,
+                breaker,
+                retry,
+                counters: Counters::default(),
+                stats: Arc::new(QueryStats::new(instrumentation::slow_threshold_from_env())),
+            }
+        }
+
+        pub fn query_stats(&self) -> &QueryStats {
+            &self.stats
+        }
+
+        #[cfg(feature = "fault-injection")]
+        pub fn fault_injector(&self) -> &std::sync::Arc<super::faults::FaultInjector> {
+            self.inner.injector()
+        }
+
+        pub fn snapshot(&self) -> ResilienceSnapshot {
+            let (state, consecutive_failures) = {
+                let inner = self.breaker.inner.lock().unwrap();
+                (inner.state, inner.consecutive_failures)
+            };
+            ResilienceSnapshot {
+                state,
+                consecutive_failures,
+                calls: self.counters.calls.load(Ordering::Relaxed),
+                failures: self.counters.failures.load(Ordering::Relaxed),
+                retries: self.counters.retries.load(Ordering::Relaxed),
+                rejected: self.counters.rejected.load(Ordering::Relaxed),
+                trips: self.counters.trips.load(Ordering::Relaxed),
+            }
+        }
+
+        /// Runs `SELECT 1` through the breaker. Health checks use it, so once `open_for`
+        /// has passed they act as the half-open probe that can close the breaker again.
+        /// It is not timed into `QueryStats`.
+        pub async fn ping(&self) -> Result<(), DbErr> {
+            let stmt = Statement::from_string(self.inner.get_database_backend(), "SELECT 1");
+            self.call(true, || self.inner.query_one(stmt.clone())).await.map(|_| ())
+        }
+
+        // Measures what the caller waited for, retries and backoff included
+        async fn timed<T, Fut>(&self, sql: &str, params: usize, fut: Fut) -> Result<T, DbErr>
+        where
+            Fut: Future<Output = Result<T, DbErr>>,
+        {
+            self.stats.time(sql, params, fut).await
+        }
+
+        /// Opens a transaction through the breaker. This shadows `TransactionTrait::begin`
+        /// so that every `db.begin()` in the services gets a `TimedTransaction`.
+        pub async fn begin(&self) -> Result<TimedTransaction, DbErr> {
+            let inner = TransactionTrait::begin(self).await?;
+            Ok(TimedTransaction {
+                inner,
+                stats: self.stats.clone(),
+                #[cfg(feature = "fault-injection")]
+                injector: self.inner.injector().clone(),
+            })
+        }
+
+        /// `idempotent` operations are retried on any transient error. Others only when
+        /// no connection was acquired, since then the statement never reached the server.
+        async fn call<T, F, Fut>(&self, idempotent: bool, mut op: F) -> Result<T, DbErr>
+        where
+            F: FnMut() -> Fut,
+            Fut: Future<Output = Result<T, DbErr>>,
+        {
+            let mut attempt = 1;
+            loop {
+                if !self.breaker.try_acquire() {
+                    self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                    return Err(DbErr::Custom(CIRCUIT_OPEN.to_string()));
+                }
+                self.counters.calls.fetch_add(1, Ordering::Relaxed);
+                match op().await {
+                    Err(err) if is_transient(&err) => {
+                        self.counters.failures.fetch_add(1, Ordering::Relaxed);
+                        if self.breaker.record_failure() {
+                            self.counters.trips.fetch_add(1, Ordering::Relaxed);
+                            eprintln!("Database circuit breaker opened after error: {}", err);
+                        }
+                        let retryable = idempotent || matches!(err, DbErr::ConnectionAcquire(_));
+                        if !retryable || attempt >= self.retry.max_attempts {
+                            return Err(err);
+                        }
+                        self.counters.retries.fetch_add(1, Ordering::Relaxed);
+                        actix_web::rt::time::sleep(self.retry.delay_for(attempt)).await;
+                        attempt += 1;
+                    }
+                    result => {
+                        self.breaker.record_success();
+                        return result;
+                    }
+                }
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ConnectionTrait for ResilientConnection {
+        fn get_database_backend(&self) -> DbBackend {
+            self.inner.get_database_backend()
+        }
+
+        async fn execute(&self, stmt: Statement) -> Result<ExecResult, DbErr> {
+            self.timed(&stmt.sql, bind_count(&stmt), self.call(false, || self.inner.execute(stmt.clone()))).await
+        }
+
+        async fn execute_unprepared(&self, sql: &str) -> Result<ExecResult, DbErr> {
+            self.timed(sql, 0, self.call(false, || self.inner.execute_unprepared(sql))).await
+        }
+
+        async fn query_one(&self, stmt: Statement) -> Result<Option<QueryResult>, DbErr> {
+            self.timed(&stmt.sql, bind_count(&stmt), self.call(true, || self.inner.query_one(stmt.clone()))).await
+        }
+
+        async fn query_all(&self, stmt: Statement) -> Result<Vec<QueryResult>, DbErr> {
+            self.timed(&stmt.sql, bind_count(&stmt), self.call(true, || self.inner.query_all(stmt.clone()))).await
+        }
+
+        fn support_returning(&self) -> bool {
+            self.inner.support_returning()
+        }
+
+        fn is_mock_connection(&self) -> bool {
+            self.inner.is_mock_connection()
+        }
+    }
+
+    /// A `DatabaseTransaction` whose statements are recorded in `QueryStats`. Nothing in
+    /// it is retried: a failed statement leaves the transaction for the caller to drop.
+    // With `fault-injection` its statements ask the same injector first, so a test can
+    // fail a write halfway through a transaction and watch it roll back.
+    pub struct TimedTransaction {
+        inner: DatabaseTransaction,
+        stats: Arc<QueryStats>,
+        #[cfg(feature = "fault-injection")]
+        injector: Arc<super::faults::FaultInjector>,
+    }
+
+    impl TimedTransaction {
+        pub async fn commit(self) -> Result<(), DbErr> {
+            self.stats.time("COMMIT", 0, self.inner.commit()).await
+        }
+
+        async fn before_statement(&self) -> Result<(), DbErr> {
+            #[cfg(feature = "fault-injection")]
+            self.injector.before_db_call().await?;
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ConnectionTrait for TimedTransaction {
+        fn get_database_backend(&self) -> DbBackend {
+            self.inner.get_database_backend()
+        }
+
+        async fn execute(&self, stmt: Statement) -> Result<ExecResult, DbErr> {
+            self.before_statement().await?;
+            let params = bind_count(&stmt);
+            let sql = stmt.sql.clone();
+            self.stats.time(&sql, params, self.inner.execute(stmt)).await
+        }
+
+        async fn execute_unprepared(&self, sql: &str) -> Result<ExecResult, DbErr> {
+            self.before_statement().await?;
+            self.stats.time(sql, 0, self.inner.execute_unprepared(sql)).await
+        }
+
+        async fn query_one(&self, stmt: Statement) -> Result<Option<QueryResult>, DbErr> {
+            self.before_statement().await?;
+            let params = bind_count(&stmt);
+            let sql = stmt.sql.clone();
+            self.stats.time(&sql, params, self.inner.query_one(stmt)).await
+        }
+
+        async fn query_all(&self, stmt: Statement) -> Result<Vec<QueryResult>, DbErr> {
+            self.before_statement().await?;
+            let params = bind_count(&stmt);
+            let sql = stmt.sql.clone();
+            self.stats.time(&sql, params, self.inner.query_all(stmt)).await
+        }
+
+        fn support_returning(&self) -> bool {
+            self.inner.support_returning()
+        }
+
+        fn is_mock_connection(&self) -> bool {
+            self.inner.is_mock_connection()
+        }
+    }
+
+    fn bind_count(stmt: &Statement) -> usize {
+        stmt.values.as_ref().map_or(0, |values| values.0.len())
+    }
+
+    // Only opening the cursor is guarded and timed; once rows are flowing a failure
+    // ends the stream, since replaying it would send the client duplicates.
+    impl StreamTrait for ResilientConnection {
+        type Stream<'a> = <Inner as StreamTrait>::Stream<'a>;
+
+        fn stream<'a>(
+            &'a self,
+            stmt: Statement,
+        ) -> Pin<Box<dyn Future<Output = Result<Self::Stream<'a>, DbErr>> + 'a + Send>> {
+            Box::pin(async move {
+                let params = bind_count(&stmt);
+                self.timed(&stmt.sql, params, self.call(true, || self.inner.stream(stmt.clone()))).await
+            })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TransactionTrait for ResilientConnection {
+        async fn begin(&self) -> Result<DatabaseTransaction, DbErr> {
+            self.call(true, || self.inner.begin()).await
+        }
+
+        async fn begin_with_config(
+            &self,
+            isolation_level: Option<IsolationLevel>,
+            access_mode: Option<AccessMode>,
+        ) -> Result<DatabaseTransaction, DbErr> {
+            self.call(true, || self.inner.begin_with_config(isolation_level, access_mode)).await
+        }
+
+        // Closure-style transactions are passed through untouched: the callback can
+        // only run once, so there is nothing to retry.
+        async fn transaction<F, T, E>(&self, callback: F) -> Result<T, TransactionError<E>>
+        where
+            F: for<'c> FnOnce(&'c DatabaseTransaction) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>> + Send,
+            T: Send,
+            E: std::error::Error + Send,
+        {
+            self.inner.transaction(callback).await
+        }
+
+        async fn transaction_with_config<F, T, E>(
+            &self,
+            callback: F,
+            isolation_level: Option<IsolationLevel>,
+            access_mode: Option<AccessMode>,
+        ) -> Result<T, TransactionError<E>>
+        where
+            F: for<'c> FnOnce(&'c DatabaseTransaction) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>> + Send,
+            T: Send,
+            E: std::error::Error + Send,
+        {
+            self.inner.transaction_with_config(callback, isolation_level, access_mode).await
+        }
+    }
+}
+
+// --- 4. Fault Injection (db/faults.rs) ---
+// Test-only: compiled in with the `fault-injection` feature so integration tests can
+// drive the retry and circuit-breaker paths without needing a flaky database. The
+// tests at the bottom run with `cargo test --features fault-injection`.
+#[cfg(feature = "fault-injection")]
+mod faults {
+    use super::resilience::ResilientConnection;
+    use actix_web::body::{BoxBody, MessageBody};
+    use actix_web::dev::{ServiceRequest, ServiceResponse};
+    use actix_web::middleware::Next;
+    use actix_web::{web, HttpResponse};
+    use sea_orm::{
+        AccessMode, ConnAcquireErr, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, DbErr, ExecResult,
+        IsolationLevel, QueryResult, RuntimeErr, Statement, StreamTrait, TransactionError, TransactionTrait,
+    };
+    use serde::Serialize;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // Probes the harness polls to watch the breaker while everything else is failing
+    const EXEMPT_PATHS: [&str; 2] = ["/health", "/metrics"];
+
+    const DB_LATENCY_SALT: u64 = 1;
+    const DB_FAULT_SALT: u64 = 2;
+    const HTTP_LATENCY_SALT: u64 = 3;
+    const HTTP_FAULT_SALT: u64 = 4;
+
+    /// Percentages are 0-100. The same latency applies to DB calls and requests.
+    #[derive(Debug, Clone, Default)]
+    pub struct FaultConfig {
+        pub seed: u64,
+        pub latency: Duration,
+        pub latency_percent: u8,
+        pub db_reset_percent: u8,
+        pub db_acquire_timeout_percent: u8,
+        pub http_5xx_percent: u8,
+        pub http_reset_percent: u8,
+    }
+
+    fn env_parse<T: std::str::FromStr>(name: &str) -> Option<T> {
+        std::env::var(name).ok().and_then(|v| v.trim().parse().ok())
+    }
+
+    fn env_percent(name: &str) -> u8 {
+        env_parse::<u8>(name).map_or(0, |p| p.min(100))
+    }
+
+    impl FaultConfig {
+        /// Reads `FAULT_SEED`, `FAULT_LATENCY_MS`, `FAULT_LATENCY_PERCENT`,
+        /// `FAULT_DB_RESET_PERCENT`, `FAULT_DB_ACQUIRE_TIMEOUT_PERCENT`,
+        /// `FAULT_HTTP_5XX_PERCENT` and `FAULT_HTTP_RESET_PERCENT`. Anything unset is 0,
+        /// except that `FAULT_LATENCY_MS` on its own delays every call.
+        pub fn from_env() -> Self {
+            let latency = Duration::from_millis(env_parse("FAULT_LATENCY_MS").unwrap_or(0));
+            let latency_percent = match env_parse::<u8>("FAULT_LATENCY_PERCENT") {
+                Some(p) => p.min(100),
+                None if !latency.is_zero() => 100,
+                None => 0,
+            };
+            Self {
+                seed: env_parse("FAULT_SEED").unwrap_or(0),
+                latency,
+                latency_percent,
+                db_reset_percent: env_percent("FAULT_DB_RESET_PERCENT"),
+                db_acquire_timeout_percent: env_percent("FAULT_DB_ACQUIRE_TIMEOUT_PERCENT"),
+                http_5xx_percent: env_percent("FAULT_HTTP_5XX_PERCENT"),
+                http_reset_percent: env_percent("FAULT_HTTP_RESET_PERCENT"),
+            }
+        }
+
+        pub fn is_enabled(&self) -> bool {
+            (self.latency_percent > 0 && !self.latency.is_zero())
+                || self.db_reset_percent > 0
+                || self.db_acquire_timeout_percent > 0
+                || self.http_5xx_percent > 0
+                || self.http_reset_percent > 0
+        }
+    }
+
+    /// SplitMix64 over (seed, salt, sequence), reduced to 0..100. Each decision
+    /// depends only on the seed and how many calls came before it on the same
+    /// channel, so a test that issues requests in order sees the same faults every run.
+    fn roll(seed: u64, salt: u64, sequence: u64) -> u8 {
+        let mut z = seed
+            ^ salt.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ sequence.wrapping_add(1).wrapping_mul(0xD1B5_4A32_D192_ED03);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z % 100) as u8
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum HttpFault {
+        ServiceUnavailable,
+        Reset,
+    }
+
+    #[derive(Default)]
+    struct Counters {
+        db_calls: AtomicU64,
+        db_delayed: AtomicU64,
+        db_resets: AtomicU64,
+        db_acquire_timeouts: AtomicU64,
+        http_requests: AtomicU64,
+        http_delayed: AtomicU64,
+        http_5xx: AtomicU64,
+        http_resets: AtomicU64,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct FaultSnapshot {
+        pub db_calls: u64,
+        pub db_delayed: u64,
+        pub db_resets: u64,
+        pub db_acquire_timeouts: u64,
+        pub http_requests: u64,
+        pub http_delayed: u64,
+        pub http_5xx: u64,
+        pub http_resets: u64,
+    }
+
+    /// Decides which calls get delayed or failed. DB calls and HTTP requests keep
+    /// separate sequences, so adding a request to a test does not shift which
+    /// statements fail.
+    pub struct FaultInjector {
+        config: FaultConfig,
+        db_sequence: AtomicU64,
+        http_sequence: AtomicU64,
+        counters: Counters,
+    }
+
+    impl FaultInjector {
+        pub fn new(config: FaultConfig) -> Self {
+            Self { config, db_sequence: AtomicU64::new(0), http_sequence: AtomicU64::new(0), counters: Counters::default() }
+        }
+
+        pub fn from_env() -> Self {
+            let config = FaultConfig::from_env();
+            if config.is_enabled() {
+                eprintln!("Fault injection enabled: {:?}", config);
+            }
+            Self::new(config)
+        }
+
+        pub fn snapshot(&self) -> FaultSnapshot {
+            let c = &self.counters;
+            FaultSnapshot {
+                db_calls: c.db_calls.load(Ordering::Relaxed),
+                db_delayed: c.db_delayed.load(Ordering::Relaxed),
+                db_resets: c.db_resets.load(Ordering::Relaxed),
+                db_acquire_timeouts: c.db_acquire_timeouts.load(Ordering::Relaxed),
+                http_requests: c.http_requests.load(Ordering::Relaxed),
+                http_delayed: c.http_delayed.load(Ordering::Relaxed),
+                http_5xx: c.http_5xx.load(Ordering::Relaxed),
+                http_resets: c.http_resets.load(Ordering::Relaxed),
+            }
+        }
+
+        async fn maybe_delay(&self, salt: u64, sequence: u64, delayed: &AtomicU64) {
+            if roll(self.config.seed, salt, sequence) < self.config.latency_percent && !self.config.latency.is_zero() {
+                delayed.fetch_add(1, Ordering::Relaxed);
+                actix_web::rt::time::sleep(self.config.latency).await;
+            }
+        }
+
+        /// Called before every statement the wrapped connection sends. Both injected
+        /// errors count as transient, so they exercise the retry loop and breaker:
+        /// an acquire timeout is retried for any statement, a reset only for reads.
+        pub async fn before_db_call(&self) -> Result<(), DbErr> {
+            let sequence = self.db_sequence.fetch_add(1, Ordering::Relaxed);
+            self.counters.db_calls.fetch_add(1, Ordering::Relaxed);
+            self.maybe_delay(DB_LATENCY_SALT, sequence, &self.counters.db_delayed).await;
+
+            let pick = roll(self.config.seed, DB_FAULT_SALT, sequence);
+            if pick < self.config.db_reset_percent {
+                self.counters.db_resets.fetch_add(1, Ordering::Relaxed);
+                return Err(DbErr::Conn(RuntimeErr::Internal("Injected fault: connection reset by peer".to_string())));
+            }
+            if pick < self.config.db_reset_percent.saturating_add(self.config.db_acquire_timeout_percent) {
+                self.counters.db_acquire_timeouts.fetch_add(1, Ordering::Relaxed);
+                return Err(DbErr::ConnectionAcquire(ConnAcquireErr::Timeout));
+            }
+            Ok(())
+        }
+
+        pub async fn before_request(&self) -> Option<HttpFault> {
+            let sequence = self.http_sequence.fetch_add(1, Ordering::Relaxed);
+            self.counters.http_requests.fetch_add(1, Ordering::Relaxed);
+            self.maybe_delay(HTTP_LATENCY_SALT, sequence, &self.counters.http_delayed).await;
+
+            let pick = roll(self.config.seed, HTTP_FAULT_SALT, sequence);
+            if pick < self.config.http_5xx_percent {
+                self.counters.http_5xx.fetch_add(1, Ordering::Relaxed);
+                return Some(HttpFault::ServiceUnavailable);
+            }
+            if pick < self.config.http_5xx_percent.saturating_add(self.config.http_reset_percent) {
+                self.counters.http_resets.fetch_add(1, Ordering::Relaxed);
+                return Some(HttpFault::Reset);
+            }
+            None
+        }
+    }
+
+    /// A `DatabaseConnection` that asks the injector before every call. It sits under
+    /// `ResilientConnection`, so injected failures go through the same retry and
+    /// breaker logic as real ones. Statements inside a transaction are checked by
+    /// `TimedTransaction` against the same injector and, as there, never retried.
+    pub struct FaultyConnection {
+        inner: DatabaseConnection,
+        injector: Arc<FaultInjector>,
+    }
+
+    impl FaultyConnection {
+        pub fn new(inner: DatabaseConnection, injector: Arc<FaultInjector>) -> Self {
+            Self { inner, injector }
+        }
+
+        pub fn injector(&self) -> &Arc<FaultInjector> {
+            &self.injector
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ConnectionTrait for FaultyConnection {
+        fn get_database_backend(&self) -> DbBackend {
+            self.inner.get_database_backend()
+        }
+
+        async fn execute(&self, stmt: Statement) -> Result<ExecResult, DbErr> {
+            self.injector.before_db_call().await?;
+            self.inner.execute(stmt).await
+        }
+
+        async fn execute_unprepared(&self, sql: &str) -> Result<ExecResult, DbErr> {
+            self.injector.before_db_call().await?;
+            self.inner.execute_unprepared(sql).await
+        }
+
+        async fn query_one(&self, stmt: Statement) -> Result<Option<QueryResult>, DbErr> {
+            self.injector.before_db_call().await?;
+            self.inner.query_one(stmt).await
+        }
+
+        async fn query_all(&self, stmt: Statement) -> Result<Vec<QueryResult>, DbErr> {
+            self.injector.before_db_call().await?;
+            self.inner.query_all(stmt).await
+        }
+
+        fn support_returning(&self) -> bool {
+            self.inner.support_returning()
+        }
+
+        fn is_mock_connection(&self) -> bool {
+            self.inner.is_mock_connection()
+        }
+    }
+
+    impl StreamTrait for FaultyConnection {
+        type Stream<'a> = <DatabaseConnection as StreamTrait>::Stream<'a>;
+
+        fn stream<'a>(
+            &'a self,
+            stmt: Statement,
+        ) -> Pin<Box<dyn Future<Output = Result<Self::Stream<'a>, DbErr>> + 'a + Send>> {
+            Box::pin(async move {
+                self.injector.before_db_call().await?;
+                self.inner.stream(stmt).await
+            })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TransactionTrait for FaultyConnection {
+        async fn begin(&self) -> Result<DatabaseTransaction, DbErr> {
+            self.injector.before_db_call().await?;
+            self.inner.begin().await
+        }
+
+        async fn begin_with_config(
+            &self,
+            isolation_level: Option<IsolationLevel>,
+            access_mode: Option<AccessMode>,
+        ) -> Result<DatabaseTransaction, DbErr> {
+            self.injector.before_db_call().await?;
+            self.inner.begin_with_config(isolation_level, access_mode).await
+        }
+
+        async fn transaction<F, T, E>(&self, callback: F) -> Result<T, TransactionError<E>>
+        where
+            F: for<'c> FnOnce(&'c DatabaseTransaction) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>> + Send,
+            T: Send,
+            E: std::error::Error + Send,
+        {
+            self.injector.before_db_call().await.map_err(TransactionError::Connection)?;
+            self.inner.transaction(callback).await
+        }
+
+        async fn transaction_with_config<F, T, E>(
+            &self,
+            callback: F,
+            isolation_level: Option<IsolationLevel>,
+            access_mode: Option<AccessMode>,
+        ) -> Result<T, TransactionError<E>>
+        where
+            F: for<'c> FnOnce(&'c DatabaseTransaction) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>> + Send,
+            T: Send,
+            E: std::error::Error + Send,
+        {
+            self.injector.before_db_call().await.map_err(TransactionError::Connection)?;
+            self.inner.transaction_with_config(callback, isolation_level, access_mode).await
+        }
+    }
+
+    /// Middleware for `from_fn`. Shares the injector held by the app's connection, so
+    /// one set of env vars configures both layers.
+    pub async fn inject_http_faults(
+        req: ServiceRequest,
+        next: Next<impl MessageBody + 'static>,
+    ) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+        let injector = req.app_data::<web::Data<Arc<ResilientConnection>>>().map(|db| db.fault_injector().clone());
+        let fault = match injector {
+            Some(injector) if !EXEMPT_PATHS.contains(&req.path()) => injector.before_request().await,
+            _ => None,
+        };
+        match fault {
+            Some(HttpFault::ServiceUnavailable) => {
+                Ok(req.into_response(HttpResponse::ServiceUnavailable().body("Injected fault: service unavailable")))
+            }
+            // The status line goes out, then the body fails and actix drops the
+            // connection, which the client sees as a reset mid-response.
+            Some(HttpFault::Reset) => {
+                let body = futures::stream::once(async {
+                    Err::<web::Bytes, _>(std::io::Error::new(
+                        std::io::ErrorKind::ConnectionReset,
+                        "Injected fault: connection reset",
+                    ))
+                });
+                Ok(req.into_response(HttpResponse::Ok().streaming(body)))
+            }
+            None => next.call(req).await.map(ServiceResponse::map_into_boxed_body),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::migrator::Migrator;
+        use crate::models::dtos::CreateUserDto;
+        use crate::repositories::UserRepository;
+        use crate::resilience::{self, CircuitBreaker, RetryPolicy};
+        use crate::services::UserService;
+        use crate::ApiError;
+        use actix_web::ResponseError;
+        use sea_orm::Database;
+        use sea_orm_migration::MigratorTrait;
+
+        const RESET_PERCENT: u8 = 50;
+
+        /// A seed whose DB fault rolls pass for the first `call` calls and fail on
+        /// that one, so a test can aim a reset at a specific statement.
+        fn seed_failing_at(call: u64) -> u64 {
+            (0..)
+                .find(|&seed| {
+                    (0..call).all(|i| roll(seed, DB_FAULT_SALT, i) >= RESET_PERCENT)
+                        && roll(seed, DB_FAULT_SALT, call) < RESET_PERCENT
+                })
+                .unwrap()
+        }
+
+        /// Migrated in-memory database (the migrations seed the default role). The raw
+        /// connection is returned too, for checking state behind the injector's back.
+        async fn setup(config: FaultConfig, breaker: CircuitBreaker) -> (DatabaseConnection, Arc<ResilientConnection>, Arc<FaultInjector>) {
+            let raw = Database::connect("sqlite::memory:").await.unwrap();
+            Migrator::up(&raw, None).await.unwrap();
+            let injector = Arc::new(FaultInjector::new(config));
+            let retry = RetryPolicy { max_attempts: 3, base_delay: Duration::ZERO, max_delay: Duration::ZERO };
+            let db = ResilientConnection::with_faults(raw.clone(), breaker, retry, injector.clone());
+            (raw, Arc::new(db), injector)
+        }
+
+        fn resets(seed: u64) -> FaultConfig {
+            FaultConfig { seed, db_reset_percent: RESET_PERCENT, ..FaultConfig::default() }
+        }
+
+        fn new_user(email: &str) -> CreateUserDto {
+            CreateUserDto { email: email.to_string(), password: "secret".to_string() }
+        }
+
+        #[actix_web::test]
+        async fn reset_inside_a_transaction_rolls_it_back_and_maps_to_503() {
+            // Count the calls a clean signup makes; the last one is the role
+            // assignment, after the user row is already written.
+            let (_, db, injector) = setup(FaultConfig::default(), CircuitBreaker::new(5, Duration::from_secs(10))).await;
+            UserService::new(db).create_user_with_default_role(new_user("probe@example.com")).await.unwrap();
+            let last = injector.snapshot().db_calls - 1;
+
+            let breaker = CircuitBreaker::new(5, Duration::from_secs(10));
+            let (raw, db, injector) = setup(resets(seed_failing_at(last)), breaker).await;
+            let err = UserService::new(db.clone())
+                .create_user_with_default_role(new_user("ada@example.com"))
+                .await
+                .unwrap_err();
+
+            assert!(matches!(&err, ApiError::DbError(e) if resilience::is_transient(e)), "{:?}", err);
+            assert_eq!(err.status_code(), actix_web::http::StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(injector.snapshot().db_resets, 1);
+            // Statements inside a transaction are never replayed
+            assert_eq!(db.snapshot().retries, 0);
+            assert!(UserRepository::find_by_email(&raw, "ada@example.com").await.unwrap().is_none());
+        }
+
+        #[actix_web::test]
+        async fn reset_on_a_read_is_retried() {
+            let breaker = CircuitBreaker::new(5, Duration::from_secs(10));
+            let (_, db, injector) = setup(resets(seed_failing_at(0)), breaker).await;
+
+            let found = UserRepository::find_by_email(&*db, "nobody@example.com").await.unwrap();
+
+            assert!(found.is_none());
+            assert_eq!(injector.snapshot().db_resets, 1);
+            let snapshot = db.snapshot();
+            assert_eq!(snapshot.retries, 1);
+            assert_eq!(snapshot.state, resilience::BreakerState::Closed);
+        }
+
+        #[actix_web::test]
+        async fn acquire_timeouts_open_the_breaker() {
+            let config = FaultConfig { db_acquire_timeout_percent: 100, ..FaultConfig::default() };
+            // Trips on the last of the three attempts, so the caller still sees the cause
+            let (_, db, _) = setup(config, CircuitBreaker::new(3, Duration::from_secs(60))).await;
+
+            let err = ApiError::from(db.ping().await.unwrap_err());
+            assert!(matches!(&err, ApiError::DbError(DbErr::ConnectionAcquire(_))), "{:?}", err);
+            assert_eq!(err.status_code(), actix_web::http::StatusCode::SERVICE_UNAVAILABLE);
+
+            let rejected = ApiError::from(db.ping().await.unwrap_err());
+            assert!(matches!(&rejected, ApiError::DbError(DbErr::Custom(msg)) if msg == resilience::CIRCUIT_OPEN));
+            assert_eq!(rejected.status_code(), actix_web::http::StatusCode::SERVICE_UNAVAILABLE);
+            let snapshot = db.snapshot();
+            assert_eq!(snapshot.state, resilience::BreakerState::Open);
+            assert_eq!((snapshot.trips, snapshot.rejected), (1, 1));
+        }
+    }
+}
+
+// --- 5. Query Instrumentation (db/instrumentation.rs) ---
+mod instrumentation {
+    use serde::Serialize;
+    use std::collections::HashMap;
+    use std::future::Future;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    /// Distinct statements beyond this are folded into one bucket so a query built
+    /// with inlined literals cannot grow the map without bound.
+    const MAX_TRACKED_STATEMENTS: usize = 500;
+    const OTHER_STATEMENTS: &str = "<other>";
+    const DEFAULT_SLOW_QUERY_MS: u64 = 200;
+
+    /// `SLOW_QUERY_MS` sets the threshold above which statements are logged.
+    pub fn slow_threshold_from_env() -> Duration {
+        let millis = std::env::var("SLOW_QUERY_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SLOW_QUERY_MS);
+        Duration::from_millis(millis)
+    }
+
+    #[derive(Debug, Clone, Default, Serialize)]
+    pub struct StatementStats {
+        pub calls: u64,
+        pub errors: u64,
+        pub slow: u64,
+        pub total_ms: f64,
+        pub max_ms: f64,
+    }
+
+    /// Per-statement timings keyed by the parameterized SQL text. Bind values are
+    /// never stored or logged, only how many there were.
+    pub struct QueryStats {
+        slow_threshold: Duration,
+        statements: Mutex<HashMap<String, StatementStats>>,
+    }
+
+    impl QueryStats {
+        pub fn new(slow_threshold: Duration) -> Self {
+            Self { slow_threshold, statements: Mutex::new(HashMap::new()) }
+        }
+
+        pub fn slow_threshold(&self) -> Duration {
+            self.slow_threshold
+        }
+
+        pub fn record(&self, sql: &str, params: usize, elapsed: Duration, ok: bool) {
+            let slow = elapsed >= self.slow_threshold;
+            if slow {
+                eprintln!("Slow query ({} ms, {} bind parameter(s) redacted): {}", elapsed.as_millis(), params, sql);
+            }
+
+            let mut statements = self.statements.lock().unwrap();
+            let key = if statements.contains_key(sql) || statements.len() < MAX_TRACKED_STATEMENTS {
+                sql
+            } else {
+                OTHER_STATEMENTS
+            };
+            let entry = statements.entry(key.to_string()).or_default();
+            let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+            entry.calls += 1;
+            entry.total_ms += elapsed_ms;
+            entry.max_ms = entry.max_ms.max(elapsed_ms);
+            if !ok {
+                entry.errors += 1;
+            }
+            if slow {
+                entry.slow += 1;
+            }
+        }
+
+        pub async fn time<T, E, Fut>(&self, sql: &str, params: usize, fut: Fut) -> Result<T, E>
+        where
+            Fut: Future<Output = Result<T, E>>,
+        {
+            let started = Instant::now();
+            let result = fut.await;
+            self.record(sql, params, started.elapsed(), result.is_ok());
+            result
+        }
+
+        /// Most expensive statements first.
+        pub fn snapshot(&self) -> Vec<(String, StatementStats)> {
+            let mut stats: Vec<_> = self.statements.lock().unwrap().iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            stats.sort_by(|a, b| b.1.total_ms.total_cmp(&a.1.total_ms));
+            stats
+        }
+    }
+}
+
+// --- 6. Query Filters (repositories/filters.rs) ---
+mod filters {
+    use super::models::{post, role, user, user_role};
+    use super::ApiError;
+    use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+    use sea_orm::{sea_query::{Expr, LikeExpr, Query, SelectStatement, SimpleExpr}, Condition, EntityTrait, Order, QueryFilter, QueryOrder, Select, Value};
+    use serde::{Deserialize, Serialize};
+    use std::marker::PhantomData;
+    use uuid::Uuid;
+
+    const MAX_FILTERS: usize = 16;
+    const MAX_IN_VALUES: usize = 100;
+    const MAX_SORT_KEYS: usize = 3;
+
+    /// Escapes LIKE wildcards so `raw` matches literally; pair with `LikeExpr::escape('\\')`.
+    pub fn escape_like(raw: &str) -> String {
+        raw.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum FilterOp {
+        Eq,
+        Ne,
+        Contains,
+        Gt,
+        Gte,
+        Lt,
+        Lte,
+        In,
+    }
+
+    impl FilterOp {
+        fn parse(op: &str) -> Option<Self> {
+            Some(match op {
+                "eq" => Self::Eq,
+                "ne" => Self::Ne,
+                "contains" => Self::Contains,
+                "gt" => Self::Gt,
+                "gte" => Self::Gte,
+                "lt" => Self::Lt,
+                "lte" => Self::Lte,
+                "in" => Self::In,
+                _ => return None,
+            })
+        }
+
+        fn as_str(self) -> &'static str {
+            match self {
+                Self::Eq => "eq",
+                Self::Ne => "ne",
+                Self::Contains => "contains",
+                Self::Gt => "gt",
+                Self::Gte => "gte",
+                Self::Lt => "lt",
+                Self::Lte => "lte",
+                Self::In => "in",
+            }
+        }
+    }
+
+    const TEXT_OPS: &[FilterOp] = &[FilterOp::Eq, FilterOp::Ne, FilterOp::Contains, FilterOp::In];
+    const EXACT_OPS: &[FilterOp] = &[FilterOp::Eq, FilterOp::Ne, FilterOp::In];
+    const BOOL_OPS: &[FilterOp] = &[FilterOp::Eq, FilterOp::Ne];
+    const RANGE_OPS: &[FilterOp] = &[FilterOp::Gt, FilterOp::Gte, FilterOp::Lt, FilterOp::Lte];
+
+    /// How a filter value is parsed before it is bound.
+    #[derive(Clone, Copy, Debug)]
+    pub enum FieldKind {
+        Text,
+        Bool,
+        Uuid,
+        /// RFC 3339, a bare `YYYY-MM-DD` standing for that whole UTC day, or
+        /// `now`/`now-7d` (units d, h, m), resolved when the filter is compiled
+        Timestamp,
+        /// One of the stored string values, matched case-insensitively
+        Enum(&'static [&'static str]),
+    }
+
+    /// The expression a filter value is compared against.
+    #[derive(Clone, Copy)]
+    pub enum FilterTarget {
+        Column(fn() -> Expr),
+        /// A column behind a to-many relation: the row matches when `key` is among
+        /// the rows of `subquery` whose `column` satisfies the filter.
+        Related {
+            key: fn() -> Expr,
+            subquery: fn() -> SelectStatement,
+            column: fn() -> Expr,
+        },
+    }
+
+    /// One entry in an entity's allowlist of filterable fields. Only `Column`
+    /// targets can be sortable.
+    pub struct FilterField {
+        pub name: &'static str,
+        pub kind: FieldKind,
+        pub ops: &'static [FilterOp],
+        pub target: FilterTarget,
+        pub sortable: bool,
+    }
+
+    /// Entities that can be narrowed with `?filter[field][op]=value` and ordered with
+    /// `?sort=-field`. Anything not in `filter_fields()` is rejected, so clients can't
+    /// filter on e.g. password hashes.
+    pub trait Filterable: EntityTrait {
+        /// Name saved searches record to say which listing they belong to.
+        const RESOURCE: &'static str;
+
+        fn filter_fields() -> &'static [FilterField];
+    }
+
+    static USER_FILTERS: [FilterField; 5] = [
+        FilterField { name: "id", kind: FieldKind::Uuid, ops: EXACT_OPS, target: FilterTarget::Column(|| Expr::col((user::Entity, user::Column::Id))), sortable: false },
+        FilterField { name: "email", kind: FieldKind::Text, ops: TEXT_OPS, target: FilterTarget::Column(|| Expr::col((user::Entity, user::Column::Email))), sortable: true },
+        FilterField { name: "is_active", kind: FieldKind::Bool, ops: BOOL_OPS, target: FilterTarget::Column(|| Expr::col((user::Entity, user::Column::IsActive))), sortable: false },
+        FilterField { name: "created_at", kind: FieldKind::Timestamp, ops: RANGE_OPS, target: FilterTarget::Column(|| Expr::col((user::Entity, user::Column::CreatedAt))), sortable: true },
+        // "has any of these roles"; ne would read as "has some other role", so it's left out
+        FilterField {
+            name: "role",
+            kind: FieldKind::Text,
+            ops: &[FilterOp::Eq, FilterOp::In],
+            target: FilterTarget::Related {
+                key: || Expr::col((user::Entity, user::Column::Id)),
+                subquery: || {
+                    Query::select()
+                        .column((user_role::Entity, user_role::Column::UserId))
+                        .from(user_role::Entity)
+                        .inner_join(
+                            role::Entity,
+                            Expr::col((role::Entity, role::Column::Id)).equals((user_role::Entity, user_role::Column::RoleId)),
+                        )
+                        .to_owned()
+                },
+                column: || Expr::col((role::Entity, role::Column::Name)),
+            },
+            sortable: false,
+        },
+    ];
+
+    static POST_FILTERS: [FilterField; 3] = [
+        FilterField { name: "user_id", kind: FieldKind::Uuid, ops: EXACT_OPS, target: FilterTarget::Column(|| Expr::col((post::Entity, post::Column::UserId))), sortable: false },
+        FilterField { name: "title", kind: FieldKind::Text, ops: TEXT_OPS, target: FilterTarget::Column(|| Expr::col((post::Entity, post::Column::Title))), sortable: true },
+        FilterField { name: "status", kind: FieldKind::Enum(&["DRAFT", "PUBLISHED"]), ops: EXACT_OPS, target: FilterTarget::Column(|| Expr::col((post::Entity, post::Column::Status))), sortable: true },
+    ];
+
+    impl Filterable for user::Entity {
+        const RESOURCE: &'static str = "users";
+
+        fn filter_fields() -> &'static [FilterField] {
+            &USER_FILTERS
+        }
+    }
+
+    impl Filterable for post::Entity {
+        const RESOURCE: &'static str = "posts";
+
+        fn filter_fields() -> &'static [FilterField] {
+            &POST_FILTERS
+        }
+    }
+
+    enum Predicate {
+        Eq(Value),
+        Ne(Value),
+        Gt(Value),
+        Gte(Value),
+        Lt(Value),
+        Lte(Value),
+        In(Vec<Value>),
+        Contains(String),
+    }
+
+    impl Predicate {
+        fn parse(kind: FieldKind, op: FilterOp, raw: &str) -> Result<Self, String> {
+            Ok(match op {
+                FilterOp::Eq => Self::Eq(parse_value(kind, raw)?),
+                FilterOp::Ne => Self::Ne(parse_value(kind, raw)?),
+                FilterOp::Gte => Self::Gte(parse_value(kind, raw)?),
+                FilterOp::Lt => Self::Lt(parse_value(kind, raw)?),
+                // A bare date means the whole day, so these bound on the next midnight
+                FilterOp::Gt => match next_midnight(kind, raw) {
+                    Some(next) => Self::Gte(next),
+                    None => Self::Gt(parse_value(kind, raw)?),
+                },
+                FilterOp::Lte => match next_midnight(kind, raw) {
+                    Some(next) => Self::Lt(next),
+                    None => Self::Lte(parse_value(kind, raw)?),
+                },
+                FilterOp::In => {
+                    let values = raw
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|v| !v.is_empty())
+                        .map(|v| parse_value(kind, v))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if values.is_empty() || values.len() > MAX_IN_VALUES {
+                        return Err(format!("expected 1 to {} comma-separated values", MAX_IN_VALUES));
+                    }
+                    Self::In(values)
+                }
+                FilterOp::Contains => {
+                    if raw.is_empty() {
+                        return Err("expected a non-empty substring".to_string());
+                    }
+                    Self::Contains(format!("%{}%", escape_like(raw)))
+                }
+            })
+        }
+
+        fn apply(self, lhs: Expr) -> SimpleExpr {
+            match self {
+                Self::Eq(v) => lhs.eq(v),
+                Self::Ne(v) => lhs.ne(v),
+                Self::Gt(v) => lhs.gt(v),
+                Self::Gte(v) => lhs.gte(v),
+                Self::Lt(v) => lhs.lt(v),
+                Self::Lte(v) => lhs.lte(v),
+                Self::In(values) => lhs.is_in(values),
+                Self::Contains(pattern) => lhs.like(LikeExpr::new(pattern).escape('\\')),
+            }
+        }
+    }
+
+    fn parse_value(kind: FieldKind, raw: &str) -> Result<Value, String> {
+        match kind {
+            FieldKind::Text => Ok(Value::from(raw.to_string())),
+            FieldKind::Bool => match raw {
+                "true" => Ok(Value::from(true)),
+                "false" => Ok(Value::from(false)),
+                _ => Err("expected true or false".to_string()),
+            },
+            FieldKind::Uuid => Uuid::parse_str(raw).map(Value::from).map_err(|_| "expected a UUID".to_string()),
+            FieldKind::Timestamp => relative_timestamp(raw)
+                .or_else(|| chrono::DateTime::parse_from_rfc3339(raw).ok().map(|at| at.with_timezone(&chrono::Utc)))
+                .or_else(|| {
+                    chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                        .ok()
+                        .map(|day| day.and_hms_opt(0, 0, 0).expect("midnight is a valid time").and_utc())
+                })
+                .map(Value::from)
+                .ok_or_else(|| "expected an RFC 3339 timestamp, YYYY-MM-DD or now-<n>d".to_string()),
+            FieldKind::Enum(allowed) => allowed
+                .iter()
+                .find(|v| v.eq_ignore_ascii_case(raw))
+                .map(|v| Value::from(v.to_string()))
+                .ok_or_else(|| format!("expected one of {}", allowed.join(", "))),
+        }
+    }
+
+    // "now", "now-7d", "now-12h", "now-30m"
+    fn relative_timestamp(raw: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+        let offset = raw.strip_prefix("now")?;
+        let now = chrono::Utc::now();
+        if offset.is_empty() {
+            return Some(now);
+        }
+        let amount = offset.strip_prefix('-')?;
+        let (n, unit) = amount.split_at(amount.len().checked_sub(1)?);
+        let n: i64 = n.parse().ok().filter(|n| (0..=1_000_000).contains(n))?;
+        let delta = match unit {
+            "d" => chrono::Duration::days(n),
+            "h" => chrono::Duration::hours(n),
+            "m" => chrono::Duration::minutes(n),
+            _ => return None,
+        };
+        now.checked_sub_signed(delta)
+    }
+
+    fn next_midnight(kind: FieldKind, raw: &str) -> Option<Value> {
+        if !matches!(kind, FieldKind::Timestamp) {
+            return None;
+        }
+        let day = chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d").ok()?.succ_opt()?;
+        Some(Value::from(day.and_hms_opt(0, 0, 0).expect("midnight is a valid time").and_utc()))
+    }
+
+    // "email][contains]" -> ("email", "contains"); "email]" is shorthand for eq
+    fn split_key(rest: &str) -> Option<(&str, &str)> {
+        let (name, op) = rest.split_once(']')?;
+        if op.is_empty() {
+            return Some((name, "eq"));
+        }
+        Some((name, op.strip_prefix('[')?.strip_suffix(']')?))
+    }
+
+    impl FilterField {
+        fn compile(&self, predicate: Predicate) -> SimpleExpr {
+            match self.target {
+                FilterTarget::Column(column) => predicate.apply(column()),
+                FilterTarget::Related { key, subquery, column } => {
+                    let mut subquery = subquery();
+                    subquery.and_where(predicate.apply(column()));
+                    key().in_subquery(subquery)
+                }
+            }
+        }
+    }
+
+    /// One `filter[field][op]=value` parameter before it is checked against an entity.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct FilterClause {
+        pub field: String,
+        #[serde(default = "default_op")]
+        pub op: String,
+        pub value: String,
+    }
+
+    fn default_op() -> String {
+        "eq".to_string()
+    }
+
+    /// Filters and sort keys as the client wrote them, not yet validated against any
+    /// entity. This is also the shape saved searches are stored in, so a stored search
+    /// goes through the same checks as a query string each time it runs.
+    #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    pub struct FilterDocument {
+        #[serde(default)]
+        pub filters: Vec<FilterClause>,
+        /// Field names, `-` prefixed for descending
+        #[serde(default)]
+        pub sort: Vec<String>,
+    }
+
+    impl FilterDocument {
+        /// Collects `filter[...]` and `sort` parameters; others are left for the handler.
+        pub fn from_query(query: &str) -> Result<Self, ApiError> {
+            let pairs = web::Query::<Vec<(String, String)>>::from_query(query)
+                .map_err(|_| ApiError::BadRequest("Malformed query string".to_string()))?
+                .into_inner();
+
+            let mut document = Self::default();
+            for (key, raw) in pairs {
+                if key == "sort" {
+                    document.sort.extend(raw.split(',').map(str::trim).filter(|k| !k.is_empty()).map(str::to_string));
+                    continue;
+                }
+                let Some(rest) = key.strip_prefix("filter[") else { continue };
+                let (name, op) = split_key(rest)
+                    .ok_or_else(|| ApiError::BadRequest(format!("Malformed filter '{}'; expected filter[field][op]", key)))?;
+                document.filters.push(FilterClause { field: name.to_string(), op: op.to_string(), value: raw });
+            }
+            Ok(document)
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.filters.is_empty() && self.sort.is_empty()
+        }
+    }
+
+    /// A `FilterDocument` checked against `E`'s allowlist: the filters ANDed into one
+    /// condition plus the requested ordering.
+    pub struct Filters<E: Filterable> {
+        condition: Condition,
+        order: Vec<(fn() -> Expr, Order)>,
+        empty: bool,
+        _entity: PhantomData<E>,
+    }
+
+    impl<E: Filterable> Filters<E> {
+        pub fn none() -> Self {
+            Self { condition: Condition::all(), order: Vec::new(), empty: true, _entity: PhantomData }
+        }
+
+        pub fn parse(query: &str) -> Result<Self, ApiError> {
+            Self::compile(&FilterDocument::from_query(query)?)
+        }
+
+        pub fn compile(document: &FilterDocument) -> Result<Self, ApiError> {
+            if document.filters.len() > MAX_FILTERS {
+                return Err(ApiError::BadRequest(format!("At most {} filters are allowed", MAX_FILTERS)));
+            }
+            if document.sort.len() > MAX_SORT_KEYS {
+                return Err(ApiError::BadRequest(format!("At most {} sort keys are allowed", MAX_SORT_KEYS)));
+            }
+
+            let mut filters = Self::none();
+            filters.empty = document.is_empty();
+            for clause in &document.filters {
+                let field = Self::field(&clause.field)?;
+                let op = FilterOp::parse(&clause.op).filter(|op| field.ops.contains(op)).ok_or_else(|| {
+                    let ops: Vec<&str> = field.ops.iter().map(|op| op.as_str()).collect();
+                    ApiError::BadRequest(format!("Unsupported operator '{}' for '{}'; expected one of {}", clause.op, field.name, ops.join(", ")))
+                })?;
+                let predicate = Predicate::parse(field.kind, op, clause.value.trim())
+                    .map_err(|e| ApiError::BadRequest(format!("Invalid value for filter[{}][{}]: {}", field.name, clause.op, e)))?;
+                filters.condition = filters.condition.add(field.compile(predicate));
+            }
+
+            for key in &document.sort {
+                let (name, order) = match key.strip_prefix('-') {
+                    Some(name) => (name, Order::Desc),
+                    None => (key.as_str(), Order::Asc),
+                };
+                let field = Self::field(name)?;
+                match field.target {
+                    FilterTarget::Column(column) if field.sortable => filters.order.push((column, order)),
+                    _ => return Err(ApiError::BadRequest(format!("Cannot sort on '{}'", name))),
+                }
+            }
+            Ok(filters)
+        }
+
+        fn field(name: &str) -> Result<&'static FilterField, ApiError> {
+            E::filter_fields().iter().find(|f| f.name == name).ok_or_else(|| {
+                let names: Vec<&str> = E::filter_fields().iter().map(|f| f.name).collect();
+                ApiError::BadRequest(format!("Unknown field '{}'; expected one of {}", name, names.join(", ")))
+            })
+        }
+
+        /// True when the request carried no filter or sort parameters at all.
+        pub fn is_empty(&self) -> bool {
+            self.empty
+        }
+
+        /// Narrows `select` and orders it; callers add their own tie-breaking order after.
+        pub fn apply(&self, select: Select<E>) -> Select<E> {
+            let select = select.filter(self.condition.clone());
+            self.order.iter().fold(select, |select, (column, order)| select.order_by(SimpleExpr::from(column()), order.clone()))
+        }
+    }
+
+    impl<E: Filterable> FromRequest for Filters<E> {
+        type Error = ApiError;
+        type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+        fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+            std::future::ready(Self::parse(req.query_string()))
+        }
+    }
+}
+
+// --- 7. Repository Layer (repositories/user_repository.rs) ---
+mod repositories {
+    use super::models::{user, post, role, user_role, profile, tag, post_tag, comment, feature_flag, feature_flag_override, tenant_setting, saved_search, dtos::{TagWithCountDto, BucketCountDto, StatusCountDto, TimeBucket, BackupDto, BACKUP_SCHEMA_VERSION}};
+    use sea_orm::{prelude::*, sea_query::{Expr, LikeExpr, OnConflict, SimpleExpr}, ActiveValue, ColumnTrait, Condition, ConnectionTrait, DbBackend, DbErr, EntityTrait, IntoActiveModel, JoinType, PrimaryKeyTrait, QueryFilter, QueryOrder, QuerySelect, RelationTrait, Select};
+    use super::filters::{escape_like, Filters};
+    use super::resilience::TimedTransaction;
+    use std::collections::{HashMap, HashSet};
+    use std::marker::PhantomData;
+
+    /// The caller on whose behalf a scoped query runs.
+    #[derive(Clone, Copy, Debug)]
+    pub struct Principal {
+        pub user_id: Option<Uuid>,
+        pub is_admin: bool,
+    }
+
+    impl Principal {
+        pub fn anonymous() -> Self {
+            Self { user_id: None, is_admin: false }
+        }
+
+        /// Sees every row. Only for maintenance paths that are already restricted to
+        /// administrators, such as backups.
+        pub fn system() -> Self {
+            Self { user_id: None, is_admin: true }
+        }
+    }
+
+    /// Entities whose rows belong to a single user.
+    pub trait OwnedEntity: EntityTrait {
+        fn owner_column() -> Self::Column;
+
+        /// Rows any caller may read regardless of owner (e.g. published posts).
+        /// Writes are never widened by this.
+        fn public_condition() -> Option<Condition> {
+            None
+        }
+    }
+
+    impl OwnedEntity for post::Entity {
+        fn owner_column() -> Self::Column {
+            post::Column::UserId
+        }
+
+        fn public_condition() -> Option<Condition> {
+            Some(Condition::all().add(post::Column::Status.eq(post::PostStatus::Published)))
+        }
+    }
+
+    impl OwnedEntity for comment::Entity {
+        fn owner_column() -> Self::Column {
+            comment::Column::AuthorId
+        }
+
+        // Comments are readable by everyone; only changes are owner-scoped
+        fn public_condition() -> Option<Condition> {
+            Some(Condition::all())
+        }
+    }
+
+    /// Wraps queries on an owned entity so non-admin callers only ever see their own
+    /// rows (plus public ones for reads). Services go through this instead of
+    /// `Entity::find()` so a handler cannot forget the ownership check. Every query
+    /// it hands out is already scoped; there is no unscoped `Select` to pass around.
+    pub struct ScopedRepository<E: OwnedEntity> {
+        principal: Principal,
+        _entity: PhantomData<E>,
+    }
+
+    impl<E: OwnedEntity> ScopedRepository<E> {
+        pub fn new(principal: Principal) -> Self {
+            Self { principal, _entity: PhantomData }
+        }
+
+        fn owner_condition(&self) -> Condition {
+            match self.principal.user_id {
+                Some(user_id) => Condition::all().add(E::owner_column().eq(user_id)),
+                // Matches nothing
+                None => Condition::all().add(Expr::val(1).eq(0)),
+            }
+        }
+
+        fn read_condition(&self) -> Option<Condition> {
+            if self.principal.is_admin {
+                return None;
+            }
+            let owner = self.owner_condition();
+            Some(match E::public_condition() {
+                Some(public) => Condition::any().add(owner).add(public),
+                None => owner,
+            })
+        }
+
+        fn write_condition(&self) -> Option<Condition> {
+            if self.principal.is_admin {
+                return None;
+            }
+            Some(self.owner_condition())
+        }
+
+        fn scope(&self, select: Select<E>) -> Select<E> {
+            match self.read_condition() {
+                Some(condition) => select.filter(condition),
+                None => select,
+            }
+        }
+
+        /// Every row the principal may read.
+        pub fn select(&self) -> Select<E> {
+            self.scope(E::find())
+        }
+
+        pub async fn find_by_id<C, K>(&self, db: &C, id: K) -> Result<Option<E::Model>, DbErr>
+        where
+            C: ConnectionTrait,
+            K: Into<<E::PrimaryKey as PrimaryKeyTrait>::ValueType>,
+        {
+            self.scope(E::find_by_id(id)).one(db).await
+        }
+
+        /// Like `find_by_id` but only returns rows the principal may modify.
+        pub async fn find_owned_by_id<C, K>(&self, db: &C, id: K) -> Result<Option<E::Model>, DbErr>
+        where
+            C: ConnectionTrait,
+            K: Into<<E::PrimaryKey as PrimaryKeyTrait>::ValueType>,
+        {
+            let select = E::find_by_id(id);
+            match self.write_condition() {
+                Some(condition) => select.filter(condition).one(db).await,
+                None => select.one(db).await,
+            }
+        }
+    }
+
+    pub struct UserRepository;
+
+    impl UserRepository {
+        pub async fn find_by_id<C: ConnectionTrait>(db: &C, id: Uuid) -> Result<Option<user::Model>, DbErr> {
+            user::Entity::find_by_id(id).one(db).await
+        }
+
+        pub async fn find_by_email<C: ConnectionTrait>(db: &C, email: &str) -> Result<Option<user::Model>, DbErr> {
+            user::Entity::find().filter(user::Column::Email.eq(email)).one(db).await
+        }
+
+        pub async fn find_all_with_filter<C: ConnectionTrait>(db: &C, filters: &Filters<user::Entity>) -> Result<Vec<user::Model>, DbErr> {
+            Self::select_with_filter(filters).all(db).await
+        }
+
+        /// Ordered by creation so exports come out in a stable order; run it with
+        /// `.stream(db)` to read rows off a cursor instead of collecting them.
+        pub fn select_with_filter(filters: &Filters<user::Entity>) -> Select<user::Entity> {
+            filters.apply(user::Entity::find()).order_by_asc(user::Column::CreatedAt)
+        }
+    }
+
+    pub struct RoleRepository;
+
+    impl RoleRepository {
+        pub async fn find_by_name<C: ConnectionTrait>(db: &C, name: &str) -> Result<Option<role::Model>, DbErr> {
+            role::Entity::find().filter(role::Column::Name.eq(name)).one(db).await
+        }
+
+        pub async fn find_by_names<C: ConnectionTrait>(db: &C, names: &[String]) -> Result<Vec<role::Model>, DbErr> {
+            role::Entity::find().filter(role::Column::Name.is_in(names.iter().cloned())).all(db).await
+        }
+
+        pub async fn find_by_id<C: ConnectionTrait>(db: &C, id: Uuid) -> Result<Option<role::Model>, DbErr> {
+            role::Entity::find_by_id(id).one(db).await
+        }
+
+        pub async fn find_all<C: ConnectionTrait>(db: &C) -> Result<Vec<role::Model>, DbErr> {
+            role::Entity::find().all(db).await
+        }
+
+        pub async fn save(txn: &TimedTransaction, role_model: role::ActiveModel) -> Result<role::Model, DbErr> {
+            role_model.insert(txn).await
+        }
+
+        pub async fn update(txn: &TimedTransaction, role_model: role::ActiveModel) -> Result<role::Model, DbErr> {
+            role_model.update(txn).await
+        }
+
+        pub async fn delete(txn: &TimedTransaction, id: Uuid) -> Result<(), DbErr> {
+            role::Entity::delete_by_id(id).exec(txn).await?;
+            Ok(())
+        }
+    }
+
+    // Post queries live on the scoped repository, so none can be built without a principal
+    impl ScopedRepository<post::Entity> {
+        pub fn all(&self, filters: &Filters<post::Entity>) -> Select<post::Entity> {
+            filters.apply(self.select())
+        }
+
+        pub fn by_author(&self, user_id: Uuid, filters: &Filters<post::Entity>) -> Select<post::Entity> {
+            filters.apply(self.select().filter(post::Column::UserId.eq(user_id)))
+        }
+
+        pub fn by_authors(&self, user_ids: &[Uuid]) -> Select<post::Entity> {
+            self.select().filter(post::Column::UserId.is_in(user_ids.iter().copied()))
+        }
+
+        pub fn by_ids(&self, ids: &HashSet<Uuid>) -> Select<post::Entity> {
+            self.select().filter(post::Column::Id.is_in(ids.iter().copied()))
+        }
+
+        pub fn by_tag(&self, tag_id: Uuid, filters: &Filters<post::Entity>) -> Select<post::Entity> {
+            filters.apply(
+                self.select()
+                    .join(JoinType::InnerJoin, post::Relation::PostTag.def())
+                    .filter(post_tag::Column::TagId.eq(tag_id)),
+            )
+        }
+    }
+
+    pub struct CommentRepository;
+
+    impl CommentRepository {
+        pub async fn find_by_id<C: ConnectionTrait>(db: &C, id: Uuid) -> Result<Option<comment::Model>, DbErr> {
+            comment::Entity::find_by_id(id).one(db).await
+        }
+
+        pub async fn insert<C: ConnectionTrait>(db: &C, comment_model: comment::ActiveModel) -> Result<comment::Model, DbErr> {
+            comment_model.insert(db).await
+        }
+
+        pub async fn count_top_level<C: ConnectionTrait>(db: &C, post_id: Uuid) -> Result<u64, DbErr> {
+            comment::Entity::find()
+                .filter(comment::Column::PostId.eq(post_id))
+                .filter(comment::Column::ParentCommentId.is_null())
+                .count(db)
+                .await
+        }
+
+        /// `page` is zero-based.
+        pub async fn find_top_level_page<C: ConnectionTrait>(db: &C, post_id: Uuid, page: u64, per_page: u64) -> Result<Vec<comment::Model>, DbErr> {
+            comment::Entity::find()
+                .filter(comment::Column::PostId.eq(post_id))
+                .filter(comment::Column::ParentCommentId.is_null())
+                .order_by_asc(comment::Column::CreatedAt)
+                .paginate(db, per_page)
+                .fetch_page(page)
+                .await
+        }
+
+        pub async fn find_children<C: ConnectionTrait>(db: &C, parent_ids: &[Uuid]) -> Result<Vec<comment::Model>, DbErr> {
+            comment::Entity::find()
+                .filter(comment::Column::ParentCommentId.is_in(parent_ids.iter().copied()))
+                .order_by_asc(comment::Column::CreatedAt)
+                .all(db)
+                .await
+        }
+
+        pub async fn delete_many(txn: &TimedTransaction, ids: &[Uuid]) -> Result<u64, DbErr> {
+            let result = comment::Entity::delete_many()
+                .filter(comment::Column::Id.is_in(ids.iter().copied()))
+                .exec(txn)
+                .await?;
+            Ok(result.rows_affected)
+        }
+    }
+
+    pub struct TagRepository;
+
+    impl TagRepository {
+        pub async fn find_by_id<C: ConnectionTrait>(db: &C, id: Uuid) -> Result<Option<tag::Model>, DbErr> {
+            tag::Entity::find_by_id(id).one(db).await
+        }
+
+        pub async fn find_by_name<C: ConnectionTrait>(db: &C, name: &str) -> Result<Option<tag::Model>, DbErr> {
+            tag::Entity::find().filter(tag::Column::Name.eq(name)).one(db).await
+        }
+
+        pub async fn find_by_names<C: ConnectionTrait>(db: &C, names: &[String]) -> Result<Vec<tag::Model>, DbErr> {
+            tag::Entity::find().filter(tag::Column::Name.is_in(names.iter().cloned())).all(db).await
+        }
+
+        pub async fn find_for_post<C: ConnectionTrait>(db: &C, post_id: Uuid) -> Result<Vec<tag::Model>, DbErr> {
+            tag::Entity::find()
+                .join(JoinType::InnerJoin, tag::Relation::PostTag.def())
+                .filter(post_tag::Column::PostId.eq(post_id))
+                .order_by_asc(tag::Column::Name)
+                .all(db)
+                .await
+        }
+
+        /// Tags for many posts in two queries, as (post_id, tag) pairs ordered by tag name.
+        pub async fn find_for_posts<C: ConnectionTrait>(db: &C, post_ids: &[Uuid]) -> Result<Vec<(Uuid, tag::Model)>, DbErr> {
+            let links = post_tag::Entity::find()
+                .filter(post_tag::Column::PostId.is_in(post_ids.iter().copied()))
+                .all(db)
+                .await?;
+            let tag_ids: HashSet<Uuid> = links.iter().map(|link| link.tag_id).collect();
+            let tags: HashMap<Uuid, tag::Model> = tag::Entity::find()
+                .filter(tag::Column::Id.is_in(tag_ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|tag| (tag.id, tag))
+                .collect();
+            let mut pairs: Vec<(Uuid, tag::Model)> = links
+                .into_iter()
+                .filter_map(|link| tags.get(&link.tag_id).map(|tag| (link.post_id, tag.clone())))
+                .collect();
+            pairs.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+            Ok(pairs)
+        }
+
+        /// Returns existing tags for `names`, creating any that are missing.
+        pub async fn find_or_create(txn: &TimedTransaction, names: &[String]) -> Result<Vec<tag::Model>, DbErr> {
+            let mut tags = Self::find_by_names(txn, names).await?;
+            let missing: Vec<tag::ActiveModel> = names.iter()
+                .filter(|name| !tags.iter().any(|t| &t.name == *name))
+                .map(|name| tag::ActiveModel {
+                    id: ActiveValue::Set(Uuid::new_v4()),
+                    name: ActiveValue::Set(name.clone()),
+                })
+                .collect();
+            for model in missing {
+                tags.push(model.insert(txn).await?);
+            }
+            Ok(tags)
+        }
+
+        // Prefix match for autocomplete, most used tags first
+        pub async fn search_with_counts<C: ConnectionTrait>(db: &C, prefix: &str, limit: u64) -> Result<Vec<TagWithCountDto>, DbErr> {
+            tag::Entity::find()
+                .select_only()
+                .column(tag::Column::Id)
+                .column(tag::Column::Name)
+                .column_as(post_tag::Column::PostId.count(), "post_count")
+                .join(JoinType::LeftJoin, tag::Relation::PostTag.def())
+                // starts_with would let `%` and `_` in the prefix act as wildcards
+                .filter(Expr::col((tag::Entity, tag::Column::Name)).like(LikeExpr::new(format!("{}%", escape_like(prefix))).escape('\\')))
+                .group_by(tag::Column::Id)
+                .group_by(tag::Column::Name)
+                .order_by_desc(Expr::cust("post_count"))
+                .order_by_asc(tag::Column::Name)
+                .limit(limit)
+                .into_model::<TagWithCountDto>()
+                .all(db)
+                .await
+        }
+
+        pub async fn delete(txn: &TimedTransaction, id: Uuid) -> Result<(), DbErr> {
+            tag::Entity::delete_by_id(id).exec(txn).await?;
+            Ok(())
+        }
+    }
+
+    pub struct PostTagRepository;
+
+    impl PostTagRepository {
+        pub async fn remove_all_for_tag(txn: &TimedTransaction, tag_id: Uuid) -> Result<u64, DbErr> {
+            let result = post_tag::Entity::delete_many()
+                .filter(post_tag::Column::TagId.eq(tag_id))
+                .exec(txn)
+                .await?;
+            Ok(result.rows_affected)
+        }
+
+        // Same diffing approach as UserRoleRepository::replace_roles
+        pub async fn replace_tags(txn: &TimedTransaction, post_id: Uuid, tag_ids: &[Uuid]) -> Result<(), DbErr> {
+            let current: HashSet<Uuid> = TagRepository::find_for_post(txn, post_id).await?
+                .into_iter()
+                .map(|t| t.id)
+                .collect();
+            let desired: HashSet<Uuid> = tag_ids.iter().copied().collect();
+
+            let to_remove: Vec<Uuid> = current.difference(&desired).copied().collect();
+            if !to_remove.is_empty() {
+                post_tag::Entity::delete_many()
+                    .filter(post_tag::Column::PostId.eq(post_id))
+                    .filter(post_tag::Column::TagId.is_in(to_remove))
+                    .exec(txn)
+                    .await?;
+            }
+
+            let to_add: Vec<post_tag::ActiveModel> = desired.difference(&current)
+                .map(|tag_id| post_tag::ActiveModel {
+                    post_id: ActiveValue::Set(post_id),
+                    tag_id: ActiveValue::Set(*tag_id),
+                })
+                .collect();
+            if !to_add.is_empty() {
+                post_tag::Entity::insert_many(to_add).exec(txn).await?;
+            }
+            Ok(())
+        }
+    }
+
+    pub struct FeatureFlagRepository;
+
+    impl FeatureFlagRepository {
+        pub async fn find_all<C: ConnectionTrait>(db: &C) -> Result<Vec<feature_flag::Model>, DbErr> {
+            feature_flag::Entity::find().order_by_asc(feature_flag::Column::Name).all(db).await
+        }
+
+        pub async fn find_all_overrides<C: ConnectionTrait>(db: &C) -> Result<Vec<feature_flag_override::Model>, DbErr> {
+            feature_flag_override::Entity::find().all(db).await
+        }
+
+        pub async fn upsert<C: ConnectionTrait>(db: &C, flag: feature_flag::ActiveModel) -> Result<(), DbErr> {
+            feature_flag::Entity::insert(flag)
+                .on_conflict(
+                    OnConflict::column(feature_flag::Column::Name)
+                        .update_columns([
+                            feature_flag::Column::Enabled,
+                            feature_flag::Column::RolloutPercentage,
+                            feature_flag::Column::Description,
+                            feature_flag::Column::UpdatedAt,
+                        ])
+                        .to_owned(),
+                )
+                .exec(db)
+                .await?;
+            Ok(())
+        }
+
+        pub async fn upsert_override<C: ConnectionTrait>(db: &C, flag_override: feature_flag_override::ActiveModel) -> Result<(), DbErr> {
+            feature_flag_override::Entity::insert(flag_override)
+                .on_conflict(
+                    OnConflict::columns([feature_flag_override::Column::FlagName, feature_flag_override::Column::TenantId])
+                        .update_column(feature_flag_override::Column::Enabled)
+                        .to_owned(),
+                )
+                .exec(db)
+                .await?;
+            Ok(())
+        }
+
+        pub async fn delete_override<C: ConnectionTrait>(db: &C, flag_name: &str, tenant_id: &str) -> Result<u64, DbErr> {
+            let result = feature_flag_override::Entity::delete_many()
+                .filter(feature_flag_override::Column::FlagName.eq(flag_name))
+                .filter(feature_flag_override::Column::TenantId.eq(tenant_id))
+                .exec(db)
+                .await?;
+            Ok(result.rows_affected)
+        }
+    }
+
+    pub struct TenantSettingRepository;
+
+    impl TenantSettingRepository {
+        pub async fn find_for_tenant<C: ConnectionTrait>(db: &C, tenant_id: &str) -> Result<Vec<tenant_setting::Model>, DbErr> {
+            tenant_setting::Entity::find()
+                .filter(tenant_setting::Column::TenantId.eq(tenant_id))
+                .order_by_asc(tenant_setting::Column::Key)
+                .all(db)
+                .await
+        }
+
+        pub async fn upsert<C: ConnectionTrait>(db: &C, setting: tenant_setting::ActiveModel) -> Result<(), DbErr> {
+            tenant_setting::Entity::insert(setting)
+                .on_conflict(
+                    OnConflict::columns([tenant_setting::Column::TenantId, tenant_setting::Column::Key])
+                        .update_columns([tenant_setting::Column::Value, tenant_setting::Column::UpdatedAt])
+                        .to_owned(),
+                )
+                .exec(db)
+                .await?;
+            Ok(())
+        }
+    }
+
+    pub struct ProfileRepository;
+
+    impl ProfileRepository {
+        pub async fn find_by_user_id<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<Option<profile::Model>, DbErr> {
+            profile::Entity::find_by_id(user_id).one(db).await
+        }
+
+        pub async fn find_by_user_ids<C: ConnectionTrait>(db: &C, user_ids: &[Uuid]) -> Result<Vec<profile::Model>, DbErr> {
+            profile::Entity::find()
+                .filter(profile::Column::UserId.is_in(user_ids.iter().copied()))
+                .all(db)
+                .await
+        }
+
+        pub async fn insert(txn: &TimedTransaction, profile_model: profile::ActiveModel) -> Result<profile::Model, DbErr> {
+            profile_model.insert(txn).await
+        }
+
+        pub async fn update(txn: &TimedTransaction, profile_model: profile::ActiveModel) -> Result<profile::Model, DbErr> {
+            profile_model.update(txn).await
+        }
+    }
+
+    pub struct UserRoleRepository;
+
+    impl UserRoleRepository {
+        pub async fn assign_role_to_user(txn: &TimedTransaction, user_id: Uuid, role_id: Uuid) -> Result<(), DbErr> {
+            let user_role = user_role::ActiveModel {
+                user_id: ActiveValue::Set(user_id),
+                role_id: ActiveValue::Set(role_id),
+            };
+            user_role::Entity::insert(user_role).exec(txn).await?;
+            Ok(())
+        }
+
+        pub async fn find_roles_for_user<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<Vec<role::Model>, DbErr> {
+            role::Entity::find()
+                .join(JoinType::InnerJoin, role::Relation::UserRole.def())
+                .filter(user_role::Column::UserId.eq(user_id))
+                .all(db)
+                .await
+        }
+
+        /// Roles for many users in two queries, as (user_id, role) pairs ordered by role name.
+        pub async fn find_roles_for_users<C: ConnectionTrait>(db: &C, user_ids: &[Uuid]) -> Result<Vec<(Uuid, role::Model)>, DbErr> {
+            let assignments = user_role::Entity::find()
+                .filter(user_role::Column::UserId.is_in(user_ids.iter().copied()))
+                .all(db)
+                .await?;
+            let role_ids: HashSet<Uuid> = assignments.iter().map(|a| a.role_id).collect();
+            let roles: HashMap<Uuid, role::Model> = role::Entity::find()
+                .filter(role::Column::Id.is_in(role_ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|role| (role.id, role))
+                .collect();
+            let mut pairs: Vec<(Uuid, role::Model)> = assignments
+                .into_iter()
+                .filter_map(|a| roles.get(&a.role_id).map(|role| (a.user_id, role.clone())))
+                .collect();
+            pairs.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+            Ok(pairs)
+        }
+
+        /// Returns the number of assignments removed (0 if the user did not have the role).
+        pub async fn remove_role(txn: &TimedTransaction, user_id: Uuid, role_id: Uuid) -> Result<u64, DbErr> {
+            let result = user_role::Entity::delete_many()
+                .filter(user_role::Column::UserId.eq(user_id))
+                .filter(user_role::Column::RoleId.eq(role_id))
+                .exec(txn)
+                .await?;
+            Ok(result.rows_affected)
+        }
+
+        pub async fn count_users_with_role<C: ConnectionTrait>(db: &C, role_id: Uuid) -> Result<u64, DbErr> {
+            user_role::Entity::find()
+                .filter(user_role::Column::RoleId.eq(role_id))
+                .count(db)
+                .await
+        }
+
+        pub async fn remove_all_for_role(txn: &TimedTransaction, role_id: Uuid) -> Result<u64, DbErr> {
+            let result = user_role::Entity::delete_many()
+                .filter(user_role::Column::RoleId.eq(role_id))
+                .exec(txn)
+                .await?;
+            Ok(result.rows_affected)
+        }
+
+        // Diffs the desired set against current assignments so untouched rows are left alone
+        pub async fn replace_roles(txn: &TimedTransaction, user_id: Uuid, role_ids: &[Uuid]) -> Result<(), DbErr> {
+            let current: HashSet<Uuid> = Self::find_roles_for_user(txn, user_id).await?
+                .into_iter()
+                .map(|r| r.id)
+                .collect();
+            let desired: HashSet<Uuid> = role_ids.iter().copied().collect();
+
+            let to_remove: Vec<Uuid> = current.difference(&desired).copied().collect();
+            if !to_remove.is_empty() {
+                user_role::Entity::delete_many()
+                    .filter(user_role::Column::UserId.eq(user_id))
+                    .filter(user_role::Column::RoleId.is_in(to_remove))
+                    .exec(txn)
+                    .await?;
+            }
+
+            let to_add: Vec<user_role::ActiveModel> = desired.difference(&current)
+                .map(|role_id| user_role::ActiveModel {
+                    user_id: ActiveValue::Set(user_id),
+                    role_id: ActiveValue::Set(*role_id),
+                })
+                .collect();
+            if !to_add.is_empty() {
+                user_role::Entity::insert_many(to_add).exec(txn).await?;
+            }
+            Ok(())
+        }
+    }
+
+    // Rows per multi-row INSERT; keeps well under SQLite's bound-parameter limit
+    const BACKUP_INSERT_BATCH: usize = 200;
+
+    pub struct BackupRepository;
+
+    impl BackupRepository {
+        pub async fn snapshot<C: ConnectionTrait>(db: &C) -> Result<BackupDto, DbErr> {
+            Ok(BackupDto {
+                schema_version: BACKUP_SCHEMA_VERSION,
+                exported_at: chrono::Utc::now(),
+                users: user::Entity::find().order_by_asc(user::Column::CreatedAt).all(db).await?,
+                roles: role::Entity::find().order_by_asc(role::Column::Name).all(db).await?,
+                user_roles: user_role::Entity::find().all(db).await?,
+                posts: ScopedRepository::<post::Entity>::new(Principal::system()).select().all(db).await?,
+                profiles: profile::Entity::find().all(db).await?,
+                tags: tag::Entity::find().order_by_asc(tag::Column::Name).all(db).await?,
+                post_tags: post_tag::Entity::find().all(db).await?,
+                // Replies are newer than their parents, so this order restores cleanly
+                comments: comment::Entity::find().order_by_asc(comment::Column::CreatedAt).all(db).await?,
+            })
+        }
+
+        /// Removes every table a backup covers, children first.
+        pub async fn clear(txn: &TimedTransaction) -> Result<(), DbErr> {
+            comment::Entity::delete_many().exec(txn).await?;
+            post_tag::Entity::delete_many().exec(txn).await?;
+            tag::Entity::delete_many().exec(txn).await?;
+            post::Entity::delete_many().exec(txn).await?;
+            profile::Entity::delete_many().exec(txn).await?;
+            user_role::Entity::delete_many().exec(txn).await?;
+            user::Entity::delete_many().exec(txn).await?;
+            role::Entity::delete_many().exec(txn).await?;
+            Ok(())
+        }
+
+        /// Upserts by primary key, parents before children.
+        pub async fn upsert(txn: &TimedTransaction, backup: &BackupDto) -> Result<(), DbErr> {
+            for chunk in backup.roles.chunks(BACKUP_INSERT_BATCH) {
+                role::Entity::insert_many(chunk.iter().cloned().map(IntoActiveModel::into_active_model))
+                    .on_conflict(OnConflict::column(role::Column::Id).update_column(role::Column::Name).to_owned())
+                    .exec(txn)
+                    .await?;
+            }
+            for chunk in backup.users.chunks(BACKUP_INSERT_BATCH) {
+                user::Entity::insert_many(chunk.iter().cloned().map(IntoActiveModel::into_active_model))
+                    .on_conflict(
+                        OnConflict::column(user::Column::Id)
+                            .update_columns([
+                                user::Column::Email,
+                                user::Column::PasswordHash,
+                                user::Column::IsActive,
+                                user::Column::CreatedAt,
+                            ])
+                            .to_owned(),
+                    )
+                    .exec(txn)
+                    .await?;
+            }
+            for chunk in backup.user_roles.chunks(BACKUP_INSERT_BATCH) {
+                let inserted = user_role::Entity::insert_many(chunk.iter().cloned().map(IntoActiveModel::into_active_model))
+                    .on_conflict(OnConflict::columns([user_role::Column::UserId, user_role::Column::RoleId]).do_nothing().to_owned())
+                    .exec(txn)
+                    .await;
+                match inserted {
+                    // Every pair in the batch already existed
+                    Ok(_) | Err(DbErr::RecordNotInserted) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            for chunk in backup.posts.chunks(BACKUP_INSERT_BATCH) {
+                post::Entity::insert_many(chunk.iter().cloned().map(IntoActiveModel::into_active_model))
+                    .on_conflict(
+                        OnConflict::column(post::Column::Id)
+                            .update_columns([post::Column::UserId, post::Column::Title, post::Column::Content, post::Column::Status])
+                            .to_owned(),
+                    )
+                    .exec(txn)
+                    .await?;
+            }
+            for chunk in backup.profiles.chunks(BACKUP_INSERT_BATCH) {
+                profile::Entity::insert_many(chunk.iter().cloned().map(IntoActiveModel::into_active_model))
+                    .on_conflict(
+                        OnConflict::column(profile::Column::UserId)
+                            .update_columns([
+                                profile::Column::DisplayName,
+                                profile::Column::Bio,
+                                profile::Column::AvatarUrl,
+                                profile::Column::Locale,
+                                profile::Column::Timezone,
+                                profile::Column::UpdatedAt,
+                            ])
+                            .to_owned(),
+                    )
+                    .exec(txn)
+                    .await?;
+            }
+            for chunk in backup.tags.chunks(BACKUP_INSERT_BATCH) {
+                tag::Entity::insert_many(chunk.iter().cloned().map(IntoActiveModel::into_active_model))
+                    .on_conflict(OnConflict::column(tag::Column::Id).update_column(tag::Column::Name).to_owned())
+                    .exec(txn)
+                    .await?;
+            }
+            for chunk in backup.post_tags.chunks(BACKUP_INSERT_BATCH) {
+                let inserted = post_tag::Entity::insert_many(chunk.iter().cloned().map(IntoActiveModel::into_active_model))
+                    .on_conflict(OnConflict::columns([post_tag::Column::PostId, post_tag::Column::TagId]).do_nothing().to_owned())
+                    .exec(txn)
+                    .await;
+                match inserted {
+                    Ok(_) | Err(DbErr::RecordNotInserted) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            // A hand-edited backup may list replies first; parents have to exist before them
+            let mut comments = backup.comments.clone();
+            comments.sort_by_key(|c| c.created_at);
+            for chunk in comments.chunks(BACKUP_INSERT_BATCH) {
+                comment::Entity::insert_many(chunk.iter().cloned().map(IntoActiveModel::into_active_model))
+                    .on_conflict(
+                        OnConflict::column(comment::Column::Id)
+                            .update_columns([
+                                comment::Column::PostId,
+                                comment::Column::AuthorId,
+                                comment::Column::ParentCommentId,
+                                comment::Column::Body,
+                                comment::Column::CreatedAt,
+                            ])
+                            .to_owned(),
+                    )
+                    .exec(txn)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        pub async fn existing_user_ids<C: ConnectionTrait>(db: &C, ids: &HashSet<Uuid>) -> Result<HashSet<Uuid>, DbErr> {
+            if ids.is_empty() {
+                return Ok(HashSet::new());
+            }
+            let users = user::Entity::find().filter(user::Column::Id.is_in(ids.iter().copied())).all(db).await?;
+            Ok(users.into_iter().map(|u| u.id).collect())
+        }
+
+        pub async fn existing_role_ids<C: ConnectionTrait>(db: &C, ids: &HashSet<Uuid>) -> Result<HashSet<Uuid>, DbErr> {
+            if ids.is_empty() {
+                return Ok(HashSet::new());
+            }
+            let roles = role::Entity::find().filter(role::Column::Id.is_in(ids.iter().copied())).all(db).await?;
+            Ok(roles.into_iter().map(|r| r.id).collect())
+        }
+
+        pub async fn existing_post_ids<C: ConnectionTrait>(db: &C, ids: &HashSet<Uuid>) -> Result<HashSet<Uuid>, DbErr> {
+            if ids.is_empty() {
+                return Ok(HashSet::new());
+            }
+            let posts = ScopedRepository::<post::Entity>::new(Principal::system()).by_ids(ids).all(db).await?;
+            Ok(posts.into_iter().map(|p| p.id).collect())
+        }
+
+        pub async fn existing_tag_ids<C: ConnectionTrait>(db: &C, ids: &HashSet<Uuid>) -> Result<HashSet<Uuid>, DbErr> {
+            if ids.is_empty() {
+                return Ok(HashSet::new());
+            }
+            let tags = tag::Entity::find().filter(tag::Column::Id.is_in(ids.iter().copied())).all(db).await?;
+            Ok(tags.into_iter().map(|t| t.id).collect())
+        }
+
+        pub async fn existing_comment_ids<C: ConnectionTrait>(db: &C, ids: &HashSet<Uuid>) -> Result<HashSet<Uuid>, DbErr> {
+            if ids.is_empty() {
+                return Ok(HashSet::new());
+            }
+            let comments = comment::Entity::find().filter(comment::Column::Id.is_in(ids.iter().copied())).all(db).await?;
+            Ok(comments.into_iter().map(|c| c.id).collect())
+        }
+
+        pub async fn users_by_emails<C: ConnectionTrait>(db: &C, emails: Vec<String>) -> Result<Vec<user::Model>, DbErr> {
+            user::Entity::find().filter(user::Column::Email.is_in(emails)).all(db).await
+        }
+
+        pub async fn roles_by_names<C: ConnectionTrait>(db: &C, names: Vec<String>) -> Result<Vec<role::Model>, DbErr> {
+            role::Entity::find().filter(role::Column::Name.is_in(names)).all(db).await
+        }
+
+        pub async fn tags_by_names<C: ConnectionTrait>(db: &C, names: Vec<String>) -> Result<Vec<tag::Model>, DbErr> {
+            tag::Entity::find().filter(tag::Column::Name.is_in(names)).all(db).await
+        }
+    }
+
+    pub struct SavedSearchRepository;
+
+    impl SavedSearchRepository {
+        pub async fn find_by_name<C: ConnectionTrait>(db: &C, user_id: Uuid, name: &str) -> Result<Option<saved_search::Model>, DbErr> {
+            saved_search::Entity::find()
+                .filter(saved_search::Column::UserId.eq(user_id))
+                .filter(saved_search::Column::Name.eq(name))
+                .one(db)
+                .await
+        }
+
+        pub async fn find_for_user<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<Vec<saved_search::Model>, DbErr> {
+            saved_search::Entity::find()
+                .filter(saved_search::Column::UserId.eq(user_id))
+                .order_by_asc(saved_search::Column::Name)
+                .all(db)
+                .await
+        }
+
+        pub async fn count_for_user<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<u64, DbErr> {
+            saved_search::Entity::find()
+                .filter(saved_search::Column::UserId.eq(user_id))
+                .count(db)
+                .await
+        }
+
+        pub async fn insert<C: ConnectionTrait>(db: &C, search: saved_search::ActiveModel) -> Result<saved_search::Model, DbErr> {
+            search.insert(db).await
+        }
+
+        pub async fn delete<C: ConnectionTrait>(db: &C, user_id: Uuid, name: &str) -> Result<u64, DbErr> {
+            let result = saved_search::Entity::delete_many()
+                .filter(saved_search::Column::UserId.eq(user_id))
+                .filter(saved_search::Column::Name.eq(name))
+                .exec(db)
+                .await?;
+            Ok(result.rows_affected)
+        }
+    }
+
+    // Aggregates for dashboards; rows are counted in the database, never loaded
+    pub struct StatsRepository;
+
+    impl StatsRepository {
+        /// Start-of-bucket date as `YYYY-MM-DD`. Date functions differ per backend.
+        fn bucket_expr(backend: DbBackend, bucket: TimeBucket, column: &str) -> SimpleExpr {
+            let sql = match (backend, bucket) {
+                (DbBackend::Sqlite, TimeBucket::Day) => format!("date({})", column),
+                // Forward to Sunday, then back to that week's Monday
+                (DbBackend::Sqlite, TimeBucket::Week) => format!("date({}, 'weekday 0', '-6 days')", column),
+                (DbBackend::Postgres, TimeBucket::Day) => format!("to_char(date_trunc('day', {}), 'YYYY-MM-DD')", column),
+                (DbBackend::Postgres, TimeBucket::Week) => format!("to_char(date_trunc('week', {}), 'YYYY-MM-DD')", column),
+                (DbBackend::MySql, TimeBucket::Day) => format!("DATE_FORMAT({}, '%Y-%m-%d')", column),
+                (DbBackend::MySql, TimeBucket::Week) => {
+                    format!("DATE_FORMAT(DATE_SUB({0}, INTERVAL WEEKDAY({0}) DAY), '%Y-%m-%d')", column)
+                }
+            };
+            Expr::cust(sql)
+        }
+
+        pub async fn users_created_per_bucket<C: ConnectionTrait>(
+            db: &C,
+            bucket: TimeBucket,
+            since: ChronoDateTimeUtc,
+        ) -> Result<Vec<BucketCountDto>, DbErr> {
+            let bucket_expr = Self::bucket_expr(db.get_database_backend(), bucket, "created_at");
+            user::Entity::find()
+                .select_only()
+                .column_as(bucket_expr.clone(), "bucket")
+                .column_as(user::Column::Id.count(), "count")
+                .filter(user::Column::CreatedAt.gte(since))
+                .group_by(bucket_expr)
+                .order_by_asc(Expr::cust("bucket"))
+                .into_model::<BucketCountDto>()
+                .all(db)
+                .await
+        }
+
+        /// `select` arrives already scoped to what the caller may see.
+        pub async fn post_counts_by_status<C: ConnectionTrait>(db: &C, select: Select<post::Entity>) -> Result<Vec<StatusCountDto>, DbErr> {
+            select
+                .select_only()
+                .column(post::Column::Status)
+                .column_as(post::Column::Id.count(), "count")
+                .group_by(post::Column::Status)
+                .order_by_asc(post::Column::Status)
+                .into_model::<StatusCountDto>()
+                .all(db)
+                .await
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::migrator::Migrator;
+        use sea_orm::{Database, DatabaseConnection};
+        use sea_orm_migration::MigratorTrait;
+
+        async fn add_user(db: &DatabaseConnection, email: &str) -> Uuid {
+            let id = Uuid::new_v4();
+            user::ActiveModel {
+                id: ActiveValue::Set(id),
+                email: ActiveValue::Set(email.to_string()),
+                password_hash: ActiveValue::Set(String::new()),
+                is_active: ActiveValue::Set(true),
+                created_at: ActiveValue::Set(chrono::Utc::now()),
+            }
+            .insert(db)
+            .await
+            .unwrap();
+            id
+        }
+
+        async fn add_post(db: &DatabaseConnection, user_id: Uuid, status: post::PostStatus) -> Uuid {
+            let id = Uuid::new_v4();
+            post::ActiveModel {
+                id: ActiveValue::Set(id),
+                user_id: ActiveValue::Set(user_id),
+                title: ActiveValue::Set("Title".to_string()),
+                content: ActiveValue::Set(String::new()),
+                status: ActiveValue::Set(status),
+            }
+            .insert(db)
+            .await
+            .unwrap();
+            id
+        }
+
+        #[actix_web::test]
+        async fn post_queries_hide_other_users_drafts() {
+            let db = Database::connect("sqlite::memory:").await.unwrap();
+            Migrator::up(&db, None).await.unwrap();
+            let author = add_user(&db, "author@example.com").await;
+            let other = add_user(&db, "other@example.com").await;
+            let published = add_post(&db, author, post::PostStatus::Published).await;
+            let draft = add_post(&db, author, post::PostStatus::Draft).await;
+
+            let visible = |principal: Principal| {
+                let db = db.clone();
+                async move {
+                    let mut ids: Vec<Uuid> = ScopedRepository::<post::Entity>::new(principal)
+                        .by_author(author, &Filters::none())
+                        .all(&db)
+                        .await
+                        .unwrap()
+                        .into_iter()
+                        .map(|p| p.id)
+                        .collect();
+                    ids.sort();
+                    ids
+                }
+            };
+            let mut both = vec![published, draft];
+            both.sort();
+
+            assert_eq!(visible(Principal::anonymous()).await, vec![published]);
+            assert_eq!(visible(Principal { user_id: Some(other), is_admin: false }).await, vec![published]);
+            assert_eq!(visible(Principal { user_id: Some(author), is_admin: false }).await, both);
+            assert_eq!(visible(Principal::system()).await, both);
+        }
+    }
+}
+
+// --- 8. Service Layer (services/user_service.rs) ---
+mod services {
+    use super::models::{dtos::{CreateUserDto, CreateRoleDto, UpdateRoleDto, ProfileMergePatchDto, TagWithCountDto, TaggedPostsDto, CreateCommentDto, CommentNodeDto, CommentPageDto, UpsertFeatureFlagDto, UserStatsQuery, UserStatsDto, BucketCountDto, StatusCountDto, TimeBucket, BackupDto, ImportMode, ImportSummaryDto, CreateSavedSearchDto, SavedSearchDto, UserWithIncludesDto, PostWithIncludesDto, BACKUP_SCHEMA_VERSION}, user, post, role, profile, tag, comment, feature_flag, feature_flag_override, tenant_setting, saved_search};
+    use super::filters::{FilterDocument, Filterable, Filters};
+    use super::repositories::{UserRepository, RoleRepository, UserRoleRepository, ProfileRepository, TagRepository, PostTagRepository, CommentRepository, FeatureFlagRepository, TenantSettingRepository, StatsRepository, BackupRepository, SavedSearchRepository, Principal, ScopedRepository};
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::io::Read;
+    use std::sync::Arc;
+    use super::ApiError;
+    use super::resilience::{ResilientConnection, TimedTransaction};
+    use sea_orm::{prelude::*, ActiveValue, QuerySelect};
+
+    const MAX_INCLUDE_DEPTH: usize = 2;
+    const MAX_EXPANDED_USERS: usize = 100;
+    const MAX_INCLUDED_POSTS: u64 = 1000;
+
+    /// Relations requested with `?include=posts,posts.tags,roles,profile`.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct UserIncludes {
+        pub posts: bool,
+        pub post_tags: bool,
+        pub roles: bool,
+        pub profile: bool,
+    }
+
+    impl UserIncludes {
+        pub fn parse(include: Option<&str>) -> Result<Self, ApiError> {
+            let mut includes = Self::default();
+            for path in include.unwrap_or_default().split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                if path.split('.').count() > MAX_INCLUDE_DEPTH {
+                    return Err(ApiError::BadRequest(format!("Include '{}' is nested more than {} levels deep", path, MAX_INCLUDE_DEPTH)));
+                }
+                match path {
+                    "posts" => includes.posts = true,
+                    "posts.tags" => {
+                        includes.posts = true;
+                        includes.post_tags = true;
+                    }
+                    "roles" => includes.roles = true,
+                    "profile" => includes.profile = true,
+                    other => return Err(ApiError::BadRequest(format!(
+                        "Unknown include '{}'; expected posts, posts.tags, roles or profile", other
+                    ))),
+                }
+            }
+            Ok(includes)
+        }
+
+        fn is_empty(&self) -> bool {
+            !(self.posts || self.roles || self.profile)
+        }
+    }
+
+    // Role names are stored uppercase; lookups accept any casing and stray whitespace
+    fn normalize_role_name(name: &str) -> String {
+        name.trim().to_uppercase()
+    }
+
+    pub struct UserService {
+        db: Arc<ResilientConnection>,
+    }
+
+    impl UserService {
+        pub fn new(db: Arc<ResilientConnection>) -> Self {
+            Self { db }
+        }
+
+        // Demonstrates Transaction and Rollback
+        pub async fn create_user_with_default_role(&self, user_data: CreateUserDto) -> Result<user::Model, ApiError> {
+            let txn = self.db.begin().await?;
+
+            // Check if user exists
+            if UserRepository::find_by_email(&txn, &user_data.email).await?.is_some() {
+                return Err(ApiError::BadRequest("Email already exists".to_string()));
+            }
+
+            // Find default role
+            let user_role = RoleRepository::find_by_name(&txn, "USER").await?
+                .ok_or_else(|| ApiError::NotFound("Default role 'USER' not found".to_string()))?;
+
+            // Create user
+            let new_user = user::ActiveModel {
+                id: ActiveValue::Set(Uuid::new_v4()),
+                email: ActiveValue::Set(user_data.email),
+                password_hash: ActiveValue::Set("...hashed_password...".to_string()), // Hashing omitted for brevity
+                is_active: ActiveValue::Set(true),
+                created_at: ActiveValue::Set(chrono::Utc::now()),
+            };
+            let user = new_user.insert(&txn).await?;
+
+            // Assign role
+            UserRoleRepository::assign_role_to_user(&txn, user.id, user_role.id).await?;
+
+            txn.commit().await?;
+            Ok(user)
+        }
+
+        pub async fn get_user(&self, user_id: Uuid, includes: UserIncludes, principal: Principal) -> Result<UserWithIncludesDto, ApiError> {
+            let user = UserRepository::find_by_id(&*self.db, user_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("User with id {} not found", user_id)))?;
+            let mut expanded = self.expand(vec![user], includes, principal).await?;
+            Ok(expanded.pop().expect("expand returns one entry per user"))
+        }
+
+        /// Attaches the requested relations using one batched query per relation,
+        /// however many users there are. Included posts are scoped to `principal`.
+        pub async fn expand(&self, users: Vec<user::Model>, includes: UserIncludes, principal: Principal) -> Result<Vec<UserWithIncludesDto>, ApiError> {
+            if !includes.is_empty() && users.len() > MAX_EXPANDED_USERS {
+                return Err(ApiError::BadRequest(format!(
+                    "include is limited to {} users per request; narrow the filter", MAX_EXPANDED_USERS
+                )));
+            }
+            let user_ids: Vec<Uuid> = users.iter().map(|u| u.id).collect();
+
+            let mut posts: HashMap<Uuid, Vec<PostWithIncludesDto>> = HashMap::new();
+            if includes.posts {
+                let rows = ScopedRepository::<post::Entity>::new(principal)
+                    .by_authors(&user_ids)
+                    .limit(MAX_INCLUDED_POSTS + 1)
+                    .all(&*self.db)
+                    .await?;
+                if rows.len() as u64 > MAX_INCLUDED_POSTS {
+                    return Err(ApiError::BadRequest(format!(
+                        "include=posts would return more than {} posts; narrow the filter", MAX_INCLUDED_POSTS
+                    )));
+                }
+                let mut tags: HashMap<Uuid, Vec<tag::Model>> = HashMap::new();
+                if includes.post_tags {
+                    let post_ids: Vec<Uuid> = rows.iter().map(|p| p.id).collect();
+                    for (post_id, tag) in TagRepository::find_for_posts(&*self.db, &post_ids).await? {
+                        tags.entry(post_id).or_default().push(tag);
+                    }
+                }
+                for post in rows {
+                    let post_tags = includes.post_tags.then(|| tags.remove(&post.id).unwrap_or_default());
+                    posts.entry(post.user_id).or_default().push(PostWithIncludesDto { post, tags: post_tags });
+                }
+            }
+
+            let mut roles: HashMap<Uuid, Vec<role::Model>> = HashMap::new();
+            if includes.roles {
+                for (user_id, role) in UserRoleRepository::find_roles_for_users(&*self.db, &user_ids).await? {
+                    roles.entry(user_id).or_default().push(role);
+                }
+            }
+
+            let mut profiles: HashMap<Uuid, profile::Model> = HashMap::new();
+            if includes.profile {
+                for profile in ProfileRepository::find_by_user_ids(&*self.db, &user_ids).await? {
+                    profiles.insert(profile.user_id, profile);
+                }
+            }
+
+            Ok(users
+                .into_iter()
+                .map(|user| UserWithIncludesDto {
+                    posts: includes.posts.then(|| posts.remove(&user.id).unwrap_or_default()),
+                    roles: includes.roles.then(|| roles.remove(&user.id).unwrap_or_default()),
+                    profile: includes.profile.then(|| profiles.remove(&user.id)),
+                    user: user.into(),
+                })
+                .collect())
+        }
+
+        // Other callers only see the user's published posts
+        pub async fn find_user_posts(&self, user_id: Uuid, principal: Principal, filters: &Filters<post::Entity>) -> Result<Vec<post::Model>, ApiError> {
+            UserRepository::find_by_id(&*self.db, user_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("User with id {} not found", user_id)))?;
+
+            let posts = ScopedRepository::<post::Entity>::new(principal)
+                .by_author(user_id, filters)
+                .all(&*self.db)
+                .await?;
+            Ok(posts)
+        }
+
+        // Idempotent single-role assignment; returns the user's resulting roles
+        pub async fn assign_role(&self, user_id: Uuid, role_name: &str) -> Result<Vec<role::Model>, ApiError> {
+            let role_name = &normalize_role_name(role_name);
+            let txn = self.db.begin().await?;
+
+            UserRepository::find_by_id(&txn, user_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("User with id {} not found", user_id)))?;
+            let role = RoleRepository::find_by_name(&txn, role_name).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Role {} not found", role_name)))?;
+
+            let current = UserRoleRepository::find_roles_for_user(&txn, user_id).await?;
+            if current.iter().any(|r| r.id == role.id) {
+                return Ok(current);
+            }
+            UserRoleRepository::assign_role_to_user(&txn, user_id, role.id).await?;
+            let updated = UserRoleRepository::find_roles_for_user(&txn, user_id).await?;
+
+            txn.commit().await?;
+            Ok(updated)
+        }
+
+        // Replaces the user's roles with exactly `role_names`, all-or-nothing
+        pub async fn replace_user_roles(&self, user_id: Uuid, role_names: Vec<String>) -> Result<Vec<role::Model>, ApiError> {
+            let role_names: Vec<String> = role_names.iter().map(|name| normalize_role_name(name)).collect();
+            let txn = self.db.begin().await?;
+
+            UserRepository::find_by_id(&txn, user_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("User with id {} not found", user_id)))?;
+
+            let roles = RoleRepository::find_by_names(&txn, &role_names).await?;
+            let unknown: Vec<&str> = role_names.iter()
+                .filter(|name| !roles.iter().any(|r| &r.name == *name))
+                .map(String::as_str)
+                .collect();
+            if !unknown.is_empty() {
+                return Err(ApiError::BadRequest(format!("Unknown roles: {}", unknown.join(", "))));
+            }
+
+            let role_ids: Vec<Uuid> = roles.iter().map(|r| r.id).collect();
+            UserRoleRepository::replace_roles(&txn, user_id, &role_ids).await?;
+            let updated = UserRoleRepository::find_roles_for_user(&txn, user_id).await?;
+
+            txn.commit().await?;
+            Ok(updated)
+        }
+
+        pub async fn remove_user_role(&self, user_id: Uuid, role_name: &str) -> Result<(), ApiError> {
+            let role_name = &normalize_role_name(role_name);
+            let txn = self.db.begin().await?;
+
+            let role = RoleRepository::find_by_name(&txn, role_name).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Role {} not found", role_name)))?;
+
+            if UserRoleRepository::remove_role(&txn, user_id, role.id).await? == 0 {
+                return Err(ApiError::NotFound(format!("User {} does not have role {}", user_id, role_name)));
+            }
+
+            txn.commit().await?;
+            Ok(())
+        }
+    }
+
+    pub struct ProfileService {
+        db: Arc<ResilientConnection>,
+    }
+
+    impl ProfileService {
+        pub fn new(db: Arc<ResilientConnection>) -> Self {
+            Self { db }
+        }
+
+        fn empty_profile(user_id: Uuid) -> profile::Model {
+            profile::Model {
+                user_id,
+                display_name: None,
+                bio: None,
+                avatar_url: None,
+                locale: None,
+                timezone: None,
+                updated_at: chrono::Utc::now(),
+            }
+        }
+
+        fn validate(patch: &ProfileMergePatchDto) -> Result<(), ApiError> {
+            if let Some(Some(name)) = &patch.display_name {
+                if name.trim().is_empty() || name.len() > 100 {
+                    return Err(ApiError::BadRequest("display_name must be between 1 and 100 characters".to_string()));
+                }
+            }
+            if let Some(Some(bio)) = &patch.bio {
+                if bio.len() > 2000 {
+                    return Err(ApiError::BadRequest("bio must be at most 2000 characters".to_string()));
+                }
+            }
+            if let Some(Some(url)) = &patch.avatar_url {
+                if !(url.starts_with("https://") || url.starts_with("http://")) {
+                    return Err(ApiError::BadRequest("avatar_url must be an http(s) URL".to_string()));
+                }
+            }
+            if let Some(Some(locale)) = &patch.locale {
+                if locale.is_empty() || locale.len() > 35 {
+                    return Err(ApiError::BadRequest("locale must be a BCP 47 language tag".to_string()));
+                }
+            }
+            if let Some(Some(tz)) = &patch.timezone {
+                if tz.is_empty() || tz.len() > 64 {
+                    return Err(ApiError::BadRequest("timezone must be an IANA time zone name".to_string()));
+                }
+            }
+            Ok(())
+        }
+
+        // Users without a stored profile get an empty one rather than a 404
+        pub async fn get_profile(&self, user_id: Uuid) -> Result<profile::Model, ApiError> {
+            UserRepository::find_by_id(&*self.db, user_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("User with id {} not found", user_id)))?;
+
+            Ok(ProfileRepository::find_by_user_id(&*self.db, user_id).await?
+                .unwrap_or_else(|| Self::empty_profile(user_id)))
+        }
+
+        pub async fn patch_profile(&self, user_id: Uuid, patch: ProfileMergePatchDto) -> Result<profile::Model, ApiError> {
+            Self::validate(&patch)?;
+            let txn = self.db.begin().await?;
+
+            UserRepository::find_by_id(&txn, user_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("User with id {} not found", user_id)))?;
+
+            let existing = ProfileRepository::find_by_user_id(&txn, user_id).await?;
+            let is_new = existing.is_none();
+            let mut active: profile::ActiveModel = existing.unwrap_or_else(|| Self::empty_profile(user_id)).into();
+
+            if let Some(v) = patch.display_name { active.display_name = ActiveValue::Set(v); }
+            if let Some(v) = patch.bio { active.bio = ActiveValue::Set(v); }
+            if let Some(v) = patch.avatar_url { active.avatar_url = ActiveValue::Set(v); }
+            if let Some(v) = patch.locale { active.locale = ActiveValue::Set(v); }
+            if let Some(v) = patch.timezone { active.timezone = ActiveValue::Set(v); }
+            active.updated_at = ActiveValue::Set(chrono::Utc::now());
+
+            let profile = if is_new {
+                ProfileRepository::insert(&txn, active).await?
+            } else {
+                ProfileRepository::update(&txn, active).await?
+            };
+
+            txn.commit().await?;
+            Ok(profile)
+        }
+    }
+
+    /// In-memory copy of the flag tables; evaluation never touches the database.
+    struct FlagSnapshot {
+        flags: HashMap<String, feature_flag::Model>,
+        overrides: HashMap<(String, String), bool>,
+    }
+
+    // FNV-1a: stable across processes and Rust versions, unlike DefaultHasher,
+    // so a subject stays in the same rollout bucket after a restart
+    fn rollout_bucket(flag_name: &str, subject: &str) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in flag_name.bytes().chain(std::iter::once(b':')).chain(subject.bytes()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash % 100
+    }
+
+    pub struct FeatureFlags {
+        db: Arc<ResilientConnection>,
+        snapshot: std::sync::RwLock<Arc<FlagSnapshot>>,
+    }
+
+    impl FeatureFlags {
+        pub fn new(db: Arc<ResilientConnection>) -> Self {
+            let empty = FlagSnapshot { flags: HashMap::new(), overrides: HashMap::new() };
+            Self { db, snapshot: std::sync::RwLock::new(Arc::new(empty)) }
+        }
+
+        pub async fn refresh(&self) -> Result<(), ApiError> {
+            let flags = FeatureFlagRepository::find_all(&*self.db).await?;
+            let overrides = FeatureFlagRepository::find_all_overrides(&*self.db).await?;
+            let snapshot = FlagSnapshot {
+                flags: flags.into_iter().map(|f| (f.name.clone(), f)).collect(),
+                overrides: overrides.into_iter().map(|o| ((o.flag_name, o.tenant_id), o.enabled)).collect(),
+            };
+            *self.snapshot.write().unwrap() = Arc::new(snapshot);
+            Ok(())
+        }
+
+        /// Reloads the cache periodically so changes made by other instances are picked up.
+        pub fn spawn_refresh_loop(self: Arc<Self>, every: std::time::Duration) {
+            actix_web::rt::spawn(async move {
+                let mut ticker = actix_web::rt::time::interval(every);
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = self.refresh().await {
+                        eprintln!("Feature flag refresh failed: {}", e);
+                    }
+                }
+            });
+        }
+
+        /// A tenant override wins outright; otherwise the flag must be enabled and the
+        /// subject (user, or the tenant itself) must fall inside the rollout percentage.
+        /// Unknown flags evaluate to false.
+        pub fn is_enabled(&self, flag_name: &str, tenant_id: &str, user_id: Option<Uuid>) -> bool {
+            let snapshot = self.snapshot.read().unwrap().clone();
+            if let Some(enabled) = snapshot.overrides.get(&(flag_name.to_string(), tenant_id.to_string())) {
+                return *enabled;
+            }
+            let Some(flag) = snapshot.flags.get(flag_name) else {
+                return false;
+            };
+            if !flag.enabled {
+                return false;
+            }
+            let subject = user_id.map(|id| id.to_string()).unwrap_or_else(|| tenant_id.to_string());
+            rollout_bucket(flag_name, &subject) < flag.rollout_percentage.clamp(0, 100) as u64
+        }
+
+        pub fn evaluate_all(&self, tenant_id: &str, user_id: Option<Uuid>) -> BTreeMap<String, bool> {
+            let names: Vec<String> = self.snapshot.read().unwrap().flags.keys().cloned().collect();
+            names.into_iter()
+                .map(|name| {
+                    let enabled = self.is_enabled(&name, tenant_id, user_id);
+                    (name, enabled)
+                })
+                .collect()
+        }
+
+        pub async fn list(&self) -> Result<Vec<feature_flag::Model>, ApiError> {
+            Ok(FeatureFlagRepository::find_all(&*self.db).await?)
+        }
+
+        pub async fn upsert_flag(&self, name: &str, data: UpsertFeatureFlagDto) -> Result<(), ApiError> {
+            if !(0..=100).contains(&data.rollout_percentage) {
+                return Err(ApiError::BadRequest("rollout_percentage must be between 0 and 100".to_string()));
+            }
+            FeatureFlagRepository::upsert(&*self.db, feature_flag::ActiveModel {
+                name: ActiveValue::Set(name.to_string()),
+                enabled: ActiveValue::Set(data.enabled),
+                rollout_percentage: ActiveValue::Set(data.rollout_percentage),
+                description: ActiveValue::Set(data.description),
+                updated_at: ActiveValue::Set(chrono::Utc::now()),
+            }).await?;
+            // Apply locally right away instead of waiting for the next refresh tick
+            self.refresh().await
+        }
+
+        pub async fn set_tenant_override(&self, name: &str, tenant_id: &str, enabled: bool) -> Result<(), ApiError> {
+            if !self.snapshot.read().unwrap().flags.contains_key(name) {
+                return Err(ApiError::NotFound(format!("Feature flag {} not found", name)));
+            }
+            FeatureFlagRepository::upsert_override(&*self.db, feature_flag_override::ActiveModel {
+                flag_name: ActiveValue::Set(name.to_string()),
+                tenant_id: ActiveValue::Set(tenant_id.to_string()),
+                enabled: ActiveValue::Set(enabled),
+            }).await?;
+            self.refresh().await
+        }
+
+        pub async fn clear_tenant_override(&self, name: &str, tenant_id: &str) -> Result<(), ApiError> {
+            if FeatureFlagRepository::delete_override(&*self.db, name, tenant_id).await? == 0 {
+                return Err(ApiError::NotFound(format!("No override of {} for tenant {}", name, tenant_id)));
+            }
+            self.refresh().await
+        }
+    }
+
+    pub struct TenantSettingsService {
+        db: Arc<ResilientConnection>,
+    }
+
+    impl TenantSettingsService {
+        pub fn new(db: Arc<ResilientConnection>) -> Self {
+            Self { db }
+        }
+
+        pub async fn get_settings(&self, tenant_id: &str) -> Result<BTreeMap<String, serde_json::Value>, ApiError> {
+            let settings = TenantSettingRepository::find_for_tenant(&*self.db, tenant_id).await?;
+            Ok(settings.into_iter().map(|s| (s.key, s.value)).collect())
+        }
+
+        pub async fn put_setting(&self, tenant_id: &str, key: &str, value: serde_json::Value) -> Result<(), ApiError> {
+            if key.is_empty() || key.len() > 100 {
+                return Err(ApiError::BadRequest("Setting key must be between 1 and 100 characters".to_string()));
+            }
+            TenantSettingRepository::upsert(&*self.db, tenant_setting::ActiveModel {
+                tenant_id: ActiveValue::Set(tenant_id.to_string()),
+                key: ActiveValue::Set(key.to_string()),
+                value: ActiveValue::Set(value),
+                updated_at: ActiveValue::Set(chrono::Utc::now()),
+            }).await?;
+            Ok(())
+        }
+    }
+
+    const DEFAULT_COMMENTS_PER_PAGE: u64 = 20;
+    const MAX_COMMENTS_PER_PAGE: u64 = 100;
+    const DEFAULT_COMMENT_DEPTH: usize = 3;
+    const MAX_COMMENT_DEPTH: usize = 10;
+
+    pub struct CommentService {
+        db: Arc<ResilientConnection>,
+    }
+
+    impl CommentService {
+        pub fn new(db: Arc<ResilientConnection>) -> Self {
+            Self { db }
+        }
+
+        pub async fn create_comment(&self, post_id: Uuid, principal: Principal, data: CreateCommentDto) -> Result<comment::Model, ApiError> {
+            let author_id = principal.user_id
+                .ok_or_else(|| ApiError::Unauthorized("Sign in to comment".to_string()))?;
+            let body = data.body.trim().to_string();
+            if body.is_empty() || body.len() > 5000 {
+                return Err(ApiError::BadRequest("Comment body must be between 1 and 5000 characters".to_string()));
+            }
+
+            ScopedRepository::<post::Entity>::new(principal).find_by_id(&*self.db, post_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Post with id {} not found", post_id)))?;
+            if let Some(parent_id) = data.parent_comment_id {
+                let parent = CommentRepository::find_by_id(&*self.db, parent_id).await?
+                    .ok_or_else(|| ApiError::NotFound(format!("Comment with id {} not found", parent_id)))?;
+                if parent.post_id != post_id {
+                    return Err(ApiError::BadRequest("Parent comment belongs to a different post".to_string()));
+                }
+            }
+
+            let comment = CommentRepository::insert(&*self.db, comment::ActiveModel {
+                id: ActiveValue::Set(Uuid::new_v4()),
+                post_id: ActiveValue::Set(post_id),
+                author_id: ActiveValue::Set(author_id),
+                parent_comment_id: ActiveValue::Set(data.parent_comment_id),
+                body: ActiveValue::Set(body),
+                created_at: ActiveValue::Set(chrono::Utc::now()),
+            }).await?;
+            Ok(comment)
+        }
+
+        // Paginates top-level comments, then loads replies one level per query up to `max_depth`
+        pub async fn get_thread_page(
+            &self,
+            post_id: Uuid,
+            principal: Principal,
+            page: Option<u64>,
+            per_page: Option<u64>,
+            max_depth: Option<usize>,
+        ) -> Result<CommentPageDto, ApiError> {
+            let page = page.unwrap_or(1).max(1);
+            let per_page = per_page.unwrap_or(DEFAULT_COMMENTS_PER_PAGE).clamp(1, MAX_COMMENTS_PER_PAGE);
+            let max_depth = max_depth.unwrap_or(DEFAULT_COMMENT_DEPTH).min(MAX_COMMENT_DEPTH);
+
+            // Comments on someone else's draft are as private as the draft itself
+            ScopedRepository::<post::Entity>::new(principal).find_by_id(&*self.db, post_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Post with id {} not found", post_id)))?;
+
+            let total_top_level = CommentRepository::count_top_level(&*self.db, post_id).await?;
+            let roots = CommentRepository::find_top_level_page(&*self.db, post_id, page - 1, per_page).await?;
+
+            let mut children_by_parent: HashMap<Uuid, Vec<comment::Model>> = HashMap::new();
+            let mut level: Vec<Uuid> = roots.iter().map(|c| c.id).collect();
+            // One extra level is fetched only to tell whether the deepest nodes have replies
+            for _ in 0..=max_depth {
+                if level.is_empty() {
+                    break;
+                }
+                let children = CommentRepository::find_children(&*self.db, &level).await?;
+                level = children.iter().map(|c| c.id).collect();
+                for child in children {
+                    if let Some(parent_id) = child.parent_comment_id {
+                        children_by_parent.entry(parent_id).or_default().push(child);
+                    }
+                }
+            }
+
+            let comments = roots.into_iter()
+                .map(|root| Self::build_node(root, &mut children_by_parent, 0, max_depth))
+                .collect();
+            Ok(CommentPageDto { page, per_page, total_top_level, comments })
+        }
+
+        fn build_node(
+            comment: comment::Model,
+            children_by_parent: &mut HashMap<Uuid, Vec<comment::Model>>,
+            depth: usize,
+            max_depth: usize,
+        ) -> CommentNodeDto {
+            let (replies, has_more_replies) = if depth < max_depth {
+                let children = children_by_parent.remove(&comment.id).unwrap_or_default();
+                let replies = children.into_iter()
+                    .map(|child| Self::build_node(child, children_by_parent, depth + 1, max_depth))
+                    .collect();
+                (replies, false)
+            } else {
+                (Vec::new(), children_by_parent.contains_key(&comment.id))
+            };
+
+            CommentNodeDto {
+                id: comment.id,
+                author_id: comment.author_id,
+                body: comment.body,
+                created_at: comment.created_at,
+                replies,
+                has_more_replies,
+            }
+        }
+
+        // Deleting a comment removes its whole reply subtree
+        pub async fn delete_comment(&self, comment_id: Uuid, principal: Principal) -> Result<(), ApiError> {
+            let txn = self.db.begin().await?;
+
+            let comments = ScopedRepository::<comment::Entity>::new(principal);
+            let Some(comment) = comments.find_owned_by_id(&txn, comment_id).await? else {
+                // Comments are public, so distinguishing "not yours" from "missing" leaks nothing
+                return Err(match comments.find_by_id(&txn, comment_id).await? {
+                    Some(_) => ApiError::Forbidden("Only the author or an administrator can delete this comment".to_string()),
+                    None => ApiError::NotFound(format!("Comment with id {} not found", comment_id)),
+                });
+            };
+
+            let mut to_delete = vec![comment.id];
+            let mut level = vec![comment.id];
+            while !level.is_empty() {
+                level = CommentRepository::find_children(&txn, &level).await?
+                    .into_iter()
+                    .map(|c| c.id)
+                    .collect();
+                to_delete.extend(&level);
+            }
+            CommentRepository::delete_many(&txn, &to_delete).await?;
+
+            txn.commit().await?;
+            Ok(())
+        }
+    }
+
+    const MAX_TAGS_PER_POST: usize = 20;
+    const DEFAULT_TAG_SEARCH_LIMIT: u64 = 10;
+    const MAX_TAG_SEARCH_LIMIT: u64 = 50;
+
+    pub struct TagService {
+        db: Arc<ResilientConnection>,
+    }
+
+    impl TagService {
+        pub fn new(db: Arc<ResilientConnection>) -> Self {
+            Self { db }
+        }
+
+        // Tags are stored lowercase and deduplicated so "Rust" and "rust " are the same tag
+        fn normalize_names(names: Vec<String>) -> Result<Vec<String>, ApiError> {
+            let mut normalized: Vec<String> = Vec::with_capacity(names.len());
+            for name in names {
+                let name = name.trim().to_lowercase();
+                if name.is_empty() || name.len() > 50 {
+                    return Err(ApiError::BadRequest("Tag names must be between 1 and 50 characters".to_string()));
+                }
+                if !normalized.contains(&name) {
+                    normalized.push(name);
+                }
+            }
+            if normalized.len() > MAX_TAGS_PER_POST {
+                return Err(ApiError::BadRequest(format!("A post can have at most {} tags", MAX_TAGS_PER_POST)));
+            }
+            Ok(normalized)
+        }
+
+        pub async fn get_post_tags(&self, post_id: Uuid, principal: Principal) -> Result<Vec<tag::Model>, ApiError> {
+            ScopedRepository::<post::Entity>::new(principal).find_by_id(&*self.db, post_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Post with id {} not found", post_id)))?;
+            Ok(TagRepository::find_for_post(&*self.db, post_id).await?)
+        }
+
+        // Replaces the post's tags with exactly `names`, creating unknown tags on the fly
+        pub async fn set_post_tags(&self, post_id: Uuid, principal: Principal, names: Vec<String>) -> Result<Vec<tag::Model>, ApiError> {
+            let names = Self::normalize_names(names)?;
+            let txn = self.db.begin().await?;
+
+            ScopedRepository::<post::Entity>::new(principal).find_owned_by_id(&txn, post_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Post with id {} not found", post_id)))?;
+
+            let tags = TagRepository::find_or_create(&txn, &names).await?;
+            let tag_ids: Vec<Uuid> = tags.iter().map(|t| t.id).collect();
+            PostTagRepository::replace_tags(&txn, post_id, &tag_ids).await?;
+            let updated = TagRepository::find_for_post(&txn, post_id).await?;
+
+            txn.commit().await?;
+            Ok(updated)
+        }
+
+        pub async fn search_tags(&self, prefix: Option<String>, limit: Option<u64>) -> Result<Vec<TagWithCountDto>, ApiError> {
+            let prefix = prefix.unwrap_or_default().trim().to_lowercase();
+            let limit = limit.unwrap_or(DEFAULT_TAG_SEARCH_LIMIT).clamp(1, MAX_TAG_SEARCH_LIMIT);
+            Ok(TagRepository::search_with_counts(&*self.db, &prefix, limit).await?)
+        }
+
+        pub async fn posts_for_tag(&self, name: &str, principal: Principal, filters: &Filters<post::Entity>) -> Result<TaggedPostsDto, ApiError> {
+            let name = name.trim().to_lowercase();
+            let tag = TagRepository::find_by_name(&*self.db, &name).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Tag {} not found", name)))?;
+
+            let posts = ScopedRepository::<post::Entity>::new(principal)
+                .by_tag(tag.id, filters)
+                .all(&*self.db)
+                .await?;
+            Ok(TaggedPostsDto { tag: tag.name, post_count: posts.len(), posts })
+        }
+
+        // Explicit cascade: SQLite only enforces the FK if foreign_keys is enabled
+        pub async fn delete_tag(&self, tag_id: Uuid) -> Result<u64, ApiError> {
+            let txn = self.db.begin().await?;
+
+            TagRepository::find_by_id(&txn, tag_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Tag with id {} not found", tag_id)))?;
+            let untagged = PostTagRepository::remove_all_for_tag(&txn, tag_id).await?;
+            TagRepository::delete(&txn, tag_id).await?;
+
+            txn.commit().await?;
+            Ok(untagged)
+        }
+    }
+
+    // Roles the application itself depends on (see create_user_with_default_role)
+    const RESERVED_ROLES: [&str; 2] = ["ADMIN", "USER"];
+
+    pub struct RoleService {
+        db: Arc<ResilientConnection>,
+    }
+
+    impl RoleService {
+        pub fn new(db: Arc<ResilientConnection>) -> Self {
+            Self { db }
+        }
+
+        fn validate_name(name: &str) -> Result<String, ApiError> {
+            let name = normalize_role_name(name);
+            if name.is_empty() || name.len() > 50 {
+                return Err(ApiError::BadRequest("Role name must be between 1 and 50 characters".to_string()));
+            }
+            Ok(name)
+        }
+
+        pub async fn list_roles(&self) -> Result<Vec<role::Model>, ApiError> {
+            Ok(RoleRepository::find_all(&*self.db).await?)
+        }
+
+        pub async fn get_role(&self, role_id: Uuid) -> Result<role::Model, ApiError> {
+            RoleRepository::find_by_id(&*self.db, role_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Role with id {} not found", role_id)))
+        }
+
+        pub async fn create_role(&self, data: CreateRoleDto) -> Result<role::Model, ApiError> {
+            let name = Self::validate_name(&data.name)?;
+            let txn = self.db.begin().await?;
+
+            if RoleRepository::find_by_name(&txn, &name).await?.is_some() {
+                return Err(ApiError::Conflict(format!("Role {} already exists", name)));
+            }
+
+            let role = RoleRepository::save(&txn, role::ActiveModel {
+                id: ActiveValue::Set(Uuid::new_v4()),
+                name: ActiveValue::Set(name),
+            }).await?;
+
+            txn.commit().await?;
+            Ok(role)
+        }
+
+        pub async fn update_role(&self, role_id: Uuid, data: UpdateRoleDto) -> Result<role::Model, ApiError> {
+            let txn = self.db.begin().await?;
+
+            let existing = RoleRepository::find_by_id(&txn, role_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Role with id {} not found", role_id)))?;
+
+            let Some(new_name) = data.name else {
+                return Ok(existing);
+            };
+            let new_name = Self::validate_name(&new_name)?;
+            if new_name == existing.name {
+                return Ok(existing);
+            }
+            if RESERVED_ROLES.contains(&existing.name.as_str()) {
+                return Err(ApiError::BadRequest(format!("Role {} is reserved and cannot be renamed", existing.name)));
+            }
+            if RoleRepository::find_by_name(&txn, &new_name).await?.is_some() {
+                return Err(ApiError::Conflict(format!("Role {} already exists", new_name)));
+            }
+
+            let mut active: role::ActiveModel = existing.into();
+            active.name = ActiveValue::Set(new_name);
+            let role = RoleRepository::update(&txn, active).await?;
+
+            txn.commit().await?;
+            Ok(role)
+        }
+
+        pub async fn delete_role(&self, role_id: Uuid, cascade: bool) -> Result<(), ApiError> {
+            let txn = self.db.begin().await?;
+
+            let role = RoleRepository::find_by_id(&txn, role_id).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Role with id {} not found", role_id)))?;
+            if RESERVED_ROLES.contains(&role.name.as_str()) {
+                return Err(ApiError::BadRequest(format!("Role {} is reserved and cannot be deleted", role.name)));
+            }
+
+            let assigned = UserRoleRepository::count_users_with_role(&txn, role_id).await?;
+            if assigned > 0 {
+                if !cascade {
+                    return Err(ApiError::Conflict(format!(
+                        "Role {} is assigned to {} user(s); pass cascade=true to remove it anyway",
+                        role.name, assigned
+                    )));
+                }
+                UserRoleRepository::remove_all_for_role(&txn, role_id).await?;
+            }
+            RoleRepository::delete(&txn, role_id).await?;
+
+            txn.commit().await?;
+            Ok(())
+        }
+    }
+
+    /// Upper bound on the decompressed size of an imported backup.
+    pub const MAX_BACKUP_JSON_BYTES: u64 = 256 * 1024 * 1024;
+
+    pub struct BackupService {
+        db: Arc<ResilientConnection>,
+    }
+
+    impl BackupService {
+        pub fn new(db: Arc<ResilientConnection>) -> Self {
+            Self { db }
+        }
+
+        /// Gzipped JSON of users, roles, user roles, posts, profiles, tags, post tags
+        /// and comments. Read inside one transaction so the tables are consistent with
+        /// each other. It includes password hashes, which is why the route needs a
+        /// verified admin token.
+        pub async fn export(&self) -> Result<Vec<u8>, ApiError> {
+            let txn = self.db.begin().await?;
+            let backup = BackupRepository::snapshot(&txn).await?;
+            txn.commit().await?;
+
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            serde_json::to_writer(&mut encoder, &backup).expect("serializing into memory cannot fail");
+            Ok(encoder.finish().expect("compressing into memory cannot fail"))
+        }
+
+        pub fn decode(archive: &[u8]) -> Result<BackupDto, ApiError> {
+            let mut json = Vec::new();
+            flate2::read::GzDecoder::new(archive)
+                .take(MAX_BACKUP_JSON_BYTES + 1)
+                .read_to_end(&mut json)
+                .map_err(|e| ApiError::BadRequest(format!("Backup is not valid gzip: {}", e)))?;
+            if json.len() as u64 > MAX_BACKUP_JSON_BYTES {
+                return Err(ApiError::BadRequest("Backup is too large".to_string()));
+            }
+            let backup: BackupDto = serde_json::from_slice(&json)
+                .map_err(|e| ApiError::BadRequest(format!("Backup is not valid: {}", e)))?;
+            if backup.schema_version > BACKUP_SCHEMA_VERSION {
+                return Err(ApiError::BadRequest(format!(
+                    "Backup schema version {} is newer than supported version {}",
+                    backup.schema_version, BACKUP_SCHEMA_VERSION
+                )));
+            }
+            Ok(backup)
+        }
+
+        /// Restores a backup in a single transaction; nothing is written unless every
+        /// reference resolves. In merge mode references may also point at existing rows.
+        pub async fn import(&self, archive: &[u8], mode: ImportMode) -> Result<ImportSummaryDto, ApiError> {
+            let backup = Self::decode(archive)?;
+            Self::check_unique(&backup)?;
+            if mode == ImportMode::Replace {
+                for reserved in RESERVED_ROLES {
+                    if !backup.roles.iter().any(|r| r.name == reserved) {
+                        return Err(ApiError::BadRequest(format!("A replace import must include the {} role", reserved)));
+                    }
+                }
+            }
+
+            let txn = self.db.begin().await?;
+            match mode {
+                ImportMode::Replace => BackupRepository::clear(&txn).await?,
+                ImportMode::Merge => Self::check_against_existing(&txn, &backup).await?,
+            }
+            Self::check_references(&txn, &backup, mode).await?;
+            BackupRepository::upsert(&txn, &backup).await?;
+            txn.commit().await?;
+
+            Ok(ImportSummaryDto {
+                mode,
+                schema_version: backup.schema_version,
+                users: backup.users.len(),
+                roles: backup.roles.len(),
+                user_roles: backup.user_roles.len(),
+                posts: backup.posts.len(),
+                profiles: backup.profiles.len(),
+                tags: backup.tags.len(),
+                post_tags: backup.post_tags.len(),
+                comments: backup.comments.len(),
+            })
+        }
+
+        fn check_unique(backup: &BackupDto) -> Result<(), ApiError> {
+            fn first_duplicate<T: std::hash::Hash + Eq + Clone>(items: impl IntoIterator<Item = T>) -> Option<T> {
+                let mut seen = HashSet::new();
+                items.into_iter().find(|item| !seen.insert(item.clone()))
+            }
+            let duplicate = |what: &str, value: String| Err(ApiError::BadRequest(format!("Backup contains duplicate {} {}", what, value)));
+
+            if let Some(id) = first_duplicate(backup.users.iter().map(|u| u.id)) { return duplicate("user id", id.to_string()); }
+            if let Some(email) = first_duplicate(backup.users.iter().map(|u| u.email.as_str())) { return duplicate("email", email.to_string()); }
+            if let Some(id) = first_duplicate(backup.roles.iter().map(|r| r.id)) { return duplicate("role id", id.to_string()); }
+            if let Some(name) = first_duplicate(backup.roles.iter().map(|r| r.name.as_str())) { return duplicate("role name", name.to_string()); }
+            if let Some(id) = first_duplicate(backup.posts.iter().map(|p| p.id)) { return duplicate("post id", id.to_string()); }
+            if let Some(id) = first_duplicate(backup.profiles.iter().map(|p| p.user_id)) { return duplicate("profile for user", id.to_string()); }
+            if let Some(id) = first_duplicate(backup.tags.iter().map(|t| t.id)) { return duplicate("tag id", id.to_string()); }
+            if let Some(name) = first_duplicate(backup.tags.iter().map(|t| t.name.as_str())) { return duplicate("tag name", name.to_string()); }
+            if let Some(id) = first_duplicate(backup.comments.iter().map(|c| c.id)) { return duplicate("comment id", id.to_string()); }
+            Ok(())
+        }
+
+        // A merge must not give an existing email or role name to a different id
+        async fn check_against_existing(txn: &TimedTransaction, backup: &BackupDto) -> Result<(), ApiError> {
+            let emails = backup.users.iter().map(|u| u.email.clone()).collect();
+            for existing in BackupRepository::users_by_emails(txn, emails).await? {
+                if backup.users.iter().any(|u| u.email == existing.email && u.id != existing.id) {
+                    return Err(ApiError::Conflict(format!("Email {} already belongs to another user", existing.email)));
+                }
+            }
+            let names = backup.roles.iter().map(|r| r.name.clone()).collect();
+            for existing in BackupRepository::roles_by_names(txn, names).await? {
+                if backup.roles.iter().any(|r| r.name == existing.name && r.id != existing.id) {
+                    return Err(ApiError::Conflict(format!("Role {} already exists with a different id", existing.name)));
+                }
+            }
+            let tag_names = backup.tags.iter().map(|t| t.name.clone()).collect();
+            for existing in BackupRepository::tags_by_names(txn, tag_names).await? {
+                if backup.tags.iter().any(|t| t.name == existing.name && t.id != existing.id) {
+                    return Err(ApiError::Conflict(format!("Tag {} already exists with a different id", existing.name)));
+                }
+            }
+            Ok(())
+        }
+
+        async fn check_references(txn: &TimedTransaction, backup: &BackupDto, mode: ImportMode) -> Result<(), ApiError> {
+            let mut user_ids: HashSet<Uuid> = backup.users.iter().map(|u| u.id).collect();
+            let mut role_ids: HashSet<Uuid> = backup.roles.iter().map(|r| r.id).collect();
+            let mut post_ids: HashSet<Uuid> = backup.posts.iter().map(|p| p.id).collect();
+            let mut tag_ids: HashSet<Uuid> = backup.tags.iter().map(|t| t.id).collect();
+            let mut comment_ids: HashSet<Uuid> = backup.comments.iter().map(|c| c.id).collect();
+
+            if mode == ImportMode::Merge {
+                let referenced_users: HashSet<Uuid> = backup.user_roles.iter().map(|ur| ur.user_id)
+                    .chain(backup.posts.iter().map(|p| p.user_id))
+                    .chain(backup.profiles.iter().map(|p| p.user_id))
+                    .chain(backup.comments.iter().map(|c| c.author_id))
+                    .filter(|id| !user_ids.contains(id))
+                    .collect();
+                let referenced_roles: HashSet<Uuid> = backup.user_roles.iter().map(|ur| ur.role_id)
+                    .filter(|id| !role_ids.contains(id))
+                    .collect();
+                let referenced_posts: HashSet<Uuid> = backup.post_tags.iter().map(|pt| pt.post_id)
+                    .chain(backup.comments.iter().map(|c| c.post_id))
+                    .filter(|id| !post_ids.contains(id))
+                    .collect();
+                let referenced_tags: HashSet<Uuid> = backup.post_tags.iter().map(|pt| pt.tag_id)
+                    .filter(|id| !tag_ids.contains(id))
+                    .collect();
+                let referenced_comments: HashSet<Uuid> = backup.comments.iter().filter_map(|c| c.parent_comment_id)
+                    .filter(|id| !comment_ids.contains(id))
+                    .collect();
+                user_ids.extend(BackupRepository::existing_user_ids(txn, &referenced_users).await?);
+                role_ids.extend(BackupRepository::existing_role_ids(txn, &referenced_roles).await?);
+                post_ids.extend(BackupRepository::existing_post_ids(txn, &referenced_posts).await?);
+                tag_ids.extend(BackupRepository::existing_tag_ids(txn, &referenced_tags).await?);
+                comment_ids.extend(BackupRepository::existing_comment_ids(txn, &referenced_comments).await?);
+            }
+
+            let dangling = |what: &str, id: Uuid| Err(ApiError::BadRequest(format!("Backup references unknown {} {}", what, id)));
+            for user_role in &backup.user_roles {
+                if !user_ids.contains(&user_role.user_id) { return dangling("user", user_role.user_id); }
+                if !role_ids.contains(&user_role.role_id) { return dangling("role", user_role.role_id); }
+            }
+            for post in &backup.posts {
+                if !user_ids.contains(&post.user_id) { return dangling("user", post.user_id); }
+            }
+            for profile in &backup.profiles {
+                if !user_ids.contains(&profile.user_id) { return dangling("user", profile.user_id); }
+            }
+            for post_tag in &backup.post_tags {
+                if !post_ids.contains(&post_tag.post_id) { return dangling("post", post_tag.post_id); }
+                if !tag_ids.contains(&post_tag.tag_id) { return dangling("tag", post_tag.tag_id); }
+            }
+            for comment in &backup.comments {
+                if !post_ids.contains(&comment.post_id) { return dangling("post", comment.post_id); }
+                if !user_ids.contains(&comment.author_id) { return dangling("user", comment.author_id); }
+                if let Some(parent) = comment.parent_comment_id {
+                    if !comment_ids.contains(&parent) { return dangling("comment", parent); }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    const DEFAULT_STATS_RANGE_DAYS: i64 = 30;
+    const MAX_STATS_RANGE_DAYS: i64 = 366;
+
+    pub struct StatsService {
+        db: Arc<ResilientConnection>,
+    }
+
+    impl StatsService {
+        pub fn new(db: Arc<ResilientConnection>) -> Self {
+            Self { db }
+        }
+
+        // "30d" or "12w", in days
+        fn parse_range(range: Option<&str>) -> Result<i64, ApiError> {
+            let Some(range) = range.map(str::trim) else { return Ok(DEFAULT_STATS_RANGE_DAYS) };
+            let days = if let Some(n) = range.strip_suffix('d') {
+                n.parse::<i64>().ok()
+            } else if let Some(n) = range.strip_suffix('w') {
+                n.parse::<i64>().ok().and_then(|weeks| weeks.checked_mul(7))
+            } else {
+                None
+            };
+            match days {
+                Some(days) if (1..=MAX_STATS_RANGE_DAYS).contains(&days) => Ok(days),
+                Some(_) => Err(ApiError::BadRequest(format!("range must be between 1 and {} days", MAX_STATS_RANGE_DAYS))),
+                None => Err(ApiError::BadRequest(format!("Invalid range '{}'; expected e.g. 30d or 12w", range))),
+            }
+        }
+
+        /// Sign-ups per UTC day or week. Every bucket in the range is returned, with
+        /// zero counts filled in, so charts don't have to interpolate gaps.
+        pub async fn user_signups(&self, query: UserStatsQuery) -> Result<UserStatsDto, ApiError> {
+            use chrono::Datelike;
+
+            let days = Self::parse_range(query.range.as_deref())?;
+            let to = chrono::Utc::now().date_naive();
+            let mut from = to - chrono::Duration::days(days - 1);
+            if query.group_by == TimeBucket::Week {
+                from -= chrono::Duration::days(from.weekday().num_days_from_monday() as i64);
+            }
+            let since = from.and_hms_opt(0, 0, 0).expect("midnight is a valid time").and_utc();
+
+            let counts: HashMap<String, i64> = StatsRepository::users_created_per_bucket(&*self.db, query.group_by, since)
+                .await?
+                .into_iter()
+                .map(|row| (row.bucket, row.count))
+                .collect();
+
+            let step = chrono::Duration::days(match query.group_by {
+                TimeBucket::Day => 1,
+                TimeBucket::Week => 7,
+            });
+            let mut buckets = Vec::new();
+            let mut day = from;
+            while day <= to {
+                let bucket = day.format("%Y-%m-%d").to_string();
+                let count = counts.get(&bucket).copied().unwrap_or(0);
+                buckets.push(BucketCountDto { bucket, count });
+                day += step;
+            }
+            Ok(UserStatsDto { group_by: query.group_by, from, to, buckets })
+        }
+
+        /// Post counts per status, limited to the posts the principal can read.
+        pub async fn post_counts(&self, group_by: Option<&str>, principal: Principal) -> Result<Vec<StatusCountDto>, ApiError> {
+            match group_by.unwrap_or("status") {
+                "status" => {}
+                other => return Err(ApiError::BadRequest(format!("Unsupported group_by '{}' for posts; expected status", other))),
+            }
+            let select = ScopedRepository::<post::Entity>::new(principal).select();
+            Ok(StatsRepository::post_counts_by_status(&*self.db, select).await?)
+        }
+    }
+
+    const MAX_SAVED_SEARCHES_PER_USER: u64 = 50;
+
+    /// Named filter+sort combinations a user can re-run with `?saved_search=name`.
+    /// Documents are validated on save and compiled again on every run, so a search
+    /// that refers to a field or operator since removed fails loudly instead of
+    /// silently matching more rows.
+    pub struct SavedSearchService {
+        db: Arc<ResilientConnection>,
+    }
+
+    impl SavedSearchService {
+        pub fn new(db: Arc<ResilientConnection>) -> Self {
+            Self { db }
+        }
+
+        fn to_dto(search: saved_search::Model) -> Result<SavedSearchDto, ApiError> {
+            let document: FilterDocument = serde_json::from_value(search.document)
+                .map_err(|e| ApiError::Conflict(format!("Saved search '{}' is unreadable: {}", search.name, e)))?;
+            Ok(SavedSearchDto { name: search.name, resource: search.resource, search: document, created_at: search.created_at })
+        }
+
+        pub async fn list(&self, user_id: Uuid) -> Result<Vec<SavedSearchDto>, ApiError> {
+            SavedSearchRepository::find_for_user(&*self.db, user_id).await?
+                .into_iter()
+                .map(Self::to_dto)
+                .collect()
+        }
+
+        pub async fn create(&self, user_id: Uuid, data: CreateSavedSearchDto) -> Result<SavedSearchDto, ApiError> {
+            let name = data.name.trim().to_lowercase();
+            if name.is_empty() || name.len() > 64 || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                return Err(ApiError::BadRequest("Saved search name must be 1-64 letters, digits, '-' or '_'".to_string()));
+            }
+            if data.search.is_empty() {
+                return Err(ApiError::BadRequest("Saved search needs at least one filter or sort key".to_string()));
+            }
+            if data.resource == post::Entity::RESOURCE {
+                Filters::<post::Entity>::compile(&data.search)?;
+            } else if data.resource == user::Entity::RESOURCE {
+                Filters::<user::Entity>::compile(&data.search)?;
+            } else {
+                return Err(ApiError::BadRequest(format!(
+                    "Unknown resource '{}'; expected {} or {}",
+                    data.resource, post::Entity::RESOURCE, user::Entity::RESOURCE
+                )));
+            }
+
+            if SavedSearchRepository::find_by_name(&*self.db, user_id, &name).await?.is_some() {
+                return Err(ApiError::Conflict(format!("Saved search '{}' already exists", name)));
+            }
+            if SavedSearchRepository::count_for_user(&*self.db, user_id).await? >= MAX_SAVED_SEARCHES_PER_USER {
+                return Err(ApiError::BadRequest(format!("At most {} saved searches are allowed per user", MAX_SAVED_SEARCHES_PER_USER)));
+            }
+
+            let document = serde_json::to_value(&data.search).expect("filter documents serialize");
+            let search = SavedSearchRepository::insert(&*self.db, saved_search::ActiveModel {
+                id: ActiveValue::Set(Uuid::new_v4()),
+                user_id: ActiveValue::Set(user_id),
+                name: ActiveValue::Set(name),
+                resource: ActiveValue::Set(data.resource),
+                document: ActiveValue::Set(document),
+                created_at: ActiveValue::Set(chrono::Utc::now()),
+            }).await?;
+            Self::to_dto(search)
+        }
+
+        pub async fn delete(&self, user_id: Uuid, name: &str) -> Result<(), ApiError> {
+            match SavedSearchRepository::delete(&*self.db, user_id, &name.to_lowercase()).await? {
+                0 => Err(ApiError::NotFound(format!("Saved search '{}' not found", name))),
+                _ => Ok(()),
+            }
+        }
+
+        /// Compiles the named search against `E`'s current allowlist.
+        pub async fn resolve<E: Filterable>(&self, user_id: Uuid, name: &str) -> Result<Filters<E>, ApiError> {
+            let search = SavedSearchRepository::find_by_name(&*self.db, user_id, &name.to_lowercase()).await?
+                .ok_or_else(|| ApiError::NotFound(format!("Saved search '{}' not found", name)))?;
+            if search.resource != E::RESOURCE {
+                return Err(ApiError::BadRequest(format!("Saved search '{}' is for {}, not {}", name, search.resource, E::RESOURCE)));
+            }
+            let dto = Self::to_dto(search)?;
+            Filters::<E>::compile(&dto.search).map_err(|e| match e {
+                ApiError::BadRequest(reason) => ApiError::Conflict(format!("Saved search '{}' is no longer valid: {}", dto.name, reason)),
+                other => other,
+            })
+        }
+    }
+}
+
+// --- 9. Request Guards (guards/admin.rs) ---
+mod guards {
+    use super::repositories::{Principal, UserRepository, UserRoleRepository};
+    use super::ApiError;
+    use actix_web::{dev::Payload, http::header, web, FromRequest, HttpRequest};
+    use futures::future::LocalBoxFuture;
+    use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+    use super::resilience::ResilientConnection;
+    use serde::{Deserialize, Serialize};
+    use std::sync::{Arc, OnceLock};
+    use uuid::Uuid;
+
+    pub const DEFAULT_TENANT: &str = "default";
+
+    /// Tenant the request is made on behalf of, from the `X-Tenant-Id` header.
+    /// Requests without the header belong to the default tenant.
+    pub struct TenantId(pub String);
+
+    impl FromRequest for TenantId {
+        type Error = ApiError;
+        type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+        fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+            let tenant = match req.headers().get("X-Tenant-Id") {
+                None => Ok(TenantId(DEFAULT_TENANT.to_string())),
+                Some(value) => value.to_str().ok()
+                    .filter(|v| !v.is_empty() && v.len() <= 64 && v.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+                    .map(|v| TenantId(v.to_string()))
+                    .ok_or_else(|| ApiError::BadRequest("Invalid X-Tenant-Id header".to_string())),
+            };
+            std::future::ready(tenant)
+        }
+    }
+
+    /// Claims of the HS256 access tokens accepted by the HTTP guards and the gRPC interceptor.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Claims {
+        pub sub: Uuid,
+        pub role: String,
+        pub exp: usize,
+    }
+
+    /// Signing key shared with the token issuer, from `JWT_SECRET`. `main` calls this
+    /// before serving so a missing key stops startup instead of failing requests.
+    pub fn jwt_secret() -> &'static [u8] {
+        static SECRET: OnceLock<Vec<u8>> = OnceLock::new();
+        SECRET.get_or_init(|| {
+            std::env::var("JWT_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .expect("JWT_SECRET must be set")
+                .into_bytes()
+        })
+    }
+
+    pub fn decode_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        decode::<Claims>(token, &DecodingKey::from_secret(jwt_secret()), &Validation::new(Algorithm::HS256))
+            .map(|data| data.claims)
+    }
+
+    /// Extractor for any existing, active user, authenticated by an
+    /// `Authorization: Bearer <jwt>` header.
+    pub struct CurrentUser {
+        pub user_id: Uuid,
+        pub is_admin: bool,
+    }
+
+    impl CurrentUser {
+        pub fn principal(&self) -> Principal {
+            Principal { user_id: Some(self.user_id), is_admin: self.is_admin }
+        }
+    }
+
+    /// Principal for endpoints that also serve anonymous callers.
+    pub fn principal_of(user: &Option<CurrentUser>) -> Principal {
+        user.as_ref().map(CurrentUser::principal).unwrap_or_else(Principal::anonymous)
+    }
+
+    // The token only names the user. Roles are re-read on every request so a revoked
+    // ADMIN role takes effect immediately rather than when the token expires.
+    fn authenticate(req: &HttpRequest) -> LocalBoxFuture<'static, Result<CurrentUser, ApiError>> {
+        let db = req.app_data::<web::Data<Arc<ResilientConnection>>>().cloned();
+        let user_id = req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| ApiError::Unauthorized("Missing bearer token".to_string()))
+            .and_then(|token| {
+                decode_token(token)
+                    .map(|claims| claims.sub)
+                    .map_err(|_| ApiError::Unauthorized("Invalid or expired token".to_string()))
+            });
+
+        Box::pin(async move {
+            let user_id = user_id?;
+            let db = db.ok_or_else(|| ApiError::Internal("Database connection is not registered as app data".to_string()))?;
+            resolve_user(db.get_ref().as_ref(), user_id).await
+        })
+    }
+
+    /// Loads the user a verified token names. The gRPC service calls this too, so a
+    /// deactivated account or revoked role is refused on both transports.
+    pub async fn resolve_user(db: &ResilientConnection, user_id: Uuid) -> Result<CurrentUser, ApiError> {
+        let user = UserRepository::find_by_id(db, user_id).await?
+            .ok_or_else(|| ApiError::Unauthorized("Unknown user".to_string()))?;
+        if !user.is_active {
+            return Err(ApiError::Unauthorized("Account is deactivated".to_string()));
+        }
+        let roles = UserRoleRepository::find_roles_for_user(db, user_id).await?;
+        Ok(CurrentUser { user_id, is_admin: roles.iter().any(|r| r.name == "ADMIN") })
+    }
+
+    impl FromRequest for CurrentUser {
+        type Error = ApiError;
+        type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+        fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+            authenticate(req)
+        }
+    }
+
+    /// Extractor that only succeeds when the authenticated caller holds the ADMIN role.
+    pub struct AdminUser;
+
+    impl FromRequest for AdminUser {
+        type Error = ApiError;
+        type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+        fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+            let current = authenticate(req);
+            Box::pin(async move {
+                let user = current.await?;
+                if user.is_admin {
+                    Ok(AdminUser)
+                } else {
+                    Err(ApiError::Forbidden("Administrator role required".to_string()))
+                }
+            })
+        }
+    }
+}
+
+// --- 10. Handler Layer (handlers/user_handler.rs, handlers/role_handler.rs) ---
+mod handlers {
+    use super::models::{post, user};
+    use super::models::dtos::{CreateUserDto, UserResponse, CreateSavedSearchDto, SavedSearchQuery, IncludeQuery, AssignRoleDto, ReplaceRolesDto, CreateRoleDto, UpdateRoleDto, DeleteRoleQuery, ProfileMergePatchDto, SetPostTagsDto, TagSearchQuery, CreateCommentDto, CommentPageQuery, UpsertFeatureFlagDto, TenantOverrideDto, TenantSettingDto, UserStatsQuery, PostStatsQuery, ImportBackupQuery};
+    use super::guards::{principal_of, AdminUser, CurrentUser, TenantId};
+    use super::services::{UserService, RoleService, ProfileService, TagService, CommentService, FeatureFlags, TenantSettingsService, StatsService, BackupService, SavedSearchService, UserIncludes};
+    use super::ApiError;
+    use super::filters::{Filterable, Filters};
+    use super::repositories::{ScopedRepository, UserRepository};
+    use actix_web::{web, web::Bytes, HttpResponse, Responder};
+    use super::instrumentation::StatementStats;
+    use super::resilience::{BreakerState, ResilientConnection};
+    use futures::TryStreamExt;
+    use std::sync::Arc;
+    use tokio_stream::wrappers::ReceiverStream;
+    use uuid::Uuid;
+
+    const EXPORT_BUFFER_ROWS: usize = 256;
+
+    // Pings the database through the breaker and reports degraded (503) while the
+    // ping fails or the breaker is not closed, so load balancers can route around the
+    // instance. A successful ping after an outage closes the breaker.
+    pub async fn health(db: web::Data<Arc<ResilientConnection>>) -> impl Responder {
+        let ping = db.ping().await;
+        let snapshot = db.snapshot();
+        let healthy = ping.is_ok() && snapshot.state == BreakerState::Closed;
+        let body = serde_json::json!({
+            "status": if healthy { "ok" } else { "degraded" },
+            "database": snapshot,
+            "error": ping.err().map(|e| e.to_string()),
+        });
+        if healthy {
+            HttpResponse::Ok().json(body)
+        } else {
+            HttpResponse::ServiceUnavailable().json(body)
+        }
+    }
+
+    fn escape_label(value: &str) -> String {
+        value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+    }
+
+    /// Prometheus text exposition of the database resilience counters and
+    /// per-statement query timings.
+    pub async fn metrics(db: web::Data<Arc<ResilientConnection>>) -> impl Responder {
+        let snapshot = db.snapshot();
+        let state = match snapshot.state {
+            BreakerState::Closed => 0,
+            BreakerState::HalfOpen => 1,
+            BreakerState::Open => 2,
+        };
+        let mut body = format!(
+            "# TYPE db_breaker_state gauge\n\
+             db_breaker_state {}\n\
+             # TYPE db_calls_total counter\n\
+             db_calls_total {}\n\
+             # TYPE db_transient_failures_total counter\n\
+             db_transient_failures_total {}\n\
+             # TYPE db_retries_total counter\n\
+             db_retries_total {}\n\
+             # TYPE db_rejected_total counter\n\
+             db_rejected_total {}\n\
+             # TYPE db_breaker_trips_total counter\n\
+             db_breaker_trips_total {}\n",
+            state, snapshot.calls, snapshot.failures, snapshot.retries, snapshot.rejected, snapshot.trips,
+        );
+
+        let stats = db.query_stats();
+        body.push_str(&format!(
+            "# TYPE db_slow_query_threshold_ms gauge\ndb_slow_query_threshold_ms {}\n",
+            stats.slow_threshold().as_millis()
+        ));
+        let statements = stats.snapshot();
+        type Family = (&'static str, &'static str, fn(&StatementStats) -> f64);
+        let families: [Family; 5] = [
+            ("db_query_calls_total", "counter", |s| s.calls as f64),
+            ("db_query_errors_total", "counter", |s| s.errors as f64),
+            ("db_slow_queries_total", "counter", |s| s.slow as f64),
+            ("db_query_duration_ms_sum", "counter", |s| s.total_ms),
+            ("db_query_duration_ms_max", "gauge", |s| s.max_ms),
+        ];
+        for (name, kind, value) in families {
+            body.push_str(&format!("# TYPE {} {}\n", name, kind));
+            for (sql, stat) in &statements {
+                body.push_str(&format!("{}{{statement=\"{}\"}} {}\n", name, escape_label(sql), value(stat)));
+            }
+        }
+
+        #[cfg(feature = "fault-injection")]
+        {
+            let faults = db.fault_injector().snapshot();
+            let injected = [
+                ("fault_db_calls_total", faults.db_calls),
+                ("fault_db_delayed_total", faults.db_delayed),
+                ("fault_db_resets_total", faults.db_resets),
+                ("fault_db_acquire_timeouts_total", faults.db_acquire_timeouts),
+                ("fault_http_requests_total", faults.http_requests),
+                ("fault_http_delayed_total", faults.http_delayed),
+                ("fault_http_5xx_total", faults.http_5xx),
+                ("fault_http_resets_total", faults.http_resets),
+            ];
+            for (name, value) in injected {
+                body.push_str(&format!("# TYPE {} counter\n{} {}\n", name, name, value));
+            }
+        }
+        HttpResponse::Ok().content_type("text/plain; version=0.0.4").body(body)
+    }
+
+    pub async fn create_user(
+        user_service: web::Data<UserService>,
+        user_data: web::Json<CreateUserDto>,
     ) -> Result<impl Responder, ApiError> {
         let user = user_service.create_user_with_default_role(user_data.into_inner()).await?;
-        Ok(HttpResponse::Created().json(user))
+        Ok(HttpResponse::Created().json(UserResponse::from(user)))
+    }
+
+    // `?saved_search=name` runs one of the caller's saved searches instead of inline filters
+    async fn resolve_filters<E: Filterable>(
+        saved_searches: &SavedSearchService,
+        current_user: &Option<CurrentUser>,
+        query: SavedSearchQuery,
+        inline: Filters<E>,
+    ) -> Result<Filters<E>, ApiError> {
+        let Some(name) = query.saved_search else { return Ok(inline) };
+        if !inline.is_empty() {
+            return Err(ApiError::BadRequest("saved_search cannot be combined with filter or sort parameters".to_string()));
+        }
+        let user = current_user.as_ref()
+            .ok_or_else(|| ApiError::Unauthorized("saved_search requires a bearer token".to_string()))?;
+        saved_searches.resolve(user.user_id, &name).await
     }
 
     pub async fn get_users(
-        db: web::Data<Arc<DatabaseConnection>>,
-        query: web::Query<UserFilterDto>,
+        current_user: Option<CurrentUser>,
+        db: web::Data<Arc<ResilientConnection>>,
+        user_service: web::Data<UserService>,
+        saved_searches: web::Data<SavedSearchService>,
+        query: web::Query<SavedSearchQuery>,
+        include: web::Query<IncludeQuery>,
+        filters: Filters<user::Entity>,
     ) -> Result<impl Responder, ApiError> {
-        let users = UserRepository::find_all_with_filter(&db, query.into_inner()).await?;
+        let includes = UserIncludes::parse(include.include.as_deref())?;
+        let filters = resolve_filters(&saved_searches, &current_user, query.into_inner(), filters).await?;
+        let users = UserRepository::find_all_with_filter(db.get_ref().as_ref(), &filters).await?;
+        let users = user_service.expand(users, includes, principal_of(&current_user)).await?;
         Ok(HttpResponse::Ok().json(users))
     }
 
+    pub async fn get_user(
+        current_user: Option<CurrentUser>,
+        user_service: web::Data<UserService>,
+        path: web::Path<Uuid>,
+        include: web::Query<IncludeQuery>,
+    ) -> Result<impl Responder, ApiError> {
+        let includes = UserIncludes::parse(include.include.as_deref())?;
+        let user = user_service.get_user(path.into_inner(), includes, principal_of(&current_user)).await?;
+        Ok(HttpResponse::Ok().json(user))
+    }
+
+    pub async fn get_posts(
+        current_user: Option<CurrentUser>,
+        db: web::Data<Arc<ResilientConnection>>,
+        saved_searches: web::Data<SavedSearchService>,
+        query: web::Query<SavedSearchQuery>,
+        filters: Filters<post::Entity>,
+    ) -> Result<impl Responder, ApiError> {
+        let filters = resolve_filters(&saved_searches, &current_user, query.into_inner(), filters).await?;
+        let posts = ScopedRepository::<post::Entity>::new(principal_of(&current_user))
+            .all(&filters)
+            .all(db.get_ref().as_ref())
+            .await?;
+        Ok(HttpResponse::Ok().json(posts))
+    }
+
+    pub async fn get_saved_searches(
+        current_user: CurrentUser,
+        saved_searches: web::Data<SavedSearchService>,
+    ) -> Result<impl Responder, ApiError> {
+        let searches = saved_searches.list(current_user.user_id).await?;
+        Ok(HttpResponse::Ok().json(searches))
+    }
+
+    pub async fn create_saved_search(
+        current_user: CurrentUser,
+        saved_searches: web::Data<SavedSearchService>,
+        body: web::Json<CreateSavedSearchDto>,
+    ) -> Result<impl Responder, ApiError> {
+        let search = saved_searches.create(current_user.user_id, body.into_inner()).await?;
+        Ok(HttpResponse::Created().json(search))
+    }
+
+    pub async fn delete_saved_search(
+        current_user: CurrentUser,
+        saved_searches: web::Data<SavedSearchService>,
+        path: web::Path<String>,
+    ) -> Result<impl Responder, ApiError> {
+        saved_searches.delete(current_user.user_id, &path.into_inner()).await?;
+        Ok(HttpResponse::NoContent().finish())
+    }
+
+    /// Streams users as newline-delimited JSON. Rows are read off a database cursor and
+    /// written as they arrive, so memory stays flat however large the table is. An error
+    /// after the first row can only cut the response short, as the 200 is already sent.
+    pub async fn export_users_ndjson(
+        db: web::Data<Arc<ResilientConnection>>,
+        filters: Filters<user::Entity>,
+    ) -> impl Responder {
+        let db = db.get_ref().clone();
+        let select = UserRepository::select_with_filter(&filters);
+        let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, actix_web::Error>>(EXPORT_BUFFER_ROWS);
+
+        actix_web::rt::spawn(async move {
+            let mut rows = match select.stream(db.as_ref()).await {
+                Ok(rows) => rows,
+                Err(e) => {
+                    let _ = tx.send(Err(ApiError::from(e).into())).await;
+                    return;
+                }
+            };
+            loop {
+                let chunk = match rows.try_next().await {
+                    Ok(Some(user)) => serde_json::to_vec(&UserResponse::from(user))
+                        .map(|mut line| {
+                            line.push(b'\n');
+                            Bytes::from(line)
+                        })
+                        .map_err(actix_web::error::ErrorInternalServerError),
+                    Ok(None) => return,
+                    Err(e) => Err(ApiError::from(e).into()),
+                };
+                let failed = chunk.is_err();
+                if tx.send(chunk).await.is_err() || failed {
+                    return; // client went away, or the stream is broken
+                }
+            }
+        });
+
+        HttpResponse::Ok()
+            .content_type("application/x-ndjson")
+            .streaming(ReceiverStream::new(rx))
+    }
+
     pub async fn get_user_posts(
+        current_user: Option<CurrentUser>,
         user_service: web::Data<UserService>,
         path: web::Path<Uuid>,
+        filters: Filters<post::Entity>,
     ) -> Result<impl Responder, ApiError> {
         let user_id = path.into_inner();
-        let posts = user_service.find_user_posts(user_id).await?;
+        let posts = user_service.find_user_posts(user_id, principal_of(&current_user), &filters).await?;
         Ok(HttpResponse::Ok().json(posts))
     }
 
     pub async fn assign_role_to_user(
-        db: web::Data<Arc<DatabaseConnection>>,
+        _admin: AdminUser,
+        user_service: web::Data<UserService>,
         path: web::Path<Uuid>,
         role_data: web::Json<AssignRoleDto>,
     ) -> Result<impl Responder, ApiError> {
         let user_id = path.into_inner();
-        let db = db.get_ref();
+        let roles = user_service.assign_role(user_id, &role_data.role_name).await?;
+        Ok(HttpResponse::Ok().json(roles))
+    }
 
-        let user = UserRepository::find_by_id(db, user_id).await?
-            .ok_or_else(|| ApiError::NotFound(format!("User {} not found", user_id)))?;
-        
-        let role = RoleRepository::find_by_name(db, &role_data.role_name).await?
-            .ok_or_else(|| ApiError::NotFound(format!("Role {} not found", role_data.role_name)))?;
+    pub async fn replace_user_roles(
+        _admin: AdminUser,
+        user_service: web::Data<UserService>,
+        path: web::Path<Uuid>,
+        role_data: web::Json<ReplaceRolesDto>,
+    ) -> Result<impl Responder, ApiError> {
+        let user_id = path.into_inner();
+        let roles = user_service.replace_user_roles(user_id, role_data.into_inner().role_names).await?;
+        Ok(HttpResponse::Ok().json(roles))
+    }
+
+    pub async fn remove_role_from_user(
+        _admin: AdminUser,
+        user_service: web::Data<UserService>,
+        path: web::Path<(Uuid, String)>,
+    ) -> Result<impl Responder, ApiError> {
+        let (user_id, role_name) = path.into_inner();
+        user_service.remove_user_role(user_id, &role_name).await?;
+        Ok(HttpResponse::NoContent().finish())
+    }
 
-        user.find_related(super::models::role::Entity)
-            .via(super::models::user_role::Entity)
-            .link(db, &role)
+    pub async fn get_user_profile(
+        profile_service: web::Data<ProfileService>,
+        path: web::Path<Uuid>,
+    ) -> Result<impl Responder, ApiError> {
+        let profile = profile_service.get_profile(path.into_inner()).await?;
+        Ok(HttpResponse::Ok().json(profile))
+    }
+
+    pub async fn patch_user_profile(
+        profile_service: web::Data<ProfileService>,
+        path: web::Path<Uuid>,
+        patch: web::Json<ProfileMergePatchDto>,
+    ) -> Result<impl Responder, ApiError> {
+        let profile = profile_service.patch_profile(path.into_inner(), patch.into_inner()).await?;
+        Ok(HttpResponse::Ok().json(profile))
+    }
+
+    // --- Comments ---
+
+    pub async fn get_post_comments(
+        current_user: Option<CurrentUser>,
+        comment_service: web::Data<CommentService>,
+        path: web::Path<Uuid>,
+        query: web::Query<CommentPageQuery>,
+    ) -> Result<impl Responder, ApiError> {
+        let query = query.into_inner();
+        let page = comment_service
+            .get_thread_page(path.into_inner(), principal_of(&current_user), query.page, query.per_page, query.max_depth)
+            .await?;
+        Ok(HttpResponse::Ok().json(page))
+    }
+
+    pub async fn create_comment(
+        current_user: CurrentUser,
+        tenant: TenantId,
+        flags: web::Data<FeatureFlags>,
+        comment_service: web::Data<CommentService>,
+        path: web::Path<Uuid>,
+        comment_data: web::Json<CreateCommentDto>,
+    ) -> Result<impl Responder, ApiError> {
+        if !flags.is_enabled("comments", &tenant.0, Some(current_user.user_id)) {
+            return Err(ApiError::Forbidden("Comments are disabled".to_string()));
+        }
+        let comment = comment_service
+            .create_comment(path.into_inner(), current_user.principal(), comment_data.into_inner())
+            .await?;
+        Ok(HttpResponse::Created().json(comment))
+    }
+
+    pub async fn delete_comment(
+        current_user: CurrentUser,
+        comment_service: web::Data<CommentService>,
+        path: web::Path<Uuid>,
+    ) -> Result<impl Responder, ApiError> {
+        comment_service
+            .delete_comment(path.into_inner(), current_user.principal())
+            .await?;
+        Ok(HttpResponse::NoContent().finish())
+    }
+
+    // --- Tags ---
+
+    pub async fn get_post_tags(
+        current_user: Option<CurrentUser>,
+        tag_service: web::Data<TagService>,
+        path: web::Path<Uuid>,
+    ) -> Result<impl Responder, ApiError> {
+        let tags = tag_service.get_post_tags(path.into_inner(), principal_of(&current_user)).await?;
+        Ok(HttpResponse::Ok().json(tags))
+    }
+
+    pub async fn set_post_tags(
+        current_user: CurrentUser,
+        tag_service: web::Data<TagService>,
+        path: web::Path<Uuid>,
+        tag_data: web::Json<SetPostTagsDto>,
+    ) -> Result<impl Responder, ApiError> {
+        let tags = tag_service
+            .set_post_tags(path.into_inner(), current_user.principal(), tag_data.into_inner().tags)
             .await?;
+        Ok(HttpResponse::Ok().json(tags))
+    }
+
+    pub async fn search_tags(
+        tag_service: web::Data<TagService>,
+        query: web::Query<TagSearchQuery>,
+    ) -> Result<impl Responder, ApiError> {
+        let query = query.into_inner();
+        let tags = tag_service.search_tags(query.prefix, query.limit).await?;
+        Ok(HttpResponse::Ok().json(tags))
+    }
+
+    pub async fn get_posts_by_tag(
+        current_user: Option<CurrentUser>,
+        tag_service: web::Data<TagService>,
+        path: web::Path<String>,
+        filters: Filters<post::Entity>,
+    ) -> Result<impl Responder, ApiError> {
+        let tagged = tag_service.posts_for_tag(&path.into_inner(), principal_of(&current_user), &filters).await?;
+        Ok(HttpResponse::Ok().json(tagged))
+    }
+
+    pub async fn delete_tag(
+        _admin: AdminUser,
+        tag_service: web::Data<TagService>,
+        path: web::Path<Uuid>,
+    ) -> Result<impl Responder, ApiError> {
+        tag_service.delete_tag(path.into_inner()).await?;
+        Ok(HttpResponse::NoContent().finish())
+    }
+
+    // --- Feature flags & tenant settings ---
+
+    pub async fn evaluate_feature_flags(
+        current_user: Option<CurrentUser>,
+        tenant: TenantId,
+        flags: web::Data<FeatureFlags>,
+    ) -> impl Responder {
+        // Anonymous callers are bucketed by tenant instead of by user
+        let user_id = current_user.map(|user| user.user_id);
+        HttpResponse::Ok().json(flags.evaluate_all(&tenant.0, user_id))
+    }
+
+    pub async fn get_feature_flags(
+        _admin: AdminUser,
+        flags: web::Data<FeatureFlags>,
+    ) -> Result<impl Responder, ApiError> {
+        Ok(HttpResponse::Ok().json(flags.list().await?))
+    }
+
+    pub async fn put_feature_flag(
+        _admin: AdminUser,
+        flags: web::Data<FeatureFlags>,
+        path: web::Path<String>,
+        flag_data: web::Json<UpsertFeatureFlagDto>,
+    ) -> Result<impl Responder, ApiError> {
+        flags.upsert_flag(&path.into_inner(), flag_data.into_inner()).await?;
+        Ok(HttpResponse::NoContent().finish())
+    }
+
+    pub async fn put_feature_flag_override(
+        _admin: AdminUser,
+        flags: web::Data<FeatureFlags>,
+        path: web::Path<(String, String)>,
+        override_data: web::Json<TenantOverrideDto>,
+    ) -> Result<impl Responder, ApiError> {
+        let (name, tenant_id) = path.into_inner();
+        flags.set_tenant_override(&name, &tenant_id, override_data.enabled).await?;
+        Ok(HttpResponse::NoContent().finish())
+    }
+
+    pub async fn delete_feature_flag_override(
+        _admin: AdminUser,
+        flags: web::Data<FeatureFlags>,
+        path: web::Path<(String, String)>,
+    ) -> Result<impl Responder, ApiError> {
+        let (name, tenant_id) = path.into_inner();
+        flags.clear_tenant_override(&name, &tenant_id).await?;
+        Ok(HttpResponse::NoContent().finish())
+    }
+
+    pub async fn get_tenant_settings(
+        _admin: AdminUser,
+        settings_service: web::Data<TenantSettingsService>,
+        path: web::Path<String>,
+    ) -> Result<impl Responder, ApiError> {
+        let settings = settings_service.get_settings(&path.into_inner()).await?;
+        Ok(HttpResponse::Ok().json(settings))
+    }
+
+    pub async fn put_tenant_setting(
+        _admin: AdminUser,
+        settings_service: web::Data<TenantSettingsService>,
+        path: web::Path<(String, String)>,
+        setting_data: web::Json<TenantSettingDto>,
+    ) -> Result<impl Responder, ApiError> {
+        let (tenant_id, key) = path.into_inner();
+        settings_service.put_setting(&tenant_id, &key, setting_data.into_inner().value).await?;
+        Ok(HttpResponse::NoContent().finish())
+    }
 
-        Ok(HttpResponse::Ok().finish())
+    // --- Role administration ---
+
+    pub async fn create_role(
+        _admin: AdminUser,
+        role_service: web::Data<RoleService>,
+        role_data: web::Json<CreateRoleDto>,
+    ) -> Result<impl Responder, ApiError> {
+        let role = role_service.create_role(role_data.into_inner()).await?;
+        Ok(HttpResponse::Created().json(role))
+    }
+
+    pub async fn get_roles(
+        _admin: AdminUser,
+        role_service: web::Data<RoleService>,
+    ) -> Result<impl Responder, ApiError> {
+        let roles = role_service.list_roles().await?;
+        Ok(HttpResponse::Ok().json(roles))
+    }
+
+    pub async fn get_role(
+        _admin: AdminUser,
+        role_service: web::Data<RoleService>,
+        path: web::Path<Uuid>,
+    ) -> Result<impl Responder, ApiError> {
+        let role = role_service.get_role(path.into_inner()).await?;
+        Ok(HttpResponse::Ok().json(role))
+    }
+
+    pub async fn update_role(
+        _admin: AdminUser,
+        role_service: web::Data<RoleService>,
+        path: web::Path<Uuid>,
+        role_data: web::Json<UpdateRoleDto>,
+    ) -> Result<impl Responder, ApiError> {
+        let role = role_service.update_role(path.into_inner(), role_data.into_inner()).await?;
+        Ok(HttpResponse::Ok().json(role))
+    }
+
+    pub async fn delete_role(
+        _admin: AdminUser,
+        role_service: web::Data<RoleService>,
+        path: web::Path<Uuid>,
+        query: web::Query<DeleteRoleQuery>,
+    ) -> Result<impl Responder, ApiError> {
+        role_service.delete_role(path.into_inner(), query.cascade).await?;
+        Ok(HttpResponse::NoContent().finish())
+    }
+
+    pub async fn get_user_stats(
+        _admin: AdminUser,
+        stats_service: web::Data<StatsService>,
+        query: web::Query<UserStatsQuery>,
+    ) -> Result<impl Responder, ApiError> {
+        let stats = stats_service.user_signups(query.into_inner()).await?;
+        Ok(HttpResponse::Ok().json(stats))
+    }
+
+    pub async fn get_post_stats(
+        current_user: Option<CurrentUser>,
+        stats_service: web::Data<StatsService>,
+        query: web::Query<PostStatsQuery>,
+    ) -> Result<impl Responder, ApiError> {
+        let counts = stats_service.post_counts(query.group_by.as_deref(), principal_of(&current_user)).await?;
+        Ok(HttpResponse::Ok().json(counts))
+    }
+
+    pub async fn export_backup(
+        _admin: AdminUser,
+        backup_service: web::Data<BackupService>,
+    ) -> Result<impl Responder, ApiError> {
+        let archive = backup_service.export().await?;
+        let filename = format!("backup-{}.json.gz", chrono::Utc::now().format("%Y%m%dT%H%M%SZ"));
+        Ok(HttpResponse::Ok()
+            .content_type("application/gzip")
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+            .body(archive))
+    }
+
+    // Body is the gzipped JSON produced by `export_backup`
+    pub async fn import_backup(
+        _admin: AdminUser,
+        backup_service: web::Data<BackupService>,
+        query: web::Query<ImportBackupQuery>,
+        body: Bytes,
+    ) -> Result<impl Responder, ApiError> {
+        let summary = backup_service.import(&body, query.mode).await?;
+        Ok(HttpResponse::Ok().json(summary))
+    }
+}
+
+// --- 11. Database Migrations (db/migrator.rs) ---
+mod migrator {
+    use sea_orm::{prelude::Uuid, sea_query::Table, ConnectionTrait, DbErr, Statement};
+    use sea_orm_migration::prelude::*;
+    use super::models::{user, post, role, user_role, profile, tag, post_tag, comment, feature_flag, feature_flag_override, tenant_setting, saved_search};
+
+    pub struct Migrator;
+
+    // Versions are spelled out rather than derived: `DeriveMigrationName` names a
+    // migration after its source file, so every migration here would share one version.
+
+    #[async_trait::async_trait]
+    impl MigratorTrait for Migrator {
+        fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+            vec![
+                Box::new(InitialMigration),
+                Box::new(CreateProfilesMigration),
+                Box::new(CreateTagsMigration),
+                Box::new(CreateCommentsMigration),
+                Box::new(CreateFeatureFlagsMigration),
+                Box::new(CreateSavedSearchesMigration),
+            ]
+        }
+    }
+
+    struct InitialMigration;
+
+    impl MigrationName for InitialMigration {
+        fn name(&self) -> &str { "m20240101_000001_create_users_posts_and_roles" }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for InitialMigration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager.create_table(
+                Table::create()
+                    .table(user::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(user::Column::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(user::Column::Email).string().not_null().unique_key())
+                    .col(ColumnDef::new(user::Column::PasswordHash).string().not_null())
+                    .col(ColumnDef::new(user::Column::IsActive).boolean().not_null())
+                    .col(ColumnDef::new(user::Column::CreatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            ).await?;
+
+            manager.create_table(
+                Table::create()
+                    .table(post::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(post::Column::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(post::Column::UserId).uuid().not_null())
+                    .col(ColumnDef::new(post::Column::Title).string().not_null())
+                    .col(ColumnDef::new(post::Column::Content).text().not_null())
+                    .col(ColumnDef::new(post::Column::Status).string().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post-user_id")
+                            .from(post::Entity, post::Column::UserId)
+                            .to(user::Entity, user::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            ).await?;
+
+            manager.create_table(
+                Table::create()
+                    .table(role::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(role::Column::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(role::Column::Name).string().not_null().unique_key())
+                    .to_owned(),
+            ).await?;
+
+            manager.create_table(
+                Table::create()
+                    .table(user_role::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(user_role::Column::UserId).uuid().not_null())
+                    .col(ColumnDef::new(user_role::Column::RoleId).uuid().not_null())
+                    .primary_key(Index::create().col(user_role::Column::UserId).col(user_role::Column::RoleId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user_role-user_id")
+                            .from(user_role::Entity, user_role::Column::UserId)
+                            .to(user::Entity, user::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user_role-role_id")
+                            .from(user_role::Entity, user_role::Column::RoleId)
+                            .to(role::Entity, role::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            ).await?;
+
+            // Seed initial roles
+            let db = manager.get_connection();
+            let admin_id = Uuid::new_v4();
+            let user_id = Uuid::new_v4();
+            db.execute(Statement::from_sql_and_values(
+                manager.get_database_backend(),
+                r#"INSERT INTO "roles" ("id", "name") VALUES ($1, 'ADMIN'), ($2, 'USER')"#,
+                [admin_id.into(), user_id.into()],
+            )).await?;
+
+            Ok(())
+        }
     }
-}
 
-// --- 6. Database Migrations (db/migrator.rs) ---
-mod migrator {
-    use sea_orm::{prelude::Uuid, sea_query::Table, ConnectionTrait, DbErr, Statement};
-    use sea_orm_migration::prelude::*;
-    use super::models::{user, post, role, user_role};
+    struct CreateProfilesMigration;
 
-    pub struct Migrator;
+    impl MigrationName for CreateProfilesMigration {
+        fn name(&self) -> &str { "m20240101_000002_create_profiles" }
+    }
 
     #[async_trait::async_trait]
-    impl MigratorTrait for Migrator {
-        fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-            vec![Box::new(InitialMigration)]
+    impl MigrationTrait for CreateProfilesMigration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager.create_table(
+                Table::create()
+                    .table(profile::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(profile::Column::UserId).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(profile::Column::DisplayName).string().null())
+                    .col(ColumnDef::new(profile::Column::Bio).text().null())
+                    .col(ColumnDef::new(profile::Column::AvatarUrl).string().null())
+                    .col(ColumnDef::new(profile::Column::Locale).string().null())
+                    .col(ColumnDef::new(profile::Column::Timezone).string().null())
+                    .col(ColumnDef::new(profile::Column::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-profile-user_id")
+                            .from(profile::Entity, profile::Column::UserId)
+                            .to(user::Entity, user::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            ).await
         }
     }
 
-    struct InitialMigration;
+    struct CreateTagsMigration;
+
+    impl MigrationName for CreateTagsMigration {
+        fn name(&self) -> &str { "m20240101_000003_create_tags" }
+    }
 
     #[async_trait::async_trait]
-    impl MigrationTrait for InitialMigration {
+    impl MigrationTrait for CreateTagsMigration {
         async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
             manager.create_table(
                 Table::create()
-                    .table(user::Entity)
+                    .table(tag::Entity)
                     .if_not_exists()
-                    .col(ColumnDef::new(user::Column::Id).uuid().not_null().primary_key())
-                    .col(ColumnDef::new(user::Column::Email).string().not_null().unique_key())
-                    .col(ColumnDef::new(user::Column::PasswordHash).string().not_null())
-                    .col(ColumnDef::new(user::Column::IsActive).boolean().not_null())
-                    .col(ColumnDef::new(user::Column::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(tag::Column::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(tag::Column::Name).string().not_null().unique_key())
                     .to_owned(),
             ).await?;
 
             manager.create_table(
                 Table::create()
-                    .table(post::Entity)
+                    .table(post_tag::Entity)
                     .if_not_exists()
-                    .col(ColumnDef::new(post::Column::Id).uuid().not_null().primary_key())
-                    .col(ColumnDef::new(post::Column::UserId).uuid().not_null())
-                    .col(ColumnDef::new(post::Column::Title).string().not_null())
-                    .col(ColumnDef::new(post::Column::Content).text().not_null())
-                    .col(ColumnDef::new(post::Column::Status).string().not_null())
+                    .col(ColumnDef::new(post_tag::Column::PostId).uuid().not_null())
+                    .col(ColumnDef::new(post_tag::Column::TagId).uuid().not_null())
+                    .primary_key(Index::create().col(post_tag::Column::PostId).col(post_tag::Column::TagId))
                     .foreign_key(
                         ForeignKey::create()
-                            .name("fk-post-user_id")
-                            .from(post::Entity, post::Column::UserId)
+                            .name("fk-post_tag-post_id")
+                            .from(post_tag::Entity, post_tag::Column::PostId)
+                            .to(post::Entity, post::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post_tag-tag_id")
+                            .from(post_tag::Entity, post_tag::Column::TagId)
+                            .to(tag::Entity, tag::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            ).await?;
+
+            // Listing posts by tag filters on tag_id, which the composite PK does not lead with
+            manager.create_index(
+                Index::create()
+                    .name("idx-post_tag-tag_id")
+                    .table(post_tag::Entity)
+                    .col(post_tag::Column::TagId)
+                    .to_owned(),
+            ).await
+        }
+    }
+
+    struct CreateCommentsMigration;
+
+    impl MigrationName for CreateCommentsMigration {
+        fn name(&self) -> &str { "m20240101_000004_create_comments" }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for CreateCommentsMigration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager.create_table(
+                Table::create()
+                    .table(comment::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(comment::Column::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(comment::Column::PostId).uuid().not_null())
+                    .col(ColumnDef::new(comment::Column::AuthorId).uuid().not_null())
+                    .col(ColumnDef::new(comment::Column::ParentCommentId).uuid().null())
+                    .col(ColumnDef::new(comment::Column::Body).text().not_null())
+                    .col(ColumnDef::new(comment::Column::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-comment-post_id")
+                            .from(comment::Entity, comment::Column::PostId)
+                            .to(post::Entity, post::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-comment-author_id")
+                            .from(comment::Entity, comment::Column::AuthorId)
                             .to(user::Entity, user::Column::Id)
                             .on_delete(ForeignKeyAction::Cascade),
                     )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-comment-parent_comment_id")
+                            .from(comment::Entity, comment::Column::ParentCommentId)
+                            .to(comment::Entity, comment::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            ).await?;
+
+            manager.create_index(
+                Index::create()
+                    .name("idx-comment-post_id-parent_comment_id")
+                    .table(comment::Entity)
+                    .col(comment::Column::PostId)
+                    .col(comment::Column::ParentCommentId)
                     .to_owned(),
             ).await?;
 
+            manager.create_index(
+                Index::create()
+                    .name("idx-comment-parent_comment_id")
+                    .table(comment::Entity)
+                    .col(comment::Column::ParentCommentId)
+                    .to_owned(),
+            ).await
+        }
+    }
+
+    struct CreateFeatureFlagsMigration;
+
+    impl MigrationName for CreateFeatureFlagsMigration {
+        fn name(&self) -> &str { "m20240101_000005_create_feature_flags" }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for CreateFeatureFlagsMigration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
             manager.create_table(
                 Table::create()
-                    .table(role::Entity)
+                    .table(feature_flag::Entity)
                     .if_not_exists()
-                    .col(ColumnDef::new(role::Column::Id).uuid().not_null().primary_key())
-                    .col(ColumnDef::new(role::Column::Name).string().not_null().unique_key())
+                    .col(ColumnDef::new(feature_flag::Column::Name).string().not_null().primary_key())
+                    .col(ColumnDef::new(feature_flag::Column::Enabled).boolean().not_null())
+                    .col(ColumnDef::new(feature_flag::Column::RolloutPercentage).small_integer().not_null())
+                    .col(ColumnDef::new(feature_flag::Column::Description).string().null())
+                    .col(ColumnDef::new(feature_flag::Column::UpdatedAt).timestamp_with_time_zone().not_null())
                     .to_owned(),
             ).await?;
 
             manager.create_table(
                 Table::create()
-                    .table(user_role::Entity)
+                    .table(feature_flag_override::Entity)
                     .if_not_exists()
-                    .col(ColumnDef::new(user_role::Column::UserId).uuid().not_null())
-                    .col(ColumnDef::new(user_role::Column::RoleId).uuid().not_null())
-                    .primary_key(Index::create().col(user_role::Column::UserId).col(user_role::Column::RoleId))
-                    .foreign_key(
-                        ForeignKey::create()
-                            .name("fk-user_role-user_id")
-                            .from(user_role::Entity, user_role::Column::UserId)
-                            .to(user::Entity, user::Column::Id)
-                            .on_delete(ForeignKeyAction::Cascade),
+                    .col(ColumnDef::new(feature_flag_override::Column::FlagName).string().not_null())
+                    .col(ColumnDef::new(feature_flag_override::Column::TenantId).string().not_null())
+                    .col(ColumnDef::new(feature_flag_override::Column::Enabled).boolean().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(feature_flag_override::Column::FlagName)
+                            .col(feature_flag_override::Column::TenantId),
                     )
                     .foreign_key(
                         ForeignKey::create()
-                            .name("fk-user_role-role_id")
-                            .from(user_role::Entity, user_role::Column::RoleId)
-                            .to(role::Entity, role::Column::Id)
+                            .name("fk-feature_flag_override-flag_name")
+                            .from(feature_flag_override::Entity, feature_flag_override::Column::FlagName)
+                            .to(feature_flag::Entity, feature_flag::Column::Name)
                             .on_delete(ForeignKeyAction::Cascade),
                     )
                     .to_owned(),
             ).await?;
 
-            // Seed initial roles
+            manager.create_table(
+                Table::create()
+                    .table(tenant_setting::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(tenant_setting::Column::TenantId).string().not_null())
+                    .col(ColumnDef::new(tenant_setting::Column::Key).string().not_null())
+                    .col(ColumnDef::new(tenant_setting::Column::Value).json().not_null())
+                    .col(ColumnDef::new(tenant_setting::Column::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(tenant_setting::Column::TenantId)
+                            .col(tenant_setting::Column::Key),
+                    )
+                    .to_owned(),
+            ).await?;
+
+            // Seed the flags the application checks today
             let db = manager.get_connection();
-            let admin_id = Uuid::new_v4();
-            let user_id = Uuid::new_v4();
             db.execute(Statement::from_sql_and_values(
                 manager.get_database_backend(),
-                r#"INSERT INTO "roles" ("id", "name") VALUES ($1, 'ADMIN'), ($2, 'USER')"#,
-                [admin_id.into(), user_id.into()],
+                r#"INSERT INTO "feature_flags" ("name", "enabled", "rollout_percentage", "description", "updated_at")
+                   VALUES ('comments', TRUE, 100, 'Allow users to comment on posts', $1),
+                          ('two_factor_required', FALSE, 0, 'Require 2FA at login', $1)"#,
+                [chrono::Utc::now().into()],
             )).await?;
 
             Ok(())
         }
     }
+
+    struct CreateSavedSearchesMigration;
+
+    impl MigrationName for CreateSavedSearchesMigration {
+        fn name(&self) -> &str { "m20240101_000006_create_saved_searches" }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for CreateSavedSearchesMigration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager.create_table(
+                Table::create()
+                    .table(saved_search::Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(saved_search::Column::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(saved_search::Column::UserId).uuid().not_null())
+                    .col(ColumnDef::new(saved_search::Column::Name).string().not_null())
+                    .col(ColumnDef::new(saved_search::Column::Resource).string().not_null())
+                    .col(ColumnDef::new(saved_search::Column::Document).json().not_null())
+                    .col(ColumnDef::new(saved_search::Column::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-saved_search-user_id")
+                            .from(saved_search::Entity, saved_search::Column::UserId)
+                            .to(user::Entity, user::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            ).await?;
+
+            manager.create_index(
+                Index::create()
+                    .name("idx-saved_search-user_id-name")
+                    .table(saved_search::Entity)
+                    .col(saved_search::Column::UserId)
+                    .col(saved_search::Column::Name)
+                    .unique()
+                    .to_owned(),
+            ).await
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use sea_orm::Database;
+
+        #[actix_web::test]
+        async fn every_migration_applies_under_its_own_version() {
+            let db = Database::connect("sqlite::memory:").await.unwrap();
+            Migrator::up(&db, None).await.unwrap();
+
+            let mut versions: Vec<String> = Migrator::migrations().iter().map(|m| m.name().to_string()).collect();
+            let applied = Migrator::get_applied_migrations(&db).await.unwrap();
+            assert_eq!(applied.len(), versions.len());
+            versions.sort();
+            versions.dedup();
+            assert_eq!(versions.len(), applied.len());
+        }
+    }
+}
+
+// --- 12. gRPC API (grpc/mod.rs) ---
+// Serves the same user operations over gRPC on a second port. Handlers go through
+// the service/repository layers above, so both transports share business rules.
+//
+// Requires `tonic`, `prost`, `tokio-stream` and `jsonwebtoken`. The service is defined
+// in proto/users.proto next to this file; build.rs (with `tonic-build` as a build
+// dependency) compiles it into the `users.v1` module included below.
+// tonic's interceptor and handler signatures return `Status` by value, so the helpers do too.
+#[allow(clippy::result_large_err)]
+mod grpc {
+    use super::models::{dtos::CreateUserDto, user};
+    use super::repositories::UserRepository;
+    use super::services::UserService;
+    use super::ApiError;
+    use super::guards::{self, decode_token, Claims, CurrentUser};
+    use super::resilience::ResilientConnection;
+    use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder};
+    use std::sync::Arc;
+    use tokio_stream::wrappers::ReceiverStream;
+    use tonic::{Request, Response, Status};
+    use uuid::Uuid;
+
+    pub mod pb {
+        tonic::include_proto!("users.v1");
+    }
+
+    use pb::user_service_server::UserService as UserServiceRpc;
+    pub use pb::user_service_server::UserServiceServer;
+
+    const LIST_BATCH_SIZE: u64 = 100;
+
+    impl From<ApiError> for Status {
+        fn from(err: ApiError) -> Self {
+            match err {
+                ApiError::NotFound(msg) => Status::not_found(msg),
+                ApiError::BadRequest(msg) => Status::invalid_argument(msg),
+                ApiError::Unauthorized(msg) => Status::unauthenticated(msg),
+                ApiError::Forbidden(msg) => Status::permission_denied(msg),
+                ApiError::Conflict(msg) => Status::already_exists(msg),
+                ApiError::Internal(msg) => {
+                    eprintln!("gRPC internal error: {}", msg);
+                    Status::internal("Internal error")
+                }
+                ApiError::DbError(e) => {
+                    eprintln!("gRPC database error: {}", e);
+                    Status::internal("Database error")
+                }
+            }
+        }
+    }
+
+    impl From<user::Model> for pb::User {
+        fn from(model: user::Model) -> Self {
+            Self {
+                id: model.id.to_string(),
+                email: model.email,
+                is_active: model.is_active,
+                created_at: model.created_at.to_rfc3339(),
+            }
+        }
+    }
+
+    /// Validates `authorization: Bearer <jwt>` metadata on every call and stores the
+    /// decoded claims in the request extensions, like the HTTP auth middleware.
+    pub fn jwt_interceptor(mut req: Request<()>) -> Result<Request<()>, Status> {
+        let header = req
+            .metadata()
+            .get("authorization")
+            .ok_or_else(|| Status::unauthenticated("No token provided"))?
+            .to_str()
+            .map_err(|_| Status::unauthenticated("Invalid token format"))?;
+        let token = header
+            .strip_prefix("Bearer ")
+            .ok_or_else(|| Status::unauthenticated("Invalid token format"))?;
+
+        let claims = decode_token(token).map_err(|_| Status::unauthenticated("Invalid token"))?;
+        req.extensions_mut().insert(claims);
+        Ok(req)
+    }
+
+    // The claims only say who is calling; `authenticate` decides what they may do.
+    fn caller_id<T>(req: &Request<T>) -> Result<Uuid, Status> {
+        req.extensions()
+            .get::<Claims>()
+            .map(|claims| claims.sub)
+            .ok_or_else(|| Status::unauthenticated("No token provided"))
+    }
+
+    fn parse_uuid(field: &str, value: &str) -> Result<Uuid, Status> {
+        Uuid::parse_str(value).map_err(|_| Status::invalid_argument(format!("{} must be a UUID", field)))
+    }
+
+    pub struct UserGrpcService {
+        db: Arc<ResilientConnection>,
+        user_service: UserService,
+    }
+
+    impl UserGrpcService {
+        pub fn new(db: Arc<ResilientConnection>) -> Self {
+            Self { user_service: UserService::new(db.clone()), db }
+        }
+
+        /// Same checks as the HTTP `CurrentUser` extractor: the account must still
+        /// exist and be active, and roles come from the database, not the token.
+        async fn authenticate(&self, user_id: Uuid) -> Result<CurrentUser, Status> {
+            Ok(guards::resolve_user(&self.db, user_id).await?)
+        }
+
+        async fn require_admin(&self, user_id: Uuid) -> Result<CurrentUser, Status> {
+            let user = self.authenticate(user_id).await?;
+            if !user.is_admin {
+                return Err(Status::permission_denied("Administrator role required"));
+            }
+            Ok(user)
+        }
+    }
+
+    #[tonic::async_trait]
+    impl UserServiceRpc for UserGrpcService {
+        type ListUsersStream = ReceiverStream<Result<pb::User, Status>>;
+
+        async fn create_user(&self, request: Request<pb::CreateUserRequest>) -> Result<Response<pb::User>, Status> {
+            self.authenticate(caller_id(&request)?).await?;
+            let req = request.into_inner();
+            let user = self
+                .user_service
+                .create_user_with_default_role(CreateUserDto { email: req.email, password: req.password })
+                .await?;
+            Ok(Response::new(user.into()))
+        }
+
+        async fn get_user(&self, request: Request<pb::GetUserRequest>) -> Result<Response<pb::User>, Status> {
+            self.authenticate(caller_id(&request)?).await?;
+            let id = parse_uuid("id", &request.get_ref().id)?;
+            let user = UserRepository::find_by_id(&*self.db, id)
+                .await
+                .map_err(ApiError::from)?
+                .ok_or_else(|| Status::not_found(format!("User with id {} not found", id)))?;
+            Ok(Response::new(user.into()))
+        }
+
+        // Pages through the table in fixed-size batches so large listings never sit in memory
+        async fn list_users(&self, request: Request<pb::ListUsersRequest>) -> Result<Response<Self::ListUsersStream>, Status> {
+            self.authenticate(caller_id(&request)?).await?;
+            let is_active = request.into_inner().is_active;
+            let db = self.db.clone();
+            let (tx, rx) = tokio::sync::mpsc::channel(LIST_BATCH_SIZE as usize);
+
+            tokio::spawn(async move {
+                let mut select = user::Entity::find().order_by_asc(user::Column::CreatedAt);
+                if let Some(is_active) = is_active {
+                    select = select.filter(user::Column::IsActive.eq(is_active));
+                }
+                let mut pages = select.paginate(db.as_ref(), LIST_BATCH_SIZE);
+                loop {
+                    match pages.fetch_and_next().await {
+                        Ok(Some(batch)) => {
+                            for model in batch {
+                                if tx.send(Ok(model.into())).await.is_err() {
+                                    return; // client went away
+                                }
+                            }
+                        }
+                        Ok(None) => return,
+                        Err(e) => {
+                            let _ = tx.send(Err(ApiError::from(e).into())).await;
+                            return;
+                        }
+                    }
+                }
+            });
+
+            Ok(Response::new(ReceiverStream::new(rx)))
+        }
+
+        async fn assign_role(&self, request: Request<pb::AssignRoleRequest>) -> Result<Response<pb::AssignRoleResponse>, Status> {
+            self.require_admin(caller_id(&request)?).await?;
+            let req = request.into_inner();
+            let user_id = parse_uuid("user_id", &req.user_id)?;
+            let roles = self.user_service.assign_role(user_id, &req.role_name).await?;
+            Ok(Response::new(pb::AssignRoleResponse { roles: roles.into_iter().map(|r| r.name).collect() }))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::migrator::Migrator;
+        use sea_orm::{ActiveModelTrait, ActiveValue, Database};
+        use sea_orm_migration::MigratorTrait;
+        use tonic::Code;
+
+        async fn service() -> (UserGrpcService, UserService) {
+            let raw = Database::connect("sqlite::memory:").await.unwrap();
+            Migrator::up(&raw, None).await.unwrap();
+            let db = Arc::new(ResilientConnection::new(raw));
+            (UserGrpcService::new(db.clone()), UserService::new(db))
+        }
+
+        async fn signup(users: &UserService, email: &str) -> user::Model {
+            users
+                .create_user_with_default_role(CreateUserDto { email: email.to_string(), password: "secret".to_string() })
+                .await
+                .unwrap()
+        }
+
+        // Every token below claims ADMIN; only the database should decide.
+        fn assign(caller: Uuid, target: Uuid) -> Request<pb::AssignRoleRequest> {
+            let mut req = Request::new(pb::AssignRoleRequest { user_id: target.to_string(), role_name: "USER".to_string() });
+            req.extensions_mut().insert(Claims { sub: caller, role: "ADMIN".to_string(), exp: usize::MAX });
+            req
+        }
+
+        #[actix_web::test]
+        async fn assign_role_rereads_the_callers_roles() {
+            let (grpc, users) = service().await;
+            let admin = signup(&users, "admin@example.com").await;
+            let target = signup(&users, "target@example.com").await;
+            users.assign_role(admin.id, "ADMIN").await.unwrap();
+
+            assert!(grpc.assign_role(assign(admin.id, target.id)).await.is_ok());
+
+            users.remove_user_role(admin.id, "ADMIN").await.unwrap();
+            let err = grpc.assign_role(assign(admin.id, target.id)).await.unwrap_err();
+            assert_eq!(err.code(), Code::PermissionDenied);
+        }
+
+        #[actix_web::test]
+        async fn deactivated_callers_are_refused() {
+            let (grpc, users) = service().await;
+            let admin = signup(&users, "admin@example.com").await;
+            users.assign_role(admin.id, "ADMIN").await.unwrap();
+            let mut deactivated: user::ActiveModel = admin.clone().into();
+            deactivated.is_active = ActiveValue::Set(false);
+            deactivated.update(&*grpc.db).await.unwrap();
+
+            let err = grpc.assign_role(assign(admin.id, admin.id)).await.unwrap_err();
+            assert_eq!(err.code(), Code::Unauthenticated);
+
+            let mut get = Request::new(pb::GetUserRequest { id: admin.id.to_string() });
+            get.extensions_mut().insert(Claims { sub: admin.id, role: "ADMIN".to_string(), exp: usize::MAX });
+            assert_eq!(grpc.get_user(get).await.unwrap_err().code(), Code::Unauthenticated);
+        }
+    }
+}
+
+// --- 13. Route Scopes (routes/mod.rs) ---
+// Each resource owns its scope, along with any scope-level config it needs, so a
+// new feature registers here instead of growing `main`.
+mod routes {
+    use super::handlers;
+    use actix_web::{web, Scope};
+
+    pub fn configure(cfg: &mut web::ServiceConfig) {
+        cfg.route("/health", web::get().to(handlers::health))
+            .route("/metrics", web::get().to(handlers::metrics))
+            .service(users::scope())
+            .service(posts::scope())
+            .service(comments::scope())
+            .service(tags::scope())
+            .service(feature_flags::scope())
+            .service(tenants::scope())
+            .service(roles::scope())
+            .service(stats::scope())
+            .service(admin::scope());
+    }
+
+    pub mod users {
+        use super::*;
+
+        pub fn scope() -> Scope {
+            web::scope("/users")
+                .route("", web::post().to(handlers::create_user))
+                .route("", web::get().to(handlers::get_users))
+                .route("/export.ndjson", web::get().to(handlers::export_users_ndjson))
+                .route("/me/saved-searches", web::get().to(handlers::get_saved_searches))
+                .route("/me/saved-searches", web::post().to(handlers::create_saved_search))
+                .route("/me/saved-searches/{name}", web::delete().to(handlers::delete_saved_search))
+                .route("/{user_id}", web::get().to(handlers::get_user))
+                .route("/{user_id}/posts", web::get().to(handlers::get_user_posts))
+                .route("/{user_id}/profile", web::get().to(handlers::get_user_profile))
+                .route("/{user_id}/profile", web::patch().to(handlers::patch_user_profile))
+                .route("/{user_id}/roles", web::post().to(handlers::assign_role_to_user))
+                .route("/{user_id}/roles", web::put().to(handlers::replace_user_roles))
+                .route("/{user_id}/roles/{role_name}", web::delete().to(handlers::remove_role_from_user))
+        }
+    }
+
+    pub mod posts {
+        use super::*;
+
+        pub fn scope() -> Scope {
+            web::scope("/posts")
+                .route("", web::get().to(handlers::get_posts))
+                .route("/{post_id}/tags", web::get().to(handlers::get_post_tags))
+                .route("/{post_id}/tags", web::put().to(handlers::set_post_tags))
+                .route("/{post_id}/comments", web::get().to(handlers::get_post_comments))
+                .route("/{post_id}/comments", web::post().to(handlers::create_comment))
+        }
+    }
+
+    pub mod comments {
+        use super::*;
+
+        pub fn scope() -> Scope {
+            web::scope("/comments")
+                .route("/{comment_id}", web::delete().to(handlers::delete_comment))
+        }
+    }
+
+    pub mod tags {
+        use super::*;
+
+        pub fn scope() -> Scope {
+            web::scope("/tags")
+                .route("", web::get().to(handlers::search_tags))
+                .route("/{tag_id}", web::delete().to(handlers::delete_tag))
+                .route("/{name}/posts", web::get().to(handlers::get_posts_by_tag))
+        }
+    }
+
+    pub mod feature_flags {
+        use super::*;
+
+        pub fn scope() -> Scope {
+            web::scope("/feature-flags")
+                .route("", web::get().to(handlers::get_feature_flags))
+                .route("/evaluate", web::get().to(handlers::evaluate_feature_flags))
+                .route("/{name}", web::put().to(handlers::put_feature_flag))
+                .route("/{name}/tenants/{tenant_id}", web::put().to(handlers::put_feature_flag_override))
+                .route("/{name}/tenants/{tenant_id}", web::delete().to(handlers::delete_feature_flag_override))
+        }
+    }
+
+    pub mod tenants {
+        use super::*;
+
+        pub fn scope() -> Scope {
+            web::scope("/tenants")
+                .route("/{tenant_id}/settings", web::get().to(handlers::get_tenant_settings))
+                .route("/{tenant_id}/settings/{key}", web::put().to(handlers::put_tenant_setting))
+        }
+    }
+
+    pub mod roles {
+        use super::*;
+
+        pub fn scope() -> Scope {
+            web::scope("/roles")
+                .route("", web::post().to(handlers::create_role))
+                .route("", web::get().to(handlers::get_roles))
+                .route("/{role_id}", web::get().to(handlers::get_role))
+                .route("/{role_id}", web::patch().to(handlers::update_role))
+                .route("/{role_id}", web::delete().to(handlers::delete_role))
+        }
+    }
+
+    pub mod stats {
+        use super::*;
+
+        pub fn scope() -> Scope {
+            web::scope("/stats")
+                .route("/users", web::get().to(handlers::get_user_stats))
+                .route("/posts", web::get().to(handlers::get_post_stats))
+        }
+    }
+
+    pub mod admin {
+        use super::*;
+
+        pub const MAX_BACKUP_UPLOAD_BYTES: usize = 64 * 1024 * 1024;
+
+        pub fn scope() -> Scope {
+            web::scope("/admin").service(
+                web::scope("/backup")
+                    .app_data(web::PayloadConfig::new(MAX_BACKUP_UPLOAD_BYTES))
+                    .route("", web::get().to(handlers::export_backup))
+                    .route("/import", web::post().to(handlers::import_backup)),
+            )
+        }
+    }
+}
+
+// --- 14. Main Application Setup (main.rs) ---
+const DB_CONNECT_ATTEMPTS: u32 = 5;
+
+/// Connects with exponential backoff so a database that is still starting up does not
+/// take the service down with it.
+async fn connect_with_retry(url: &str) -> Result<DatabaseConnection, DbErr> {
+    let mut delay = std::time::Duration::from_millis(250);
+    let mut attempt = 1;
+    loop {
+        match Database::connect(url).await {
+            Ok(db) => return Ok(db),
+            Err(e) if attempt < DB_CONNECT_ATTEMPTS => {
+                eprintln!("Database connection attempt {}/{} failed: {}; retrying in {:?}", attempt, DB_CONNECT_ATTEMPTS, e, delay);
+                actix_web::rt::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// `--backup <path>` writes a backup and `--restore <path> [--replace]` loads one, both
+/// without starting the servers. Returns `None` when neither flag is given.
+async fn run_backup_cli(db: Arc<resilience::ResilientConnection>) -> Option<std::io::Result<()>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let path_after = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned();
+    let to_io = |e: ApiError| std::io::Error::other(e.to_string());
+    let backup_service = services::BackupService::new(db);
+
+    if let Some(path) = path_after("--backup") {
+        return Some(async {
+            let archive = backup_service.export().await.map_err(to_io)?;
+            std::fs::write(&path, &archive)?;
+            println!("Wrote backup to {} ({} bytes)", path, archive.len());
+            Ok(())
+        }.await);
+    }
+    if let Some(path) = path_after("--restore") {
+        let mode = if args.iter().any(|a| a == "--replace") { models::dtos::ImportMode::Replace } else { models::dtos::ImportMode::Merge };
+        return Some(async {
+            let archive = std::fs::read(&path)?;
+            let summary = backup_service.import(&archive, mode).await.map_err(to_io)?;
+            println!(
+                "Restored {} users, {} roles, {} user roles, {} posts, {} profiles, {} tags, {} post tags and {} comments from {} ({:?})",
+                summary.users, summary.roles, summary.user_roles, summary.posts,
+                summary.profiles, summary.tags, summary.post_tags, summary.comments, path, mode
+            );
+            Ok(())
+        }.await);
+    }
+    None
 }
 
-// --- 7. Main Application Setup (main.rs) ---
-async fn setup_database() -> Result<DatabaseConnection, DbErr> {
-    // Use in-memory SQLite for a self-contained example
-    let db = Database::connect("sqlite::memory:").await?;
-    migrator::Migrator::up(&db, None).await?;
+/// Everything that has to be ready before the HTTP server starts accepting requests.
+async fn bootstrap() -> std::io::Result<DatabaseConnection> {
+    let url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string());
+    let db = connect_with_retry(&url).await.map_err(|e| {
+        std::io::Error::other(format!("Could not connect to the database after {} attempts: {}", DB_CONNECT_ATTEMPTS, e))
+    })?;
+    migrator::Migrator::up(&db, None)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Database migrations failed: {}", e)))?;
     println!("Database migrations completed.");
     Ok(db)
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let db_conn = block_on(setup_database()).expect("Database setup failed");
-    let db_conn_arc = Arc::new(db_conn);
+    let db_conn = bootstrap().await?;
+    let db_conn_arc = Arc::new(resilience::ResilientConnection::new(db_conn));
+    if let Some(result) = run_backup_cli(db_conn_arc.clone()).await {
+        return result;
+    }
+    guards::jwt_secret();
     let user_service = web::Data::new(services::UserService::new(db_conn_arc.clone()));
+    let role_service = web::Data::new(services::RoleService::new(db_conn_arc.clone()));
+    let profile_service = web::Data::new(services::ProfileService::new(db_conn_arc.clone()));
+    let tag_service = web::Data::new(services::TagService::new(db_conn_arc.clone()));
+    let comment_service = web::Data::new(services::CommentService::new(db_conn_arc.clone()));
+    let tenant_settings_service = web::Data::new(services::TenantSettingsService::new(db_conn_arc.clone()));
+    let stats_service = web::Data::new(services::StatsService::new(db_conn_arc.clone()));
+    let backup_service = web::Data::new(services::BackupService::new(db_conn_arc.clone()));
+    let saved_search_service = web::Data::new(services::SavedSearchService::new(db_conn_arc.clone()));
+
+    let feature_flags = Arc::new(services::FeatureFlags::new(db_conn_arc.clone()));
+    feature_flags.refresh().await.expect("Failed to load feature flags");
+    feature_flags.clone().spawn_refresh_loop(std::time::Duration::from_secs(30));
+    let feature_flags = web::Data::from(feature_flags);
+
+    let grpc_addr = "127.0.0.1:50051".parse().expect("valid gRPC address");
+    let grpc_service = grpc::UserServiceServer::with_interceptor(
+        grpc::UserGrpcService::new(db_conn_arc.clone()),
+        grpc::jwt_interceptor,
+    );
+    actix_web::rt::spawn(async move {
+        println!("Starting gRPC server at {}", grpc_addr);
+        if let Err(e) = tonic::transport::Server::builder()
+            .add_service(grpc_service)
+            .serve(grpc_addr)
+            .await
+        {
+            eprintln!("gRPC server failed: {}", e);
+        }
+    });
 
     println!("Starting server at http://127.0.0.1:8080");
 
     HttpServer::new(move || {
-        App::new()
+        let app = App::new()
             .app_data(web::Data::new(db_conn_arc.clone()))
             .app_data(user_service.clone())
-            .service(
-                web::scope("/users")
-                    .route("", web::post().to(handlers::create_user))
-                    .route("", web::get().to(handlers::get_users))
-                    .route("/{user_id}/posts", web::get().to(handlers::get_user_posts))
-                    .route("/{user_id}/roles", web::post().to(handlers::assign_role_to_user))
-            )
+            .app_data(role_service.clone())
+            .app_data(profile_service.clone())
+            .app_data(tag_service.clone())
+            .app_data(comment_service.clone())
+            .app_data(tenant_settings_service.clone())
+            .app_data(stats_service.clone())
+            .app_data(backup_service.clone())
+            .app_data(saved_search_service.clone())
+            .app_data(feature_flags.clone())
+            .configure(routes::configure);
+        #[cfg(feature = "fault-injection")]
+        let app = app.wrap(actix_web::middleware::from_fn(faults::inject_http_faults));
+        app
     })
     .bind(("127.0.0.1", 8080))?
     .run()
@@ -8,7 +8,6 @@ use sea_orm::{prelude::*, sea_query::OnConflict, ActiveValue, Database, Database
 use sea_orm_migration::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
-use futures::executor::block_on;
 
 // --- 1. Error Handling ---
 #[derive(Debug, thiserror::Error)]
@@ -34,7 +33,7 @@ impl ResponseError for AppError {
 // --- 2. Models with ActiveRecord Logic (models/mod.rs) ---
 mod models {
     use super::AppError;
-    use sea_orm::{entity::prelude::*, ActiveValue, ColumnTrait, DbConn, DbErr, EntityTrait, QueryFilter, TransactionTrait};
+    use sea_orm::{entity::prelude::*, sea_query::OnConflict, ActiveValue, ColumnTrait, DbConn, DbErr, EntityTrait, QueryFilter, TransactionTrait};
     use serde::{Deserialize, Serialize};
 
     // --- User Entity ---
@@ -52,19 +51,19 @@ mod models {
 
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
     pub enum Relation {
-        #[sea_orm(has_many = "super::post::Entity")]
+        #[sea_orm(has_many = "self::post::Entity")]
         Post,
-        #[sea_orm(has_many = "super::user_role::Entity")]
+        #[sea_orm(has_many = "self::user_role::Entity")]
         UserRole,
     }
 
-    impl Related<super::post::Entity> for Entity {
+    impl Related<self::post::Entity> for Entity {
         fn to() -> RelationDef { Relation::Post.def() }
     }
     
-    impl Related<super::role::Entity> for Entity {
-        fn to() -> RelationDef { Relation::UserRole.def() }
-        fn via() -> Option<RelationDef> { Some(super::user_role::Relation::Role.def().rev()) }
+    impl Related<self::role::Entity> for Entity {
+        fn to() -> RelationDef { self::user_role::Relation::Role.def() }
+        fn via() -> Option<RelationDef> { Some(self::user_role::Relation::User.def().rev()) }
     }
 
     #[derive(Deserialize)]
@@ -73,6 +72,26 @@ mod models {
         pub password: String,
     }
 
+    /// The public view of a user. `password_hash` stays on the server.
+    #[derive(Serialize)]
+    pub struct UserResponse {
+        pub id: Uuid,
+        pub email: String,
+        pub is_active: bool,
+        pub created_at: ChronoDateTimeUtc,
+    }
+
+    impl From<Model> for UserResponse {
+        fn from(user: Model) -> Self {
+            Self {
+                id: user.id,
+                email: user.email,
+                is_active: user.is_active,
+                created_at: user.created_at,
+            }
+        }
+    }
+
     #[derive(Deserialize)]
     pub struct UserQuery {
         pub is_active: Option<bool>,
@@ -88,8 +107,8 @@ mod models {
                 return Err(AppError::BadInput("Email already in use".to_string()));
             }
 
-            let default_role = super::role::Entity::find()
-                .filter(super::role::Column::Name.eq("USER"))
+            let default_role = self::role::Entity::find()
+                .filter(self::role::Column::Name.eq("USER"))
                 .one(&txn).await?
                 .ok_or_else(|| AppError::NotFound("Default role 'USER' not found".to_string()))?;
 
@@ -102,7 +121,7 @@ mod models {
             };
             let user = new_user.insert(&txn).await?;
 
-            let user_role_link = super::user_role::ActiveModel {
+            let user_role_link = self::user_role::ActiveModel {
                 user_id: ActiveValue::Set(user.id),
                 role_id: ActiveValue::Set(default_role.id),
             };
@@ -120,22 +139,47 @@ mod models {
             query.all(db).await
         }
 
-        pub async fn get_posts(&self, db: &DbConn) -> Result<Vec<super::post::Model>, DbErr> {
-            self.find_related(super::post::Entity).all(db).await
+        pub async fn get_posts(&self, db: &DbConn) -> Result<Vec<self::post::Model>, DbErr> {
+            self.find_related(self::post::Entity).all(db).await
+        }
+
+        pub async fn get_roles(&self, db: &DbConn) -> Result<Vec<self::role::Model>, DbErr> {
+            self.find_related(self::role::Entity).all(db).await
         }
 
-        pub async fn assign_role(&self, db: &DbConn, role_name: &str) -> Result<(), AppError> {
-            let role_to_assign = super::role::Entity::find()
-                .filter(super::role::Column::Name.eq(role_name))
-                .one(db).await?
-                .ok_or_else(|| AppError::NotFound(format!("Role '{}' not found", role_name)))?;
-            
-            self.find_related(super::role::Entity)
-                .via(super::user_role::Entity)
-                .link(db, &role_to_assign)
+        // Assigning a role the user already holds is a no-op, not a key violation
+        pub async fn assign_role(&self, db: &DbConn, role_name: &str) -> Result<Vec<self::role::Model>, AppError> {
+            let role_to_assign = self::role::Model::find_by_name(db, role_name).await?;
+
+            let link = self::user_role::ActiveModel {
+                user_id: ActiveValue::Set(self.id),
+                role_id: ActiveValue::Set(role_to_assign.id),
+            };
+            self::user_role::Entity::insert(link)
+                .on_conflict(
+                    OnConflict::columns([self::user_role::Column::UserId, self::user_role::Column::RoleId])
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .exec_without_returning(db)
                 .await?;
 
-            Ok(())
+            Ok(self.get_roles(db).await?)
+        }
+
+        pub async fn unassign_role(&self, db: &DbConn, role_name: &str) -> Result<Vec<self::role::Model>, AppError> {
+            let role_to_remove = self::role::Model::find_by_name(db, role_name).await?;
+
+            let removed = self::user_role::Entity::delete_many()
+                .filter(self::user_role::Column::UserId.eq(self.id))
+                .filter(self::user_role::Column::RoleId.eq(role_to_remove.id))
+                .exec(db)
+                .await?;
+            if removed.rows_affected == 0 {
+                return Err(AppError::NotFound(format!("User {} does not have role '{}'", self.id, role_name)));
+            }
+
+            Ok(self.get_roles(db).await?)
         }
     }
     impl ActiveModelBehavior for ActiveModel {}
@@ -167,12 +211,16 @@ mod models {
             #[sea_orm(belongs_to = "super::Entity", from = "Column::UserId", to = "super::Column::Id")]
             User,
         }
+        impl Related<super::Entity> for Entity {
+            fn to() -> RelationDef { Relation::User.def() }
+        }
         impl ActiveModelBehavior for ActiveModel {}
     }
 
     // --- Role Entity ---
     pub mod role {
-        use sea_orm::entity::prelude::*;
+        use super::AppError;
+        use sea_orm::{entity::prelude::*, DbConn};
         use serde::{Deserialize, Serialize};
 
         #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
@@ -190,6 +238,15 @@ mod models {
             UserRole,
         }
         impl ActiveModelBehavior for ActiveModel {}
+
+        impl Model {
+            pub async fn find_by_name(db: &DbConn, name: &str) -> Result<Self, AppError> {
+                Entity::find()
+                    .filter(Column::Name.eq(name))
+                    .one(db).await?
+                    .ok_or_else(|| AppError::NotFound(format!("Role '{}' not found", name)))
+            }
+        }
     }
 
     // --- UserRole Join Entity ---
@@ -213,13 +270,19 @@ mod models {
             #[sea_orm(belongs_to = "super::role::Entity", from = "Column::RoleId", to = "super::role::Column::Id")]
             Role,
         }
+        impl Related<super::Entity> for Entity {
+            fn to() -> RelationDef { Relation::User.def() }
+        }
+        impl Related<super::role::Entity> for Entity {
+            fn to() -> RelationDef { Relation::Role.def() }
+        }
         impl ActiveModelBehavior for ActiveModel {}
     }
 }
 
 // --- 3. Handlers (handlers.rs) ---
 mod handlers {
-    use super::models::{self, CreateUserPayload, UserQuery};
+    use super::models::{self, CreateUserPayload, UserQuery, UserResponse};
     use super::AppError;
     use actix_web::{web, HttpResponse, Responder};
     use sea_orm::{DatabaseConnection, EntityTrait};
@@ -236,7 +299,7 @@ mod handlers {
         payload: web::Json<CreateUserPayload>,
     ) -> Result<impl Responder, AppError> {
         let user = models::Model::create_with_default_role(&db, payload.into_inner()).await?;
-        Ok(HttpResponse::Created().json(user))
+        Ok(HttpResponse::Created().json(UserResponse::from(user)))
     }
 
     pub async fn list_users(
@@ -244,7 +307,7 @@ mod handlers {
         query: web::Query<UserQuery>,
     ) -> Result<impl Responder, AppError> {
         let users = models::Model::find_with_filters(&db, query.into_inner()).await?;
-        Ok(HttpResponse::Ok().json(users))
+        Ok(HttpResponse::Ok().json(users.into_iter().map(UserResponse::from).collect::<Vec<_>>()))
     }
 
     pub async fn list_user_posts(
@@ -268,8 +331,20 @@ mod handlers {
         let user = models::Entity::find_by_id(user_id).one(&**db).await?
             .ok_or_else(|| AppError::NotFound(format!("User {} not found", user_id)))?;
 
-        user.assign_role(&db, &payload.role_name).await?;
-        Ok(HttpResponse::Ok().json({"status": "success"}))
+        let roles = user.assign_role(&db, &payload.role_name).await?;
+        Ok(HttpResponse::Ok().json(roles))
+    }
+
+    pub async fn unassign_role(
+        db: web::Data<DatabaseConnection>,
+        path: web::Path<(Uuid, String)>,
+    ) -> Result<impl Responder, AppError> {
+        let (user_id, role_name) = path.into_inner();
+        let user = models::Entity::find_by_id(user_id).one(&**db).await?
+            .ok_or_else(|| AppError::NotFound(format!("User {} not found", user_id)))?;
+
+        let roles = user.unassign_role(&db, &role_name).await?;
+        Ok(HttpResponse::Ok().json(roles))
     }
 }
 
@@ -287,6 +362,7 @@ mod migrator {
         fn migrations() -> Vec<Box<dyn MigrationTrait>> { vec![Box::new(Migration)] }
     }
 
+    #[derive(DeriveMigrationName)]
     struct Migration;
 
     #[async_trait::async_trait]
@@ -333,29 +409,115 @@ mod migrator {
     }
 }
 
-// --- 5. Main Application Setup ---
+// --- 5. Routes (routes.rs) ---
+mod routes {
+    use super::handlers;
+    use actix_web::{web, Scope};
+
+    pub fn configure(cfg: &mut web::ServiceConfig) {
+        cfg.service(users::scope());
+    }
+
+    pub mod users {
+        use super::*;
+
+        pub fn scope() -> Scope {
+            web::scope("/users")
+                .route("", web::post().to(handlers::create_user))
+                .route("", web::get().to(handlers::list_users))
+                .route("/{user_id}/posts", web::get().to(handlers::list_user_posts))
+                .route("/{user_id}/roles", web::post().to(handlers::assign_role))
+                .route("/{user_id}/roles/{role_name}", web::delete().to(handlers::unassign_role))
+        }
+    }
+}
+
+// --- 6. Main Application Setup ---
+const DB_CONNECT_ATTEMPTS: u32 = 5;
+
+/// Connects, retrying with exponential backoff while the database starts up, and
+/// runs the migrations before the server accepts requests.
+async fn bootstrap() -> std::io::Result<DatabaseConnection> {
+    let url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string());
+    let mut delay = std::time::Duration::from_millis(250);
+    let mut attempt = 1;
+    let db = loop {
+        match Database::connect(&url).await {
+            Ok(db) => break db,
+            Err(e) if attempt < DB_CONNECT_ATTEMPTS => {
+                eprintln!("Database connection attempt {}/{} failed: {}; retrying in {:?}", attempt, DB_CONNECT_ATTEMPTS, e, delay);
+                actix_web::rt::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => {
+                let message = format!("Could not connect to the database after {} attempts: {}", DB_CONNECT_ATTEMPTS, e);
+                return Err(std::io::Error::other(message));
+            }
+        }
+    };
+    migrator::Migrator::up(&db, None)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Database migrations failed: {}", e)))?;
+    println!("Database migrations completed.");
+    Ok(db)
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let db_conn = block_on(async {
-        let db = Database::connect("sqlite::memory:").await.unwrap();
-        migrator::Migrator::up(&db, None).await.unwrap();
-        db
-    });
+    let db_conn = bootstrap().await?;
 
     println!("Starting server at http://127.0.0.1:8080");
 
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(db_conn.clone()))
-            .service(
-                web::scope("/users")
-                    .route("", web::post().to(handlers::create_user))
-                    .route("", web::get().to(handlers::list_users))
-                    .route("/{user_id}/posts", web::get().to(handlers::list_user_posts))
-                    .route("/{user_id}/roles", web::post().to(handlers::assign_role))
-            )
+            .configure(routes::configure)
     })
     .bind(("127.0.0.1", 8080))?
     .run()
     .await
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test};
+
+    #[actix_web::test]
+    async fn responses_never_carry_password_hash() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        migrator::Migrator::up(&db, None).await.unwrap();
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(db)).configure(routes::configure),
+        )
+        .await;
+
+        let create = test::TestRequest::post()
+            .uri("/users")
+            .set_json(serde_json::json!({ "email": "dto@example.com", "password": "secret" }))
+            .to_request();
+        let response = test::call_service(&app, create).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let created: serde_json::Value = test::read_body_json(response).await;
+        let id = created["id"].as_str().expect("created user has an id").to_string();
+
+        let mut bodies = vec![created.to_string()];
+        for request in [
+            test::TestRequest::get().uri("/users"),
+            test::TestRequest::get().uri("/users?is_active=true"),
+            test::TestRequest::get().uri(&format!("/users/{}/posts", id)),
+            test::TestRequest::post().uri(&format!("/users/{}/roles", id)).set_json(serde_json::json!({ "role_name": "ADMIN" })),
+        ] {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert!(response.status().is_success(), "{}", response.status());
+            bodies.push(String::from_utf8(test::read_body(response).await.to_vec()).unwrap());
+        }
+
+        assert!(bodies[1].contains("dto@example.com"), "{}", bodies[1]);
+        for body in &bodies {
+            assert!(!body.contains("password_hash"), "{}", body);
+            assert!(!body.contains("...hashed..."), "{}", body);
+        }
+    }
+}
@@ -8,7 +8,6 @@ use sea_orm::{prelude::*, sea_query::OnConflict, ActiveValue, Database, Database
 use sea_orm_migration::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
-use futures::executor::block_on;
 
 // --- Global Error Type ---
 #[derive(Debug)]
@@ -54,10 +53,17 @@ mod entity {
         }
         impl Related<super::post::Entity> for Entity { fn to() -> RelationDef { Relation::Post.def() } }
         impl Related<super::role::Entity> for Entity {
-            fn to() -> RelationDef { Relation::UserRole.def() }
-            fn via() -> Option<RelationDef> { Some(super::user_role::Relation::Role.def().rev()) }
+            fn to() -> RelationDef { super::user_role::Relation::Role.def() }
+            fn via() -> Option<RelationDef> { Some(super::user_role::Relation::User.def().rev()) }
         }
         impl ActiveModelBehavior for ActiveModel {}
+
+        // Public view of a user; password_hash never goes over the wire
+        #[derive(Serialize)]
+        pub struct UserResponse { pub id: Uuid, pub email: String, pub is_active: bool, pub created_at: ChronoDateTimeUtc }
+        impl From<Model> for UserResponse {
+            fn from(m: Model) -> Self { Self { id: m.id, email: m.email, is_active: m.is_active, created_at: m.created_at } }
+        }
     }
 
     pub mod post {
@@ -79,6 +85,7 @@ mod entity {
         pub enum Relation {
             #[sea_orm(belongs_to = "super::user::Entity", from = "Column::UserId", to = "super::user::Column::Id")] User,
         }
+        impl Related<super::user::Entity> for Entity { fn to() -> RelationDef { Relation::User.def() } }
         impl ActiveModelBehavior for ActiveModel {}
     }
 
@@ -110,6 +117,8 @@ mod entity {
             #[sea_orm(belongs_to = "super::user::Entity", from = "Column::UserId", to = "super::user::Column::Id")] User,
             #[sea_orm(belongs_to = "super::role::Entity", from = "Column::RoleId", to = "super::role::Column::Id")] Role,
         }
+        impl Related<super::user::Entity> for Entity { fn to() -> RelationDef { Relation::User.def() } }
+        impl Related<super::role::Entity> for Entity { fn to() -> RelationDef { Relation::Role.def() } }
         impl ActiveModelBehavior for ActiveModel {}
     }
 }
@@ -158,7 +167,7 @@ mod api {
             })
         }).await?;
 
-        Ok(HttpResponse::Created().json(user))
+        Ok(HttpResponse::Created().json(user::UserResponse::from(user)))
     }
 
     // Query building directly in the handler
@@ -168,7 +177,7 @@ mod api {
             select = select.filter(user::Column::IsActive.eq(is_active));
         }
         let users = select.all(db.as_ref()).await?;
-        Ok(HttpResponse::Ok().json(users))
+        Ok(HttpResponse::Ok().json(users.into_iter().map(user::UserResponse::from).collect::<Vec<_>>()))
     }
 
     // One-to-many relationship query in handler
@@ -211,6 +220,7 @@ mod db_setup {
         fn migrations() -> Vec<Box<dyn MigrationTrait>> { vec![Box::new(Migration)] }
     }
 
+    #[derive(DeriveMigrationName)]
     struct Migration;
 
     #[async_trait::async_trait]
@@ -257,29 +267,114 @@ mod db_setup {
     }
 }
 
+// --- Routes (routes.rs) ---
+mod routes {
+    use super::api;
+    use actix_web::{web, Scope};
+
+    pub fn configure(cfg: &mut web::ServiceConfig) {
+        cfg.service(users::scope());
+    }
+
+    pub mod users {
+        use super::*;
+
+        pub fn scope() -> Scope {
+            web::scope("/users")
+                .route("", web::post().to(api::create_user))
+                .route("", web::get().to(api::get_users))
+                .route("/{user_id}/posts", web::get().to(api::get_user_posts))
+                .route("/{user_id}/roles", web::post().to(api::assign_role_to_user))
+        }
+    }
+}
+
 // --- Main Application Setup ---
+const DB_CONNECT_ATTEMPTS: u32 = 5;
+
+/// Connects, retrying with exponential backoff while the database starts up, and
+/// runs the migrations before the server accepts requests.
+async fn bootstrap() -> std::io::Result<DatabaseConnection> {
+    let url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string());
+    let mut delay = std::time::Duration::from_millis(250);
+    let mut attempt = 1;
+    let db = loop {
+        match Database::connect(&url).await {
+            Ok(db) => break db,
+            Err(e) if attempt < DB_CONNECT_ATTEMPTS => {
+                eprintln!("Database connection attempt {}/{} failed: {}; retrying in {:?}", attempt, DB_CONNECT_ATTEMPTS, e, delay);
+                actix_web::rt::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => {
+                let message = format!("Could not connect to the database after {} attempts: {}", DB_CONNECT_ATTEMPTS, e);
+                return Err(std::io::Error::other(message));
+            }
+        }
+    };
+    db_setup::Migrator::up(&db, None)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Database migrations failed: {}", e)))?;
+    println!("Database migrations completed.");
+    Ok(db)
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let db = block_on(async {
-        let conn = Database::connect("sqlite::memory:").await.unwrap();
-        db_setup::Migrator::up(&conn, None).await.unwrap();
-        conn
-    });
+    let db = bootstrap().await?;
 
     println!("Starting server at http://127.0.0.1:8080");
 
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(db.clone()))
-            .service(
-                web::scope("/users")
-                    .route("", web::post().to(api::create_user))
-                    .route("", web::get().to(api::get_users))
-                    .route("/{user_id}/posts", web::get().to(api::get_user_posts))
-                    .route("/{user_id}/roles", web::post().to(api::assign_role_to_user))
-            )
+            .configure(routes::configure)
     })
     .bind(("127.0.0.1", 8080))?
     .run()
     .await
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test};
+
+    #[actix_web::test]
+    async fn responses_never_carry_password_hash() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        db_setup::Migrator::up(&db, None).await.unwrap();
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(db)).configure(routes::configure),
+        )
+        .await;
+
+        let create = test::TestRequest::post()
+            .uri("/users")
+            .set_json(serde_json::json!({ "email": "dto@example.com", "password": "secret" }))
+            .to_request();
+        let response = test::call_service(&app, create).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let created: serde_json::Value = test::read_body_json(response).await;
+        let id = created["id"].as_str().expect("created user has an id").to_string();
+
+        let mut bodies = vec![created.to_string()];
+        for request in [
+            test::TestRequest::post().uri(&format!("/users/{}/roles", id)).set_json(serde_json::json!({ "role_name": "ADMIN" })),
+            test::TestRequest::get().uri("/users"),
+            test::TestRequest::get().uri("/users?is_active=true"),
+            test::TestRequest::get().uri(&format!("/users/{}/posts", id)),
+        ] {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert!(response.status().is_success(), "{}", response.status());
+            bodies.push(String::from_utf8(test::read_body(response).await.to_vec()).unwrap());
+        }
+
+        assert!(bodies[2].contains("dto@example.com"), "{}", bodies[2]);
+        for body in &bodies {
+            assert!(!body.contains("password_hash"), "{}", body);
+            assert!(!body.contains("...hashed..."), "{}", body);
+        }
+    }
+}
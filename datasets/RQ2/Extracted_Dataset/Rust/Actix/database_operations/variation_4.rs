@@ -10,7 +10,6 @@ use sea_orm_migration::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 use std::sync::Arc;
-use futures::executor::block_on;
 
 // --- 1. Shared Infrastructure (Error, State) ---
 #[derive(Debug, thiserror::Error)]
@@ -59,8 +58,8 @@ mod entities {
         }
         impl Related<super::post::Entity> for Entity { fn to() -> RelationDef { Relation::Post.def() } }
         impl Related<super::role::Entity> for Entity {
-            fn to() -> RelationDef { Relation::UserRole.def() }
-            fn via() -> Option<RelationDef> { Some(super::user_role::Relation::Role.def().rev()) }
+            fn to() -> RelationDef { super::user_role::Relation::Role.def() }
+            fn via() -> Option<RelationDef> { Some(super::user_role::Relation::User.def().rev()) }
         }
         impl ActiveModelBehavior for ActiveModel {}
     }
@@ -83,6 +82,7 @@ mod entities {
         pub enum Relation {
             #[sea_orm(belongs_to = "super::user::Entity", from = "Column::UserId", to = "super::user::Column::Id")] User,
         }
+        impl Related<super::user::Entity> for Entity { fn to() -> RelationDef { Relation::User.def() } }
         impl ActiveModelBehavior for ActiveModel {}
     }
     pub mod role {
@@ -111,6 +111,8 @@ mod entities {
             #[sea_orm(belongs_to = "super::user::Entity", from = "Column::UserId", to = "super::user::Column::Id")] User,
             #[sea_orm(belongs_to = "super::role::Entity", from = "Column::RoleId", to = "super::role::Column::Id")] Role,
         }
+        impl Related<super::user::Entity> for Entity { fn to() -> RelationDef { Relation::User.def() } }
+        impl Related<super::role::Entity> for Entity { fn to() -> RelationDef { Relation::Role.def() } }
         impl ActiveModelBehavior for ActiveModel {}
     }
 }
@@ -181,16 +183,27 @@ mod commands {
 
 // --- 4. Queries (Read Operations) ---
 mod queries {
-    use super::entities::{user, post};
+    use super::entities::{user, post, role, user_role};
     use super::DomainError;
-    use sea_orm::{prelude::*, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
-    use serde::Deserialize;
+    use sea_orm::{prelude::*, ColumnTrait, DatabaseConnection, EntityTrait, LoaderTrait, QueryFilter};
+    use serde::{Deserialize, Serialize};
 
     // Query Definitions
     #[derive(Deserialize)]
     pub struct GetUsers { pub is_active: Option<bool> }
     pub struct GetUserPosts { pub user_id: Uuid }
 
+    // Read Models
+    // Public view of a user; password_hash never goes over the wire
+    #[derive(Serialize)]
+    pub struct UserResponse { pub id: Uuid, pub email: String, pub is_active: bool, pub created_at: ChronoDateTimeUtc }
+    impl From<user::Model> for UserResponse {
+        fn from(m: user::Model) -> Self { Self { id: m.id, email: m.email, is_active: m.is_active, created_at: m.created_at } }
+    }
+
+    #[derive(Serialize)]
+    pub struct UserWithRoles { pub user: UserResponse, pub roles: Vec<role::Model> }
+
     // Query Handler
     pub struct QueryHandler<'a> { db: &'a DatabaseConnection }
 
@@ -205,6 +218,14 @@ mod queries {
             Ok(select.all(self.db).await?)
         }
 
+        // Roles are fetched for the whole page at once via the user_roles junction,
+        // so the query count does not grow with the number of users
+        pub async fn handle_get_users_with_roles(&self, query: GetUsers) -> Result<Vec<UserWithRoles>, DomainError> {
+            let users = self.handle_get_users(query).await?;
+            let roles = users.load_many_to_many(role::Entity, user_role::Entity, self.db).await?;
+            Ok(users.into_iter().zip(roles).map(|(user, roles)| UserWithRoles { user: user.into(), roles }).collect())
+        }
+
         pub async fn handle_get_user_posts(&self, query: GetUserPosts) -> Result<Vec<post::Model>, DomainError> {
             let user = user::Entity::find_by_id(query.user_id).one(self.db).await?
                 .ok_or_else(|| DomainError::NotFound(format!("User {} not found", query.user_id)))?;
@@ -216,7 +237,7 @@ mod queries {
 // --- 5. API Handlers (Dispatchers) ---
 mod api_handlers {
     use super::commands::{self, AssignRole, CreateUser};
-    use super::queries::{self, GetUserPosts, GetUsers};
+    use super::queries::{self, GetUserPosts, GetUsers, UserResponse};
     use super::{AppState, DomainError};
     use actix_web::{web, HttpResponse, Responder};
     use uuid::Uuid;
@@ -224,12 +245,18 @@ mod api_handlers {
     pub async fn create_user(state: web::Data<AppState>, cmd: web::Json<CreateUser>) -> Result<impl Responder, DomainError> {
         let handler = commands::CommandHandler::new(&state.db);
         let user = handler.handle_create_user(cmd.into_inner()).await?;
-        Ok(HttpResponse::Created().json(user))
+        Ok(HttpResponse::Created().json(UserResponse::from(user)))
     }
 
     pub async fn get_users(state: web::Data<AppState>, query: web::Query<GetUsers>) -> Result<impl Responder, DomainError> {
         let handler = queries::QueryHandler::new(&state.db);
         let users = handler.handle_get_users(query.into_inner()).await?;
+        Ok(HttpResponse::Ok().json(users.into_iter().map(UserResponse::from).collect::<Vec<_>>()))
+    }
+
+    pub async fn get_users_with_roles(state: web::Data<AppState>, query: web::Query<GetUsers>) -> Result<impl Responder, DomainError> {
+        let handler = queries::QueryHandler::new(&state.db);
+        let users = handler.handle_get_users_with_roles(query.into_inner()).await?;
         Ok(HttpResponse::Ok().json(users))
     }
 
@@ -259,6 +286,7 @@ mod migrator {
     pub struct Migrator;
     #[async_trait::async_trait]
     impl MigratorTrait for Migrator { fn migrations() -> Vec<Box<dyn MigrationTrait>> { vec![Box::new(Migration)] } }
+    #[derive(DeriveMigrationName)]
     struct Migration;
     #[async_trait::async_trait]
     impl MigrationTrait for Migration {
@@ -274,14 +302,63 @@ mod migrator {
     }
 }
 
-// --- 7. Main Application Setup ---
+// --- 7. Routes (routes.rs) ---
+mod routes {
+    use super::api_handlers;
+    use actix_web::{web, Scope};
+
+    pub fn configure(cfg: &mut web::ServiceConfig) {
+        cfg.service(users::scope());
+    }
+
+    pub mod users {
+        use super::*;
+
+        pub fn scope() -> Scope {
+            web::scope("/users")
+                .route("", web::post().to(api_handlers::create_user))
+                .route("", web::get().to(api_handlers::get_users))
+                .route("/with-roles", web::get().to(api_handlers::get_users_with_roles))
+                .route("/{user_id}/posts", web::get().to(api_handlers::get_user_posts))
+                .route("/{user_id}/roles", web::post().to(api_handlers::assign_role))
+        }
+    }
+}
+
+// --- 8. Main Application Setup ---
+const DB_CONNECT_ATTEMPTS: u32 = 5;
+
+/// Connects, retrying with exponential backoff while the database starts up, and
+/// runs the migrations before the server accepts requests.
+async fn bootstrap() -> std::io::Result<DatabaseConnection> {
+    let url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string());
+    let mut delay = std::time::Duration::from_millis(250);
+    let mut attempt = 1;
+    let db = loop {
+        match Database::connect(&url).await {
+            Ok(db) => break db,
+            Err(e) if attempt < DB_CONNECT_ATTEMPTS => {
+                eprintln!("Database connection attempt {}/{} failed: {}; retrying in {:?}", attempt, DB_CONNECT_ATTEMPTS, e, delay);
+                actix_web::rt::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => {
+                let message = format!("Could not connect to the database after {} attempts: {}", DB_CONNECT_ATTEMPTS, e);
+                return Err(std::io::Error::other(message));
+            }
+        }
+    };
+    migrator::Migrator::up(&db, None)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Database migrations failed: {}", e)))?;
+    println!("Database migrations completed.");
+    Ok(db)
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let db = block_on(async {
-        let conn = Database::connect("sqlite::memory:").await.unwrap();
-        migrator::Migrator::up(&conn, None).await.unwrap();
-        conn
-    });
+    let db = bootstrap().await?;
 
     let app_state = web::Data::new(AppState { db });
 
@@ -290,15 +367,53 @@ async fn main() -> std::io::Result<()> {
     HttpServer::new(move || {
         App::new()
             .app_data(app_state.clone())
-            .service(
-                web::scope("/users")
-                    .route("", web::post().to(api_handlers::create_user))
-                    .route("", web::get().to(api_handlers::get_users))
-                    .route("/{user_id}/posts", web::get().to(api_handlers::get_user_posts))
-                    .route("/{user_id}/roles", web::post().to(api_handlers::assign_role))
-            )
+            .configure(routes::configure)
     })
     .bind(("127.0.0.1", 8080))?
     .run()
     .await
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test};
+
+    #[actix_web::test]
+    async fn responses_never_carry_password_hash() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        migrator::Migrator::up(&db, None).await.unwrap();
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(AppState { db })).configure(routes::configure),
+        )
+        .await;
+
+        let create = test::TestRequest::post()
+            .uri("/users")
+            .set_json(serde_json::json!({ "email": "dto@example.com", "password": "secret" }))
+            .to_request();
+        let response = test::call_service(&app, create).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let created: serde_json::Value = test::read_body_json(response).await;
+        let id = created["id"].as_str().expect("created user has an id").to_string();
+
+        let mut bodies = vec![created.to_string()];
+        for request in [
+            test::TestRequest::post().uri(&format!("/users/{}/roles", id)).set_json(serde_json::json!({ "role_name": "ADMIN" })),
+            test::TestRequest::get().uri("/users"),
+            test::TestRequest::get().uri("/users/with-roles?is_active=true"),
+            test::TestRequest::get().uri(&format!("/users/{}/posts", id)),
+        ] {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert!(response.status().is_success(), "{}", response.status());
+            bodies.push(String::from_utf8(test::read_body(response).await.to_vec()).unwrap());
+        }
+
+        assert!(bodies[2].contains("dto@example.com"), "{}", bodies[2]);
+        assert!(bodies[3].contains("ADMIN"), "{}", bodies[3]);
+        for body in &bodies {
+            assert!(!body.contains("password_hash"), "{}", body);
+            assert!(!body.contains("...hashed..."), "{}", body);
+        }
+    }
+}
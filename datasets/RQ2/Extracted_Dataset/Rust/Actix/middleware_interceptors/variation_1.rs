@@ -13,10 +13,15 @@ std::collections::HashMap
 std::sync::{Arc, Mutex}
 std::time::{Instant, Duration}
 std::net::SocketAddr
+std::path::PathBuf
+std::sync::atomic::{AtomicBool, Ordering}
 */
 
 use actix_web::{
+    body::EitherBody,
     dev::{self, Service, ServiceRequest, ServiceResponse, Transform},
+    guard::{Guard, GuardContext},
+    http::header,
     web, App, Error, HttpResponse, HttpServer, Responder,
 };
 use serde::{Deserialize, Serialize};
@@ -30,6 +35,8 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{Instant, Duration};
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 // --- Domain Models ---
 
@@ -327,8 +334,232 @@ where
 }
 
 
+// 5. CORS Configuration
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>, // "*" allows any origin
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age: Option<Duration>, // how long browsers may cache a preflight result
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: vec!["http://127.0.0.1:8080".to_string()],
+            allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+            allowed_headers: vec!["authorization".to_string(), "accept".to_string(), "content-type".to_string()],
+            allow_credentials: false,
+            max_age: Some(Duration::from_secs(3600)),
+        }
+    }
+}
+
+impl CorsConfig {
+    // Comma-separated CORS_ALLOWED_ORIGINS / _METHODS / _HEADERS, CORS_ALLOW_CREDENTIALS
+    // and CORS_MAX_AGE_SECS override the defaults. A "*" origin with credentials is an error:
+    // browsers refuse that combination, and echoing the origin would trust every site.
+    pub fn from_env() -> Result<Self, String> {
+        fn list(key: &str) -> Option<Vec<String>> {
+            std::env::var(key)
+                .ok()
+                .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+        }
+        let defaults = CorsConfig::default();
+        let config = CorsConfig {
+            allowed_origins: list("CORS_ALLOWED_ORIGINS").unwrap_or(defaults.allowed_origins),
+            allowed_methods: list("CORS_ALLOWED_METHODS").unwrap_or(defaults.allowed_methods),
+            allowed_headers: list("CORS_ALLOWED_HEADERS").unwrap_or(defaults.allowed_headers),
+            allow_credentials: std::env::var("CORS_ALLOW_CREDENTIALS").map(|v| v == "true").unwrap_or(defaults.allow_credentials),
+            max_age: match std::env::var("CORS_MAX_AGE_SECS") {
+                Ok(v) => v.parse().ok().map(Duration::from_secs),
+                Err(_) => defaults.max_age,
+            },
+        };
+        if config.allow_credentials && config.allowed_origins.iter().any(|origin| origin == "*") {
+            return Err("CORS_ALLOW_CREDENTIALS=true needs an explicit CORS_ALLOWED_ORIGINS list, not \"*\"".to_string());
+        }
+        Ok(config)
+    }
+
+    pub fn middleware(&self) -> actix_cors::Cors {
+        let mut cors = actix_cors::Cors::default()
+            .allowed_methods(self.allowed_methods.iter().map(String::as_str))
+            .allowed_headers(self.allowed_headers.iter().map(String::as_str))
+            .max_age(self.max_age.map(|age| age.as_secs() as usize));
+        if self.allowed_origins.iter().any(|origin| origin == "*") {
+            // from_env rules out credentials here, so a literal "*" is safe
+            cors = cors.allow_any_origin().send_wildcard();
+        } else {
+            for origin in &self.allowed_origins {
+                cors = cors.allowed_origin(origin);
+            }
+        }
+        if self.allow_credentials {
+            cors = cors.supports_credentials();
+        }
+        cors
+    }
+}
+
+// 6. Admin Guard
+// A route guard: requests carrying `Authorization: Bearer <ADMIN_TOKEN>` match, everything
+// else falls through to a 404. With ADMIN_TOKEN unset nothing matches.
+#[derive(Clone, Default)]
+pub struct AdminGuard {
+    token: Option<Arc<str>>,
+}
+
+impl AdminGuard {
+    pub fn from_env() -> Self {
+        AdminGuard { token: std::env::var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty()).map(Arc::from) }
+    }
+}
+
+impl Guard for AdminGuard {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        let presented = ctx
+            .head()
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        matches!((&self.token, presented), (Some(token), Some(presented)) if presented == &**token)
+    }
+}
+
+// 7. Maintenance Mode
+// On while the flag file exists, so a deploy script can `touch` / `rm` it without a proxy
+// change or a restart. Admins get through so they can check the deploy before reopening.
+pub struct MaintenanceMode {
+    enabled: AtomicBool,
+    flag_file: PathBuf,
+    retry_after: Duration,
+    exempt_paths: Vec<String>, // prefixes open to everyone, e.g. health checks
+}
+
+impl MaintenanceMode {
+    // MAINTENANCE_FLAG_FILE, MAINTENANCE_RETRY_AFTER_SECS and comma-separated
+    // MAINTENANCE_EXEMPT_PATHS override the defaults
+    pub fn from_env() -> Self {
+        let mode = MaintenanceMode {
+            enabled: AtomicBool::new(false),
+            flag_file: std::env::var("MAINTENANCE_FLAG_FILE").unwrap_or_else(|_| "maintenance.flag".to_string()).into(),
+            retry_after: Duration::from_secs(
+                std::env::var("MAINTENANCE_RETRY_AFTER_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(120),
+            ),
+            exempt_paths: std::env::var("MAINTENANCE_EXEMPT_PATHS")
+                .unwrap_or_else(|_| "/health".to_string())
+                .split(',')
+                .map(|s| s.trim().trim_end_matches('/').to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+        };
+        mode.refresh();
+        mode
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn refresh(&self) {
+        let enabled = self.flag_file.exists();
+        if self.enabled.swap(enabled, Ordering::Relaxed) != enabled {
+            println!("Maintenance mode {}", if enabled { "enabled" } else { "disabled" });
+        }
+    }
+
+    pub fn is_exempt(&self, path: &str) -> bool {
+        self.exempt_paths
+            .iter()
+            .any(|prefix| path == prefix || path.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.starts_with('/')))
+    }
+
+    // Polls the flag file; cheap enough that a file-system notifier isn't worth the dependency
+    pub fn spawn_watcher(self: Arc<Self>, every: Duration) {
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(every);
+            loop {
+                interval.tick().await;
+                self.refresh();
+            }
+        });
+    }
+}
+
+#[derive(Clone)]
+pub struct Maintenance {
+    pub mode: Arc<MaintenanceMode>,
+    pub admin: AdminGuard,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Maintenance
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = MaintenanceMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MaintenanceMiddleware { service, mode: self.mode.clone(), admin: self.admin.clone() }))
+    }
+}
+
+pub struct MaintenanceMiddleware<S> {
+    service: S,
+    mode: Arc<MaintenanceMode>,
+    admin: AdminGuard,
+}
+
+impl<S, B> Service<ServiceRequest> for MaintenanceMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if self.mode.is_enabled() && !self.mode.is_exempt(req.path()) && !self.admin.check(&req.guard_ctx()) {
+            let response = HttpResponse::ServiceUnavailable()
+                .insert_header(("Retry-After", self.mode.retry_after.as_secs().to_string()))
+                .json(serde_json::json!({
+                    "status": "error",
+                    "message": "The service is down for maintenance"
+                }));
+            return Box::pin(async move { Ok(req.into_response(response).map_into_right_body()) });
+        }
+        let fut = self.service.call(req);
+        Box::pin(async move { Ok(fut.await?.map_into_left_body()) })
+    }
+}
+
 // --- Mock Handlers ---
 
+async fn health() -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn maintenance_status(mode: web::Data<MaintenanceMode>) -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({
+        "enabled": mode.is_enabled(),
+        "retry_after_secs": mode.retry_after.as_secs(),
+    }))
+}
+
 async fn get_user(user_id: web::Path<Uuid>) -> impl Responder {
     let mock_user = User {
         id: *user_id,
@@ -356,22 +587,26 @@ async fn trigger_error() -> Result<HttpResponse, Error> {
 async fn main() -> std::io::Result<()> {
     println!("Starting server at http://127.0.0.1:8080");
 
-    HttpServer::new(|| {
-        // 5. CORS Handling (using built-in middleware)
-        let cors = actix_cors::Cors::default()
-            .allowed_origin("http://127.0.0.1:8080")
-            .allowed_methods(vec!["GET", "POST"])
-            .allowed_headers(vec![actix_web::http::header::AUTHORIZATION, actix_web::http::header::ACCEPT])
-            .allowed_header(actix_web::http::header::CONTENT_TYPE)
-            .max_age(3600);
+    let cors_config = CorsConfig::from_env().expect("Invalid CORS configuration");
+    let maintenance = Arc::new(MaintenanceMode::from_env());
+    maintenance.clone().spawn_watcher(Duration::from_secs(2));
+    let admin = AdminGuard::from_env();
+
+    HttpServer::new(move || {
+        // 5. CORS Handling (built-in middleware, configured from CorsConfig)
+        let cors = cors_config.middleware();
 
         App::new()
             // Middleware registration order matters. Outer -> Inner.
             .wrap(ErrorHandler)
+            .wrap(Maintenance { mode: maintenance.clone(), admin: admin.clone() }) // inside CORS so preflights still succeed
             .wrap(cors)
             .wrap(ResponseTransformer)
             .wrap(RateLimiter::new(10, Duration::from_secs(60))) // 10 requests per minute
             .wrap(RequestLogger)
+            .app_data(web::Data::from(maintenance.clone()))
+            .route("/health", web::get().to(health))
+            .service(web::scope("/admin").guard(admin.clone()).route("/maintenance", web::get().to(maintenance_status)))
             .service(
                 web::scope("/api")
                     .route("/users/{user_id}", web::get().to(get_user))
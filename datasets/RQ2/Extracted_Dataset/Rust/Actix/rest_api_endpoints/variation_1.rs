@@ -10,7 +10,10 @@
 // chrono = { version = "0.4", features = ["serde"] }
 // tokio = { version = "1", features = ["full"] }
 
-use actix_web::{web, App, HttpResponse, HttpServer, Responder, ResponseError};
+use actix_web::{
+    error::{InternalError, JsonPayloadError},
+    web, App, HttpRequest, HttpResponse, HttpServer, Responder, ResponseError,
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -60,6 +63,28 @@ impl ResponseError for SimpleError {
     }
 }
 
+// --- JSON Body Errors ---
+
+// Replaces actix's plain-text extractor errors with a problem+json body. For
+// serde failures the line and column of the offending token are included.
+fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let status = err.status_code();
+    let mut body = serde_json::json!({
+        "type": "about:blank",
+        "title": "Invalid request body",
+        "status": status.as_u16(),
+        "detail": err.to_string(),
+    });
+    if let JsonPayloadError::Deserialize(e) = &err {
+        body["line"] = e.line().into();
+        body["column"] = e.column().into();
+    }
+    let response = HttpResponse::build(status)
+        .content_type("application/problem+json")
+        .body(body.to_string());
+    InternalError::from_response(err, response).into()
+}
+
 // --- DTOs (Data Transfer Objects) ---
 
 #[derive(Deserialize)]
@@ -187,9 +212,8 @@ async fn list_users(
     HttpResponse::Ok().json(paginated_users)
 }
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    // Initialize mock data
+// In-memory state seeded with one admin; also used by the end-to-end tests.
+fn seeded_state() -> web::Data<AppState> {
     let mut user_map = HashMap::new();
     let admin_user = User {
         id: Uuid::new_v4(),
@@ -201,25 +225,214 @@ async fn main() -> std::io::Result<()> {
     };
     user_map.insert(admin_user.id, admin_user);
 
-    let app_state = web::Data::new(AppState {
+    web::Data::new(AppState {
         users: Mutex::new(user_map),
-    });
+    })
+}
+
+fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::JsonConfig::default().error_handler(json_error_handler))
+        .service(
+            web::scope("/users")
+                .route("", web::post().to(create_user))
+                .route("", web::get().to(list_users))
+                .route("/{id}", web::get().to(get_user_by_id))
+                .route("/{id}", web::put().to(update_user))
+                .route("/{id}", web::delete().to(delete_user)),
+        );
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let app_state = seeded_state();
 
     println!("Server running at http://127.0.0.1:8080");
 
-    HttpServer::new(move || {
-        App::new()
-            .app_data(app_state.clone())
-            .service(
-                web::scope("/users")
-                    .route("", web::post().to(create_user))
-                    .route("", web::get().to(list_users))
-                    .route("/{id}", web::get().to(get_user_by_id))
-                    .route("/{id}", web::put().to(update_user))
-                    .route("/{id}", web::delete().to(delete_user)),
-            )
-    })
-    .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
-}
\ No newline at end of file
+    HttpServer::new(move || App::new().app_data(app_state.clone()).configure(configure))
+        .bind(("127.0.0.1", 8080))?
+        .run()
+        .await
+}
+
+// --- End-to-End Tests ---
+
+// The users scenario shared with the Axum and Rocket REST variations: create,
+// fetch, assign a role, list by role, delete.
+#[cfg(test)]
+mod e2e {
+    use super::*;
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    #[actix_web::test]
+    async fn user_lifecycle() {
+        let app = test::init_service(App::new().app_data(seeded_state()).configure(configure)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(json!({"email": "grace@example.com", "password": "s3cret!"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let created: Value = test::read_body_json(resp).await;
+        let id = created["id"].as_str().unwrap().to_string();
+        assert_eq!(created["role"], "USER");
+
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .set_json(json!({"email": "grace@example.com", "password": "again"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+
+        let req = test::TestRequest::get().uri(&format!("/users/{}", id)).to_request();
+        let fetched: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(fetched["email"], "grace@example.com");
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/users/{}", id))
+            .set_json(json!({"role": "ADMIN"}))
+            .to_request();
+        let promoted: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(promoted["role"], "ADMIN");
+
+        let req = test::TestRequest::get().uri("/users?role=admin&limit=50").to_request();
+        let admins: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+        let emails: Vec<&str> = admins.iter().filter_map(|u| u["email"].as_str()).collect();
+        assert!(emails.contains(&"grace@example.com"), "{:?}", emails);
+        assert!(emails.contains(&"admin@example.com"), "{:?}", emails);
+
+        let req = test::TestRequest::delete().uri(&format!("/users/{}", id)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+        let req = test::TestRequest::get().uri(&format!("/users/{}", id)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    }
+}
+// --- Response Snapshots ---
+
+// Golden files for every handler's success and error responses, kept in
+// snapshots/variation_1/<case>.snap next to this file (or under $SNAPSHOT_DIR).
+// Ids and timestamps are redacted; UPDATE_SNAPSHOTS=1 rewrites the files. A
+// response that mentions a password hash fails whatever its snapshot says.
+#[cfg(test)]
+mod snapshots {
+    use super::*;
+    use actix_web::test;
+    use serde_json::{json, Value};
+
+    const MISSING_ID: &str = "00000000-0000-0000-0000-000000000000";
+    const LEAK_MARKERS: [&str; 2] = ["password_hash", "hashed_"];
+
+    // Each case runs against a freshly seeded state; `admin` is the seeded user's id
+    type Case = (&'static str, fn(Uuid) -> test::TestRequest);
+
+    static CASES: [Case; 12] = [
+        ("create_user_created", |_| {
+            test::TestRequest::post().uri("/users").set_json(json!({"email": "new@example.com", "password": "secret"}))
+        }),
+        ("create_user_conflict", |_| {
+            test::TestRequest::post().uri("/users").set_json(json!({"email": "admin@example.com", "password": "secret"}))
+        }),
+        ("create_user_malformed_body", |_| {
+            test::TestRequest::post().uri("/users").insert_header(("content-type", "application/json")).set_payload("{")
+        }),
+        ("get_user_ok", |admin| test::TestRequest::get().uri(&format!("/users/{}", admin))),
+        ("get_user_not_found", |_| test::TestRequest::get().uri(&format!("/users/{}", MISSING_ID))),
+        ("get_user_invalid_id", |_| test::TestRequest::get().uri("/users/not-a-uuid")),
+        ("list_users_filtered", |_| test::TestRequest::get().uri("/users?role=admin&is_active=true")),
+        ("update_user_ok", |admin| {
+            test::TestRequest::put().uri(&format!("/users/{}", admin)).set_json(json!({"is_active": false}))
+        }),
+        ("update_user_not_found", |_| {
+            test::TestRequest::put().uri(&format!("/users/{}", MISSING_ID)).set_json(json!({}))
+        }),
+        ("update_user_invalid_role", |admin| {
+            test::TestRequest::put().uri(&format!("/users/{}", admin)).set_json(json!({"role": "OWNER"}))
+        }),
+        ("delete_user_no_content", |admin| test::TestRequest::delete().uri(&format!("/users/{}", admin))),
+        ("delete_user_not_found", |_| test::TestRequest::delete().uri(&format!("/users/{}", MISSING_ID))),
+    ];
+
+    fn redact(s: &str) -> String {
+        if DateTime::parse_from_rfc3339(s).is_ok() {
+            return "[timestamp]".to_string();
+        }
+        let mut out = s.to_string();
+        for word in s.split(|c: char| !(c.is_ascii_hexdigit() || c == '-')) {
+            if Uuid::parse_str(word).is_ok() && word.len() == 36 {
+                out = out.replace(word, "[uuid]");
+            }
+        }
+        out
+    }
+
+    fn redact_json(value: &mut Value) {
+        match value {
+            Value::String(s) => *s = redact(s),
+            Value::Array(items) => items.iter_mut().for_each(redact_json),
+            Value::Object(map) => map.values_mut().for_each(redact_json),
+            _ => {}
+        }
+    }
+
+    fn render(status: u16, content_type: Option<&str>, body: &[u8]) -> String {
+        let mut out = format!("status: {}\n", status);
+        if let Some(content_type) = content_type {
+            out.push_str(&format!("content-type: {}\n", content_type));
+        }
+        out.push('\n');
+        match serde_json::from_slice::<Value>(body) {
+            Ok(mut json) => {
+                redact_json(&mut json);
+                out.push_str(&serde_json::to_string_pretty(&json).unwrap());
+            }
+            Err(_) => out.push_str(&redact(&String::from_utf8_lossy(body))),
+        }
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out
+    }
+
+    fn snapshot_dir() -> std::path::PathBuf {
+        match std::env::var_os("SNAPSHOT_DIR") {
+            Some(dir) => dir.into(),
+            None => std::path::Path::new(file!()).with_file_name("snapshots").join("variation_1"),
+        }
+    }
+
+    #[actix_web::test]
+    async fn responses_match_their_snapshots() {
+        let dir = snapshot_dir();
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        if update {
+            std::fs::create_dir_all(&dir).unwrap();
+        }
+        let mut failures = Vec::new();
+        for (name, request) in &CASES {
+            let state = seeded_state();
+            let admin = *state.users.lock().unwrap().keys().next().unwrap();
+            let app = test::init_service(App::new().app_data(state).configure(configure)).await;
+            let resp = test::call_service(&app, request(admin).to_request()).await;
+            let status = resp.status().as_u16();
+            let content_type = resp.headers().get("content-type").and_then(|v| v.to_str().ok()).map(str::to_string);
+            let body = test::read_body(resp).await;
+            let actual = render(status, content_type.as_deref(), &body);
+            let path = dir.join(format!("{}.snap", name));
+
+            if let Some(marker) = LEAK_MARKERS.iter().find(|marker| actual.contains(*marker)) {
+                failures.push(format!("{}: response contains {:?}", name, marker));
+                continue;
+            }
+            if update {
+                std::fs::write(&path, &actual).unwrap();
+                continue;
+            }
+            match std::fs::read_to_string(&path) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => failures.push(format!("{}: expected\n{}got\n{}", name, expected, actual)),
+                Err(_) => failures.push(format!("{}: no snapshot at {}; rerun with UPDATE_SNAPSHOTS=1", name, path.display())),
+            }
+        }
+        assert!(failures.is_empty(), "{} of {} snapshots failed:\n{}", failures.len(), CASES.len(), failures.join("\n"));
+    }
+}
@@ -1,7 +1,7 @@
 /*
 --- CARGO.TOML ---
 [dependencies]
-axum = { version = "0.7", features = ["macros"] }
+axum = { version = "0.7", features = ["macros", "ws"] }
 tokio = { version = "1", features = ["full"] }
 serde = { version = "1.0", features = ["derive"] }
 serde_json = "1.0"
@@ -9,6 +9,11 @@ uuid = { version = "1.6", features = ["v4", "serde"] }
 chrono = { version = "0.4", features = ["serde"] }
 jsonwebtoken = "9.2"
 argon2 = "0.5"
+bcrypt = "0.15"
+scrypt = "0.11"
+hmac = "0.12"
+sha2 = "0.10"
+hex = "0.4"
 rand_core = { version = "0.6", features = ["std"] }
 axum-extra = { version = "0.9", features = ["typed-header"] }
 headers = "0.4"
@@ -17,14 +22,21 @@ once_cell = "1.19"
 dashmap = "5.5"
 async-trait = "0.1.77"
 thiserror = "1.0"
+futures = "0.3"
+tracing = "0.1"
+tracing-subscriber = "0.3"
 */
 
 use axum::{
     async_trait,
-    extract::{FromRequestParts, State},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        FromRef, FromRequestParts, Path, Query, State,
+    },
     http::{request::Parts, StatusCode},
+    middleware,
     response::{IntoResponse, Response, Json},
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use axum_extra::headers::{authorization::Bearer, Authorization};
@@ -69,11 +81,27 @@ pub struct Post {
     status: PostStatus,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    id: Uuid,
+    post_id: Uuid,
+    parent_id: Option<Uuid>,
+    author_id: Uuid,
+    body: String,
+    created_at: DateTime<Utc>,
+}
+
 // --- 2. Application State & DB ---
 #[derive(Clone)]
 pub struct AppState {
     db: Db,
     jwt_secret: String,
+    hasher: Arc<passwords::PasswordHasher>,
+    notifications: notifications::NotificationHub,
+    inbox: inbox::InboxService,
+    sessions: sessions::SessionService,
+    impersonation: impersonation::ImpersonationService,
+    audit: audit::AuditLog,
 }
 
 type Db = Arc<MockDb>;
@@ -81,6 +109,12 @@ type Db = Arc<MockDb>;
 pub struct MockDb {
     users: DashMap<Uuid, User>,
     posts: DashMap<Uuid, Post>,
+    comments: DashMap<Uuid, Comment>,
+    notifications: DashMap<Uuid, inbox::Notification>,
+    sessions: DashMap<Uuid, sessions::Session>,
+    login_events: DashMap<Uuid, sessions::LoginEvent>,
+    impersonations: DashMap<Uuid, impersonation::ImpersonationGrant>,
+    audit_events: DashMap<Uuid, audit::AuditEvent>,
 }
 
 impl MockDb {
@@ -88,6 +122,12 @@ impl MockDb {
         Self {
             users: DashMap::new(),
             posts: DashMap::new(),
+            comments: DashMap::new(),
+            notifications: DashMap::new(),
+            sessions: DashMap::new(),
+            login_events: DashMap::new(),
+            impersonations: DashMap::new(),
+            audit_events: DashMap::new(),
         }
     }
 }
@@ -101,6 +141,14 @@ pub enum AppError {
     InvalidToken,
     #[error("User not found")]
     UserNotFound,
+    #[error("Post not found")]
+    PostNotFound,
+    #[error("Comment not found")]
+    CommentNotFound,
+    #[error("Notification not found")]
+    NotificationNotFound,
+    #[error("Session not found")]
+    SessionNotFound,
     #[error("Access forbidden")]
     Forbidden,
     #[error("Internal server error")]
@@ -114,56 +162,353 @@ impl IntoResponse for AppError {
             AppError::InvalidToken => (StatusCode::UNAUTHORIZED, self.to_string()),
             AppError::Forbidden => (StatusCode::FORBIDDEN, self.to_string()),
             AppError::UserNotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::PostNotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::CommentNotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::NotificationNotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::SessionNotFound => (StatusCode::NOT_FOUND, self.to_string()),
             AppError::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
         };
         (status, Json(serde_json::json!({ "error": error_message }))).into_response()
     }
 }
 
-// --- 4. Authentication & Authorization Logic ---
-mod auth {
+// --- 4. Password Hashing ---
+mod passwords {
     use super::*;
-    use argon2::{
-        password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
-        Argon2, PasswordVerifier,
+    use argon2::password_hash::{
+        rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString,
     };
+    use argon2::Argon2;
+    use hmac::{Hmac, Mac};
+    use scrypt::Scrypt;
+    use sha2::Sha256;
+    use std::collections::BTreeMap;
+    use std::fmt;
+
+    const DEFAULT_BCRYPT_COST: u32 = bcrypt::DEFAULT_COST;
+
+    /// Every stored hash carries its scheme in its prefix (`$argon2id$`,
+    /// `$scrypt$`, `$2b$`), so accounts hashed under an older scheme can be
+    /// verified and upgraded without a separate column.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum HashAlgorithm {
+        Argon2id,
+        Scrypt,
+        Bcrypt,
+    }
+
+    impl HashAlgorithm {
+        pub fn identify(hash: &str) -> Option<Self> {
+            if hash.starts_with("$argon2") {
+                Some(Self::Argon2id)
+            } else if hash.starts_with("$scrypt$") {
+                Some(Self::Scrypt)
+            } else if ["$2a$", "$2b$", "$2y$"].iter().any(|p| hash.starts_with(p)) {
+                Some(Self::Bcrypt)
+            } else {
+                None
+            }
+        }
+
+        fn parse(name: &str) -> Option<Self> {
+            match name.to_ascii_lowercase().as_str() {
+                "argon2id" | "argon2" => Some(Self::Argon2id),
+                "scrypt" => Some(Self::Scrypt),
+                "bcrypt" => Some(Self::Bcrypt),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct MigrationReport {
+        pub preferred: HashAlgorithm,
+        pub total: usize,
+        pub by_algorithm: BTreeMap<HashAlgorithm, usize>,
+        /// Hashes that will be rewritten on the user's next successful login.
+        pub pending_rehash: usize,
+        /// Hashes with no recognised prefix; those users cannot log in.
+        pub unrecognized: usize,
+        pub pepper_configured: bool,
+        /// Set while a pepper rotation is in progress. Hashes made with the
+        /// retired pepper are indistinguishable from the rest until verified,
+        /// so they are not reflected in `pending_rehash`.
+        pub pepper_rotation_active: bool,
+    }
+
+    /// Server-side secret mixed into every password with HMAC-SHA256 before it
+    /// is hashed. It lives outside the database, so a dump of `users` alone is
+    /// not enough to mount an offline guessing attack.
+    #[derive(Clone)]
+    enum Pepper {
+        None,
+        Secret(Vec<u8>),
+    }
+
+    impl Pepper {
+        fn apply(&self, password: &str) -> String {
+            match self {
+                Pepper::None => password.to_string(),
+                Pepper::Secret(key) => {
+                    let mut mac = Hmac::<Sha256>::new_from_slice(key)
+                        .expect("HMAC accepts keys of any length");
+                    mac.update(password.as_bytes());
+                    hex::encode(mac.finalize().into_bytes())
+                }
+            }
+        }
+
+        /// Reads `NAME`, or the file named by `NAME_FILE` for mounted secrets.
+        /// `None` means the variable is unset; an empty value is `Pepper::None`.
+        fn from_env(name: &str) -> Option<Self> {
+            let value = std::env::var(name).ok().or_else(|| {
+                let path = std::env::var(format!("{name}_FILE")).ok()?;
+                std::fs::read_to_string(path).ok()
+            })?;
+            let value = value.trim();
+            Some(if value.is_empty() {
+                Pepper::None
+            } else {
+                Pepper::Secret(value.as_bytes().to_vec())
+            })
+        }
+    }
+
+    impl fmt::Debug for Pepper {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Pepper::None => f.write_str("None"),
+                Pepper::Secret(_) => f.write_str("Secret(<redacted>)"),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Verification {
+        Invalid,
+        Valid,
+        /// Correct password, but the stored hash uses an outdated scheme,
+        /// weaker parameters or the retired pepper.
+        ValidNeedsRehash,
+    }
+
+    /// Hashes new passwords with the preferred algorithm and verifies against
+    /// any supported one.
+    ///
+    /// Rotating the pepper is done with a verification window:
+    /// 1. Move the old secret to `PASSWORD_PEPPER_PREVIOUS` and put the new one
+    ///    in `PASSWORD_PEPPER`. To introduce a pepper for the first time, set
+    ///    `PASSWORD_PEPPER_PREVIOUS` to an empty value.
+    /// 2. Logins that only match under the previous pepper are rehashed with
+    ///    the current one.
+    /// 3. Once enough users have logged in, unset `PASSWORD_PEPPER_PREVIOUS`.
+    ///    Anyone left on the old pepper has to reset their password.
+    #[derive(Debug, Clone)]
+    pub struct PasswordHasher {
+        preferred: HashAlgorithm,
+        bcrypt_cost: u32,
+        pepper: Pepper,
+        previous_pepper: Option<Pepper>,
+    }
+
+    impl PasswordHasher {
+        /// Reads `PASSWORD_HASH_ALGORITHM` (argon2id, scrypt or bcrypt,
+        /// defaulting to argon2id), `PASSWORD_PEPPER` and
+        /// `PASSWORD_PEPPER_PREVIOUS`. Both peppers may be given as `*_FILE`.
+        pub fn from_env() -> Self {
+            let preferred = std::env::var("PASSWORD_HASH_ALGORITHM")
+                .ok()
+                .and_then(|name| HashAlgorithm::parse(&name))
+                .unwrap_or(HashAlgorithm::Argon2id);
+            Self {
+                preferred,
+                bcrypt_cost: DEFAULT_BCRYPT_COST,
+                pepper: Pepper::from_env("PASSWORD_PEPPER").unwrap_or(Pepper::None),
+                previous_pepper: Pepper::from_env("PASSWORD_PEPPER_PREVIOUS"),
+            }
+        }
+
+        pub fn hash(&self, password: &str) -> Result<String, AppError> {
+            self.hash_with(self.preferred, password)
+        }
+
+        pub fn hash_with(&self, algorithm: HashAlgorithm, password: &str) -> Result<String, AppError> {
+            let password = self.pepper.apply(password);
+            let password = password.as_str();
+            let salt = SaltString::generate(&mut OsRng);
+            let hashed = match algorithm {
+                HashAlgorithm::Argon2id => Argon2::default()
+                    .hash_password(password.as_bytes(), &salt)
+                    .map(|hash| hash.to_string()),
+                HashAlgorithm::Scrypt => Scrypt
+                    .hash_password(password.as_bytes(), &salt)
+                    .map(|hash| hash.to_string()),
+                HashAlgorithm::Bcrypt => {
+                    return bcrypt::hash(password, self.bcrypt_cost)
+                        .map_err(|_| AppError::InternalServerError)
+                }
+            };
+            hashed.map_err(|_| AppError::InternalServerError)
+        }
+
+        /// Tries the current pepper first, then the previous one if a rotation
+        /// is in progress.
+        pub fn verify(&self, hash: &str, password: &str) -> Verification {
+            if Self::verify_scheme(hash, &self.pepper.apply(password)) {
+                if self.needs_rehash(hash) {
+                    Verification::ValidNeedsRehash
+                } else {
+                    Verification::Valid
+                }
+            } else if self
+                .previous_pepper
+                .as_ref()
+                .is_some_and(|previous| Self::verify_scheme(hash, &previous.apply(password)))
+            {
+                Verification::ValidNeedsRehash
+            } else {
+                Verification::Invalid
+            }
+        }
+
+        /// Hashes with an unrecognised or malformed prefix never verify.
+        fn verify_scheme(hash: &str, password: &str) -> bool {
+            match HashAlgorithm::identify(hash) {
+                Some(HashAlgorithm::Argon2id) => PasswordHash::new(hash)
+                    .map(|parsed| {
+                        Argon2::default()
+                            .verify_password(password.as_bytes(), &parsed)
+                            .is_ok()
+                    })
+                    .unwrap_or(false),
+                Some(HashAlgorithm::Scrypt) => PasswordHash::new(hash)
+                    .map(|parsed| Scrypt.verify_password(password.as_bytes(), &parsed).is_ok())
+                    .unwrap_or(false),
+                Some(HashAlgorithm::Bcrypt) => bcrypt::verify(password, hash).unwrap_or(false),
+                None => false,
+            }
+        }
+
+        /// True when the hash was produced by a different algorithm, or by the
+        /// preferred one with weaker parameters than it now uses.
+        fn needs_rehash(&self, hash: &str) -> bool {
+            if HashAlgorithm::identify(hash) != Some(self.preferred) {
+                return true;
+            }
+            match self.preferred {
+                HashAlgorithm::Argon2id => PasswordHash::new(hash)
+                    .map(|parsed| {
+                        let current = Argon2::default();
+                        let current = current.params();
+                        parsed.algorithm != argon2::ARGON2ID_IDENT
+                            || argon2::Params::try_from(&parsed).map_or(true, |params| {
+                                params.m_cost() < current.m_cost()
+                                    || params.t_cost() < current.t_cost()
+                                    || params.p_cost() < current.p_cost()
+                            })
+                    })
+                    .unwrap_or(true),
+                HashAlgorithm::Scrypt => PasswordHash::new(hash)
+                    .map(|parsed| {
+                        scrypt::Params::try_from(&parsed)
+                            .map(|params| params.log_n() < scrypt::Params::RECOMMENDED_LOG_N)
+                            .unwrap_or(true)
+                    })
+                    .unwrap_or(true),
+                HashAlgorithm::Bcrypt => hash
+                    .get(4..6)
+                    .and_then(|cost| cost.parse::<u32>().ok())
+                    .is_none_or(|cost| cost < self.bcrypt_cost),
+            }
+        }
+
+        pub fn report<'a>(&self, hashes: impl Iterator<Item = &'a str>) -> MigrationReport {
+            let mut report = MigrationReport {
+                preferred: self.preferred,
+                total: 0,
+                by_algorithm: BTreeMap::new(),
+                pending_rehash: 0,
+                unrecognized: 0,
+                pepper_configured: matches!(self.pepper, Pepper::Secret(_)),
+                pepper_rotation_active: self.previous_pepper.is_some(),
+            };
+            for hash in hashes {
+                report.total += 1;
+                match HashAlgorithm::identify(hash) {
+                    Some(algorithm) => {
+                        *report.by_algorithm.entry(algorithm).or_default() += 1;
+                        if self.needs_rehash(hash) {
+                            report.pending_rehash += 1;
+                        }
+                    }
+                    None => report.unrecognized += 1,
+                }
+            }
+            report
+        }
+    }
+}
+
+// --- 5. Authentication & Authorization Logic ---
+mod auth {
+    use super::*;
+    use impersonation::Impersonation;
     use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 
+    /// Short-lived because the refresh token is what keeps a device signed in.
+    const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+
     #[derive(Debug, Serialize, Deserialize)]
     pub struct Claims {
         pub sub: String, // Subject (user_id)
         pub role: Role,
+        pub sid: Uuid, // Session the token was issued for
         pub exp: usize, // Expiration time
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub act_as: Option<Uuid>, // User being impersonated; `sub` stays the admin
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub imp: Option<Uuid>, // Impersonation grant
     }
 
-    pub fn hash_password(password: &str) -> Result<String, AppError> {
-        let salt = SaltString::generate(&mut OsRng);
-        Argon2::default()
-            .hash_password(password.as_bytes(), &salt)
-            .map(|hash| hash.to_string())
-            .map_err(|_| AppError::InternalServerError)
-    }
-
-    pub fn verify_password(hash: &str, password: &str) -> Result<bool, AppError> {
-        let parsed_hash = argon2::PasswordHash::new(hash).map_err(|_| AppError::InvalidCredentials)?;
-        Ok(Argon2::default()
-            .verify_password(password.as_bytes(), &parsed_hash)
-            .is_ok())
-    }
-
-    pub fn create_jwt(user_id: Uuid, role: &Role, secret: &str) -> Result<String, AppError> {
-        let expiration = Utc::now()
-            .checked_add_signed(chrono::Duration::hours(24))
+    fn expires_in(minutes: i64) -> usize {
+        Utc::now()
+            .checked_add_signed(chrono::Duration::minutes(minutes))
             .expect("valid timestamp")
-            .timestamp();
+            .timestamp() as usize
+    }
 
+    pub fn create_jwt(user_id: Uuid, role: &Role, session_id: Uuid, secret: &str) -> Result<String, AppError> {
         let claims = Claims {
             sub: user_id.to_string(),
             role: role.clone(),
-            exp: expiration as usize,
+            sid: session_id,
+            exp: expires_in(ACCESS_TOKEN_TTL_MINUTES),
+            act_as: None,
+            imp: None,
         };
+        sign(&claims, secret)
+    }
+
+    pub fn create_impersonation_jwt(
+        grant: &impersonation::ImpersonationGrant,
+        target_role: &Role,
+        secret: &str,
+    ) -> Result<String, AppError> {
+        let claims = Claims {
+            sub: grant.admin_id.to_string(),
+            role: target_role.clone(),
+            sid: grant.session_id,
+            exp: expires_in(impersonation::TTL_MINUTES),
+            act_as: Some(grant.user_id),
+            imp: Some(grant.id),
+        };
+        sign(&claims, secret)
+    }
+
+    fn sign(claims: &Claims, secret: &str) -> Result<String, AppError> {
         let header = Header::new(jsonwebtoken::Algorithm::HS256);
-        encode(&header, &claims, &EncodingKey::from_secret(secret.as_ref()))
+        encode(&header, claims, &EncodingKey::from_secret(secret.as_ref()))
             .map_err(|_| AppError::InternalServerError)
     }
 
@@ -177,11 +522,59 @@ mod auth {
         .map_err(|_| AppError::InvalidToken)
     }
 
-    // Auth Guard Extractor
-    pub struct AuthenticatedUser(pub User);
+    /// Resolves a bearer token to an active user on a live session. For an
+    /// impersonation token that is the impersonated user, with the admin in
+    /// `impersonator`.
+    pub fn authenticate(token: &str, app_state: &AppState) -> Result<AuthenticatedSession, AppError> {
+        let claims = validate_jwt(token, &app_state.jwt_secret)?;
+        let subject_id = Uuid::parse_str(&claims.sub).map_err(|_| AppError::InvalidToken)?;
+        if !app_state.sessions.is_active(claims.sid) {
+            return Err(AppError::InvalidToken);
+        }
+
+        let (user_id, impersonator) = match (claims.act_as, claims.imp) {
+            (None, None) => (subject_id, None),
+            (Some(target_id), Some(grant_id)) => {
+                if !app_state.impersonation.is_active(grant_id, subject_id, target_id, claims.sid) {
+                    return Err(AppError::InvalidToken);
+                }
+                // Demoting or deactivating the admin ends their impersonation too
+                let still_admin = app_state
+                    .db
+                    .users
+                    .get(&subject_id)
+                    .is_some_and(|admin| admin.role == Role::ADMIN && admin.is_active);
+                if !still_admin {
+                    return Err(AppError::Forbidden);
+                }
+                (target_id, Some(Impersonation { grant_id, admin_id: subject_id }))
+            }
+            _ => return Err(AppError::InvalidToken),
+        };
+
+        let user = app_state
+            .db
+            .users
+            .get(&user_id)
+            .map(|u| u.value().clone())
+            .ok_or(AppError::UserNotFound)?;
+
+        if !user.is_active {
+            return Err(AppError::Forbidden);
+        }
+
+        Ok(AuthenticatedSession { user, session_id: claims.sid, impersonator })
+    }
+
+    // Auth Guard Extractor, for handlers that also need the caller's session
+    pub struct AuthenticatedSession {
+        pub user: User,
+        pub session_id: Uuid,
+        pub impersonator: Option<Impersonation>,
+    }
 
     #[async_trait]
-    impl<S> FromRequestParts<S> for AuthenticatedUser
+    impl<S> FromRequestParts<S> for AuthenticatedSession
     where
         S: Send + Sync,
         AppState: FromRef<S>,
@@ -195,21 +588,24 @@ mod auth {
                     .await
                     .map_err(|_| AppError::InvalidToken)?;
 
-            let claims = validate_jwt(bearer.token(), &app_state.jwt_secret)?;
-            let user_id = Uuid::parse_str(&claims.sub).map_err(|_| AppError::InvalidToken)?;
+            authenticate(bearer.token(), &app_state)
+        }
+    }
 
-            let user = app_state
-                .db
-                .users
-                .get(&user_id)
-                .map(|u| u.value().clone())
-                .ok_or(AppError::UserNotFound)?;
+    pub struct AuthenticatedUser(pub User);
 
-            if !user.is_active {
-                return Err(AppError::Forbidden);
-            }
+    #[async_trait]
+    impl<S> FromRequestParts<S> for AuthenticatedUser
+    where
+        S: Send + Sync,
+        AppState: FromRef<S>,
+    {
+        type Rejection = AppError;
 
-            Ok(AuthenticatedUser(user))
+        async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+            AuthenticatedSession::from_request_parts(parts, state)
+                .await
+                .map(|session| AuthenticatedUser(session.user))
         }
     }
 
@@ -232,12 +628,772 @@ mod auth {
             Ok(AdminUser(user))
         }
     }
+
+    // Account-security guard: the account's owner only, never an admin acting as them
+    pub struct AccountOwner(pub AuthenticatedSession);
+
+    #[async_trait]
+    impl<S> FromRequestParts<S> for AccountOwner
+    where
+        S: Send + Sync,
+        AppState: FromRef<S>,
+    {
+        type Rejection = AppError;
+
+        async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+            let session = AuthenticatedSession::from_request_parts(parts, state).await?;
+            if session.impersonator.is_some() {
+                return Err(AppError::Forbidden);
+            }
+            Ok(AccountOwner(session))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use axum::http::{header::AUTHORIZATION, Request};
+
+        fn user(role: Role) -> User {
+            User {
+                id: Uuid::new_v4(),
+                email: format!("{}@example.com", Uuid::new_v4()),
+                password_hash: String::new(),
+                role,
+                is_active: true,
+                created_at: Utc::now(),
+            }
+        }
+
+        fn state() -> AppState {
+            let db = Arc::new(MockDb::new());
+            let notifications = notifications::NotificationHub::new();
+            AppState {
+                inbox: inbox::InboxService::new(db.clone(), notifications.clone()),
+                sessions: sessions::SessionService::new(db.clone()),
+                impersonation: impersonation::ImpersonationService::new(db.clone()),
+                audit: audit::AuditLog::new(db.clone()),
+                db,
+                jwt_secret: "test-secret".to_string(),
+                hasher: Arc::new(passwords::PasswordHasher::from_env()),
+                notifications,
+            }
+        }
+
+        fn client() -> sessions::ClientInfo {
+            sessions::ClientInfo { ip: None, user_agent: None }
+        }
+
+        fn parts(token: &str) -> Parts {
+            let request = Request::builder().header(AUTHORIZATION, format!("Bearer {}", token)).body(()).unwrap();
+            request.into_parts().0
+        }
+
+        #[tokio::test]
+        async fn impersonation_tokens_are_not_the_account_owner() {
+            let state = state();
+            let (admin, target) = (user(Role::ADMIN), user(Role::USER));
+            state.db.users.insert(admin.id, admin.clone());
+            state.db.users.insert(target.id, target.clone());
+
+            let (admin_session, _) = state.sessions.start(admin.id, &client());
+            let grant = state.impersonation.start(admin.id, target.id, admin_session.id);
+            let impersonating = create_impersonation_jwt(&grant, &target.role, &state.jwt_secret).unwrap();
+
+            let acting = AuthenticatedSession::from_request_parts(&mut parts(&impersonating), &state).await.unwrap();
+            assert_eq!(acting.user.id, target.id);
+            let owner = AccountOwner::from_request_parts(&mut parts(&impersonating), &state).await;
+            assert!(matches!(owner, Err(AppError::Forbidden)));
+
+            let (own_session, _) = state.sessions.start(target.id, &client());
+            let own = create_jwt(target.id, &target.role, own_session.id, &state.jwt_secret).unwrap();
+            let AccountOwner(owner) = AccountOwner::from_request_parts(&mut parts(&own), &state).await.unwrap();
+            assert_eq!((owner.user.id, owner.session_id), (target.id, own_session.id));
+        }
+    }
+}
+
+// --- 6. Sessions & Login History ---
+mod sessions {
+    use super::*;
+    use axum::extract::ConnectInfo;
+    use axum::http::header::USER_AGENT;
+    use rand_core::{OsRng, RngCore};
+    use sha2::{Digest, Sha256};
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+
+    const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+    const MAX_USER_AGENT_LEN: usize = 256;
+
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    pub enum AuthMethod {
+        Password,
+        RefreshToken,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct LoginEvent {
+        pub id: Uuid,
+        /// `None` when the email didn't match any account.
+        pub user_id: Option<Uuid>,
+        pub email: String,
+        pub ip: Option<String>,
+        pub user_agent: Option<String>,
+        pub method: AuthMethod,
+        pub success: bool,
+        pub created_at: DateTime<Utc>,
+    }
+
+    /// One device's refresh-token session. Access tokens carry its id, so revoking
+    /// the session also rejects access tokens already issued for it.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Session {
+        pub id: Uuid,
+        pub user_id: Uuid,
+        #[serde(skip_serializing)]
+        pub refresh_token_hash: String,
+        pub ip: Option<String>,
+        pub user_agent: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub last_used_at: DateTime<Utc>,
+        pub expires_at: DateTime<Utc>,
+        pub revoked_at: Option<DateTime<Utc>>,
+    }
+
+    impl Session {
+        fn is_active(&self, now: DateTime<Utc>) -> bool {
+            self.revoked_at.is_none() && self.expires_at > now
+        }
+    }
+
+    /// Where a request came from, as recorded on sessions and login events.
+    pub struct ClientInfo {
+        pub ip: Option<String>,
+        pub user_agent: Option<String>,
+    }
+
+    #[async_trait]
+    impl<S> FromRequestParts<S> for ClientInfo
+    where
+        S: Send + Sync,
+    {
+        type Rejection = Infallible;
+
+        async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+            // Only the peer address; X-Forwarded-For is client-controlled without a trusted proxy
+            let ip = parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string());
+            let user_agent = parts
+                .headers
+                .get(USER_AGENT)
+                .and_then(|v| v.to_str().ok())
+                .map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect());
+            Ok(ClientInfo { ip, user_agent })
+        }
+    }
+
+    pub struct SessionRepository {
+        db: Db,
+    }
+
+    impl SessionRepository {
+        pub fn new(db: Db) -> Self {
+            Self { db }
+        }
+
+        pub fn insert(&self, session: Session) {
+            self.db.sessions.insert(session.id, session);
+        }
+
+        pub fn find(&self, id: Uuid) -> Option<Session> {
+            self.db.sessions.get(&id).map(|s| s.value().clone())
+        }
+
+        /// Applies `update` and returns the result, or `None` if there is no such session.
+        pub fn update(&self, id: Uuid, update: impl FnOnce(&mut Session)) -> Option<Session> {
+            let mut entry = self.db.sessions.get_mut(&id)?;
+            update(&mut entry);
+            Some(entry.clone())
+        }
+
+        /// Most recently used first.
+        pub fn find_active_for_user(&self, user_id: Uuid, now: DateTime<Utc>) -> Vec<Session> {
+            let mut items: Vec<Session> = self
+                .db
+                .sessions
+                .iter()
+                .filter(|s| s.user_id == user_id && s.is_active(now))
+                .map(|s| s.value().clone())
+                .collect();
+            items.sort_by_key(|i| std::cmp::Reverse(i.last_used_at));
+            items
+        }
+
+        pub fn insert_login_event(&self, event: LoginEvent) {
+            self.db.login_events.insert(event.id, event);
+        }
+
+        /// Newest first.
+        pub fn find_login_events_for_user(&self, user_id: Uuid, limit: usize) -> Vec<LoginEvent> {
+            let mut items: Vec<LoginEvent> = self
+                .db
+                .login_events
+                .iter()
+                .filter(|e| e.user_id == Some(user_id))
+                .map(|e| e.value().clone())
+                .collect();
+            items.sort_by_key(|i| std::cmp::Reverse(i.created_at));
+            items.truncate(limit);
+            items
+        }
+    }
+
+    /// Issues, rotates and revokes refresh-token sessions, and keeps the login log.
+    ///
+    /// A refresh token is `<session id>.<secret>`; only a SHA-256 of the secret is
+    /// stored. Each refresh replaces the secret, so presenting an old one means the
+    /// token was copied and the whole session is revoked.
+    #[derive(Clone)]
+    pub struct SessionService {
+        repo: Arc<SessionRepository>,
+    }
+
+    impl SessionService {
+        pub fn new(db: Db) -> Self {
+            Self { repo: Arc::new(SessionRepository::new(db)) }
+        }
+
+        fn generate_secret() -> (String, String) {
+            let mut bytes = [0u8; 32];
+            OsRng.fill_bytes(&mut bytes);
+            let secret = hex::encode(bytes);
+            let hash = hex::encode(Sha256::digest(secret.as_bytes()));
+            (secret, hash)
+        }
+
+        pub fn record_login(
+            &self,
+            user_id: Option<Uuid>,
+            email: &str,
+            client: &ClientInfo,
+            method: AuthMethod,
+            success: bool,
+        ) {
+            self.repo.insert_login_event(LoginEvent {
+                id: Uuid::new_v4(),
+                user_id,
+                email: email.to_string(),
+                ip: client.ip.clone(),
+                user_agent: client.user_agent.clone(),
+                method,
+                success,
+                created_at: Utc::now(),
+            });
+        }
+
+        /// Opens a session and returns it with its refresh token.
+        pub fn start(&self, user_id: Uuid, client: &ClientInfo) -> (Session, String) {
+            let now = Utc::now();
+            let (secret, hash) = Self::generate_secret();
+            let session = Session {
+                id: Uuid::new_v4(),
+                user_id,
+                refresh_token_hash: hash,
+                ip: client.ip.clone(),
+                user_agent: client.user_agent.clone(),
+                created_at: now,
+                last_used_at: now,
+                expires_at: now + chrono::Duration::days(REFRESH_TOKEN_TTL_DAYS),
+                revoked_at: None,
+            };
+            self.repo.insert(session.clone());
+            let token = format!("{}.{}", session.id, secret);
+            (session, token)
+        }
+
+        /// Swaps a refresh token for a new one on the same session.
+        pub fn rotate(&self, refresh_token: &str, client: &ClientInfo) -> Result<(Session, String), AppError> {
+            let (id, secret) = refresh_token.split_once('.').ok_or(AppError::InvalidToken)?;
+            let id = Uuid::parse_str(id).map_err(|_| AppError::InvalidToken)?;
+            let now = Utc::now();
+            let presented = hex::encode(Sha256::digest(secret.as_bytes()));
+            let (secret, hash) = Self::generate_secret();
+
+            // Compare and swap while holding the entry, so when two refreshes race with
+            // the same token only one rotates and the other counts as reuse
+            let mut rotated = false;
+            let session = self
+                .repo
+                .update(id, |s| {
+                    if !s.is_active(now) {
+                        return;
+                    }
+                    if s.refresh_token_hash != presented {
+                        s.revoked_at.get_or_insert(now);
+                        return;
+                    }
+                    s.refresh_token_hash = hash;
+                    s.last_used_at = now;
+                    s.ip = client.ip.clone();
+                    s.user_agent = client.user_agent.clone();
+                    rotated = true;
+                })
+                .ok_or(AppError::InvalidToken)?;
+            if !rotated {
+                return Err(AppError::InvalidToken);
+            }
+            let token = format!("{}.{}", session.id, secret);
+            Ok((session, token))
+        }
+
+        pub fn is_active(&self, id: Uuid) -> bool {
+            self.repo.find(id).is_some_and(|s| s.is_active(Utc::now()))
+        }
+
+        pub fn list_active(&self, user_id: Uuid) -> Vec<Session> {
+            self.repo.find_active_for_user(user_id, Utc::now())
+        }
+
+        pub fn login_history(&self, user_id: Uuid, limit: usize) -> Vec<LoginEvent> {
+            self.repo.find_login_events_for_user(user_id, limit)
+        }
+
+        /// Users can only revoke their own sessions; anyone else's reads as not found.
+        pub fn revoke(&self, user_id: Uuid, id: Uuid) -> Result<(), AppError> {
+            let now = Utc::now();
+            self.repo
+                .find(id)
+                .filter(|s| s.user_id == user_id && s.is_active(now))
+                .ok_or(AppError::SessionNotFound)?;
+            self.repo
+                .update(id, |s| {
+                    s.revoked_at = Some(now);
+                })
+                .map(drop)
+                .ok_or(AppError::SessionNotFound)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn client() -> ClientInfo {
+            ClientInfo { ip: Some("127.0.0.1".to_string()), user_agent: None }
+        }
+
+        #[test]
+        fn rotation_replaces_the_token_and_reuse_revokes_the_session() {
+            let service = SessionService::new(Arc::new(MockDb::new()));
+            let (session, first) = service.start(Uuid::new_v4(), &client());
+            let (_, second) = service.rotate(&first, &client()).unwrap();
+            assert_ne!(first, second);
+
+            assert!(matches!(service.rotate(&first, &client()), Err(AppError::InvalidToken)));
+            assert!(!service.is_active(session.id));
+            assert!(matches!(service.rotate(&second, &client()), Err(AppError::InvalidToken)));
+        }
+
+        #[test]
+        fn concurrent_refreshes_with_one_token_rotate_once() {
+            for _ in 0..500 {
+                let service = SessionService::new(Arc::new(MockDb::new()));
+                let (session, token) = service.start(Uuid::new_v4(), &client());
+                let barrier = std::sync::Barrier::new(8);
+                let successes = std::thread::scope(|scope| {
+                    let handles: Vec<_> = (0..8)
+                        .map(|_| {
+                            scope.spawn(|| {
+                                barrier.wait();
+                                service.rotate(&token, &client()).is_ok()
+                            })
+                        })
+                        .collect();
+                    handles.into_iter().map(|h| h.join().unwrap()).filter(|ok| *ok).count()
+                });
+                assert_eq!(successes, 1);
+                assert!(!service.is_active(session.id), "the losing refreshes are token reuse");
+            }
+        }
+    }
+}
+
+// --- 7. Audit Log ---
+mod audit {
+    use super::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct AuditEvent {
+        pub id: Uuid,
+        pub action: String,
+        /// Who actually did it; the admin when impersonating.
+        pub actor_id: Uuid,
+        /// The account acted on or as, if any.
+        pub subject_id: Option<Uuid>,
+        pub impersonation_id: Option<Uuid>,
+        pub detail: serde_json::Value,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Clone)]
+    pub struct AuditLog {
+        db: Db,
+    }
+
+    impl AuditLog {
+        pub fn new(db: Db) -> Self {
+            Self { db }
+        }
+
+        pub fn record(
+            &self,
+            action: &str,
+            actor_id: Uuid,
+            subject_id: Option<Uuid>,
+            impersonation_id: Option<Uuid>,
+            detail: serde_json::Value,
+        ) {
+            let event = AuditEvent {
+                id: Uuid::new_v4(),
+                action: action.to_string(),
+                actor_id,
+                subject_id,
+                impersonation_id,
+                detail,
+                created_at: Utc::now(),
+            };
+            // Impersonated actions are also logged at warn so they stand out
+            if let Some(impersonation_id) = impersonation_id {
+                tracing::warn!(
+                    %impersonation_id,
+                    admin_id = %actor_id,
+                    user_id = ?subject_id,
+                    action = %event.action,
+                    detail = %event.detail,
+                    "impersonated action"
+                );
+            }
+            self.db.audit_events.insert(event.id, event);
+        }
+
+        /// Newest first.
+        pub fn recent(&self, limit: usize) -> Vec<AuditEvent> {
+            let mut items: Vec<AuditEvent> = self.db.audit_events.iter().map(|e| e.value().clone()).collect();
+            items.sort_by_key(|i| std::cmp::Reverse(i.created_at));
+            items.truncate(limit);
+            items
+        }
+    }
+}
+
+// --- 8. Impersonation ---
+mod impersonation {
+    use super::*;
+    use axum::extract::Request;
+    use axum::http::header::{HeaderValue, AUTHORIZATION};
+    use axum::middleware::Next;
+
+    pub const TTL_MINUTES: i64 = 10;
+
+    /// A support admin acting as another user. Tokens for it carry `act_as`
+    /// alongside the admin's own `sub`, and stop working once this ends.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ImpersonationGrant {
+        pub id: Uuid,
+        pub admin_id: Uuid,
+        pub user_id: Uuid,
+        /// The admin's own session; revoking it ends the impersonation too.
+        pub session_id: Uuid,
+        pub started_at: DateTime<Utc>,
+        pub expires_at: DateTime<Utc>,
+        pub ended_at: Option<DateTime<Utc>>,
+    }
+
+    impl ImpersonationGrant {
+        fn is_active(&self, now: DateTime<Utc>) -> bool {
+            self.ended_at.is_none() && self.expires_at > now
+        }
+    }
+
+    /// Both identities behind an impersonated request. The middleware puts this
+    /// in the request extensions; `AuthenticatedSession` carries it as well.
+    #[derive(Debug, Clone)]
+    pub struct Impersonation {
+        pub grant_id: Uuid,
+        pub admin_id: Uuid,
+    }
+
+    #[derive(Clone)]
+    pub struct ImpersonationService {
+        db: Db,
+    }
+
+    impl ImpersonationService {
+        pub fn new(db: Db) -> Self {
+            Self { db }
+        }
+
+        pub fn start(&self, admin_id: Uuid, user_id: Uuid, session_id: Uuid) -> ImpersonationGrant {
+            let now = Utc::now();
+            let grant = ImpersonationGrant {
+                id: Uuid::new_v4(),
+                admin_id,
+                user_id,
+                session_id,
+                started_at: now,
+                expires_at: now + chrono::Duration::minutes(TTL_MINUTES),
+                ended_at: None,
+            };
+            self.db.impersonations.insert(grant.id, grant.clone());
+            grant
+        }
+
+        pub fn is_active(&self, grant_id: Uuid, admin_id: Uuid, user_id: Uuid, session_id: Uuid) -> bool {
+            self.db.impersonations.get(&grant_id).is_some_and(|g| {
+                g.admin_id == admin_id && g.user_id == user_id && g.session_id == session_id && g.is_active(Utc::now())
+            })
+        }
+
+        pub fn stop(&self, grant_id: Uuid) -> Option<ImpersonationGrant> {
+            let mut grant = self.db.impersonations.get_mut(&grant_id)?;
+            grant.ended_at.get_or_insert(Utc::now());
+            Some(grant.clone())
+        }
+    }
+
+    /// Audits every request made with an impersonation token, tagging the
+    /// response so clients can show a banner.
+    pub async fn audit_impersonated_requests(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+        let current = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .and_then(|token| auth::authenticate(token, &state).ok());
+        let Some((user_id, impersonation)) =
+            current.and_then(|current| current.impersonator.map(|imp| (current.user.id, imp)))
+        else {
+            return next.run(request).await;
+        };
+
+        let method = request.method().to_string();
+        let path = request.uri().path().to_string();
+        request.extensions_mut().insert(impersonation.clone());
+        let mut response = next.run(request).await;
+
+        state.audit.record(
+            "impersonation.request",
+            impersonation.admin_id,
+            Some(user_id),
+            Some(impersonation.grant_id),
+            serde_json::json!({ "method": method, "path": path, "status": response.status().as_u16() }),
+        );
+        if let Ok(value) = HeaderValue::from_str(&impersonation.admin_id.to_string()) {
+            response.headers_mut().insert("x-impersonated-by", value);
+        }
+        response
+    }
 }
 
-// --- 5. Handlers ---
+// --- 9. Notifications ---
+mod notifications {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use tokio::sync::mpsc;
+
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    pub enum NotificationEvent {
+        PostPublished { post_id: Uuid, author_id: Uuid, title: String },
+        /// Still sent alongside `NotificationCreated` for clients written before the inbox.
+        RoleAssigned { user_id: Uuid, role: Role },
+        NotificationCreated { notification: inbox::Notification },
+    }
+
+    type Connections = Vec<(u64, mpsc::UnboundedSender<NotificationEvent>)>;
+
+    /// Tracks open WebSocket connections per user and fans events out to them.
+    /// A user may hold several connections (e.g. multiple tabs).
+    #[derive(Clone, Default)]
+    pub struct NotificationHub {
+        connections: Arc<DashMap<Uuid, Connections>>,
+        next_id: Arc<AtomicU64>,
+    }
+
+    impl NotificationHub {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn register(&self, user_id: Uuid) -> (u64, mpsc::UnboundedReceiver<NotificationEvent>) {
+            let (tx, rx) = mpsc::unbounded_channel();
+            let conn_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+            self.connections.entry(user_id).or_default().push((conn_id, tx));
+            (conn_id, rx)
+        }
+
+        pub fn unregister(&self, user_id: Uuid, conn_id: u64) {
+            if let Some(mut conns) = self.connections.get_mut(&user_id) {
+                conns.retain(|(id, _)| *id != conn_id);
+            }
+            self.connections.remove_if(&user_id, |_, conns| conns.is_empty());
+        }
+
+        pub fn send_to(&self, user_id: Uuid, event: NotificationEvent) {
+            if let Some(mut conns) = self.connections.get_mut(&user_id) {
+                // Drop senders whose socket task has already gone away
+                conns.retain(|(_, tx)| tx.send(event.clone()).is_ok());
+            }
+        }
+
+        pub fn broadcast(&self, event: NotificationEvent) {
+            for mut entry in self.connections.iter_mut() {
+                entry.value_mut().retain(|(_, tx)| tx.send(event.clone()).is_ok());
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn role_assigned_keeps_its_wire_format() {
+            let user_id = Uuid::new_v4();
+            let event = NotificationEvent::RoleAssigned { user_id, role: Role::ADMIN };
+            assert_eq!(
+                serde_json::to_value(&event).unwrap(),
+                serde_json::json!({ "type": "role_assigned", "user_id": user_id, "role": "ADMIN" })
+            );
+        }
+    }
+}
+
+// --- 10. Inbox ---
+mod inbox {
+    use super::*;
+    use notifications::{NotificationEvent, NotificationHub};
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(tag = "kind", rename_all = "snake_case")]
+    pub enum NotificationKind {
+        RoleAssigned { role: Role },
+        CommentReply { post_id: Uuid, comment_id: Uuid, replied_by: Uuid },
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Notification {
+        pub id: Uuid,
+        pub user_id: Uuid,
+        #[serde(flatten)]
+        pub kind: NotificationKind,
+        pub read_at: Option<DateTime<Utc>>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    pub struct NotificationRepository {
+        db: Db,
+    }
+
+    impl NotificationRepository {
+        pub fn new(db: Db) -> Self {
+            Self { db }
+        }
+
+        pub fn insert(&self, notification: Notification) {
+            self.db.notifications.insert(notification.id, notification);
+        }
+
+        /// Newest first.
+        pub fn find_for_user(&self, user_id: Uuid, unread_only: bool) -> Vec<Notification> {
+            let mut items: Vec<Notification> = self
+                .db
+                .notifications
+                .iter()
+                .filter(|n| n.user_id == user_id && (!unread_only || n.read_at.is_none()))
+                .map(|n| n.value().clone())
+                .collect();
+            items.sort_by_key(|i| std::cmp::Reverse(i.created_at));
+            items
+        }
+
+        pub fn mark_read(&self, user_id: Uuid, id: Uuid, at: DateTime<Utc>) -> Option<Notification> {
+            let mut entry = self.db.notifications.get_mut(&id).filter(|n| n.user_id == user_id)?;
+            entry.read_at.get_or_insert(at);
+            Some(entry.clone())
+        }
+
+        pub fn mark_all_read(&self, user_id: Uuid, at: DateTime<Utc>) -> usize {
+            let mut updated = 0;
+            for mut entry in self.db.notifications.iter_mut() {
+                if entry.user_id == user_id && entry.read_at.is_none() {
+                    entry.read_at = Some(at);
+                    updated += 1;
+                }
+            }
+            updated
+        }
+    }
+
+    /// Persists notifications and pushes them to any open WebSocket for the recipient.
+    #[derive(Clone)]
+    pub struct InboxService {
+        repository: Arc<NotificationRepository>,
+        hub: NotificationHub,
+    }
+
+    impl InboxService {
+        pub fn new(db: Db, hub: NotificationHub) -> Self {
+            Self { repository: Arc::new(NotificationRepository::new(db)), hub }
+        }
+
+        pub fn notify(&self, user_id: Uuid, kind: NotificationKind) -> Notification {
+            let notification = Notification {
+                id: Uuid::new_v4(),
+                user_id,
+                kind,
+                read_at: None,
+                created_at: Utc::now(),
+            };
+            self.repository.insert(notification.clone());
+            self.hub.send_to(
+                user_id,
+                NotificationEvent::NotificationCreated { notification: notification.clone() },
+            );
+            notification
+        }
+
+        pub fn list(&self, user_id: Uuid, unread_only: bool) -> Vec<Notification> {
+            self.repository.find_for_user(user_id, unread_only)
+        }
+
+        pub fn mark_read(&self, user_id: Uuid, id: Uuid) -> Result<Notification, AppError> {
+            self.repository
+                .mark_read(user_id, id, Utc::now())
+                .ok_or(AppError::NotificationNotFound)
+        }
+
+        pub fn mark_all_read(&self, user_id: Uuid) -> usize {
+            self.repository.mark_all_read(user_id, Utc::now())
+        }
+    }
+}
+
+// --- 11. Handlers ---
 mod handlers {
     use super::*;
-    use auth::{AdminUser, AuthenticatedUser};
+    use auth::{AccountOwner, AdminUser, AuthenticatedSession, AuthenticatedUser};
+    use futures::{SinkExt, StreamExt};
+    use inbox::NotificationKind;
+    use notifications::NotificationEvent;
+    use sessions::{AuthMethod, ClientInfo};
+
+    const LOGIN_HISTORY_LIMIT: usize = 50;
+    const AUDIT_LOG_LIMIT: usize = 200;
 
     #[derive(Deserialize)]
     pub struct LoginPayload {
@@ -245,8 +1401,23 @@ mod handlers {
         password: String,
     }
 
+    fn token_response(
+        state: &AppState,
+        user: &User,
+        session_id: Uuid,
+        refresh_token: String,
+    ) -> Result<Json<serde_json::Value>, AppError> {
+        let token = auth::create_jwt(user.id, &user.role, session_id, &state.jwt_secret)?;
+        Ok(Json(serde_json::json!({
+            "token": token,
+            "refresh_token": refresh_token,
+            "session_id": session_id,
+        })))
+    }
+
     pub async fn login(
         State(state): State<AppState>,
+        client: ClientInfo,
         Json(payload): Json<LoginPayload>,
     ) -> Result<Json<serde_json::Value>, AppError> {
         let user = state
@@ -254,15 +1425,182 @@ mod handlers {
             .users
             .iter()
             .find(|entry| entry.value().email == payload.email)
-            .map(|entry| entry.value().clone())
-            .ok_or(AppError::InvalidCredentials)?;
+            .map(|entry| entry.value().clone());
+        let Some(user) = user else {
+            state.sessions.record_login(None, &payload.email, &client, AuthMethod::Password, false);
+            return Err(AppError::InvalidCredentials);
+        };
 
-        if !auth::verify_password(&user.password_hash, &payload.password)? {
+        let verification = state.hasher.verify(&user.password_hash, &payload.password);
+        if verification == passwords::Verification::Invalid {
+            state.sessions.record_login(Some(user.id), &user.email, &client, AuthMethod::Password, false);
             return Err(AppError::InvalidCredentials);
         }
 
-        let token = auth::create_jwt(user.id, &user.role, &state.jwt_secret)?;
-        Ok(Json(serde_json::json!({ "token": token })))
+        // The plaintext is only available here, so this is where legacy hashes
+        // get upgraded. A failed rehash must not fail an otherwise valid login.
+        if verification == passwords::Verification::ValidNeedsRehash {
+            if let Ok(new_hash) = state.hasher.hash(&payload.password) {
+                if let Some(mut stored) = state.db.users.get_mut(&user.id) {
+                    // Skip if the hash changed concurrently (e.g. a password reset)
+                    if stored.password_hash == user.password_hash {
+                        stored.password_hash = new_hash;
+                    }
+                }
+            }
+        }
+
+        let (session, refresh_token) = state.sessions.start(user.id, &client);
+        state.sessions.record_login(Some(user.id), &user.email, &client, AuthMethod::Password, true);
+        token_response(&state, &user, session.id, refresh_token)
+    }
+
+    #[derive(Deserialize)]
+    pub struct RefreshPayload {
+        refresh_token: String,
+    }
+
+    pub async fn refresh_session(
+        State(state): State<AppState>,
+        client: ClientInfo,
+        Json(payload): Json<RefreshPayload>,
+    ) -> Result<Json<serde_json::Value>, AppError> {
+        let (session, refresh_token) = state.sessions.rotate(&payload.refresh_token, &client)?;
+        let user = state
+            .db
+            .users
+            .get(&session.user_id)
+            .map(|u| u.value().clone())
+            .ok_or(AppError::InvalidToken)?;
+        if !user.is_active {
+            state.sessions.revoke(user.id, session.id)?;
+            return Err(AppError::Forbidden);
+        }
+        state.sessions.record_login(Some(user.id), &user.email, &client, AuthMethod::RefreshToken, true);
+        token_response(&state, &user, session.id, refresh_token)
+    }
+
+    #[derive(Serialize)]
+    pub struct SessionView {
+        #[serde(flatten)]
+        session: sessions::Session,
+        current: bool,
+    }
+
+    pub async fn list_sessions(
+        State(state): State<AppState>,
+        current: AuthenticatedSession,
+    ) -> Json<Vec<SessionView>> {
+        let sessions = state
+            .sessions
+            .list_active(current.user.id)
+            .into_iter()
+            .map(|session| SessionView { current: session.id == current.session_id, session })
+            .collect();
+        Json(sessions)
+    }
+
+    pub async fn revoke_session(
+        State(state): State<AppState>,
+        AccountOwner(current): AccountOwner,
+        Path(session_id): Path<Uuid>,
+    ) -> Result<StatusCode, AppError> {
+        state.sessions.revoke(current.user.id, session_id)?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    #[derive(Deserialize)]
+    pub struct ChangePasswordPayload {
+        current_password: String,
+        new_password: String,
+    }
+
+    /// Also signs out every other session, in case the old password leaked.
+    pub async fn change_password(
+        State(state): State<AppState>,
+        AccountOwner(current): AccountOwner,
+        Json(payload): Json<ChangePasswordPayload>,
+    ) -> Result<StatusCode, AppError> {
+        let user = current.user;
+        if state.hasher.verify(&user.password_hash, &payload.current_password) == passwords::Verification::Invalid {
+            return Err(AppError::InvalidCredentials);
+        }
+        let new_hash = state.hasher.hash(&payload.new_password)?;
+        state.db.users.get_mut(&user.id).ok_or(AppError::UserNotFound)?.password_hash = new_hash;
+        for session in state.sessions.list_active(user.id) {
+            if session.id != current.session_id {
+                state.sessions.revoke(user.id, session.id)?;
+            }
+        }
+        state.audit.record("user.password_changed", user.id, Some(user.id), None, serde_json::Value::Null);
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn start_impersonation(
+        State(state): State<AppState>,
+        current: AuthenticatedSession,
+        Path(user_id): Path<Uuid>,
+    ) -> Result<Json<serde_json::Value>, AppError> {
+        // An impersonation token belongs to a non-admin, so this also blocks nesting
+        if current.user.role != Role::ADMIN || current.impersonator.is_some() {
+            return Err(AppError::Forbidden);
+        }
+        let target = state
+            .db
+            .users
+            .get(&user_id)
+            .map(|u| u.value().clone())
+            .ok_or(AppError::UserNotFound)?;
+        // Acting as another admin would be a way around per-admin auditing
+        if target.id == current.user.id || target.role == Role::ADMIN || !target.is_active {
+            return Err(AppError::Forbidden);
+        }
+
+        let grant = state.impersonation.start(current.user.id, target.id, current.session_id);
+        let token = auth::create_impersonation_jwt(&grant, &target.role, &state.jwt_secret)?;
+        state.audit.record(
+            "impersonation.started",
+            current.user.id,
+            Some(target.id),
+            Some(grant.id),
+            serde_json::json!({ "expires_at": grant.expires_at }),
+        );
+        Ok(Json(serde_json::json!({
+            "token": token,
+            "impersonation_id": grant.id,
+            "expires_at": grant.expires_at,
+        })))
+    }
+
+    /// Called with the impersonation token; the admin's own token keeps working.
+    pub async fn stop_impersonation(
+        State(state): State<AppState>,
+        current: AuthenticatedSession,
+    ) -> Result<Json<impersonation::ImpersonationGrant>, AppError> {
+        let impersonator = current.impersonator.ok_or(AppError::Forbidden)?;
+        let grant = state.impersonation.stop(impersonator.grant_id).ok_or(AppError::InvalidToken)?;
+        state.audit.record(
+            "impersonation.stopped",
+            impersonator.admin_id,
+            Some(current.user.id),
+            Some(grant.id),
+            serde_json::Value::Null,
+        );
+        Ok(Json(grant))
+    }
+
+    pub async fn audit_log(
+        State(state): State<AppState>,
+        _admin: AdminUser,
+    ) -> Json<Vec<audit::AuditEvent>> {
+        Json(state.audit.recent(AUDIT_LOG_LIMIT))
+    }
+
+    pub async fn login_history(
+        State(state): State<AppState>,
+        AuthenticatedUser(user): AuthenticatedUser,
+    ) -> Json<Vec<sessions::LoginEvent>> {
+        Json(state.sessions.login_history(user.id, LOGIN_HISTORY_LIMIT))
     }
 
     #[derive(Deserialize)]
@@ -300,15 +1638,215 @@ mod handlers {
         let posts = state.db.posts.iter().map(|p| p.value().clone()).collect();
         Json(posts)
     }
+
+    pub async fn publish_post(
+        State(state): State<AppState>,
+        AuthenticatedUser(user): AuthenticatedUser,
+        Path(post_id): Path<Uuid>,
+    ) -> Result<Json<Post>, AppError> {
+        let post = {
+            let mut entry = state.db.posts.get_mut(&post_id).ok_or(AppError::PostNotFound)?;
+            if entry.user_id != user.id && user.role != Role::ADMIN {
+                return Err(AppError::Forbidden);
+            }
+            if entry.status == PostStatus::PUBLISHED {
+                return Ok(Json(entry.clone()));
+            }
+            entry.status = PostStatus::PUBLISHED;
+            entry.clone()
+        };
+
+        state.notifications.broadcast(NotificationEvent::PostPublished {
+            post_id: post.id,
+            author_id: post.user_id,
+            title: post.title.clone(),
+        });
+        Ok(Json(post))
+    }
+
+    #[derive(Deserialize)]
+    pub struct AssignRolePayload {
+        role: Role,
+    }
+
+    pub async fn assign_role(
+        State(state): State<AppState>,
+        _admin: AdminUser,
+        Path(user_id): Path<Uuid>,
+        Json(payload): Json<AssignRolePayload>,
+    ) -> Result<Json<User>, AppError> {
+        let user = {
+            let mut entry = state.db.users.get_mut(&user_id).ok_or(AppError::UserNotFound)?;
+            entry.role = payload.role.clone();
+            entry.clone()
+        };
+
+        state.notifications.send_to(
+            user.id,
+            NotificationEvent::RoleAssigned { user_id: user.id, role: payload.role.clone() },
+        );
+        state.inbox.notify(user.id, NotificationKind::RoleAssigned { role: payload.role });
+        Ok(Json(user))
+    }
+
+    #[derive(Deserialize)]
+    pub struct CreateCommentPayload {
+        body: String,
+        parent_id: Option<Uuid>,
+    }
+
+    pub async fn create_comment(
+        State(state): State<AppState>,
+        AuthenticatedUser(user): AuthenticatedUser,
+        Path(post_id): Path<Uuid>,
+        Json(payload): Json<CreateCommentPayload>,
+    ) -> Result<Json<Comment>, AppError> {
+        if !state.db.posts.contains_key(&post_id) {
+            return Err(AppError::PostNotFound);
+        }
+        let parent_author = match payload.parent_id {
+            Some(parent_id) => {
+                let parent = state
+                    .db
+                    .comments
+                    .get(&parent_id)
+                    .filter(|c| c.post_id == post_id)
+                    .ok_or(AppError::CommentNotFound)?;
+                Some(parent.author_id)
+            }
+            None => None,
+        };
+
+        let comment = Comment {
+            id: Uuid::new_v4(),
+            post_id,
+            parent_id: payload.parent_id,
+            author_id: user.id,
+            body: payload.body,
+            created_at: Utc::now(),
+        };
+        state.db.comments.insert(comment.id, comment.clone());
+
+        // Replying to yourself is not worth a notification
+        if let Some(parent_author) = parent_author.filter(|author| *author != user.id) {
+            state.inbox.notify(
+                parent_author,
+                NotificationKind::CommentReply { post_id, comment_id: comment.id, replied_by: user.id },
+            );
+        }
+        Ok(Json(comment))
+    }
+
+    #[derive(Deserialize)]
+    pub struct ListNotificationsQuery {
+        #[serde(default = "default_unread_only")]
+        unread_only: bool,
+    }
+
+    fn default_unread_only() -> bool {
+        true
+    }
+
+    pub async fn list_notifications(
+        State(state): State<AppState>,
+        AuthenticatedUser(user): AuthenticatedUser,
+        Query(query): Query<ListNotificationsQuery>,
+    ) -> Json<Vec<inbox::Notification>> {
+        Json(state.inbox.list(user.id, query.unread_only))
+    }
+
+    pub async fn mark_notification_read(
+        State(state): State<AppState>,
+        AuthenticatedUser(user): AuthenticatedUser,
+        Path(notification_id): Path<Uuid>,
+    ) -> Result<Json<inbox::Notification>, AppError> {
+        state.inbox.mark_read(user.id, notification_id).map(Json)
+    }
+
+    pub async fn mark_all_notifications_read(
+        State(state): State<AppState>,
+        AuthenticatedUser(user): AuthenticatedUser,
+    ) -> Json<serde_json::Value> {
+        let updated = state.inbox.mark_all_read(user.id);
+        Json(serde_json::json!({ "updated": updated }))
+    }
+
+    #[derive(Deserialize)]
+    pub struct WsAuthQuery {
+        token: Option<String>,
+    }
+
+    /// Browsers cannot set headers on a WebSocket handshake, so the token may
+    /// also be supplied as `?token=`.
+    pub async fn notifications_ws(
+        State(state): State<AppState>,
+        Query(query): Query<WsAuthQuery>,
+        bearer: Option<TypedHeader<Authorization<Bearer>>>,
+        ws: WebSocketUpgrade,
+    ) -> Result<Response, AppError> {
+        let token = bearer
+            .map(|TypedHeader(Authorization(b))| b.token().to_string())
+            .or(query.token)
+            .ok_or(AppError::InvalidToken)?;
+        let user = auth::authenticate(&token, &state)?.user;
+
+        Ok(ws.on_upgrade(move |socket| handle_notification_socket(socket, state, user.id)))
+    }
+
+    pub async fn password_hash_report(
+        State(state): State<AppState>,
+        _admin: AdminUser,
+    ) -> Json<passwords::MigrationReport> {
+        let hashes: Vec<String> = state
+            .db
+            .users
+            .iter()
+            .map(|entry| entry.value().password_hash.clone())
+            .collect();
+        Json(state.hasher.report(hashes.iter().map(String::as_str)))
+    }
+
+    async fn handle_notification_socket(socket: WebSocket, state: AppState, user_id: Uuid) {
+        let (conn_id, mut events) = state.notifications.register(user_id);
+        let (mut sender, mut receiver) = socket.split();
+
+        let mut send_task = tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                let Ok(text) = serde_json::to_string(&event) else { continue };
+                if sender.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Clients are not expected to send anything; we only watch for the close
+        let mut recv_task = tokio::spawn(async move {
+            while let Some(Ok(message)) = receiver.next().await {
+                if let Message::Close(_) = message {
+                    break;
+                }
+            }
+        });
+
+        tokio::select! {
+            _ = &mut send_task => recv_task.abort(),
+            _ = &mut recv_task => send_task.abort(),
+        }
+
+        state.notifications.unregister(user_id, conn_id);
+    }
 }
 
-// --- 6. Main Application Setup ---
+// --- 12. Main Application Setup ---
 #[tokio::main]
 async fn main() {
+    tracing_subscriber::fmt::init();
+
     // Initialize mock database with a user
     let db = Arc::new(MockDb::new());
+    let hasher = Arc::new(passwords::PasswordHasher::from_env());
     let admin_password = "verysecurepassword";
-    let admin_password_hash = auth::hash_password(admin_password).unwrap();
+    let admin_password_hash = hasher.hash(admin_password).unwrap();
     let admin_user = User {
         id: Uuid::new_v4(),
         email: "admin@example.com".to_string(),
@@ -319,19 +1857,62 @@ async fn main() {
     };
     db.users.insert(admin_user.id, admin_user);
 
+    // An account carried over from the bcrypt era; upgraded on first login
+    let legacy_user = User {
+        id: Uuid::new_v4(),
+        email: "legacy@example.com".to_string(),
+        password_hash: hasher
+            .hash_with(passwords::HashAlgorithm::Bcrypt, "legacypassword")
+            .unwrap(),
+        role: Role::USER,
+        is_active: true,
+        created_at: Utc::now(),
+    };
+    db.users.insert(legacy_user.id, legacy_user);
+
+    let notifications = notifications::NotificationHub::new();
     let app_state = AppState {
+        inbox: inbox::InboxService::new(db.clone(), notifications.clone()),
+        sessions: sessions::SessionService::new(db.clone()),
+        impersonation: impersonation::ImpersonationService::new(db.clone()),
+        audit: audit::AuditLog::new(db.clone()),
         db,
         jwt_secret: "a_very_secret_key".to_string(),
+        hasher,
+        notifications,
     };
 
     let app = Router::new()
         .route("/login", post(handlers::login))
+        .route("/token/refresh", post(handlers::refresh_session))
+        .route("/users/me/sessions", get(handlers::list_sessions))
+        .route("/users/me/sessions/:id", delete(handlers::revoke_session))
+        .route("/users/me/password", put(handlers::change_password))
+        .route("/users/me/logins", get(handlers::login_history))
         .route("/profile", get(handlers::get_current_user_profile))
         .route("/posts", post(handlers::create_post))
+        .route("/posts/:id/publish", post(handlers::publish_post))
+        .route("/posts/:id/comments", post(handlers::create_comment))
+        .route("/notifications", get(handlers::list_notifications))
+        .route("/notifications/read-all", post(handlers::mark_all_notifications_read))
+        .route("/notifications/:id/read", post(handlers::mark_notification_read))
         .route("/admin/posts", get(handlers::get_all_posts_admin))
+        .route("/admin/users/:id/role", put(handlers::assign_role))
+        .route("/admin/password-hashes", get(handlers::password_hash_report))
+        .route("/admin/impersonate/stop", post(handlers::stop_impersonation))
+        .route("/admin/impersonate/:user_id", post(handlers::start_impersonation))
+        .route("/admin/audit-log", get(handlers::audit_log))
+        .route("/ws/notifications", get(handlers::notifications_ws))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            impersonation::audit_impersonated_requests,
+        ))
         .with_state(app_state);
 
-    println!("Server running on http://127.0.0.1:3000");
+    tracing::info!("Server running on http://127.0.0.1:3000");
     let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Peer addresses are recorded on sessions and login events
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+        .await
+        .unwrap();
 }
\ No newline at end of file
@@ -6,8 +6,9 @@
 
 // Cargo.toml dependencies:
 [dependencies]
-axum = "0.7"
+axum = { version = "0.7", features = ["multipart"] }
 tokio = { version = "1", features = ["full"] }
+tokio-util = { version = "0.7", features = ["io-util"] }
 serde = { version = "1", features = ["derive"] }
 serde_json = "1"
 uuid = { version = "1", features = ["v4", "serde"] }
@@ -17,14 +18,41 @@ tracing = "0.1"
 tracing-subscriber = { version = "0.3", features = ["env-filter"] }
 sqlx = { version = "0.7", features = ["runtime-tokio", "sqlite", "uuid", "chrono", "json"] }
 tokio-cron-scheduler = "0.10"
+cron = "0.12"
 rand = "0.8"
+futures = "0.3"
+reqwest = { version = "0.11", features = ["json"] }
+hmac = "0.12"
+sha2 = "0.10"
+hex = "0.4"
+async-trait = "0.1"
+zip = { version = "0.6", default-features = false, features = ["deflate"] }
+lettre = { version = "0.11", default-features = false, features = ["builder", "smtp-transport", "tokio1-rustls-tls"] }
+handlebars = "5"
+redis = { version = "0.27", features = ["tokio-comp", "connection-manager", "streams"] }
+csv = "1.3"
+image = { version = "0.24", default-features = false, features = ["jpeg", "png", "webp"] }
+pulldown-cmark = { version = "0.10", default-features = false, features = ["html"] }
+ammonia = "4"
+argon2 = "0.5"
+sha1 = "0.10"
+jsonwebtoken = "9"
+
+# Job queue load test: cargo bench --bench loadtest_variation_1
+[[bench]]
+name = "loadtest_variation_1"
+path = "benches/loadtest_variation_1.rs"
+harness = false
 */
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Json},
-    routing::{get, post},
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json,
+    },
+    routing::{delete, get, post},
     Router,
 };
 use chrono::{DateTime, Utc};
@@ -34,6 +62,7 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
 use tokio_cron_scheduler::{Job, JobScheduler};
+use tokio_util::sync::CancellationToken;
 use tracing::info;
 use uuid::Uuid;
 
@@ -53,19 +82,92 @@ pub struct User {
     created_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, sqlx::Type)]
+#[allow(non_camel_case_types)]
+pub enum PostStatus {
+    DRAFT,
+    PUBLISHED,
+    /// Held by moderation until an admin approves or rejects it.
+    PENDING_REVIEW,
+}
+
+#[derive(Debug, Serialize, Clone, FromRow)]
+pub struct Post {
+    id: Uuid,
+    user_id: Uuid,
+    title: String,
+    // Derived from the title; earlier slugs live on in post_slugs as redirects
+    slug: String,
+    // Markdown; rendered to sanitized HTML on request, never stored as HTML
+    content: String,
+    status: PostStatus,
+    // When set on a DRAFT, the scheduler publishes the post once this time passes
+    publish_at: Option<DateTime<Utc>>,
+    // When the post last became PUBLISHED; cleared once it is unpublished
+    published_at: Option<DateTime<Utc>>,
+    // Denormalized from post_likes; only ever changed with in-place SQL arithmetic
+    like_count: i64,
+    // Why moderation held the post, flagged it while publishing, or an admin rejected it
+    moderation_reason: Option<String>,
+}
+
 // --- Error Handling ---
 #[derive(thiserror::Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
     Sqlx(#[from] sqlx::Error),
+    #[error("Queue error: {0}")]
+    Queue(#[from] queue_driver::DriverError),
     #[error("Job not found: {0}")]
     JobNotFound(Uuid),
+    #[error("User not found: {0}")]
+    UserNotFound(Uuid),
+    #[error("Webhook subscription not found: {0}")]
+    WebhookNotFound(Uuid),
+    #[error("Post not found: {0}")]
+    PostNotFound(Uuid),
+    #[error("No post with slug: {0}")]
+    SlugNotFound(String),
+    #[error("Avatar not found for user: {0}")]
+    AvatarNotFound(Uuid),
+    #[error("Revision {revision} of post {post_id} not found")]
+    RevisionNotFound { post_id: Uuid, revision: i64 },
+    #[error("Missing or invalid bearer token")]
+    Unauthorized,
+    #[error("Not allowed to access this resource")]
+    Forbidden,
+    #[error("Data export not found: {0}")]
+    ExportNotFound(Uuid),
+    #[error("User import not found: {0}")]
+    ImportNotFound(Uuid),
+    #[error("Erasure request not found: {0}")]
+    ErasureNotFound(Uuid),
+    #[error("Workflow not found: {0}")]
+    WorkflowNotFound(String),
+    #[error("Workflow run not found: {0}")]
+    WorkflowRunNotFound(Uuid),
+    #[error("Validation error: {0}")]
+    Validation(String),
+    #[error("Upload exceeds the {0} byte limit")]
+    PayloadTooLarge(usize),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Invalid or expired token")]
+    InvalidToken,
+    #[error("Current password is incorrect")]
+    ReauthenticationFailed,
+    #[error("Password does not meet the password policy")]
+    PasswordRejected(Vec<passwords::Violation>),
     #[error("Internal server error")]
     Internal,
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
+        let violations = match &self {
+            AppError::PasswordRejected(violations) => Some(violations.clone()),
+            _ => None,
+        };
         let (status, error_message) = match self {
             AppError::Sqlx(e) => {
                 tracing::error!("SQLx error: {:?}", e);
@@ -74,295 +176,7906 @@ impl IntoResponse for AppError {
                     "Database operation failed".to_string(),
                 )
             }
+            AppError::Queue(e) => {
+                tracing::error!("Queue driver error: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Job queue operation failed".to_string(),
+                )
+            }
             AppError::JobNotFound(id) => (
                 StatusCode::NOT_FOUND,
                 format!("Job with ID {} not found", id),
             ),
+            AppError::UserNotFound(id) => (
+                StatusCode::NOT_FOUND,
+                format!("User with ID {} not found", id),
+            ),
+            AppError::WebhookNotFound(id) => (
+                StatusCode::NOT_FOUND,
+                format!("Webhook subscription with ID {} not found", id),
+            ),
+            AppError::PostNotFound(id) => (
+                StatusCode::NOT_FOUND,
+                format!("Post with ID {} not found", id),
+            ),
+            AppError::SlugNotFound(slug) => (
+                StatusCode::NOT_FOUND,
+                format!("No post with slug '{}'", slug),
+            ),
+            AppError::AvatarNotFound(id) => (
+                StatusCode::NOT_FOUND,
+                format!("User {} has no avatar in that size", id),
+            ),
+            AppError::RevisionNotFound { .. } => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, self.to_string()),
+            AppError::Forbidden => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::ReauthenticationFailed => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::ExportNotFound(id) => (
+                StatusCode::NOT_FOUND,
+                format!("Data export with ID {} not found", id),
+            ),
+            AppError::ImportNotFound(id) => (
+                StatusCode::NOT_FOUND,
+                format!("User import with ID {} not found", id),
+            ),
+            AppError::ErasureNotFound(id) => (
+                StatusCode::NOT_FOUND,
+                format!("Erasure request with ID {} not found", id),
+            ),
+            AppError::WorkflowNotFound(name) => (
+                StatusCode::NOT_FOUND,
+                format!("Workflow '{}' not found", name),
+            ),
+            AppError::WorkflowRunNotFound(id) => (
+                StatusCode::NOT_FOUND,
+                format!("Workflow run with ID {} not found", id),
+            ),
+            AppError::Validation(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
+            AppError::PayloadTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, self.to_string()),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            AppError::InvalidToken => (
+                StatusCode::BAD_REQUEST,
+                "Confirmation token is invalid or has expired".to_string(),
+            ),
+            AppError::PasswordRejected(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
             AppError::Internal => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "An internal error occurred".to_string(),
             ),
         };
-        (status, Json(serde_json::json!({ "error": error_message }))).into_response()
+        let mut body = serde_json::json!({ "error": error_message });
+        if let Some(violations) = violations {
+            body["violations"] = serde_json::json!(violations);
+        }
+        (status, Json(body)).into_response()
+    }
+}
+
+impl From<job_queue_service::InvalidTransition> for AppError {
+    fn from(err: job_queue_service::InvalidTransition) -> Self {
+        AppError::Conflict(err.to_string())
+    }
+}
+
+// --- Clock ---
+pub(crate) mod clock {
+    use super::*;
+
+    /// Where job scheduling, backoff, token expiry and retention read the time.
+    /// `main` builds one and hands it to every service, worker and the scheduler.
+    pub trait Clock: Send + Sync {
+        fn now(&self) -> DateTime<Utc>;
+    }
+
+    pub struct SystemClock;
+
+    impl Clock for SystemClock {
+        fn now(&self) -> DateTime<Utc> {
+            Utc::now()
+        }
+    }
+
+    /// Stands still until moved, so a test can step past a backoff delay or a token
+    /// TTL instead of sleeping through it.
+    #[cfg(test)]
+    pub struct ManualClock {
+        now: std::sync::Mutex<DateTime<Utc>>,
+    }
+
+    #[cfg(test)]
+    impl ManualClock {
+        pub fn new(start: DateTime<Utc>) -> Self {
+            Self { now: std::sync::Mutex::new(start) }
+        }
+
+        pub fn advance(&self, by: chrono::Duration) {
+            *self.now.lock().unwrap() += by;
+        }
+    }
+
+    #[cfg(test)]
+    impl Clock for ManualClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.now.lock().unwrap()
+        }
     }
 }
 
 // --- Task Definitions ---
-mod tasks {
+pub(crate) mod tasks {
     use super::*;
-    use rand::Rng;
+    use clock::Clock;
+
+    /// Task failures. Whether one is retried is up to the task's `RetryPolicy`.
+    #[derive(Debug)]
+    pub enum TaskError {
+        Retryable(String),
+        Permanent(String),
+        /// The payload itself is unusable; retrying cannot help.
+        Validation(String),
+        /// The task stopped early because its job was cancelled.
+        Cancelled,
+    }
+
+    impl TaskError {
+        pub fn message(&self) -> &str {
+            match self {
+                TaskError::Retryable(msg) | TaskError::Permanent(msg) | TaskError::Validation(msg) => msg,
+                TaskError::Cancelled => "Job was cancelled",
+            }
+        }
+    }
+
+    /// Long tasks call this between stages; cancellation is cooperative.
+    fn check_cancelled(cancel: &CancellationToken) -> Result<(), TaskError> {
+        if cancel.is_cancelled() {
+            return Err(TaskError::Cancelled);
+        }
+        Ok(())
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub enum Backoff {
+        Fixed(Duration),
+        /// `base * 2^(attempt - 1)`, capped at `max`.
+        Exponential { base: Duration, max: Duration },
+    }
+
+    impl Backoff {
+        /// Delay before retry number `attempt` (1-based).
+        pub fn delay(&self, attempt: i32) -> Duration {
+            match *self {
+                Backoff::Fixed(delay) => delay,
+                Backoff::Exponential { base, max } => {
+                    let factor = 2u32.saturating_pow(attempt.saturating_sub(1).max(0) as u32);
+                    base.saturating_mul(factor).min(max)
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct RetryPolicy {
+        /// Total runs including the first; 1 means never retry.
+        pub max_attempts: i32,
+        pub backoff: Backoff,
+        pub is_retryable: fn(&TaskError) -> bool,
+    }
+
+    fn retry_unless_permanent(err: &TaskError) -> bool {
+        matches!(err, TaskError::Retryable(_))
+    }
+
+    impl RetryPolicy {
+        pub const DEFAULT: RetryPolicy = RetryPolicy {
+            max_attempts: 5,
+            backoff: Backoff::Exponential { base: Duration::from_secs(2), max: Duration::from_secs(60) },
+            is_retryable: retry_unless_permanent,
+        };
+        /// Mail providers fail transiently all the time; keep trying for about an hour.
+        pub const EMAIL: RetryPolicy = RetryPolicy {
+            max_attempts: 10,
+            backoff: Backoff::Exponential { base: Duration::from_secs(5), max: Duration::from_secs(15 * 60) },
+            is_retryable: retry_unless_permanent,
+        };
+        /// Image work is expensive; a couple of spaced-out retries, none for bad input.
+        pub const MEDIA: RetryPolicy = RetryPolicy {
+            max_attempts: 3,
+            backoff: Backoff::Fixed(Duration::from_secs(30)),
+            is_retryable: retry_unless_permanent,
+        };
+        pub const WEBHOOK: RetryPolicy = RetryPolicy {
+            max_attempts: 5,
+            backoff: Backoff::Exponential { base: Duration::from_secs(2), max: Duration::from_secs(10 * 60) },
+            is_retryable: retry_unless_permanent,
+        };
+
+        /// How long to wait before retrying after the `attempts`-th failed run, or
+        /// `None` when the job should be marked failed.
+        pub fn retry_delay(&self, err: &TaskError, attempts: i32) -> Option<Duration> {
+            ((self.is_retryable)(err) && attempts < self.max_attempts).then(|| self.backoff.delay(attempts))
+        }
+    }
+
+    impl From<String> for TaskError {
+        fn from(msg: String) -> Self {
+            TaskError::Retryable(msg)
+        }
+    }
 
     #[derive(Serialize, Deserialize, Debug, Clone)]
     #[serde(tag = "type")]
     pub enum TaskPayload {
         SendWelcomeEmail { user_id: Uuid, email: String },
         ProcessImage { post_id: Uuid, image_url: String },
+        SendEmailChangeConfirmation { user_id: Uuid, new_email: String, token: String },
+        SendEmailChangeApproval { user_id: Uuid, old_email: String, new_email: String, token: String },
+        NotifyEmailChanged { user_id: Uuid, old_email: String, new_email: String },
+        SendPasswordReset { user_id: Uuid, email: String, token: String },
+        DeliverWebhook { delivery_id: Uuid },
+        PublishScheduledPosts,
+        CompileUserDataExport { export_id: Uuid },
+        EraseUser { erasure_id: Uuid },
+        SendDataExportReady { user_id: Uuid, email: String, download_url: String, expires_at: DateTime<Utc> },
+        GenerateThumbnails { post_id: Uuid, image_url: String },
+        NotifyAuthor { post_id: Uuid, thumbnail_urls: Vec<String> },
+        FetchRemoteImage { post_id: Uuid, source_url: String },
+        ProcessAvatar { user_id: Uuid, version: Uuid },
+        ImportUsersCsv { import_id: Uuid },
+    }
+
+    /// Data a task hands to the next step of its workflow. Fields of an object are
+    /// merged into the run's context; `Null` means the task has nothing to pass on.
+    pub type TaskOutput = serde_json::Value;
+
+    pub const THUMBNAIL_SIZES: [u32; 2] = [128, 512];
+
+    /// Named queues, each drained by its own `worker::WorkerPool`, so a backlog
+    /// of image work cannot hold up email delivery.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+    #[serde(rename_all = "lowercase")]
+    #[sqlx(rename_all = "lowercase")]
+    pub enum Queue {
+        Default,
+        Emails,
+        Media,
+    }
+
+    impl Queue {
+        pub const ALL: [Queue; 3] = [Queue::Default, Queue::Emails, Queue::Media];
+
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                Queue::Default => "default",
+                Queue::Emails => "emails",
+                Queue::Media => "media",
+            }
+        }
     }
 
-    pub async fn execute_task(payload: TaskPayload, db_pool: SqlitePool) -> Result<(), String> {
+    impl TaskPayload {
+        /// The queue a job lands on unless the caller overrides it.
+        pub fn queue(&self) -> Queue {
+            match self {
+                TaskPayload::SendWelcomeEmail { .. }
+                | TaskPayload::SendEmailChangeConfirmation { .. }
+                | TaskPayload::SendEmailChangeApproval { .. }
+                | TaskPayload::NotifyEmailChanged { .. }
+                | TaskPayload::SendPasswordReset { .. }
+                | TaskPayload::SendDataExportReady { .. }
+                | TaskPayload::NotifyAuthor { .. } => Queue::Emails,
+                TaskPayload::ProcessImage { .. }
+                | TaskPayload::GenerateThumbnails { .. }
+                | TaskPayload::FetchRemoteImage { .. }
+                | TaskPayload::ProcessAvatar { .. } => Queue::Media,
+                TaskPayload::DeliverWebhook { .. }
+                | TaskPayload::PublishScheduledPosts
+                | TaskPayload::CompileUserDataExport { .. }
+                | TaskPayload::ImportUsersCsv { .. }
+                | TaskPayload::EraseUser { .. } => Queue::Default,
+            }
+        }
+
+
+        pub fn retry_policy(&self) -> RetryPolicy {
+            match self {
+                TaskPayload::SendWelcomeEmail { .. }
+                | TaskPayload::SendEmailChangeConfirmation { .. }
+                | TaskPayload::SendEmailChangeApproval { .. }
+                | TaskPayload::NotifyEmailChanged { .. }
+                | TaskPayload::SendPasswordReset { .. }
+                | TaskPayload::SendDataExportReady { .. }
+                | TaskPayload::NotifyAuthor { .. } => RetryPolicy::EMAIL,
+                TaskPayload::ProcessImage { .. }
+                | TaskPayload::GenerateThumbnails { .. }
+                | TaskPayload::FetchRemoteImage { .. }
+                | TaskPayload::ProcessAvatar { .. } => RetryPolicy::MEDIA,
+                TaskPayload::DeliverWebhook { .. } => RetryPolicy::WEBHOOK,
+                TaskPayload::PublishScheduledPosts
+                | TaskPayload::CompileUserDataExport { .. }
+                | TaskPayload::ImportUsersCsv { .. }
+                | TaskPayload::EraseUser { .. } => RetryPolicy::DEFAULT,
+            }
+        }
+    }
+
+    fn validate_image_url(image_url: &str) -> Result<(), TaskError> {
+        let is_http = image_url.starts_with("https://") || image_url.starts_with("http://");
+        if !is_http || image_url.contains(char::is_whitespace) {
+            return Err(TaskError::Validation(format!("'{}' is not an http(s) image URL", image_url)));
+        }
+        Ok(())
+    }
+
+    /// Fires post.published webhooks unless the post's author has opted out.
+    pub async fn dispatch_post_published(db_pool: &SqlitePool, clock: &Arc<dyn Clock>, post_id: Uuid) {
+        let author: Option<Uuid> = match sqlx::query_scalar("SELECT user_id FROM posts WHERE id = ?")
+            .bind(post_id)
+            .fetch_optional(db_pool)
+            .await
+        {
+            Ok(author) => author,
+            Err(e) => {
+                tracing::error!(?post_id, "Failed to look up post author: {}", e);
+                return;
+            }
+        };
+        if let Some(author_id) = author {
+            match preferences::is_enabled(db_pool, author_id, preferences::Channel::Webhook, webhooks::POST_PUBLISHED).await {
+                Ok(false) => return,
+                Ok(true) => {}
+                Err(e) => tracing::warn!(?post_id, "Preference lookup failed, dispatching anyway: {}", e),
+            }
+        }
+        let webhook_service = webhooks::WebhookService::new(
+            db_pool.clone(),
+            job_queue_service::JobQueueService::new(db_pool.clone(), clock.clone()),
+            clock.clone(),
+        );
+        if let Err(e) = webhook_service
+            .dispatch(webhooks::POST_PUBLISHED, serde_json::json!({ "post_id": post_id }))
+            .await
+        {
+            tracing::error!(?post_id, "Failed to dispatch post.published webhooks: {}", e);
+        }
+    }
+
+    pub async fn execute_task(
+        payload: TaskPayload,
+        db_pool: SqlitePool,
+        http: &http_client::HttpClientService,
+        clock: &Arc<dyn Clock>,
+        progress: &events::ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<TaskOutput, TaskError> {
         match payload {
             TaskPayload::SendWelcomeEmail { user_id, email } => {
-                info!(?user_id, "Starting to send welcome email to {}", email);
-                // Simulate a fallible network operation
-                sleep(Duration::from_secs(2)).await;
-                if rand::thread_rng().gen_bool(0.2) { // 20% chance of failure
-                    let err_msg = "Failed to connect to SMTP server".to_string();
-                    tracing::error!("{}", err_msg);
-                    return Err(err_msg);
+                if !preferences::is_enabled(&db_pool, user_id, preferences::Channel::Email, preferences::ACCOUNT_WELCOME)
+                    .await
+                    .map_err(|e| e.to_string())?
+                {
+                    info!(?user_id, "Welcome email disabled by notification preferences");
+                    return Ok(TaskOutput::Null);
                 }
+                info!(?user_id, "Starting to send welcome email to {}", email);
+                mailer::send_template(mailer::WELCOME, &email, serde_json::json!({ "email": email })).await?;
                 info!("Successfully sent welcome email to {}", email);
-                Ok(())
+                Ok(TaskOutput::Null)
             }
             TaskPayload::ProcessImage { post_id, image_url } => {
+                validate_image_url(&image_url)?;
                 info!(?post_id, "Starting image processing for {}", image_url);
                 // Step 1: Download
                 sleep(Duration::from_secs(1)).await;
                 info!(?post_id, "Downloaded image from {}", image_url);
+                progress.report(25, "Downloaded image");
+                check_cancelled(cancel)?;
                 // Step 2: Resize
                 sleep(Duration::from_secs(2)).await;
                 info!(?post_id, "Resized image");
+                progress.report(50, "Resized image");
+                check_cancelled(cancel)?;
                 // Step 3: Watermark
                 sleep(Duration::from_secs(1)).await;
                 info!(?post_id, "Watermarked image");
+                progress.report(75, "Watermarked image");
+                // Last chance to stop; past the upload the post is published
+                check_cancelled(cancel)?;
                 // Step 4: Upload to storage
                 sleep(Duration::from_secs(1)).await;
                 info!(?post_id, "Uploaded processed image to storage");
-                // Here you would update the post status in the DB
-                let _ = sqlx::query("UPDATE posts SET status = 'PUBLISHED' WHERE id = ?")
-                    .bind(post_id)
-                    .execute(&db_pool)
-                    .await;
-                Ok(())
+                progress.report(100, "Uploaded processed image");
+                match post_service::publish_reviewed(&db_pool, moderation::from_env().as_ref(), clock.as_ref(), post_id).await {
+                    Ok(true) => dispatch_post_published(&db_pool, clock, post_id).await,
+                    Ok(false) => info!(?post_id, "Post held for moderation, not published"),
+                    Err(e) => tracing::error!(?post_id, "Failed to publish post: {}", e),
+                }
+                Ok(serde_json::json!({ "processed_image_url": format!("{}?processed=1", image_url) }))
+            }
+            TaskPayload::FetchRemoteImage { post_id, source_url } => {
+                remote_image::fetch_and_store(http, clock.as_ref(), post_id, &source_url).await
+            }
+            TaskPayload::ProcessAvatar { user_id, version } => avatars::process(&db_pool, user_id, version).await,
+            TaskPayload::GenerateThumbnails { post_id, image_url } => {
+                validate_image_url(&image_url)?;
+                let mut thumbnail_urls = Vec::with_capacity(THUMBNAIL_SIZES.len());
+                for (i, size) in THUMBNAIL_SIZES.iter().enumerate() {
+                    check_cancelled(cancel)?;
+                    sleep(Duration::from_millis(500)).await;
+                    thumbnail_urls.push(format!("{}&thumb={}", image_url, size));
+                    progress.report((((i + 1) * 100) / THUMBNAIL_SIZES.len()) as u8, &format!("Generated {}px thumbnail", size));
+                }
+                info!(?post_id, "Generated {} thumbnails", thumbnail_urls.len());
+                Ok(serde_json::json!({ "thumbnail_urls": thumbnail_urls }))
+            }
+            TaskPayload::NotifyAuthor { post_id, thumbnail_urls } => {
+                let author: Option<(Uuid, String, String)> = sqlx::query_as(
+                    "SELECT u.id, u.email, p.title FROM posts p JOIN users u ON u.id = p.user_id WHERE p.id = ?",
+                )
+                .bind(post_id)
+                .fetch_optional(&db_pool)
+                .await
+                .map_err(|e| e.to_string())?;
+                let Some((user_id, email, title)) = author else {
+                    return Err(TaskError::Permanent(format!("post {} or its author no longer exists", post_id)));
+                };
+                if !preferences::is_enabled(&db_pool, user_id, preferences::Channel::Email, preferences::POST_IMAGES_READY)
+                    .await
+                    .map_err(|e| e.to_string())?
+                {
+                    info!(?user_id, "Post image email disabled by notification preferences");
+                    return Ok(TaskOutput::Null);
+                }
+                let data = serde_json::json!({ "title": title, "thumbnail_urls": thumbnail_urls });
+                mailer::send_template(mailer::POST_IMAGES_READY, &email, data).await?;
+                Ok(TaskOutput::Null)
+            }
+            TaskPayload::SendEmailChangeConfirmation { user_id, new_email, token } => {
+                info!(?user_id, "Sending email change confirmation to {}", new_email);
+                let data = serde_json::json!({
+                    "email": new_email,
+                    "token": token,
+                    "expires_in_hours": email_change_service::TOKEN_TTL_HOURS,
+                });
+                mailer::send_template(mailer::EMAIL_VERIFICATION, &new_email, data).await?;
+                Ok(TaskOutput::Null)
+            }
+            TaskPayload::SendEmailChangeApproval { user_id, old_email, new_email, token } => {
+                info!(?user_id, "Asking {} to approve the change to {}", old_email, new_email);
+                let data = serde_json::json!({
+                    "new_email": new_email,
+                    "token": token,
+                    "expires_in_hours": email_change_service::TOKEN_TTL_HOURS,
+                });
+                mailer::send_template(mailer::EMAIL_CHANGE_APPROVAL, &old_email, data).await?;
+                Ok(TaskOutput::Null)
+            }
+            TaskPayload::NotifyEmailChanged { user_id, old_email, new_email } => {
+                info!(?user_id, "Notifying {} that the account email changed to {}", old_email, new_email);
+                mailer::send_template(mailer::EMAIL_CHANGED, &old_email, serde_json::json!({ "new_email": new_email })).await?;
+                Ok(TaskOutput::Null)
+            }
+            TaskPayload::SendPasswordReset { user_id, email, token } => {
+                info!(?user_id, "Sending password reset link to {}", email);
+                let base_url = std::env::var("PUBLIC_BASE_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+                let data = serde_json::json!({
+                    "email": email,
+                    "reset_url": format!("{}/password-reset?token={}", base_url.trim_end_matches('/'), token),
+                    "expires_in_hours": password_reset_service::TOKEN_TTL_HOURS,
+                });
+                mailer::send_template(mailer::PASSWORD_RESET, &email, data).await?;
+                Ok(TaskOutput::Null)
+            }
+            TaskPayload::DeliverWebhook { delivery_id } => {
+                webhooks::deliver(&db_pool, http, clock.as_ref(), delivery_id).await?;
+                Ok(TaskOutput::Null)
+            }
+            TaskPayload::CompileUserDataExport { export_id } => {
+                data_export::compile(&db_pool, clock, export_id).await?;
+                Ok(TaskOutput::Null)
+            }
+            TaskPayload::EraseUser { erasure_id } => {
+                erasure::run(&db_pool, clock.as_ref(), erasure_id).await?;
+                Ok(TaskOutput::Null)
+            }
+            TaskPayload::ImportUsersCsv { import_id } => user_import::run(&db_pool, clock.as_ref(), import_id, progress, cancel).await,
+            TaskPayload::SendDataExportReady { user_id, email, download_url, expires_at } => {
+                if !preferences::is_enabled(&db_pool, user_id, preferences::Channel::Email, preferences::DATA_EXPORT_READY)
+                    .await
+                    .map_err(|e| e.to_string())?
+                {
+                    info!(?user_id, "Data export email disabled by notification preferences");
+                    return Ok(TaskOutput::Null);
+                }
+                info!(?user_id, "Sending data export link to {} (valid until {})", email, expires_at);
+                let data = serde_json::json!({ "download_url": download_url, "expires_at": expires_at.to_rfc2822() });
+                mailer::send_template(mailer::DATA_EXPORT_READY, &email, data).await?;
+                Ok(TaskOutput::Null)
+            }
+            TaskPayload::PublishScheduledPosts => {
+                let published = post_service::publish_due_posts(&db_pool, moderation::from_env().as_ref(), clock.as_ref())
+                    .await
+                    .map_err(|e| format!("Failed to publish scheduled posts: {}", e))?;
+                for &post_id in &published {
+                    dispatch_post_published(&db_pool, clock, post_id).await;
+                }
+                info!("Published {} scheduled posts", published.len());
+                Ok(TaskOutput::Null)
             }
         }
     }
 }
 
-// --- Job Queue Service ---
-mod job_queue_service {
+// --- Mailer ---
+mod mailer {
     use super::*;
+    use handlebars::Handlebars;
+    use lettre::{
+        message::{Mailbox, MultiPart},
+        transport::smtp::authentication::Credentials,
+        AsyncSmtpTransport, AsyncTransport, Tokio1Executor,
+    };
+    use std::sync::OnceLock;
 
-    #[derive(Debug, Clone, Serialize, FromRow)]
-    pub struct JobRecord {
-        pub id: Uuid,
-        #[sqlx(json)]
-        pub payload: tasks::TaskPayload,
-        pub status: String,
-        pub attempts: i32,
-        pub run_at: DateTime<Utc>,
-        pub created_at: DateTime<Utc>,
-        pub error_message: Option<String>,
+    pub const WELCOME: &str = "welcome";
+    pub const PASSWORD_RESET: &str = "password_reset";
+    pub const EMAIL_VERIFICATION: &str = "email_verification";
+    pub const EMAIL_CHANGE_APPROVAL: &str = "email_change_approval";
+    pub const EMAIL_CHANGED: &str = "email_changed";
+    pub const DATA_EXPORT_READY: &str = "data_export_ready";
+    pub const POST_IMAGES_READY: &str = "post_images_ready";
+
+    #[derive(Debug, Clone)]
+    pub struct Message {
+        pub to: String,
+        pub subject: String,
+        pub html_body: String,
+        pub text_body: String,
     }
 
-    #[derive(Clone)]
-    pub struct JobQueueService {
-        db_pool: SqlitePool,
+    /// Providers report failures differently; everything is folded into these two
+    /// so the worker can decide whether a retry has any chance of succeeding.
+    #[derive(Debug, thiserror::Error)]
+    pub enum MailError {
+        #[error("transient mail error: {0}")]
+        Transient(String),
+        #[error("permanent mail error: {0}")]
+        Permanent(String),
     }
 
-    impl JobQueueService {
-        pub fn new(db_pool: SqlitePool) -> Self {
-            Self { db_pool }
+    impl From<MailError> for tasks::TaskError {
+        fn from(err: MailError) -> Self {
+            match err {
+                MailError::Transient(_) => tasks::TaskError::Retryable(err.to_string()),
+                MailError::Permanent(_) => tasks::TaskError::Permanent(err.to_string()),
+            }
         }
+    }
 
-        pub async fn schedule_task(&self, payload: tasks::TaskPayload) -> Result<Uuid, AppError> {
-            let job_id = Uuid::new_v4();
-            sqlx::query(
-                "INSERT INTO jobs (id, payload, status, attempts, run_at) VALUES (?, ?, 'pending', 0, ?)",
-            )
-            .bind(job_id)
-            .bind(serde_json::to_value(&payload).unwrap())
-            .bind(Utc::now())
-            .execute(&self.db_pool)
-            .await?;
-            Ok(job_id)
-        }
+    #[async_trait::async_trait]
+    pub trait Mailer: Send + Sync {
+        fn provider(&self) -> &'static str;
+        async fn send(&self, message: &Message) -> Result<(), MailError>;
+    }
 
-        pub async fn get_job_status(&self, job_id: Uuid) -> Result<JobRecord, AppError> {
-            sqlx::query_as::<_, JobRecord>("SELECT * FROM jobs WHERE id = ?")
-                .bind(job_id)
-                .fetch_optional(&self.db_pool)
-                .await?
-                .ok_or(AppError::JobNotFound(job_id))
+    pub struct SmtpMailer {
+        transport: AsyncSmtpTransport<Tokio1Executor>,
+        from: Mailbox,
+    }
+
+    impl SmtpMailer {
+        pub fn new(host: &str, port: u16, credentials: Option<(String, String)>, from: &str) -> Result<Self, String> {
+            let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                .map_err(|e| e.to_string())?
+                .port(port);
+            if let Some((username, password)) = credentials {
+                builder = builder.credentials(Credentials::new(username, password));
+            }
+            let from = from.parse().map_err(|e: lettre::address::AddressError| e.to_string())?;
+            Ok(Self { transport: builder.build(), from })
         }
     }
-}
 
-// --- Background Worker ---
-mod worker {
-    use super::*;
-    use job_queue_service::JobRecord;
+    #[async_trait::async_trait]
+    impl Mailer for SmtpMailer {
+        fn provider(&self) -> &'static str {
+            "smtp"
+        }
 
-    const MAX_RETRIES: i32 = 5;
+        async fn send(&self, message: &Message) -> Result<(), MailError> {
+            let to: Mailbox = message
+                .to
+                .parse()
+                .map_err(|e: lettre::address::AddressError| MailError::Permanent(format!("invalid recipient: {}", e)))?;
+            let email = lettre::Message::builder()
+                .from(self.from.clone())
+                .to(to)
+                .subject(&message.subject)
+                .multipart(MultiPart::alternative_plain_html(
+                    message.text_body.clone(),
+                    message.html_body.clone(),
+                ))
+                .map_err(|e| MailError::Permanent(e.to_string()))?;
 
-    pub fn spawn_worker(db_pool: SqlitePool) {
-        tokio::spawn(async move {
-            info!("Background worker started.");
-            loop {
-                match fetch_and_process_job(&db_pool).await {
-                    Ok(Some(job_id)) => info!("Successfully processed job {}", job_id),
-                    Ok(None) => sleep(Duration::from_secs(5)).await, // No jobs, wait a bit
-                    Err(e) => tracing::error!("Error in worker loop: {:?}", e),
+            self.transport.send(email).await.map(|_| ()).map_err(|e| {
+                // 5xx replies (unknown mailbox, rejected content) will not change on retry
+                if e.is_permanent() {
+                    MailError::Permanent(e.to_string())
+                } else {
+                    MailError::Transient(e.to_string())
                 }
-            }
-        });
+            })
+        }
     }
 
-    async fn fetch_and_process_job(db_pool: &SqlitePool) -> Result<Option<Uuid>, sqlx::Error> {
-        let mut tx = db_pool.begin().await?;
+    /// Writes messages to the log instead of sending them. The default outside production.
+    pub struct LogMailer;
 
-        let maybe_job: Option<JobRecord> = sqlx::query_as(
-            "SELECT * FROM jobs WHERE status = 'pending' AND run_at <= ? ORDER BY created_at LIMIT 1",
-        )
-        .bind(Utc::now())
-        .fetch_optional(&mut *tx)
-        .await?;
+    #[async_trait::async_trait]
+    impl Mailer for LogMailer {
+        fn provider(&self) -> &'static str {
+            "log"
+        }
 
-        let job = match maybe_job {
-            Some(job) => job,
-            None => {
-                tx.commit().await?;
-                return Ok(None);
-            }
+        async fn send(&self, message: &Message) -> Result<(), MailError> {
+            info!(to = %message.to, subject = %message.subject, "Email (not sent):\n{}", message.text_body);
+            Ok(())
+        }
+    }
+
+    fn build_from_env() -> Arc<dyn Mailer> {
+        if std::env::var("MAILER").as_deref() != Ok("smtp") {
+            return Arc::new(LogMailer);
+        }
+        let host = std::env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
+        let port = std::env::var("SMTP_PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(587);
+        let credentials = match (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+            (Ok(username), Ok(password)) => Some((username, password)),
+            _ => None,
         };
+        let from = std::env::var("MAIL_FROM").unwrap_or_else(|_| "no-reply@example.com".to_string());
+        match SmtpMailer::new(&host, port, credentials, &from) {
+            Ok(mailer) => Arc::new(mailer),
+            Err(e) => panic!("Invalid SMTP configuration: {}", e),
+        }
+    }
 
-        sqlx::query("UPDATE jobs SET status = 'running' WHERE id = ?")
-            .bind(job.id)
-            .execute(&mut *tx)
-            .await?;
-        
-        tx.commit().await?;
+    // One instance per process so the SMTP connection pool is shared across tasks
+    pub fn global() -> Arc<dyn Mailer> {
+        static MAILER: OnceLock<Arc<dyn Mailer>> = OnceLock::new();
+        MAILER.get_or_init(build_from_env).clone()
+    }
 
-        let task_result = tasks::execute_task(job.payload.clone(), db_pool.clone()).await;
+    /// Email templates, each with a subject, HTML and plain-text part. Only the HTML part
+    /// is HTML-escaped; subjects and text bodies are rendered by a registry with
+    /// `no_escape`, so a title like `Q&A` isn't mailed as `Q&amp;A`.
+    pub struct TemplateRegistry {
+        html: Handlebars<'static>,
+        plain: Handlebars<'static>,
+        names: Vec<&'static str>,
+    }
 
-        match task_result {
-            Ok(_) => {
-                sqlx::query("UPDATE jobs SET status = 'completed' WHERE id = ?")
-                    .bind(job.id)
-                    .execute(db_pool)
-                    .await?;
-            }
-            Err(e) => {
-                let new_attempts = job.attempts + 1;
-                if new_attempts >= MAX_RETRIES {
-                    sqlx::query("UPDATE jobs SET status = 'failed', error_message = ? WHERE id = ?")
-                        .bind(e)
-                        .bind(job.id)
-                        .execute(db_pool)
-                        .await?;
-                } else {
-                    let backoff_seconds = 2i64.pow(new_attempts as u32);
-                    let next_run_at = Utc::now() + chrono::Duration::seconds(backoff_seconds);
-                    sqlx::query(
-                        "UPDATE jobs SET status = 'pending', attempts = ?, run_at = ?, error_message = ? WHERE id = ?",
-                    )
-                    .bind(new_attempts)
-                    .bind(next_run_at)
-                    .bind(e)
-                    .bind(job.id)
-                    .execute(db_pool)
-                    .await?;
-                }
+    impl TemplateRegistry {
+        pub fn new() -> Self {
+            let mut registry = Self { html: Handlebars::new(), plain: Handlebars::new(), names: Vec::new() };
+            registry.plain.register_escape_fn(handlebars::no_escape);
+            // Missing variables are bugs, not empty strings
+            registry.html.set_strict_mode(true);
+            registry.plain.set_strict_mode(true);
+            registry.register(
+                WELCOME,
+                "Welcome to {{app_name}}",
+                "<h1>Welcome, {{email}}!</h1><p>Your {{app_name}} account is ready.</p>",
+                "Welcome, {{email}}!\n\nYour {{app_name}} account is ready.",
+            );
+            registry.register(
+                PASSWORD_RESET,
+                "Reset your {{app_name}} password",
+                "<p>Someone asked to reset the password for {{email}}.</p><p><a href=\"{{reset_url}}\">Choose a new password</a>. The link expires in {{expires_in_hours}} hours.</p><p>If this wasn't you, ignore this email.</p>",
+                "Someone asked to reset the password for {{email}}.\n\nChoose a new password: {{reset_url}}\nThe link expires in {{expires_in_hours}} hours.\n\nIf this wasn't you, ignore this email.",
+            );
+            registry.register(
+                EMAIL_VERIFICATION,
+                "Confirm your new email address",
+                "<p>Use this code to confirm {{email}} as your new address:</p><p><code>{{token}}</code></p><p>It expires in {{expires_in_hours}} hours.</p>",
+                "Use this code to confirm {{email}} as your new address:\n\n{{token}}\n\nIt expires in {{expires_in_hours}} hours.",
+            );
+            registry.register(
+                EMAIL_CHANGE_APPROVAL,
+                "Approve the new email address for your account",
+                "<p>Someone asked to change the email address on your {{app_name}} account to {{new_email}}.</p><p>To approve, use this code:</p><p><code>{{token}}</code></p><p>It expires in {{expires_in_hours}} hours. If this wasn't you, ignore this email and change your password.</p>",
+                "Someone asked to change the email address on your {{app_name}} account to {{new_email}}.\n\nTo approve, use this code:\n\n{{token}}\n\nIt expires in {{expires_in_hours}} hours. If this wasn't you, ignore this email and change your password.",
+            );
+            registry.register(
+                EMAIL_CHANGED,
+                "Your email address was changed",
+                "<p>The email address on your {{app_name}} account was changed to {{new_email}}.</p><p>If you didn't make this change, contact support immediately.</p>",
+                "The email address on your {{app_name}} account was changed to {{new_email}}.\n\nIf you didn't make this change, contact support immediately.",
+            );
+            registry.register(
+                DATA_EXPORT_READY,
+                "Your data export is ready",
+                "<p>Your data export is ready. <a href=\"{{download_url}}\">Download it</a> before {{expires_at}}.</p>",
+                "Your data export is ready. Download it before {{expires_at}}:\n\n{{download_url}}",
+            );
+            registry.register(
+                POST_IMAGES_READY,
+                "Images for \"{{title}}\" are ready",
+                "<p>We finished processing the images for <strong>{{title}}</strong>.</p><ul>{{#each thumbnail_urls}}<li><a href=\"{{this}}\">{{this}}</a></li>{{/each}}</ul>",
+                "We finished processing the images for \"{{title}}\".\n\n{{#each thumbnail_urls}}{{this}}\n{{/each}}",
+            );
+            registry
+        }
+
+        fn register(&mut self, name: &'static str, subject: &str, html: &str, text: &str) {
+            for (part, source) in [("subject", subject), ("html", html), ("text", text)] {
+                self.engine(part)
+                    .register_template_string(&format!("{}.{}", name, part), source)
+                    .unwrap_or_else(|e| panic!("Invalid email template {}.{}: {}", name, part, e));
             }
+            self.names.push(name);
         }
 
-        Ok(Some(job.id))
-    }
-}
+        fn engine(&mut self, part: &str) -> &mut Handlebars<'static> {
+            if part == "html" { &mut self.html } else { &mut self.plain }
+        }
 
-// --- Periodic Task Scheduler ---
-mod scheduler {
-    use super::*;
-    
-    pub async fn setup_scheduler(db_pool: SqlitePool) -> JobScheduler {
-        let sched = JobScheduler::new().await.expect("Failed to create scheduler");
+        pub fn names(&self) -> &[&'static str] {
+            &self.names
+        }
 
-        // Example: A periodic task to clean up old, failed jobs every hour
-        let cleanup_job = Job::new_async("0 0 * * * *", move |uuid, mut l| {
-            let pool = db_pool.clone();
-            Box::pin(async move {
-                info!("Running periodic job (ID: {}): Cleaning up old failed jobs.", uuid);
-                let cutoff_date = Utc::now() - chrono::Duration::days(30);
-                match sqlx::query("DELETE FROM jobs WHERE status = 'failed' AND created_at < ?")
-                    .bind(cutoff_date)
-                    .execute(&pool)
-                    .await
-                {
-                    Ok(result) => info!("Cleaned up {} old failed jobs.", result.rows_affected()),
-                    Err(e) => tracing::error!("Periodic cleanup job failed: {}", e),
-                }
-                let next_tick = l.next_tick_for_job(uuid).await;
-                match next_tick {
-                    Ok(Some(ts)) => info!("Next cleanup run at: {:?}", ts),
-                    _ => info!("Could not get next cleanup run time."),
-                }
+        /// Renders a template. `app_name` is filled in unless the caller provides one.
+        pub fn render(&self, name: &str, to: &str, data: &serde_json::Value) -> Result<Message, MailError> {
+            if !self.names.contains(&name) {
+                return Err(MailError::Permanent(format!("unknown email template '{}'", name)));
+            }
+            let mut data = data.clone();
+            if let Some(map) = data.as_object_mut() {
+                map.entry("app_name").or_insert_with(|| serde_json::json!("Acme"));
+            }
+            let render = |part: &str| {
+                let engine = if part == "html" { &self.html } else { &self.plain };
+                engine
+                    .render(&format!("{}.{}", name, part), &data)
+                    .map_err(|e| MailError::Permanent(format!("failed to render {}.{}: {}", name, part, e)))
+            };
+            Ok(Message {
+                to: to.to_string(),
+                subject: render("subject")?,
+                html_body: render("html")?,
+                text_body: render("text")?,
             })
-        }).expect("Failed to create cleanup job");
+        }
+    }
 
-        sched.add(cleanup_job).await.expect("Failed to add job to scheduler");
-        sched.start().await.expect("Failed to start scheduler");
-        info!("Periodic job scheduler started.");
+    pub fn templates() -> &'static TemplateRegistry {
+        static TEMPLATES: OnceLock<TemplateRegistry> = OnceLock::new();
+        TEMPLATES.get_or_init(TemplateRegistry::new)
+    }
+
+    /// Controls the `/dev/emails` preview routes. On by default only with an explicit
+    /// `APP_ENV=development`; when enabled elsewhere the routes additionally require an admin.
+    #[derive(Debug, Clone, Copy)]
+    pub struct PreviewConfig {
+        pub enabled: bool,
+        pub require_admin: bool,
+    }
+
+    impl PreviewConfig {
+        pub fn from_env() -> Self {
+            Self::from_values(
+                std::env::var("APP_ENV").ok().as_deref(),
+                std::env::var("EMAIL_PREVIEWS_ENABLED").ok().as_deref(),
+            )
+        }
+
+        /// An unset `APP_ENV` counts as production.
+        fn from_values(app_env: Option<&str>, previews_enabled: Option<&str>) -> Self {
+            let is_dev = app_env == Some("development");
+            let enabled = previews_enabled.map_or(is_dev, |v| v == "true" || v == "1");
+            Self { enabled, require_admin: !is_dev }
+        }
+    }
+
+    /// Representative data for rendering a template without a real job behind it.
+    /// Expiry times are counted from `now`.
+    pub fn sample_data(name: &str, now: DateTime<Utc>) -> Option<serde_json::Value> {
+        let data = match name {
+            WELCOME => serde_json::json!({ "email": "ada@example.com" }),
+            PASSWORD_RESET => serde_json::json!({
+                "email": "ada@example.com",
+                "reset_url": "http://localhost:3000/password-reset?token=sample-token",
+                "expires_in_hours": password_reset_service::TOKEN_TTL_HOURS,
+            }),
+            EMAIL_VERIFICATION => serde_json::json!({
+                "email": "ada.new@example.com",
+                "token": "sample-confirmation-token",
+                "expires_in_hours": email_change_service::TOKEN_TTL_HOURS,
+            }),
+            EMAIL_CHANGE_APPROVAL => serde_json::json!({
+                "new_email": "ada.new@example.com",
+                "token": "sample-approval-token",
+                "expires_in_hours": email_change_service::TOKEN_TTL_HOURS,
+            }),
+            EMAIL_CHANGED => serde_json::json!({ "new_email": "ada.new@example.com" }),
+            DATA_EXPORT_READY => serde_json::json!({
+                "download_url": "http://localhost:3000/downloads/exports/sample.zip?expires=0&signature=sample",
+                "expires_at": (now + chrono::Duration::hours(data_export::DOWNLOAD_TTL_HOURS)).to_rfc2822(),
+            }),
+            POST_IMAGES_READY => serde_json::json!({
+                "title": "Hello, world",
+                "thumbnail_urls": ["https://example.com/image.jpg?processed=1&thumb=128"],
+            }),
+            _ => return None,
+        };
+        Some(data)
+    }
+
+    pub async fn send_template(name: &str, to: &str, data: serde_json::Value) -> Result<(), MailError> {
+        let message = templates().render(name, to, &data)?;
+        let mailer = global();
+        mailer.send(&message).await.map_err(|e| {
+            tracing::warn!(provider = mailer.provider(), template = name, "Email send failed: {}", e);
+            e
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn only_the_html_part_is_escaped() {
+            let data = serde_json::json!({
+                "title": "Q&A <draft>",
+                "thumbnail_urls": ["https://example.com/a.jpg?w=128&h=128"],
+            });
+            let message = TemplateRegistry::new().render(POST_IMAGES_READY, "ada@example.com", &data).unwrap();
+            assert_eq!(message.subject, "Images for \"Q&A <draft>\" are ready");
+            assert!(message.text_body.contains("\"Q&A <draft>\""));
+            assert!(message.text_body.contains("?w=128&h=128"));
+            assert!(message.html_body.contains("Q&amp;A &lt;draft&gt;"));
+        }
+
+        #[test]
+        fn previews_are_open_only_in_explicit_development() {
+            let unset = PreviewConfig::from_values(None, None);
+            assert!(!unset.enabled);
+            let production = PreviewConfig::from_values(Some("production"), None);
+            assert!(!production.enabled);
+
+            let development = PreviewConfig::from_values(Some("development"), None);
+            assert!(development.enabled && !development.require_admin);
+
+            // Opting in anywhere else still puts the routes behind admin auth
+            let opted_in = PreviewConfig::from_values(None, Some("true"));
+            assert!(opted_in.enabled && opted_in.require_admin);
+        }
+    }
+}
+
+// --- Queue Drivers ---
+pub(crate) mod queue_driver {
+    use super::*;
+    use redis::aio::ConnectionManager;
+    use redis::streams::{StreamAutoClaimOptions, StreamAutoClaimReply, StreamId, StreamReadOptions, StreamReadReply};
+    use clock::Clock;
+    use redis::AsyncCommands;
+    use std::sync::OnceLock;
+
+    #[derive(thiserror::Error, Debug)]
+    pub enum DriverError {
+        #[error("Database error: {0}")]
+        Sqlx(#[from] sqlx::Error),
+        #[error("Redis error: {0}")]
+        Redis(#[from] redis::RedisError),
+    }
+
+    /// A committed job that workers should be told about.
+    #[derive(Debug, Clone, Copy)]
+    pub struct QueuedJob {
+        pub id: Uuid,
+        pub queue: tasks::Queue,
+        pub run_at: DateTime<Utc>,
+    }
+
+    /// A job handed to a worker. `receipt` is whatever the driver needs to ack it.
+    #[derive(Debug)]
+    pub struct Delivery {
+        pub job_id: Uuid,
+        queue: tasks::Queue,
+        receipt: Option<String>,
+    }
+
+    /// Decides how workers find ready jobs. Under every driver the `jobs` table stays
+    /// the source of truth: workers claim a delivered job with a guarded update, so
+    /// stale and duplicate deliveries are harmless.
+    #[async_trait::async_trait]
+    pub trait QueueDriver: Send + Sync {
+        /// Called once the job's row is committed, and again whenever it is requeued.
+        async fn push(&self, job: QueuedJob) -> Result<(), DriverError>;
+        async fn push_many(&self, jobs: &[QueuedJob]) -> Result<(), DriverError> {
+            for job in jobs {
+                self.push(*job).await?;
+            }
+            Ok(())
+        }
+        /// The next job on `queue` that is due, if any.
+        async fn next(&self, queue: tasks::Queue, worker_id: &str) -> Result<Option<Delivery>, DriverError>;
+        /// The worker is done with the delivery, whatever became of the job.
+        async fn ack(&self, delivery: &Delivery) -> Result<(), DriverError>;
+    }
+
+    /// Polls the `jobs` table directly. Nothing extra to run, but every idle worker
+    /// queries the database every few seconds.
+    pub struct SqlQueueDriver {
+        db_pool: SqlitePool,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl SqlQueueDriver {
+        pub fn new(db_pool: SqlitePool, clock: Arc<dyn Clock>) -> Self {
+            Self { db_pool, clock }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl QueueDriver for SqlQueueDriver {
+        async fn push(&self, _job: QueuedJob) -> Result<(), DriverError> {
+            Ok(())
+        }
+
+        async fn next(&self, queue: tasks::Queue, _worker_id: &str) -> Result<Option<Delivery>, DriverError> {
+            let job_id: Option<Uuid> = sqlx::query_scalar(
+                "SELECT id FROM jobs WHERE status = 'pending' AND queue = ? AND run_at <= ? ORDER BY created_at LIMIT 1",
+            )
+            .bind(queue)
+            .bind(self.clock.now())
+            .fetch_optional(&self.db_pool)
+            .await?;
+            Ok(job_id.map(|job_id| Delivery { job_id, queue, receipt: None }))
+        }
+
+        async fn ack(&self, _delivery: &Delivery) -> Result<(), DriverError> {
+            Ok(())
+        }
+    }
+
+    const CONSUMER_GROUP: &str = "workers";
+    /// Deliveries unacked for this long are taken over from their (presumably dead) consumer.
+    const CLAIM_IDLE: Duration = Duration::from_secs(60);
+    /// Pending rows this far past `run_at` are pushed again, covering a crash between commit and push.
+    const RESYNC_AFTER: Duration = Duration::from_secs(10 * 60);
+    const RESYNC_INTERVAL: Duration = Duration::from_secs(5 * 60);
+    const PROMOTE_BATCH: isize = 100;
+
+    /// One stream per queue, read through a consumer group. Jobs with a future
+    /// `run_at` wait in a sorted set until they are due.
+    pub struct RedisQueueDriver {
+        conn: ConnectionManager,
+        clock: Arc<dyn Clock>,
+    }
+
+    fn stream_key(queue: tasks::Queue) -> String {
+        format!("jobs:{}", queue.as_str())
+    }
+
+    fn delayed_key(queue: tasks::Queue) -> String {
+        format!("jobs:{}:delayed", queue.as_str())
+    }
+
+    impl RedisQueueDriver {
+        pub async fn connect(url: &str, clock: Arc<dyn Clock>) -> Result<Self, DriverError> {
+            let mut conn = ConnectionManager::new(redis::Client::open(url)?).await?;
+            for queue in tasks::Queue::ALL {
+                let created: redis::RedisResult<()> = conn.xgroup_create_mkstream(stream_key(queue), CONSUMER_GROUP, "0").await;
+                // BUSYGROUP just means another process created the group first
+                if let Err(e) = created {
+                    if e.code() != Some("BUSYGROUP") {
+                        return Err(e.into());
+                    }
+                }
+            }
+            Ok(Self { conn, clock })
+        }
+
+        /// Moves due entries from the delayed set onto the stream. ZREM decides which
+        /// worker moves an entry, so each is added once.
+        async fn promote_due(&self, queue: tasks::Queue) -> Result<(), DriverError> {
+            let mut conn = self.conn.clone();
+            let due: Vec<String> = conn
+                .zrangebyscore_limit(delayed_key(queue), "-inf", self.clock.now().timestamp_millis(), 0, PROMOTE_BATCH)
+                .await?;
+            for job_id in due {
+                let removed: i64 = conn.zrem(delayed_key(queue), &job_id).await?;
+                if removed == 1 {
+                    let _: String = conn.xadd(stream_key(queue), "*", &[("job_id", job_id.as_str())]).await?;
+                }
+            }
+            Ok(())
+        }
+
+        async fn delivery(&self, queue: tasks::Queue, entry: StreamId) -> Result<Option<Delivery>, DriverError> {
+            let job_id = entry.get::<String>("job_id").and_then(|id| Uuid::parse_str(&id).ok());
+            let delivery = Delivery { job_id: job_id.unwrap_or_default(), queue, receipt: Some(entry.id) };
+            if job_id.is_none() {
+                tracing::warn!("Dropping malformed entry {:?} on {}", delivery.receipt, stream_key(queue));
+                self.ack(&delivery).await?;
+                return Ok(None);
+            }
+            Ok(Some(delivery))
+        }
+
+        /// Re-pushes long-overdue pending jobs. `run_at` is bumped so a job is pushed
+        /// at most once per `RESYNC_AFTER` even while the queue is backed up.
+        pub fn spawn_resync(self: Arc<Self>, db_pool: SqlitePool) {
+            tokio::spawn(async move {
+                loop {
+                    sleep(RESYNC_INTERVAL).await;
+                    if let Err(e) = self.resync(&db_pool).await {
+                        tracing::error!("Redis queue resync failed: {:?}", e);
+                    }
+                }
+            });
+        }
+
+        async fn resync(&self, db_pool: &SqlitePool) -> Result<(), DriverError> {
+            let now = self.clock.now();
+            let cutoff = now - chrono::Duration::seconds(RESYNC_AFTER.as_secs() as i64);
+            let overdue: Vec<(Uuid, tasks::Queue)> = sqlx::query_as(
+                "UPDATE jobs SET run_at = ? WHERE status = 'pending' AND run_at < ? RETURNING id, queue",
+            )
+            .bind(now)
+            .bind(cutoff)
+            .fetch_all(db_pool)
+            .await?;
+            for (id, queue) in overdue {
+                self.push(QueuedJob { id, queue, run_at: now }).await?;
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl QueueDriver for RedisQueueDriver {
+        async fn push(&self, job: QueuedJob) -> Result<(), DriverError> {
+            let mut conn = self.conn.clone();
+            let job_id = job.id.to_string();
+            if job.run_at <= self.clock.now() {
+                let _: String = conn.xadd(stream_key(job.queue), "*", &[("job_id", job_id.as_str())]).await?;
+            } else {
+                let _: i64 = conn.zadd(delayed_key(job.queue), job_id, job.run_at.timestamp_millis()).await?;
+            }
+            Ok(())
+        }
+
+        /// One round trip for the whole batch.
+        async fn push_many(&self, jobs: &[QueuedJob]) -> Result<(), DriverError> {
+            if jobs.is_empty() {
+                return Ok(());
+            }
+            let now = self.clock.now();
+            let mut pipe = redis::pipe();
+            for job in jobs {
+                let job_id = job.id.to_string();
+                if job.run_at <= now {
+                    pipe.xadd(stream_key(job.queue), "*", &[("job_id", job_id.as_str())]).ignore();
+                } else {
+                    pipe.zadd(delayed_key(job.queue), job_id, job.run_at.timestamp_millis()).ignore();
+                }
+            }
+            pipe.query_async::<()>(&mut self.conn.clone()).await?;
+            Ok(())
+        }
+
+        async fn next(&self, queue: tasks::Queue, worker_id: &str) -> Result<Option<Delivery>, DriverError> {
+            self.promote_due(queue).await?;
+            let mut conn = self.conn.clone();
+
+            // No BLOCK: the connection is multiplexed across all workers
+            let options = StreamReadOptions::default().group(CONSUMER_GROUP, worker_id).count(1);
+            let reply: StreamReadReply = conn.xread_options(&[stream_key(queue)], &[">"], &options).await?;
+            if let Some(entry) = reply.keys.into_iter().flat_map(|key| key.ids).next() {
+                return self.delivery(queue, entry).await;
+            }
+
+            // Nothing new, so pick up an entry a crashed consumer never acked
+            let reclaimed: StreamAutoClaimReply = conn
+                .xautoclaim_options(
+                    stream_key(queue),
+                    CONSUMER_GROUP,
+                    worker_id,
+                    CLAIM_IDLE.as_millis() as u64,
+                    "0-0",
+                    StreamAutoClaimOptions::default().count(1),
+                )
+                .await?;
+            match reclaimed.claimed.into_iter().next() {
+                Some(entry) => self.delivery(queue, entry).await,
+                None => Ok(None),
+            }
+        }
+
+        async fn ack(&self, delivery: &Delivery) -> Result<(), DriverError> {
+            let Some(entry_id) = &delivery.receipt else { return Ok(()) };
+            let mut conn = self.conn.clone();
+            let key = stream_key(delivery.queue);
+            let _: i64 = conn.xack(&key, CONSUMER_GROUP, &[entry_id]).await?;
+            let _: i64 = conn.xdel(&key, &[entry_id]).await?;
+            Ok(())
+        }
+    }
+
+    static DRIVER: OnceLock<Arc<dyn QueueDriver>> = OnceLock::new();
+
+    /// `QUEUE_DRIVER=redis` (with `REDIS_URL`) uses Redis Streams; anything else polls SQL.
+    pub async fn init_from_env(db_pool: &SqlitePool, clock: Arc<dyn Clock>) -> Result<(), DriverError> {
+        let driver: Arc<dyn QueueDriver> = match std::env::var("QUEUE_DRIVER").as_deref() {
+            Ok("redis") => {
+                let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+                let driver = Arc::new(RedisQueueDriver::connect(&url, clock).await?);
+                driver.clone().spawn_resync(db_pool.clone());
+                info!("Using Redis Streams queue driver at {}", url);
+                driver
+            }
+            _ => Arc::new(SqlQueueDriver::new(db_pool.clone(), clock)),
+        };
+        if DRIVER.set(driver).is_err() {
+            tracing::warn!("Queue driver already initialised; keeping the first one");
+        }
+        Ok(())
+    }
+
+    pub fn current() -> Arc<dyn QueueDriver> {
+        DRIVER.get().expect("queue driver is initialised at startup").clone()
+    }
+}
+
+// --- Job Queue Service ---
+pub(crate) mod job_queue_service {
+    use super::*;
+    use clock::Clock;
+    use queue_driver::{QueueDriver, QueuedJob};
+
+    #[derive(Debug, Clone, Serialize, FromRow)]
+    pub struct JobRecord {
+        pub id: Uuid,
+        #[sqlx(json)]
+        pub payload: tasks::TaskPayload,
+        pub queue: tasks::Queue,
+        #[sqlx(try_from = "String")]
+        pub status: JobStatus,
+        pub attempts: i32,
+        pub run_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+        pub error_message: Option<String>,
+        pub workflow_run_id: Option<Uuid>,
+        pub workflow_step: Option<i64>,
+        pub locked_by: Option<String>,
+        pub heartbeat_at: Option<DateTime<Utc>>,
+        /// When the job reached a terminal status.
+        pub finished_at: Option<DateTime<Utc>>,
+    }
+
+    /// Lifecycle of a row in `jobs`, stored as its lowercase name.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum JobStatus {
+        Pending,
+        Running,
+        Completed,
+        Failed,
+        Cancelled,
+    }
+
+    impl JobStatus {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                JobStatus::Pending => "pending",
+                JobStatus::Running => "running",
+                JobStatus::Completed => "completed",
+                JobStatus::Failed => "failed",
+                JobStatus::Cancelled => "cancelled",
+            }
+        }
+
+        pub fn is_terminal(&self) -> bool {
+            matches!(self, JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled)
+        }
+
+        /// The whole state machine. Running jobs go back to pending when retried
+        /// or reclaimed from a dead worker; terminal states never change.
+        pub fn can_transition_to(&self, next: JobStatus) -> bool {
+            use JobStatus::*;
+            matches!(
+                (self, next),
+                (Pending, Running) | (Pending, Cancelled) | (Running, Pending) | (Running, Completed) | (Running, Failed) | (Running, Cancelled)
+            )
+        }
+
+        pub fn ensure_transition(&self, next: JobStatus) -> Result<(), InvalidTransition> {
+            if self.can_transition_to(next) {
+                Ok(())
+            } else {
+                Err(InvalidTransition { from: *self, to: next })
+            }
+        }
+    }
+
+    impl std::fmt::Display for JobStatus {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
+
+    #[derive(thiserror::Error, Debug)]
+    #[error("unknown job status '{0}'")]
+    pub struct UnknownJobStatus(String);
+
+    impl TryFrom<String> for JobStatus {
+        type Error = UnknownJobStatus;
+
+        fn try_from(value: String) -> Result<Self, Self::Error> {
+            match value.as_str() {
+                "pending" => Ok(JobStatus::Pending),
+                "running" => Ok(JobStatus::Running),
+                "completed" => Ok(JobStatus::Completed),
+                "failed" => Ok(JobStatus::Failed),
+                "cancelled" => Ok(JobStatus::Cancelled),
+                _ => Err(UnknownJobStatus(value)),
+            }
+        }
+    }
+
+    #[derive(thiserror::Error, Debug)]
+    #[error("job cannot move from {from} to {to}")]
+    pub struct InvalidTransition {
+        pub from: JobStatus,
+        pub to: JobStatus,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct ListJobsQuery {
+        pub status: Option<JobStatus>,
+        pub queue: Option<tasks::Queue>,
+        /// The payload's `type` tag, e.g. `ProcessImage`.
+        #[serde(rename = "type")]
+        pub task_type: Option<String>,
+        /// Inclusive lower bound on `run_at`.
+        pub from: Option<DateTime<Utc>>,
+        /// Exclusive upper bound on `run_at`.
+        pub to: Option<DateTime<Utc>>,
+        pub limit: Option<i64>,
+        pub offset: Option<i64>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct JobPage {
+        pub jobs: Vec<JobRecord>,
+        pub total: i64,
+        pub limit: i64,
+        pub offset: i64,
+    }
+
+    impl JobRecord {
+        /// The workflow run and step index this job executes, if any.
+        pub fn workflow_step(&self) -> Option<(Uuid, usize)> {
+            Some((self.workflow_run_id?, self.workflow_step? as usize))
+        }
+    }
+
+    /// Takes any executor so a job can be enqueued inside the caller's transaction.
+    /// The job is due at `run_at`, normally the caller's current time.
+    pub async fn insert_job<'e, E>(
+        executor: E,
+        payload: &tasks::TaskPayload,
+        queue: tasks::Queue,
+        workflow_step: Option<(Uuid, usize)>,
+        run_at: DateTime<Utc>,
+    ) -> Result<QueuedJob, sqlx::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        let job = QueuedJob { id: Uuid::new_v4(), queue, run_at };
+        sqlx::query(
+            "INSERT INTO jobs (id, payload, queue, status, attempts, run_at, workflow_run_id, workflow_step) VALUES (?, ?, ?, 'pending', 0, ?, ?, ?)",
+        )
+        .bind(job.id)
+        .bind(serde_json::to_value(payload).unwrap())
+        .bind(queue)
+        .bind(job.run_at)
+        .bind(workflow_step.map(|(run_id, _)| run_id))
+        .bind(workflow_step.map(|(_, step)| step as i64))
+        .execute(executor)
+        .await?;
+        Ok(job)
+    }
+
+    /// Rows per multi-row INSERT; four bound parameters each keeps a chunk well
+    /// under SQLite's variable limit.
+    const INSERT_CHUNK: usize = 1000;
+
+    #[derive(Clone)]
+    pub struct JobQueueService {
+        db_pool: SqlitePool,
+        driver: Arc<dyn QueueDriver>,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl JobQueueService {
+        pub fn new(db_pool: SqlitePool, clock: Arc<dyn Clock>) -> Self {
+            Self { db_pool, driver: queue_driver::current(), clock }
+        }
+
+        pub async fn schedule_task(&self, payload: tasks::TaskPayload) -> Result<Uuid, AppError> {
+            let queue = payload.queue();
+            self.schedule_task_on(payload, queue).await
+        }
+
+        /// Like `schedule_task`, but on an explicit queue instead of the payload's default.
+        pub async fn schedule_task_on(&self, payload: tasks::TaskPayload, queue: tasks::Queue) -> Result<Uuid, AppError> {
+            let job = insert_job(&self.db_pool, &payload, queue, None, self.clock.now()).await?;
+            self.driver.push(job).await?;
+            Ok(job.id)
+        }
+
+        /// Enqueues every payload on its default queue in one transaction, with one
+        /// INSERT per `INSERT_CHUNK` jobs. Returns the job ids in payload order.
+        pub async fn schedule_many(&self, payloads: Vec<tasks::TaskPayload>) -> Result<Vec<Uuid>, AppError> {
+            let run_at = self.clock.now();
+            let jobs: Vec<QueuedJob> = payloads
+                .iter()
+                .map(|payload| QueuedJob { id: Uuid::new_v4(), queue: payload.queue(), run_at })
+                .collect();
+
+            let mut tx = self.db_pool.begin().await?;
+            for (payloads, jobs) in payloads.chunks(INSERT_CHUNK).zip(jobs.chunks(INSERT_CHUNK)) {
+                let mut insert = sqlx::QueryBuilder::<sqlx::Sqlite>::new("INSERT INTO jobs (id, payload, queue, run_at) ");
+                insert.push_values(payloads.iter().zip(jobs), |mut row, (payload, job)| {
+                    row.push_bind(job.id)
+                        .push_bind(serde_json::to_value(payload).unwrap())
+                        .push_bind(job.queue)
+                        .push_bind(job.run_at);
+                });
+                insert.build().execute(&mut *tx).await?;
+            }
+            tx.commit().await?;
+
+            self.driver.push_many(&jobs).await?;
+            Ok(jobs.iter().map(|job| job.id).collect())
+        }
+
+        /// Pending jobs are cancelled outright. Running jobs are signalled and reach
+        /// `cancelled` once the task notices; the returned record may still say `running`.
+        pub async fn cancel(
+            &self,
+            job_id: Uuid,
+            running_jobs: &worker::RunningJobs,
+            job_events: &events::JobEventSender,
+        ) -> Result<JobRecord, AppError> {
+            let mut tx = self.db_pool.begin().await?;
+            let now = self.clock.now();
+            let cancelled = sqlx::query("UPDATE jobs SET status = ?, finished_at = ? WHERE id = ? AND status = ?")
+                .bind(JobStatus::Cancelled.as_str())
+                .bind(now)
+                .bind(job_id)
+                .bind(JobStatus::Pending.as_str())
+                .execute(&mut *tx)
+                .await?
+                .rows_affected()
+                > 0;
+            let job = sqlx::query_as::<_, JobRecord>("SELECT * FROM jobs WHERE id = ?")
+                .bind(job_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or(AppError::JobNotFound(job_id))?;
+            if cancelled {
+                if let Some((run_id, step)) = job.workflow_step() {
+                    workflows::fail(&mut tx, run_id, step, "cancelled", now).await?;
+                }
+                tx.commit().await?;
+                let _ = job_events.send(events::JobEvent::status(job_id, JobStatus::Cancelled, None, now));
+                return Ok(job);
+            }
+            tx.commit().await?;
+            job.status.ensure_transition(JobStatus::Cancelled)?;
+            if job.status == JobStatus::Running && running_jobs.cancel(job_id) {
+                return Ok(job);
+            }
+            Err(AppError::Conflict(format!("Job is {} on another worker and can't be signalled from here", job.status)))
+        }
+
+        /// Jobs ordered by `run_at`, so a `from`/`to` window reads like a calendar.
+        pub async fn list_jobs(&self, query: ListJobsQuery) -> Result<JobPage, AppError> {
+            if let (Some(from), Some(to)) = (query.from, query.to) {
+                if from >= to {
+                    return Err(AppError::Validation("from must be before to".to_string()));
+                }
+            }
+            let limit = query.limit.unwrap_or(50).clamp(1, 200);
+            let offset = query.offset.unwrap_or(0).max(0);
+
+            let total: i64 = Self::filtered(&query, "SELECT COUNT(*) FROM jobs")
+                .build_query_scalar()
+                .fetch_one(&self.db_pool)
+                .await?;
+            let mut select = Self::filtered(&query, "SELECT * FROM jobs");
+            select.push(" ORDER BY run_at, created_at LIMIT ").push_bind(limit).push(" OFFSET ").push_bind(offset);
+            let jobs = select.build_query_as::<JobRecord>().fetch_all(&self.db_pool).await?;
+
+            Ok(JobPage { jobs, total, limit, offset })
+        }
+
+        fn filtered<'q>(query: &'q ListJobsQuery, base: &str) -> sqlx::QueryBuilder<'q, sqlx::Sqlite> {
+            let mut builder = sqlx::QueryBuilder::new(base);
+            builder.push(" WHERE 1 = 1");
+            if let Some(status) = query.status {
+                builder.push(" AND status = ").push_bind(status.as_str());
+            }
+            if let Some(queue) = query.queue {
+                builder.push(" AND queue = ").push_bind(queue);
+            }
+            if let Some(task_type) = &query.task_type {
+                builder.push(" AND json_extract(payload, '$.type') = ").push_bind(task_type);
+            }
+            if let Some(from) = query.from {
+                builder.push(" AND run_at >= ").push_bind(from);
+            }
+            if let Some(to) = query.to {
+                builder.push(" AND run_at < ").push_bind(to);
+            }
+            builder
+        }
+
+        pub async fn get_job_status(&self, job_id: Uuid) -> Result<JobRecord, AppError> {
+            sqlx::query_as::<_, JobRecord>("SELECT * FROM jobs WHERE id = ?")
+                .bind(job_id)
+                .fetch_optional(&self.db_pool)
+                .await?
+                .ok_or(AppError::JobNotFound(job_id))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use JobStatus::*;
+
+        const ALL: [JobStatus; 5] = [Pending, Running, Completed, Failed, Cancelled];
+
+        #[test]
+        fn transition_table_covers_every_pair() {
+            // Rows are the current status, columns the requested one, both in `ALL` order.
+            let table = [
+                //          Pending Running Completed Failed Cancelled
+                /* Pending   */ [false, true, false, false, true],
+                /* Running   */ [true, false, true, true, true],
+                /* Completed */ [false, false, false, false, false],
+                /* Failed    */ [false, false, false, false, false],
+                /* Cancelled */ [false, false, false, false, false],
+            ];
+            for (from, row) in ALL.iter().zip(table) {
+                for (to, allowed) in ALL.iter().zip(row) {
+                    assert_eq!(from.can_transition_to(*to), allowed, "{} -> {}", from, to);
+                    match from.ensure_transition(*to) {
+                        Ok(()) => assert!(allowed, "{} -> {} should be rejected", from, to),
+                        Err(e) => {
+                            assert!(!allowed, "{} -> {} should be allowed", from, to);
+                            assert_eq!((e.from, e.to), (*from, *to));
+                        }
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn terminal_statuses_have_no_way_out() {
+            for from in ALL {
+                let exits = ALL.iter().filter(|to| from.can_transition_to(**to)).count();
+                assert_eq!(from.is_terminal(), exits == 0, "{}", from);
+            }
+        }
+
+        #[test]
+        fn statuses_round_trip_through_their_stored_form() {
+            for status in ALL {
+                assert_eq!(JobStatus::try_from(status.to_string()).unwrap(), status);
+                assert_eq!(serde_json::to_value(status).unwrap(), status.as_str());
+            }
+            assert!(JobStatus::try_from("Running".to_string()).is_err());
+            assert!(JobStatus::try_from("done".to_string()).is_err());
+        }
+    }
+}
+
+// --- Passwords ---
+mod passwords {
+    use super::*;
+    use argon2::{
+        password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+        Argon2,
+    };
+    use http_client::{Destination, HttpClientService, HttpRequest};
+    use sha1::{Digest, Sha1};
+    use std::collections::HashSet;
+
+    const MIN_LENGTH: usize = 12;
+    const MAX_LENGTH: usize = 128;
+    const MIN_ENTROPY_BITS: f64 = 50.0;
+    const DEFAULT_RANGE_URL: &str = "https://api.pwnedpasswords.com/range";
+
+    /// Refused even when the range API is disabled or unreachable. Only entries
+    /// that would otherwise pass the length and entropy rules are worth listing.
+    const COMMON_PASSWORDS: &[&str] = &[
+        "password1234",
+        "password12345",
+        "passwordpassword",
+        "qwertyuiop123",
+        "iloveyou1234",
+        "letmein12345",
+        "welcome12345",
+        "changeme1234",
+        "administrator1",
+        "trustno1trustno1",
+        "1qaz2wsx3edc4rfv",
+        "zaq12wsxcde3",
+    ];
+
+    /// One failed rule, reported back to the client as-is.
+    #[derive(Debug, Clone, Serialize)]
+    pub struct Violation {
+        pub rule: &'static str,
+        pub message: String,
+    }
+
+    impl Violation {
+        fn new(rule: &'static str, message: impl Into<String>) -> Self {
+            Self { rule, message: message.into() }
+        }
+    }
+
+    enum BreachLookup {
+        Found(u64),
+        NotFound,
+        Unavailable(String),
+    }
+
+    /// Rules applied to every password a user chooses, at registration and on reset.
+    #[derive(Clone)]
+    pub struct PasswordPolicy {
+        http: HttpClientService,
+        /// `None` when the k-anonymity range lookup is switched off.
+        range_url: Option<String>,
+    }
+
+    impl PasswordPolicy {
+        pub fn new(http: HttpClientService, range_url: Option<String>) -> Self {
+            Self { http, range_url }
+        }
+
+        /// `PASSWORD_BREACH_CHECK=hibp` enables the Pwned Passwords range lookup;
+        /// `PASSWORD_RANGE_URL` points it at a mirror.
+        pub fn from_env(http: HttpClientService) -> Self {
+            let enabled = std::env::var("PASSWORD_BREACH_CHECK").is_ok_and(|v| v.eq_ignore_ascii_case("hibp"));
+            let range_url = enabled.then(|| {
+                std::env::var("PASSWORD_RANGE_URL").unwrap_or_else(|_| DEFAULT_RANGE_URL.to_string())
+            });
+            Self::new(http, range_url)
+        }
+
+        /// Collects every failed rule instead of stopping at the first, so the
+        /// client can show them all at once.
+        pub async fn check(&self, password: &str, email: &str) -> Result<(), AppError> {
+            let mut violations = Vec::new();
+            let length = password.chars().count();
+            if length < MIN_LENGTH {
+                violations.push(Violation::new("min_length", format!("must be at least {} characters", MIN_LENGTH)));
+            }
+            if length > MAX_LENGTH {
+                violations.push(Violation::new("max_length", format!("must be at most {} characters", MAX_LENGTH)));
+            }
+            if estimated_entropy_bits(password) < MIN_ENTROPY_BITS {
+                violations.push(Violation::new(
+                    "min_entropy",
+                    "is too predictable; use a longer passphrase or mix character types",
+                ));
+            }
+            let lowered = password.to_lowercase();
+            let local_part = email.split('@').next().unwrap_or_default().to_lowercase();
+            if local_part.len() >= 3 && lowered.contains(&local_part) {
+                violations.push(Violation::new("contains_email", "must not contain your email address"));
+            }
+
+            if COMMON_PASSWORDS.contains(&lowered.as_str()) {
+                violations.push(Violation::new("common_password", "is one of the most commonly used passwords"));
+            } else if length <= MAX_LENGTH {
+                if let Some(range_url) = &self.range_url {
+                    match self.lookup_breach(range_url, password).await {
+                        BreachLookup::Found(count) => violations.push(Violation::new(
+                            "breached",
+                            format!("has appeared in {} known data breaches", count),
+                        )),
+                        BreachLookup::NotFound => {}
+                        // Refusing every registration while the API is down would be worse
+                        // than accepting a password only the offline list has vetted
+                        BreachLookup::Unavailable(reason) => {
+                            tracing::warn!("Breached-password lookup unavailable, using offline list only: {}", reason)
+                        }
+                    }
+                }
+            }
+
+            if violations.is_empty() {
+                Ok(())
+            } else {
+                Err(AppError::PasswordRejected(violations))
+            }
+        }
+
+        /// Only the first five hex characters of the SHA-1 leave the process; the
+        /// suffix is matched locally against the returned range.
+        async fn lookup_breach(&self, range_url: &str, password: &str) -> BreachLookup {
+            let digest = hex::encode_upper(Sha1::digest(password.as_bytes()));
+            let (prefix, suffix) = digest.split_at(5);
+            let request = HttpRequest::get(&format!("{}/{}", range_url.trim_end_matches('/'), prefix))
+                .header("Add-Padding", "true");
+            let response = match self.http.send(Destination::PASSWORD_RANGE, request).await {
+                Ok(response) if response.is_success() => response,
+                Ok(response) => return BreachLookup::Unavailable(format!("range API returned {}", response.status)),
+                Err(e) => return BreachLookup::Unavailable(e.to_string()),
+            };
+            // Padding entries carry a count of 0 and so never match as breached
+            let count = String::from_utf8_lossy(&response.body)
+                .lines()
+                .filter_map(|line| line.trim().split_once(':'))
+                .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+                .and_then(|(_, count)| count.trim().parse::<u64>().ok())
+                .unwrap_or(0);
+            if count > 0 {
+                BreachLookup::Found(count)
+            } else {
+                BreachLookup::NotFound
+            }
+        }
+    }
+
+    /// Rough charset-size estimate. The length counted is capped at twice the
+    /// number of distinct characters, so padding with repeats doesn't help.
+    fn estimated_entropy_bits(password: &str) -> f64 {
+        let mut pool = 0u32;
+        if password.chars().any(|c| c.is_ascii_lowercase()) {
+            pool += 26;
+        }
+        if password.chars().any(|c| c.is_ascii_uppercase()) {
+            pool += 26;
+        }
+        if password.chars().any(|c| c.is_ascii_digit()) {
+            pool += 10;
+        }
+        if password.chars().any(|c| c.is_ascii_punctuation() || c == ' ') {
+            pool += 33;
+        }
+        if !password.is_ascii() {
+            pool += 100;
+        }
+        let distinct = password.chars().collect::<HashSet<_>>().len();
+        let effective_length = password.chars().count().min(distinct * 2);
+        if pool == 0 {
+            return 0.0;
+        }
+        effective_length as f64 * f64::from(pool).log2()
+    }
+
+    pub fn hash_password(password: &str) -> Result<String, AppError> {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|_| AppError::Internal)
+    }
+
+    /// False for a malformed hash as well as a wrong password.
+    pub fn verify_password(password: &str, hash: &str) -> bool {
+        PasswordHash::new(hash)
+            .is_ok_and(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+    }
+}
+
+// --- Email Change Service ---
+mod email_change_service {
+    use super::*;
+    use clock::Clock;
+    use job_queue_service::JobQueueService;
+    use rand::{distributions::Alphanumeric, Rng};
+
+    const TOKEN_LENGTH: usize = 48;
+    pub const TOKEN_TTL_HOURS: i64 = 24;
+
+    #[derive(Clone)]
+    pub struct EmailChangeService {
+        db_pool: SqlitePool,
+        job_queue_service: JobQueueService,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl EmailChangeService {
+        pub fn new(db_pool: SqlitePool, job_queue_service: JobQueueService, clock: Arc<dyn Clock>) -> Self {
+            Self { db_pool, job_queue_service, clock }
+        }
+
+        fn generate_token() -> String {
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(TOKEN_LENGTH)
+                .map(char::from)
+                .collect()
+        }
+
+        async fn email_in_use(&self, email: &str) -> Result<bool, AppError> {
+            let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE email = ?")
+                .bind(email)
+                .fetch_one(&self.db_pool)
+                .await?;
+            Ok(count > 0)
+        }
+
+        /// Records a pending change after checking the caller's password, then emails
+        /// one code to the new address (proving it is reachable) and another to the
+        /// current one (proving the owner agrees). The email is not touched until both
+        /// codes are confirmed.
+        pub async fn request_change(
+            &self,
+            user_id: Uuid,
+            new_email: String,
+            current_password: &str,
+        ) -> Result<Uuid, AppError> {
+            let new_email = new_email.trim().to_lowercase();
+            if !new_email.contains('@') || new_email.len() > 254 {
+                return Err(AppError::Validation("new_email must be a valid email address".to_string()));
+            }
+
+            let account: Option<(String, Option<String>)> =
+                sqlx::query_as("SELECT email, password_hash FROM users WHERE id = ?")
+                    .bind(user_id)
+                    .fetch_optional(&self.db_pool)
+                    .await?;
+            let (current_email, password_hash) = account.ok_or(AppError::UserNotFound(user_id))?;
+            // Imported accounts without a password have to reset it first
+            if !password_hash.is_some_and(|hash| passwords::verify_password(current_password, &hash)) {
+                return Err(AppError::ReauthenticationFailed);
+            }
+            if current_email == new_email {
+                return Err(AppError::Validation("new_email matches the current address".to_string()));
+            }
+            if self.email_in_use(&new_email).await? {
+                return Err(AppError::Conflict("Email address is already in use".to_string()));
+            }
+
+            let change_id = Uuid::new_v4();
+            let token = Self::generate_token();
+            let old_token = Self::generate_token();
+            let mut tx = self.db_pool.begin().await?;
+            // Only the most recent request per user stays valid
+            sqlx::query("DELETE FROM pending_email_changes WHERE user_id = ?")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(
+                "INSERT INTO pending_email_changes (id, user_id, new_email, token, old_token, expires_at) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(change_id)
+            .bind(user_id)
+            .bind(&new_email)
+            .bind(&token)
+            .bind(&old_token)
+            .bind(self.clock.now() + chrono::Duration::hours(TOKEN_TTL_HOURS))
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            self.job_queue_service
+                .schedule_task(tasks::TaskPayload::SendEmailChangeApproval {
+                    user_id,
+                    old_email: current_email,
+                    new_email: new_email.clone(),
+                    token: old_token,
+                })
+                .await?;
+            self.job_queue_service
+                .schedule_task(tasks::TaskPayload::SendEmailChangeConfirmation { user_id, new_email, token })
+                .await?;
+            Ok(change_id)
+        }
+
+        /// Marks whichever address `token` was sent to as confirmed. Once both are, swaps
+        /// the email in the same transaction and notifies the previous address.
+        pub async fn confirm_change(&self, token: &str) -> Result<Confirmation, AppError> {
+            let now = self.clock.now();
+            let mut tx = self.db_pool.begin().await?;
+
+            let pending: Option<(Uuid, String, bool, bool)> = sqlx::query_as(
+                "SELECT user_id, new_email, \
+                        token = ? OR new_confirmed_at IS NOT NULL, \
+                        old_token = ? OR old_confirmed_at IS NOT NULL \
+                 FROM pending_email_changes WHERE (token = ? OR old_token = ?) AND expires_at > ?",
+            )
+            .bind(token)
+            .bind(token)
+            .bind(token)
+            .bind(token)
+            .bind(now)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let (user_id, new_email, new_confirmed, old_confirmed) = pending.ok_or(AppError::InvalidToken)?;
+
+            if !(new_confirmed && old_confirmed) {
+                sqlx::query(
+                    "UPDATE pending_email_changes SET \
+                        new_confirmed_at = CASE WHEN token = ? THEN ? ELSE new_confirmed_at END, \
+                        old_confirmed_at = CASE WHEN old_token = ? THEN ? ELSE old_confirmed_at END \
+                     WHERE user_id = ?",
+                )
+                .bind(token)
+                .bind(now)
+                .bind(token)
+                .bind(now)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+                tx.commit().await?;
+                let address = if new_confirmed { "current" } else { "new" };
+                return Ok(Confirmation::Awaiting { user_id, address });
+            }
+
+            let old_email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+                .bind(user_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or(AppError::UserNotFound(user_id))?;
+
+            // The address may have been claimed since the request was made; the UNIQUE
+            // constraint on users.email is the final guard.
+            let update = sqlx::query("UPDATE users SET email = ? WHERE id = ?")
+                .bind(&new_email)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await;
+            if let Err(sqlx::Error::Database(db_err)) = &update {
+                if db_err.is_unique_violation() {
+                    return Err(AppError::Conflict("Email address is already in use".to_string()));
+                }
+            }
+            update?;
+
+            sqlx::query("DELETE FROM pending_email_changes WHERE user_id = ?")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+
+            self.job_queue_service
+                .schedule_task(tasks::TaskPayload::NotifyEmailChanged {
+                    user_id,
+                    old_email,
+                    new_email: new_email.clone(),
+                })
+                .await?;
+            Ok(Confirmation::Completed { user_id, email: new_email })
+        }
+    }
+
+    pub enum Confirmation {
+        Completed { user_id: Uuid, email: String },
+        /// `address` ("new" or "current") names the side whose code is still unused.
+        Awaiting { user_id: Uuid, address: &'static str },
+    }
+}
+
+// --- Password Reset Service ---
+mod password_reset_service {
+    use super::*;
+    use clock::Clock;
+    use job_queue_service::JobQueueService;
+    use passwords::PasswordPolicy;
+    use rand::{distributions::Alphanumeric, Rng};
+
+    const TOKEN_LENGTH: usize = 48;
+    pub const TOKEN_TTL_HOURS: i64 = 1;
+
+    #[derive(Clone)]
+    pub struct PasswordResetService {
+        db_pool: SqlitePool,
+        job_queue_service: JobQueueService,
+        policy: PasswordPolicy,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl PasswordResetService {
+        pub fn new(db_pool: SqlitePool, job_queue_service: JobQueueService, policy: PasswordPolicy, clock: Arc<dyn Clock>) -> Self {
+            Self { db_pool, job_queue_service, policy, clock }
+        }
+
+        fn generate_token() -> String {
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(TOKEN_LENGTH)
+                .map(char::from)
+                .collect()
+        }
+
+        /// Emails a reset link if the address belongs to an active account. Returns
+        /// the same way either way so the endpoint can't be used to probe for accounts.
+        pub async fn request_reset(&self, email: &str) -> Result<(), AppError> {
+            let email = email.trim().to_lowercase();
+            let user_id: Option<Uuid> =
+                sqlx::query_scalar("SELECT id FROM users WHERE email = ? AND is_active = TRUE AND erased_at IS NULL")
+                    .bind(&email)
+                    .fetch_optional(&self.db_pool)
+                    .await?;
+            let Some(user_id) = user_id else {
+                return Ok(());
+            };
+
+            let token = Self::generate_token();
+            let mut tx = self.db_pool.begin().await?;
+            // Only the most recent request per user stays valid
+            sqlx::query("DELETE FROM password_resets WHERE user_id = ?")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("INSERT INTO password_resets (id, user_id, token, expires_at) VALUES (?, ?, ?, ?)")
+                .bind(Uuid::new_v4())
+                .bind(user_id)
+                .bind(&token)
+                .bind(self.clock.now() + chrono::Duration::hours(TOKEN_TTL_HOURS))
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+
+            self.job_queue_service
+                .schedule_task(tasks::TaskPayload::SendPasswordReset { user_id, email, token })
+                .await?;
+            Ok(())
+        }
+
+        /// Checks the new password against the policy before consuming the token,
+        /// so a rejected password can be retried with the same link.
+        pub async fn confirm_reset(&self, token: &str, new_password: &str) -> Result<Uuid, AppError> {
+            let pending: Option<(Uuid, String)> = sqlx::query_as(
+                "SELECT r.user_id, u.email FROM password_resets r JOIN users u ON u.id = r.user_id \
+                 WHERE r.token = ? AND r.expires_at > ?",
+            )
+            .bind(token)
+            .bind(self.clock.now())
+            .fetch_optional(&self.db_pool)
+            .await?;
+            let (user_id, email) = pending.ok_or(AppError::InvalidToken)?;
+
+            self.policy.check(new_password, &email).await?;
+            let password_hash = passwords::hash_password(new_password)?;
+
+            let mut tx = self.db_pool.begin().await?;
+            // Deleting the row is what makes the token single-use, even under concurrent confirms
+            let consumed = sqlx::query("DELETE FROM password_resets WHERE token = ? AND expires_at > ?")
+                .bind(token)
+                .bind(self.clock.now())
+                .execute(&mut *tx)
+                .await?;
+            if consumed.rows_affected() == 0 {
+                return Err(AppError::InvalidToken);
+            }
+            sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+                .bind(&password_hash)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM password_resets WHERE user_id = ?")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            Ok(user_id)
+        }
+    }
+}
+
+// --- Content Moderation ---
+mod moderation {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Verdict {
+        Approve,
+        /// Publish, but keep the post in the admin review queue.
+        Flag(String),
+        /// Keep the post out of sight as PENDING_REVIEW until an admin decides.
+        Hold(String),
+    }
+
+    /// Consulted whenever a post is about to become PUBLISHED.
+    #[async_trait::async_trait]
+    pub trait ModerationService: Send + Sync {
+        async fn review(&self, title: &str, content: &str) -> Result<Verdict, String>;
+    }
+
+    /// Case-insensitive whole-word matching against two keyword lists.
+    pub struct KeywordBlocklist {
+        hold: Vec<String>,
+        flag: Vec<String>,
+    }
+
+    impl KeywordBlocklist {
+        pub fn new(hold: Vec<String>, flag: Vec<String>) -> Self {
+            let normalize = |terms: Vec<String>| -> Vec<String> {
+                terms.into_iter().map(|term| term.trim().to_lowercase()).filter(|term| !term.is_empty()).collect()
+            };
+            Self { hold: normalize(hold), flag: normalize(flag) }
+        }
+
+        fn first_match<'a>(terms: &'a [String], words: &[&str], text: &str) -> Option<&'a str> {
+            terms
+                .iter()
+                // Phrases are matched as substrings, single words only as whole words
+                .find(|term| if term.contains(' ') { text.contains(term.as_str()) } else { words.contains(&term.as_str()) })
+                .map(String::as_str)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ModerationService for KeywordBlocklist {
+        async fn review(&self, title: &str, content: &str) -> Result<Verdict, String> {
+            let text = format!("{}\n{}", title, content).to_lowercase();
+            let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+            if let Some(term) = Self::first_match(&self.hold, &words, &text) {
+                return Ok(Verdict::Hold(format!("Contains blocked term '{}'", term)));
+            }
+            if let Some(term) = Self::first_match(&self.flag, &words, &text) {
+                return Ok(Verdict::Flag(format!("Contains flagged term '{}'", term)));
+            }
+            Ok(Verdict::Approve)
+        }
+    }
+
+    /// Placeholder for a hosted moderation provider. Until one is wired up every
+    /// review errors, which callers treat as a hold.
+    pub struct ExternalModerationApi {
+        endpoint: String,
+    }
+
+    impl ExternalModerationApi {
+        pub fn new(endpoint: String) -> Self {
+            Self { endpoint }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ModerationService for ExternalModerationApi {
+        async fn review(&self, _title: &str, _content: &str) -> Result<Verdict, String> {
+            Err(format!("External moderation API at {} is not implemented", self.endpoint))
+        }
+    }
+
+    fn env_list(name: &str) -> Vec<String> {
+        std::env::var(name).map(|value| value.split(',').map(str::to_string).collect()).unwrap_or_default()
+    }
+
+    /// `MODERATION_PROVIDER=external` uses `MODERATION_API_URL`; otherwise the keyword
+    /// lists come from `MODERATION_BLOCKLIST` (hold) and `MODERATION_FLAGLIST` (flag).
+    pub fn from_env() -> Arc<dyn ModerationService> {
+        match std::env::var("MODERATION_PROVIDER").as_deref() {
+            Ok("external") => {
+                let endpoint = std::env::var("MODERATION_API_URL").unwrap_or_else(|_| "http://localhost:8081/moderate".to_string());
+                Arc::new(ExternalModerationApi::new(endpoint))
+            }
+            _ => Arc::new(KeywordBlocklist::new(env_list("MODERATION_BLOCKLIST"), env_list("MODERATION_FLAGLIST"))),
+        }
+    }
+}
+
+// --- Slugs ---
+mod slugs {
+    use super::*;
+    use sqlx::{Sqlite, Transaction};
+
+    const MAX_SLUG_LEN: usize = 80;
+
+    /// Lowercase ASCII letters and digits, with every other run of characters
+    /// collapsed into one hyphen.
+    pub fn slugify(title: &str) -> String {
+        let mut slug = String::with_capacity(title.len());
+        for c in title.chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c.to_ascii_lowercase());
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        slug.truncate(MAX_SLUG_LEN);
+        let slug = slug.trim_end_matches('-');
+        if slug.is_empty() { "post".to_string() } else { slug.to_string() }
+    }
+
+    async fn is_taken(tx: &mut Transaction<'_, Sqlite>, slug: &str, post_id: Uuid) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM posts WHERE slug = ? AND id != ?) \
+             OR EXISTS (SELECT 1 FROM post_slugs WHERE slug = ? AND post_id != ?)",
+        )
+        .bind(slug)
+        .bind(post_id)
+        .bind(slug)
+        .bind(post_id)
+        .fetch_one(&mut **tx)
+        .await
+    }
+
+    /// The title's slug, or the first of `-2`, `-3`, ... appended to it that no other
+    /// post uses. Old slugs count as used since they keep redirecting.
+    pub async fn unique_slug(tx: &mut Transaction<'_, Sqlite>, title: &str, post_id: Uuid) -> Result<String, sqlx::Error> {
+        let base = slugify(title);
+        let mut candidate = base.clone();
+        let mut suffix = 2;
+        while is_taken(tx, &candidate, post_id).await? {
+            candidate = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        Ok(candidate)
+    }
+
+    /// Re-derives the slug after a title change, moving the old one into history.
+    /// A slug the post had before is taken back out of history.
+    pub async fn retitle(tx: &mut Transaction<'_, Sqlite>, post: &mut Post, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        let slug = unique_slug(tx, &post.title, post.id).await?;
+        if slug == post.slug {
+            return Ok(());
+        }
+        sqlx::query("INSERT OR IGNORE INTO post_slugs (slug, post_id, created_at) VALUES (?, ?, ?)")
+            .bind(&post.slug)
+            .bind(post.id)
+            .bind(now)
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DELETE FROM post_slugs WHERE slug = ? AND post_id = ?")
+            .bind(&slug)
+            .bind(post.id)
+            .execute(&mut **tx)
+            .await?;
+        post.slug = slug;
+        Ok(())
+    }
+
+    pub enum SlugLookup {
+        Current(Post),
+        /// The slug is an old one; holds the post's current slug.
+        Moved(String),
+    }
+
+    #[derive(Clone)]
+    pub struct SlugService {
+        db_pool: SqlitePool,
+    }
+
+    impl SlugService {
+        pub fn new(db_pool: SqlitePool) -> Self {
+            Self { db_pool }
+        }
+
+        pub async fn resolve(&self, slug: &str) -> Result<SlugLookup, AppError> {
+            let post: Option<Post> = sqlx::query_as("SELECT * FROM posts WHERE slug = ?")
+                .bind(slug)
+                .fetch_optional(&self.db_pool)
+                .await?;
+            if let Some(post) = post {
+                return Ok(SlugLookup::Current(post));
+            }
+            let current: Option<String> =
+                sqlx::query_scalar("SELECT p.slug FROM post_slugs s JOIN posts p ON p.id = s.post_id WHERE s.slug = ?")
+                    .bind(slug)
+                    .fetch_optional(&self.db_pool)
+                    .await?;
+            current.map(SlugLookup::Moved).ok_or_else(|| AppError::SlugNotFound(slug.to_string()))
+        }
+    }
+}
+
+// --- Post Service ---
+mod post_service {
+    use super::*;
+    use clock::Clock;
+    use moderation::{ModerationService, Verdict};
+
+    pub const REVISIONS_ALWAYS_KEPT: i64 = 20;
+    pub const REVISION_RETENTION_DAYS: i64 = 90;
+
+    #[derive(Debug, Clone, Serialize, FromRow)]
+    pub struct PostRevision {
+        pub id: Uuid,
+        pub post_id: Uuid,
+        pub revision: i64,
+        pub title: String,
+        pub content: String,
+        pub status: PostStatus,
+        pub editor_id: Uuid,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct CreatePost {
+        pub title: String,
+        pub content: String,
+        pub publish_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum PostSort {
+        #[default]
+        Recent,
+        Popular,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct ListPostsQuery {
+        #[serde(default)]
+        pub sort: PostSort,
+        pub limit: Option<i64>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct LikeResult {
+        pub post_id: Uuid,
+        pub liked: bool,
+        pub like_count: i64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct UpdatePost {
+        pub title: Option<String>,
+        pub content: Option<String>,
+        pub status: Option<PostStatus>,
+        pub publish_at: Option<DateTime<Utc>>,
+    }
+
+    fn validate_title(title: &str) -> Result<(), AppError> {
+        if title.trim().is_empty() || title.len() > 200 {
+            return Err(AppError::Validation("title must be between 1 and 200 characters".to_string()));
+        }
+        Ok(())
+    }
+
+    fn validate_publish_at(publish_at: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), AppError> {
+        if publish_at <= now {
+            return Err(AppError::Validation("publish_at must be in the future".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn fetch_post<'e, E>(executor: E, post_id: Uuid) -> Result<Post, AppError>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        sqlx::query_as::<_, Post>("SELECT * FROM posts WHERE id = ?")
+            .bind(post_id)
+            .fetch_optional(executor)
+            .await?
+            .ok_or(AppError::PostNotFound(post_id))
+    }
+
+    /// Snapshots the post as it is *before* a change, numbering revisions per post.
+    async fn record_revision(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        post: &Post,
+        editor_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<i64, AppError> {
+        let next: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(revision), 0) + 1 FROM post_revisions WHERE post_id = ?")
+            .bind(post.id)
+            .fetch_one(&mut **tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO post_revisions (id, post_id, revision, title, content, status, editor_id, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(post.id)
+        .bind(next)
+        .bind(&post.title)
+        .bind(&post.content)
+        .bind(post.status)
+        .bind(editor_id)
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+        Ok(next)
+    }
+
+    async fn write_post(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, post: &Post) -> Result<(), AppError> {
+        sqlx::query(
+            "UPDATE posts SET title = ?, slug = ?, content = ?, status = ?, publish_at = ?, published_at = ?, moderation_reason = ? \
+             WHERE id = ?",
+        )
+        .bind(&post.title)
+        .bind(&post.slug)
+        .bind(&post.content)
+        .bind(post.status)
+        .bind(post.publish_at)
+        .bind(post.published_at)
+        .bind(&post.moderation_reason)
+        .bind(post.id)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// The status and `moderation_reason` a post about to be published should get.
+    /// A reviewer that errors holds the post rather than letting it through unchecked.
+    async fn moderate(moderation: &dyn ModerationService, post: &Post) -> (PostStatus, Option<String>) {
+        match moderation.review(&post.title, &post.content).await {
+            Ok(Verdict::Approve) => (PostStatus::PUBLISHED, None),
+            Ok(Verdict::Flag(reason)) => (PostStatus::PUBLISHED, Some(reason)),
+            Ok(Verdict::Hold(reason)) => (PostStatus::PENDING_REVIEW, Some(reason)),
+            Err(e) => {
+                tracing::warn!(post_id = ?post.id, "Moderation failed, holding post: {}", e);
+                (PostStatus::PENDING_REVIEW, Some("Moderation unavailable".to_string()))
+            }
+        }
+    }
+
+    /// Publishes a post outside the edit flow (e.g. once its image is processed),
+    /// still going through moderation. Returns whether it ended up PUBLISHED.
+    pub async fn publish_reviewed(
+        db_pool: &SqlitePool,
+        moderation: &dyn ModerationService,
+        clock: &dyn Clock,
+        post_id: Uuid,
+    ) -> Result<bool, AppError> {
+        let post = fetch_post(db_pool, post_id).await?;
+        if post.status == PostStatus::PUBLISHED {
+            return Ok(true);
+        }
+        let (status, reason) = moderate(moderation, &post).await;
+        // Skipped if the post was edited while under review
+        let updated = sqlx::query(
+            "UPDATE posts SET status = ?, moderation_reason = ?, publish_at = NULL, published_at = ? \
+             WHERE id = ? AND status = ? AND title = ? AND content = ?",
+        )
+        .bind(status)
+        .bind(&reason)
+        .bind((status == PostStatus::PUBLISHED).then(|| clock.now()))
+        .bind(post_id)
+        .bind(post.status)
+        .bind(&post.title)
+        .bind(&post.content)
+        .execute(db_pool)
+        .await?
+        .rows_affected();
+        Ok(updated == 1 && status == PostStatus::PUBLISHED)
+    }
+
+    #[derive(Clone)]
+    pub struct PostService {
+        db_pool: SqlitePool,
+        moderation: Arc<dyn ModerationService>,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl PostService {
+        pub fn new(db_pool: SqlitePool, moderation: Arc<dyn ModerationService>, clock: Arc<dyn Clock>) -> Self {
+            Self { db_pool, moderation, clock }
+        }
+
+        /// Runs moderation when an edit moves a post into PUBLISHED, and keeps
+        /// `published_at` in step with the resulting status.
+        async fn review_if_publishing(&self, previous: &Post, post: &mut Post, now: DateTime<Utc>) {
+            if post.status == PostStatus::PUBLISHED && previous.status != PostStatus::PUBLISHED {
+                let (status, reason) = moderate(self.moderation.as_ref(), post).await;
+                post.status = status;
+                post.moderation_reason = reason;
+                post.published_at = Some(now);
+            }
+            if post.status != PostStatus::PUBLISHED {
+                post.published_at = None;
+            }
+        }
+
+        pub async fn create_post(&self, author_id: Uuid, input: CreatePost) -> Result<Post, AppError> {
+            validate_title(&input.title)?;
+            if let Some(publish_at) = input.publish_at {
+                validate_publish_at(publish_at, self.clock.now())?;
+            }
+            let id = Uuid::new_v4();
+            let mut tx = self.db_pool.begin().await?;
+            let post = Post {
+                id,
+                user_id: author_id,
+                slug: slugs::unique_slug(&mut tx, &input.title, id).await?,
+                title: input.title,
+                content: input.content,
+                status: PostStatus::DRAFT,
+                publish_at: input.publish_at,
+                published_at: None,
+                like_count: 0,
+                moderation_reason: None,
+            };
+            sqlx::query("INSERT INTO posts (id, user_id, title, slug, content, status, publish_at) VALUES (?, ?, ?, ?, ?, ?, ?)")
+                .bind(post.id)
+                .bind(post.user_id)
+                .bind(&post.title)
+                .bind(&post.slug)
+                .bind(&post.content)
+                .bind(post.status)
+                .bind(post.publish_at)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            Ok(post)
+        }
+
+        /// `viewer_id` is the caller, if authenticated. A post that is not yet
+        /// PUBLISHED reads as missing to anyone but its author and admins.
+        pub async fn get_post(&self, post_id: Uuid, viewer_id: Option<Uuid>) -> Result<Post, AppError> {
+            let post = fetch_post(&self.db_pool, post_id).await?;
+            if !self.can_see(&post, viewer_id).await? {
+                return Err(AppError::PostNotFound(post_id));
+            }
+            Ok(post)
+        }
+
+        /// Edits, restores and the revision history are for the author and admins only.
+        async fn ensure_can_edit(&self, post: &Post, user_id: Uuid) -> Result<(), AppError> {
+            if post.user_id != user_id && !data_export::is_admin(&self.db_pool, user_id).await? {
+                return Err(AppError::Forbidden);
+            }
+            Ok(())
+        }
+
+        pub async fn can_see(&self, post: &Post, viewer_id: Option<Uuid>) -> Result<bool, AppError> {
+            if post.status == PostStatus::PUBLISHED {
+                return Ok(true);
+            }
+            match viewer_id {
+                Some(user_id) => Ok(post.user_id == user_id || data_export::is_admin(&self.db_pool, user_id).await?),
+                None => Ok(false),
+            }
+        }
+
+        pub async fn update_post(&self, post_id: Uuid, editor_id: Uuid, input: UpdatePost) -> Result<Post, AppError> {
+            let now = self.clock.now();
+            if let Some(title) = &input.title {
+                validate_title(title)?;
+            }
+            if let Some(publish_at) = input.publish_at {
+                validate_publish_at(publish_at, now)?;
+            }
+            if input.status == Some(PostStatus::PENDING_REVIEW) {
+                return Err(AppError::Validation("PENDING_REVIEW is only set by moderation".to_string()));
+            }
+            let mut tx = self.db_pool.begin().await?;
+
+            let previous = fetch_post(&mut *tx, post_id).await?;
+            self.ensure_can_edit(&previous, editor_id).await?;
+            let mut post = previous.clone();
+            if let Some(title) = input.title { post.title = title; }
+            if let Some(content) = input.content { post.content = content; }
+            if let Some(status) = input.status { post.status = status; }
+            if let Some(publish_at) = input.publish_at {
+                if post.status == PostStatus::PUBLISHED {
+                    return Err(AppError::Validation("Only draft posts can be scheduled".to_string()));
+                }
+                post.publish_at = Some(publish_at);
+            }
+            if post.title != previous.title {
+                slugs::retitle(&mut tx, &mut post, now).await?;
+            }
+            self.review_if_publishing(&previous, &mut post, now).await;
+            // A published or held post has nothing left to schedule
+            if post.status != PostStatus::DRAFT {
+                post.publish_at = None;
+            }
+
+            record_revision(&mut tx, &previous, editor_id, now).await?;
+            write_post(&mut tx, &post).await?;
+
+            tx.commit().await?;
+            Ok(post)
+        }
+
+        /// Published posts, plus the viewer's own unpublished ones. Admins see everything.
+        pub async fn list_posts(&self, query: ListPostsQuery, viewer_id: Option<Uuid>) -> Result<Vec<Post>, AppError> {
+            let limit = query.limit.unwrap_or(20).clamp(1, 100);
+            let sees_all = match viewer_id {
+                Some(user_id) => data_export::is_admin(&self.db_pool, user_id).await?,
+                None => false,
+            };
+            // A NULL viewer matches no user_id, so anonymous callers only get PUBLISHED rows
+            let sql = match query.sort {
+                PostSort::Popular => {
+                    "SELECT * FROM posts WHERE (? OR status = 'PUBLISHED' OR user_id = ?) \
+                     ORDER BY like_count DESC, rowid DESC LIMIT ?"
+                }
+                PostSort::Recent => "SELECT * FROM posts WHERE (? OR status = 'PUBLISHED' OR user_id = ?) ORDER BY rowid DESC LIMIT ?",
+            };
+            Ok(sqlx::query_as(sql).bind(sees_all).bind(viewer_id).bind(limit).fetch_all(&self.db_pool).await?)
+        }
+
+        /// Idempotent per user: the post_likes primary key rejects a second like, and
+        /// the counter is only bumped when a row was actually inserted.
+        pub async fn like_post(&self, post_id: Uuid, user_id: Uuid) -> Result<LikeResult, AppError> {
+            let mut tx = self.db_pool.begin().await?;
+            fetch_post(&mut *tx, post_id).await?;
+
+            let inserted = sqlx::query("INSERT OR IGNORE INTO post_likes (post_id, user_id, created_at) VALUES (?, ?, ?)")
+                .bind(post_id)
+                .bind(user_id)
+                .bind(self.clock.now())
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            if inserted == 1 {
+                sqlx::query("UPDATE posts SET like_count = like_count + 1 WHERE id = ?")
+                    .bind(post_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            let like_count: i64 = sqlx::query_scalar("SELECT like_count FROM posts WHERE id = ?")
+                .bind(post_id)
+                .fetch_one(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(LikeResult { post_id, liked: true, like_count })
+        }
+
+        pub async fn unlike_post(&self, post_id: Uuid, user_id: Uuid) -> Result<LikeResult, AppError> {
+            let mut tx = self.db_pool.begin().await?;
+            fetch_post(&mut *tx, post_id).await?;
+
+            let removed = sqlx::query("DELETE FROM post_likes WHERE post_id = ? AND user_id = ?")
+                .bind(post_id)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            if removed == 1 {
+                sqlx::query("UPDATE posts SET like_count = MAX(like_count - 1, 0) WHERE id = ?")
+                    .bind(post_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            let like_count: i64 = sqlx::query_scalar("SELECT like_count FROM posts WHERE id = ?")
+                .bind(post_id)
+                .fetch_one(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(LikeResult { post_id, liked: false, like_count })
+        }
+
+        pub async fn list_revisions(&self, post_id: Uuid, requester_id: Uuid) -> Result<Vec<PostRevision>, AppError> {
+            let post = fetch_post(&self.db_pool, post_id).await?;
+            self.ensure_can_edit(&post, requester_id).await?;
+            Ok(sqlx::query_as("SELECT * FROM post_revisions WHERE post_id = ? ORDER BY revision DESC")
+                .bind(post_id)
+                .fetch_all(&self.db_pool)
+                .await?)
+        }
+
+        /// Restoring is itself an edit, so the current state is kept as a new revision first.
+        pub async fn restore_revision(&self, post_id: Uuid, revision: i64, editor_id: Uuid) -> Result<Post, AppError> {
+            let mut tx = self.db_pool.begin().await?;
+
+            let current = fetch_post(&mut *tx, post_id).await?;
+            self.ensure_can_edit(&current, editor_id).await?;
+            let target: PostRevision = sqlx::query_as("SELECT * FROM post_revisions WHERE post_id = ? AND revision = ?")
+                .bind(post_id)
+                .bind(revision)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or(AppError::RevisionNotFound { post_id, revision })?;
+
+            let mut restored = Post {
+                title: target.title,
+                content: target.content,
+                status: target.status,
+                ..current.clone()
+            };
+            let now = self.clock.now();
+            if restored.title != current.title {
+                slugs::retitle(&mut tx, &mut restored, now).await?;
+            }
+            self.review_if_publishing(&current, &mut restored, now).await;
+            record_revision(&mut tx, &current, editor_id, now).await?;
+            write_post(&mut tx, &restored).await?;
+
+            tx.commit().await?;
+            Ok(restored)
+        }
+
+        /// Held posts plus published ones that moderation flagged.
+        pub async fn moderation_queue(&self) -> Result<Vec<Post>, AppError> {
+            Ok(sqlx::query_as(
+                "SELECT * FROM posts WHERE status = 'PENDING_REVIEW' OR (status = 'PUBLISHED' AND moderation_reason IS NOT NULL) \
+                 ORDER BY rowid",
+            )
+            .fetch_all(&self.db_pool)
+            .await?)
+        }
+
+        async fn fetch_queued(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>, post_id: Uuid) -> Result<Post, AppError> {
+            let post = fetch_post(&mut **tx, post_id).await?;
+            let queued = post.status == PostStatus::PENDING_REVIEW
+                || (post.status == PostStatus::PUBLISHED && post.moderation_reason.is_some());
+            if !queued {
+                return Err(AppError::Conflict("Post is not awaiting moderation".to_string()));
+            }
+            Ok(post)
+        }
+
+        /// Publishes a held post, or clears the flag on a published one.
+        pub async fn approve(&self, post_id: Uuid, admin_id: Uuid) -> Result<Post, AppError> {
+            let mut tx = self.db_pool.begin().await?;
+            let now = self.clock.now();
+            let previous = Self::fetch_queued(&mut tx, post_id).await?;
+            let post = Post {
+                status: PostStatus::PUBLISHED,
+                publish_at: None,
+                published_at: previous.published_at.or(Some(now)),
+                moderation_reason: None,
+                ..previous.clone()
+            };
+            record_revision(&mut tx, &previous, admin_id, now).await?;
+            write_post(&mut tx, &post).await?;
+            audit::record(
+                &mut *tx,
+                Some(admin_id),
+                Some(post.user_id),
+                "post.moderation_approved",
+                serde_json::json!({ "post_id": post_id, "reason": previous.moderation_reason }),
+                now,
+            )
+            .await?;
+            tx.commit().await?;
+            if previous.status == PostStatus::PENDING_REVIEW {
+                tasks::dispatch_post_published(&self.db_pool, &self.clock, post_id).await;
+            }
+            Ok(post)
+        }
+
+        /// Sends a held or flagged post back to DRAFT with the admin's reason.
+        pub async fn reject(&self, post_id: Uuid, admin_id: Uuid, reason: String) -> Result<Post, AppError> {
+            if reason.trim().is_empty() {
+                return Err(AppError::Validation("A rejection reason is required".to_string()));
+            }
+            let now = self.clock.now();
+            let mut tx = self.db_pool.begin().await?;
+            let previous = Self::fetch_queued(&mut tx, post_id).await?;
+            let post = Post {
+                status: PostStatus::DRAFT,
+                publish_at: None,
+                published_at: None,
+                moderation_reason: Some(format!("Rejected: {}", reason.trim())),
+                ..previous.clone()
+            };
+            record_revision(&mut tx, &previous, admin_id, now).await?;
+            write_post(&mut tx, &post).await?;
+            audit::record(
+                &mut *tx,
+                Some(admin_id),
+                Some(post.user_id),
+                "post.moderation_rejected",
+                serde_json::json!({ "post_id": post_id, "reason": reason.trim() }),
+                now,
+            )
+            .await?;
+            tx.commit().await?;
+            Ok(post)
+        }
+    }
+
+    /// Moves every due DRAFT through moderation to PUBLISHED (or PENDING_REVIEW) and
+    /// returns the ids that this call published. The status check lives in each UPDATE,
+    /// so concurrent workers racing on the same rows claim disjoint posts and none is
+    /// announced twice; a post edited since it was reviewed is left for the next run.
+    pub async fn publish_due_posts(
+        db_pool: &SqlitePool,
+        moderation: &dyn ModerationService,
+        clock: &dyn Clock,
+    ) -> Result<Vec<Uuid>, sqlx::Error> {
+        let now = clock.now();
+        let due: Vec<Post> =
+            sqlx::query_as("SELECT * FROM posts WHERE status = 'DRAFT' AND publish_at IS NOT NULL AND publish_at <= ?")
+                .bind(now)
+                .fetch_all(db_pool)
+                .await?;
+        let mut published = Vec::new();
+        for post in due {
+            let (status, reason) = moderate(moderation, &post).await;
+            let claimed = sqlx::query(
+                "UPDATE posts SET status = ?, moderation_reason = ?, publish_at = NULL, published_at = ?
+                 WHERE id = ? AND status = 'DRAFT' AND publish_at IS NOT NULL AND publish_at <= ? AND title = ? AND content = ?",
+            )
+            .bind(status)
+            .bind(&reason)
+            .bind((status == PostStatus::PUBLISHED).then_some(now))
+            .bind(post.id)
+            .bind(now)
+            .bind(&post.title)
+            .bind(&post.content)
+            .execute(db_pool)
+            .await?
+            .rows_affected()
+                == 1;
+            if claimed && status == PostStatus::PUBLISHED {
+                published.push(post.id);
+            }
+        }
+        Ok(published)
+    }
+
+    /// Recomputes `like_count` from post_likes for any post whose counter has drifted
+    /// (e.g. after manual data fixes or a partially applied migration).
+    pub async fn reconcile_like_counts(db_pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE posts
+             SET like_count = (SELECT COUNT(*) FROM post_likes WHERE post_likes.post_id = posts.id)
+             WHERE like_count != (SELECT COUNT(*) FROM post_likes WHERE post_likes.post_id = posts.id)",
+        )
+        .execute(db_pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes revisions past the retention window, always sparing the most recent
+    /// `REVISIONS_ALWAYS_KEPT` per post.
+    pub async fn prune_revisions(db_pool: &SqlitePool, clock: &dyn Clock) -> Result<u64, sqlx::Error> {
+        let cutoff = clock.now() - chrono::Duration::days(REVISION_RETENTION_DAYS);
+        let result = sqlx::query(
+            "DELETE FROM post_revisions
+             WHERE created_at < ?
+               AND id IN (
+                   SELECT id FROM (
+                       SELECT id, ROW_NUMBER() OVER (PARTITION BY post_id ORDER BY revision DESC) AS rn
+                       FROM post_revisions
+                   ) WHERE rn > ?
+               )",
+        )
+        .bind(cutoff)
+        .bind(REVISIONS_ALWAYS_KEPT)
+        .execute(db_pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        struct Fixture {
+            posts: PostService,
+            author: Uuid,
+            other: Uuid,
+            admin: Uuid,
+            draft: Uuid,
+            published: Uuid,
+        }
+
+        async fn add_user(db_pool: &SqlitePool, role: &str) -> Uuid {
+            let id = Uuid::new_v4();
+            sqlx::query("INSERT INTO users (id, email, role) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(format!("{}@example.com", id))
+                .bind(role)
+                .execute(db_pool)
+                .await
+                .unwrap();
+            id
+        }
+
+        fn edit(status: Option<PostStatus>) -> UpdatePost {
+            UpdatePost { title: None, content: Some("Edited".to_string()), status, publish_at: None }
+        }
+
+        /// One author with a draft and a published post, another user, and an admin.
+        async fn fixture() -> Fixture {
+            let db_pool = setup_database().await;
+            let posts = PostService::new(
+                db_pool.clone(),
+                Arc::new(moderation::KeywordBlocklist::new(Vec::new(), Vec::new())),
+                Arc::new(clock::SystemClock),
+            );
+            let (author, other, admin) = (add_user(&db_pool, "USER").await, add_user(&db_pool, "USER").await, add_user(&db_pool, "ADMIN").await);
+            let new_post = |title: &str| CreatePost { title: title.to_string(), content: "Body".to_string(), publish_at: None };
+            let draft = posts.create_post(author, new_post("Draft")).await.unwrap().id;
+            let published = posts.create_post(author, new_post("Published")).await.unwrap().id;
+            posts.update_post(published, author, edit(Some(PostStatus::PUBLISHED))).await.unwrap();
+            Fixture { posts, author, other, admin, draft, published }
+        }
+
+        async fn listed(f: &Fixture, viewer_id: Option<Uuid>) -> Vec<Uuid> {
+            let query = ListPostsQuery { sort: PostSort::Recent, limit: None };
+            let mut ids: Vec<Uuid> = f.posts.list_posts(query, viewer_id).await.unwrap().into_iter().map(|p| p.id).collect();
+            ids.sort();
+            ids
+        }
+
+        #[tokio::test]
+        async fn unpublished_posts_are_hidden_from_everyone_but_the_author_and_admins() {
+            let f = fixture().await;
+            let mut both = vec![f.draft, f.published];
+            both.sort();
+
+            assert_eq!(listed(&f, None).await, vec![f.published]);
+            assert_eq!(listed(&f, Some(f.other)).await, vec![f.published]);
+            assert_eq!(listed(&f, Some(f.author)).await, both);
+            assert_eq!(listed(&f, Some(f.admin)).await, both);
+
+            for viewer_id in [None, Some(f.other)] {
+                assert!(matches!(f.posts.get_post(f.draft, viewer_id).await, Err(AppError::PostNotFound(_))));
+                assert!(f.posts.get_post(f.published, viewer_id).await.is_ok());
+            }
+            for viewer_id in [f.author, f.admin] {
+                assert!(f.posts.get_post(f.draft, Some(viewer_id)).await.is_ok());
+            }
+        }
+
+        #[tokio::test]
+        async fn only_the_author_or_an_admin_can_edit_restore_or_read_history() {
+            let f = fixture().await;
+
+            let denied = f.posts.update_post(f.published, f.other, edit(None)).await;
+            assert!(matches!(denied, Err(AppError::Forbidden)), "{:?}", denied);
+            let denied = f.posts.restore_revision(f.published, 1, f.other).await;
+            assert!(matches!(denied, Err(AppError::Forbidden)), "{:?}", denied);
+            let denied = f.posts.list_revisions(f.published, f.other).await;
+            assert!(matches!(denied, Err(AppError::Forbidden)), "{:?}", denied);
+            // Nothing was written by the refused calls
+            assert_eq!(f.posts.get_post(f.published, None).await.unwrap().content, "Edited");
+            assert_eq!(f.posts.list_revisions(f.published, f.author).await.unwrap().len(), 1);
+
+            f.posts.update_post(f.published, f.author, edit(None)).await.unwrap();
+            let restored = f.posts.restore_revision(f.published, 1, f.admin).await.unwrap();
+            assert_eq!(restored.content, "Body");
+            assert_eq!(f.posts.list_revisions(f.published, f.admin).await.unwrap().len(), 3);
+        }
+    }
+}
+
+// --- Markdown Rendering ---
+mod markdown {
+    use super::*;
+    use pulldown_cmark::{html, Options, Parser};
+    use sha2::{Digest, Sha256};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const RENDER_CACHE_ENTRIES: usize = 1024;
+
+    /// Strips anything that could run script or escape the post's markup. Kept behind a
+    /// trait so the allow-list policy can change without touching the renderer.
+    pub trait HtmlSanitizer: Send + Sync {
+        fn sanitize(&self, html: &str) -> String;
+    }
+
+    /// ammonia's default allow-list, limited to http(s) and mailto links, with every
+    /// link marked `nofollow`.
+    pub struct AmmoniaSanitizer {
+        builder: ammonia::Builder<'static>,
+    }
+
+    impl Default for AmmoniaSanitizer {
+        fn default() -> Self {
+            let mut builder = ammonia::Builder::default();
+            builder
+                .url_schemes(["http", "https", "mailto"].into_iter().collect())
+                .link_rel(Some("nofollow noopener noreferrer"));
+            Self { builder }
+        }
+    }
+
+    impl HtmlSanitizer for AmmoniaSanitizer {
+        fn sanitize(&self, html: &str) -> String {
+            self.builder.clean(html).to_string()
+        }
+    }
+
+    /// Markdown to sanitized HTML, caching results by the SHA-256 of the source so
+    /// unchanged posts are only rendered once.
+    #[derive(Clone)]
+    pub struct MarkdownRenderer {
+        sanitizer: Arc<dyn HtmlSanitizer>,
+        cache: Arc<Mutex<HashMap<[u8; 32], Arc<str>>>>,
+    }
+
+    impl MarkdownRenderer {
+        pub fn new(sanitizer: Arc<dyn HtmlSanitizer>) -> Self {
+            Self { sanitizer, cache: Arc::default() }
+        }
+
+        pub fn render(&self, source: &str) -> Arc<str> {
+            let key: [u8; 32] = Sha256::digest(source.as_bytes()).into();
+            if let Some(html) = self.cache.lock().unwrap().get(&key) {
+                return html.clone();
+            }
+
+            let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+            let mut unsafe_html = String::with_capacity(source.len() * 3 / 2);
+            html::push_html(&mut unsafe_html, Parser::new_ext(source, options));
+            // Raw HTML in the markdown passes through the parser untouched; this is
+            // what makes it safe to serve
+            let html: Arc<str> = self.sanitizer.sanitize(&unsafe_html).into();
+
+            let mut cache = self.cache.lock().unwrap();
+            // Crude bound: start over rather than track recency
+            if cache.len() >= RENDER_CACHE_ENTRIES {
+                cache.clear();
+            }
+            cache.insert(key, html.clone());
+            html
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn render(source: &str) -> String {
+            MarkdownRenderer::new(Arc::new(AmmoniaSanitizer::default())).render(source).to_string()
+        }
+
+        /// Every `href`/`src` that survives must be relative or use an allowed scheme.
+        fn urls_are_safe(html: &str) -> bool {
+            ["href=\"", "src=\""].iter().all(|attr| {
+                html.split(attr).skip(1).all(|rest| {
+                    let url = &rest[..rest.find('"').unwrap_or(rest.len())];
+                    let scheme_end = url.find(['/', '?', '#']).unwrap_or(url.len());
+                    !url[..scheme_end].contains(':') || ["http:", "https:", "mailto:"].iter().any(|scheme| url.starts_with(scheme))
+                })
+            })
+        }
+
+        fn assert_inert(source: &str) -> String {
+            let html = render(source).to_ascii_lowercase();
+            for needle in ["<script", "<iframe", "<style", "<svg", "<object", "<form", "<body", " onerror=", " onload=", " onclick=", " onmouseover="] {
+                assert!(!html.contains(needle), "{:?} rendered {:?} containing {:?}", source, html, needle);
+            }
+            assert!(urls_are_safe(&html), "{:?} rendered {:?} with an unsafe URL", source, html);
+            html
+        }
+
+        #[test]
+        fn script_tags_are_removed() {
+            assert_inert("Hello <script>alert(1)</script> world");
+            assert_inert("<script src=\"https://evil.example/x.js\"></script>");
+            assert_inert("<SCRIPT>alert(1)</SCRIPT>");
+            let html = assert_inert("before\n\n<script>\nalert(1)\n</script>\n\nafter");
+            assert!(html.contains("before") && html.contains("after"), "{}", html);
+        }
+
+        #[test]
+        fn javascript_links_are_stripped() {
+            for source in [
+                "[click](javascript:alert(1))",
+                "[click](JavaScript:alert(1))",
+                "[click](jav&#x61;script:alert(1))",
+                "<javascript:alert(1)>",
+                "[click][ref]\n\n[ref]: javascript:alert(1)",
+                "<a href=\"javascript:alert(1)\">click</a>",
+                "<a href=\" javascript:alert(1)\">click</a>",
+                "[click](vbscript:msgbox(1))",
+                "[click](data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==)",
+            ] {
+                assert_inert(source);
+            }
+        }
+
+        #[test]
+        fn event_handler_attributes_are_dropped() {
+            for source in [
+                "<img src=\"x\" onerror=\"alert(1)\">",
+                "<img src=x onerror=alert(1)>",
+                "<p onclick=\"alert(1)\">hi</p>",
+                "<a href=\"https://example.com\" onmouseover=\"alert(1)\">hi</a>",
+                "<body onload=alert(1)>",
+            ] {
+                assert_inert(source);
+            }
+        }
+
+        #[test]
+        fn raw_html_inside_markdown_is_sanitized() {
+            for source in [
+                "# Title\n\n<iframe src=\"https://evil.example\"></iframe>",
+                "* item <style>body { display: none }</style>",
+                "<svg onload=alert(1)><circle r=\"1\"/></svg>",
+                "<object data=\"evil.swf\"></object>",
+                "<form action=\"https://evil.example\"><input name=q></form>",
+                "**bold <img src=x onerror=alert(1)>**",
+                "| a | b |\n|---|---|\n| <script>alert(1)</script> | c |",
+                "> quote <div onclick=alert(1)>x</div>",
+            ] {
+                assert_inert(source);
+            }
+        }
+
+        #[test]
+        fn code_blocks_show_markup_as_text() {
+            let html = render("```\n<script>alert(1)</script>\n```");
+            assert!(html.contains("&lt;script&gt;"), "{}", html);
+            assert!(!html.contains("<script"), "{}", html);
+        }
+
+        #[test]
+        fn ordinary_markdown_survives() {
+            let html = render("# Title\n\nSome **bold** and ~~old~~ text with [a link](https://example.com).\n\n| a |\n|---|\n| b |");
+            assert!(html.contains("<h1>Title</h1>"), "{}", html);
+            assert!(html.contains("<strong>bold</strong>"), "{}", html);
+            assert!(html.contains("<del>old</del>"), "{}", html);
+            assert!(html.contains("<table>"), "{}", html);
+            assert!(html.contains("href=\"https://example.com\""), "{}", html);
+            assert!(html.contains("rel=\"nofollow noopener noreferrer\""), "{}", html);
+            assert!(render("[mail](mailto:ada@example.com)").contains("href=\"mailto:ada@example.com\""));
+            assert!(!urls_are_safe("<a href=\"javascript:alert(1)\">"));
+        }
+
+        #[test]
+        fn renders_are_cached_by_content() {
+            let renderer = MarkdownRenderer::new(Arc::new(AmmoniaSanitizer::default()));
+            let first = renderer.render("*hi*");
+            assert!(Arc::ptr_eq(&first, &renderer.render("*hi*")));
+            assert!(!Arc::ptr_eq(&first, &renderer.render("*bye*")));
+        }
+    }
+}
+
+// --- Feeds ---
+mod feeds {
+    use super::*;
+    use futures::{Stream, StreamExt};
+    use sha2::{Digest, Sha256};
+
+    /// The sitemap protocol's per-file limit.
+    const SITEMAP_MAX_URLS: i64 = 50_000;
+    const RSS_ITEMS: i64 = 50;
+    const RSS_EXCERPT_CHARS: usize = 280;
+    /// Rendered chunks buffered ahead of a slow client.
+    const STREAM_BUFFER: usize = 32;
+
+    const SITEMAP_SQL: &str = "SELECT p.id, p.slug, p.title, '' AS content, p.published_at, \
+         (SELECT MAX(r.created_at) FROM post_revisions r WHERE r.post_id = p.id) AS last_edited_at \
+         FROM posts p WHERE p.status = 'PUBLISHED' AND p.published_at IS NOT NULL ORDER BY p.published_at DESC LIMIT ?";
+    const RSS_SQL: &str = "SELECT p.id, p.slug, p.title, p.content, p.published_at, \
+         (SELECT MAX(r.created_at) FROM post_revisions r WHERE r.post_id = p.id) AS last_edited_at \
+         FROM posts p WHERE p.status = 'PUBLISHED' AND p.published_at IS NOT NULL ORDER BY p.published_at DESC LIMIT ?";
+
+    #[derive(Debug, Clone, Copy)]
+    pub enum Feed {
+        Sitemap,
+        Rss,
+    }
+
+    #[derive(FromRow)]
+    struct FeedEntry {
+        id: Uuid,
+        slug: String,
+        title: String,
+        content: String,
+        published_at: DateTime<Utc>,
+        last_edited_at: Option<DateTime<Utc>>,
+    }
+
+    impl FeedEntry {
+        fn updated_at(&self) -> DateTime<Utc> {
+            self.last_edited_at.map_or(self.published_at, |edited| edited.max(self.published_at))
+        }
+    }
+
+    fn escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&apos;"),
+                _ => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
+    #[derive(Clone)]
+    pub struct FeedService {
+        db_pool: SqlitePool,
+        base_url: String,
+        title: String,
+    }
+
+    impl FeedService {
+        pub fn new(db_pool: SqlitePool, base_url: &str, title: String) -> Self {
+            Self { db_pool, base_url: base_url.trim_end_matches('/').to_string(), title }
+        }
+
+        /// Links use `PUBLIC_BASE_URL`, the same base object storage signs URLs against.
+        pub fn from_env(db_pool: SqlitePool) -> Self {
+            let base_url = std::env::var("PUBLIC_BASE_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+            let title = std::env::var("FEED_TITLE").unwrap_or_else(|_| "Latest posts".to_string());
+            Self::new(db_pool, &base_url, title)
+        }
+
+        fn post_url(&self, slug: &str) -> String {
+            format!("{}/posts/by-slug/{}", self.base_url, slug)
+        }
+
+        /// Derived from aggregates rather than the documents themselves, so a match can
+        /// be answered without rendering anything. Publishing, unpublishing and every
+        /// edit (each one writes a revision) all change it.
+        pub async fn etag(&self, feed: Feed) -> Result<String, AppError> {
+            let (count, last_published, last_revision): (i64, Option<String>, Option<String>) = sqlx::query_as(
+                "SELECT COUNT(*), MAX(published_at), (SELECT MAX(created_at) FROM post_revisions) \
+                 FROM posts WHERE status = 'PUBLISHED' AND published_at IS NOT NULL",
+            )
+            .fetch_one(&self.db_pool)
+            .await?;
+            let digest = Sha256::digest(
+                format!("{:?}|{}|{}|{:?}|{:?}|{}", feed, count, self.base_url, last_published, last_revision, self.title).as_bytes(),
+            );
+            Ok(format!("\"{}\"", hex::encode(&digest[..16])))
+        }
+
+        pub fn sitemap(&self) -> impl Stream<Item = Result<String, std::io::Error>> {
+            let header = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n"
+                .to_string();
+            self.stream(SITEMAP_SQL, SITEMAP_MAX_URLS, header, "</urlset>\n", |service, entry| {
+                format!(
+                    "<url><loc>{}</loc><lastmod>{}</lastmod></url>\n",
+                    escape(&service.post_url(&entry.slug)),
+                    entry.updated_at().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                )
+            })
+        }
+
+        pub fn rss(&self) -> impl Stream<Item = Result<String, std::io::Error>> {
+            let header = format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\"><channel>\n\
+                 <title>{title}</title><link>{base}</link><description>{title}</description>\n\
+                 <atom:link href=\"{base}/feed.rss\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+                title = escape(&self.title),
+                base = escape(&self.base_url),
+            );
+            self.stream(RSS_SQL, RSS_ITEMS, header, "</channel></rss>\n", |service, entry| {
+                let excerpt: String = entry.content.chars().take(RSS_EXCERPT_CHARS).collect();
+                // The guid is the id, not the link, because slugs change with the title
+                format!(
+                    "<item><title>{}</title><link>{}</link><guid isPermaLink=\"false\">{}</guid>\
+                     <pubDate>{}</pubDate><description>{}</description></item>\n",
+                    escape(&entry.title),
+                    escape(&service.post_url(&entry.slug)),
+                    entry.id,
+                    entry.published_at.to_rfc2822(),
+                    escape(&excerpt),
+                )
+            })
+        }
+
+        /// Renders rows as they are read, so memory stays flat however many posts there
+        /// are. A database error mid-way ends the body early; the status line is long gone.
+        fn stream(
+            &self,
+            sql: &'static str,
+            limit: i64,
+            header: String,
+            footer: &'static str,
+            render: fn(&FeedService, &FeedEntry) -> String,
+        ) -> impl Stream<Item = Result<String, std::io::Error>> {
+            let (tx, rx) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+            let service = self.clone();
+            tokio::spawn(async move {
+                if tx.send(Ok(header)).await.is_err() {
+                    return;
+                }
+                let mut rows = sqlx::query_as::<_, FeedEntry>(sql).bind(limit).fetch(&service.db_pool);
+                while let Some(row) = rows.next().await {
+                    let chunk = match row {
+                        Ok(entry) => Ok(render(&service, &entry)),
+                        Err(e) => {
+                            tracing::error!("Feed query failed: {}", e);
+                            let _ = tx.send(Err(std::io::Error::other(e))).await;
+                            return;
+                        }
+                    };
+                    if tx.send(chunk).await.is_err() {
+                        return;
+                    }
+                }
+                let _ = tx.send(Ok(footer.to_string())).await;
+            });
+            futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (chunk, rx)) })
+        }
+    }
+}
+
+// --- Notification Preferences ---
+mod preferences {
+    use super::*;
+    use clock::Clock;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+    #[serde(rename_all = "snake_case")]
+    #[sqlx(rename_all = "snake_case")]
+    pub enum Channel {
+        Email,
+        Webhook,
+        InApp,
+    }
+
+    pub const ACCOUNT_WELCOME: &str = "account.welcome";
+    pub const ACCOUNT_SECURITY: &str = "account.security";
+    pub const DATA_EXPORT_READY: &str = "data_export.ready";
+    pub const POST_IMAGES_READY: &str = "post.images_ready";
+
+    struct EventSpec {
+        event_type: &'static str,
+        channels: &'static [Channel],
+        /// Locked events are always delivered and cannot be turned off.
+        locked: bool,
+    }
+
+    const EVENTS: &[EventSpec] = &[
+        EventSpec { event_type: ACCOUNT_WELCOME, channels: &[Channel::Email], locked: false },
+        EventSpec { event_type: ACCOUNT_SECURITY, channels: &[Channel::Email], locked: true },
+        EventSpec { event_type: DATA_EXPORT_READY, channels: &[Channel::Email, Channel::InApp], locked: false },
+        EventSpec { event_type: POST_IMAGES_READY, channels: &[Channel::Email], locked: false },
+        EventSpec { event_type: webhooks::USER_CREATED, channels: &[Channel::Webhook], locked: false },
+        EventSpec {
+            event_type: webhooks::POST_PUBLISHED,
+            channels: &[Channel::Webhook, Channel::InApp],
+            locked: false,
+        },
+    ];
+
+    fn spec(channel: Channel, event_type: &str) -> Option<&'static EventSpec> {
+        EVENTS
+            .iter()
+            .find(|spec| spec.event_type == event_type && spec.channels.contains(&channel))
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Preference {
+        pub channel: Channel,
+        pub event_type: &'static str,
+        pub enabled: bool,
+        pub locked: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct PreferenceUpdate {
+        pub channel: Channel,
+        pub event_type: String,
+        pub enabled: bool,
+    }
+
+    /// Whether `user_id` wants `event_type` on `channel`. Users without a stored
+    /// row get everything; that is also what a freshly registered user sees.
+    pub async fn is_enabled(db_pool: &SqlitePool, user_id: Uuid, channel: Channel, event_type: &str) -> Result<bool, sqlx::Error> {
+        if spec(channel, event_type).is_none_or(|spec| spec.locked) {
+            return Ok(true);
+        }
+        let stored: Option<bool> = sqlx::query_scalar(
+            "SELECT enabled FROM notification_preferences WHERE user_id = ? AND channel = ? AND event_type = ?",
+        )
+        .bind(user_id)
+        .bind(channel)
+        .bind(event_type)
+        .fetch_optional(db_pool)
+        .await?;
+        Ok(stored.unwrap_or(true))
+    }
+
+    #[derive(Clone)]
+    pub struct PreferenceService {
+        db_pool: SqlitePool,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl PreferenceService {
+        pub fn new(db_pool: SqlitePool, clock: Arc<dyn Clock>) -> Self {
+            Self { db_pool, clock }
+        }
+
+        async fn authorize(&self, user_id: Uuid, requester_id: Uuid) -> Result<(), AppError> {
+            if requester_id != user_id && !data_export::is_admin(&self.db_pool, requester_id).await? {
+                return Err(AppError::Forbidden);
+            }
+            let exists: Option<i64> = sqlx::query_scalar("SELECT 1 FROM users WHERE id = ?")
+                .bind(user_id)
+                .fetch_optional(&self.db_pool)
+                .await?;
+            exists.map(|_| ()).ok_or(AppError::UserNotFound(user_id))
+        }
+
+        /// The full channel/event matrix with defaults filled in.
+        pub async fn get(&self, user_id: Uuid, requester_id: Uuid) -> Result<Vec<Preference>, AppError> {
+            self.authorize(user_id, requester_id).await?;
+            let stored: Vec<(Channel, String, bool)> =
+                sqlx::query_as("SELECT channel, event_type, enabled FROM notification_preferences WHERE user_id = ?")
+                    .bind(user_id)
+                    .fetch_all(&self.db_pool)
+                    .await?;
+
+            let mut preferences = Vec::new();
+            for spec in EVENTS {
+                for &channel in spec.channels {
+                    let enabled = spec.locked
+                        || stored
+                            .iter()
+                            .find(|(c, e, _)| *c == channel && e == spec.event_type)
+                            .is_none_or(|(_, _, enabled)| *enabled);
+                    preferences.push(Preference { channel, event_type: spec.event_type, enabled, locked: spec.locked });
+                }
+            }
+            Ok(preferences)
+        }
+
+        pub async fn update(&self, user_id: Uuid, requester_id: Uuid, updates: Vec<PreferenceUpdate>) -> Result<Vec<Preference>, AppError> {
+            self.authorize(user_id, requester_id).await?;
+            for update in &updates {
+                match spec(update.channel, &update.event_type) {
+                    None => {
+                        return Err(AppError::Validation(format!(
+                            "'{}' is not available on the {:?} channel",
+                            update.event_type, update.channel
+                        )))
+                    }
+                    Some(spec) if spec.locked && !update.enabled => {
+                        return Err(AppError::Validation(format!("'{}' notifications cannot be disabled", spec.event_type)))
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            let now = self.clock.now();
+            let mut tx = self.db_pool.begin().await?;
+            for update in &updates {
+                sqlx::query(
+                    "INSERT INTO notification_preferences (user_id, channel, event_type, enabled, updated_at) VALUES (?, ?, ?, ?, ?)
+                     ON CONFLICT (user_id, channel, event_type) DO UPDATE SET enabled = excluded.enabled, updated_at = excluded.updated_at",
+                )
+                .bind(user_id)
+                .bind(update.channel)
+                .bind(&update.event_type)
+                .bind(update.enabled)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await?;
+            self.get(user_id, requester_id).await
+        }
+    }
+}
+
+// --- Audit Log ---
+mod audit {
+    use super::*;
+
+    #[derive(Debug, Clone, Serialize, FromRow)]
+    pub struct AuditEvent {
+        pub id: Uuid,
+        pub actor_id: Option<Uuid>,
+        pub subject_user_id: Option<Uuid>,
+        pub action: String,
+        #[sqlx(json)]
+        pub details: serde_json::Value,
+        pub created_at: DateTime<Utc>,
+    }
+
+    /// Takes any executor so events can be written inside the caller's transaction.
+    pub async fn record<'e, E>(
+        executor: E,
+        actor_id: Option<Uuid>,
+        subject_user_id: Option<Uuid>,
+        action: &str,
+        details: serde_json::Value,
+        at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        sqlx::query(
+            "INSERT INTO audit_events (id, actor_id, subject_user_id, action, details, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(actor_id)
+        .bind(subject_user_id)
+        .bind(action)
+        .bind(details)
+        .bind(at)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
+
+// --- Object Storage ---
+mod object_storage {
+    use super::*;
+    use futures::StreamExt;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use std::path::PathBuf;
+    use tokio::io::AsyncWriteExt;
+
+    /// Body of an object too large to hold in memory.
+    pub type ByteStream<'a> = futures::stream::BoxStream<'a, std::io::Result<axum::body::Bytes>>;
+    pub type ObjectReader = Box<dyn tokio::io::AsyncRead + Send + Unpin>;
+
+    #[async_trait::async_trait]
+    pub trait ObjectStorage: Send + Sync {
+        async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), String>;
+        /// Stores `body` chunk by chunk and returns its length. Nothing is stored
+        /// under `key` if the stream fails part way.
+        async fn put_stream(&self, key: &str, body: ByteStream<'_>) -> Result<u64, String>;
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String>;
+        /// Reads the object incrementally instead of loading it whole like `get`.
+        async fn open(&self, key: &str) -> Result<Option<ObjectReader>, String>;
+        async fn delete(&self, key: &str) -> Result<(), String>;
+        /// URL that grants read access to `key` until `expires_at`.
+        fn presigned_url(&self, key: &str, expires_at: DateTime<Utc>) -> String;
+        /// `now` is the caller's clock reading; links past `expires` are refused.
+        fn verify_presigned(&self, key: &str, expires: i64, signature: &str, now: DateTime<Utc>) -> bool;
+    }
+
+    /// Stores objects under a local directory and serves them through `/downloads`.
+    pub struct LocalObjectStorage {
+        root: PathBuf,
+        public_base_url: String,
+        signing_key: Vec<u8>,
+    }
+
+    impl LocalObjectStorage {
+        pub fn new(root: PathBuf, public_base_url: String, signing_key: Vec<u8>) -> Self {
+            Self { root, public_base_url, signing_key }
+        }
+
+        fn path_for(&self, key: &str) -> Result<PathBuf, String> {
+            if key.is_empty() || key.starts_with('/') || key.split('/').any(|part| part == "..") {
+                return Err(format!("Invalid object key '{}'", key));
+            }
+            Ok(self.root.join(key))
+        }
+
+        fn mac(&self, key: &str, expires: i64) -> Hmac<Sha256> {
+            let mut mac = Hmac::<Sha256>::new_from_slice(&self.signing_key).expect("HMAC accepts any key length");
+            mac.update(key.as_bytes());
+            mac.update(b"\n");
+            mac.update(expires.to_string().as_bytes());
+            mac
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ObjectStorage for LocalObjectStorage {
+        async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), String> {
+            let path = self.path_for(key)?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+            }
+            tokio::fs::write(path, bytes).await.map_err(|e| e.to_string())
+        }
+
+        async fn put_stream(&self, key: &str, mut body: ByteStream<'_>) -> Result<u64, String> {
+            let path = self.path_for(key)?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await.map_err(|e| e.to_string())?;
+            }
+            // Renamed into place once complete, so readers never see a truncated object
+            let mut partial = path.clone().into_os_string();
+            partial.push(".partial");
+            let partial = PathBuf::from(partial);
+            let written = async {
+                let mut file = tokio::fs::File::create(&partial).await?;
+                let mut written = 0u64;
+                while let Some(chunk) = body.next().await {
+                    let chunk = chunk?;
+                    file.write_all(&chunk).await?;
+                    written += chunk.len() as u64;
+                }
+                file.sync_all().await?;
+                tokio::fs::rename(&partial, &path).await?;
+                Ok::<_, std::io::Error>(written)
+            }
+            .await;
+            if written.is_err() {
+                let _ = tokio::fs::remove_file(&partial).await;
+            }
+            written.map_err(|e| e.to_string())
+        }
+
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+            match tokio::fs::read(self.path_for(key)?).await {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+
+        async fn open(&self, key: &str) -> Result<Option<ObjectReader>, String> {
+            match tokio::fs::File::open(self.path_for(key)?).await {
+                Ok(file) => Ok(Some(Box::new(file))),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+
+        async fn delete(&self, key: &str) -> Result<(), String> {
+            match tokio::fs::remove_file(self.path_for(key)?).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+                _ => Ok(()),
+            }
+        }
+
+        fn presigned_url(&self, key: &str, expires_at: DateTime<Utc>) -> String {
+            let expires = expires_at.timestamp();
+            let signature = hex::encode(self.mac(key, expires).finalize().into_bytes());
+            format!("{}/downloads/{}?expires={}&signature={}", self.public_base_url, key, expires, signature)
+        }
+
+        fn verify_presigned(&self, key: &str, expires: i64, signature: &str, now: DateTime<Utc>) -> bool {
+            if expires < now.timestamp() {
+                return false;
+            }
+            let Ok(signature) = hex::decode(signature) else { return false };
+            // verify_slice compares in constant time
+            self.mac(key, expires).verify_slice(&signature).is_ok()
+        }
+    }
+
+    /// Storage configured from `OBJECT_STORAGE_DIR`, `PUBLIC_BASE_URL` and `OBJECT_SIGNING_KEY`.
+    /// Panics without a signing key unless `APP_ENV=development`, so a misconfigured
+    /// deployment fails at startup instead of signing links with a well-known key.
+    pub fn from_env() -> Arc<dyn ObjectStorage> {
+        let root = std::env::var("OBJECT_STORAGE_DIR").unwrap_or_else(|_| "./storage".to_string());
+        let base_url = std::env::var("PUBLIC_BASE_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let signing_key = signing_key(std::env::var("APP_ENV").ok().as_deref(), std::env::var("OBJECT_SIGNING_KEY").ok())
+            .expect("OBJECT_SIGNING_KEY must be set unless APP_ENV=development");
+        Arc::new(LocalObjectStorage::new(root.into(), base_url, signing_key))
+    }
+
+    fn signing_key(app_env: Option<&str>, configured: Option<String>) -> Option<Vec<u8>> {
+        match configured.filter(|key| !key.is_empty()) {
+            Some(key) => Some(key.into_bytes()),
+            None if app_env == Some("development") => Some(b"dev-only-signing-key".to_vec()),
+            None => None,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn the_fallback_signing_key_is_for_development_only() {
+            assert_eq!(signing_key(None, None), None);
+            assert_eq!(signing_key(Some("production"), Some(String::new())), None);
+            assert_eq!(signing_key(Some("development"), None).as_deref(), Some(&b"dev-only-signing-key"[..]));
+            assert_eq!(signing_key(None, Some("s3cret".to_string())).as_deref(), Some(&b"s3cret"[..]));
+        }
+    }
+}
+
+// --- Data Export Service ---
+mod data_export {
+    use super::*;
+    use clock::Clock;
+    use job_queue_service::JobQueueService;
+    use object_storage::ObjectStorage;
+    use std::io::Write;
+
+    pub const DOWNLOAD_TTL_HOURS: i64 = 24;
+    // Non-admins may request one export per window
+    const REQUEST_COOLDOWN_HOURS: i64 = 24;
+
+    #[derive(Debug, Clone, Serialize, FromRow)]
+    pub struct DataExport {
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub requested_by: Uuid,
+        pub status: String,
+        pub object_key: Option<String>,
+        pub error_message: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub completed_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct DataExportStatus {
+        #[serde(flatten)]
+        pub export: DataExport,
+        pub download_url: Option<String>,
+        pub download_expires_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Serialize, FromRow)]
+    struct ExportedAccount {
+        id: Uuid,
+        email: String,
+        role: String,
+        is_active: bool,
+        created_at: DateTime<Utc>,
+        avatar_url: Option<String>,
+    }
+
+    #[derive(Debug, Serialize, FromRow)]
+    struct ExportedPreference {
+        channel: String,
+        event_type: String,
+        enabled: bool,
+        updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Serialize, FromRow)]
+    struct ExportedLike {
+        post_id: Uuid,
+        created_at: DateTime<Utc>,
+    }
+
+    pub async fn is_admin(db_pool: &SqlitePool, user_id: Uuid) -> Result<bool, AppError> {
+        let role: Option<String> = sqlx::query_scalar("SELECT role FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(db_pool)
+            .await?;
+        Ok(role.as_deref() == Some("ADMIN"))
+    }
+
+    #[derive(Clone)]
+    pub struct DataExportService {
+        db_pool: SqlitePool,
+        job_queue_service: JobQueueService,
+        storage: Arc<dyn ObjectStorage>,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl DataExportService {
+        pub fn new(
+            db_pool: SqlitePool,
+            job_queue_service: JobQueueService,
+            storage: Arc<dyn ObjectStorage>,
+            clock: Arc<dyn Clock>,
+        ) -> Self {
+            Self { db_pool, job_queue_service, storage, clock }
+        }
+
+        /// Users may export their own data; admins may export anyone's and skip the cooldown.
+        pub async fn request_export(&self, user_id: Uuid, requester_id: Uuid) -> Result<DataExport, AppError> {
+            let requester_is_admin = is_admin(&self.db_pool, requester_id).await?;
+            if requester_id != user_id && !requester_is_admin {
+                return Err(AppError::Forbidden);
+            }
+            let exists: Option<i64> = sqlx::query_scalar("SELECT 1 FROM users WHERE id = ?")
+                .bind(user_id)
+                .fetch_optional(&self.db_pool)
+                .await?;
+            if exists.is_none() {
+                return Err(AppError::UserNotFound(user_id));
+            }
+
+            let now = self.clock.now();
+            if !requester_is_admin {
+                let since = now - chrono::Duration::hours(REQUEST_COOLDOWN_HOURS);
+                let recent: i64 = sqlx::query_scalar(
+                    "SELECT COUNT(*) FROM data_exports WHERE user_id = ? AND status != 'failed' AND created_at > ?",
+                )
+                .bind(user_id)
+                .bind(since)
+                .fetch_one(&self.db_pool)
+                .await?;
+                if recent > 0 {
+                    return Err(AppError::Conflict("A data export was already requested in the last 24 hours".to_string()));
+                }
+            }
+
+            let export = DataExport {
+                id: Uuid::new_v4(),
+                user_id,
+                requested_by: requester_id,
+                status: "pending".to_string(),
+                object_key: None,
+                error_message: None,
+                created_at: now,
+                completed_at: None,
+            };
+            let mut tx = self.db_pool.begin().await?;
+            sqlx::query("INSERT INTO data_exports (id, user_id, requested_by, status, created_at) VALUES (?, ?, ?, 'pending', ?)")
+                .bind(export.id)
+                .bind(user_id)
+                .bind(requester_id)
+                .bind(export.created_at)
+                .execute(&mut *tx)
+                .await?;
+            audit::record(
+                &mut *tx,
+                Some(requester_id),
+                Some(user_id),
+                "data_export.requested",
+                serde_json::json!({ "export_id": export.id, "admin_override": requester_id != user_id }),
+                now,
+            )
+            .await?;
+            tx.commit().await?;
+
+            self.job_queue_service
+                .schedule_task(tasks::TaskPayload::CompileUserDataExport { export_id: export.id })
+                .await?;
+            Ok(export)
+        }
+
+        pub async fn get_status(&self, user_id: Uuid, export_id: Uuid, requester_id: Uuid) -> Result<DataExportStatus, AppError> {
+            if requester_id != user_id && !is_admin(&self.db_pool, requester_id).await? {
+                return Err(AppError::Forbidden);
+            }
+            let export: DataExport = sqlx::query_as("SELECT * FROM data_exports WHERE id = ? AND user_id = ?")
+                .bind(export_id)
+                .bind(user_id)
+                .fetch_optional(&self.db_pool)
+                .await?
+                .ok_or(AppError::ExportNotFound(export_id))?;
+
+            // A fresh link is minted on every status check
+            let (download_url, download_expires_at) = match (&export.status[..], &export.object_key) {
+                ("completed", Some(key)) => {
+                    let expires_at = self.clock.now() + chrono::Duration::hours(DOWNLOAD_TTL_HOURS);
+                    (Some(self.storage.presigned_url(key, expires_at)), Some(expires_at))
+                }
+                _ => (None, None),
+            };
+            Ok(DataExportStatus { export, download_url, download_expires_at })
+        }
+    }
+
+    fn write_json<T: Serialize>(zip: &mut zip::ZipWriter<std::io::Cursor<Vec<u8>>>, name: &str, value: &T) -> Result<(), String> {
+        zip.start_file(name, zip::write::FileOptions::default()).map_err(|e| e.to_string())?;
+        let json = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+        zip.write_all(&json).map_err(|e| e.to_string())
+    }
+
+    async fn build_bundle(db_pool: &SqlitePool, export: &DataExport, now: DateTime<Utc>) -> Result<Vec<u8>, String> {
+        let db_err = |e: sqlx::Error| e.to_string();
+        let account: ExportedAccount =
+            sqlx::query_as("SELECT id, email, role, is_active, created_at, avatar_url FROM users WHERE id = ?")
+                .bind(export.user_id)
+                .fetch_one(db_pool)
+                .await
+                .map_err(db_err)?;
+        // Only choices the user made; defaults that were never changed have no row
+        let preferences: Vec<ExportedPreference> = sqlx::query_as(
+            "SELECT channel, event_type, enabled, updated_at FROM notification_preferences WHERE user_id = ? ORDER BY channel, event_type",
+        )
+        .bind(export.user_id)
+        .fetch_all(db_pool)
+        .await
+        .map_err(db_err)?;
+        let posts: Vec<Post> = sqlx::query_as("SELECT * FROM posts WHERE user_id = ?")
+            .bind(export.user_id)
+            .fetch_all(db_pool)
+            .await
+            .map_err(db_err)?;
+        let revisions: Vec<post_service::PostRevision> = sqlx::query_as("SELECT * FROM post_revisions WHERE editor_id = ? ORDER BY created_at")
+            .bind(export.user_id)
+            .fetch_all(db_pool)
+            .await
+            .map_err(db_err)?;
+        let likes: Vec<ExportedLike> = sqlx::query_as("SELECT post_id, created_at FROM post_likes WHERE user_id = ? ORDER BY created_at")
+            .bind(export.user_id)
+            .fetch_all(db_pool)
+            .await
+            .map_err(db_err)?;
+        let audit_events: Vec<audit::AuditEvent> = sqlx::query_as(
+            "SELECT * FROM audit_events WHERE subject_user_id = ? OR actor_id = ? ORDER BY created_at",
+        )
+        .bind(export.user_id)
+        .bind(export.user_id)
+        .fetch_all(db_pool)
+        .await
+        .map_err(db_err)?;
+
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        write_json(&mut zip, "manifest.json", &serde_json::json!({
+            "export_id": export.id,
+            "user_id": export.user_id,
+            "generated_at": now,
+            "files": [
+                "account.json",
+                "notification_preferences.json",
+                "posts.json",
+                "post_revisions.json",
+                "likes.json",
+                "audit_events.json",
+            ],
+        }))?;
+        write_json(&mut zip, "account.json", &account)?;
+        write_json(&mut zip, "notification_preferences.json", &preferences)?;
+        write_json(&mut zip, "posts.json", &posts)?;
+        write_json(&mut zip, "post_revisions.json", &revisions)?;
+        write_json(&mut zip, "likes.json", &likes)?;
+        write_json(&mut zip, "audit_events.json", &audit_events)?;
+        let cursor = zip.finish().map_err(|e| e.to_string())?;
+        Ok(cursor.into_inner())
+    }
+
+    /// Body of the `CompileUserDataExport` task.
+    pub async fn compile(db_pool: &SqlitePool, clock: &Arc<dyn Clock>, export_id: Uuid) -> Result<(), String> {
+        let storage = object_storage::from_env();
+        let export: DataExport = sqlx::query_as("SELECT * FROM data_exports WHERE id = ?")
+            .bind(export_id)
+            .fetch_optional(db_pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Data export {} no longer exists", export_id))?;
+
+        sqlx::query("UPDATE data_exports SET status = 'running' WHERE id = ?")
+            .bind(export_id)
+            .execute(db_pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let key = format!("exports/{}/{}.zip", export.user_id, export.id);
+        let result = match build_bundle(db_pool, &export, clock.now()).await {
+            Ok(bundle) => storage.put(&key, bundle).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            // Left as failed until a retry succeeds
+            let _ = sqlx::query("UPDATE data_exports SET status = 'failed', error_message = ? WHERE id = ?")
+                .bind(&e)
+                .bind(export_id)
+                .execute(db_pool)
+                .await;
+            return Err(e);
+        }
+
+        let completed_at = clock.now();
+        let mut tx = db_pool.begin().await.map_err(|e| e.to_string())?;
+        sqlx::query("UPDATE data_exports SET status = 'completed', object_key = ?, error_message = NULL, completed_at = ? WHERE id = ?")
+            .bind(&key)
+            .bind(completed_at)
+            .bind(export_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        audit::record(
+            &mut *tx,
+            None,
+            Some(export.user_id),
+            "data_export.completed",
+            serde_json::json!({ "export_id": export_id }),
+            completed_at,
+        )
+        .await
+            .map_err(|e| e.to_string())?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        let email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+            .bind(export.user_id)
+            .fetch_one(db_pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        let expires_at = clock.now() + chrono::Duration::hours(DOWNLOAD_TTL_HOURS);
+        JobQueueService::new(db_pool.clone(), clock.clone())
+            .schedule_task(tasks::TaskPayload::SendDataExportReady {
+                user_id: export.user_id,
+                email,
+                download_url: storage.presigned_url(&key, expires_at),
+                expires_at,
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+// --- Bulk User Import ---
+mod user_import {
+    use super::*;
+    use clock::Clock;
+    use futures::StreamExt;
+    use job_queue_service::JobQueueService;
+    use object_storage::ObjectStorage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tasks::{TaskError, TaskOutput};
+
+    /// Room for multi-hundred-MB exports from other systems. Uploads are streamed
+    /// to storage and back, so this bounds disk use rather than memory.
+    pub const MAX_UPLOAD_BYTES: usize = 512 * 1024 * 1024;
+    /// How much of the upload is buffered to find the header line.
+    const MAX_HEADER_BYTES: usize = 64 * 1024;
+    /// Rows per transaction. Progress is checkpointed with each chunk, so a
+    /// retried job resumes after the last committed one.
+    const CHUNK_ROWS: usize = 1000;
+    /// Only the first errors are kept in the report; `invalid_rows` counts them all.
+    const MAX_REPORTED_ERRORS: usize = 1000;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct RowError {
+        /// 1-based CSV line number.
+        pub line: u64,
+        pub error: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, FromRow)]
+    pub struct UserImport {
+        pub id: Uuid,
+        pub requested_by: Uuid,
+        pub status: String,
+        #[serde(skip)]
+        pub object_key: String,
+        pub total_bytes: i64,
+        pub processed_bytes: i64,
+        pub processed_rows: i64,
+        pub imported: i64,
+        /// Emails that already belong to an account.
+        pub skipped: i64,
+        pub invalid_rows: i64,
+        #[serde(skip)]
+        pub row_errors: String,
+        pub error_message: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub completed_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct UserImportStatus {
+        #[serde(flatten)]
+        pub import: UserImport,
+        pub progress: u8,
+        /// The per-row error report, once the import has completed.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub errors: Option<Vec<RowError>>,
+        pub errors_truncated: bool,
+    }
+
+    fn email_column<R: std::io::Read>(reader: &mut csv::Reader<R>) -> Result<usize, String> {
+        reader
+            .headers()
+            .map_err(|e| format!("Invalid CSV header: {}", e))?
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case("email"))
+            .ok_or_else(|| "CSV must have an 'email' column".to_string())
+    }
+
+    #[derive(Clone)]
+    pub struct UserImportService {
+        db_pool: SqlitePool,
+        job_queue_service: JobQueueService,
+        storage: Arc<dyn ObjectStorage>,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl UserImportService {
+        pub fn new(
+            db_pool: SqlitePool,
+            job_queue_service: JobQueueService,
+            storage: Arc<dyn ObjectStorage>,
+            clock: Arc<dyn Clock>,
+        ) -> Self {
+            Self { db_pool, job_queue_service, storage, clock }
+        }
+
+        async fn load(&self, import_id: Uuid) -> Result<UserImport, AppError> {
+            sqlx::query_as("SELECT * FROM user_imports WHERE id = ?")
+                .bind(import_id)
+                .fetch_optional(&self.db_pool)
+                .await?
+                .ok_or(AppError::ImportNotFound(import_id))
+        }
+
+        /// Admin only. The header is checked here so an obviously wrong file fails
+        /// the upload rather than the job.
+        pub async fn start(&self, requester_id: Uuid, body: axum::body::Body) -> Result<UserImport, AppError> {
+            if !data_export::is_admin(&self.db_pool, requester_id).await? {
+                return Err(AppError::Forbidden);
+            }
+            let read_error = |e: axum::Error| AppError::Validation(format!("Failed to read upload: {}", e));
+            let mut body = body.into_data_stream();
+            let mut head = Vec::new();
+            while !head.contains(&b'\n') && head.len() <= MAX_HEADER_BYTES {
+                match body.next().await {
+                    Some(chunk) => head.extend_from_slice(&chunk.map_err(read_error)?),
+                    None => break,
+                }
+            }
+            email_column(&mut csv::Reader::from_reader(head.as_slice())).map_err(AppError::Validation)?;
+
+            let import_id = Uuid::new_v4();
+            let key = format!("imports/users/{}.csv", import_id);
+            // The raw body isn't covered by DefaultBodyLimit, so the limit is enforced here
+            let received = AtomicUsize::new(0);
+            let upload = futures::stream::once(async { Ok(axum::body::Bytes::from(head)) })
+                .chain(body)
+                .map(|chunk| {
+                    let chunk = chunk.map_err(std::io::Error::other)?;
+                    if received.fetch_add(chunk.len(), Ordering::Relaxed) + chunk.len() > MAX_UPLOAD_BYTES {
+                        return Err(std::io::Error::other("upload too large"));
+                    }
+                    Ok(chunk)
+                })
+                .boxed();
+            let stored = self.storage.put_stream(&key, upload).await;
+            if received.load(Ordering::Relaxed) > MAX_UPLOAD_BYTES {
+                return Err(AppError::PayloadTooLarge(MAX_UPLOAD_BYTES));
+            }
+            let total_bytes = stored.map_err(|e| {
+                tracing::error!(?import_id, "Failed to store user import upload: {}", e);
+                AppError::Internal
+            })? as i64;
+
+            let now = self.clock.now();
+            let mut tx = self.db_pool.begin().await?;
+            sqlx::query(
+                "INSERT INTO user_imports (id, requested_by, status, object_key, total_bytes, created_at) VALUES (?, ?, 'pending', ?, ?, ?)",
+            )
+            .bind(import_id)
+            .bind(requester_id)
+            .bind(&key)
+            .bind(total_bytes)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+            audit::record(
+                &mut *tx,
+                Some(requester_id),
+                None,
+                "user_import.requested",
+                serde_json::json!({ "import_id": import_id, "bytes": total_bytes }),
+                now,
+            )
+            .await?;
+            tx.commit().await?;
+
+            self.job_queue_service
+                .schedule_task(tasks::TaskPayload::ImportUsersCsv { import_id })
+                .await?;
+            self.load(import_id).await
+        }
+
+        pub async fn get_status(&self, import_id: Uuid, requester_id: Uuid) -> Result<UserImportStatus, AppError> {
+            if !data_export::is_admin(&self.db_pool, requester_id).await? {
+                return Err(AppError::Forbidden);
+            }
+            let import = self.load(import_id).await?;
+            let progress = match import.status.as_str() {
+                "completed" => 100,
+                _ => (import.processed_bytes * 100 / import.total_bytes.max(1)).min(99) as u8,
+            };
+            let errors = (import.status == "completed")
+                .then(|| serde_json::from_str::<Vec<RowError>>(&import.row_errors).unwrap_or_default());
+            let errors_truncated = import.invalid_rows as usize > MAX_REPORTED_ERRORS;
+            Ok(UserImportStatus { import, progress, errors, errors_truncated })
+        }
+    }
+
+    /// Body of the `ImportUsersCsv` task. A failed attempt leaves the import marked
+    /// failed until a retry completes it; cancelling leaves it `cancelled` with
+    /// the chunks processed so far kept.
+    pub async fn run(
+        db_pool: &SqlitePool,
+        clock: &dyn Clock,
+        import_id: Uuid,
+        progress: &events::ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<TaskOutput, TaskError> {
+        let result = process(db_pool, clock, import_id, progress, cancel).await;
+        let failure = match &result {
+            Ok(_) => None,
+            Err(TaskError::Cancelled) => Some(("cancelled", None)),
+            Err(e) => Some(("failed", Some(e.message().to_string()))),
+        };
+        if let Some((status, message)) = failure {
+            let _ = sqlx::query("UPDATE user_imports SET status = ?, error_message = ? WHERE id = ? AND status != 'completed'")
+                .bind(status)
+                .bind(message)
+                .bind(import_id)
+                .execute(db_pool)
+                .await;
+        }
+        result
+    }
+
+    type Chunk = Result<Vec<csv::Result<csv::StringRecord>>, TaskError>;
+
+    /// Parses the upload on a blocking thread and hands over `CHUNK_ROWS` records
+    /// at a time, skipping the first `skip` already imported by an earlier attempt.
+    /// The bounded channel keeps at most a couple of chunks in memory; the reader
+    /// stops once the receiver is dropped.
+    async fn read_chunks(
+        upload: object_storage::ObjectReader,
+        skip: usize,
+    ) -> Result<(usize, tokio::sync::mpsc::Receiver<Chunk>), TaskError> {
+        let (header_tx, header_rx) = tokio::sync::oneshot::channel();
+        let (chunk_tx, chunk_rx) = tokio::sync::mpsc::channel::<Chunk>(2);
+        let upload = tokio_util::io::SyncIoBridge::new(upload);
+        tokio::task::spawn_blocking(move || {
+            // Flexible so a short or long row is reported as invalid instead of aborting
+            let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(upload);
+            let column = email_column(&mut reader);
+            let found = column.is_ok();
+            if header_tx.send(column).is_err() || !found {
+                return;
+            }
+            let mut records = reader.into_records().skip(skip);
+            loop {
+                let mut chunk = Vec::with_capacity(CHUNK_ROWS);
+                for record in records.by_ref().take(CHUNK_ROWS) {
+                    // A storage failure is worth a retry, unlike a malformed row
+                    if let Err(e) = &record {
+                        if let csv::ErrorKind::Io(io) = e.kind() {
+                            let _ = chunk_tx.blocking_send(Err(TaskError::Retryable(io.to_string())));
+                            return;
+                        }
+                    }
+                    chunk.push(record);
+                }
+                if chunk.is_empty() || chunk_tx.blocking_send(Ok(chunk)).is_err() {
+                    return;
+                }
+            }
+        });
+        let column = header_rx
+            .await
+            .map_err(|_| TaskError::Retryable("CSV reader stopped unexpectedly".to_string()))?
+            .map_err(TaskError::Validation)?;
+        Ok((column, chunk_rx))
+    }
+
+    async fn process(
+        db_pool: &SqlitePool,
+        clock: &dyn Clock,
+        import_id: Uuid,
+        progress: &events::ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<TaskOutput, TaskError> {
+        let db_err = |e: sqlx::Error| TaskError::Retryable(e.to_string());
+        let storage = object_storage::from_env();
+        let mut import: UserImport = sqlx::query_as("SELECT * FROM user_imports WHERE id = ?")
+            .bind(import_id)
+            .fetch_optional(db_pool)
+            .await
+            .map_err(db_err)?
+            .ok_or_else(|| TaskError::Permanent(format!("User import {} no longer exists", import_id)))?;
+        if import.status == "completed" {
+            return Ok(TaskOutput::Null);
+        }
+        let upload = storage
+            .open(&import.object_key)
+            .await?
+            .ok_or_else(|| TaskError::Permanent(format!("Upload for user import {} is missing", import_id)))?;
+        let (email_column, mut chunks) = read_chunks(upload, import.processed_rows as usize).await?;
+
+        sqlx::query("UPDATE user_imports SET status = 'running', error_message = NULL WHERE id = ?")
+            .bind(import_id)
+            .execute(db_pool)
+            .await
+            .map_err(db_err)?;
+
+        let mut row_errors: Vec<RowError> = serde_json::from_str(&import.row_errors).unwrap_or_default();
+        let driver = queue_driver::current();
+
+        loop {
+            if cancel.is_cancelled() {
+                return Err(TaskError::Cancelled);
+            }
+            let Some(chunk) = chunks.recv().await else {
+                break;
+            };
+            let chunk = chunk?;
+
+            let now = clock.now();
+            let mut tx = db_pool.begin().await.map_err(db_err)?;
+            let mut welcome_jobs = Vec::new();
+            for record in chunk {
+                import.processed_rows += 1;
+                let (line, email) = match record {
+                    Ok(record) => {
+                        let position = record.position();
+                        if let Some(position) = position {
+                            import.processed_bytes = position.byte() as i64;
+                        }
+                        let line = position.map(|p| p.line()).unwrap_or(0);
+                        (line, record.get(email_column).unwrap_or("").trim().to_lowercase())
+                    }
+                    Err(e) => {
+                        let line = e.position().map(|p| p.line()).unwrap_or(0);
+                        import.invalid_rows += 1;
+                        if row_errors.len() < MAX_REPORTED_ERRORS {
+                            row_errors.push(RowError { line, error: format!("Unreadable row: {}", e) });
+                        }
+                        continue;
+                    }
+                };
+                if !email.contains('@') || email.contains(char::is_whitespace) {
+                    import.invalid_rows += 1;
+                    if row_errors.len() < MAX_REPORTED_ERRORS {
+                        row_errors.push(RowError { line, error: "Missing or malformed email".to_string() });
+                    }
+                    continue;
+                }
+
+                let user_id = Uuid::new_v4();
+                let inserted = sqlx::query(
+                    "INSERT OR IGNORE INTO users (id, email, role, is_active, created_at) VALUES (?, ?, 'USER', TRUE, ?)",
+                )
+                .bind(user_id)
+                .bind(&email)
+                .bind(now)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?
+                .rows_affected();
+                if inserted == 1 {
+                    import.imported += 1;
+                    // Enqueued in the chunk's transaction so a retry can't lose or repeat them
+                    let payload = tasks::TaskPayload::SendWelcomeEmail { user_id, email };
+                    let job = job_queue_service::insert_job(&mut *tx, &payload, payload.queue(), None, now)
+                        .await
+                        .map_err(db_err)?;
+                    welcome_jobs.push(job);
+                } else {
+                    import.skipped += 1;
+                }
+            }
+
+            sqlx::query(
+                "UPDATE user_imports SET processed_rows = ?, processed_bytes = ?, imported = ?, skipped = ?, invalid_rows = ?, row_errors = ? WHERE id = ?",
+            )
+            .bind(import.processed_rows)
+            .bind(import.processed_bytes)
+            .bind(import.imported)
+            .bind(import.skipped)
+            .bind(import.invalid_rows)
+            .bind(serde_json::to_string(&row_errors).unwrap_or_else(|_| "[]".to_string()))
+            .bind(import_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+            tx.commit().await.map_err(db_err)?;
+
+            driver.push_many(&welcome_jobs).await.map_err(|e| TaskError::Retryable(e.to_string()))?;
+            let percent = (import.processed_bytes * 100 / import.total_bytes.max(1)).min(99) as u8;
+            progress.report(percent, &format!("Processed {} rows", import.processed_rows));
+        }
+
+        let completed_at = clock.now();
+        let mut tx = db_pool.begin().await.map_err(db_err)?;
+        sqlx::query("UPDATE user_imports SET status = 'completed', processed_bytes = total_bytes, completed_at = ? WHERE id = ?")
+            .bind(completed_at)
+            .bind(import_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        audit::record(
+            &mut *tx,
+            None,
+            None,
+            "user_import.completed",
+            serde_json::json!({ "import_id": import_id, "imported": import.imported }),
+            completed_at,
+        )
+        .await
+        .map_err(db_err)?;
+        tx.commit().await.map_err(db_err)?;
+        progress.report(100, "Import complete");
+
+        // The report has everything callers need; the raw upload may hold personal data
+        if let Err(e) = storage.delete(&import.object_key).await {
+            tracing::warn!(?import_id, "Failed to delete user import upload: {}", e);
+        }
+        info!(
+            ?import_id,
+            "Imported {} users ({} skipped, {} invalid)", import.imported, import.skipped, import.invalid_rows
+        );
+        Ok(serde_json::json!({
+            "imported": import.imported,
+            "skipped": import.skipped,
+            "invalid_rows": import.invalid_rows,
+        }))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use object_storage::LocalObjectStorage;
+
+        fn storage(name: &str) -> LocalObjectStorage {
+            let root = std::env::temp_dir().join(format!("user-import-{}-{}", name, Uuid::new_v4()));
+            LocalObjectStorage::new(root, "http://localhost".to_string(), b"test-key".to_vec())
+        }
+
+        fn chunked(csv: String) -> object_storage::ByteStream<'static> {
+            let chunks: Vec<_> = csv.into_bytes().chunks(4096).map(|c| Ok(axum::body::Bytes::copy_from_slice(c))).collect();
+            futures::stream::iter(chunks).boxed()
+        }
+
+        #[tokio::test]
+        async fn uploads_stream_through_storage_in_chunks() {
+            let storage = storage("chunks");
+            let rows = CHUNK_ROWS * 2 + 500;
+            let csv = std::iter::once("name,email\n".to_string())
+                .chain((0..rows).map(|i| format!("user {},user{}@example.com\n", i, i)))
+                .collect::<String>();
+            let len = csv.len() as u64;
+            assert_eq!(storage.put_stream("imports/users/a.csv", chunked(csv)).await.unwrap(), len);
+
+            // Resuming after the first committed chunk
+            let upload = storage.open("imports/users/a.csv").await.unwrap().unwrap();
+            let (column, mut chunks) = read_chunks(upload, CHUNK_ROWS).await.unwrap();
+            assert_eq!(column, 1);
+            let mut sizes = Vec::new();
+            let mut first = None;
+            while let Some(chunk) = chunks.recv().await {
+                let chunk = chunk.unwrap();
+                first.get_or_insert_with(|| chunk[0].as_ref().unwrap().get(1).unwrap().to_string());
+                sizes.push(chunk.len());
+            }
+            assert_eq!(sizes, vec![CHUNK_ROWS, 500]);
+            assert_eq!(first.as_deref(), Some("user1000@example.com"));
+        }
+
+        #[tokio::test]
+        async fn missing_email_column_fails_before_any_rows() {
+            let storage = storage("header");
+            storage.put_stream("a.csv", chunked("name,phone\nada,123\n".to_string())).await.unwrap();
+            let upload = storage.open("a.csv").await.unwrap().unwrap();
+            assert!(matches!(read_chunks(upload, 0).await, Err(TaskError::Validation(_))));
+        }
+
+        #[tokio::test]
+        async fn failed_upload_leaves_no_object_behind() {
+            let storage = storage("failed");
+            let body = futures::stream::iter(vec![
+                Ok(axum::body::Bytes::from_static(b"email\na@example.com\n")),
+                Err(std::io::Error::other("connection reset")),
+            ])
+            .boxed();
+            assert!(storage.put_stream("b.csv", body).await.is_err());
+            assert!(storage.open("b.csv").await.unwrap().is_none());
+        }
+    }
+}
+
+// --- Erasure Service ---
+mod erasure {
+    use super::*;
+    use clock::Clock;
+    use job_queue_service::JobQueueService;
+    use sha2::{Digest, Sha256};
+
+    /// Owner of posts reassigned away from erased accounts. Seeded in `setup_database`.
+    pub const ANONYMOUS_AUTHOR_ID: Uuid = Uuid::nil();
+    pub const TOMBSTONE_TITLE: &str = "[deleted]";
+
+    #[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, sqlx::Type)]
+    #[serde(rename_all = "snake_case")]
+    #[sqlx(rename_all = "snake_case")]
+    pub enum PostPolicy {
+        /// Keep the rows but blank their content and revision history.
+        #[default]
+        Tombstone,
+        /// Keep the content and hand ownership to the anonymous author.
+        Reassign,
+    }
+
+    #[derive(Debug, Deserialize, Default)]
+    pub struct EraseQuery {
+        #[serde(default)]
+        pub posts: PostPolicy,
+    }
+
+    #[derive(Debug, Clone, Serialize, FromRow)]
+    pub struct UserErasure {
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub requested_by: Uuid,
+        pub post_policy: PostPolicy,
+        pub status: String,
+        pub error_message: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub verified_at: Option<DateTime<Utc>>,
+    }
+
+    /// Deterministic per user so a retried erasure writes the same value.
+    fn placeholder_email(user_id: Uuid, email: &str) -> String {
+        let digest = Sha256::new()
+            .chain_update(user_id.as_bytes())
+            .chain_update(email.as_bytes())
+            .finalize();
+        format!("erased-{}@erased.invalid", &hex::encode(digest)[..16])
+    }
+
+    #[derive(Clone)]
+    pub struct ErasureService {
+        db_pool: SqlitePool,
+        job_queue_service: JobQueueService,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl ErasureService {
+        pub fn new(db_pool: SqlitePool, job_queue_service: JobQueueService, clock: Arc<dyn Clock>) -> Self {
+            Self { db_pool, job_queue_service, clock }
+        }
+
+        pub async fn request_erasure(&self, user_id: Uuid, requester_id: Uuid, policy: PostPolicy) -> Result<UserErasure, AppError> {
+            if requester_id != user_id && !data_export::is_admin(&self.db_pool, requester_id).await? {
+                return Err(AppError::Forbidden);
+            }
+            if user_id == ANONYMOUS_AUTHOR_ID {
+                return Err(AppError::Validation("The anonymous author cannot be erased".to_string()));
+            }
+            let erased_at: Option<Option<DateTime<Utc>>> = sqlx::query_scalar("SELECT erased_at FROM users WHERE id = ?")
+                .bind(user_id)
+                .fetch_optional(&self.db_pool)
+                .await?;
+            match erased_at {
+                None => return Err(AppError::UserNotFound(user_id)),
+                Some(Some(_)) => return Err(AppError::Conflict("User has already been erased".to_string())),
+                Some(None) => {}
+            }
+
+            let erasure = UserErasure {
+                id: Uuid::new_v4(),
+                user_id,
+                requested_by: requester_id,
+                post_policy: policy,
+                status: "pending".to_string(),
+                error_message: None,
+                created_at: self.clock.now(),
+                verified_at: None,
+            };
+            let mut tx = self.db_pool.begin().await?;
+            sqlx::query(
+                "INSERT INTO user_erasures (id, user_id, requested_by, post_policy, status, created_at) VALUES (?, ?, ?, ?, 'pending', ?)",
+            )
+            .bind(erasure.id)
+            .bind(user_id)
+            .bind(requester_id)
+            .bind(policy)
+            .bind(erasure.created_at)
+            .execute(&mut *tx)
+            .await?;
+            audit::record(
+                &mut *tx,
+                Some(requester_id),
+                Some(user_id),
+                "user_erasure.requested",
+                serde_json::json!({ "erasure_id": erasure.id, "post_policy": policy }),
+                erasure.created_at,
+            )
+            .await?;
+            tx.commit().await?;
+
+            self.job_queue_service
+                .schedule_task(tasks::TaskPayload::EraseUser { erasure_id: erasure.id })
+                .await?;
+            Ok(erasure)
+        }
+
+        pub async fn get_status(&self, user_id: Uuid, erasure_id: Uuid, requester_id: Uuid) -> Result<UserErasure, AppError> {
+            if requester_id != user_id && !data_export::is_admin(&self.db_pool, requester_id).await? {
+                return Err(AppError::Forbidden);
+            }
+            sqlx::query_as("SELECT * FROM user_erasures WHERE id = ? AND user_id = ?")
+                .bind(erasure_id)
+                .bind(user_id)
+                .fetch_optional(&self.db_pool)
+                .await?
+                .ok_or(AppError::ErasureNotFound(erasure_id))
+        }
+    }
+
+    /// What `scrub` leaves for the steps after it.
+    struct Scrubbed {
+        object_keys: Vec<String>,
+        /// The address before erasure; `None` when an earlier attempt already replaced it.
+        original_email: Option<String>,
+    }
+
+    /// Scrubs everything in one transaction. Each statement is safe to re-run, so a
+    /// retry after a partial failure converges on the same end state.
+    async fn scrub(db_pool: &SqlitePool, erasure: &UserErasure, now: DateTime<Utc>) -> Result<Scrubbed, sqlx::Error> {
+        let user_id = erasure.user_id;
+        let mut tx = db_pool.begin().await?;
+
+        let (email, erased_at): (String, Option<DateTime<Utc>>) =
+            sqlx::query_as("SELECT email, erased_at FROM users WHERE id = ?")
+                .bind(user_id)
+                .fetch_one(&mut *tx)
+                .await?;
+        let (placeholder, original_email) = match erased_at {
+            Some(_) => (email, None),
+            None => (placeholder_email(user_id, &email), Some(email)),
+        };
+        let avatar_version: Option<Uuid> = sqlx::query_scalar("SELECT avatar_version FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        sqlx::query(
+            "UPDATE users SET email = ?, is_active = FALSE, erased_at = ?, avatar_version = NULL, avatar_url = NULL, \
+             password_hash = NULL WHERE id = ? AND erased_at IS NULL",
+        )
+        .bind(&placeholder)
+        .bind(now)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM pending_email_changes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM password_resets WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "UPDATE posts SET like_count = MAX(like_count - 1, 0) WHERE id IN (SELECT post_id FROM post_likes WHERE user_id = ?)",
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM post_likes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let affected_posts = match erasure.post_policy {
+            PostPolicy::Tombstone => {
+                sqlx::query("DELETE FROM post_revisions WHERE post_id IN (SELECT id FROM posts WHERE user_id = ?)")
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("UPDATE posts SET title = ?, content = '', status = 'DRAFT', publish_at = NULL WHERE user_id = ?")
+                    .bind(TOMBSTONE_TITLE)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected()
+            }
+            PostPolicy::Reassign => sqlx::query("UPDATE posts SET user_id = ? WHERE user_id = ?")
+                .bind(ANONYMOUS_AUTHOR_ID)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected(),
+        };
+        // Edits the user made to other people's posts
+        sqlx::query("UPDATE post_revisions SET editor_id = ? WHERE editor_id = ?")
+            .bind(ANONYMOUS_AUTHOR_ID)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Job and webhook payloads are kept after they run, and some carry the address
+        redact_payloads(&mut tx, user_id, original_email.as_deref(), &placeholder).await?;
+
+        // Export bundles are full copies of the user's data
+        let mut object_keys: Vec<String> = sqlx::query_scalar("SELECT object_key FROM data_exports WHERE user_id = ? AND object_key IS NOT NULL")
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM data_exports WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        audit::record(
+            &mut *tx,
+            Some(erasure.requested_by),
+            Some(user_id),
+            "user_erasure.completed",
+            serde_json::json!({
+                "erasure_id": erasure.id,
+                "post_policy": erasure.post_policy,
+                "posts_affected": affected_posts,
+            }),
+            now,
+        )
+        .await?;
+        sqlx::query("UPDATE user_erasures SET status = 'scrubbed', error_message = NULL WHERE id = ?")
+            .bind(erasure.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        if let Some(version) = avatar_version {
+            object_keys.extend(avatars::object_keys(user_id, version));
+        }
+        Ok(Scrubbed { object_keys, original_email })
+    }
+
+    /// Overwrites email fields in the user's jobs (`email`, `old_email`, `new_email`)
+    /// and `user.created` deliveries, then any other payload quoting the address.
+    async fn redact_payloads(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        user_id: Uuid,
+        original_email: Option<&str>,
+        placeholder: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE jobs SET payload = json_replace(payload, '$.email', ?, '$.old_email', ?, '$.new_email', ?) \
+             WHERE json_extract(payload, '$.user_id') = ?",
+        )
+        .bind(placeholder)
+        .bind(placeholder)
+        .bind(placeholder)
+        .bind(user_id.to_string())
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query(
+            "UPDATE webhook_deliveries SET payload = json_replace(payload, '$.data.email', ?) \
+             WHERE json_extract(payload, '$.data.user_id') = ?",
+        )
+        .bind(placeholder)
+        .bind(user_id.to_string())
+        .execute(&mut **tx)
+        .await?;
+
+        let Some(email) = original_email else { return Ok(()) };
+        let (needle, replacement) = (json_string(email), json_string(placeholder));
+        for table in ["jobs", "webhook_deliveries"] {
+            sqlx::query(&format!(
+                "UPDATE {table} SET payload = replace(payload, ?, ?) WHERE instr(payload, ?) > 0"
+            ))
+            .bind(&needle)
+            .bind(&replacement)
+            .bind(&needle)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// How a string appears inside a stored JSON payload, quotes included.
+    fn json_string(value: &str) -> String {
+        serde_json::Value::from(value).to_string()
+    }
+
+    /// Re-reads every location the scrub touched and reports what still holds user data.
+    async fn verify(
+        db_pool: &SqlitePool,
+        erasure: &UserErasure,
+        original_email: Option<&str>,
+    ) -> Result<Vec<&'static str>, sqlx::Error> {
+        let user_id = erasure.user_id;
+        let mut leftovers = Vec::new();
+
+        let scrubbed: bool = sqlx::query_scalar(
+            "SELECT COUNT(*) > 0 FROM users WHERE id = ? AND erased_at IS NOT NULL AND is_active = FALSE \
+             AND avatar_url IS NULL AND password_hash IS NULL AND email LIKE 'erased-%@erased.invalid'",
+        )
+        .bind(user_id)
+        .fetch_one(db_pool)
+        .await?;
+        if !scrubbed {
+            leftovers.push("users");
+        }
+
+        let checks: [(&'static str, &str); 6] = [
+            ("pending_email_changes", "SELECT COUNT(*) FROM pending_email_changes WHERE user_id = ?"),
+            ("password_resets", "SELECT COUNT(*) FROM password_resets WHERE user_id = ?"),
+            ("post_likes", "SELECT COUNT(*) FROM post_likes WHERE user_id = ?"),
+            ("post_revisions", "SELECT COUNT(*) FROM post_revisions WHERE editor_id = ?"),
+            ("data_exports", "SELECT COUNT(*) FROM data_exports WHERE user_id = ?"),
+            (
+                "posts",
+                match erasure.post_policy {
+                    PostPolicy::Tombstone => "SELECT COUNT(*) FROM posts WHERE user_id = ? AND (content != '' OR status != 'DRAFT')",
+                    PostPolicy::Reassign => "SELECT COUNT(*) FROM posts WHERE user_id = ?",
+                },
+            ),
+        ];
+        for (table, sql) in checks {
+            let remaining: i64 = sqlx::query_scalar(sql).bind(user_id).fetch_one(db_pool).await?;
+            if remaining > 0 {
+                leftovers.push(table);
+            }
+        }
+
+        if let Some(email) = original_email {
+            let needle = json_string(email);
+            for (table, sql) in [
+                ("jobs", "SELECT COUNT(*) FROM jobs WHERE instr(payload, ?) > 0"),
+                ("webhook_deliveries", "SELECT COUNT(*) FROM webhook_deliveries WHERE instr(payload, ?) > 0"),
+            ] {
+                let remaining: i64 = sqlx::query_scalar(sql).bind(&needle).fetch_one(db_pool).await?;
+                if remaining > 0 {
+                    leftovers.push(table);
+                }
+            }
+        }
+        Ok(leftovers)
+    }
+
+    /// Body of the `EraseUser` task.
+    pub async fn run(db_pool: &SqlitePool, clock: &dyn Clock, erasure_id: Uuid) -> Result<(), String> {
+        let erasure: UserErasure = sqlx::query_as("SELECT * FROM user_erasures WHERE id = ?")
+            .bind(erasure_id)
+            .fetch_optional(db_pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Erasure request {} no longer exists", erasure_id))?;
+        if erasure.status == "completed" {
+            return Ok(());
+        }
+
+        let fail = |message: String| async move {
+            let _ = sqlx::query("UPDATE user_erasures SET status = 'failed', error_message = ? WHERE id = ?")
+                .bind(&message)
+                .bind(erasure_id)
+                .execute(db_pool)
+                .await;
+            Err(message)
+        };
+
+        let scrubbed = match scrub(db_pool, &erasure, clock.now()).await {
+            Ok(scrubbed) => scrubbed,
+            Err(e) => return fail(format!("Erasure transaction failed: {}", e)).await,
+        };
+        let storage = object_storage::from_env();
+        for key in &scrubbed.object_keys {
+            if let Err(e) = storage.delete(key).await {
+                return fail(format!("Failed to delete stored object {}: {}", key, e)).await;
+            }
+        }
+
+        match verify(db_pool, &erasure, scrubbed.original_email.as_deref()).await {
+            Ok(leftovers) if leftovers.is_empty() => {
+                sqlx::query("UPDATE user_erasures SET status = 'completed', verified_at = ? WHERE id = ?")
+                    .bind(clock.now())
+                    .bind(erasure_id)
+                    .execute(db_pool)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                info!(user_id = ?erasure.user_id, "Erasure {} verified", erasure_id);
+                Ok(())
+            }
+            Ok(leftovers) => fail(format!("Verification found remaining data in: {}", leftovers.join(", "))).await,
+            Err(e) => fail(format!("Verification query failed: {}", e)).await,
+        }
+    }
+}
+
+// --- Outbound HTTP ---
+pub(crate) mod http_client {
+    use super::*;
+    use rand::Rng;
+    use std::net::SocketAddr;
+    use tracing::Instrument;
+
+    const BACKOFF_BASE: Duration = Duration::from_millis(200);
+    const BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+    /// Limits for one kind of outbound call.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Destination {
+        pub name: &'static str,
+        pub timeout: Duration,
+        /// Total tries including the first.
+        pub max_attempts: u32,
+        pub max_response_bytes: usize,
+        /// When false a 3xx is handed back as-is instead of being followed.
+        pub follow_redirects: bool,
+    }
+
+    impl Destination {
+        /// The job queue already retries deliveries, so one try per job run.
+        pub const WEBHOOK: Destination = Destination {
+            name: "webhook",
+            timeout: Duration::from_secs(10),
+            max_attempts: 1,
+            max_response_bytes: 64 * 1024,
+            // The target was checked, wherever it redirects to was not
+            follow_redirects: false,
+        };
+        pub const IMAGE_FETCH: Destination = Destination {
+            name: "image_fetch",
+            timeout: Duration::from_secs(15),
+            max_attempts: 3,
+            max_response_bytes: 10 * 1024 * 1024,
+            // A redirect could point anywhere, including back inside the network
+            follow_redirects: false,
+        };
+        /// Called while a registration or reset request waits, so it gives up fast
+        /// and leaves the caller to fall back to its offline checks.
+        pub const PASSWORD_RANGE: Destination = Destination {
+            name: "password_range",
+            timeout: Duration::from_secs(3),
+            max_attempts: 2,
+            max_response_bytes: 256 * 1024,
+            follow_redirects: false,
+        };
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct HttpRequest {
+        pub method: reqwest::Method,
+        pub url: String,
+        pub headers: Vec<(String, String)>,
+        pub body: Option<Vec<u8>>,
+        /// Connect to this address instead of resolving the URL's host again.
+        pub pinned_addr: Option<SocketAddr>,
+    }
+
+    impl HttpRequest {
+        pub fn get(url: &str) -> Self {
+            Self { method: reqwest::Method::GET, url: url.to_string(), headers: Vec::new(), body: None, pinned_addr: None }
+        }
+
+        pub fn post(url: &str, body: Vec<u8>) -> Self {
+            Self {
+                method: reqwest::Method::POST,
+                url: url.to_string(),
+                headers: Vec::new(),
+                body: Some(body),
+                pinned_addr: None,
+            }
+        }
+
+        pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+            self.headers.push((name.to_string(), value.into()));
+            self
+        }
+
+        pub fn pinned_to(mut self, addr: SocketAddr) -> Self {
+            self.pinned_addr = Some(addr);
+            self
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct HttpResponse {
+        pub status: u16,
+        pub content_type: Option<String>,
+        pub body: Vec<u8>,
+    }
+
+    impl HttpResponse {
+        pub fn is_success(&self) -> bool {
+            (200..300).contains(&self.status)
+        }
+    }
+
+    #[derive(thiserror::Error, Debug)]
+    pub enum HttpError {
+        #[error("request timed out")]
+        Timeout,
+        #[error("request failed: {0}")]
+        Transport(String),
+        #[error("response body exceeded {0} bytes")]
+        TooLarge(usize),
+    }
+
+    /// Performs a single request. The service is generic over this so the network
+    /// can be replaced with canned responses.
+    #[async_trait::async_trait]
+    pub trait HttpTransport: Send + Sync {
+        async fn send(&self, request: &HttpRequest, destination: &Destination) -> Result<HttpResponse, HttpError>;
+    }
+
+    pub struct ReqwestTransport {
+        client: reqwest::Client,
+        no_redirect_client: reqwest::Client,
+    }
+
+    fn redirect_policy(destination: &Destination) -> reqwest::redirect::Policy {
+        if destination.follow_redirects {
+            reqwest::redirect::Policy::limited(3)
+        } else {
+            reqwest::redirect::Policy::none()
+        }
+    }
+
+    impl Default for ReqwestTransport {
+        fn default() -> Self {
+            let build = |policy| reqwest::Client::builder().redirect(policy).build().expect("Failed to build HTTP client");
+            Self {
+                client: build(reqwest::redirect::Policy::limited(3)),
+                no_redirect_client: build(reqwest::redirect::Policy::none()),
+            }
+        }
+    }
+
+    fn transport_error(e: reqwest::Error) -> HttpError {
+        if e.is_timeout() {
+            HttpError::Timeout
+        } else {
+            HttpError::Transport(e.to_string())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpTransport for ReqwestTransport {
+        async fn send(&self, request: &HttpRequest, destination: &Destination) -> Result<HttpResponse, HttpError> {
+            let client = match request.pinned_addr {
+                // reqwest only pins DNS per client, so pinned requests get their own
+                Some(addr) => {
+                    let url = reqwest::Url::parse(&request.url).map_err(|e| HttpError::Transport(e.to_string()))?;
+                    let host = url.host_str().ok_or_else(|| HttpError::Transport("URL has no host".to_string()))?;
+                    reqwest::Client::builder()
+                        .redirect(redirect_policy(destination))
+                        .resolve(host, addr)
+                        .build()
+                        .map_err(transport_error)?
+                }
+                None if destination.follow_redirects => self.client.clone(),
+                None => self.no_redirect_client.clone(),
+            };
+            let mut builder = client.request(request.method.clone(), &request.url).timeout(destination.timeout);
+            for (name, value) in &request.headers {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+            if let Some(body) = &request.body {
+                builder = builder.body(body.clone());
+            }
+            let mut response = builder.send().await.map_err(transport_error)?;
+
+            let limit = destination.max_response_bytes;
+            if response.content_length().is_some_and(|len| len > limit as u64) {
+                return Err(HttpError::TooLarge(limit));
+            }
+            let status = response.status().as_u16();
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            // Content-Length may be missing or wrong, so the limit also applies while reading
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(transport_error)? {
+                if body.len() + chunk.len() > limit {
+                    return Err(HttpError::TooLarge(limit));
+                }
+                body.extend_from_slice(&chunk);
+            }
+            Ok(HttpResponse { status, content_type, body })
+        }
+    }
+
+    /// Shared client for every outbound call.
+    #[derive(Clone)]
+    pub struct HttpClientService {
+        transport: Arc<dyn HttpTransport>,
+    }
+
+    impl HttpClientService {
+        pub fn new(transport: Arc<dyn HttpTransport>) -> Self {
+            Self { transport }
+        }
+
+        /// Retries timeouts, transport errors, 429 and 5xx responses up to the
+        /// destination's `max_attempts`, sleeping a random fraction of an exponential
+        /// backoff in between. Any other response is returned for the caller to judge.
+        pub async fn send(&self, destination: Destination, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+            let host = reqwest::Url::parse(&request.url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_default();
+            let span = tracing::info_span!("http_request", destination = destination.name, method = %request.method, %host);
+            async move {
+                let mut attempt = 1;
+                loop {
+                    let started = std::time::Instant::now();
+                    let result = self.transport.send(&request, &destination).await;
+                    let elapsed_ms = started.elapsed().as_millis() as u64;
+                    let retryable = match &result {
+                        Ok(response) => {
+                            tracing::debug!(attempt, status = response.status, elapsed_ms, "HTTP response");
+                            response.status == 429 || response.status >= 500
+                        }
+                        Err(e) => {
+                            tracing::warn!(attempt, elapsed_ms, "HTTP request failed: {}", e);
+                            !matches!(e, HttpError::TooLarge(_))
+                        }
+                    };
+                    if !retryable || attempt >= destination.max_attempts {
+                        return result;
+                    }
+                    sleep(backoff(attempt)).await;
+                    attempt += 1;
+                }
+            }
+            .instrument(span)
+            .await
+        }
+    }
+
+    fn backoff(attempt: u32) -> Duration {
+        let ceiling = BACKOFF_BASE.saturating_mul(2u32.saturating_pow(attempt - 1)).min(BACKOFF_MAX);
+        ceiling.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+// --- Remote Images ---
+mod remote_image {
+    use super::*;
+    use clock::Clock;
+    use http_client::{Destination, HttpClientService, HttpError, HttpRequest};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use tasks::{TaskError, TaskOutput};
+
+    /// Accepted content types and the extension the stored copy gets.
+    const ALLOWED_TYPES: [(&str, &str); 4] =
+        [("image/jpeg", "jpg"), ("image/png", "png"), ("image/gif", "gif"), ("image/webp", "webp")];
+    const ACCEPT: &str = "image/jpeg, image/png, image/gif, image/webp";
+    const ALLOWED_PORTS: [u16; 2] = [80, 443];
+    /// The stored copy only has to stay readable for the rest of the pipeline.
+    const PIPELINE_URL_TTL_DAYS: i64 = 7;
+
+    #[derive(thiserror::Error, Debug)]
+    pub enum SourceError {
+        #[error("{0}")]
+        Rejected(String),
+        #[error("Could not resolve '{0}'")]
+        Lookup(String),
+    }
+
+    /// A source URL that passed validation, and the address it resolved to.
+    pub struct ResolvedSource {
+        pub url: reqwest::Url,
+        pub addr: SocketAddr,
+    }
+
+    /// Accepts plain http(s) URLs on the standard ports whose host resolves only to
+    /// public addresses. Every address is checked, not just the first, so a name with
+    /// one public and one internal record is still rejected.
+    pub async fn resolve_source(raw: &str) -> Result<ResolvedSource, SourceError> {
+        let reject = |msg: String| SourceError::Rejected(msg);
+        let url = reqwest::Url::parse(raw).map_err(|e| reject(format!("'{}' is not a valid URL: {}", raw, e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(reject("Only http and https URLs can be fetched".to_string()));
+        }
+        if !url.username().is_empty() || url.password().is_some() {
+            return Err(reject("URLs with credentials are not allowed".to_string()));
+        }
+        let port = url.port_or_known_default().unwrap_or(0);
+        if !ALLOWED_PORTS.contains(&port) {
+            return Err(reject(format!("Port {} is not allowed", port)));
+        }
+        let host = url.host_str().ok_or_else(|| reject("URL has no host".to_string()))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']').trim_end_matches('.').to_ascii_lowercase();
+
+        let addrs: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![SocketAddr::new(ip, port)],
+            Err(_) => {
+                if host == "localhost" || [".localhost", ".local", ".internal"].iter().any(|suffix| host.ends_with(suffix)) {
+                    return Err(reject(format!("Host '{}' is not allowed", host)));
+                }
+                tokio::net::lookup_host((host.as_str(), port))
+                    .await
+                    .map_err(|_| SourceError::Lookup(host.clone()))?
+                    .collect()
+            }
+        };
+        let Some(&addr) = addrs.first() else { return Err(SourceError::Lookup(host)) };
+        if addrs.iter().any(|addr| !is_public(addr.ip())) {
+            return Err(reject(format!("Host '{}' resolves to a non-public address", host)));
+        }
+        Ok(ResolvedSource { url, addr })
+    }
+
+    fn is_public(ip: IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => is_public_v4(ip),
+            IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                Some(mapped) => is_public_v4(mapped),
+                None => is_public_v6(ip),
+            },
+        }
+    }
+
+    fn is_public_v4(ip: Ipv4Addr) -> bool {
+        let [a, b, ..] = ip.octets();
+        !(ip.is_private()
+            || ip.is_loopback()
+            || ip.is_link_local()
+            || ip.is_unspecified()
+            || ip.is_broadcast()
+            || ip.is_multicast()
+            || ip.is_documentation()
+            || a == 0
+            // Carrier-grade NAT, benchmarking and the reserved 240/4 block
+            || (a == 100 && (64..128).contains(&b))
+            || (a == 198 && (b == 18 || b == 19))
+            || a >= 240)
+    }
+
+    fn is_public_v6(ip: Ipv6Addr) -> bool {
+        let segments = ip.segments();
+        !(ip.is_loopback()
+            || ip.is_unspecified()
+            || ip.is_multicast()
+            // Unique local, link-local and documentation ranges
+            || (segments[0] & 0xfe00) == 0xfc00
+            || (segments[0] & 0xffc0) == 0xfe80
+            || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+    }
+
+    /// Content type implied by the file's magic bytes.
+    fn sniff(bytes: &[u8]) -> Option<&'static str> {
+        if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some("image/jpeg")
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some("image/png")
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some("image/gif")
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some("image/webp")
+        } else {
+            None
+        }
+    }
+
+    /// Downloads `source_url` into object storage and outputs the `image_url` the
+    /// `process_image` step reads. The host is resolved again here and the connection
+    /// pinned to that answer, so DNS that changed since the request was accepted
+    /// cannot point the fetch inward.
+    pub async fn fetch_and_store(
+        http: &HttpClientService,
+        clock: &dyn Clock,
+        post_id: Uuid,
+        source_url: &str,
+    ) -> Result<TaskOutput, TaskError> {
+        let source = resolve_source(source_url).await.map_err(|e| match e {
+            SourceError::Rejected(msg) => TaskError::Validation(msg),
+            SourceError::Lookup(_) => TaskError::Retryable(e.to_string()),
+        })?;
+        let request = HttpRequest::get(source.url.as_str()).header("Accept", ACCEPT).pinned_to(source.addr);
+        let response = http.send(Destination::IMAGE_FETCH, request).await.map_err(|e| match e {
+            HttpError::TooLarge(_) => TaskError::Validation(e.to_string()),
+            _ => TaskError::Retryable(e.to_string()),
+        })?;
+        if (300..400).contains(&response.status) {
+            return Err(TaskError::Permanent(format!("{} redirected; redirects are not followed", source_url)));
+        }
+        if !response.is_success() {
+            let msg = format!("{} responded with {}", source_url, response.status);
+            return Err(if response.status == 429 || response.status >= 500 {
+                TaskError::Retryable(msg)
+            } else {
+                TaskError::Permanent(msg)
+            });
+        }
+
+        let declared = response
+            .content_type
+            .as_deref()
+            .and_then(|ct| ct.split(';').next())
+            .map(|ct| ct.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        let Some(&(mime, extension)) = ALLOWED_TYPES.iter().find(|(mime, _)| *mime == declared) else {
+            return Err(TaskError::Validation(format!("Unsupported content type '{}'", declared)));
+        };
+        // The header is the server's claim; the bytes have to agree with it
+        if sniff(&response.body) != Some(mime) {
+            return Err(TaskError::Validation(format!("Response body is not a valid {}", mime)));
+        }
+
+        let key = format!("post-images/{}/{}.{}", post_id, Uuid::new_v4(), extension);
+        let storage = object_storage::from_env();
+        storage.put(&key, response.body).await?;
+        let image_url = storage.presigned_url(&key, clock.now() + chrono::Duration::days(PIPELINE_URL_TTL_DAYS));
+        info!(?post_id, "Stored remote image from {} as {}", source.url, key);
+        Ok(serde_json::json!({ "image_url": image_url }))
+    }
+}
+
+// --- Avatars ---
+mod avatars {
+    use super::*;
+    use image::{imageops::FilterType, ImageFormat};
+    use object_storage::ObjectStorage;
+    use std::io::Cursor;
+    use tasks::{TaskError, TaskOutput, TaskPayload};
+
+    pub const MAX_UPLOAD_BYTES: usize = 5 * 1024 * 1024;
+    const MIN_DIMENSION: u32 = 64;
+    const MAX_DIMENSION: u32 = 4096;
+    const ALLOWED_TYPES: [(&str, ImageFormat); 3] =
+        [("image/jpeg", ImageFormat::Jpeg), ("image/png", ImageFormat::Png), ("image/webp", ImageFormat::WebP)];
+    /// Edge lengths, in pixels, of the square PNG variants kept per upload.
+    pub const SIZES: [u32; 3] = [64, 128, 256];
+    /// The variant `avatar_url` points at.
+    const PROFILE_SIZE: u32 = 256;
+
+    fn original_key(user_id: Uuid, version: Uuid) -> String {
+        format!("avatars/{}/{}/original", user_id, version)
+    }
+
+    fn variant_key(user_id: Uuid, version: Uuid, size: u32) -> String {
+        format!("avatars/{}/{}/{}.png", user_id, version, size)
+    }
+
+    /// Every object an avatar version can leave behind.
+    pub fn object_keys(user_id: Uuid, version: Uuid) -> Vec<String> {
+        let mut keys: Vec<String> = SIZES.iter().map(|&size| variant_key(user_id, version, size)).collect();
+        keys.push(original_key(user_id, version));
+        keys
+    }
+
+    fn storage_error(key: &str, e: String) -> AppError {
+        tracing::error!("Object storage failed for {}: {}", key, e);
+        AppError::Internal
+    }
+
+    /// Only reads the image header, so oversized or mislabelled files are turned away
+    /// before anything is decoded.
+    fn validate(content_type: Option<&str>, bytes: &[u8]) -> Result<(), AppError> {
+        let invalid = |msg: String| AppError::Validation(msg);
+        if bytes.is_empty() {
+            return Err(invalid("Avatar file is empty".to_string()));
+        }
+        if bytes.len() > MAX_UPLOAD_BYTES {
+            return Err(invalid(format!("Avatar must be at most {} MB", MAX_UPLOAD_BYTES / (1024 * 1024))));
+        }
+        let declared = content_type.unwrap_or("");
+        let Some(&(_, format)) = ALLOWED_TYPES.iter().find(|(mime, _)| *mime == declared) else {
+            let allowed: Vec<&str> = ALLOWED_TYPES.iter().map(|(mime, _)| *mime).collect();
+            return Err(invalid(format!("Unsupported avatar type '{}'; expected one of {}", declared, allowed.join(", "))));
+        };
+        let (width, height) = image::io::Reader::with_format(Cursor::new(bytes), format)
+            .into_dimensions()
+            .map_err(|_| invalid(format!("File is not a valid {} image", declared)))?;
+        if width < MIN_DIMENSION || height < MIN_DIMENSION {
+            return Err(invalid(format!(
+                "Avatar is {}x{} pixels; both sides must be at least {}",
+                width, height, MIN_DIMENSION
+            )));
+        }
+        if width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(invalid(format!(
+                "Avatar is {}x{} pixels; both sides must be at most {}",
+                width, height, MAX_DIMENSION
+            )));
+        }
+        Ok(())
+    }
+
+    /// Validates and stores the upload, then leaves cropping and resizing to the
+    /// media queue. Returns the `ProcessAvatar` job id.
+    pub async fn accept_upload(
+        db_pool: &SqlitePool,
+        storage: &dyn ObjectStorage,
+        job_queue_service: &job_queue_service::JobQueueService,
+        user_id: Uuid,
+        content_type: Option<&str>,
+        bytes: Vec<u8>,
+    ) -> Result<Uuid, AppError> {
+        validate(content_type, &bytes)?;
+        let exists: Option<i64> = sqlx::query_scalar("SELECT 1 FROM users WHERE id = ? AND erased_at IS NULL")
+            .bind(user_id)
+            .fetch_optional(db_pool)
+            .await?;
+        if exists.is_none() {
+            return Err(AppError::UserNotFound(user_id));
+        }
+        let version = Uuid::new_v4();
+        let key = original_key(user_id, version);
+        storage.put(&key, bytes).await.map_err(|e| storage_error(&key, e))?;
+        job_queue_service
+            .schedule_task(TaskPayload::ProcessAvatar { user_id, version })
+            .await
+    }
+
+    /// The stored PNG for `size` of the user's current avatar.
+    pub async fn variant(db_pool: &SqlitePool, storage: &dyn ObjectStorage, user_id: Uuid, size: u32) -> Result<Vec<u8>, AppError> {
+        if !SIZES.contains(&size) {
+            return Err(AppError::AvatarNotFound(user_id));
+        }
+        let version: Option<Option<Uuid>> = sqlx::query_scalar("SELECT avatar_version FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(db_pool)
+            .await?;
+        let version = version.ok_or(AppError::UserNotFound(user_id))?.ok_or(AppError::AvatarNotFound(user_id))?;
+        let key = variant_key(user_id, version, size);
+        storage.get(&key).await.map_err(|e| storage_error(&key, e))?.ok_or(AppError::AvatarNotFound(user_id))
+    }
+
+    fn render_variants(original: &[u8]) -> Result<Vec<(u32, Vec<u8>)>, image::ImageError> {
+        let image = image::load_from_memory(original)?;
+        let side = image.width().min(image.height());
+        // Centre crop first so non-square uploads are not stretched
+        let square = image.crop_imm((image.width() - side) / 2, (image.height() - side) / 2, side, side);
+        SIZES
+            .iter()
+            .map(|&size| {
+                let mut png = Vec::new();
+                square
+                    .resize_exact(size, size, FilterType::Lanczos3)
+                    .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+                Ok((size, png))
+            })
+            .collect()
+    }
+
+    async fn delete_all(storage: &dyn ObjectStorage, keys: Vec<String>) -> Result<(), TaskError> {
+        for key in keys {
+            storage.delete(&key).await?;
+        }
+        Ok(())
+    }
+
+    /// Body of the `ProcessAvatar` task. Writes every variant before switching the
+    /// profile over, then deletes the version it replaced.
+    pub async fn process(db_pool: &SqlitePool, user_id: Uuid, version: Uuid) -> Result<TaskOutput, TaskError> {
+        let storage = object_storage::from_env();
+        let Some(original) = storage.get(&original_key(user_id, version)).await? else {
+            return Err(TaskError::Permanent(format!("Avatar upload {} no longer exists", version)));
+        };
+        // Decoding and resampling are CPU-bound
+        let variants = tokio::task::spawn_blocking(move || render_variants(&original))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| TaskError::Validation(format!("Could not process avatar: {}", e)))?;
+        for (size, png) in variants {
+            storage.put(&variant_key(user_id, version, size), png).await?;
+        }
+
+        let avatar_url = format!("/users/{}/avatar/{}?v={}", user_id, PROFILE_SIZE, version);
+        let mut tx = db_pool.begin().await.map_err(|e| e.to_string())?;
+        let previous: Option<Option<Uuid>> = sqlx::query_scalar("SELECT avatar_version FROM users WHERE id = ? AND erased_at IS NULL")
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        let Some(previous) = previous else {
+            // Erased while the job waited; nothing may keep the new files
+            delete_all(storage.as_ref(), object_keys(user_id, version)).await?;
+            return Err(TaskError::Permanent(format!("User {} no longer exists", user_id)));
+        };
+        sqlx::query("UPDATE users SET avatar_version = ?, avatar_url = ? WHERE id = ?")
+            .bind(version)
+            .bind(&avatar_url)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+
+        storage.delete(&original_key(user_id, version)).await?;
+        if let Some(previous) = previous.filter(|previous| *previous != version) {
+            delete_all(storage.as_ref(), object_keys(user_id, previous)).await?;
+        }
+        info!(?user_id, "Avatar {} processed", version);
+        Ok(serde_json::json!({ "avatar_url": avatar_url }))
+    }
+}
+
+// --- Webhook Service ---
+mod webhooks {
+    use super::*;
+    use clock::Clock;
+    use hmac::{Hmac, Mac};
+    use job_queue_service::JobQueueService;
+    use rand::{distributions::Alphanumeric, Rng};
+    use sha2::Sha256;
+
+    pub const USER_CREATED: &str = "user.created";
+    pub const POST_PUBLISHED: &str = "post.published";
+    const SUPPORTED_EVENTS: [&str; 2] = [USER_CREATED, POST_PUBLISHED];
+
+    const SECRET_LENGTH: usize = 32;
+
+    #[derive(Debug, Clone, Serialize, FromRow)]
+    pub struct WebhookSubscription {
+        pub id: Uuid,
+        pub url: String,
+        #[serde(skip_serializing)]
+        pub secret: String,
+        #[sqlx(json)]
+        pub event_types: Vec<String>,
+        pub is_active: bool,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, FromRow)]
+    pub struct DeliveryAttempt {
+        pub id: Uuid,
+        pub delivery_id: Uuid,
+        pub event_type: String,
+        pub attempt: i32,
+        pub status_code: Option<i32>,
+        pub error_message: Option<String>,
+        pub duration_ms: i64,
+        pub attempted_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct CreateSubscription {
+        pub url: String,
+        pub secret: Option<String>,
+        pub event_types: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct UpdateSubscription {
+        pub url: Option<String>,
+        pub event_types: Option<Vec<String>>,
+        pub is_active: Option<bool>,
+    }
+
+    #[derive(FromRow)]
+    struct PendingDelivery {
+        subscription_id: Uuid,
+        event_type: String,
+        #[sqlx(json)]
+        payload: serde_json::Value,
+        url: String,
+        secret: String,
+    }
+
+    /// Same rules as remote image sources: http(s) on a standard port, resolving only
+    /// to public addresses. Checked again before every delivery.
+    async fn validate_url(url: &str) -> Result<(), AppError> {
+        remote_image::resolve_source(url).await.map(|_| ()).map_err(|e| AppError::Validation(e.to_string()))
+    }
+
+    fn validate_event_types(event_types: &[String]) -> Result<(), AppError> {
+        if event_types.is_empty() {
+            return Err(AppError::Validation("event_types must not be empty".to_string()));
+        }
+        match event_types.iter().find(|e| !SUPPORTED_EVENTS.contains(&e.as_str())) {
+            Some(unknown) => Err(AppError::Validation(format!("Unsupported event type '{}'", unknown))),
+            None => Ok(()),
+        }
+    }
+
+    /// Hex-encoded HMAC-SHA256 over `"{timestamp}.{body}"`, so receivers can
+    /// reject replayed requests by checking the timestamp header.
+    pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    #[derive(Clone)]
+    pub struct WebhookService {
+        db_pool: SqlitePool,
+        job_queue_service: JobQueueService,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl WebhookService {
+        pub fn new(db_pool: SqlitePool, job_queue_service: JobQueueService, clock: Arc<dyn Clock>) -> Self {
+            Self { db_pool, job_queue_service, clock }
+        }
+
+        pub async fn create_subscription(
+            &self,
+            input: CreateSubscription,
+        ) -> Result<(WebhookSubscription, String), AppError> {
+            validate_url(&input.url).await?;
+            validate_event_types(&input.event_types)?;
+            let secret = input.secret.unwrap_or_else(|| {
+                rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(SECRET_LENGTH)
+                    .map(char::from)
+                    .collect()
+            });
+
+            let subscription = WebhookSubscription {
+                id: Uuid::new_v4(),
+                url: input.url,
+                secret: secret.clone(),
+                event_types: input.event_types,
+                is_active: true,
+                created_at: self.clock.now(),
+            };
+            sqlx::query(
+                "INSERT INTO webhook_subscriptions (id, url, secret, event_types, is_active, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(subscription.id)
+            .bind(&subscription.url)
+            .bind(&subscription.secret)
+            .bind(serde_json::to_value(&subscription.event_types).unwrap())
+            .bind(subscription.is_active)
+            .bind(subscription.created_at)
+            .execute(&self.db_pool)
+            .await?;
+            // The secret is only ever returned on creation
+            Ok((subscription, secret))
+        }
+
+        pub async fn list_subscriptions(&self) -> Result<Vec<WebhookSubscription>, AppError> {
+            Ok(sqlx::query_as("SELECT * FROM webhook_subscriptions ORDER BY created_at")
+                .fetch_all(&self.db_pool)
+                .await?)
+        }
+
+        pub async fn get_subscription(&self, id: Uuid) -> Result<WebhookSubscription, AppError> {
+            sqlx::query_as("SELECT * FROM webhook_subscriptions WHERE id = ?")
+                .bind(id)
+                .fetch_optional(&self.db_pool)
+                .await?
+                .ok_or(AppError::WebhookNotFound(id))
+        }
+
+        pub async fn update_subscription(
+            &self,
+            id: Uuid,
+            input: UpdateSubscription,
+        ) -> Result<WebhookSubscription, AppError> {
+            let mut subscription = self.get_subscription(id).await?;
+            if let Some(url) = input.url {
+                validate_url(&url).await?;
+                subscription.url = url;
+            }
+            if let Some(event_types) = input.event_types {
+                validate_event_types(&event_types)?;
+                subscription.event_types = event_types;
+            }
+            if let Some(is_active) = input.is_active {
+                subscription.is_active = is_active;
+            }
+
+            sqlx::query("UPDATE webhook_subscriptions SET url = ?, event_types = ?, is_active = ? WHERE id = ?")
+                .bind(&subscription.url)
+                .bind(serde_json::to_value(&subscription.event_types).unwrap())
+                .bind(subscription.is_active)
+                .bind(id)
+                .execute(&self.db_pool)
+                .await?;
+            Ok(subscription)
+        }
+
+        pub async fn delete_subscription(&self, id: Uuid) -> Result<(), AppError> {
+            let result = sqlx::query("DELETE FROM webhook_subscriptions WHERE id = ?")
+                .bind(id)
+                .execute(&self.db_pool)
+                .await?;
+            if result.rows_affected() == 0 {
+                return Err(AppError::WebhookNotFound(id));
+            }
+            Ok(())
+        }
+
+        pub async fn list_attempts(&self, subscription_id: Uuid) -> Result<Vec<DeliveryAttempt>, AppError> {
+            // Distinguish "no attempts yet" from "no such subscription"
+            self.get_subscription(subscription_id).await?;
+            Ok(sqlx::query_as(
+                "SELECT a.id, a.delivery_id, d.event_type, a.attempt, a.status_code, a.error_message, a.duration_ms, a.attempted_at
+                 FROM webhook_delivery_attempts a
+                 JOIN webhook_deliveries d ON d.id = a.delivery_id
+                 WHERE d.subscription_id = ?
+                 ORDER BY a.attempted_at DESC",
+            )
+            .bind(subscription_id)
+            .fetch_all(&self.db_pool)
+            .await?)
+        }
+
+        /// Records one delivery per matching active subscription and queues it.
+        /// Retries and backoff are handled by the job queue.
+        pub async fn dispatch(&self, event_type: &str, data: serde_json::Value) -> Result<usize, AppError> {
+            let subscriptions: Vec<WebhookSubscription> =
+                sqlx::query_as("SELECT * FROM webhook_subscriptions WHERE is_active = TRUE")
+                    .fetch_all(&self.db_pool)
+                    .await?;
+
+            let mut queued = 0;
+            for subscription in subscriptions.iter().filter(|s| s.event_types.iter().any(|e| e == event_type)) {
+                let delivery_id = Uuid::new_v4();
+                let payload = serde_json::json!({
+                    "id": delivery_id,
+                    "event": event_type,
+                    "created_at": self.clock.now(),
+                    "data": data,
+                });
+                sqlx::query(
+                    "INSERT INTO webhook_deliveries (id, subscription_id, event_type, payload, status) VALUES (?, ?, ?, ?, 'pending')",
+                )
+                .bind(delivery_id)
+                .bind(subscription.id)
+                .bind(event_type)
+                .bind(&payload)
+                .execute(&self.db_pool)
+                .await?;
+                self.job_queue_service
+                    .schedule_task(tasks::TaskPayload::DeliverWebhook { delivery_id })
+                    .await?;
+                queued += 1;
+            }
+            Ok(queued)
+        }
+    }
+
+    /// Performs a single delivery attempt and records it. A retryable `Err` lets the
+    /// worker reschedule the job per `RetryPolicy::WEBHOOK`. The URL is resolved and
+    /// checked again and the connection pinned to that answer, so a subscription whose
+    /// DNS now points inward is failed rather than delivered.
+    pub async fn deliver(
+        db_pool: &SqlitePool,
+        http: &http_client::HttpClientService,
+        clock: &dyn Clock,
+        delivery_id: Uuid,
+    ) -> Result<(), tasks::TaskError> {
+        let delivery: Option<PendingDelivery> = sqlx::query_as(
+            "SELECT d.subscription_id, d.event_type, d.payload, s.url, s.secret
+             FROM webhook_deliveries d
+             JOIN webhook_subscriptions s ON s.id = d.subscription_id
+             WHERE d.id = ?",
+        )
+        .bind(delivery_id)
+        .fetch_optional(db_pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let Some(delivery) = delivery else {
+            // Subscription was deleted in the meantime; nothing left to do
+            info!(?delivery_id, "Skipping webhook delivery for removed subscription");
+            return Ok(());
+        };
+
+        let previous_attempts: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM webhook_delivery_attempts WHERE delivery_id = ?")
+                .bind(delivery_id)
+                .fetch_one(db_pool)
+                .await
+                .map_err(|e| e.to_string())?;
+        let attempt = previous_attempts as i32 + 1;
+
+        let body = serde_json::to_vec(&delivery.payload).map_err(|e| e.to_string())?;
+        let timestamp = clock.now().timestamp();
+        let signature = sign(&delivery.secret, timestamp, &body);
+
+        let started = std::time::Instant::now();
+        let (status_code, outcome) = match remote_image::resolve_source(&delivery.url).await {
+            Err(remote_image::SourceError::Rejected(msg)) => (None, Err(tasks::TaskError::Permanent(msg))),
+            Err(e) => (None, Err(tasks::TaskError::Retryable(e.to_string()))),
+            Ok(source) => {
+                let request = http_client::HttpRequest::post(source.url.as_str(), body)
+                    .header("Content-Type", "application/json")
+                    .header("X-Webhook-Event", delivery.event_type.as_str())
+                    .header("X-Webhook-Delivery", delivery_id.to_string())
+                    .header("X-Webhook-Timestamp", timestamp.to_string())
+                    .header("X-Webhook-Signature", format!("sha256={}", signature))
+                    .pinned_to(source.addr);
+                match http.send(http_client::Destination::WEBHOOK, request).await {
+                    Ok(resp) if resp.is_success() => (Some(resp.status as i32), Ok(())),
+                    Ok(resp) => (
+                        Some(resp.status as i32),
+                        Err(tasks::TaskError::Retryable(format!("Endpoint responded with {}", resp.status))),
+                    ),
+                    Err(e) => (None, Err(tasks::TaskError::Retryable(e.to_string()))),
+                }
+            }
+        };
+        let duration_ms = started.elapsed().as_millis() as i64;
+
+        sqlx::query(
+            "INSERT INTO webhook_delivery_attempts (id, delivery_id, attempt, status_code, error_message, duration_ms, attempted_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(delivery_id)
+        .bind(attempt)
+        .bind(status_code)
+        .bind(outcome.as_ref().err().map(tasks::TaskError::message))
+        .bind(duration_ms)
+        .bind(clock.now())
+        .execute(db_pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let status = match &outcome {
+            Ok(()) => "delivered",
+            Err(tasks::TaskError::Retryable(_)) if attempt < tasks::RetryPolicy::WEBHOOK.max_attempts => "retrying",
+            Err(_) => "failed",
+        };
+        sqlx::query("UPDATE webhook_deliveries SET status = ?, attempts = ? WHERE id = ?")
+            .bind(status)
+            .bind(attempt)
+            .bind(delivery_id)
+            .execute(db_pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        info!(?delivery_id, subscription_id = ?delivery.subscription_id, attempt, status, "Webhook delivery attempted");
+        outcome
+    }
+}
+
+// --- Job Events ---
+pub(crate) mod events {
+    use super::*;
+    use clock::Clock;
+    use job_queue_service::{JobRecord, JobStatus};
+    use tokio::sync::broadcast;
+
+    /// Capacity of the in-process event bus; slow subscribers that fall further
+    /// behind than this resynchronise from the database.
+    pub const CHANNEL_CAPACITY: usize = 1024;
+
+    #[derive(Debug, Clone, Serialize)]
+    pub struct JobEvent {
+        pub job_id: Uuid,
+        pub status: JobStatus,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub progress: Option<u8>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub message: Option<String>,
+        pub at: DateTime<Utc>,
+    }
+
+    impl JobEvent {
+        pub fn status(job_id: Uuid, status: JobStatus, message: Option<String>, at: DateTime<Utc>) -> Self {
+            Self { job_id, status, progress: None, message, at }
+        }
+
+        pub fn from_record(job: &JobRecord, at: DateTime<Utc>) -> Self {
+            Self::status(job.id, job.status, job.error_message.clone(), at)
+        }
+
+        pub fn is_terminal(&self) -> bool {
+            self.status.is_terminal()
+        }
+    }
+
+    pub type JobEventSender = broadcast::Sender<JobEvent>;
+
+    pub fn channel() -> JobEventSender {
+        broadcast::channel(CHANNEL_CAPACITY).0
+    }
+
+    /// Handed to running tasks so they can publish intermediate progress.
+    #[derive(Clone)]
+    pub struct ProgressReporter {
+        job_id: Uuid,
+        sender: JobEventSender,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl ProgressReporter {
+        pub fn new(job_id: Uuid, sender: JobEventSender, clock: Arc<dyn Clock>) -> Self {
+            Self { job_id, sender, clock }
+        }
+
+        pub fn report(&self, percent: u8, message: &str) {
+            let event = JobEvent {
+                job_id: self.job_id,
+                status: JobStatus::Running,
+                progress: Some(percent.min(100)),
+                message: Some(message.to_string()),
+                at: self.clock.now(),
+            };
+            // No subscribers is not an error
+            let _ = self.sender.send(event);
+        }
+    }
+}
+
+// --- Workflows ---
+mod workflows {
+    use super::*;
+    use clock::Clock;
+    use queue_driver::QueuedJob;
+    use sqlx::{Sqlite, Transaction};
+    use tasks::{TaskOutput, TaskPayload};
+
+    /// One step of a workflow. `build` turns the run's context (the start input plus
+    /// every earlier step's output) into the task to enqueue.
+    pub struct Step {
+        pub name: &'static str,
+        pub build: fn(&serde_json::Value) -> Result<TaskPayload, String>,
+    }
+
+    pub struct Workflow {
+        pub name: &'static str,
+        pub steps: &'static [Step],
+    }
+
+    fn field<T: serde::de::DeserializeOwned>(context: &serde_json::Value, key: &str) -> Result<T, String> {
+        let value = context.get(key).ok_or_else(|| format!("missing '{}'", key))?;
+        serde_json::from_value(value.clone()).map_err(|e| format!("invalid '{}': {}", key, e))
+    }
+
+    fn fetch_image(context: &serde_json::Value) -> Result<TaskPayload, String> {
+        Ok(TaskPayload::FetchRemoteImage { post_id: field(context, "post_id")?, source_url: field(context, "source_url")? })
+    }
+
+    fn process_image(context: &serde_json::Value) -> Result<TaskPayload, String> {
+        Ok(TaskPayload::ProcessImage { post_id: field(context, "post_id")?, image_url: field(context, "image_url")? })
+    }
+
+    fn generate_thumbnails(context: &serde_json::Value) -> Result<TaskPayload, String> {
+        Ok(TaskPayload::GenerateThumbnails {
+            post_id: field(context, "post_id")?,
+            image_url: field(context, "processed_image_url")?,
+        })
+    }
+
+    fn notify_author(context: &serde_json::Value) -> Result<TaskPayload, String> {
+        Ok(TaskPayload::NotifyAuthor { post_id: field(context, "post_id")?, thumbnail_urls: field(context, "thumbnail_urls")? })
+    }
+
+    /// Input: `{ "post_id": ..., "image_url": ... }`.
+    pub const POST_IMAGE_PIPELINE: Workflow = Workflow {
+        name: "post_image_pipeline",
+        steps: &[
+            Step { name: "process_image", build: process_image },
+            Step { name: "generate_thumbnails", build: generate_thumbnails },
+            Step { name: "notify_author", build: notify_author },
+        ],
+    };
+
+    /// Input: `{ "post_id": ..., "source_url": ... }`. Downloads the image, then runs
+    /// the same steps as `POST_IMAGE_PIPELINE`.
+    pub const POST_IMAGE_FROM_URL: Workflow = Workflow {
+        name: "post_image_from_url",
+        steps: &[
+            Step { name: "fetch_image", build: fetch_image },
+            Step { name: "process_image", build: process_image },
+            Step { name: "generate_thumbnails", build: generate_thumbnails },
+            Step { name: "notify_author", build: notify_author },
+        ],
+    };
+
+    const WORKFLOWS: &[&Workflow] = &[&POST_IMAGE_PIPELINE, &POST_IMAGE_FROM_URL];
+
+    pub fn find(name: &str) -> Option<&'static Workflow> {
+        WORKFLOWS.iter().copied().find(|workflow| workflow.name == name)
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum StepStatus {
+        Pending,
+        Running,
+        Completed,
+        Failed,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct StepState {
+        pub name: String,
+        pub status: StepStatus,
+        pub job_id: Option<Uuid>,
+        pub error_message: Option<String>,
+        pub completed_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, FromRow)]
+    pub struct WorkflowRun {
+        pub id: Uuid,
+        pub workflow: String,
+        /// `running`, `completed` or `failed`
+        pub status: String,
+        pub current_step: i64,
+        #[sqlx(json)]
+        pub context: serde_json::Value,
+        #[sqlx(json)]
+        pub steps: Vec<StepState>,
+        pub error_message: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    async fn load(tx: &mut Transaction<'_, Sqlite>, run_id: Uuid) -> Result<Option<WorkflowRun>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM workflow_runs WHERE id = ?")
+            .bind(run_id)
+            .fetch_optional(&mut **tx)
+            .await
+    }
+
+    async fn save(tx: &mut Transaction<'_, Sqlite>, run: &WorkflowRun, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE workflow_runs SET status = ?, current_step = ?, context = ?, steps = ?, error_message = ?, updated_at = ? WHERE id = ?",
+        )
+        .bind(&run.status)
+        .bind(run.current_step)
+        .bind(&run.context)
+        .bind(sqlx::types::Json(&run.steps))
+        .bind(&run.error_message)
+        .bind(now)
+        .bind(run.id)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// Builds step `index` from the context and enqueues it, or records why it couldn't be built.
+    /// The returned job must be pushed to the queue driver once the transaction commits.
+    async fn enqueue_step(
+        tx: &mut Transaction<'_, Sqlite>,
+        workflow: &Workflow,
+        run: &mut WorkflowRun,
+        index: usize,
+        now: DateTime<Utc>,
+    ) -> Result<Option<QueuedJob>, sqlx::Error> {
+        run.current_step = index as i64;
+        let step = &mut run.steps[index];
+        match (workflow.steps[index].build)(&run.context) {
+            Ok(payload) => {
+                let job = job_queue_service::insert_job(&mut **tx, &payload, payload.queue(), Some((run.id, index)), now).await?;
+                step.job_id = Some(job.id);
+                step.status = StepStatus::Pending;
+                step.error_message = None;
+                run.status = "running".to_string();
+                run.error_message = None;
+                Ok(Some(job))
+            }
+            Err(e) => {
+                let message = format!("step '{}' could not start: {}", step.name, e);
+                step.status = StepStatus::Failed;
+                step.error_message = Some(message.clone());
+                run.status = "failed".to_string();
+                run.error_message = Some(message);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Step lookups for jobs whose run was deleted or has moved on are ignored.
+    async fn load_at_step(tx: &mut Transaction<'_, Sqlite>, run_id: Uuid, index: usize) -> Result<Option<WorkflowRun>, sqlx::Error> {
+        let run = load(tx, run_id).await?;
+        Ok(run.filter(|run| run.status == "running" && run.current_step == index as i64 && index < run.steps.len()))
+    }
+
+    pub async fn mark_running(
+        tx: &mut Transaction<'_, Sqlite>,
+        run_id: Uuid,
+        index: usize,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let Some(mut run) = load_at_step(tx, run_id, index).await? else { return Ok(()) };
+        run.steps[index].status = StepStatus::Running;
+        save(tx, &run, now).await
+    }
+
+    /// Records a finished step and enqueues the next one in the same transaction as
+    /// the job's own completion, so a crash can't lose the hand-off.
+    pub async fn advance(
+        tx: &mut Transaction<'_, Sqlite>,
+        run_id: Uuid,
+        index: usize,
+        output: TaskOutput,
+        now: DateTime<Utc>,
+    ) -> Result<Option<QueuedJob>, sqlx::Error> {
+        let Some(mut run) = load_at_step(tx, run_id, index).await? else { return Ok(None) };
+        let Some(workflow) = find(&run.workflow) else {
+            tracing::error!(?run_id, "Unknown workflow '{}'", run.workflow);
+            return Ok(None);
+        };
+        if let (Some(context), serde_json::Value::Object(output)) = (run.context.as_object_mut(), output) {
+            context.extend(output);
+        }
+        run.steps[index].status = StepStatus::Completed;
+        run.steps[index].completed_at = Some(now);
+        let next = if index + 1 < run.steps.len() {
+            enqueue_step(tx, workflow, &mut run, index + 1, now).await?
+        } else {
+            run.status = "completed".to_string();
+            None
+        };
+        save(tx, &run, now).await?;
+        Ok(next)
+    }
+
+    pub async fn fail(
+        tx: &mut Transaction<'_, Sqlite>,
+        run_id: Uuid,
+        index: usize,
+        error: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let Some(mut run) = load_at_step(tx, run_id, index).await? else { return Ok(()) };
+        run.steps[index].status = StepStatus::Failed;
+        run.steps[index].error_message = Some(error.to_string());
+        run.status = "failed".to_string();
+        run.error_message = Some(format!("step '{}' failed: {}", run.steps[index].name, error));
+        save(tx, &run, now).await
+    }
+
+    #[derive(Clone)]
+    pub struct WorkflowService {
+        db_pool: SqlitePool,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl WorkflowService {
+        pub fn new(db_pool: SqlitePool, clock: Arc<dyn Clock>) -> Self {
+            Self { db_pool, clock }
+        }
+
+        pub async fn start(&self, name: &str, input: serde_json::Value) -> Result<WorkflowRun, AppError> {
+            let workflow = find(name).ok_or_else(|| AppError::WorkflowNotFound(name.to_string()))?;
+            if !input.is_object() {
+                return Err(AppError::Validation("Workflow input must be a JSON object".to_string()));
+            }
+            // Reject bad input up front rather than creating a run that fails immediately
+            (workflow.steps[0].build)(&input).map_err(|e| AppError::Validation(format!("Invalid workflow input: {}", e)))?;
+
+            let now = self.clock.now();
+            let mut run = WorkflowRun {
+                id: Uuid::new_v4(),
+                workflow: workflow.name.to_string(),
+                status: "running".to_string(),
+                current_step: 0,
+                context: input,
+                steps: workflow
+                    .steps
+                    .iter()
+                    .map(|step| StepState {
+                        name: step.name.to_string(),
+                        status: StepStatus::Pending,
+                        job_id: None,
+                        error_message: None,
+                        completed_at: None,
+                    })
+                    .collect(),
+                error_message: None,
+                created_at: now,
+                updated_at: now,
+            };
+            let mut tx = self.db_pool.begin().await?;
+            sqlx::query(
+                "INSERT INTO workflow_runs (id, workflow, status, current_step, context, steps, created_at, updated_at) VALUES (?, ?, ?, 0, ?, ?, ?, ?)",
+            )
+            .bind(run.id)
+            .bind(&run.workflow)
+            .bind(&run.status)
+            .bind(&run.context)
+            .bind(sqlx::types::Json(&run.steps))
+            .bind(now)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+            let job = enqueue_step(&mut tx, workflow, &mut run, 0, now).await?;
+            save(&mut tx, &run, now).await?;
+            tx.commit().await?;
+            if let Some(job) = job {
+                queue_driver::current().push(job).await?;
+            }
+            Ok(run)
+        }
+
+        pub async fn get(&self, run_id: Uuid) -> Result<WorkflowRun, AppError> {
+            sqlx::query_as("SELECT * FROM workflow_runs WHERE id = ?")
+                .bind(run_id)
+                .fetch_optional(&self.db_pool)
+                .await?
+                .ok_or(AppError::WorkflowRunNotFound(run_id))
+        }
+
+        /// Re-enqueues the step that failed, with the context as it stood before it ran.
+        pub async fn resume(&self, run_id: Uuid) -> Result<WorkflowRun, AppError> {
+            let mut tx = self.db_pool.begin().await?;
+            let mut run = load(&mut tx, run_id).await?.ok_or(AppError::WorkflowRunNotFound(run_id))?;
+            if run.status != "failed" {
+                return Err(AppError::Conflict(format!("Workflow run is {}, only failed runs can be resumed", run.status)));
+            }
+            let workflow = find(&run.workflow).ok_or_else(|| AppError::WorkflowNotFound(run.workflow.clone()))?;
+            let index = run.current_step as usize;
+            let now = self.clock.now();
+            let job = enqueue_step(&mut tx, workflow, &mut run, index, now).await?;
+            if run.status == "failed" {
+                return Err(AppError::Validation(run.error_message.unwrap_or_default()));
+            }
+            save(&mut tx, &run, now).await?;
+            tx.commit().await?;
+            if let Some(job) = job {
+                queue_driver::current().push(job).await?;
+            }
+            Ok(run)
+        }
+    }
+}
+
+// --- Background Worker ---
+pub(crate) mod worker {
+    use super::*;
+    use clock::Clock;
+    use events::{JobEvent, JobEventSender, ProgressReporter};
+    use job_queue_service::{InvalidTransition, JobRecord, JobStatus};
+    use queue_driver::{Delivery, DriverError, QueueDriver, QueuedJob};
+
+    #[derive(thiserror::Error, Debug)]
+    pub enum WorkerError {
+        #[error(transparent)]
+        Queue(#[from] DriverError),
+        #[error("Database error: {0}")]
+        Sqlx(#[from] sqlx::Error),
+        #[error(transparent)]
+        Transition(#[from] InvalidTransition),
+    }
+
+    /// Cancellation tokens for the jobs this process is currently executing.
+    #[derive(Clone, Default)]
+    pub struct RunningJobs(Arc<std::sync::Mutex<std::collections::HashMap<Uuid, CancellationToken>>>);
+
+    impl RunningJobs {
+        fn register(&self, job_id: Uuid) -> CancellationToken {
+            let token = CancellationToken::new();
+            self.0.lock().unwrap().insert(job_id, token.clone());
+            token
+        }
+
+        fn finish(&self, job_id: Uuid) {
+            self.0.lock().unwrap().remove(&job_id);
+        }
+
+        /// Signals a running job; `false` if it isn't running here.
+        pub fn cancel(&self, job_id: Uuid) -> bool {
+            match self.0.lock().unwrap().get(&job_id) {
+                Some(token) => {
+                    token.cancel();
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    /// How often a worker touches `heartbeat_at` while a job runs.
+    const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+    /// A running job whose heartbeat is older than this is presumed orphaned.
+    const STALE_AFTER: Duration = Duration::from_secs(60);
+    const REAPER_INTERVAL: Duration = Duration::from_secs(30);
+    const LOST_WORKER_ERROR: &str = "Worker stopped heartbeating";
+
+    const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+    /// What every worker loop shares.
+    #[derive(Clone)]
+    pub struct WorkerContext {
+        pub db_pool: SqlitePool,
+        pub job_events: JobEventSender,
+        pub running_jobs: RunningJobs,
+        pub http: http_client::HttpClientService,
+        pub driver: Arc<dyn QueueDriver>,
+        pub clock: Arc<dyn Clock>,
+    }
+
+    /// A set of workers draining a single queue.
+    pub struct WorkerPool {
+        queue: tasks::Queue,
+        concurrency: usize,
+        poll_interval: Duration,
+    }
+
+    impl WorkerPool {
+        pub fn new(queue: tasks::Queue) -> Self {
+            Self { queue, concurrency: 1, poll_interval: DEFAULT_POLL_INTERVAL }
+        }
+
+        /// Reads `<QUEUE>_WORKERS` (e.g. `MEDIA_WORKERS=1`), defaulting to two workers,
+        /// and `WORKER_POLL_MS` for how long an idle worker waits before looking again.
+        pub fn from_env(queue: tasks::Queue) -> Self {
+            let concurrency = std::env::var(format!("{}_WORKERS", queue.as_str().to_uppercase()))
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2);
+            let poll_interval = std::env::var("WORKER_POLL_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map_or(DEFAULT_POLL_INTERVAL, Duration::from_millis);
+            Self::new(queue).concurrency(concurrency).poll_interval(poll_interval)
+        }
+
+        pub fn concurrency(mut self, concurrency: usize) -> Self {
+            self.concurrency = concurrency;
+            self
+        }
+
+        pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+            self.poll_interval = poll_interval;
+            self
+        }
+
+        pub fn start(self, ctx: WorkerContext) {
+            let (queue, poll_interval) = (self.queue, self.poll_interval);
+            for n in 0..self.concurrency {
+                let ctx = ctx.clone();
+                tokio::spawn(async move {
+                    let worker_id = format!("{}-{}-{}-{}", queue.as_str(), n, std::process::id(), Uuid::new_v4().simple());
+                    info!("Background worker {} started.", worker_id);
+                    loop {
+                        match fetch_and_process_job(&ctx, queue, &worker_id).await {
+                            Ok(Some(job_id)) => info!("Successfully processed job {}", job_id),
+                            Ok(None) => sleep(poll_interval).await, // No jobs, wait a bit
+                            Err(e) => tracing::error!("Error in worker loop: {:?}", e),
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    /// Returns `running` jobs whose worker went quiet to the queue. Each reclaim
+    /// counts as an attempt, so a job that keeps crashing its worker still ends up `failed`.
+    pub fn spawn_reaper(ctx: WorkerContext) {
+        tokio::spawn(async move {
+            loop {
+                sleep(REAPER_INTERVAL).await;
+                match reap_stale_jobs(&ctx).await {
+                    Ok(0) => {}
+                    Ok(count) => tracing::warn!("Reclaimed {} jobs with a stale heartbeat", count),
+                    Err(e) => tracing::error!("Stale job reaper failed: {:?}", e),
+                }
+            }
+        });
+    }
+
+    async fn reap_stale_jobs(ctx: &WorkerContext) -> Result<u64, WorkerError> {
+        let db_pool = &ctx.db_pool;
+        let cutoff = ctx.clock.now() - chrono::Duration::seconds(STALE_AFTER.as_secs() as i64);
+        let stale: Vec<JobRecord> = sqlx::query_as(
+            "SELECT * FROM jobs WHERE status = 'running' AND (heartbeat_at IS NULL OR heartbeat_at < ?)",
+        )
+        .bind(cutoff)
+        .fetch_all(db_pool)
+        .await?;
+
+        let mut reclaimed = 0;
+        for job in stale {
+            let new_attempts = job.attempts + 1;
+            let retry = new_attempts < job.payload.retry_policy().max_attempts;
+            let status = if retry { JobStatus::Pending } else { JobStatus::Failed };
+            JobStatus::Running.ensure_transition(status)?;
+
+            let mut tx = db_pool.begin().await?;
+            // Guarded on the lock holder and heartbeat so a worker that just recovered keeps its job
+            let run_at = ctx.clock.now();
+            let updated = sqlx::query(
+                "UPDATE jobs SET status = ?, attempts = ?, run_at = ?, error_message = ?, locked_by = NULL, heartbeat_at = NULL, \
+                 finished_at = ? WHERE id = ? AND status = 'running' AND locked_by IS ? AND heartbeat_at IS ?",
+            )
+            .bind(status.as_str())
+            .bind(new_attempts)
+            .bind(run_at)
+            .bind(LOST_WORKER_ERROR)
+            .bind(status.is_terminal().then_some(run_at))
+            .bind(job.id)
+            .bind(&job.locked_by)
+            .bind(job.heartbeat_at)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if updated == 0 {
+                continue;
+            }
+            if !retry {
+                if let Some((run_id, step)) = job.workflow_step() {
+                    workflows::fail(&mut tx, run_id, step, LOST_WORKER_ERROR, run_at).await?;
+                }
+            }
+            tx.commit().await?;
+            if retry {
+                ctx.driver.push(QueuedJob { id: job.id, queue: job.queue, run_at }).await?;
+            }
+            tracing::warn!(
+                "Job {} held by {:?} had a stale heartbeat; marked {}",
+                job.id,
+                job.locked_by,
+                status
+            );
+            let _ = ctx.job_events.send(JobEvent::status(job.id, status, Some(LOST_WORKER_ERROR.to_string()), run_at));
+            reclaimed += 1;
+        }
+        Ok(reclaimed)
+    }
+
+    async fn heartbeat(db_pool: SqlitePool, clock: Arc<dyn Clock>, job_id: Uuid, worker_id: String) {
+        loop {
+            sleep(HEARTBEAT_INTERVAL).await;
+            let result = sqlx::query("UPDATE jobs SET heartbeat_at = ? WHERE id = ? AND locked_by = ? AND status = 'running'")
+                .bind(clock.now())
+                .bind(job_id)
+                .bind(&worker_id)
+                .execute(&db_pool)
+                .await;
+            match result {
+                Ok(r) if r.rows_affected() == 0 => {
+                    tracing::warn!("Job {} was reclaimed from {}; stopping heartbeat", job_id, worker_id);
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Heartbeat for job {} failed: {:?}", job_id, e),
+            }
+        }
+    }
+
+    /// Releases the job's lock and moves it out of `running` for this run. Returns
+    /// `false` when the reaper already took the job from this worker.
+    async fn release(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        job: &JobRecord,
+        worker_id: &str,
+        status: JobStatus,
+        error: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<bool, WorkerError> {
+        JobStatus::Running.ensure_transition(status)?;
+        let updated = sqlx::query(
+            "UPDATE jobs SET status = ?, error_message = ?, locked_by = NULL, heartbeat_at = NULL, finished_at = ? \
+             WHERE id = ? AND locked_by = ? AND status = 'running'",
+        )
+        .bind(status.as_str())
+        .bind(error)
+        .bind(status.is_terminal().then_some(now))
+        .bind(job.id)
+        .bind(worker_id)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            tracing::warn!("Job {} was reclaimed from {} before it finished; discarding result", job.id, worker_id);
+        }
+        Ok(updated > 0)
+    }
+
+    async fn fetch_and_process_job(ctx: &WorkerContext, queue: tasks::Queue, worker_id: &str) -> Result<Option<Uuid>, WorkerError> {
+        let Some((delivery, job, tx)) = claim_next(ctx, queue, worker_id).await? else { return Ok(None) };
+        let result = process_job(ctx, worker_id, job, tx).await;
+        // Acked even when processing errored; the reaper recovers a job left `running`
+        ctx.driver.ack(&delivery).await?;
+        result
+    }
+
+    /// Dequeues the next due job on `queue` and marks it running for `worker_id`. The
+    /// claim is only visible to others once the returned transaction commits, and the
+    /// delivery must be acked once the worker is done with it.
+    pub async fn claim_next<'c>(
+        ctx: &'c WorkerContext,
+        queue: tasks::Queue,
+        worker_id: &str,
+    ) -> Result<Option<(Delivery, JobRecord, sqlx::Transaction<'c, sqlx::Sqlite>)>, WorkerError> {
+        let driver = &ctx.driver;
+        loop {
+            let Some(delivery) = driver.next(queue, worker_id).await? else { return Ok(None) };
+            let now = ctx.clock.now();
+            let mut tx = ctx.db_pool.begin().await?;
+            // Claimed only while still pending and due, which makes stale or duplicate
+            // deliveries (and workers racing for the same row) harmless
+            let claimed: Option<JobRecord> = sqlx::query_as(
+                "UPDATE jobs SET status = 'running', locked_by = ?, heartbeat_at = ? \
+                 WHERE id = ? AND status = 'pending' AND run_at <= ? RETURNING *",
+            )
+            .bind(worker_id)
+            .bind(now)
+            .bind(delivery.job_id)
+            .bind(now)
+            .fetch_optional(&mut *tx)
+            .await?;
+            match claimed {
+                Some(job) => return Ok(Some((delivery, job, tx))),
+                None => {
+                    tx.commit().await?;
+                    driver.ack(&delivery).await?;
+                }
+            }
+        }
+    }
+
+    async fn process_job(
+        ctx: &WorkerContext,
+        worker_id: &str,
+        job: JobRecord,
+        mut tx: sqlx::Transaction<'_, sqlx::Sqlite>,
+    ) -> Result<Option<Uuid>, WorkerError> {
+        let WorkerContext { db_pool, job_events, running_jobs, http, driver, clock } = ctx;
+        if let Some((run_id, step)) = job.workflow_step() {
+            workflows::mark_running(&mut tx, run_id, step, clock.now()).await?;
+        }
+        // Registered before the commit so a cancel request never sees `running` without a token
+        let cancel = running_jobs.register(job.id);
+        
+        if let Err(e) = tx.commit().await {
+            running_jobs.finish(job.id);
+            return Err(e.into());
+        }
+        let _ = job_events.send(JobEvent::status(job.id, JobStatus::Running, None, clock.now()));
+
+        let progress = ProgressReporter::new(job.id, job_events.clone(), clock.clone());
+        let heartbeat = tokio::spawn(heartbeat(db_pool.clone(), clock.clone(), job.id, worker_id.to_string()));
+        let task_result = tasks::execute_task(job.payload.clone(), db_pool.clone(), http, clock, &progress, &cancel).await;
+        heartbeat.abort();
+        running_jobs.finish(job.id);
+        let now = clock.now();
+
+        match task_result {
+            Ok(output) => {
+                let mut tx = db_pool.begin().await?;
+                if !release(&mut tx, &job, worker_id, JobStatus::Completed, None, now).await? {
+                    return Ok(Some(job.id));
+                }
+                let next_step = match job.workflow_step() {
+                    Some((run_id, step)) => workflows::advance(&mut tx, run_id, step, output, now).await?,
+                    None => None,
+                };
+                tx.commit().await?;
+                if let Some(next_step) = next_step {
+                    driver.push(next_step).await?;
+                }
+                let _ = job_events.send(JobEvent::status(job.id, JobStatus::Completed, None, now));
+            }
+            Err(tasks::TaskError::Cancelled) => {
+                let mut tx = db_pool.begin().await?;
+                if !release(&mut tx, &job, worker_id, JobStatus::Cancelled, None, now).await? {
+                    return Ok(Some(job.id));
+                }
+                if let Some((run_id, step)) = job.workflow_step() {
+                    workflows::fail(&mut tx, run_id, step, "cancelled", now).await?;
+                }
+                tx.commit().await?;
+                info!("Job {} cancelled while running", job.id);
+                let _ = job_events.send(JobEvent::status(job.id, JobStatus::Cancelled, None, now));
+            }
+            Err(err) => {
+                let new_attempts = job.attempts + 1;
+                let retry_delay = job.payload.retry_policy().retry_delay(&err, new_attempts);
+                let e = err.message().to_string();
+                if let Some(delay) = retry_delay {
+                    let next_run_at = now + chrono::Duration::milliseconds(delay.as_millis() as i64);
+                    let mut tx = db_pool.begin().await?;
+                    if !release(&mut tx, &job, worker_id, JobStatus::Pending, Some(&e), now).await? {
+                        return Ok(Some(job.id));
+                    }
+                    sqlx::query("UPDATE jobs SET attempts = ?, run_at = ? WHERE id = ?")
+                        .bind(new_attempts)
+                        .bind(next_run_at)
+                        .bind(job.id)
+                        .execute(&mut *tx)
+                        .await?;
+                    tx.commit().await?;
+                    driver.push(QueuedJob { id: job.id, queue: job.queue, run_at: next_run_at }).await?;
+                    let _ = job_events.send(JobEvent::status(job.id, JobStatus::Pending, Some(e), now));
+                } else {
+                    let mut tx = db_pool.begin().await?;
+                    if !release(&mut tx, &job, worker_id, JobStatus::Failed, Some(&e), now).await? {
+                        return Ok(Some(job.id));
+                    }
+                    if let Some((run_id, step)) = job.workflow_step() {
+                        workflows::fail(&mut tx, run_id, step, &e, now).await?;
+                    }
+                    tx.commit().await?;
+                    let _ = job_events.send(JobEvent::status(job.id, JobStatus::Failed, Some(e), now));
+                }
+            }
+        }
+
+        Ok(Some(job.id))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use clock::ManualClock;
+        use queue_driver::SqlQueueDriver;
+
+        /// A worker context on its own database whose clock only moves when told to.
+        async fn context() -> (WorkerContext, Arc<ManualClock>) {
+            let db_pool = setup_database().await;
+            let manual = Arc::new(ManualClock::new("2024-01-01T00:00:00Z".parse().unwrap()));
+            let ctx = WorkerContext {
+                driver: Arc::new(SqlQueueDriver::new(db_pool.clone(), manual.clone())),
+                db_pool,
+                job_events: events::channel(),
+                running_jobs: RunningJobs::default(),
+                http: http_client::HttpClientService::new(Arc::new(http_client::ReqwestTransport::default())),
+                clock: manual.clone(),
+            };
+            (ctx, manual)
+        }
+
+        async fn job(ctx: &WorkerContext, job_id: Uuid) -> JobRecord {
+            sqlx::query_as("SELECT * FROM jobs WHERE id = ?").bind(job_id).fetch_one(&ctx.db_pool).await.unwrap()
+        }
+
+        #[tokio::test]
+        async fn a_failed_job_is_picked_up_again_only_once_its_backoff_has_elapsed() {
+            let (ctx, manual) = context().await;
+            let started = ctx.clock.now();
+            // The erasure row doesn't exist, so every run fails with a retryable error
+            let payload = tasks::TaskPayload::EraseUser { erasure_id: Uuid::new_v4() };
+            let queue = payload.queue();
+            let queued = job_queue_service::insert_job(&ctx.db_pool, &payload, queue, None, started).await.unwrap();
+
+            assert_eq!(fetch_and_process_job(&ctx, queue, "worker-1").await.unwrap(), Some(queued.id));
+            let retried = job(&ctx, queued.id).await;
+            assert_eq!((retried.status, retried.attempts), (JobStatus::Pending, 1));
+            assert_eq!(retried.run_at, started + chrono::Duration::seconds(2));
+
+            // Not due yet: DEFAULT backs off 2s after the first failure
+            assert_eq!(fetch_and_process_job(&ctx, queue, "worker-1").await.unwrap(), None);
+            manual.advance(chrono::Duration::seconds(1));
+            assert_eq!(fetch_and_process_job(&ctx, queue, "worker-1").await.unwrap(), None);
+
+            manual.advance(chrono::Duration::seconds(1));
+            assert_eq!(fetch_and_process_job(&ctx, queue, "worker-1").await.unwrap(), Some(queued.id));
+            let retried = job(&ctx, queued.id).await;
+            assert_eq!(retried.attempts, 2);
+            assert_eq!(retried.run_at, started + chrono::Duration::seconds(2 + 4));
+        }
+
+        #[tokio::test]
+        async fn a_running_job_is_reclaimed_once_its_heartbeat_goes_stale() {
+            let (ctx, manual) = context().await;
+            let payload = tasks::TaskPayload::EraseUser { erasure_id: Uuid::new_v4() };
+            let queued = job_queue_service::insert_job(&ctx.db_pool, &payload, payload.queue(), None, ctx.clock.now())
+                .await
+                .unwrap();
+            sqlx::query("UPDATE jobs SET status = 'running', locked_by = 'gone', heartbeat_at = ? WHERE id = ?")
+                .bind(ctx.clock.now())
+                .bind(queued.id)
+                .execute(&ctx.db_pool)
+                .await
+                .unwrap();
+
+            manual.advance(chrono::Duration::seconds(STALE_AFTER.as_secs() as i64 - 1));
+            assert_eq!(reap_stale_jobs(&ctx).await.unwrap(), 0);
+            assert_eq!(job(&ctx, queued.id).await.status, JobStatus::Running);
+
+            manual.advance(chrono::Duration::seconds(2));
+            assert_eq!(reap_stale_jobs(&ctx).await.unwrap(), 1);
+            let reclaimed = job(&ctx, queued.id).await;
+            assert_eq!((reclaimed.status, reclaimed.attempts), (JobStatus::Pending, 1));
+            assert_eq!(reclaimed.run_at, ctx.clock.now());
+            assert_eq!(reclaimed.locked_by, None);
+        }
+    }
+}
+
+// --- Data Retention ---
+mod retention {
+    use super::*;
+    use clock::Clock;
+    use tokio::sync::RwLock;
+
+    /// One cleanup rule. Rows in `table` matching `predicate` whose `age_column` is
+    /// older than `max_age` are deleted in batches of `batch_size`.
+    #[derive(Debug, Clone)]
+    pub struct RetentionPolicy {
+        pub name: &'static str,
+        pub table: &'static str,
+        pub age_column: &'static str,
+        pub predicate: &'static str,
+        pub max_age: chrono::Duration,
+        pub batch_size: i64,
+    }
+
+    impl RetentionPolicy {
+        fn where_clause(&self) -> String {
+            format!("{} AND {} < ?", self.predicate, self.age_column)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub struct PolicyRun {
+        pub policy: &'static str,
+        pub dry_run: bool,
+        pub matched: u64,
+        pub deleted: u64,
+        pub batches: u32,
+        pub duration_ms: u128,
+        pub error: Option<String>,
+        pub ran_at: DateTime<Utc>,
+    }
+
+    pub struct RetentionRegistry {
+        policies: Vec<RetentionPolicy>,
+        last_runs: RwLock<Vec<PolicyRun>>,
+    }
+
+    impl RetentionRegistry {
+        pub fn new() -> Self {
+            Self { policies: Vec::new(), last_runs: RwLock::new(Vec::new()) }
+        }
+
+        pub fn register(mut self, policy: RetentionPolicy) -> Self {
+            self.policies.push(policy);
+            self
+        }
+
+        pub fn with_default_policies() -> Self {
+            Self::new()
+                .register(RetentionPolicy {
+                    name: "failed_jobs",
+                    table: "jobs",
+                    age_column: "created_at",
+                    predicate: "status = 'failed'",
+                    max_age: chrono::Duration::days(30),
+                    batch_size: 500,
+                })
+                .register(RetentionPolicy {
+                    name: "audit_events",
+                    table: "audit_events",
+                    age_column: "created_at",
+                    predicate: "1 = 1",
+                    max_age: chrono::Duration::days(365),
+                    batch_size: 1000,
+                })
+                .register(RetentionPolicy {
+                    name: "expired_email_change_tokens",
+                    table: "pending_email_changes",
+                    age_column: "expires_at",
+                    predicate: "1 = 1",
+                    max_age: chrono::Duration::days(1),
+                    batch_size: 500,
+                })
+                .register(RetentionPolicy {
+                    name: "expired_password_reset_tokens",
+                    table: "password_resets",
+                    age_column: "expires_at",
+                    predicate: "1 = 1",
+                    max_age: chrono::Duration::days(1),
+                    batch_size: 500,
+                })
+                // Erased accounts are kept while they still own tombstoned posts
+                .register(RetentionPolicy {
+                    name: "erased_users",
+                    table: "users",
+                    age_column: "erased_at",
+                    predicate: "erased_at IS NOT NULL AND id NOT IN (SELECT user_id FROM posts)",
+                    max_age: chrono::Duration::days(30),
+                    batch_size: 100,
+                })
+        }
+
+        pub async fn last_runs(&self) -> Vec<PolicyRun> {
+            self.last_runs.read().await.clone()
+        }
+
+        pub async fn run_all(&self, db_pool: &SqlitePool, clock: &dyn Clock, dry_run: bool) -> Vec<PolicyRun> {
+            let mut runs = Vec::with_capacity(self.policies.len());
+            for policy in &self.policies {
+                let run = run_policy(db_pool, policy, dry_run, clock.now()).await;
+                match &run.error {
+                    None => info!(
+                        policy = run.policy,
+                        dry_run,
+                        matched = run.matched,
+                        deleted = run.deleted,
+                        batches = run.batches,
+                        duration_ms = run.duration_ms as u64,
+                        "Retention policy applied"
+                    ),
+                    Some(e) => tracing::error!(policy = run.policy, "Retention policy failed: {}", e),
+                }
+                runs.push(run);
+            }
+            *self.last_runs.write().await = runs.clone();
+            runs
+        }
+    }
+
+    async fn run_policy(db_pool: &SqlitePool, policy: &RetentionPolicy, dry_run: bool, now: DateTime<Utc>) -> PolicyRun {
+        let started = std::time::Instant::now();
+        let cutoff = now - policy.max_age;
+        let mut run = PolicyRun {
+            policy: policy.name,
+            dry_run,
+            matched: 0,
+            deleted: 0,
+            batches: 0,
+            duration_ms: 0,
+            error: None,
+            ran_at: now,
+        };
+
+        let count_sql = format!("SELECT COUNT(*) FROM {} WHERE {}", policy.table, policy.where_clause());
+        match sqlx::query_scalar::<_, i64>(&count_sql).bind(cutoff).fetch_one(db_pool).await {
+            Ok(count) => run.matched = count as u64,
+            Err(e) => run.error = Some(e.to_string()),
+        }
+
+        if !dry_run && run.error.is_none() {
+            // Small batches keep each write lock short on SQLite
+            let delete_sql = format!(
+                "DELETE FROM {table} WHERE rowid IN (SELECT rowid FROM {table} WHERE {clause} LIMIT ?)",
+                table = policy.table,
+                clause = policy.where_clause(),
+            );
+            loop {
+                match sqlx::query(&delete_sql).bind(cutoff).bind(policy.batch_size).execute(db_pool).await {
+                    Ok(result) => {
+                        run.batches += 1;
+                        run.deleted += result.rows_affected();
+                        if result.rows_affected() < policy.batch_size as u64 {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        run.error = Some(e.to_string());
+                        break;
+                    }
+                }
+            }
+        }
+
+        run.duration_ms = started.elapsed().as_millis();
+        run
+    }
+
+    /// `RETENTION_DRY_RUN=true` makes scheduled runs report without deleting.
+    pub fn dry_run_from_env() -> bool {
+        std::env::var("RETENTION_DRY_RUN").map(|v| v == "true" || v == "1").unwrap_or(false)
+    }
+}
+
+// --- Periodic Task Scheduler ---
+mod scheduler {
+    use super::*;
+    use clock::Clock;
+    use std::str::FromStr;
+
+    const UPCOMING_RUNS: usize = 5;
+
+    /// A recurring job registered with the scheduler. Cron expressions use the
+    /// six-field (seconds first) syntax of tokio-cron-scheduler.
+    #[derive(Debug, Serialize)]
+    pub struct ScheduleDefinition {
+        pub name: &'static str,
+        pub cron: &'static str,
+        pub description: &'static str,
+    }
+
+    pub const RETENTION: ScheduleDefinition = ScheduleDefinition {
+        name: "retention",
+        cron: "0 0 * * * *",
+        description: "Apply every registered retention policy",
+    };
+    pub const PRUNE_REVISIONS: ScheduleDefinition = ScheduleDefinition {
+        name: "prune_revisions",
+        cron: "0 0 3 * * *",
+        description: "Enforce the post revision retention policy",
+    };
+    pub const PUBLISH_SCHEDULED_POSTS: ScheduleDefinition = ScheduleDefinition {
+        name: "publish_scheduled_posts",
+        cron: "0 * * * * *",
+        description: "Queue a PublishScheduledPosts sweep on the worker",
+    };
+    pub const RECONCILE_LIKES: ScheduleDefinition = ScheduleDefinition {
+        name: "reconcile_likes",
+        cron: "0 30 * * * *",
+        description: "Repair drift between post_likes and the denormalized counters",
+    };
+
+    pub const SCHEDULES: [&ScheduleDefinition; 4] = [&RETENTION, &PRUNE_REVISIONS, &PUBLISH_SCHEDULED_POSTS, &RECONCILE_LIKES];
+
+    #[derive(Debug, Serialize)]
+    pub struct ScheduleInfo {
+        #[serde(flatten)]
+        pub definition: &'static ScheduleDefinition,
+        pub next_run_at: Option<DateTime<Utc>>,
+        pub upcoming: Vec<DateTime<Utc>>,
+    }
+
+    /// Next runs are computed from the cron expressions, not read back from the
+    /// running scheduler, so this also works before the scheduler has started.
+    pub fn list_schedules(now: DateTime<Utc>) -> Vec<ScheduleInfo> {
+        SCHEDULES
+            .iter()
+            .map(|definition| {
+                let upcoming: Vec<DateTime<Utc>> = cron::Schedule::from_str(definition.cron)
+                    .expect("schedule definitions use valid cron expressions")
+                    .after(&now)
+                    .take(UPCOMING_RUNS)
+                    .collect();
+                ScheduleInfo { definition, next_run_at: upcoming.first().copied(), upcoming }
+            })
+            .collect()
+    }
+    
+    pub async fn setup_scheduler(
+        db_pool: SqlitePool,
+        retention_registry: Arc<retention::RetentionRegistry>,
+        clock: Arc<dyn Clock>,
+    ) -> JobScheduler {
+        // Ticks follow the wall clock; what each job does with the time (cutoffs,
+        // due dates) reads `clock`.
+        let sched = JobScheduler::new().await.expect("Failed to create scheduler");
+        let revisions_pool = db_pool.clone();
+        let publish_pool = db_pool.clone();
+        let likes_pool = db_pool.clone();
+        let retention_clock = clock.clone();
+        let revisions_clock = clock.clone();
+
+        // Hourly: apply every registered retention policy
+        let dry_run = retention::dry_run_from_env();
+        let cleanup_job = Job::new_async(RETENTION.cron, move |uuid, mut l| {
+            let pool = db_pool.clone();
+            let registry = retention_registry.clone();
+            let clock = retention_clock.clone();
+            Box::pin(async move {
+                info!("Running periodic job (ID: {}): Applying retention policies (dry_run = {}).", uuid, dry_run);
+                let runs = registry.run_all(&pool, clock.as_ref(), dry_run).await;
+                let deleted: u64 = runs.iter().map(|r| r.deleted).sum();
+                info!("Retention run finished: {} rows deleted across {} policies.", deleted, runs.len());
+                let next_tick = l.next_tick_for_job(uuid).await;
+                match next_tick {
+                    Ok(Some(ts)) => info!("Next cleanup run at: {:?}", ts),
+                    _ => info!("Could not get next cleanup run time."),
+                }
+            })
+        }).expect("Failed to create cleanup job");
+
+        sched.add(cleanup_job).await.expect("Failed to add job to scheduler");
+
+        // Daily at 03:00: enforce the post revision retention policy
+        let prune_revisions_job = Job::new_async(PRUNE_REVISIONS.cron, move |uuid, _l| {
+            let pool = revisions_pool.clone();
+            let clock = revisions_clock.clone();
+            Box::pin(async move {
+                info!("Running periodic job (ID: {}): Pruning old post revisions.", uuid);
+                match post_service::prune_revisions(&pool, clock.as_ref()).await {
+                    Ok(count) => info!("Pruned {} old post revisions.", count),
+                    Err(e) => tracing::error!("Revision pruning job failed: {}", e),
+                }
+            })
+        }).expect("Failed to create revision pruning job");
+
+        sched.add(prune_revisions_job).await.expect("Failed to add job to scheduler");
+
+        // Every minute: enqueue a sweep for scheduled posts. The sweep runs on the
+        // regular worker so it gets the queue's retries like any other task.
+        let job_queue_service = job_queue_service::JobQueueService::new(publish_pool, clock.clone());
+        let publish_job = Job::new_async(PUBLISH_SCHEDULED_POSTS.cron, move |uuid, _l| {
+            let queue = job_queue_service.clone();
+            Box::pin(async move {
+                match queue.schedule_task(tasks::TaskPayload::PublishScheduledPosts).await {
+                    Ok(job_id) => info!("Periodic job (ID: {}) queued scheduled-post sweep {}", uuid, job_id),
+                    Err(e) => tracing::error!("Failed to queue scheduled-post sweep: {}", e),
+                }
+            })
+        }).expect("Failed to create scheduled publishing job");
+
+        sched.add(publish_job).await.expect("Failed to add job to scheduler");
+
+        // Hourly at :30: repair drift between post_likes and the denormalized counters
+        let reconcile_likes_job = Job::new_async(RECONCILE_LIKES.cron, move |uuid, _l| {
+            let pool = likes_pool.clone();
+            Box::pin(async move {
+                info!("Running periodic job (ID: {}): Reconciling like counters.", uuid);
+                match post_service::reconcile_like_counts(&pool).await {
+                    Ok(0) => info!("Like counters are consistent."),
+                    Ok(count) => tracing::warn!("Repaired like_count drift on {} posts.", count),
+                    Err(e) => tracing::error!("Like reconciliation job failed: {}", e),
+                }
+            })
+        }).expect("Failed to create like reconciliation job");
+
+        sched.add(reconcile_likes_job).await.expect("Failed to add job to scheduler");
+        sched.start().await.expect("Failed to start scheduler");
+        info!("Periodic job scheduler started.");
         sched
     }
-}
+}
+
+// --- Admin Service ---
+mod admin {
+    use super::*;
+    use clock::Clock;
+
+    const RECENT_AUDIT_EVENTS: i64 = 20;
+
+    #[derive(Debug, Serialize, FromRow)]
+    pub struct UserCount {
+        pub role: String,
+        pub is_active: bool,
+        pub count: i64,
+    }
+
+    #[derive(Debug, Serialize, FromRow)]
+    pub struct StatusCount {
+        pub status: String,
+        pub count: i64,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct JobHealth {
+        /// Jobs waiting for or held by a worker.
+        pub queue_depth: i64,
+        pub by_status: Vec<StatusCount>,
+        pub completed_24h: i64,
+        pub failed_24h: i64,
+        /// Failed share of jobs that finished in the window; `None` when none did.
+        pub failure_rate_24h: Option<f64>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Overview {
+        pub generated_at: DateTime<Utc>,
+        pub users: Vec<UserCount>,
+        pub posts: Vec<StatusCount>,
+        pub jobs: JobHealth,
+        pub recent_audit_events: Vec<audit::AuditEvent>,
+    }
+
+    /// Every figure is an aggregate computed in SQL, so the cost doesn't grow with
+    /// the number of rows returned.
+    #[derive(Clone)]
+    pub struct AdminService {
+        db_pool: SqlitePool,
+        clock: Arc<dyn Clock>,
+    }
+
+    impl AdminService {
+        pub fn new(db_pool: SqlitePool, clock: Arc<dyn Clock>) -> Self {
+            Self { db_pool, clock }
+        }
+
+        pub async fn overview(&self) -> Result<Overview, AppError> {
+            let generated_at = self.clock.now();
+            let window_start = generated_at - chrono::Duration::hours(24);
+
+            // Erased accounts and the anonymous author are bookkeeping rows, not users
+            let users = sqlx::query_as::<_, UserCount>(
+                "SELECT role, is_active, COUNT(*) AS count FROM users
+                 WHERE erased_at IS NULL AND id != ?
+                 GROUP BY role, is_active ORDER BY role, is_active DESC",
+            )
+            .bind(erasure::ANONYMOUS_AUTHOR_ID)
+            .fetch_all(&self.db_pool)
+            .await?;
+
+            let posts = sqlx::query_as::<_, StatusCount>(
+                "SELECT status, COUNT(*) AS count FROM posts GROUP BY status ORDER BY status",
+            )
+            .fetch_all(&self.db_pool)
+            .await?;
+
+            let jobs_by_status = sqlx::query_as::<_, StatusCount>(
+                "SELECT status, COUNT(*) AS count FROM jobs GROUP BY status ORDER BY status",
+            )
+            .fetch_all(&self.db_pool)
+            .await?;
+
+            // Counted by when jobs finished, not when they were enqueued. datetime() on both
+            // sides because bound timestamps are RFC 3339 while column defaults are not.
+            let (completed_24h, failed_24h): (i64, i64) = sqlx::query_as(
+                "SELECT COALESCE(SUM(status = 'completed'), 0), COALESCE(SUM(status = 'failed'), 0)
+                 FROM jobs WHERE finished_at IS NOT NULL AND datetime(finished_at) > datetime(?)",
+            )
+            .bind(window_start)
+            .fetch_one(&self.db_pool)
+            .await?;
+
+            let recent_audit_events = sqlx::query_as::<_, audit::AuditEvent>(
+                "SELECT * FROM audit_events ORDER BY created_at DESC LIMIT ?",
+            )
+            .bind(RECENT_AUDIT_EVENTS)
+            .fetch_all(&self.db_pool)
+            .await?;
+
+            let queue_depth = jobs_by_status
+                .iter()
+                .filter(|s| s.status == "pending" || s.status == "running")
+                .map(|s| s.count)
+                .sum();
+            let finished = completed_24h + failed_24h;
+
+            Ok(Overview {
+                generated_at,
+                users,
+                posts,
+                jobs: JobHealth {
+                    queue_depth,
+                    by_status: jobs_by_status,
+                    completed_24h,
+                    failed_24h,
+                    failure_rate_24h: (finished > 0).then(|| failed_24h as f64 / finished as f64),
+                },
+                recent_audit_events,
+            })
+        }
+    }
+}
+
+// --- Authentication ---
+mod auth {
+    use super::*;
+    use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+
+    #[derive(Deserialize)]
+    struct Claims {
+        sub: Uuid,
+    }
+
+    /// Tokens are issued by the identity service that shares `JWT_SECRET`; this
+    /// service only verifies them.
+    #[derive(Clone)]
+    pub struct TokenVerifier {
+        key: Arc<DecodingKey>,
+    }
+
+    impl TokenVerifier {
+        pub fn from_secret(secret: &[u8]) -> Self {
+            Self { key: Arc::new(DecodingKey::from_secret(secret)) }
+        }
+
+        pub fn from_env() -> Self {
+            let secret = std::env::var("JWT_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .expect("JWT_SECRET must be set");
+            Self::from_secret(secret.as_bytes())
+        }
+
+        /// The user named by an `Authorization: Bearer <jwt>` header.
+        pub fn verify(&self, headers: &HeaderMap) -> Result<Uuid, AppError> {
+            let token = headers
+                .get(axum::http::header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .ok_or(AppError::Unauthorized)?;
+            decode::<Claims>(token, &self.key, &Validation::new(Algorithm::HS256))
+                .map(|data| data.claims.sub)
+                .map_err(|_| AppError::Unauthorized)
+        }
+    }
+}
+
+// --- API Handlers ---
+mod handlers {
+    use super::*;
+
+    #[derive(Deserialize)]
+    pub struct RegisterUserPayload {
+        email: String,
+        password: String,
+    }
+
+    /// Upper bound for `POST /jobs/batch`; the CSV import isn't limited by it.
+    const MAX_BATCH_JOBS: usize = 1000;
+
+    #[derive(Deserialize)]
+    pub struct BatchJobsPayload {
+        tasks: Vec<tasks::TaskPayload>,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(rename_all = "lowercase")]
+    pub enum PostFormat {
+        #[default]
+        Markdown,
+        Html,
+    }
+
+    #[derive(Deserialize)]
+    pub struct PostFormatQuery {
+        #[serde(default)]
+        format: PostFormat,
+    }
+
+    /// The post as stored, plus its content rendered for display.
+    #[derive(Serialize)]
+    pub struct RenderedPost {
+        #[serde(flatten)]
+        post: Post,
+        content_html: String,
+    }
+
+    #[derive(Deserialize)]
+    pub struct RejectPostPayload {
+        reason: String,
+    }
+
+    #[derive(Deserialize)]
+    pub struct ImageFromUrlPayload {
+        url: String,
+    }
+
+    #[derive(Deserialize)]
+    pub struct EmailChangePayload {
+        new_email: String,
+        current_password: String,
+    }
+
+    #[derive(Deserialize)]
+    pub struct ConfirmEmailChangePayload {
+        token: String,
+    }
+
+    #[derive(Deserialize)]
+    pub struct PasswordResetPayload {
+        email: String,
+    }
+
+    #[derive(Deserialize)]
+    pub struct ConfirmPasswordResetPayload {
+        token: String,
+        new_password: String,
+    }
+
+    fn acting_user_id(app_state: &AppState, headers: &HeaderMap) -> Result<Uuid, AppError> {
+        app_state.tokens.verify(headers)
+    }
+
+    /// For routes anonymous callers may use too. A token that is present but invalid
+    /// is still rejected rather than treated as anonymous.
+    fn optional_user_id(app_state: &AppState, headers: &HeaderMap) -> Result<Option<Uuid>, AppError> {
+        if !headers.contains_key(axum::http::header::AUTHORIZATION) {
+            return Ok(None);
+        }
+        acting_user_id(app_state, headers).map(Some)
+    }
+
+    async fn require_admin(app_state: &AppState, headers: &HeaderMap) -> Result<Uuid, AppError> {
+        let user_id = acting_user_id(app_state, headers)?;
+        if !data_export::is_admin(&app_state.db_pool, user_id).await? {
+            return Err(AppError::Forbidden);
+        }
+        Ok(user_id)
+    }
+
+    pub async fn register_user(
+        State(app_state): State<Arc<AppState>>,
+        Json(payload): Json<RegisterUserPayload>,
+    ) -> Result<impl IntoResponse, AppError> {
+        // 1. Create user in DB
+        let new_user = User {
+            id: Uuid::new_v4(),
+            email: payload.email.trim().to_lowercase(),
+            role: UserRole::USER,
+            is_active: true,
+            created_at: app_state.clock.now(),
+        };
+        app_state.password_policy.check(&payload.password, &new_user.email).await?;
+        let password_hash = passwords::hash_password(&payload.password)?;
+        let inserted = sqlx::query(
+            "INSERT INTO users (id, email, password_hash, role, is_active, created_at) VALUES (?, ?, ?, 'USER', ?, ?)",
+        )
+        .bind(new_user.id)
+        .bind(&new_user.email)
+        .bind(&password_hash)
+        .bind(new_user.is_active)
+        .bind(new_user.created_at)
+        .execute(&app_state.db_pool)
+        .await;
+        if let Err(sqlx::Error::Database(db_err)) = &inserted {
+            if db_err.is_unique_violation() {
+                return Err(AppError::Conflict("Email address is already in use".to_string()));
+            }
+        }
+        inserted?;
+        info!("User created: {}", new_user.id);
+        // The account exists from here on, so a failed dispatch is logged rather than
+        // turned into an error response the client would retry
+        let webhooks_enabled =
+            preferences::is_enabled(&app_state.db_pool, new_user.id, preferences::Channel::Webhook, webhooks::USER_CREATED)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!(user_id = ?new_user.id, "Preference lookup failed, dispatching anyway: {}", e);
+                    true
+                });
+        if webhooks_enabled {
+            if let Err(e) = app_state
+                .webhook_service
+                .dispatch(
+                    webhooks::USER_CREATED,
+                    serde_json::json!({ "user_id": new_user.id, "email": new_user.email }),
+                )
+                .await
+            {
+                tracing::error!(user_id = ?new_user.id, "Failed to dispatch user.created webhooks: {}", e);
+            }
+        }
+
+        // 2. Schedule a background job to send a welcome email
+        let task = tasks::TaskPayload::SendWelcomeEmail {
+            user_id: new_user.id,
+            email: new_user.email,
+        };
+        let job_id = app_state.job_queue_service.schedule_task(task).await?;
+        info!("Scheduled welcome email job: {}", job_id);
+
+        // 3. Schedule an image processing job (for demonstration)
+        let image_task = tasks::TaskPayload::ProcessImage {
+            post_id: Uuid::new_v4(), // Assume a new post was created
+            image_url: "https://example.com/image.jpg".to_string(),
+        };
+        let image_job_id = app_state.job_queue_service.schedule_task(image_task).await?;
+        info!("Scheduled image processing job: {}", image_job_id);
+
+        Ok((
+            StatusCode::CREATED,
+            Json(serde_json::json!({
+                "message": "User registered successfully. Welcome email and image processing jobs are scheduled.",
+                "user_id": new_user.id,
+                "email_job_id": job_id,
+                "image_job_id": image_job_id,
+            })),
+        ))
+    }
+
+    pub async fn get_job_status(
+        State(app_state): State<Arc<AppState>>,
+        Path(job_id): Path<Uuid>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let job = app_state.job_queue_service.get_job_status(job_id).await?;
+        Ok(Json(job))
+    }
+
+    pub async fn schedule_job_batch(
+        State(app_state): State<Arc<AppState>>,
+        headers: HeaderMap,
+        Json(payload): Json<BatchJobsPayload>,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        if payload.tasks.is_empty() || payload.tasks.len() > MAX_BATCH_JOBS {
+            return Err(AppError::Validation(format!("tasks must contain between 1 and {} entries", MAX_BATCH_JOBS)));
+        }
+        let job_ids = app_state.job_queue_service.schedule_many(payload.tasks).await?;
+        Ok((StatusCode::CREATED, Json(serde_json::json!({ "job_ids": job_ids }))))
+    }
+
+    pub async fn list_jobs(
+        State(app_state): State<Arc<AppState>>,
+        Query(query): Query<job_queue_service::ListJobsQuery>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.job_queue_service.list_jobs(query).await?))
+    }
+
+    pub async fn list_schedules(
+        State(app_state): State<Arc<AppState>>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        Ok(Json(scheduler::list_schedules(app_state.clock.now())))
+    }
+
+    pub async fn cancel_job(
+        State(app_state): State<Arc<AppState>>,
+        Path(job_id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        let job = app_state
+            .job_queue_service
+            .cancel(job_id, &app_state.running_jobs, &app_state.job_events)
+            .await?;
+        Ok((StatusCode::ACCEPTED, Json(job)))
+    }
+
+    /// Streams status transitions and progress for a job as Server-Sent Events.
+    /// The current status is sent first; the stream ends after `completed`, `failed`
+    /// or `cancelled`.
+    pub async fn job_events(
+        State(app_state): State<Arc<AppState>>,
+        Path(job_id): Path<Uuid>,
+    ) -> Result<impl IntoResponse, AppError> {
+        use futures::stream::{self, StreamExt};
+        use tokio::sync::broadcast::error::RecvError;
+
+        // Subscribe before reading the current status so no transition can slip in between
+        let rx = app_state.job_events.subscribe();
+        let job = app_state.job_queue_service.get_job_status(job_id).await?;
+        let initial = events::JobEvent::from_record(&job, app_state.clock.now());
+        let queue = app_state.job_queue_service.clone();
+        let clock = app_state.clock.clone();
+
+        let stream = stream::unfold((Some(initial), rx, false), move |(pending, mut rx, done)| {
+            let (queue, clock) = (queue.clone(), clock.clone());
+            async move {
+                if done {
+                    return None;
+                }
+                if let Some(event) = pending {
+                    let terminal = event.is_terminal();
+                    return Some((event, (None, rx, terminal)));
+                }
+                loop {
+                    match rx.recv().await {
+                        Ok(event) if event.job_id == job_id => {
+                            let terminal = event.is_terminal();
+                            return Some((event, (None, rx, terminal)));
+                        }
+                        Ok(_) => continue,
+                        // We may have missed the terminal event; resync from the database
+                        Err(RecvError::Lagged(_)) => match queue.get_job_status(job_id).await {
+                            Ok(job) => {
+                                let event = events::JobEvent::from_record(&job, clock.now());
+                                let terminal = event.is_terminal();
+                                return Some((event, (None, rx, terminal)));
+                            }
+                            Err(_) => return None,
+                        },
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        })
+        .map(|event| Event::default().event("status").json_data(&event));
+
+        Ok(Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(15)).text("ping")))
+    }
+
+    /// Accepts a CSV body with an `email` column and imports it in the background;
+    /// poll `GET /imports/:id` for progress and the per-row error report. Existing
+    /// emails are skipped and each new account gets a welcome email. Unlike single
+    /// registration, no `user.created` webhooks are fired.
+    pub async fn import_users(
+        State(app_state): State<Arc<AppState>>,
+        headers: HeaderMap,
+        body: axum::body::Body,
+    ) -> Result<impl IntoResponse, AppError> {
+        let requester_id = acting_user_id(&app_state, &headers)?;
+        let import = app_state.user_import_service.start(requester_id, body).await?;
+        let location = format!("/imports/{}", import.id);
+        Ok((StatusCode::ACCEPTED, [(axum::http::header::LOCATION, location)], Json(import)))
+    }
+
+    pub async fn get_user_import(
+        State(app_state): State<Arc<AppState>>,
+        Path(import_id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let requester_id = acting_user_id(&app_state, &headers)?;
+        Ok(Json(app_state.user_import_service.get_status(import_id, requester_id).await?))
+    }
+
+    /// Only the account owner can ask, and only after re-entering their password.
+    pub async fn request_email_change(
+        State(app_state): State<Arc<AppState>>,
+        Path(user_id): Path<Uuid>,
+        headers: HeaderMap,
+        Json(payload): Json<EmailChangePayload>,
+    ) -> Result<impl IntoResponse, AppError> {
+        if acting_user_id(&app_state, &headers)? != user_id {
+            return Err(AppError::Forbidden);
+        }
+        let change_id = app_state
+            .email_change_service
+            .request_change(user_id, payload.new_email, &payload.current_password)
+            .await?;
+        Ok((
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({
+                "message": "Confirmation codes have been sent to the current and the new address.",
+                "change_id": change_id,
+            })),
+        ))
+    }
+
+    /// Accepts either address's code. The email changes once both have been used.
+    pub async fn confirm_email_change(
+        State(app_state): State<Arc<AppState>>,
+        Json(payload): Json<ConfirmEmailChangePayload>,
+    ) -> Result<axum::response::Response, AppError> {
+        use email_change_service::Confirmation;
+        let response = match app_state.email_change_service.confirm_change(&payload.token).await? {
+            Confirmation::Completed { user_id, email } => {
+                Json(serde_json::json!({ "user_id": user_id, "email": email })).into_response()
+            }
+            Confirmation::Awaiting { user_id, address } => (
+                StatusCode::ACCEPTED,
+                Json(serde_json::json!({
+                    "user_id": user_id,
+                    "message": format!("Confirmed; still waiting on the {} address.", address),
+                })),
+            )
+                .into_response(),
+        };
+        Ok(response)
+    }
+
+    pub async fn request_password_reset(
+        State(app_state): State<Arc<AppState>>,
+        Json(payload): Json<PasswordResetPayload>,
+    ) -> Result<impl IntoResponse, AppError> {
+        app_state.password_reset_service.request_reset(&payload.email).await?;
+        Ok((
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({
+                "message": "If an account exists for that address, a reset link has been sent.",
+            })),
+        ))
+    }
+
+    pub async fn confirm_password_reset(
+        State(app_state): State<Arc<AppState>>,
+        Json(payload): Json<ConfirmPasswordResetPayload>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let user_id = app_state
+            .password_reset_service
+            .confirm_reset(&payload.token, &payload.new_password)
+            .await?;
+        Ok(Json(serde_json::json!({ "user_id": user_id })))
+    }
+
+    pub async fn create_post(
+        State(app_state): State<Arc<AppState>>,
+        headers: HeaderMap,
+        Json(payload): Json<post_service::CreatePost>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let author_id = acting_user_id(&app_state, &headers)?;
+        let post = app_state.post_service.create_post(author_id, payload).await?;
+        Ok((StatusCode::CREATED, Json(post)))
+    }
+
+    pub async fn list_posts(
+        State(app_state): State<Arc<AppState>>,
+        headers: HeaderMap,
+        Query(query): Query<post_service::ListPostsQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let viewer_id = optional_user_id(&app_state, &headers)?;
+        Ok(Json(app_state.post_service.list_posts(query, viewer_id).await?))
+    }
+
+    pub async fn like_post(
+        State(app_state): State<Arc<AppState>>,
+        Path(post_id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let user_id = acting_user_id(&app_state, &headers)?;
+        Ok(Json(app_state.post_service.like_post(post_id, user_id).await?))
+    }
+
+    pub async fn unlike_post(
+        State(app_state): State<Arc<AppState>>,
+        Path(post_id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let user_id = acting_user_id(&app_state, &headers)?;
+        Ok(Json(app_state.post_service.unlike_post(post_id, user_id).await?))
+    }
+
+    pub async fn get_post(
+        State(app_state): State<Arc<AppState>>,
+        Path(post_id): Path<Uuid>,
+        headers: HeaderMap,
+        Query(query): Query<PostFormatQuery>,
+    ) -> Result<axum::response::Response, AppError> {
+        let viewer_id = optional_user_id(&app_state, &headers)?;
+        let post = app_state.post_service.get_post(post_id, viewer_id).await?;
+        Ok(match query.format {
+            PostFormat::Markdown => Json(post).into_response(),
+            PostFormat::Html => {
+                let content_html = app_state.markdown.render(&post.content).to_string();
+                Json(RenderedPost { post, content_html }).into_response()
+            }
+        })
+    }
+
+    fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+        headers
+            .get(axum::http::header::IF_NONE_MATCH)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.split(',').map(str::trim).any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag))
+    }
+
+    async fn feed_response(app_state: &AppState, headers: &HeaderMap, feed: feeds::Feed) -> Result<axum::response::Response, AppError> {
+        use axum::http::header;
+        let etag = app_state.feed_service.etag(feed).await?;
+        if etag_matches(headers, &etag) {
+            return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+        }
+        let (content_type, body) = match feed {
+            feeds::Feed::Sitemap => ("application/xml; charset=utf-8", axum::body::Body::from_stream(app_state.feed_service.sitemap())),
+            feeds::Feed::Rss => ("application/rss+xml; charset=utf-8", axum::body::Body::from_stream(app_state.feed_service.rss())),
+        };
+        let headers = [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, "public, max-age=300".to_string()),
+        ];
+        Ok((headers, body).into_response())
+    }
+
+    pub async fn sitemap(
+        State(app_state): State<Arc<AppState>>,
+        headers: HeaderMap,
+    ) -> Result<axum::response::Response, AppError> {
+        feed_response(&app_state, &headers, feeds::Feed::Sitemap).await
+    }
+
+    pub async fn rss_feed(
+        State(app_state): State<Arc<AppState>>,
+        headers: HeaderMap,
+    ) -> Result<axum::response::Response, AppError> {
+        feed_response(&app_state, &headers, feeds::Feed::Rss).await
+    }
+
+    /// Old slugs answer with a 301 pointing at the post's current slug.
+    pub async fn get_post_by_slug(
+        State(app_state): State<Arc<AppState>>,
+        Path(slug): Path<String>,
+        headers: HeaderMap,
+    ) -> Result<axum::response::Response, AppError> {
+        let viewer_id = optional_user_id(&app_state, &headers)?;
+        Ok(match app_state.slug_service.resolve(&slug).await? {
+            slugs::SlugLookup::Current(post) if !app_state.post_service.can_see(&post, viewer_id).await? => {
+                return Err(AppError::SlugNotFound(slug));
+            }
+            slugs::SlugLookup::Current(post) => Json(post).into_response(),
+            slugs::SlugLookup::Moved(current) => (
+                StatusCode::MOVED_PERMANENTLY,
+                [(axum::http::header::LOCATION, format!("/posts/by-slug/{}", current))],
+            )
+                .into_response(),
+        })
+    }
+
+    pub async fn update_post(
+        State(app_state): State<Arc<AppState>>,
+        Path(post_id): Path<Uuid>,
+        headers: HeaderMap,
+        Json(payload): Json<post_service::UpdatePost>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let editor_id = acting_user_id(&app_state, &headers)?;
+        Ok(Json(app_state.post_service.update_post(post_id, editor_id, payload).await?))
+    }
+
+    pub async fn list_moderation_queue(
+        State(app_state): State<Arc<AppState>>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.post_service.moderation_queue().await?))
+    }
+
+    pub async fn approve_post(
+        State(app_state): State<Arc<AppState>>,
+        Path(post_id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let admin_id = require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.post_service.approve(post_id, admin_id).await?))
+    }
+
+    pub async fn reject_post(
+        State(app_state): State<Arc<AppState>>,
+        Path(post_id): Path<Uuid>,
+        headers: HeaderMap,
+        Json(payload): Json<RejectPostPayload>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let admin_id = require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.post_service.reject(post_id, admin_id, payload.reason).await?))
+    }
+
+    pub async fn list_post_revisions(
+        State(app_state): State<Arc<AppState>>,
+        Path(post_id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let requester_id = acting_user_id(&app_state, &headers)?;
+        Ok(Json(app_state.post_service.list_revisions(post_id, requester_id).await?))
+    }
+
+    pub async fn restore_post_revision(
+        State(app_state): State<Arc<AppState>>,
+        Path((post_id, revision)): Path<(Uuid, i64)>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let editor_id = acting_user_id(&app_state, &headers)?;
+        Ok(Json(app_state.post_service.restore_revision(post_id, revision, editor_id).await?))
+    }
+
+    pub async fn request_data_export(
+        State(app_state): State<Arc<AppState>>,
+        Path(user_id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let requester_id = acting_user_id(&app_state, &headers)?;
+        let export = app_state.data_export_service.request_export(user_id, requester_id).await?;
+        Ok((StatusCode::ACCEPTED, Json(export)))
+    }
+
+    pub async fn get_data_export(
+        State(app_state): State<Arc<AppState>>,
+        Path((user_id, export_id)): Path<(Uuid, Uuid)>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let requester_id = acting_user_id(&app_state, &headers)?;
+        Ok(Json(app_state.data_export_service.get_status(user_id, export_id, requester_id).await?))
+    }
+
+    #[derive(Deserialize)]
+    pub struct DownloadQuery {
+        expires: i64,
+        signature: String,
+    }
+
+    // The signed query string is the only credential for downloads
+    pub async fn download_object(
+        State(app_state): State<Arc<AppState>>,
+        Path(key): Path<String>,
+        Query(query): Query<DownloadQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        if !app_state.object_storage.verify_presigned(&key, query.expires, &query.signature, app_state.clock.now()) {
+            return Err(AppError::Forbidden);
+        }
+        let bytes = app_state
+            .object_storage
+            .get(&key)
+            .await
+            .map_err(|e| {
+                tracing::error!("Object storage read failed for {}: {}", key, e);
+                AppError::Internal
+            })?
+            .ok_or(AppError::Forbidden)?;
+        let file_name = key.rsplit('/').next().unwrap_or("download").to_string();
+        Ok((
+            [
+                (axum::http::header::CONTENT_TYPE, "application/zip".to_string()),
+                (axum::http::header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)),
+            ],
+            bytes,
+        ))
+    }
 
-// --- API Handlers ---
-mod handlers {
-    use super::*;
-    use job_queue_service::JobQueueService;
+    pub async fn request_erasure(
+        State(app_state): State<Arc<AppState>>,
+        Path(user_id): Path<Uuid>,
+        Query(query): Query<erasure::EraseQuery>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let requester_id = acting_user_id(&app_state, &headers)?;
+        let erasure = app_state.erasure_service.request_erasure(user_id, requester_id, query.posts).await?;
+        Ok((StatusCode::ACCEPTED, Json(erasure)))
+    }
+
+    pub async fn get_erasure(
+        State(app_state): State<Arc<AppState>>,
+        Path((user_id, erasure_id)): Path<(Uuid, Uuid)>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let requester_id = acting_user_id(&app_state, &headers)?;
+        Ok(Json(app_state.erasure_service.get_status(user_id, erasure_id, requester_id).await?))
+    }
 
     #[derive(Deserialize)]
-    pub struct RegisterUserPayload {
-        email: String,
-        // password etc.
+    pub struct RetentionRunQuery {
+        #[serde(default)]
+        dry_run: bool,
     }
 
-    pub async fn register_user(
+    pub async fn admin_overview(
         State(app_state): State<Arc<AppState>>,
-        Json(payload): Json<RegisterUserPayload>,
+        headers: HeaderMap,
     ) -> Result<impl IntoResponse, AppError> {
-        // 1. Create user in DB (mocked for simplicity)
-        let new_user = User {
-            id: Uuid::new_v4(),
-            email: payload.email.clone(),
-            role: UserRole::USER,
-            is_active: true,
-            created_at: Utc::now(),
-        };
-        info!("User created: {}", new_user.id);
+        require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.admin_service.overview().await?))
+    }
 
-        // 2. Schedule a background job to send a welcome email
-        let task = tasks::TaskPayload::SendWelcomeEmail {
-            user_id: new_user.id,
-            email: new_user.email,
-        };
-        let job_id = app_state.job_queue_service.schedule_task(task).await?;
-        info!("Scheduled welcome email job: {}", job_id);
+    pub async fn upload_avatar(
+        State(app_state): State<Arc<AppState>>,
+        Path(user_id): Path<Uuid>,
+        headers: HeaderMap,
+        mut multipart: Multipart,
+    ) -> Result<impl IntoResponse, AppError> {
+        if acting_user_id(&app_state, &headers)? != user_id {
+            return Err(AppError::Forbidden);
+        }
+        let malformed = |e: axum::extract::multipart::MultipartError| AppError::Validation(e.body_text());
+        while let Some(field) = multipart.next_field().await.map_err(malformed)? {
+            if field.name() != Some("avatar") {
+                continue;
+            }
+            let content_type = field.content_type().map(str::to_string);
+            let bytes = field.bytes().await.map_err(malformed)?;
+            let job_id = avatars::accept_upload(
+                &app_state.db_pool,
+                app_state.object_storage.as_ref(),
+                &app_state.job_queue_service,
+                user_id,
+                content_type.as_deref(),
+                bytes.to_vec(),
+            )
+            .await?;
+            return Ok((StatusCode::ACCEPTED, Json(serde_json::json!({ "job_id": job_id }))));
+        }
+        Err(AppError::Validation("Multipart field 'avatar' is missing".to_string()))
+    }
 
-        // 3. Schedule an image processing job (for demonstration)
-        let image_task = tasks::TaskPayload::ProcessImage {
-            post_id: Uuid::new_v4(), // Assume a new post was created
-            image_url: "https://example.com/image.jpg".to_string(),
+    pub async fn get_avatar(
+        State(app_state): State<Arc<AppState>>,
+        Path((user_id, size)): Path<(Uuid, u32)>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let png = avatars::variant(&app_state.db_pool, app_state.object_storage.as_ref(), user_id, size).await?;
+        Ok((
+            [
+                (axum::http::header::CONTENT_TYPE, "image/png"),
+                (axum::http::header::CACHE_CONTROL, "public, max-age=3600"),
+            ],
+            png,
+        ))
+    }
+
+    /// Checks the URL up front so obvious SSRF attempts get a 422, then leaves the
+    /// download itself to the media queue.
+    pub async fn image_from_url(
+        State(app_state): State<Arc<AppState>>,
+        Path(post_id): Path<Uuid>,
+        headers: HeaderMap,
+        Json(payload): Json<ImageFromUrlPayload>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let user_id = acting_user_id(&app_state, &headers)?;
+        let post = app_state.post_service.get_post(post_id, Some(user_id)).await?;
+        if post.user_id != user_id {
+            return Err(AppError::Forbidden);
+        }
+        remote_image::resolve_source(&payload.url).await.map_err(|e| AppError::Validation(e.to_string()))?;
+        let input = serde_json::json!({ "post_id": post_id, "source_url": payload.url });
+        let run = app_state.workflow_service.start(workflows::POST_IMAGE_FROM_URL.name, input).await?;
+        Ok((StatusCode::ACCEPTED, Json(run)))
+    }
+
+    pub async fn start_workflow(
+        State(app_state): State<Arc<AppState>>,
+        Path(name): Path<String>,
+        headers: HeaderMap,
+        Json(input): Json<serde_json::Value>,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        let run = app_state.workflow_service.start(&name, input).await?;
+        Ok((StatusCode::ACCEPTED, Json(run)))
+    }
+
+    pub async fn get_workflow_run(
+        State(app_state): State<Arc<AppState>>,
+        Path(run_id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.workflow_service.get(run_id).await?))
+    }
+
+    pub async fn resume_workflow_run(
+        State(app_state): State<Arc<AppState>>,
+        Path(run_id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        Ok((StatusCode::ACCEPTED, Json(app_state.workflow_service.resume(run_id).await?)))
+    }
+
+    pub async fn list_retention_runs(
+        State(app_state): State<Arc<AppState>>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.retention_registry.last_runs().await))
+    }
+
+    pub async fn run_retention(
+        State(app_state): State<Arc<AppState>>,
+        Query(query): Query<RetentionRunQuery>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.retention_registry.run_all(&app_state.db_pool, app_state.clock.as_ref(), query.dry_run).await))
+    }
+
+    pub async fn get_notification_preferences(
+        State(app_state): State<Arc<AppState>>,
+        Path(user_id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let requester_id = acting_user_id(&app_state, &headers)?;
+        Ok(Json(app_state.preference_service.get(user_id, requester_id).await?))
+    }
+
+    pub async fn update_notification_preferences(
+        State(app_state): State<Arc<AppState>>,
+        Path(user_id): Path<Uuid>,
+        headers: HeaderMap,
+        Json(payload): Json<Vec<preferences::PreferenceUpdate>>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let requester_id = acting_user_id(&app_state, &headers)?;
+        Ok(Json(app_state.preference_service.update(user_id, requester_id, payload).await?))
+    }
+
+    async fn check_preview_access(app_state: &AppState, headers: &HeaderMap) -> Result<(), AppError> {
+        if app_state.email_previews.require_admin {
+            require_admin(app_state, headers).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn list_email_previews(
+        State(app_state): State<Arc<AppState>>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        check_preview_access(&app_state, &headers).await?;
+        Ok(Json(mailer::templates().names().to_vec()))
+    }
+
+    #[derive(Deserialize)]
+    pub struct EmailPreviewQuery {
+        #[serde(default)]
+        format: Option<String>,
+    }
+
+    /// Renders with sample data; `?format=text` or `?format=json` for the other parts.
+    pub async fn preview_email(
+        State(app_state): State<Arc<AppState>>,
+        Path(template): Path<String>,
+        Query(query): Query<EmailPreviewQuery>,
+        headers: HeaderMap,
+    ) -> Result<axum::response::Response, AppError> {
+        check_preview_access(&app_state, &headers).await?;
+        let data = mailer::sample_data(&template, app_state.clock.now())
+            .ok_or_else(|| AppError::Validation(format!("Unknown email template '{}'", template)))?;
+        let message = mailer::templates()
+            .render(&template, "preview@example.com", &data)
+            .map_err(|e| {
+                tracing::error!("Email preview failed: {}", e);
+                AppError::Internal
+            })?;
+        let response = match query.format.as_deref() {
+            Some("text") => message.text_body.into_response(),
+            Some("json") => Json(serde_json::json!({
+                "template": template,
+                "subject": message.subject,
+                "html": message.html_body,
+                "text": message.text_body,
+                "data": data,
+            }))
+            .into_response(),
+            _ => axum::response::Html(message.html_body).into_response(),
         };
-        let image_job_id = app_state.job_queue_service.schedule_task(image_task).await?;
-        info!("Scheduled image processing job: {}", image_job_id);
+        Ok(response)
+    }
 
+    pub async fn create_webhook(
+        State(app_state): State<Arc<AppState>>,
+        headers: HeaderMap,
+        Json(payload): Json<webhooks::CreateSubscription>,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        let (subscription, secret) = app_state.webhook_service.create_subscription(payload).await?;
         Ok((
             StatusCode::CREATED,
-            Json(serde_json::json!({
-                "message": "User registered successfully. Welcome email and image processing jobs are scheduled.",
-                "user_id": new_user.id,
-                "email_job_id": job_id,
-                "image_job_id": image_job_id,
-            })),
+            Json(serde_json::json!({ "subscription": subscription, "secret": secret })),
         ))
     }
 
-    pub async fn get_job_status(
+    pub async fn list_webhooks(
         State(app_state): State<Arc<AppState>>,
-        Path(job_id): Path<Uuid>,
+        headers: HeaderMap,
     ) -> Result<impl IntoResponse, AppError> {
-        let job = app_state.job_queue_service.get_job_status(job_id).await?;
-        Ok(Json(job))
+        require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.webhook_service.list_subscriptions().await?))
+    }
+
+    pub async fn get_webhook(
+        State(app_state): State<Arc<AppState>>,
+        Path(id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.webhook_service.get_subscription(id).await?))
+    }
+
+    pub async fn update_webhook(
+        State(app_state): State<Arc<AppState>>,
+        Path(id): Path<Uuid>,
+        headers: HeaderMap,
+        Json(payload): Json<webhooks::UpdateSubscription>,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.webhook_service.update_subscription(id, payload).await?))
+    }
+
+    pub async fn delete_webhook(
+        State(app_state): State<Arc<AppState>>,
+        Path(id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        app_state.webhook_service.delete_subscription(id).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn list_webhook_deliveries(
+        State(app_state): State<Arc<AppState>>,
+        Path(id): Path<Uuid>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        require_admin(&app_state, &headers).await?;
+        Ok(Json(app_state.webhook_service.list_attempts(id).await?))
     }
 }
 
@@ -370,9 +8083,30 @@ mod handlers {
 pub struct AppState {
     db_pool: SqlitePool,
     job_queue_service: job_queue_service::JobQueueService,
+    email_change_service: email_change_service::EmailChangeService,
+    password_policy: passwords::PasswordPolicy,
+    password_reset_service: password_reset_service::PasswordResetService,
+    webhook_service: webhooks::WebhookService,
+    post_service: post_service::PostService,
+    slug_service: slugs::SlugService,
+    feed_service: feeds::FeedService,
+    markdown: markdown::MarkdownRenderer,
+    data_export_service: data_export::DataExportService,
+    user_import_service: user_import::UserImportService,
+    erasure_service: erasure::ErasureService,
+    preference_service: preferences::PreferenceService,
+    object_storage: Arc<dyn object_storage::ObjectStorage>,
+    retention_registry: Arc<retention::RetentionRegistry>,
+    admin_service: admin::AdminService,
+    workflow_service: workflows::WorkflowService,
+    tokens: auth::TokenVerifier,
+    email_previews: mailer::PreviewConfig,
+    job_events: events::JobEventSender,
+    running_jobs: worker::RunningJobs,
+    clock: Arc<dyn clock::Clock>,
 }
 
-async fn setup_database() -> SqlitePool {
+pub(crate) async fn setup_database() -> SqlitePool {
     let pool = SqlitePoolOptions::new()
         .connect("sqlite::memory:")
         .await
@@ -382,31 +8116,290 @@ async fn setup_database() -> SqlitePool {
         "CREATE TABLE IF NOT EXISTS jobs (
             id TEXT PRIMARY KEY,
             payload TEXT NOT NULL,
+            queue TEXT NOT NULL DEFAULT 'default',
             status TEXT NOT NULL DEFAULT 'pending',
             attempts INTEGER NOT NULL DEFAULT 0,
             run_at DATETIME NOT NULL,
             created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            error_message TEXT
+            error_message TEXT,
+            workflow_run_id TEXT,
+            workflow_step INTEGER,
+            locked_by TEXT,
+            heartbeat_at DATETIME,
+            finished_at DATETIME
         );",
     )
     .execute(&pool)
     .await
     .expect("Failed to create jobs table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS workflow_runs (
+            id TEXT PRIMARY KEY,
+            workflow TEXT NOT NULL,
+            status TEXT NOT NULL,
+            current_step INTEGER NOT NULL DEFAULT 0,
+            context TEXT NOT NULL,
+            steps TEXT NOT NULL,
+            error_message TEXT,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create workflow_runs table");
     
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS users (
+            id TEXT PRIMARY KEY,
+            email TEXT NOT NULL UNIQUE,
+            role TEXT NOT NULL DEFAULT 'USER',
+            is_active BOOLEAN NOT NULL DEFAULT TRUE,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            erased_at DATETIME,
+            avatar_version TEXT,
+            avatar_url TEXT,
+            -- NULL for imported accounts until they go through a password reset
+            password_hash TEXT
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create users table");
+
+    sqlx::query("INSERT OR IGNORE INTO users (id, email, role, is_active) VALUES (?, 'anonymous@erased.invalid', 'USER', FALSE)")
+        .bind(erasure::ANONYMOUS_AUTHOR_ID)
+        .execute(&pool)
+        .await
+        .expect("Failed to seed anonymous author");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS pending_email_changes (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            new_email TEXT NOT NULL,
+            -- Sent to the new address; `old_token` goes to the current one
+            token TEXT NOT NULL UNIQUE,
+            old_token TEXT NOT NULL UNIQUE,
+            new_confirmed_at DATETIME,
+            old_confirmed_at DATETIME,
+            expires_at DATETIME NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create pending_email_changes table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS password_resets (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            token TEXT NOT NULL UNIQUE,
+            expires_at DATETIME NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create password_resets table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS webhook_subscriptions (
+            id TEXT PRIMARY KEY,
+            url TEXT NOT NULL,
+            secret TEXT NOT NULL,
+            event_types TEXT NOT NULL,
+            is_active BOOLEAN NOT NULL DEFAULT TRUE,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create webhook_subscriptions table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS webhook_deliveries (
+            id TEXT PRIMARY KEY,
+            subscription_id TEXT NOT NULL REFERENCES webhook_subscriptions(id) ON DELETE CASCADE,
+            event_type TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create webhook_deliveries table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS webhook_delivery_attempts (
+            id TEXT PRIMARY KEY,
+            delivery_id TEXT NOT NULL REFERENCES webhook_deliveries(id) ON DELETE CASCADE,
+            attempt INTEGER NOT NULL,
+            status_code INTEGER,
+            error_message TEXT,
+            duration_ms INTEGER NOT NULL,
+            attempted_at DATETIME NOT NULL
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create webhook_delivery_attempts table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS audit_events (
+            id TEXT PRIMARY KEY,
+            actor_id TEXT,
+            subject_user_id TEXT,
+            action TEXT NOT NULL,
+            details TEXT NOT NULL,
+            created_at DATETIME NOT NULL
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create audit_events table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS data_exports (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            requested_by TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            object_key TEXT,
+            error_message TEXT,
+            created_at DATETIME NOT NULL,
+            completed_at DATETIME
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create data_exports table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS user_imports (
+            id TEXT PRIMARY KEY,
+            requested_by TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            object_key TEXT NOT NULL,
+            total_bytes INTEGER NOT NULL,
+            processed_bytes INTEGER NOT NULL DEFAULT 0,
+            processed_rows INTEGER NOT NULL DEFAULT 0,
+            imported INTEGER NOT NULL DEFAULT 0,
+            skipped INTEGER NOT NULL DEFAULT 0,
+            invalid_rows INTEGER NOT NULL DEFAULT 0,
+            row_errors TEXT NOT NULL DEFAULT '[]',
+            error_message TEXT,
+            created_at DATETIME NOT NULL,
+            completed_at DATETIME
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create user_imports table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS user_erasures (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            requested_by TEXT NOT NULL,
+            post_policy TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            error_message TEXT,
+            created_at DATETIME NOT NULL,
+            verified_at DATETIME
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create user_erasures table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS notification_preferences (
+            user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            channel TEXT NOT NULL,
+            event_type TEXT NOT NULL,
+            enabled BOOLEAN NOT NULL,
+            updated_at DATETIME NOT NULL,
+            PRIMARY KEY (user_id, channel, event_type)
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create notification_preferences table");
+
     // Mock posts table for image processing task
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS posts (
             id TEXT PRIMARY KEY,
             user_id TEXT NOT NULL,
             title TEXT NOT NULL,
+            slug TEXT NOT NULL UNIQUE,
             content TEXT NOT NULL,
-            status TEXT NOT NULL DEFAULT 'DRAFT'
+            status TEXT NOT NULL DEFAULT 'DRAFT',
+            publish_at DATETIME,
+            published_at DATETIME,
+            like_count INTEGER NOT NULL DEFAULT 0,
+            moderation_reason TEXT
         );"
     )
     .execute(&pool)
     .await
     .expect("Failed to create posts table");
 
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_posts_like_count ON posts (like_count)")
+        .execute(&pool)
+        .await
+        .expect("Failed to create posts like_count index");
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_posts_published_at ON posts (status, published_at)")
+        .execute(&pool)
+        .await
+        .expect("Failed to create posts published_at index");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS post_likes (
+            post_id TEXT NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+            user_id TEXT NOT NULL,
+            created_at DATETIME NOT NULL,
+            PRIMARY KEY (post_id, user_id)
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create post_likes table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS post_slugs (
+            slug TEXT PRIMARY KEY,
+            post_id TEXT NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+            created_at DATETIME NOT NULL
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create post_slugs table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS post_revisions (
+            id TEXT PRIMARY KEY,
+            post_id TEXT NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+            revision INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            content TEXT NOT NULL,
+            status TEXT NOT NULL,
+            editor_id TEXT NOT NULL,
+            created_at DATETIME NOT NULL,
+            UNIQUE (post_id, revision)
+        );",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create post_revisions table");
+
     pool
 }
 
@@ -416,24 +8409,154 @@ async fn main() {
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
+    let clock: Arc<dyn clock::Clock> = Arc::new(clock::SystemClock);
     let db_pool = setup_database().await;
-    let job_queue_service = job_queue_service::JobQueueService::new(db_pool.clone());
+    queue_driver::init_from_env(&db_pool, clock.clone()).await.expect("Failed to initialise queue driver");
+    let job_queue_service = job_queue_service::JobQueueService::new(db_pool.clone(), clock.clone());
+    let email_change_service =
+        email_change_service::EmailChangeService::new(db_pool.clone(), job_queue_service.clone(), clock.clone());
+    let webhook_service = webhooks::WebhookService::new(db_pool.clone(), job_queue_service.clone(), clock.clone());
+    let post_service = post_service::PostService::new(db_pool.clone(), moderation::from_env(), clock.clone());
+    let slug_service = slugs::SlugService::new(db_pool.clone());
+    let feed_service = feeds::FeedService::from_env(db_pool.clone());
+    let markdown = markdown::MarkdownRenderer::new(Arc::new(markdown::AmmoniaSanitizer::default()));
+    let object_storage = object_storage::from_env();
+    let data_export_service = data_export::DataExportService::new(
+        db_pool.clone(),
+        job_queue_service.clone(),
+        object_storage.clone(),
+        clock.clone(),
+    );
+    let user_import_service = user_import::UserImportService::new(
+        db_pool.clone(),
+        job_queue_service.clone(),
+        object_storage.clone(),
+        clock.clone(),
+    );
+    let erasure_service = erasure::ErasureService::new(db_pool.clone(), job_queue_service.clone(), clock.clone());
+    let preference_service = preferences::PreferenceService::new(db_pool.clone(), clock.clone());
+    let admin_service = admin::AdminService::new(db_pool.clone(), clock.clone());
+    let workflow_service = workflows::WorkflowService::new(db_pool.clone(), clock.clone());
+
+    let job_events = events::channel();
+    let running_jobs = worker::RunningJobs::default();
+    let http_client = http_client::HttpClientService::new(Arc::new(http_client::ReqwestTransport::default()));
+    let password_policy = passwords::PasswordPolicy::from_env(http_client.clone());
+    let password_reset_service = password_reset_service::PasswordResetService::new(
+        db_pool.clone(),
+        job_queue_service.clone(),
+        password_policy.clone(),
+        clock.clone(),
+    );
+    let retention_registry = Arc::new(retention::RetentionRegistry::with_default_policies());
+    let email_previews = mailer::PreviewConfig::from_env();
 
     let app_state = Arc::new(AppState {
         db_pool: db_pool.clone(),
         job_queue_service,
+        email_change_service,
+        password_policy,
+        password_reset_service,
+        webhook_service,
+        post_service,
+        slug_service,
+        feed_service,
+        markdown,
+        data_export_service,
+        user_import_service,
+        erasure_service,
+        preference_service,
+        object_storage,
+        retention_registry: retention_registry.clone(),
+        admin_service,
+        workflow_service,
+        tokens: auth::TokenVerifier::from_env(),
+        email_previews,
+        job_events: job_events.clone(),
+        running_jobs: running_jobs.clone(),
+        clock: clock.clone(),
     });
 
-    // Spawn background worker
-    worker::spawn_worker(db_pool.clone());
+    // Spawn one worker pool per queue
+    let worker_context = worker::WorkerContext {
+        db_pool: db_pool.clone(),
+        job_events,
+        running_jobs,
+        http: http_client,
+        driver: queue_driver::current(),
+        clock: clock.clone(),
+    };
+    for queue in tasks::Queue::ALL {
+        worker::WorkerPool::from_env(queue).start(worker_context.clone());
+    }
+    worker::spawn_reaper(worker_context);
     
     // Setup and start periodic tasks
-    let _scheduler = scheduler::setup_scheduler(db_pool.clone()).await;
+    let _scheduler = scheduler::setup_scheduler(db_pool.clone(), retention_registry, clock).await;
 
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/users/register", post(handlers::register_user))
+        .route("/users/import", post(handlers::import_users))
+        .route("/imports/:id", get(handlers::get_user_import))
+        .route(
+            "/users/:id/avatar",
+            // Room for the multipart framing around a maximum-size file
+            post(handlers::upload_avatar).layer(DefaultBodyLimit::max(avatars::MAX_UPLOAD_BYTES + 64 * 1024)),
+        )
+        .route("/users/:id/avatar/:size", get(handlers::get_avatar))
+        .route("/users/:id/email-change", post(handlers::request_email_change))
+        .route("/users/email-change/confirm", post(handlers::confirm_email_change))
+        .route("/password-reset", post(handlers::request_password_reset))
+        .route("/password-reset/confirm", post(handlers::confirm_password_reset))
+        .route("/users/:id/data-export", post(handlers::request_data_export))
+        .route("/users/:id/data-export/:export_id", get(handlers::get_data_export))
+        .route(
+            "/users/:id/notification-preferences",
+            get(handlers::get_notification_preferences).put(handlers::update_notification_preferences),
+        )
+        .route("/users/:id/erase", delete(handlers::request_erasure))
+        .route("/users/:id/erase/:erasure_id", get(handlers::get_erasure))
+        .route("/downloads/*key", get(handlers::download_object))
+        .route("/admin/overview", get(handlers::admin_overview))
+        .route("/admin/retention", get(handlers::list_retention_runs))
+        .route("/admin/moderation", get(handlers::list_moderation_queue))
+        .route("/admin/posts/:id/approve", post(handlers::approve_post))
+        .route("/admin/posts/:id/reject", post(handlers::reject_post))
+        .route("/admin/retention/run", post(handlers::run_retention))
+        .route("/jobs", get(handlers::list_jobs))
+        .route("/jobs/batch", post(handlers::schedule_job_batch))
         .route("/jobs/:id", get(handlers::get_job_status))
-        .with_state(app_state);
+        .route("/jobs/:id/events", get(handlers::job_events))
+        .route("/jobs/:id/cancel", post(handlers::cancel_job))
+        .route("/schedules", get(handlers::list_schedules))
+        .route("/workflows/:name/runs", post(handlers::start_workflow))
+        .route("/workflow-runs/:id", get(handlers::get_workflow_run))
+        .route("/workflow-runs/:id/resume", post(handlers::resume_workflow_run))
+        .route("/posts", post(handlers::create_post).get(handlers::list_posts))
+        .route("/posts/:id/like", post(handlers::like_post).delete(handlers::unlike_post))
+        .route("/posts/:id/image-from-url", post(handlers::image_from_url))
+        .route("/posts/:id", get(handlers::get_post).patch(handlers::update_post))
+        .route("/posts/by-slug/:slug", get(handlers::get_post_by_slug))
+        .route("/sitemap.xml", get(handlers::sitemap))
+        .route("/feed.rss", get(handlers::rss_feed))
+        .route("/posts/:id/revisions", get(handlers::list_post_revisions))
+        .route("/posts/:id/revisions/:rev/restore", post(handlers::restore_post_revision))
+        .route("/webhooks", post(handlers::create_webhook).get(handlers::list_webhooks))
+        .route(
+            "/webhooks/:id",
+            get(handlers::get_webhook)
+                .put(handlers::update_webhook)
+                .delete(handlers::delete_webhook),
+        )
+        .route("/webhooks/:id/deliveries", get(handlers::list_webhook_deliveries));
+
+    if email_previews.enabled {
+        info!("Email previews enabled at /dev/emails (admin only: {})", email_previews.require_admin);
+        app = app
+            .route("/dev/emails", get(handlers::list_email_previews))
+            .route("/dev/emails/:template", get(handlers::preview_email));
+    }
+    let app = app.with_state(app_state);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
     info!("Server listening on {}", listener.local_addr().unwrap());
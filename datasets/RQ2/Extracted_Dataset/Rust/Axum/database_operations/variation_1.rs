@@ -13,9 +13,16 @@ serde_json = "1"
 sqlx = { version = "0.7", features = ["runtime-tokio", "sqlite", "uuid", "chrono", "macros"] }
 uuid = { version = "1", features = ["v4", "serde"] }
 chrono = { version = "0.4", features = ["serde"] }
+chrono-tz = "0.9"
 thiserror = "1"
 tracing = "0.1"
 tracing-subscriber = { version = "0.3", features = ["env-filter"] }
+async-graphql = { version = "7", features = ["chrono", "dataloader"] }
+futures = "0.3"
+tokio-stream = "0.1"
+arrow = { version = "51", default-features = false }
+parquet = { version = "51", default-features = false, features = ["arrow", "snap"] }
+jsonwebtoken = "9"
 */
 
 // --- Main Application File (main.rs) ---
@@ -25,17 +32,408 @@ use axum::{
     extract::{FromRequest, FromRequestParts, Path, Query, State},
     http::{request::Parts, StatusCode},
     response::{IntoResponse, Response},
-    routing::{get, post},
-    Json, Router,
+    routing::{get, post, put},
+    Extension, Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
 use std::net::SocketAddr;
 use uuid::Uuid;
 
+// --- Authentication ---
+// Tokens come from the identity service that shares `JWT_SECRET`. Here they only
+// pick whose locale and timezone preferences apply, so a missing or bad token
+// just means "no acting user".
+mod auth {
+    use axum::http::{header, HeaderMap};
+    use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+    use serde::Deserialize;
+    use std::sync::OnceLock;
+    use uuid::Uuid;
+
+    #[derive(Deserialize)]
+    struct Claims {
+        sub: Uuid,
+    }
+
+    fn decoding_key() -> &'static DecodingKey {
+        static KEY: OnceLock<DecodingKey> = OnceLock::new();
+        KEY.get_or_init(|| {
+            let secret = std::env::var("JWT_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .expect("JWT_SECRET must be set");
+            DecodingKey::from_secret(secret.as_bytes())
+        })
+    }
+
+    /// Reads the key once at startup so a missing secret fails there, not on a request.
+    pub fn init() {
+        decoding_key();
+    }
+
+    /// The user named by a verified `Authorization: Bearer <jwt>` header.
+    pub fn bearer_user(headers: &HeaderMap) -> Option<Uuid> {
+        let token = headers.get(header::AUTHORIZATION)?.to_str().ok()?.strip_prefix("Bearer ")?;
+        decode::<Claims>(token, decoding_key(), &Validation::new(Algorithm::HS256))
+            .ok()
+            .map(|data| data.claims.sub)
+    }
+}
+
+// --- Localization ---
+mod i18n {
+    use super::auth;
+    use super::errors::Problem;
+    use super::repositories;
+    use axum::{
+        extract::{Request, State},
+        http::header,
+        middleware::Next,
+        response::Response,
+    };
+    use sqlx::SqlitePool;
+    use std::fmt;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Locale {
+        En,
+        Es,
+        Fr,
+    }
+
+    impl Locale {
+        pub const DEFAULT: Locale = Locale::En;
+        pub const SUPPORTED: [Locale; 3] = [Locale::En, Locale::Es, Locale::Fr];
+
+        pub fn tag(self) -> &'static str {
+            match self {
+                Locale::En => "en",
+                Locale::Es => "es",
+                Locale::Fr => "fr",
+            }
+        }
+
+        /// Matches on the primary subtag, so `fr-CA` gets French.
+        pub fn parse(tag: &str) -> Option<Locale> {
+            let primary = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+            Self::SUPPORTED.into_iter().find(|locale| locale.tag() == primary)
+        }
+    }
+
+    /// The best supported match for an `Accept-Language` header, by q-value and then
+    /// by position. Ranges with `q=0` are refusals and never match.
+    pub fn negotiate(accept_language: &str) -> Option<Locale> {
+        let mut ranges: Vec<(f32, &str)> = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let q = match parts.find_map(|param| param.trim().strip_prefix("q=")) {
+                    Some(q) => q.parse::<f32>().ok()?,
+                    None => 1.0,
+                };
+                (q > 0.0 && !tag.is_empty()).then_some((q, tag))
+            })
+            .collect();
+        // Stable, so equal weights keep the client's order
+        ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranges.into_iter().find_map(|(_, tag)| Locale::parse(tag))
+    }
+
+    /// A catalog key plus named arguments for its `{placeholders}`. The key doubles as
+    /// the error `code` clients match on, so it must never be translated or renamed.
+    #[derive(Debug, Clone)]
+    pub struct Message {
+        pub key: &'static str,
+        args: Vec<(&'static str, String)>,
+    }
+
+    impl Message {
+        pub fn new(key: &'static str) -> Self {
+            Self { key, args: Vec::new() }
+        }
+
+        pub fn arg(mut self, name: &'static str, value: impl fmt::Display) -> Self {
+            self.args.push((name, value.to_string()));
+            self
+        }
+
+        /// Falls back to English, then to the bare key, for untranslated entries.
+        pub fn translate(&self, locale: Locale) -> String {
+            let template = lookup(locale, self.key).or_else(|| lookup(Locale::DEFAULT, self.key)).unwrap_or(self.key);
+            self.args
+                .iter()
+                .fold(template.to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+        }
+    }
+
+    impl fmt::Display for Message {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.translate(Locale::DEFAULT))
+        }
+    }
+
+    const EN: &[(&str, &str)] = &[
+        ("title.not_found", "Not found"),
+        ("title.validation", "Invalid request"),
+        ("title.internal", "Internal server error"),
+        ("resource_not_found", "Resource not found"),
+        ("role_not_found", "Role '{role}' not found"),
+        ("invalid_email", "email must be a valid email address"),
+        ("password_too_short", "password must be at least {min} characters"),
+        ("invalid_id", "'{id}' is not a valid id"),
+        ("unsupported_locale", "Locale '{locale}' is not supported; use one of: {supported}"),
+        ("unsupported_timezone", "'{timezone}' is not a known IANA timezone"),
+        ("invalid_tz_mode", "tz must be 'utc' or 'local'"),
+        ("local_tz_requires_user", "tz=local requires a bearer token"),
+        ("invalid_date", "'{value}' is neither a date (YYYY-MM-DD) nor an RFC 3339 timestamp"),
+        ("empty_date_range", "'from' must be earlier than 'to'"),
+        ("database_error", "Database error"),
+        ("internal_error", "An internal error occurred"),
+    ];
+
+    const ES: &[(&str, &str)] = &[
+        ("title.not_found", "No encontrado"),
+        ("title.validation", "Solicitud no válida"),
+        ("title.internal", "Error interno del servidor"),
+        ("resource_not_found", "Recurso no encontrado"),
+        ("role_not_found", "No se encontró el rol '{role}'"),
+        ("invalid_email", "email debe ser una dirección de correo válida"),
+        ("password_too_short", "password debe tener al menos {min} caracteres"),
+        ("invalid_id", "'{id}' no es un identificador válido"),
+        ("unsupported_locale", "El idioma '{locale}' no está disponible; usa uno de: {supported}"),
+        ("unsupported_timezone", "'{timezone}' no es una zona horaria IANA conocida"),
+        ("invalid_tz_mode", "tz debe ser 'utc' o 'local'"),
+        ("local_tz_requires_user", "tz=local requiere un token de portador"),
+        ("invalid_date", "'{value}' no es una fecha (YYYY-MM-DD) ni una marca de tiempo RFC 3339"),
+        ("empty_date_range", "'from' debe ser anterior a 'to'"),
+        ("database_error", "Error de base de datos"),
+        ("internal_error", "Se produjo un error interno"),
+    ];
+
+    const FR: &[(&str, &str)] = &[
+        ("title.not_found", "Introuvable"),
+        ("title.validation", "Requête invalide"),
+        ("title.internal", "Erreur interne du serveur"),
+        ("resource_not_found", "Ressource introuvable"),
+        ("role_not_found", "Rôle '{role}' introuvable"),
+        ("invalid_email", "email doit être une adresse e-mail valide"),
+        ("password_too_short", "password doit contenir au moins {min} caractères"),
+        ("invalid_id", "'{id}' n'est pas un identifiant valide"),
+        ("unsupported_locale", "La langue '{locale}' n'est pas prise en charge ; utilisez : {supported}"),
+        ("unsupported_timezone", "'{timezone}' n'est pas un fuseau horaire IANA connu"),
+        ("invalid_tz_mode", "tz doit valoir 'utc' ou 'local'"),
+        ("local_tz_requires_user", "tz=local nécessite un jeton porteur"),
+        ("invalid_date", "'{value}' n'est ni une date (AAAA-MM-JJ) ni un horodatage RFC 3339"),
+        ("empty_date_range", "'from' doit précéder 'to'"),
+        ("database_error", "Erreur de base de données"),
+        ("internal_error", "Une erreur interne s'est produite"),
+    ];
+
+    fn lookup(locale: Locale, key: &str) -> Option<&'static str> {
+        let catalog = match locale {
+            Locale::En => EN,
+            Locale::Es => ES,
+            Locale::Fr => FR,
+        };
+        catalog.iter().find(|(k, _)| *k == key).map(|(_, text)| *text)
+    }
+
+    /// Re-renders the English problem body an `AppError` produced in the caller's
+    /// language: the bearer-token user's profile locale when set, otherwise
+    /// `Accept-Language`. Successful responses pass through untouched.
+    pub async fn localize_problems(State(pool): State<SqlitePool>, request: Request, next: Next) -> Response {
+        let accept_language = request
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let user_id = auth::bearer_user(request.headers());
+
+        let mut response = next.run(request).await;
+        let Some(problem) = response.extensions_mut().remove::<Problem>() else {
+            return response;
+        };
+        let profile_locale = match user_id {
+            // A failed lookup only costs the preference; the error still gets rendered
+            Some(user_id) => repositories::find_user_locale(&pool, user_id).await.ok().flatten(),
+            None => None,
+        };
+        let locale = profile_locale
+            .as_deref()
+            .and_then(Locale::parse)
+            .or_else(|| accept_language.as_deref().and_then(negotiate))
+            .unwrap_or(Locale::DEFAULT);
+        problem.render(locale)
+    }
+}
+
+// --- Timezone Preferences ---
+// Storage is always UTC. A user's timezone only changes how timestamps are
+// rendered, and how bare dates in range filters are read, when they opt in
+// with `?tz=local`.
+mod timezones {
+    use super::auth;
+    use super::errors::AppError;
+    use super::i18n::Message;
+    use super::repositories;
+    use axum::{
+        async_trait,
+        extract::{FromRequestParts, Query},
+        http::request::Parts,
+        response::{IntoResponse, Response},
+        Json,
+    };
+    use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+    use chrono_tz::Tz;
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use sqlx::SqlitePool;
+
+    /// Keys rewritten by `Zoned`, wherever they appear in the response.
+    const TIMESTAMP_FIELDS: &[&str] = &["created_at"];
+
+    pub fn parse_zone(name: &str) -> Result<Tz, AppError> {
+        name.trim()
+            .parse::<Tz>()
+            .map_err(|_| AppError::Validation(Message::new("unsupported_timezone").arg("timezone", name)))
+    }
+
+    #[derive(Deserialize)]
+    struct TzQuery {
+        tz: Option<String>,
+    }
+
+    /// The zone to render in: `None` (plain UTC) unless the request carries
+    /// `?tz=local`, in which case it is the bearer-token user's preference.
+    pub struct DisplayZone(pub Option<Tz>);
+
+    #[async_trait]
+    impl FromRequestParts<SqlitePool> for DisplayZone {
+        type Rejection = AppError;
+
+        async fn from_request_parts(parts: &mut Parts, pool: &SqlitePool) -> Result<Self, Self::Rejection> {
+            let Query(query) = Query::<TzQuery>::try_from_uri(&parts.uri)
+                .map_err(|_| AppError::Validation(Message::new("invalid_tz_mode")))?;
+            match query.tz.as_deref() {
+                None | Some("utc") => Ok(DisplayZone(None)),
+                Some("local") => {
+                    let user_id = auth::bearer_user(&parts.headers).ok_or_else(|| AppError::Validation(Message::new("local_tz_requires_user")))?;
+                    // No preference set, or one from an older tz database: fall back to UTC
+                    let zone = repositories::find_user_timezone(pool, user_id)
+                        .await?
+                        .and_then(|name| name.parse::<Tz>().ok())
+                        .unwrap_or(Tz::UTC);
+                    Ok(DisplayZone(Some(zone)))
+                }
+                Some(_) => Err(AppError::Validation(Message::new("invalid_tz_mode"))),
+            }
+        }
+    }
+
+    /// JSON response whose `created_at` fields are shifted into `zone` (keeping the
+    /// offset, so the instant is unchanged). With no zone it is plain `Json`.
+    pub struct Zoned<T> {
+        value: T,
+        zone: Option<Tz>,
+    }
+
+    impl<T> Zoned<T> {
+        pub fn new(value: T, zone: Option<Tz>) -> Self {
+            Self { value, zone }
+        }
+    }
+
+    impl<T: Serialize> IntoResponse for Zoned<T> {
+        fn into_response(self) -> Response {
+            let Some(zone) = self.zone else {
+                return Json(self.value).into_response();
+            };
+            match serde_json::to_value(&self.value) {
+                Ok(mut value) => {
+                    localize(&mut value, zone);
+                    Json(value).into_response()
+                }
+                Err(_) => AppError::Internal.into_response(),
+            }
+        }
+    }
+
+    fn localize(value: &mut Value, zone: Tz) {
+        match value {
+            Value::Array(items) => items.iter_mut().for_each(|item| localize(item, zone)),
+            Value::Object(fields) => {
+                for (key, field) in fields.iter_mut() {
+                    match field {
+                        Value::String(raw) if TIMESTAMP_FIELDS.contains(&key.as_str()) => {
+                            if let Ok(instant) = DateTime::parse_from_rfc3339(raw) {
+                                *raw = instant.with_timezone(&zone).to_rfc3339();
+                            }
+                        }
+                        _ => localize(field, zone),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct DateRangeQuery {
+        pub from: Option<String>,
+        pub to: Option<String>,
+    }
+
+    /// Half-open `[start, end)` in UTC, ready to bind.
+    #[derive(Debug, Default)]
+    pub struct UtcRange {
+        pub start: Option<DateTime<Utc>>,
+        pub end: Option<DateTime<Utc>>,
+    }
+
+    /// Bounds may be RFC 3339 timestamps, taken as-is, or bare dates read in `zone`.
+    /// A bare `to` date is inclusive, so `from=2024-03-01&to=2024-03-01` is that whole day.
+    pub fn parse_range(query: &DateRangeQuery, zone: Tz) -> Result<UtcRange, AppError> {
+        let start = query.from.as_deref().map(|raw| parse_bound(raw, zone, false)).transpose()?;
+        let end = query.to.as_deref().map(|raw| parse_bound(raw, zone, true)).transpose()?;
+        if let (Some(start), Some(end)) = (start, end) {
+            if start >= end {
+                return Err(AppError::Validation(Message::new("empty_date_range")));
+            }
+        }
+        Ok(UtcRange { start, end })
+    }
+
+    fn parse_bound(raw: &str, zone: Tz, exclusive_end: bool) -> Result<DateTime<Utc>, AppError> {
+        let invalid = || AppError::Validation(Message::new("invalid_date").arg("value", raw));
+        if let Ok(instant) = DateTime::parse_from_rfc3339(raw) {
+            return Ok(instant.with_timezone(&Utc));
+        }
+        let mut date = NaiveDate::parse_from_str(raw, "%Y-%m-%d").map_err(|_| invalid())?;
+        if exclusive_end {
+            date = date.succ_opt().ok_or_else(invalid)?;
+        }
+        start_of_day(date, zone).ok_or_else(invalid)
+    }
+
+    /// Midnight can fall in a DST gap (e.g. America/Santiago); the day then starts
+    /// at the first valid local time after it.
+    fn start_of_day(date: NaiveDate, zone: Tz) -> Option<DateTime<Utc>> {
+        let midnight = date.and_hms_opt(0, 0, 0)?;
+        (0..=2)
+            .find_map(|hours| zone.from_local_datetime(&(midnight + Duration::hours(hours))).earliest())
+            .map(|local| local.with_timezone(&Utc))
+    }
+}
+
 // --- Error Handling ---
 mod errors {
-    use axum::{http::StatusCode, response::IntoResponse, Json};
+    use super::i18n::{Locale, Message};
+    use axum::{
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+    };
     use serde_json::json;
     use thiserror::Error;
 
@@ -44,36 +442,63 @@ mod errors {
         #[error("Database error: {0}")]
         Sqlx(#[from] sqlx::Error),
         #[error("Item not found: {0}")]
-        NotFound(String),
+        NotFound(Message),
         #[error("Validation error: {0}")]
-        Validation(String),
+        Validation(Message),
         #[error("Internal server error")]
         Internal,
     }
 
+    /// Everything needed to render an error as RFC 7807 problem details in any
+    /// locale. Attached to error responses so `i18n::localize_problems` can redo them.
+    #[derive(Debug, Clone)]
+    pub struct Problem {
+        status: StatusCode,
+        title: &'static str,
+        message: Message,
+    }
+
+    impl Problem {
+        pub fn render(&self, locale: Locale) -> Response {
+            let body = json!({
+                "type": "about:blank",
+                "title": Message::new(self.title).translate(locale),
+                "status": self.status.as_u16(),
+                "detail": self.message.translate(locale),
+                "code": self.message.key,
+            });
+            (
+                self.status,
+                [
+                    (header::CONTENT_TYPE, "application/problem+json"),
+                    (header::CONTENT_LANGUAGE, locale.tag()),
+                    (header::VARY, "Accept-Language"),
+                ],
+                body.to_string(),
+            )
+                .into_response()
+        }
+    }
+
     impl IntoResponse for AppError {
-        fn into_response(self) -> axum::response::Response {
-            let (status, error_message) = match self {
+        fn into_response(self) -> Response {
+            let (status, title, message) = match self {
                 AppError::Sqlx(sqlx::Error::RowNotFound) => {
-                    (StatusCode::NOT_FOUND, "Resource not found".to_string())
+                    (StatusCode::NOT_FOUND, "title.not_found", Message::new("resource_not_found"))
                 }
                 AppError::Sqlx(e) => {
                     tracing::error!("SQLx error: {:?}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Database error".to_string(),
-                    )
+                    (StatusCode::INTERNAL_SERVER_ERROR, "title.internal", Message::new("database_error"))
                 }
-                AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-                AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
-                AppError::Internal => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "An internal error occurred".to_string(),
-                ),
+                AppError::NotFound(message) => (StatusCode::NOT_FOUND, "title.not_found", message),
+                AppError::Validation(message) => (StatusCode::BAD_REQUEST, "title.validation", message),
+                AppError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "title.internal", Message::new("internal_error")),
             };
 
-            let body = Json(json!({ "error": error_message }));
-            (status, body).into_response()
+            let problem = Problem { status, title, message };
+            let mut response = problem.render(Locale::DEFAULT);
+            response.extensions_mut().insert(problem);
+            response
         }
     }
 }
@@ -83,14 +508,14 @@ mod models {
     use super::*;
     use chrono::{DateTime, Utc};
 
-    #[derive(Debug, Serialize, sqlx::Type)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type, async_graphql::Enum)]
     #[sqlx(rename_all = "UPPERCASE")]
     pub enum Role {
         ADMIN,
         USER,
     }
 
-    #[derive(Debug, Serialize, sqlx::Type)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type, async_graphql::Enum)]
     #[sqlx(rename_all = "UPPERCASE")]
     pub enum PostStatus {
         DRAFT,
@@ -108,7 +533,7 @@ mod models {
         pub created_at: DateTime<Utc>,
     }
 
-    #[derive(Debug, Serialize, sqlx::FromRow)]
+    #[derive(Debug, Clone, Serialize, sqlx::FromRow)]
     pub struct Post {
         pub id: Uuid,
         pub user_id: Uuid,
@@ -125,10 +550,165 @@ mod models {
     }
 }
 
+// --- Columnar Exports ---
+// Parquet keeps the types a CSV flattens away: timestamps stay UTC instants and
+// enums are dictionary-encoded strings rather than free text.
+mod exports {
+    use super::errors::AppError;
+    use super::models::{Post, PostStatus, Role, User};
+    use arrow::array::{ArrayRef, BooleanArray, StringArray, StringDictionaryBuilder, TimestampMicrosecondArray};
+    use arrow::datatypes::{DataType, Field, Int8Type, Schema, SchemaRef, TimeUnit};
+    use arrow::error::ArrowError;
+    use arrow::record_batch::RecordBatch;
+    use futures::{Stream, TryStreamExt};
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+    use std::sync::Arc;
+
+    /// Rows per record batch; each one is appended to the current row group.
+    const BATCH_ROWS: usize = 8192;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ExportFormat {
+        Ndjson,
+        Parquet,
+    }
+
+    impl ExportFormat {
+        pub fn content_type(self) -> &'static str {
+            match self {
+                ExportFormat::Ndjson => "application/x-ndjson",
+                ExportFormat::Parquet => "application/vnd.apache.parquet",
+            }
+        }
+    }
+
+    fn enum_type() -> DataType {
+        DataType::Dictionary(Box::new(DataType::Int8), Box::new(DataType::Utf8))
+    }
+
+    fn enum_column<'a>(values: impl Iterator<Item = &'a str>) -> ArrayRef {
+        let mut builder = StringDictionaryBuilder::<Int8Type>::new();
+        values.for_each(|value| builder.append_value(value));
+        Arc::new(builder.finish())
+    }
+
+    fn uuid_column<'a>(values: impl Iterator<Item = &'a uuid::Uuid>) -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(values.map(|id| id.to_string())))
+    }
+
+    pub fn users_schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("email", DataType::Utf8, false),
+            Field::new("role", enum_type(), false),
+            Field::new("is_active", DataType::Boolean, false),
+            Field::new("created_at", DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())), false),
+        ]))
+    }
+
+    fn users_batch(schema: &SchemaRef, users: &[User]) -> Result<RecordBatch, ArrowError> {
+        let role = |user: &User| match user.role {
+            Role::ADMIN => "ADMIN",
+            Role::USER => "USER",
+        };
+        let created_at: Vec<i64> = users.iter().map(|user| user.created_at.timestamp_micros()).collect();
+        RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                uuid_column(users.iter().map(|user| &user.id)),
+                Arc::new(StringArray::from_iter_values(users.iter().map(|user| user.email.as_str()))),
+                enum_column(users.iter().map(role)),
+                Arc::new(BooleanArray::from(users.iter().map(|user| user.is_active).collect::<Vec<_>>())),
+                Arc::new(TimestampMicrosecondArray::from(created_at).with_timezone("UTC")),
+            ],
+        )
+    }
+
+    pub fn posts_schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("user_id", DataType::Utf8, false),
+            Field::new("title", DataType::Utf8, false),
+            Field::new("content", DataType::Utf8, false),
+            Field::new("status", enum_type(), false),
+        ]))
+    }
+
+    fn posts_batch(schema: &SchemaRef, posts: &[Post]) -> Result<RecordBatch, ArrowError> {
+        let status = |post: &Post| match post.status {
+            PostStatus::DRAFT => "DRAFT",
+            PostStatus::PUBLISHED => "PUBLISHED",
+        };
+        RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                uuid_column(posts.iter().map(|post| &post.id)),
+                uuid_column(posts.iter().map(|post| &post.user_id)),
+                Arc::new(StringArray::from_iter_values(posts.iter().map(|post| post.title.as_str()))),
+                Arc::new(StringArray::from_iter_values(posts.iter().map(|post| post.content.as_str()))),
+                enum_column(posts.iter().map(status)),
+            ],
+        )
+    }
+
+    fn encode_failed(e: impl std::fmt::Display) -> AppError {
+        tracing::error!("Parquet export failed: {}", e);
+        AppError::Internal
+    }
+
+    /// Rows are pulled off the cursor a batch at a time, but the finished file is
+    /// buffered: Parquet's footer is only known once every row group is written.
+    async fn write_parquet<T, S>(
+        schema: SchemaRef,
+        mut rows: S,
+        to_batch: fn(&SchemaRef, &[T]) -> Result<RecordBatch, ArrowError>,
+    ) -> Result<Vec<u8>, AppError>
+    where
+        S: Stream<Item = Result<T, sqlx::Error>> + Unpin,
+    {
+        let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+        let mut writer = ArrowWriter::try_new(Vec::new(), schema.clone(), Some(props)).map_err(encode_failed)?;
+        let mut pending = Vec::with_capacity(BATCH_ROWS);
+        loop {
+            let row = rows.try_next().await?;
+            let done = row.is_none();
+            pending.extend(row);
+            if pending.len() == BATCH_ROWS || (done && !pending.is_empty()) {
+                let batch = to_batch(&schema, &pending).map_err(encode_failed)?;
+                writer.write(&batch).map_err(encode_failed)?;
+                pending.clear();
+            }
+            if done {
+                break;
+            }
+        }
+        writer.into_inner().map_err(encode_failed)
+    }
+
+    pub async fn users_parquet<S>(rows: S) -> Result<Vec<u8>, AppError>
+    where
+        S: Stream<Item = Result<User, sqlx::Error>> + Unpin,
+    {
+        write_parquet(users_schema(), rows, users_batch).await
+    }
+
+    pub async fn posts_parquet<S>(rows: S) -> Result<Vec<u8>, AppError>
+    where
+        S: Stream<Item = Result<Post, sqlx::Error>> + Unpin,
+    {
+        write_parquet(posts_schema(), rows, posts_batch).await
+    }
+}
+
 // --- Data Access Layer (Repositories) ---
 mod repositories {
     use super::models::{Post, User};
     use super::errors::AppError;
+    use super::i18n::Message;
+    use super::timezones::UtcRange;
+    use futures::stream::BoxStream;
     use sqlx::SqlitePool;
     use uuid::Uuid;
 
@@ -151,29 +731,159 @@ mod repositories {
             .map_err(AppError::from)
     }
 
-    pub async fn find_users_with_filters(pool: &SqlitePool, filters: UserFilters) -> Result<Vec<User>, AppError> {
-        let mut query = "SELECT id, email, password_hash, role, is_active, created_at FROM users WHERE 1=1".to_string();
-        let mut args = Vec::new();
+    /// One query for the posts of every user in `user_ids`.
+    pub async fn find_posts_by_user_ids(pool: &SqlitePool, user_ids: &[Uuid]) -> Result<Vec<Post>, AppError> {
+        if user_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; user_ids.len()].join(", ");
+        let sql = format!("SELECT id, user_id, title, content, status FROM posts WHERE user_id IN ({})", placeholders);
+        let mut query = sqlx::query_as::<_, Post>(&sql);
+        for user_id in user_ids {
+            query = query.bind(user_id);
+        }
+        query.fetch_all(pool).await.map_err(AppError::from)
+    }
+
+    pub async fn find_users_with_filters(
+        pool: &SqlitePool,
+        filters: UserFilters,
+        created: &UtcRange,
+    ) -> Result<Vec<User>, AppError> {
+        // datetime() normalises both the stored and the bound timestamp formats
+        sqlx::query_as::<_, User>(
+            "SELECT id, email, password_hash, role, is_active, created_at FROM users
+             WHERE (?1 IS NULL OR is_active = ?1)
+               AND (?2 IS NULL OR datetime(created_at) >= datetime(?2))
+               AND (?3 IS NULL OR datetime(created_at) < datetime(?3))",
+        )
+        .bind(filters.is_active)
+        .bind(created.start)
+        .bind(created.end)
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::from)
+    }
+
+    /// Rows are pulled from the cursor on demand rather than collected into a `Vec`.
+    pub fn stream_users(pool: &SqlitePool, is_active: Option<bool>) -> BoxStream<'_, Result<User, sqlx::Error>> {
+        sqlx::query_as::<_, User>(
+            "SELECT id, email, password_hash, role, is_active, created_at FROM users
+             WHERE (?1 IS NULL OR is_active = ?1)
+             ORDER BY created_at, id",
+        )
+        .bind(is_active)
+        .fetch(pool)
+    }
+
+    pub fn stream_posts(pool: &SqlitePool) -> BoxStream<'_, Result<Post, sqlx::Error>> {
+        sqlx::query_as::<_, Post>("SELECT id, user_id, title, content, status FROM posts ORDER BY user_id, id").fetch(pool)
+    }
+
+    pub async fn find_users_page(
+        pool: &SqlitePool,
+        is_active: Option<bool>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
+        sqlx::query_as::<_, User>(
+            "SELECT id, email, password_hash, role, is_active, created_at FROM users
+             WHERE (?1 IS NULL OR is_active = ?1)
+             ORDER BY created_at, id
+             LIMIT ?2 OFFSET ?3",
+        )
+        .bind(is_active)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::from)
+    }
+
+    pub async fn count_users(pool: &SqlitePool, is_active: Option<bool>) -> Result<i64, AppError> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE (?1 IS NULL OR is_active = ?1)")
+            .bind(is_active)
+            .fetch_one(pool)
+            .await
+            .map_err(AppError::from)
+    }
 
-        if let Some(is_active) = filters.is_active {
-            query.push_str(" AND is_active = ?");
-            args.push(is_active.to_string());
+    pub async fn find_role_names_for_user(pool: &SqlitePool, user_id: Uuid) -> Result<Vec<String>, AppError> {
+        sqlx::query_scalar(
+            "SELECT r.name FROM roles r JOIN user_roles ur ON ur.role_id = r.id WHERE ur.user_id = ? ORDER BY r.name",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::from)
+    }
+
+    pub async fn insert_user(pool: &SqlitePool, email: &str, password_hash: &str) -> Result<Uuid, AppError> {
+        let user_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO users (id, email, password_hash, role, is_active) VALUES (?, ?, ?, 'USER', true)")
+            .bind(user_id)
+            .bind(email)
+            .bind(password_hash)
+            .execute(pool)
+            .await?;
+        Ok(user_id)
+    }
+
+    pub async fn find_user_locale(pool: &SqlitePool, user_id: Uuid) -> Result<Option<String>, AppError> {
+        let locale: Option<Option<String>> = sqlx::query_scalar("SELECT locale FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(locale.flatten())
+    }
+
+    pub async fn update_user_locale(pool: &SqlitePool, user_id: Uuid, locale: &str) -> Result<(), AppError> {
+        let updated = sqlx::query("UPDATE users SET locale = ? WHERE id = ?")
+            .bind(locale)
+            .bind(user_id)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        if updated == 0 {
+            return Err(AppError::Sqlx(sqlx::Error::RowNotFound));
         }
-        
-        // This is a simplified example. A real implementation would use a proper query builder.
-        // For now, we'll just handle the one filter.
-        if filters.is_active.is_some() {
-            sqlx::query_as::<_, User>(query.as_str())
-                .bind(filters.is_active.unwrap())
-                .fetch_all(pool)
-                .await
-                .map_err(AppError::from)
-        } else {
-            sqlx::query_as::<_, User>(query.as_str())
-                .fetch_all(pool)
-                .await
-                .map_err(AppError::from)
+        Ok(())
+    }
+
+    pub async fn find_user_timezone(pool: &SqlitePool, user_id: Uuid) -> Result<Option<String>, AppError> {
+        let timezone: Option<Option<String>> = sqlx::query_scalar("SELECT timezone FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(timezone.flatten())
+    }
+
+    pub async fn update_user_timezone(pool: &SqlitePool, user_id: Uuid, timezone: &str) -> Result<(), AppError> {
+        let updated = sqlx::query("UPDATE users SET timezone = ? WHERE id = ?")
+            .bind(timezone)
+            .bind(user_id)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        if updated == 0 {
+            return Err(AppError::Sqlx(sqlx::Error::RowNotFound));
         }
+        Ok(())
+    }
+
+    pub async fn assign_role(pool: &SqlitePool, user_id: Uuid, role_name: &str) -> Result<(), AppError> {
+        let role_id: Option<Uuid> = sqlx::query_scalar("SELECT id FROM roles WHERE name = ?")
+            .bind(role_name)
+            .fetch_optional(pool)
+            .await?;
+        let role_id = role_id.ok_or_else(|| AppError::NotFound(Message::new("role_not_found").arg("role", role_name)))?;
+
+        sqlx::query("INSERT OR IGNORE INTO user_roles (user_id, role_id) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(role_id)
+            .execute(pool)
+            .await?;
+        Ok(())
     }
 
     // Transactional operation example
@@ -210,19 +920,97 @@ mod repositories {
 // --- Business Logic Layer (Services) ---
 mod services {
     use super::errors::AppError;
-    use super::models::{User, UserWithPosts};
+    use super::i18n::{Locale, Message};
+    use super::timezones::{self, UtcRange};
+    use super::models::{Post, Role, User, UserWithPosts};
     use super::repositories;
     use sqlx::SqlitePool;
     use uuid::Uuid;
 
+    pub async fn fetch_user(pool: &SqlitePool, id: Uuid) -> Result<User, AppError> {
+        repositories::find_user_by_id(pool, id).await
+    }
+
+    pub async fn fetch_posts_for_users(pool: &SqlitePool, user_ids: &[Uuid]) -> Result<Vec<Post>, AppError> {
+        repositories::find_posts_by_user_ids(pool, user_ids).await
+    }
+
+    pub async fn fetch_user_role_names(pool: &SqlitePool, user_id: Uuid) -> Result<Vec<String>, AppError> {
+        repositories::find_role_names_for_user(pool, user_id).await
+    }
+
+    /// Either the `role` column or an `ADMIN` grant through `user_roles` makes an admin.
+    pub async fn is_admin(pool: &SqlitePool, user_id: Uuid) -> Result<bool, AppError> {
+        let user = match repositories::find_user_by_id(pool, user_id).await {
+            Ok(user) => user,
+            Err(AppError::Sqlx(sqlx::Error::RowNotFound)) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if user.role == Role::ADMIN {
+            return Ok(true);
+        }
+        Ok(repositories::find_role_names_for_user(pool, user_id).await?.iter().any(|name| name == "ADMIN"))
+    }
+
+    pub async fn count_users(pool: &SqlitePool, is_active: Option<bool>) -> Result<usize, AppError> {
+        Ok(repositories::count_users(pool, is_active).await? as usize)
+    }
+
+    pub async fn fetch_users_page(
+        pool: &SqlitePool,
+        is_active: Option<bool>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<User>, AppError> {
+        repositories::find_users_page(pool, is_active, offset as i64, limit as i64).await
+    }
+
+    pub async fn create_user(pool: &SqlitePool, email: &str, password: &str) -> Result<User, AppError> {
+        if !email.contains('@') {
+            return Err(AppError::Validation(Message::new("invalid_email")));
+        }
+        if password.len() < 8 {
+            return Err(AppError::Validation(Message::new("password_too_short").arg("min", 8)));
+        }
+        // In a real app, you'd hash the password here
+        let password_hash = format!("hashed_{}", password);
+        let user_id = repositories::insert_user(pool, email, &password_hash).await?;
+        repositories::find_user_by_id(pool, user_id).await
+    }
+
+    /// Stored as the bare tag, e.g. `fr` for `fr-CA`.
+    pub async fn set_user_locale(pool: &SqlitePool, user_id: Uuid, tag: &str) -> Result<(), AppError> {
+        let locale = Locale::parse(tag).ok_or_else(|| {
+            let supported: Vec<&str> = Locale::SUPPORTED.iter().map(|locale| locale.tag()).collect();
+            AppError::Validation(Message::new("unsupported_locale").arg("locale", tag).arg("supported", supported.join(", ")))
+        })?;
+        repositories::update_user_locale(pool, user_id, locale.tag()).await
+    }
+
+    /// Stored as the canonical IANA name; timestamps themselves always stay UTC.
+    pub async fn set_user_timezone(pool: &SqlitePool, user_id: Uuid, name: &str) -> Result<(), AppError> {
+        let zone = timezones::parse_zone(name)?;
+        repositories::update_user_timezone(pool, user_id, zone.name()).await
+    }
+
+    pub async fn assign_role_to_user(pool: &SqlitePool, user_id: Uuid, role_name: &str) -> Result<(), AppError> {
+        // Surface a NotFound for unknown users instead of a foreign key failure
+        repositories::find_user_by_id(pool, user_id).await?;
+        repositories::assign_role(pool, user_id, role_name).await
+    }
+
     pub async fn fetch_user_with_posts(pool: &SqlitePool, id: Uuid) -> Result<UserWithPosts, AppError> {
         let user = repositories::find_user_by_id(pool, id).await?;
         let posts = repositories::find_posts_by_user_id(pool, user.id).await?;
         Ok(UserWithPosts { user, posts })
     }
 
-    pub async fn fetch_filtered_users(pool: &SqlitePool, filters: repositories::UserFilters) -> Result<Vec<User>, AppError> {
-        repositories::find_users_with_filters(pool, filters).await
+    pub async fn fetch_filtered_users(
+        pool: &SqlitePool,
+        filters: repositories::UserFilters,
+        created: &UtcRange,
+    ) -> Result<Vec<User>, AppError> {
+        repositories::find_users_with_filters(pool, filters, created).await
     }
 
     pub async fn create_user_with_initial_post(
@@ -240,18 +1028,41 @@ mod services {
 
 // --- API Layer (Handlers) ---
 mod handlers {
+    use super::auth;
     use super::errors::AppError;
+    use super::graphql::{self, AppSchema};
     use super::models::{User, UserWithPosts};
     use super::repositories::UserFilters;
+    use super::repositories;
     use super::services;
+    use super::exports::{self, ExportFormat};
+    use super::timezones::{self, DateRangeQuery, DisplayZone, Zoned};
     use axum::{
+        body::Body,
         extract::{Path, Query, State},
-        Json,
+        http::{header, HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        Extension, Json,
     };
+    use chrono_tz::Tz;
+    use futures::TryStreamExt;
     use serde::Deserialize;
     use sqlx::SqlitePool;
+    use tokio_stream::wrappers::ReceiverStream;
     use uuid::Uuid;
 
+    const EXPORT_BUFFER_ROWS: usize = 256;
+
+    #[derive(Deserialize)]
+    pub struct SetLocalePayload {
+        pub locale: String,
+    }
+
+    #[derive(Deserialize)]
+    pub struct SetTimezonePayload {
+        pub timezone: String,
+    }
+
     #[derive(Deserialize)]
     pub struct CreateUserAndPostPayload {
         pub email: String,
@@ -263,17 +1074,103 @@ mod handlers {
     pub async fn get_user(
         State(pool): State<SqlitePool>,
         Path(id): Path<Uuid>,
-    ) -> Result<Json<UserWithPosts>, AppError> {
+        DisplayZone(zone): DisplayZone,
+    ) -> Result<Zoned<UserWithPosts>, AppError> {
         let user_with_posts = services::fetch_user_with_posts(&pool, id).await?;
-        Ok(Json(user_with_posts))
+        Ok(Zoned::new(user_with_posts, zone))
+    }
+
+    pub async fn set_user_locale(
+        State(pool): State<SqlitePool>,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<SetLocalePayload>,
+    ) -> Result<StatusCode, AppError> {
+        services::set_user_locale(&pool, id, &payload.locale).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn set_user_timezone(
+        State(pool): State<SqlitePool>,
+        Path(id): Path<Uuid>,
+        Json(payload): Json<SetTimezonePayload>,
+    ) -> Result<StatusCode, AppError> {
+        services::set_user_timezone(&pool, id, &payload.timezone).await?;
+        Ok(StatusCode::NO_CONTENT)
     }
 
+    /// `from`/`to` bound `created_at`; bare dates are read in the same zone the
+    /// response is rendered in, so `?tz=local&from=2024-03-01` means local midnight.
     pub async fn list_users(
         State(pool): State<SqlitePool>,
         Query(filters): Query<UserFilters>,
-    ) -> Result<Json<Vec<User>>, AppError> {
-        let users = services::fetch_filtered_users(&pool, filters).await?;
-        Ok(Json(users))
+        Query(range): Query<DateRangeQuery>,
+        DisplayZone(zone): DisplayZone,
+    ) -> Result<Zoned<Vec<User>>, AppError> {
+        let created = timezones::parse_range(&range, zone.unwrap_or(Tz::UTC))?;
+        let users = services::fetch_filtered_users(&pool, filters, &created).await?;
+        Ok(Zoned::new(users, zone))
+    }
+
+    /// Streams users as newline-delimited JSON with chunked transfer encoding, one line
+    /// per row as it comes off the cursor. An error mid-stream truncates the body.
+    pub async fn export_users_ndjson(
+        State(pool): State<SqlitePool>,
+        Query(filters): Query<UserFilters>,
+    ) -> Response {
+        let (tx, rx) = tokio::sync::mpsc::channel::<Result<Vec<u8>, AppError>>(EXPORT_BUFFER_ROWS);
+
+        tokio::spawn(async move {
+            let mut rows = repositories::stream_users(&pool, filters.is_active);
+            loop {
+                let chunk = match rows.try_next().await {
+                    Ok(Some(user)) => serde_json::to_vec(&user)
+                        .map(|mut line| {
+                            line.push(b'\n');
+                            line
+                        })
+                        .map_err(|_| AppError::Internal),
+                    Ok(None) => return,
+                    Err(e) => {
+                        tracing::error!("User export aborted: {}", e);
+                        Err(AppError::from(e))
+                    }
+                };
+                let failed = chunk.is_err();
+                if tx.send(chunk).await.is_err() || failed {
+                    return; // client went away, or the cursor failed
+                }
+            }
+        });
+
+        (
+            [(header::CONTENT_TYPE, ExportFormat::Ndjson.content_type())],
+            Body::from_stream(ReceiverStream::new(rx)),
+        )
+            .into_response()
+    }
+
+    pub async fn export_users_parquet(
+        State(pool): State<SqlitePool>,
+        Query(filters): Query<UserFilters>,
+    ) -> Result<Response, AppError> {
+        let file = exports::users_parquet(repositories::stream_users(&pool, filters.is_active)).await?;
+        Ok(parquet_attachment("users.parquet", file))
+    }
+
+    pub async fn export_posts_parquet(State(pool): State<SqlitePool>) -> Result<Response, AppError> {
+        let file = exports::posts_parquet(repositories::stream_posts(&pool)).await?;
+        Ok(parquet_attachment("posts.parquet", file))
+    }
+
+    fn parquet_attachment(filename: &str, file: Vec<u8>) -> Response {
+        (
+            [
+                (header::CONTENT_TYPE, ExportFormat::Parquet.content_type().to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+            ],
+            file,
+        )
+            .into_response()
     }
 
     pub async fn create_user_with_post(
@@ -290,6 +1187,267 @@ mod handlers {
         .await?;
         Ok(Json(ids))
     }
+
+    /// Runs a GraphQL request, or a batch of them, on behalf of the bearer-token
+    /// user, if any.
+    pub async fn graphql(
+        Extension(schema): Extension<AppSchema>,
+        headers: HeaderMap,
+        Json(request): Json<async_graphql::BatchRequest>,
+    ) -> Json<async_graphql::BatchResponse> {
+        let viewer = graphql::Viewer(auth::bearer_user(&headers));
+        Json(schema.execute_batch(request.data(viewer)).await)
+    }
+
+    pub async fn graphiql() -> axum::response::Html<String> {
+        axum::response::Html(async_graphql::http::GraphiQLSource::build().endpoint("/graphql").finish())
+    }
+}
+
+// --- GraphQL API Layer ---
+// Resolvers delegate to the same `services` functions as the REST handlers so
+// business rules live in one place.
+mod graphql {
+    use super::errors::AppError;
+    use super::i18n::Message;
+    use super::models::{Post, PostStatus, Role, User};
+    use super::services;
+    use async_graphql::{
+        connection::{query, Connection, Edge},
+        dataloader::{DataLoader, Loader},
+        Context, EmptySubscription, Error, ErrorExtensions, Object, Result, Schema, ID,
+    };
+    use chrono::{DateTime, Utc};
+    use sqlx::SqlitePool;
+    use std::collections::HashMap;
+    use std::future::Future;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    pub type AppSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+    const DEFAULT_PAGE_SIZE: usize = 20;
+    const MAX_PAGE_SIZE: usize = 100;
+
+    pub fn build_schema(pool: SqlitePool) -> AppSchema {
+        Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+            .data(DataLoader::new(PostsByUser { pool: pool.clone() }, tokio::spawn))
+            .data(pool)
+            .finish()
+    }
+
+    /// The bearer-token user a request runs as; attached per request by `handlers::graphql`.
+    #[derive(Clone, Copy)]
+    pub struct Viewer(pub Option<Uuid>);
+
+    async fn require_admin(ctx: &Context<'_>) -> Result<()> {
+        let pool = ctx.data::<SqlitePool>()?;
+        let Some(user_id) = ctx.data_opt::<Viewer>().and_then(|viewer| viewer.0) else {
+            return Err(Error::new("Authentication required").extend_with(|_, e| e.set("code", "UNAUTHENTICATED")));
+        };
+        if !services::is_admin(pool, user_id).await? {
+            return Err(Error::new("Admin role required").extend_with(|_, e| e.set("code", "FORBIDDEN")));
+        }
+        Ok(())
+    }
+
+    /// Batches `User.posts` so a page of users costs one posts query, not one per user.
+    pub struct PostsByUser {
+        pool: SqlitePool,
+    }
+
+    impl Loader<Uuid> for PostsByUser {
+        type Value = Vec<Post>;
+        type Error = Arc<AppError>;
+
+        async fn load(&self, user_ids: &[Uuid]) -> std::result::Result<HashMap<Uuid, Vec<Post>>, Self::Error> {
+            let posts = services::fetch_posts_for_users(&self.pool, user_ids).await.map_err(Arc::new)?;
+            let mut by_user: HashMap<Uuid, Vec<Post>> = HashMap::new();
+            for post in posts {
+                by_user.entry(post.user_id).or_default().push(post);
+            }
+            Ok(by_user)
+        }
+    }
+
+    fn parse_id(id: &ID) -> Result<Uuid> {
+        Uuid::parse_str(id.as_str()).map_err(|_| AppError::Validation(Message::new("invalid_id").arg("id", id.as_str())).into())
+    }
+
+    /// Relay-style pagination over an offset-addressable collection. Cursors are opaque
+    /// offsets; `fetch(offset, limit)` loads one window of `total` items.
+    async fn paginate<T, N, F, Fut>(
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+        total: usize,
+        fetch: F,
+    ) -> Result<Connection<usize, N>>
+    where
+        N: async_graphql::OutputType,
+        F: FnOnce(usize, usize) -> Fut,
+        Fut: Future<Output = std::result::Result<Vec<T>, AppError>>,
+        T: Into<N>,
+    {
+        query(after, before, first, last, |after, before, first: Option<usize>, last: Option<usize>| async move {
+            let mut start = after.map(|a: usize| a + 1).unwrap_or(0);
+            let mut end = before.unwrap_or(total).min(total);
+            if first.is_none() && last.is_none() {
+                end = end.min(start + DEFAULT_PAGE_SIZE);
+            }
+            if let Some(first) = first {
+                end = end.min(start + first.min(MAX_PAGE_SIZE));
+            }
+            if let Some(last) = last {
+                start = start.max(end.saturating_sub(last.min(MAX_PAGE_SIZE)));
+            }
+
+            let items = if start < end { fetch(start, end - start).await? } else { Vec::new() };
+            let mut connection = Connection::new(start > 0, end < total);
+            connection
+                .edges
+                .extend(items.into_iter().enumerate().map(|(i, item)| Edge::new(start + i, item.into())));
+            Ok::<_, async_graphql::Error>(connection)
+        })
+        .await
+    }
+
+    pub struct UserNode(User);
+
+    impl From<User> for UserNode {
+        fn from(user: User) -> Self {
+            Self(user)
+        }
+    }
+
+    #[Object(name = "User")]
+    impl UserNode {
+        async fn id(&self) -> ID {
+            ID(self.0.id.to_string())
+        }
+
+        async fn email(&self) -> &str {
+            &self.0.email
+        }
+
+        async fn role(&self) -> Role {
+            self.0.role
+        }
+
+        async fn is_active(&self) -> bool {
+            self.0.is_active
+        }
+
+        async fn created_at(&self) -> DateTime<Utc> {
+            self.0.created_at
+        }
+
+        /// Names of the roles assigned through `assignRole`.
+        async fn roles(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+            let pool = ctx.data::<SqlitePool>()?;
+            Ok(services::fetch_user_role_names(pool, self.0.id).await?)
+        }
+
+        async fn posts(
+            &self,
+            ctx: &Context<'_>,
+            after: Option<String>,
+            before: Option<String>,
+            first: Option<i32>,
+            last: Option<i32>,
+        ) -> Result<Connection<usize, PostNode>> {
+            let loader = ctx.data::<DataLoader<PostsByUser>>()?;
+            let posts = loader.load_one(self.0.id).await?.unwrap_or_default();
+            let total = posts.len();
+            paginate(after, before, first, last, total, |offset, limit| async move {
+                Ok::<Vec<Post>, AppError>(posts.into_iter().skip(offset).take(limit).collect())
+            })
+            .await
+        }
+    }
+
+    pub struct PostNode(Post);
+
+    impl From<Post> for PostNode {
+        fn from(post: Post) -> Self {
+            Self(post)
+        }
+    }
+
+    #[Object(name = "Post")]
+    impl PostNode {
+        async fn id(&self) -> ID {
+            ID(self.0.id.to_string())
+        }
+
+        async fn title(&self) -> &str {
+            &self.0.title
+        }
+
+        async fn content(&self) -> &str {
+            &self.0.content
+        }
+
+        async fn status(&self) -> PostStatus {
+            self.0.status
+        }
+
+        async fn author(&self, ctx: &Context<'_>) -> Result<UserNode> {
+            let pool = ctx.data::<SqlitePool>()?;
+            Ok(services::fetch_user(pool, self.0.user_id).await?.into())
+        }
+    }
+
+    pub struct QueryRoot;
+
+    #[Object]
+    impl QueryRoot {
+        async fn user(&self, ctx: &Context<'_>, id: ID) -> Result<Option<UserNode>> {
+            let pool = ctx.data::<SqlitePool>()?;
+            match services::fetch_user(pool, parse_id(&id)?).await {
+                Ok(user) => Ok(Some(user.into())),
+                Err(AppError::Sqlx(sqlx::Error::RowNotFound)) => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        }
+
+        async fn users(
+            &self,
+            ctx: &Context<'_>,
+            is_active: Option<bool>,
+            after: Option<String>,
+            before: Option<String>,
+            first: Option<i32>,
+            last: Option<i32>,
+        ) -> Result<Connection<usize, UserNode>> {
+            let pool = ctx.data::<SqlitePool>()?;
+            let total = services::count_users(pool, is_active).await?;
+            paginate(after, before, first, last, total, |offset, limit| {
+                services::fetch_users_page(pool, is_active, offset, limit)
+            })
+            .await
+        }
+    }
+
+    pub struct MutationRoot;
+
+    #[Object]
+    impl MutationRoot {
+        async fn create_user(&self, ctx: &Context<'_>, email: String, password: String) -> Result<UserNode> {
+            let pool = ctx.data::<SqlitePool>()?;
+            Ok(services::create_user(pool, &email, &password).await?.into())
+        }
+
+        /// Admin only. Idempotent: assigning a role the user already has is a no-op.
+        async fn assign_role(&self, ctx: &Context<'_>, user_id: ID, role_name: String) -> Result<UserNode> {
+            require_admin(ctx).await?;
+            let pool = ctx.data::<SqlitePool>()?;
+            let user_id = parse_id(&user_id)?;
+            services::assign_role_to_user(pool, user_id, &role_name).await?;
+            Ok(services::fetch_user(pool, user_id).await?.into())
+        }
+    }
 }
 
 // --- Application State ---
@@ -310,7 +1468,9 @@ async fn setup_database() -> SqlitePool {
             password_hash TEXT NOT NULL,
             role TEXT NOT NULL,
             is_active BOOLEAN NOT NULL DEFAULT TRUE,
-            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            locale TEXT,
+            timezone TEXT
         );",
     )
     .execute(&pool)
@@ -349,6 +1509,14 @@ async fn setup_database() -> SqlitePool {
         );"
     ).execute(&pool).await.unwrap();
 
+    // Seed the assignable roles
+    sqlx::query("INSERT INTO roles (id, name) VALUES (?, 'ADMIN'), (?, 'USER')")
+        .bind(Uuid::new_v4())
+        .bind(Uuid::new_v4())
+        .execute(&pool)
+        .await
+        .expect("Failed to seed roles");
+
     pool
 }
 
@@ -356,13 +1524,23 @@ async fn setup_database() -> SqlitePool {
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
+    auth::init();
 
     let db_pool = setup_database().await;
+    let schema = graphql::build_schema(db_pool.clone());
 
     let app = Router::new()
         .route("/users", get(handlers::list_users))
         .route("/users/with_post", post(handlers::create_user_with_post))
+        .route("/users/export.ndjson", get(handlers::export_users_ndjson))
+        .route("/users/export.parquet", get(handlers::export_users_parquet))
+        .route("/posts/export.parquet", get(handlers::export_posts_parquet))
         .route("/users/:id", get(handlers::get_user))
+        .route("/users/:id/locale", put(handlers::set_user_locale))
+        .route("/users/:id/timezone", put(handlers::set_user_timezone))
+        .route("/graphql", get(handlers::graphiql).post(handlers::graphql))
+        .layer(Extension(schema))
+        .layer(axum::middleware::from_fn_with_state(db_pool.clone(), i18n::localize_problems))
         .with_state(db_pool);
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
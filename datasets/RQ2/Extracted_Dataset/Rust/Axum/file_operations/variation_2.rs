@@ -17,6 +17,9 @@ axum-macros = "0.4"
 thiserror = "1.0"
 bytes = "1"
 tokio-util = { version = "0.7", features = ["io"] }
+hmac = "0.12"
+sha2 = "0.10"
+hex = "0.4"
 */
 
 use axum::{
@@ -56,7 +59,7 @@ mod models {
         pub created_at: DateTime<Utc>,
     }
 
-    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
     pub enum PostStatus { DRAFT, PUBLISHED }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,10 +69,12 @@ mod models {
         pub title: String,
         pub content: String,
         pub status: PostStatus,
+        pub created_at: DateTime<Utc>,
     }
 }
 
 mod errors {
+    use super::signing::SignatureError;
     use axum::{http::StatusCode, response::{IntoResponse, Response}, Json};
     use serde_json::json;
     use thiserror::Error;
@@ -88,6 +93,8 @@ mod errors {
         Validation(String),
         #[error("Not found: {0}")]
         NotFound(String),
+        #[error("Forbidden: {0}")]
+        Forbidden(String),
         #[error("Multipart error: {0}")]
         Multipart(#[from] axum::extract::multipart::MultipartError),
     }
@@ -97,18 +104,112 @@ mod errors {
             let (status, error_message) = match self {
                 ServiceError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
                 ServiceError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
+                ServiceError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
                 _ => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             };
             let body = Json(json!({ "error": error_message }));
             (status, body).into_response()
         }
     }
+
+    impl From<SignatureError> for ServiceError {
+        fn from(err: SignatureError) -> Self {
+            let msg = match err {
+                SignatureError::Expired => "Image link has expired",
+                SignatureError::Missing => "Image link is not signed",
+                SignatureError::Malformed | SignatureError::Invalid => "Image link signature is invalid",
+            };
+            ServiceError::Forbidden(msg.to_string())
+        }
+    }
+}
+
+mod signing {
+    use chrono::Utc;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use std::{sync::Arc, time::Duration};
+    use uuid::Uuid;
+
+    type HmacSha256 = Hmac<Sha256>;
+
+    const DEFAULT_TTL: Duration = Duration::from_secs(15 * 60);
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum SignatureError {
+        Missing,
+        Malformed,
+        Invalid,
+        Expired,
+    }
+
+    /// Issues expiring links of the form `<path>?expires=<unix secs>&sig=<hex>`.
+    /// The HMAC-SHA256 covers both the path and the expiry, so neither can be
+    /// edited without invalidating the link.
+    #[derive(Clone)]
+    pub struct SignedUrlService {
+        key: Arc<[u8]>,
+        ttl: Duration,
+    }
+
+    impl SignedUrlService {
+        pub fn new(key: impl Into<Vec<u8>>, ttl: Duration) -> Self {
+            Self { key: Arc::from(key.into()), ttl }
+        }
+
+        /// `MEDIA_SIGNING_KEY` and `MEDIA_URL_TTL_SECS`. Without a key, one is generated
+        /// per process, so links stop working after a restart.
+        pub fn from_env() -> Self {
+            let ttl = std::env::var("MEDIA_URL_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_TTL);
+            match std::env::var("MEDIA_SIGNING_KEY") {
+                Ok(key) if !key.is_empty() => Self::new(key, ttl),
+                _ => {
+                    tracing::warn!("MEDIA_SIGNING_KEY not set; signed image URLs will not survive a restart");
+                    let key: Vec<u8> = (0..2).flat_map(|_| Uuid::new_v4().into_bytes()).collect();
+                    Self::new(key, ttl)
+                }
+            }
+        }
+
+        pub fn sign(&self, path: &str) -> String {
+            let expires = Utc::now().timestamp() + self.ttl.as_secs() as i64;
+            let sig = hex::encode(self.mac(path, expires).finalize().into_bytes());
+            format!("{}?expires={}&sig={}", path, expires, sig)
+        }
+
+        /// Returns the seconds left before expiry. The signature is checked (in
+        /// constant time) first, so a tampered link never reports as merely expired.
+        pub fn verify(&self, path: &str, expires: Option<i64>, sig: Option<&str>) -> Result<i64, SignatureError> {
+            let (expires, sig) = expires.zip(sig).ok_or(SignatureError::Missing)?;
+            let sig = hex::decode(sig).map_err(|_| SignatureError::Malformed)?;
+            self.mac(path, expires)
+                .verify_slice(&sig)
+                .map_err(|_| SignatureError::Invalid)?;
+            let remaining = expires - Utc::now().timestamp();
+            if remaining <= 0 {
+                return Err(SignatureError::Expired);
+            }
+            Ok(remaining)
+        }
+
+        fn mac(&self, path: &str, expires: i64) -> HmacSha256 {
+            let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+            mac.update(path.as_bytes());
+            mac.update(b"\n");
+            mac.update(expires.to_string().as_bytes());
+            mac
+        }
+    }
 }
 
 mod services {
-    use super::{errors::ServiceError, models::{Post, User, UserRole}};
+    use super::{errors::ServiceError, models::{Post, PostStatus, User, UserRole}, signing::SignedUrlService};
     use bytes::Bytes;
-    use chrono::Utc;
+    use chrono::{DateTime, NaiveDate, Utc};
     use serde::Deserialize;
     use std::{
         collections::HashMap,
@@ -160,15 +261,174 @@ mod services {
 
     type PostDbMock = Arc<Mutex<HashMap<Uuid, Post>>>;
 
+    /// Private storage only serves images through signed, expiring URLs.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum StorageVisibility {
+        Public,
+        Private,
+    }
+
+    impl StorageVisibility {
+        /// `MEDIA_STORAGE_VISIBILITY=public` opts out; anything else is private.
+        pub fn from_env() -> Self {
+            match std::env::var("MEDIA_STORAGE_VISIBILITY").as_deref() {
+                Ok("public") => StorageVisibility::Public,
+                _ => StorageVisibility::Private,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PostColumn { Id, UserId, Title, Content, Status, CreatedAt }
+
+    impl PostColumn {
+        pub const ALL: [PostColumn; 6] = [
+            PostColumn::Id,
+            PostColumn::UserId,
+            PostColumn::Title,
+            PostColumn::Content,
+            PostColumn::Status,
+            PostColumn::CreatedAt,
+        ];
+        /// The projection used when `columns` is omitted, matching the original export.
+        pub const DEFAULT: [PostColumn; 4] = [PostColumn::Id, PostColumn::UserId, PostColumn::Title, PostColumn::Status];
+
+        pub fn name(self) -> &'static str {
+            match self {
+                PostColumn::Id => "id",
+                PostColumn::UserId => "user_id",
+                PostColumn::Title => "title",
+                PostColumn::Content => "content",
+                PostColumn::Status => "status",
+                PostColumn::CreatedAt => "created_at",
+            }
+        }
+
+        fn value(self, post: &Post) -> String {
+            match self {
+                PostColumn::Id => post.id.to_string(),
+                PostColumn::UserId => post.user_id.to_string(),
+                PostColumn::Title => post.title.clone(),
+                PostColumn::Content => post.content.clone(),
+                PostColumn::Status => format!("{:?}", post.status),
+                PostColumn::CreatedAt => post.created_at.to_rfc3339(),
+            }
+        }
+    }
+
+    /// Raw `?columns=id,title&status=PUBLISHED&from=2024-01-01&to=...` parameters.
+    #[derive(Debug, Default, Deserialize)]
+    pub struct CsvExportQuery {
+        pub columns: Option<String>,
+        pub status: Option<String>,
+        pub from: Option<String>,
+        pub to: Option<String>,
+    }
+
+    /// A validated export: which columns to emit, in order, and which posts to include.
+    /// `from` is inclusive and `to` exclusive, both on `created_at`.
+    #[derive(Debug, Clone)]
+    pub struct CsvExport {
+        columns: Vec<PostColumn>,
+        status: Option<PostStatus>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    }
+
+    impl CsvExport {
+        pub fn parse(query: &CsvExportQuery) -> Result<Self, ServiceError> {
+            let columns = match query.columns.as_deref() {
+                None => PostColumn::DEFAULT.to_vec(),
+                Some(raw) => parse_columns(raw)?,
+            };
+            let status = match query.status.as_deref().map(str::to_ascii_uppercase).as_deref() {
+                None => None,
+                Some("DRAFT") => Some(PostStatus::DRAFT),
+                Some("PUBLISHED") => Some(PostStatus::PUBLISHED),
+                Some(other) => {
+                    return Err(ServiceError::Validation(format!(
+                        "Unknown status '{}'; expected DRAFT or PUBLISHED",
+                        other
+                    )))
+                }
+            };
+            let from = query.from.as_deref().map(|raw| parse_timestamp("from", raw)).transpose()?;
+            let to = query.to.as_deref().map(|raw| parse_timestamp("to", raw)).transpose()?;
+            Ok(Self { columns, status, from, to })
+        }
+
+        fn includes(&self, post: &Post) -> bool {
+            self.status.map_or(true, |status| post.status == status)
+                && self.from.map_or(true, |from| post.created_at >= from)
+                && self.to.map_or(true, |to| post.created_at < to)
+        }
+    }
+
+    fn parse_columns(raw: &str) -> Result<Vec<PostColumn>, ServiceError> {
+        let mut columns = Vec::new();
+        let mut unknown = Vec::new();
+        for name in raw.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            match PostColumn::ALL.into_iter().find(|column| column.name() == name) {
+                Some(column) if columns.contains(&column) => {
+                    return Err(ServiceError::Validation(format!("Column '{}' is selected more than once", name)))
+                }
+                Some(column) => columns.push(column),
+                None => unknown.push(name),
+            }
+        }
+        if !unknown.is_empty() {
+            let available: Vec<&str> = PostColumn::ALL.iter().map(|column| column.name()).collect();
+            return Err(ServiceError::Validation(format!(
+                "Unknown column(s): {}; available: {}",
+                unknown.join(", "),
+                available.join(", ")
+            )));
+        }
+        if columns.is_empty() {
+            return Err(ServiceError::Validation("'columns' must name at least one column".to_string()));
+        }
+        Ok(columns)
+    }
+
+    /// RFC 3339, or a bare `YYYY-MM-DD` taken as midnight UTC.
+    fn parse_timestamp(param: &str, raw: &str) -> Result<DateTime<Utc>, ServiceError> {
+        if let Ok(instant) = DateTime::parse_from_rfc3339(raw) {
+            return Ok(instant.with_timezone(&Utc));
+        }
+        NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|midnight| midnight.and_utc())
+            .ok_or_else(|| {
+                ServiceError::Validation(format!("'{}' must be an RFC 3339 timestamp or YYYY-MM-DD date", param))
+            })
+    }
+
     #[derive(Clone)]
     pub struct PostService {
         db: PostDbMock,
         storage_path: Arc<Path>,
+        visibility: StorageVisibility,
+        signed_urls: SignedUrlService,
     }
 
     impl PostService {
-        pub fn new(db: PostDbMock, storage_path: PathBuf) -> Self {
-            Self { db, storage_path: Arc::from(storage_path) }
+        pub fn new(
+            db: PostDbMock,
+            storage_path: PathBuf,
+            visibility: StorageVisibility,
+            signed_urls: SignedUrlService,
+        ) -> Self {
+            Self { db, storage_path: Arc::from(storage_path), visibility, signed_urls }
+        }
+
+        /// The URL to hand to clients for a stored image: signed when storage is private.
+        pub fn image_url(&self, image_name: &str) -> String {
+            let path = format!("/images/{}", image_name);
+            match self.visibility {
+                StorageVisibility::Public => path,
+                StorageVisibility::Private => self.signed_urls.sign(&path),
+            }
         }
 
         pub fn process_post_image(&self, post_id: Uuid, image_data: Bytes, content_type: &str) -> Result<String, ServiceError> {
@@ -194,17 +454,29 @@ mod services {
             let final_path = self.storage_path.join(&image_name);
             temp_file.persist(&final_path)?;
 
-            Ok(format!("/images/{}", image_name))
+            Ok(self.image_url(&image_name))
         }
 
-        pub fn export_to_csv_stream(&self) -> Result<impl futures_util::Stream<Item = Result<Bytes, std::io::Error>>, ServiceError> {
-            let posts = self.db.lock().unwrap().values().cloned().collect::<Vec<_>>();
-            
+        /// The header row always mirrors `export.columns`, even when no post matches.
+        pub fn export_to_csv_stream(
+            &self,
+            export: CsvExport,
+        ) -> Result<impl futures_util::Stream<Item = Result<Bytes, std::io::Error>>, ServiceError> {
+            let mut posts = self
+                .db
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|post| export.includes(post))
+                .cloned()
+                .collect::<Vec<_>>();
+            posts.sort_by_key(|post| (post.created_at, post.id));
+
             let (tx, rx) = tokio::sync::mpsc::channel(1);
 
             tokio::spawn(async move {
                 let mut wtr = csv::WriterBuilder::new().from_writer(Vec::new());
-                if wtr.write_record(&["id", "user_id", "title", "status"]).is_err() {
+                if wtr.write_record(export.columns.iter().map(|column| column.name())).is_err() {
                     return;
                 }
                 if tx.send(Ok(Bytes::from(wtr.into_inner().unwrap()))).await.is_err() {
@@ -213,7 +485,7 @@ mod services {
 
                 for post in posts {
                     let mut wtr = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
-                    if wtr.serialize((post.id, post.user_id, &post.title, &post.status)).is_err() {
+                    if wtr.write_record(export.columns.iter().map(|column| column.value(&post))).is_err() {
                         continue;
                     }
                     if tx.send(Ok(Bytes::from(wtr.into_inner().unwrap()))).await.is_err() {
@@ -231,15 +503,17 @@ mod handlers {
     use super::{
         errors::ServiceError,
         models::{Post, User},
-        services::{PostService, UserService},
+        services::{CsvExport, CsvExportQuery, PostService, StorageVisibility, UserService},
+        signing::SignedUrlService,
     };
     use axum::{
         body::Body,
-        extract::{Multipart, Path, State},
+        extract::{Multipart, Path, Query, State},
         http::header,
         response::IntoResponse,
         Json,
     };
+    use serde::Deserialize;
     use std::sync::Arc;
     use uuid::Uuid;
 
@@ -247,6 +521,14 @@ mod handlers {
         pub user_service: UserService,
         pub post_service: PostService,
         pub storage_path: PathBuf,
+        pub storage_visibility: StorageVisibility,
+        pub signed_urls: SignedUrlService,
+    }
+
+    #[derive(Deserialize)]
+    pub struct SignatureQuery {
+        pub expires: Option<i64>,
+        pub sig: Option<String>,
     }
 
     pub async fn upload_users_csv_handler(
@@ -282,8 +564,11 @@ mod handlers {
 
     pub async fn download_posts_csv_handler(
         State(state): State<Arc<AppState>>,
+        Query(query): Query<CsvExportQuery>,
     ) -> Result<impl IntoResponse, ServiceError> {
-        let stream = state.post_service.export_to_csv_stream()?;
+        // Validated up front so a bad query is a 400, not a truncated download
+        let export = CsvExport::parse(&query)?;
+        let stream = state.post_service.export_to_csv_stream(export)?;
         let headers = [
             (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
             (header::CONTENT_DISPOSITION, "attachment; filename=\"posts.csv\""),
@@ -294,7 +579,20 @@ mod handlers {
     pub async fn serve_image_handler(
         State(state): State<Arc<AppState>>,
         Path(image_name): Path<String>,
+        Query(signature): Query<SignatureQuery>,
     ) -> Result<impl IntoResponse, ServiceError> {
+        // Checked before touching the filesystem, so unsigned requests can't probe for files
+        let cache_control = match state.storage_visibility {
+            StorageVisibility::Public => "public".to_string(),
+            StorageVisibility::Private => {
+                let remaining = state.signed_urls.verify(
+                    &format!("/images/{}", image_name),
+                    signature.expires,
+                    signature.sig.as_deref(),
+                )?;
+                format!("private, max-age={}", remaining)
+            }
+        };
         let path = state.storage_path.join(&image_name);
         if !path.exists() {
             return Err(ServiceError::NotFound("Image not found".to_string()));
@@ -303,7 +601,7 @@ mod handlers {
         let stream = tokio_util::io::ReaderStream::new(file);
         let body = Body::from_stream(stream);
         let content_type = mime_guess::from_path(&image_name).first_or_octet_stream().to_string();
-        let headers = [(header::CONTENT_TYPE, content_type)];
+        let headers = [(header::CONTENT_TYPE, content_type), (header::CACHE_CONTROL, cache_control)];
         Ok((headers, body))
     }
 }
@@ -311,7 +609,8 @@ mod handlers {
 // --- MAIN & ROUTER SETUP ---
 
 use models::{Post, PostStatus, User};
-use services::{PostService, UserService};
+use services::{PostService, StorageVisibility, UserService};
+use signing::SignedUrlService;
 use handlers::AppState;
 use std::path::PathBuf;
 
@@ -329,7 +628,8 @@ async fn main() {
     // Pre-populate
     let post_id = Uuid::new_v4();
     post_db.lock().unwrap().insert(post_id, Post {
-        id: post_id, user_id: Uuid::new_v4(), title: "Test".to_string(), content: "".to_string(), status: PostStatus::DRAFT
+        id: post_id, user_id: Uuid::new_v4(), title: "Test".to_string(), content: "".to_string(), status: PostStatus::DRAFT,
+        created_at: chrono::Utc::now(),
     });
 
     // Setup storage
@@ -337,13 +637,17 @@ async fn main() {
     tokio::fs::create_dir_all(&storage_path).await.unwrap();
 
     // Setup services
+    let storage_visibility = StorageVisibility::from_env();
+    let signed_urls = SignedUrlService::from_env();
     let user_service = UserService::new(user_db.clone());
-    let post_service = PostService::new(post_db.clone(), storage_path.clone());
+    let post_service = PostService::new(post_db.clone(), storage_path.clone(), storage_visibility, signed_urls.clone());
 
     let app_state = Arc::new(AppState {
         user_service,
         post_service,
         storage_path,
+        storage_visibility,
+        signed_urls,
     });
 
     let app = Router::new()
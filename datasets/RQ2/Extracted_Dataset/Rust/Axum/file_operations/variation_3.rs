@@ -5,6 +5,7 @@
 axum = "0.7"
 tokio = { version = "1", features = ["full"] }
 serde = { version = "1", features = ["derive"] }
+serde_json = "1"
 uuid = { version = "1", features = ["v4", "serde"] }
 chrono = { version = "0.4", features = ["serde"] }
 csv = "1.3"
@@ -17,6 +18,8 @@ axum-macros = "0.4"
 async-trait = "0.1"
 bytes = "1"
 tokio-util = { version = "0.7", features = ["io"] }
+sha2 = "0.10"
+hex = "0.4"
 */
 
 use axum::{
@@ -35,17 +38,41 @@ use uuid::Uuid;
 pub type AppResult<T> = Result<T, AppError>;
 
 #[derive(Debug)]
-pub struct AppError(String);
+pub enum AppError {
+    Internal(String),
+    NotFound(String),
+    /// This upload doesn't fit in the remaining quota (413).
+    QuotaExceeded { usage: domain::StorageUsage, upload_bytes: u64 },
+    /// The user has no quota left at all, so no upload can succeed (403).
+    QuotaExhausted { usage: domain::StorageUsage },
+}
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        (StatusCode::INTERNAL_SERVER_ERROR, self.0).into_response()
+        match self {
+            AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response(),
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg).into_response(),
+            AppError::QuotaExceeded { usage, upload_bytes } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(serde_json::json!({
+                    "error": "Upload exceeds remaining storage quota",
+                    "upload_bytes": upload_bytes,
+                    "usage": usage,
+                })),
+            )
+                .into_response(),
+            AppError::QuotaExhausted { usage } => (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({ "error": "Storage quota exhausted", "usage": usage })),
+            )
+                .into_response(),
+        }
     }
 }
 
 impl<T: std::error::Error> From<T> for AppError {
     fn from(err: T) -> Self {
-        AppError(err.to_string())
+        AppError::Internal(err.to_string())
     }
 }
 
@@ -79,11 +106,44 @@ mod domain {
         pub content: String,
         pub status: PostStatus,
     }
+
+    /// A row of the `stored_files` table: who an image is billed to and which
+    /// content-addressed blob holds its bytes.
+    #[derive(Debug, Clone)]
+    pub struct StoredFile {
+        pub name: String,
+        pub user_id: Uuid,
+        pub bytes: u64,
+        pub sha256: String,
+    }
+
+    /// Upload response. `sha256` identifies the content, so clients can key caches
+    /// on it; identical images share one blob.
+    #[derive(Debug, Clone, Serialize)]
+    pub struct StoredImage {
+        pub url: String,
+        pub sha256: String,
+        pub bytes: u64,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub struct StorageUsage {
+        pub user_id: Uuid,
+        pub used_bytes: u64,
+        pub limit_bytes: u64,
+        pub remaining_bytes: u64,
+    }
+
+    impl StorageUsage {
+        pub fn new(user_id: Uuid, used_bytes: u64, limit_bytes: u64) -> Self {
+            Self { user_id, used_bytes, limit_bytes, remaining_bytes: limit_bytes.saturating_sub(used_bytes) }
+        }
+    }
 }
 
 // --- REPOSITORY TRAITS & IMPLEMENTATIONS (DATA LAYER) ---
 mod persistence {
-    use super::domain::{Post, User};
+    use super::domain::{Post, StoredFile, User};
     use super::AppResult;
     use async_trait::async_trait;
     use std::{
@@ -103,11 +163,87 @@ mod persistence {
         async fn find_all(&self) -> AppResult<Vec<Post>>;
     }
 
+    /// Outcome of `StorageUsageRepository::record_upload` when the file doesn't fit.
+    #[derive(Debug)]
+    pub struct QuotaCheckFailed {
+        pub used_bytes: u64,
+    }
+
+    #[derive(Debug)]
+    pub struct UploadRecorded {
+        pub used_bytes: u64,
+        /// The blob of the image this upload replaced, if nothing references it any more.
+        pub orphaned_blob: Option<String>,
+    }
+
+    #[derive(Debug)]
+    pub struct DeleteRecorded {
+        pub file: StoredFile,
+        /// Whether this was the blob's last reference, so it can go from disk.
+        pub orphaned_blob: bool,
+    }
+
+    /// Per-user byte totals (`storage_usage`), the files they are made of
+    /// (`stored_files`) and reference counts of the blobs behind those files
+    /// (`blobs`). Each method updates all three tables as one transaction.
+    #[async_trait]
+    pub trait StorageUsageRepository: Send + Sync {
+        async fn used_bytes(&self, user_id: Uuid) -> AppResult<u64>;
+        async fn find_file(&self, name: &str) -> AppResult<Option<StoredFile>>;
+        /// Checks the quota and records the file in the same transaction, so two
+        /// concurrent uploads can't both squeeze into the last free bytes. A file
+        /// replacing one of the same name is credited with the old size first.
+        async fn record_upload(
+            &self,
+            file: StoredFile,
+            limit_bytes: u64,
+        ) -> AppResult<Result<UploadRecorded, QuotaCheckFailed>>;
+        async fn record_delete(&self, name: &str) -> AppResult<Option<DeleteRecorded>>;
+    }
+
+    #[derive(Default)]
+    struct StorageTables {
+        storage_usage: HashMap<Uuid, u64>,
+        stored_files: HashMap<String, StoredFile>,
+        blobs: HashMap<String, usize>,
+    }
+
+    impl StorageTables {
+        fn add_file(&mut self, file: StoredFile) -> u64 {
+            *self.blobs.entry(file.sha256.clone()).or_insert(0) += 1;
+            let used = self.storage_usage.entry(file.user_id).or_insert(0);
+            *used += file.bytes;
+            let total = *used;
+            self.stored_files.insert(file.name.clone(), file);
+            total
+        }
+
+        fn remove_file(&mut self, name: &str) -> Option<DeleteRecorded> {
+            let file = self.stored_files.remove(name)?;
+            if let Some(used) = self.storage_usage.get_mut(&file.user_id) {
+                *used = used.saturating_sub(file.bytes);
+            }
+            let orphaned_blob = match self.blobs.get_mut(&file.sha256) {
+                Some(refs) if *refs > 1 => {
+                    *refs -= 1;
+                    false
+                }
+                _ => {
+                    self.blobs.remove(&file.sha256);
+                    true
+                }
+            };
+            Some(DeleteRecorded { file, orphaned_blob })
+        }
+    }
+
     // In-memory implementation for demonstration
     #[derive(Clone, Default)]
     pub struct InMemoryDb {
         users: Arc<Mutex<HashMap<Uuid, User>>>,
         posts: Arc<Mutex<HashMap<Uuid, Post>>>,
+        // One lock over both tables stands in for a database transaction
+        storage: Arc<Mutex<StorageTables>>,
     }
     
     impl InMemoryDb {
@@ -144,33 +280,171 @@ mod persistence {
             Ok(lock.values().cloned().collect())
         }
     }
+
+    #[async_trait]
+    impl StorageUsageRepository for InMemoryDb {
+        async fn used_bytes(&self, user_id: Uuid) -> AppResult<u64> {
+            let lock = self.storage.lock().unwrap();
+            Ok(lock.storage_usage.get(&user_id).copied().unwrap_or(0))
+        }
+
+        async fn find_file(&self, name: &str) -> AppResult<Option<StoredFile>> {
+            let lock = self.storage.lock().unwrap();
+            Ok(lock.stored_files.get(name).cloned())
+        }
+
+        async fn record_upload(
+            &self,
+            file: StoredFile,
+            limit_bytes: u64,
+        ) -> AppResult<Result<UploadRecorded, QuotaCheckFailed>> {
+            let mut lock = self.storage.lock().unwrap();
+            let replaced = lock
+                .stored_files
+                .get(&file.name)
+                .filter(|old| old.user_id == file.user_id)
+                .map_or(0, |old| old.bytes);
+            let used_bytes = lock.storage_usage.get(&file.user_id).copied().unwrap_or(0);
+            let new_total = used_bytes - replaced + file.bytes;
+            if new_total > limit_bytes {
+                return Ok(Err(QuotaCheckFailed { used_bytes }));
+            }
+            // Re-uploading identical content releases and re-takes the same blob
+            let orphaned_blob = lock
+                .remove_file(&file.name)
+                .filter(|removed| removed.orphaned_blob && removed.file.sha256 != file.sha256)
+                .map(|removed| removed.file.sha256);
+            let used_bytes = lock.add_file(file);
+            Ok(Ok(UploadRecorded { used_bytes, orphaned_blob }))
+        }
+
+        async fn record_delete(&self, name: &str) -> AppResult<Option<DeleteRecorded>> {
+            let mut lock = self.storage.lock().unwrap();
+            Ok(lock.remove_file(name))
+        }
+    }
 }
 
 // --- FILE PROCESSING SERVICE (BUSINESS LOGIC) ---
 mod services {
-    use super::domain::{User, UserRole};
-    use super::persistence::{PostRepository, UserRepository};
-    use super::AppResult;
+    use super::domain::{StorageUsage, StoredFile, StoredImage, User, UserRole};
+    use super::persistence::{PostRepository, StorageUsageRepository, UserRepository};
+    use super::{AppError, AppResult};
     use bytes::Bytes;
     use chrono::Utc;
     use serde::Deserialize;
-    use std::{io::Write, path::Path, sync::Arc};
+    use sha2::{Digest, Sha256};
+    use std::{
+        collections::HashMap,
+        io::{Cursor, Write},
+        path::Path,
+        sync::Arc,
+    };
     use tempfile::NamedTempFile;
+    use tokio::sync::Mutex;
     use uuid::Uuid;
 
+    const DEFAULT_QUOTA_BYTES: u64 = 50 * 1024 * 1024;
+
+    /// Per-user storage limits: a default plus individual overrides.
+    #[derive(Debug, Clone)]
+    pub struct QuotaPolicy {
+        default_bytes: u64,
+        overrides: HashMap<Uuid, u64>,
+    }
+
+    impl QuotaPolicy {
+        pub fn new(default_bytes: u64) -> Self {
+            Self { default_bytes, overrides: HashMap::new() }
+        }
+
+        pub fn with_override(mut self, user_id: Uuid, bytes: u64) -> Self {
+            self.overrides.insert(user_id, bytes);
+            self
+        }
+
+        /// `STORAGE_QUOTA_BYTES` for the default and `STORAGE_QUOTA_OVERRIDES` as
+        /// comma-separated `<user id>=<bytes>` pairs. Malformed entries are skipped.
+        pub fn from_env() -> Self {
+            let default_bytes = std::env::var("STORAGE_QUOTA_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_QUOTA_BYTES);
+            let overrides = std::env::var("STORAGE_QUOTA_OVERRIDES").unwrap_or_default();
+            overrides
+                .split(',')
+                .filter_map(|entry| {
+                    let (user_id, bytes) = entry.trim().split_once('=')?;
+                    Some((Uuid::parse_str(user_id).ok()?, bytes.parse().ok()?))
+                })
+                .fold(Self::new(default_bytes), |policy, (user_id, bytes)| policy.with_override(user_id, bytes))
+        }
+
+        pub fn limit_for(&self, user_id: Uuid) -> u64 {
+            self.overrides.get(&user_id).copied().unwrap_or(self.default_bytes)
+        }
+    }
+
     pub struct FileService {
         user_repo: Arc<dyn UserRepository>,
         post_repo: Arc<dyn PostRepository>,
+        storage_repo: Arc<dyn StorageUsageRepository>,
+        quotas: QuotaPolicy,
         storage_path: PathBuf,
+        /// Serialises blob writes and removals against their refcount updates, so a
+        /// blob can't be deleted from disk just as a new upload starts sharing it.
+        blob_io: Mutex<()>,
     }
 
     impl FileService {
         pub fn new(
             user_repo: Arc<dyn UserRepository>,
             post_repo: Arc<dyn PostRepository>,
+            storage_repo: Arc<dyn StorageUsageRepository>,
+            quotas: QuotaPolicy,
             storage_path: PathBuf,
         ) -> Self {
-            Self { user_repo, post_repo, storage_path }
+            Self { user_repo, post_repo, storage_repo, quotas, storage_path, blob_io: Mutex::new(()) }
+        }
+
+        fn blob_path(&self, sha256: &str) -> PathBuf {
+            self.storage_path.join("blobs").join(sha256)
+        }
+
+        /// A no-op when the blob is already on disk; identical content needs storing once.
+        fn write_blob(&self, sha256: &str, data: &[u8]) -> AppResult<()> {
+            let path = self.blob_path(sha256);
+            if path.exists() {
+                return Ok(());
+            }
+            let dir = self.storage_path.join("blobs");
+            std::fs::create_dir_all(&dir)?;
+            let mut temp_file = NamedTempFile::new_in(&dir)?;
+            temp_file.write_all(data)?;
+            temp_file.persist(&path)?;
+            Ok(())
+        }
+
+        async fn remove_blob(&self, sha256: &str) -> AppResult<()> {
+            match tokio::fs::remove_file(self.blob_path(sha256)).await {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e.into()),
+            }
+        }
+
+        /// Where the bytes behind a post image live.
+        pub async fn locate_image(&self, image_name: &str) -> AppResult<(StoredFile, PathBuf)> {
+            let Some(file) = self.storage_repo.find_file(image_name).await? else {
+                return Err(AppError::NotFound("Image not found".to_string()));
+            };
+            let path = self.blob_path(&file.sha256);
+            Ok((file, path))
+        }
+
+        pub async fn storage_usage(&self, user_id: Uuid) -> AppResult<StorageUsage> {
+            let used_bytes = self.storage_repo.used_bytes(user_id).await?;
+            Ok(StorageUsage::new(user_id, used_bytes, self.quotas.limit_for(user_id)))
         }
 
         pub async fn bulk_import_users(&self, csv_data: Bytes) -> AppResult<Vec<Uuid>> {
@@ -200,29 +474,74 @@ mod services {
             post_id: Uuid,
             image_data: Bytes,
             content_type: &str,
-        ) -> AppResult<String> {
-            if self.post_repo.find_by_id(post_id).await?.is_none() {
-                return Err(super::AppError("Post not found".to_string()));
+        ) -> AppResult<StoredImage> {
+            let Some(post) = self.post_repo.find_by_id(post_id).await? else {
+                return Err(AppError::Internal("Post not found".to_string()));
+            };
+
+            // Refuse early when nothing could fit, before spending time on the image
+            let usage = self.storage_usage(post.user_id).await?;
+            if usage.remaining_bytes == 0 {
+                return Err(AppError::QuotaExhausted { usage });
             }
 
             let extension = match content_type {
                 "image/jpeg" => "jpg",
                 "image/png" => "png",
-                _ => return Err(super::AppError("Unsupported image type".to_string())),
+                _ => return Err(AppError::Internal("Unsupported image type".to_string())),
             };
 
             let image = image::load_from_memory(&image_data)?;
             let resized = image.resize(300, 300, image::imageops::FilterType::Lanczos3);
             
-            let mut temp_file = NamedTempFile::new_in(&self.storage_path)?;
             let format = image::ImageFormat::from_extension(extension).unwrap();
-            resized.write_to(&mut temp_file, format)?;
+            let mut encoded = Cursor::new(Vec::new());
+            resized.write_to(&mut encoded, format)?;
+            let encoded = encoded.into_inner();
+            // Hash the stored (resized) bytes: that's what is shared on disk
+            let sha256 = hex::encode(Sha256::digest(&encoded));
 
+            // Each post is billed for its image even when the blob is shared
             let image_name = format!("{}.{}", post_id, extension);
-            let final_path = self.storage_path.join(&image_name);
-            temp_file.persist(&final_path)?;
+            let file = StoredFile {
+                name: image_name.clone(),
+                user_id: post.user_id,
+                bytes: encoded.len() as u64,
+                sha256: sha256.clone(),
+            };
+            let upload_bytes = file.bytes;
+            let limit_bytes = self.quotas.limit_for(post.user_id);
+
+            let _blob_io = self.blob_io.lock().await;
+            let recorded = match self.storage_repo.record_upload(file, limit_bytes).await? {
+                Ok(recorded) => recorded,
+                Err(failed) => {
+                    let usage = StorageUsage::new(post.user_id, failed.used_bytes, limit_bytes);
+                    return Err(AppError::QuotaExceeded { usage, upload_bytes });
+                }
+            };
+            if let Err(e) = self.write_blob(&sha256, &encoded) {
+                self.storage_repo.record_delete(&image_name).await?;
+                return Err(e);
+            }
+            if let Some(orphaned) = recorded.orphaned_blob {
+                self.remove_blob(&orphaned).await?;
+            }
 
-            Ok(format!("/images/{}", image_name))
+            Ok(StoredImage { url: format!("/images/{}", image_name), sha256, bytes: upload_bytes })
+        }
+
+        /// Frees the owner's quota right away; the blob itself is only removed from
+        /// disk once no other post image references it.
+        pub async fn delete_image(&self, image_name: &str) -> AppResult<StorageUsage> {
+            let _blob_io = self.blob_io.lock().await;
+            let Some(deleted) = self.storage_repo.record_delete(image_name).await? else {
+                return Err(AppError::NotFound("Image not found".to_string()));
+            };
+            if deleted.orphaned_blob {
+                self.remove_blob(&deleted.file.sha256).await?;
+            }
+            self.storage_usage(deleted.file.user_id).await
         }
 
         pub async fn export_posts_as_csv(&self) -> AppResult<Bytes> {
@@ -239,7 +558,7 @@ mod services {
 
 // --- AXUM HANDLERS (PRESENTATION LAYER) ---
 mod handlers {
-    use super::domain::Post;
+    use super::domain::{Post, StorageUsage, StoredImage};
     use super::services::FileService;
     use super::AppResult;
     use axum::{
@@ -263,23 +582,37 @@ mod handlers {
                 return Ok(Json(ids));
             }
         }
-        Err(super::AppError("Field 'users_file' not found".into()))
+        Err(super::AppError::Internal("Field 'users_file' not found".into()))
     }
 
     pub async fn handle_post_image_upload(
         State(file_service): State<Arc<FileService>>,
         Path(post_id): Path<Uuid>,
         mut multipart: Multipart,
-    ) -> AppResult<Json<String>> {
+    ) -> AppResult<Json<StoredImage>> {
         while let Some(field) = multipart.next_field().await? {
             if field.name() == Some("image") {
                 let content_type = field.content_type().unwrap_or("").to_string();
                 let data = field.bytes().await?;
-                let url = file_service.process_and_store_image(post_id, data, &content_type).await?;
-                return Ok(Json(url));
+                let image = file_service.process_and_store_image(post_id, data, &content_type).await?;
+                return Ok(Json(image));
             }
         }
-        Err(super::AppError("Field 'image' not found".into()))
+        Err(super::AppError::Internal("Field 'image' not found".into()))
+    }
+
+    pub async fn handle_delete_image(
+        State(file_service): State<Arc<FileService>>,
+        Path(image_name): Path<String>,
+    ) -> AppResult<Json<StorageUsage>> {
+        Ok(Json(file_service.delete_image(&image_name).await?))
+    }
+
+    pub async fn handle_user_storage(
+        State(file_service): State<Arc<FileService>>,
+        Path(user_id): Path<Uuid>,
+    ) -> AppResult<Json<StorageUsage>> {
+        Ok(Json(file_service.storage_usage(user_id).await?))
     }
 
     pub async fn handle_posts_csv_download(
@@ -297,19 +630,20 @@ mod handlers {
         State(file_service): State<Arc<FileService>>,
         Path(image_name): Path<String>,
     ) -> AppResult<impl IntoResponse> {
-        let path = file_service.storage_path.join(&image_name);
+        let (stored, path) = file_service.locate_image(&image_name).await?;
         let file = tokio::fs::File::open(path).await?;
         let stream = tokio_util::io::ReaderStream::new(file);
         let body = Body::from_stream(stream);
         let content_type = mime_guess::from_path(&image_name).first_or_octet_stream().to_string();
-        Ok(([(header::CONTENT_TYPE, content_type)], body))
+        let etag = format!("\"{}\"", stored.sha256);
+        Ok(([(header::CONTENT_TYPE, content_type), (header::ETAG, etag)], body))
     }
 }
 
 // --- MAIN & ROUTER SETUP ---
 use domain::{Post, PostStatus};
-use persistence::{InMemoryDb, PostRepository, UserRepository};
-use services::FileService;
+use persistence::{InMemoryDb, PostRepository, StorageUsageRepository, UserRepository};
+use services::{FileService, QuotaPolicy};
 
 #[tokio::main]
 async fn main() {
@@ -327,15 +661,23 @@ async fn main() {
         id: post_id, user_id: Uuid::new_v4(), title: "Test".to_string(), content: "".to_string(), status: PostStatus::DRAFT
     }));
     let user_repo: Arc<dyn UserRepository> = db.clone();
-    let post_repo: Arc<dyn PostRepository> = db;
+    let post_repo: Arc<dyn PostRepository> = db.clone();
+    let storage_repo: Arc<dyn StorageUsageRepository> = db;
 
-    let file_service = Arc::new(FileService::new(user_repo, post_repo, storage_path));
+    let file_service = Arc::new(FileService::new(
+        user_repo,
+        post_repo,
+        storage_repo,
+        QuotaPolicy::from_env(),
+        storage_path,
+    ));
 
     let app = Router::new()
         .route("/users/upload/csv", post(handlers::handle_user_csv_upload))
         .route("/posts/:post_id/image", post(handlers::handle_post_image_upload))
         .route("/posts/download/csv", get(handlers::handle_posts_csv_download))
-        .route("/images/:image_name", get(handlers::handle_serve_image))
+        .route("/images/:image_name", get(handlers::handle_serve_image).delete(handlers::handle_delete_image))
+        .route("/users/:id/storage", get(handlers::handle_user_storage))
         .with_state(file_service)
         .layer(DefaultBodyLimit::max(10 * 1024 * 1024));
 
@@ -27,6 +27,8 @@ bytes = "1"
 // --- main.rs ---
 use axum::{routing::get, routing::post, Router};
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tower::ServiceBuilder;
 use tracing::info;
 
@@ -48,12 +50,28 @@ async fn main() {
         // This middleware is route-specific for demonstration
         .route_layer(axum::middleware::from_fn(middleware::auth_guard));
 
+    let cors = middleware::CorsConfig::from_env().expect("Invalid CORS configuration");
+    let maintenance = Arc::new(middleware::MaintenanceMode::from_env());
+    maintenance.clone().spawn_watcher(Duration::from_secs(2));
+    let admin = middleware::AdminGuard::from_env();
+
+    let admin_routes = Router::new()
+        .route("/maintenance", get(handlers::maintenance_status))
+        .route_layer(axum::middleware::from_fn_with_state(admin.clone(), middleware::admin_guard));
+
     let app = Router::new()
+        .route("/health", get(handlers::health))
+        .nest("/admin", admin_routes)
         .nest("/v1", api_routes)
+        .with_state(maintenance.clone())
         .layer(
             ServiceBuilder::new()
                 .layer(middleware::setup_tracing_layer())
-                .layer(middleware::setup_cors_layer())
+                .layer(middleware::setup_cors_layer(&cors))
+                .layer(axum::middleware::from_fn_with_state(
+                    middleware::MaintenanceGate { mode: maintenance, admin },
+                    middleware::maintenance_gate,
+                ))
                 .layer(middleware::setup_governor_layer())
                 .layer(axum::middleware::from_fn(middleware::json_response_wrapper)),
         );
@@ -145,18 +163,26 @@ pub mod middleware {
     use crate::errors::ApiError;
     use axum::{
         body::Body,
-        extract::Request,
-        http::{header, Method, StatusCode},
+        extract::{Request, State},
+        http::{header, HeaderMap, Method, StatusCode},
         middleware::Next,
         response::{IntoResponse, Response},
+        Json,
     };
     use bytes::Bytes;
     use http_body_util::BodyExt;
     use tower_governor::{
         governor::GovernorConfigBuilder, key_extractor::SmartIpKeyExtractor, GovernorLayer,
     };
-    use tower_http::{cors::CorsLayer, trace::TraceLayer};
-    use tracing::info;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tower_http::{
+        cors::{AllowOrigin, CorsLayer},
+        trace::TraceLayer,
+    };
+    use tracing::{info, warn};
 
     // 1. Request Logging
     pub fn setup_tracing_layer() -> TraceLayer {
@@ -164,11 +190,83 @@ pub mod middleware {
     }
 
     // 2. CORS Handling
-    pub fn setup_cors_layer() -> CorsLayer {
-        CorsLayer::new()
-            .allow_origin("http://localhost:3000".parse::<header::HeaderValue>().unwrap())
-            .allow_methods([Method::GET, Method::POST])
-            .allow_headers([header::CONTENT_TYPE])
+    #[derive(Debug, Clone)]
+    pub struct CorsConfig {
+        pub allowed_origins: Vec<String>, // "*" allows any origin
+        pub allowed_methods: Vec<String>,
+        pub allowed_headers: Vec<String>,
+        pub allow_credentials: bool,
+        pub max_age: Option<Duration>, // preflight cache lifetime
+    }
+
+    impl Default for CorsConfig {
+        fn default() -> Self {
+            CorsConfig {
+                allowed_origins: vec!["http://localhost:3000".to_string()],
+                allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+                allowed_headers: vec!["content-type".to_string()],
+                allow_credentials: false,
+                max_age: Some(Duration::from_secs(3600)),
+            }
+        }
+    }
+
+    impl CorsConfig {
+        // Comma-separated CORS_ALLOWED_ORIGINS / _METHODS / _HEADERS, CORS_ALLOW_CREDENTIALS
+        // and CORS_MAX_AGE_SECS override the defaults. Credentials require named origins.
+        pub fn from_env() -> Result<Self, String> {
+            fn list(key: &str) -> Option<Vec<String>> {
+                std::env::var(key)
+                    .ok()
+                    .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+            }
+            let defaults = CorsConfig::default();
+            let config = CorsConfig {
+                allowed_origins: list("CORS_ALLOWED_ORIGINS").unwrap_or(defaults.allowed_origins),
+                allowed_methods: list("CORS_ALLOWED_METHODS").unwrap_or(defaults.allowed_methods),
+                allowed_headers: list("CORS_ALLOWED_HEADERS").unwrap_or(defaults.allowed_headers),
+                allow_credentials: std::env::var("CORS_ALLOW_CREDENTIALS").map(|v| v == "true").unwrap_or(defaults.allow_credentials),
+                max_age: match std::env::var("CORS_MAX_AGE_SECS") {
+                    Ok(v) => v.parse().ok().map(Duration::from_secs),
+                    Err(_) => defaults.max_age,
+                },
+            };
+            if config.allow_credentials && config.allowed_origins.iter().any(|o| o == "*") {
+                return Err("CORS_ALLOW_CREDENTIALS=true cannot be combined with CORS_ALLOWED_ORIGINS=*; list the origins".to_string());
+            }
+            Ok(config)
+        }
+    }
+
+    // Values that don't parse are logged and dropped rather than failing startup
+    fn parse_all<T: std::str::FromStr>(values: &[String], what: &str) -> Vec<T> {
+        values
+            .iter()
+            .filter_map(|v| match v.parse() {
+                Ok(parsed) => Some(parsed),
+                Err(_) => {
+                    warn!("Ignoring invalid CORS {}: {}", what, v);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn setup_cors_layer(config: &CorsConfig) -> CorsLayer {
+        let allow_origin = if config.allowed_origins.iter().any(|o| o == "*") {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(parse_all::<header::HeaderValue>(&config.allowed_origins, "origin"))
+        };
+        let layer = CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(parse_all::<Method>(&config.allowed_methods, "method"))
+            .allow_headers(parse_all::<header::HeaderName>(&config.allowed_headers, "header"))
+            .allow_credentials(config.allow_credentials);
+        match config.max_age {
+            Some(max_age) => layer.max_age(max_age),
+            None => layer,
+        }
     }
 
     // 3. Rate Limiting
@@ -183,7 +281,119 @@ pub mod middleware {
         GovernorLayer { config: Box::leak(config) }
     }
 
-    // 4. Request/Response Transformation
+    // 4. Maintenance Mode
+    // Toggled by the presence of a flag file that deploy scripts create and remove.
+    pub struct MaintenanceMode {
+        enabled: AtomicBool,
+        flag_file: PathBuf,
+        pub retry_after: Duration,
+        exempt_paths: Vec<String>, // prefixes open to everyone, e.g. health checks
+    }
+
+    impl MaintenanceMode {
+        // MAINTENANCE_FLAG_FILE, MAINTENANCE_RETRY_AFTER_SECS and comma-separated
+        // MAINTENANCE_EXEMPT_PATHS override the defaults
+        pub fn from_env() -> Self {
+            let mode = MaintenanceMode {
+                enabled: AtomicBool::new(false),
+                flag_file: std::env::var("MAINTENANCE_FLAG_FILE").unwrap_or_else(|_| "maintenance.flag".to_string()).into(),
+                retry_after: Duration::from_secs(
+                    std::env::var("MAINTENANCE_RETRY_AFTER_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(120),
+                ),
+                exempt_paths: std::env::var("MAINTENANCE_EXEMPT_PATHS")
+                    .unwrap_or_else(|_| "/health".to_string())
+                    .split(',')
+                    .map(|s| s.trim().trim_end_matches('/').to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+            };
+            mode.refresh();
+            mode
+        }
+
+        pub fn is_enabled(&self) -> bool {
+            self.enabled.load(Ordering::Relaxed)
+        }
+
+        pub fn refresh(&self) {
+            let enabled = self.flag_file.exists();
+            if self.enabled.swap(enabled, Ordering::Relaxed) != enabled {
+                info!("Maintenance mode {}", if enabled { "enabled" } else { "disabled" });
+            }
+        }
+
+        pub fn is_exempt(&self, path: &str) -> bool {
+            self.exempt_paths
+                .iter()
+                .any(|prefix| path == prefix || path.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.starts_with('/')))
+        }
+
+        // Polls the flag file; cheap enough that a file-system notifier isn't worth the dependency
+        pub fn spawn_watcher(self: Arc<Self>, every: Duration) {
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(every);
+                loop {
+                    interval.tick().await;
+                    self.refresh();
+                }
+            });
+        }
+    }
+
+    /// State for `maintenance_gate`: requests that would pass `admin_guard` are let through.
+    #[derive(Clone)]
+    pub struct MaintenanceGate {
+        pub mode: Arc<MaintenanceMode>,
+        pub admin: AdminGuard,
+    }
+
+    pub async fn maintenance_gate(State(MaintenanceGate { mode, admin }): State<MaintenanceGate>, req: Request, next: Next) -> Response {
+        if mode.is_enabled() && !mode.is_exempt(req.uri().path()) && !admin.admits(req.headers()) {
+            let body = Json(serde_json::json!({
+                "status": "error",
+                "message": "The service is down for maintenance",
+            }));
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, mode.retry_after.as_secs().to_string())],
+                body,
+            )
+                .into_response();
+        }
+        next.run(req).await
+    }
+
+    // 5. Admin Guard
+    // Operators send `Authorization: Bearer <ADMIN_TOKEN>`. Leaving ADMIN_TOKEN unset locks
+    // the admin routes rather than opening them.
+    #[derive(Clone, Default)]
+    pub struct AdminGuard {
+        token: Option<Arc<str>>,
+    }
+
+    impl AdminGuard {
+        pub fn from_env() -> Self {
+            AdminGuard { token: std::env::var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty()).map(Arc::from) }
+        }
+
+        pub fn admits(&self, headers: &HeaderMap) -> bool {
+            let presented = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "));
+            matches!((&self.token, presented), (Some(token), Some(presented)) if presented == &**token)
+        }
+    }
+
+    pub async fn admin_guard(State(guard): State<AdminGuard>, req: Request, next: Next) -> Result<Response, ApiError> {
+        if guard.admits(req.headers()) {
+            Ok(next.run(req).await)
+        } else {
+            Err(ApiError::Unauthorized)
+        }
+    }
+
+    // 6. Request/Response Transformation
     pub async fn json_response_wrapper(req: Request, next: Next) -> impl IntoResponse {
         let response = next.run(req).await;
         
@@ -236,11 +446,25 @@ pub mod middleware {
 // --- handlers.rs ---
 pub mod handlers {
     use crate::errors::ApiError;
+    use crate::middleware::MaintenanceMode;
     use crate::models::{Post, PostStatus, PublishPostRequest, User, UserRole};
-    use axum::Json;
+    use axum::{extract::State, Json};
     use chrono::Utc;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
     use uuid::Uuid;
 
+    pub async fn health() -> Json<Value> {
+        Json(json!({ "status": "ok" }))
+    }
+
+    pub async fn maintenance_status(State(mode): State<Arc<MaintenanceMode>>) -> Json<Value> {
+        Json(json!({
+            "enabled": mode.is_enabled(),
+            "retry_after_secs": mode.retry_after.as_secs(),
+        }))
+    }
+
     pub async fn fetch_current_user() -> Result<Json<User>, ApiError> {
         let current_user = User {
             id: Uuid::new_v4(),
@@ -0,0 +1,222 @@
+/*
+In-process load test for the users API in variation_2.rs.
+
+Each scenario is driven through the router with `tower::ServiceExt`, so no sockets
+are involved and the numbers reflect handlers, services and repositories. Settings
+come from the environment:
+- `LOADTEST_REQUESTS` per scenario (default 2000)
+- `LOADTEST_CONCURRENCY` workers (default 16)
+- `LOADTEST_BASELINE` file (default `loadtest-baseline.json`)
+- `LOADTEST_TOLERANCE` allowed slowdown as a fraction (default 0.25)
+- `SEED` / `SEED_USERS` pick the fixture data, as for the server
+
+The run fails when any percentile exceeds its baseline by more than the tolerance,
+or when any request does not succeed. `-- --record-baseline` overwrites the baseline.
+
+Uses the `[[bench]]` entry and dev-dependencies listed in variation_2.rs:
+    cargo bench --features loadtest --bench loadtest_variation_2
+*/
+
+// The server itself (main, the listener, tracing) is unused here, and a harness-less
+// target drops the #[test] functions its test modules import for
+#[allow(dead_code, unused_imports)]
+#[path = "../variation_2.rs"]
+mod app;
+
+use app::domain::User;
+use app::tokens::TokenKeys;
+use axum::{
+    body::Body,
+    http::{header, Request},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+const SECRET: &[u8] = b"loadtest-only";
+
+/// An HS256 bearer token for `user_id`, signed the way the API's `TokenKeys` expects.
+fn bearer(user_id: Uuid) -> String {
+    let exp = (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp();
+    let claims = serde_json::json!({"sub": user_id, "exp": exp});
+    let token = jsonwebtoken::encode(&jsonwebtoken::Header::default(), &claims, &jsonwebtoken::EncodingKey::from_secret(SECRET))
+        .expect("HS256 signing cannot fail");
+    format!("Bearer {}", token)
+}
+
+struct Scenario {
+    name: &'static str,
+    request: fn(&[User], usize) -> Request<Body>,
+}
+
+// Seeded users come first: users[0] is the admin
+static SCENARIOS: [Scenario; 4] = [
+    Scenario {
+        name: "list_users",
+        request: |_, _| Request::get("/users?limit=20").body(Body::empty()).expect("valid request"),
+    },
+    Scenario {
+        name: "get_user",
+        request: |users, i| {
+            Request::get(format!("/users/{}", users[i % users.len()].id)).body(Body::empty()).expect("valid request")
+        },
+    },
+    Scenario {
+        name: "create_user",
+        request: |_, i| {
+            let body = serde_json::json!({"email": format!("loadtest-{}@example.com", i), "password": "loadtest", "role": "USER"});
+            Request::post("/users")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .expect("valid request")
+        },
+    },
+    Scenario {
+        name: "admin_search",
+        request: |users, _| {
+            Request::get("/admin/users?email_contains=user&limit=50")
+                .header(header::AUTHORIZATION, bearer(users[0].id))
+                .body(Body::empty())
+                .expect("valid request")
+        },
+    },
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct Percentiles {
+    p50_us: u64,
+    p95_us: u64,
+    p99_us: u64,
+}
+
+impl Percentiles {
+    // Nearest-rank on sorted samples
+    fn from_sorted(samples: &[Duration]) -> Self {
+        let rank = |p: usize| {
+            let index = (samples.len() * p).div_ceil(100).saturating_sub(1);
+            samples[index].as_micros() as u64
+        };
+        Self { p50_us: rank(50), p95_us: rank(95), p99_us: rank(99) }
+    }
+}
+
+struct Config {
+    requests: usize,
+    concurrency: usize,
+    baseline: String,
+    tolerance: f64,
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+impl Config {
+    fn from_env() -> Self {
+        Self {
+            requests: env_or("LOADTEST_REQUESTS", 2000).max(1),
+            concurrency: env_or("LOADTEST_CONCURRENCY", 16).max(1),
+            baseline: env_or("LOADTEST_BASELINE", "loadtest-baseline.json".to_string()),
+            tolerance: env_or("LOADTEST_TOLERANCE", 0.25),
+        }
+    }
+}
+
+/// Runs `requests` requests across `concurrency` workers; returns sorted latencies
+/// and the number of non-2xx responses.
+async fn run_scenario(app: &Router, users: Arc<Vec<User>>, scenario: &'static Scenario, config: &Config) -> (Vec<Duration>, usize) {
+    let next = Arc::new(AtomicUsize::new(0));
+    let workers: Vec<_> = (0..config.concurrency)
+        .map(|_| {
+            let (app, users, next, total) = (app.clone(), users.clone(), next.clone(), config.requests);
+            tokio::spawn(async move {
+                let mut samples = Vec::new();
+                let mut failures = 0;
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= total {
+                        return (samples, failures);
+                    }
+                    let request = (scenario.request)(&users, i);
+                    let started = Instant::now();
+                    let response = app.clone().oneshot(request).await.expect("router is infallible");
+                    samples.push(started.elapsed());
+                    if !response.status().is_success() {
+                        failures += 1;
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let mut samples = Vec::with_capacity(config.requests);
+    let mut failures = 0;
+    for worker in workers {
+        let (worker_samples, worker_failures) = worker.await.expect("load test worker panicked");
+        samples.extend(worker_samples);
+        failures += worker_failures;
+    }
+    samples.sort();
+    (samples, failures)
+}
+
+#[tokio::main]
+async fn main() {
+    let config = Config::from_env();
+    let record = std::env::args().any(|arg| arg == "--record-baseline");
+    let seed = env_or("SEED", 42);
+    let extra_users = env_or("SEED_USERS", 0);
+    let app = app::build_app(seed, extra_users, TokenKeys::from_secret(SECRET));
+    let users = Arc::new(app::seed_users(seed, extra_users));
+
+    let baseline: BTreeMap<String, Percentiles> = std::fs::read_to_string(&config.baseline)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default();
+
+    let mut results = BTreeMap::new();
+    let mut failed = false;
+    for scenario in &SCENARIOS {
+        let (samples, failures) = run_scenario(&app, users.clone(), scenario, &config).await;
+        let measured = Percentiles::from_sorted(&samples);
+        println!(
+            "{:<14} p50 {:>7}us  p95 {:>7}us  p99 {:>7}us  ({} requests, {} failed)",
+            scenario.name, measured.p50_us, measured.p95_us, measured.p99_us, samples.len(), failures
+        );
+        if failures > 0 {
+            failed = true;
+        }
+        if let (Some(expected), false) = (baseline.get(scenario.name), record) {
+            let limit = |us: u64| (us as f64 * (1.0 + config.tolerance)).ceil() as u64;
+            for (label, got, want) in [
+                ("p50", measured.p50_us, expected.p50_us),
+                ("p95", measured.p95_us, expected.p95_us),
+                ("p99", measured.p99_us, expected.p99_us),
+            ] {
+                if got > limit(want) {
+                    eprintln!("  regression: {} {} is {}us, baseline {}us", scenario.name, label, got, want);
+                    failed = true;
+                }
+            }
+        }
+        results.insert(scenario.name.to_string(), measured);
+    }
+
+    if record {
+        let raw = serde_json::to_string_pretty(&results).expect("percentiles serialize");
+        if let Err(e) = std::fs::write(&config.baseline, raw) {
+            eprintln!("Failed to write baseline {}: {}", config.baseline, e);
+            std::process::exit(1);
+        }
+        println!("Baseline written to {}", config.baseline);
+        return;
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -19,18 +19,21 @@ thiserror = "1"
 tracing = "0.1"
 tracing-subscriber = { version = "0.3", features = ["env-filter"] }
 tower-http = { version = "0.4", features = ["trace"] }
+serde_path_to_error = "0.1"
 */
 
 use axum::{
     async_trait,
-    extract::{Path, Query, State},
-    http::StatusCode,
+    body::Bytes,
+    extract::{rejection::JsonRejection, FromRequest, Path, Query, State},
+    http::{header, HeaderMap, Request, StatusCode},
     response::{IntoResponse, Response},
     routing::{delete, get, patch, post},
     Json, Router,
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::{
     collections::HashMap,
     net::SocketAddr,
@@ -84,6 +87,7 @@ pub struct CreateUserPayload {
     role: UserRole,
 }
 
+// Built from a JSON Patch or Merge Patch document; see `apply_patch`.
 #[derive(Deserialize)]
 pub struct UpdateUserPayload {
     email: Option<String>,
@@ -131,6 +135,14 @@ pub enum AppError {
     EmailAlreadyExists,
     #[error("Internal server error")]
     InternalServerError,
+    #[error("{0}")]
+    InvalidPatch(String),
+    #[error("Unsupported patch format '{0}'")]
+    UnsupportedMediaType(String),
+    #[error("{0}")]
+    PreconditionFailed(String),
+    #[error("{0}")]
+    Unprocessable(String),
 }
 
 impl IntoResponse for AppError {
@@ -139,11 +151,152 @@ impl IntoResponse for AppError {
             AppError::UserNotFound => (StatusCode::NOT_FOUND, self.to_string()),
             AppError::EmailAlreadyExists => (StatusCode::CONFLICT, self.to_string()),
             AppError::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
+            AppError::InvalidPatch(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::UnsupportedMediaType(_) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, self.to_string()),
+            AppError::PreconditionFailed(_) => (StatusCode::PRECONDITION_FAILED, self.to_string()),
+            AppError::Unprocessable(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
         };
         (status, Json(serde_json::json!({ "error": error_message }))).into_response()
     }
 }
 
+// --- JSON EXTRACTION ---
+
+// Drop-in for `Json` whose rejection is a problem+json body instead of axum's
+// plain-text message. Serde failures also report the line and column.
+pub struct AppJson<T>(pub T);
+
+#[async_trait]
+impl<S, B, T> FromRequest<S, B> for AppJson<T>
+where
+    Json<T>: FromRequest<S, B, Rejection = JsonRejection>,
+    S: Send + Sync,
+    B: Send + 'static,
+{
+    type Rejection = JsonProblem;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(AppJson(value)),
+            Err(rejection) => Err(JsonProblem(rejection)),
+        }
+    }
+}
+
+pub struct JsonProblem(JsonRejection);
+
+impl IntoResponse for JsonProblem {
+    fn into_response(self) -> Response {
+        let status = self.0.status();
+        let mut body = serde_json::json!({
+            "type": "about:blank",
+            "title": "Invalid request body",
+            "status": status.as_u16(),
+            "detail": self.0.body_text(),
+        });
+        if let Some(err) = find_source::<serde_path_to_error::Error<serde_json::Error>>(&self.0) {
+            body["line"] = err.inner().line().into();
+            body["column"] = err.inner().column().into();
+        }
+        (status, [(header::CONTENT_TYPE, "application/problem+json")], body.to_string()).into_response()
+    }
+}
+
+fn find_source<'a, E: std::error::Error + 'static>(err: &'a (dyn std::error::Error + 'static)) -> Option<&'a E> {
+    let mut source = err.source();
+    while let Some(err) = source {
+        if let Some(found) = err.downcast_ref::<E>() {
+            return Some(found);
+        }
+        source = err.source();
+    }
+    None
+}
+
+// --- PATCH DOCUMENTS ---
+
+// PATCH accepts RFC 6902 JSON Patch and RFC 7396 Merge Patch. Plain
+// `application/json` is read as a merge patch, which is what the endpoint
+// accepted before. Every patchable field is required, so a patch may
+// replace a field but never remove it or set it to `null`.
+const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+const PATCHABLE_FIELDS: [&str; 3] = ["email", "role", "is_active"];
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+fn field_from_pointer(pointer: &str) -> Result<&str, AppError> {
+    match pointer.strip_prefix('/') {
+        Some(field) if PATCHABLE_FIELDS.contains(&field) => Ok(field),
+        _ => Err(AppError::InvalidPatch(format!("Path '{}' is not patchable", pointer))),
+    }
+}
+
+fn removal_rejected(field: &str) -> AppError {
+    AppError::Unprocessable(format!("Field '{}' is not nullable and cannot be removed", field))
+}
+
+fn apply_patch(user: &User, content_type: &str, body: &[u8]) -> Result<UpdateUserPayload, AppError> {
+    let mut doc = Map::new();
+    doc.insert("email".to_string(), Value::from(user.email.clone()));
+    doc.insert("role".to_string(), serde_json::to_value(&user.role).map_err(|_| AppError::InternalServerError)?);
+    doc.insert("is_active".to_string(), Value::from(user.is_active));
+
+    match content_type.split(';').next().unwrap_or("").trim() {
+        JSON_PATCH_CONTENT_TYPE => {
+            let ops: Vec<PatchOperation> = serde_json::from_slice(body)
+                .map_err(|e| AppError::InvalidPatch(format!("Invalid JSON Patch document: {}", e)))?;
+            for op in ops {
+                match op {
+                    // Every patchable field always exists, so `add` behaves like `replace`
+                    PatchOperation::Add { path, value } | PatchOperation::Replace { path, value } => {
+                        doc.insert(field_from_pointer(&path)?.to_string(), value);
+                    }
+                    PatchOperation::Remove { path } => return Err(removal_rejected(field_from_pointer(&path)?)),
+                    PatchOperation::Move { from, path } => {
+                        let source = field_from_pointer(&from)?;
+                        if source != field_from_pointer(&path)? {
+                            return Err(removal_rejected(source));
+                        }
+                    }
+                    PatchOperation::Copy { from, path } => {
+                        let value = doc[field_from_pointer(&from)?].clone();
+                        doc.insert(field_from_pointer(&path)?.to_string(), value);
+                    }
+                    PatchOperation::Test { path, value } => {
+                        if doc[field_from_pointer(&path)?] != value {
+                            return Err(AppError::PreconditionFailed(format!("Test failed for path '{}'", path)));
+                        }
+                    }
+                }
+            }
+        }
+        MERGE_PATCH_CONTENT_TYPE | "application/json" => {
+            let patch: Map<String, Value> = serde_json::from_slice(body)
+                .map_err(|e| AppError::InvalidPatch(format!("Merge patch must be a JSON object: {}", e)))?;
+            for (key, value) in patch {
+                let field = field_from_pointer(&format!("/{}", key))?.to_string();
+                if value.is_null() {
+                    return Err(removal_rejected(&field));
+                }
+                doc.insert(field, value);
+            }
+        }
+        other => return Err(AppError::UnsupportedMediaType(other.to_string())),
+    }
+
+    serde_json::from_value(Value::Object(doc)).map_err(|e| AppError::InvalidPatch(e.to_string()))
+}
+
 // --- APPLICATION STATE ---
 
 type Db = Arc<RwLock<HashMap<Uuid, User>>>;
@@ -157,7 +310,7 @@ pub struct AppState {
 
 async fn create_user(
     State(state): State<AppState>,
-    Json(payload): Json<CreateUserPayload>,
+    AppJson(payload): AppJson<CreateUserPayload>,
 ) -> Result<(StatusCode, Json<UserResponse>), AppError> {
     let db = state.db.read().map_err(|_| AppError::InternalServerError)?;
     if db.values().any(|u| u.email == payload.email) {
@@ -212,10 +365,16 @@ async fn list_users(
 async fn update_user(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-    Json(payload): Json<UpdateUserPayload>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> Result<Json<UserResponse>, AppError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/json");
     let mut db = state.db.write().map_err(|_| AppError::InternalServerError)?;
     let user = db.get_mut(&id).ok_or(AppError::UserNotFound)?;
+    let payload = apply_patch(user, content_type, &body)?;
 
     if let Some(email) = payload.email {
         user.email = email;
@@ -311,4 +470,91 @@ fn populate_db(db: Db) {
             created_at: Utc::now(),
         },
     );
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn state_with_user() -> (AppState, Uuid) {
+        let id = Uuid::new_v4();
+        let user = User {
+            id,
+            email: "ada@example.com".to_string(),
+            password_hash: String::new(),
+            role: UserRole::ADMIN,
+            is_active: false,
+            created_at: Utc::now(),
+        };
+        let db = Db::default();
+        db.write().unwrap().insert(id, user);
+        (AppState { db }, id)
+    }
+
+    async fn patch_status(content_type: &str, body: Value) -> StatusCode {
+        let (state, id) = state_with_user();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+        let body = Bytes::from(serde_json::to_vec(&body).unwrap());
+        match update_user(State(state), Path(id), headers, body).await {
+            Ok(_) => StatusCode::OK,
+            Err(e) => e.into_response().status(),
+        }
+    }
+
+    #[tokio::test]
+    async fn json_patch_operations_map_to_statuses() {
+        let cases = [
+            (json!([{"op": "replace", "path": "/role", "value": "USER"}]), StatusCode::OK),
+            (json!([{"op": "replace", "path": "/is_active", "value": true}]), StatusCode::OK),
+            (json!([{"op": "add", "path": "/is_active", "value": true}]), StatusCode::OK),
+            (json!([{"op": "remove", "path": "/role"}]), StatusCode::UNPROCESSABLE_ENTITY),
+            (json!([{"op": "remove", "path": "/is_active"}]), StatusCode::UNPROCESSABLE_ENTITY),
+            (json!([{"op": "remove", "path": "/password_hash"}]), StatusCode::BAD_REQUEST),
+            (json!([{"op": "move", "from": "/role", "path": "/email"}]), StatusCode::UNPROCESSABLE_ENTITY),
+            (json!([{"op": "test", "path": "/role", "value": "USER"}]), StatusCode::PRECONDITION_FAILED),
+            (json!([{"op": "replace", "path": "/is_active", "value": "yes"}]), StatusCode::BAD_REQUEST),
+        ];
+        for (body, expected) in cases {
+            assert_eq!(patch_status(JSON_PATCH_CONTENT_TYPE, body.clone()).await, expected, "{}", body);
+        }
+    }
+
+    #[tokio::test]
+    async fn merge_patch_fields_map_to_statuses() {
+        let cases = [
+            (json!({"role": "USER", "is_active": true}), StatusCode::OK),
+            (json!({"role": null}), StatusCode::UNPROCESSABLE_ENTITY),
+            (json!({"is_active": null}), StatusCode::UNPROCESSABLE_ENTITY),
+            (json!({"role": "ROOT"}), StatusCode::BAD_REQUEST),
+            (json!({"id": "x"}), StatusCode::BAD_REQUEST),
+        ];
+        for (body, expected) in cases {
+            assert_eq!(patch_status(MERGE_PATCH_CONTENT_TYPE, body.clone()).await, expected, "{}", body);
+            assert_eq!(patch_status("application/json", body.clone()).await, expected, "{}", body);
+        }
+        assert_eq!(patch_status("text/plain", json!({})).await, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn replaced_fields_are_applied_and_the_rest_kept() {
+        let (state, id) = state_with_user();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, JSON_PATCH_CONTENT_TYPE.parse().unwrap());
+        let body = Bytes::from_static(br#"[{"op": "replace", "path": "/role", "value": "USER"}]"#);
+        assert!(update_user(State(state.clone()), Path(id), headers, body).await.is_ok());
+
+        let user = state.db.read().unwrap()[&id].clone();
+        assert_eq!(user.role, UserRole::USER);
+        assert!(!user.is_active);
+        assert_eq!(user.email, "ada@example.com");
+    }
+
+    #[tokio::test]
+    async fn patching_a_missing_user_is_not_found() {
+        let (state, _) = state_with_user();
+        let body = Bytes::from_static(br#"{"is_active": true}"#);
+        let err = update_user(State(state), Path(Uuid::new_v4()), HeaderMap::new(), body).await.err().unwrap();
+        assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -23,12 +23,31 @@ tracing = "0.1"
 tracing-subscriber = { version = "0.3", features = ["env-filter"] }
 tower-http = { version = "0.4", features = ["trace"] }
 async-trait = "0.1"
+utoipa = { version = "3", features = ["axum_extras", "uuid", "chrono"] }
+utoipa-swagger-ui = { version = "3", features = ["axum"] }
+jsonwebtoken = "9"
+
+[dev-dependencies]
+tower = { version = "0.4", features = ["util"] }
+proptest = "1"
+
+[features]
+# Lets the load test mint its own admin tokens
+loadtest = []
+
+# In-process load test: cargo bench --features loadtest --bench loadtest_variation_2
+[[bench]]
+name = "loadtest_variation_2"
+path = "benches/loadtest_variation_2.rs"
+harness = false
+required-features = ["loadtest"]
 */
 
 use axum::{
     async_trait,
-    extract::{Path, Query, State},
-    http::StatusCode,
+    body::Bytes,
+    extract::{FromRequestParts, OriginalUri, Path, Query, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     routing::{delete, get, patch, post},
     Json, Router,
@@ -43,13 +62,15 @@ use std::{
 use thiserror::Error;
 use tower_http::trace::{DefaultMakeSpan, TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
 use uuid::Uuid;
 
 // --- 1. Models (domain.rs) ---
-mod domain {
+pub(crate) mod domain {
     use super::*;
 
-    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
     pub enum UserRole {
         ADMIN,
         USER,
@@ -86,21 +107,25 @@ mod dtos {
     use super::domain::*;
     use super::*;
 
-    #[derive(Deserialize)]
+    #[derive(Deserialize, ToSchema)]
+    #[schema(example = json!({"email": "jane@example.com", "password": "correct horse", "role": "USER"}))]
     pub struct CreateUserPayload {
         pub email: String,
         pub password: String,
         pub role: UserRole,
     }
 
-    #[derive(Deserialize, Default)]
+    /// Merge-patch view of a user. Every field is optional, but none may be `null`.
+    #[derive(Deserialize, Default, ToSchema)]
+    #[schema(example = json!({"email": "jane.doe@example.com", "is_active": false}))]
     pub struct UpdateUserPayload {
         pub email: Option<String>,
         pub role: Option<UserRole>,
         pub is_active: Option<bool>,
     }
 
-    #[derive(Deserialize, Debug, Default)]
+    #[derive(Deserialize, Debug, Default, IntoParams)]
+    #[into_params(parameter_in = Query)]
     pub struct ListUsersParams {
         pub offset: Option<usize>,
         pub limit: Option<usize>,
@@ -108,7 +133,14 @@ mod dtos {
         pub is_active: Option<bool>,
     }
 
-    #[derive(Serialize)]
+    #[derive(Serialize, ToSchema)]
+    #[schema(example = json!({
+        "id": "6f1c2a4e-3b7d-4c1e-9a8f-2d5b6c7e8f90",
+        "email": "jane@example.com",
+        "role": "USER",
+        "is_active": true,
+        "created_at": "2024-01-01T00:00:00Z"
+    }))]
     pub struct UserResponse {
         pub id: Uuid,
         pub email: String,
@@ -128,6 +160,472 @@ mod dtos {
             }
         }
     }
+
+    #[derive(Deserialize, Debug, Default, Clone)]
+    pub struct AdminUserFilter {
+        pub created_from: Option<DateTime<Utc>>,
+        pub created_to: Option<DateTime<Utc>>,
+        pub email_contains: Option<String>,
+        pub role: Option<UserRole>,
+        pub is_active: Option<bool>,
+        pub offset: Option<usize>,
+        pub limit: Option<usize>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct BulkDeactivatePayload {
+        pub ids: Vec<Uuid>,
+    }
+
+    #[derive(Serialize)]
+    pub struct BulkDeactivateResponse {
+        pub deactivated: Vec<Uuid>,
+        pub not_found: Vec<Uuid>,
+    }
+}
+
+// --- 2. DTOs & API Payloads (envelope.rs) ---
+/// Standard `{ data, links, meta }` response envelope with HATEOAS links.
+mod envelope {
+    use super::dtos::*;
+    use super::*;
+    use axum::http::{HeaderValue, Uri};
+
+    #[derive(Serialize, Debug, Default, ToSchema)]
+    pub struct Links {
+        #[serde(rename = "self")]
+        pub self_link: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub next: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub prev: Option<String>,
+    }
+
+    #[derive(Serialize, Debug, ToSchema)]
+    pub struct PageMeta {
+        pub total: usize,
+        pub offset: usize,
+        pub limit: usize,
+    }
+
+    #[derive(Serialize, Debug, ToSchema)]
+    #[aliases(UserEnvelope = ResponseEnvelope<UserResponse>, UserPageEnvelope = ResponseEnvelope<Vec<UserResponse>>)]
+    pub struct ResponseEnvelope<T> {
+        pub data: T,
+        pub links: Links,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub meta: Option<PageMeta>,
+    }
+
+    /// Resources addressable as `<collection>/<segment>`. The collection comes from the
+    /// request URI, so links stay correct wherever the router is mounted.
+    pub trait Linkable {
+        fn path_segment(&self) -> String;
+
+        fn into_envelope(self, collection: &str) -> ResponseEnvelope<Self>
+        where
+            Self: Sized,
+        {
+            let self_link = format!("{}/{}", collection.trim_end_matches('/'), self.path_segment());
+            let links = Links { self_link, ..Default::default() };
+            ResponseEnvelope { data: self, links, meta: None }
+        }
+    }
+
+    impl Linkable for UserResponse {
+        fn path_segment(&self) -> String {
+            self.id.to_string()
+        }
+    }
+
+    /// The collection an item URI such as `/users/{id}` belongs to.
+    pub fn collection_of(uri: &Uri) -> &str {
+        uri.path().trim_end_matches('/').rsplit_once('/').map_or("", |(collection, _)| collection)
+    }
+
+    /// Rebuilds `uri` with its `offset`/`limit` query parameters replaced, keeping any filters.
+    fn page_uri(uri: &Uri, offset: usize, limit: usize) -> String {
+        let mut pairs: Vec<String> = uri
+            .query()
+            .unwrap_or("")
+            .split('&')
+            .filter(|pair| !pair.is_empty() && !pair.starts_with("offset=") && !pair.starts_with("limit="))
+            .map(str::to_string)
+            .collect();
+        pairs.push(format!("offset={}", offset));
+        pairs.push(format!("limit={}", limit));
+        format!("{}?{}", uri.path(), pairs.join("&"))
+    }
+
+    impl<T> ResponseEnvelope<Vec<T>> {
+        /// Wraps one page of a collection, deriving self/next/prev links from the request URI.
+        pub fn page(data: Vec<T>, uri: &Uri, offset: usize, limit: usize, total: usize) -> Self {
+            let next = (offset + limit < total).then(|| page_uri(uri, offset + limit, limit));
+            let prev = (offset > 0).then(|| page_uri(uri, offset.saturating_sub(limit), limit));
+            ResponseEnvelope {
+                data,
+                links: Links { self_link: page_uri(uri, offset, limit), next, prev },
+                meta: Some(PageMeta { total, offset, limit }),
+            }
+        }
+    }
+
+    impl<T> ResponseEnvelope<T> {
+        /// RFC 8288 `Link` header mirroring the envelope's navigation links.
+        pub fn link_header(&self) -> Option<HeaderValue> {
+            let mut parts = vec![format!("<{}>; rel=\"self\"", self.links.self_link)];
+            if let Some(next) = &self.links.next {
+                parts.push(format!("<{}>; rel=\"next\"", next));
+            }
+            if let Some(prev) = &self.links.prev {
+                parts.push(format!("<{}>; rel=\"prev\"", prev));
+            }
+            HeaderValue::from_str(&parts.join(", ")).ok()
+        }
+    }
+}
+
+// --- 2. DTOs & API Payloads (fields.rs) ---
+/// Sparse fieldsets: `?fields[user]=id,email` trims every serialized `UserResponse` to
+/// the listed fields. Masks are applied at serialization time, so envelopes and links
+/// are built from the full resource as usual.
+mod fields {
+    use super::dtos::*;
+    use super::envelope::*;
+    use super::errors::*;
+    use super::*;
+    use axum::http::Uri;
+    use serde::{ser::Error as _, Serializer};
+    use serde_json::Value;
+    use std::marker::PhantomData;
+
+    /// Resources that support `fields[<RESOURCE>]`; `FIELDS` is the allowlist.
+    pub trait SparseFields: Serialize {
+        const RESOURCE: &'static str;
+        const FIELDS: &'static [&'static str];
+    }
+
+    impl SparseFields for UserResponse {
+        const RESOURCE: &'static str = "user";
+        const FIELDS: &'static [&'static str] = &["id", "email", "role", "is_active", "created_at"];
+    }
+
+    /// The fields requested for `T`; no `fields[...]` parameter keeps every field.
+    pub struct FieldMask<T> {
+        fields: Option<Arc<[&'static str]>>,
+        _resource: PhantomData<fn() -> T>,
+    }
+
+    impl<T: SparseFields> FieldMask<T> {
+        pub fn from_uri(uri: &Uri) -> Result<Self, AppError> {
+            let Query(pairs) = Query::<Vec<(String, String)>>::try_from_uri(uri)
+                .map_err(|_| AppError::ValidationError("Malformed query string".to_string()))?;
+
+            let mut fields: Option<Vec<&'static str>> = None;
+            for (key, value) in pairs {
+                let Some(resource) = key.strip_prefix("fields[").and_then(|k| k.strip_suffix(']')) else { continue };
+                if resource != T::RESOURCE {
+                    return Err(AppError::ValidationError(format!(
+                        "Unknown resource type '{}' in {}; expected fields[{}]",
+                        resource, key, T::RESOURCE
+                    )));
+                }
+                let selected = fields.get_or_insert_with(Vec::new);
+                for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                    let field = T::FIELDS.iter().copied().find(|f| *f == name).ok_or_else(|| {
+                        AppError::ValidationError(format!(
+                            "Unknown field '{}' in {}; expected any of {}",
+                            name, key, T::FIELDS.join(", ")
+                        ))
+                    })?;
+                    if !selected.contains(&field) {
+                        selected.push(field);
+                    }
+                }
+                if selected.is_empty() {
+                    return Err(AppError::ValidationError(format!("{} must list at least one field", key)));
+                }
+            }
+            Ok(Self { fields: fields.map(Into::into), _resource: PhantomData })
+        }
+
+        pub fn apply(&self, value: T) -> Sparse<T> {
+            Sparse { value, fields: self.fields.clone() }
+        }
+    }
+
+    #[async_trait]
+    impl<T: SparseFields, S: Send + Sync> FromRequestParts<S> for FieldMask<T> {
+        type Rejection = AppError;
+
+        async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+            Self::from_uri(&parts.uri)
+        }
+    }
+
+    /// `T` serialized through a `FieldMask`.
+    pub struct Sparse<T> {
+        value: T,
+        fields: Option<Arc<[&'static str]>>,
+    }
+
+    impl<T: Serialize> Serialize for Sparse<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let Some(fields) = &self.fields else { return self.value.serialize(serializer) };
+            let Value::Object(mut map) = serde_json::to_value(&self.value).map_err(S::Error::custom)? else {
+                return Err(S::Error::custom("sparse fieldsets only apply to objects"));
+            };
+            map.retain(|key, _| fields.contains(&key.as_str()));
+            map.serialize(serializer)
+        }
+    }
+
+    impl<T: Linkable> Linkable for Sparse<T> {
+        fn path_segment(&self) -> String {
+            self.value.path_segment()
+        }
+    }
+}
+
+// --- 2. DTOs & API Payloads (patch.rs) ---
+/// PATCH support for users: RFC 7386 JSON Merge Patch and RFC 6902 JSON Patch.
+///
+/// Both formats are applied to a JSON view of the patchable fields and the result is
+/// converted back into a fully-populated `UpdateUserPayload`. None of those fields is
+/// nullable, so removing one (JSON Patch `remove` or `move`, or `null` in a merge patch)
+/// is rejected with 422 instead of being silently ignored. A failed `test` operation is
+/// a 412.
+mod patch {
+    use super::domain::*;
+    use super::dtos::*;
+    use super::errors::*;
+    use super::*;
+    use serde_json::{Map, Value};
+
+    pub const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+    pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+    const PATCHABLE_FIELDS: [&str; 3] = ["email", "role", "is_active"];
+
+    #[derive(Deserialize, Debug)]
+    #[serde(tag = "op", rename_all = "lowercase")]
+    pub enum PatchOperation {
+        Add { path: String, value: Value },
+        Remove { path: String },
+        Replace { path: String, value: Value },
+        Move { from: String, path: String },
+        Copy { from: String, path: String },
+        Test { path: String, value: Value },
+    }
+
+    pub enum PatchDocument {
+        Merge(Map<String, Value>),
+        Json(Vec<PatchOperation>),
+    }
+
+    impl PatchDocument {
+        /// Parses a request body according to its `Content-Type`. Plain `application/json`
+        /// is treated as a merge patch for backwards compatibility.
+        pub fn parse(content_type: &str, body: &[u8]) -> Result<Self, AppError> {
+            let media_type = content_type.split(';').next().unwrap_or("").trim();
+            match media_type {
+                JSON_PATCH_CONTENT_TYPE => serde_json::from_slice::<Vec<PatchOperation>>(body)
+                    .map(PatchDocument::Json)
+                    .map_err(|e| AppError::ValidationError(format!("Invalid JSON Patch document: {}", e))),
+                MERGE_PATCH_CONTENT_TYPE | "application/json" => match serde_json::from_slice::<Value>(body) {
+                    Ok(Value::Object(map)) => Ok(PatchDocument::Merge(map)),
+                    Ok(_) => Err(AppError::ValidationError("Merge patch must be a JSON object".to_string())),
+                    Err(e) => Err(AppError::ValidationError(format!("Invalid JSON: {}", e))),
+                },
+                other => Err(AppError::UnsupportedMediaType(other.to_string())),
+            }
+        }
+    }
+
+    /// Maps a JSON Pointer such as `/is_active` to a patchable field name.
+    fn field_from_pointer(pointer: &str) -> Result<&str, AppError> {
+        let field = pointer
+            .strip_prefix('/')
+            .ok_or_else(|| AppError::ValidationError(format!("Invalid JSON Pointer '{}'", pointer)))?;
+        if PATCHABLE_FIELDS.contains(&field) {
+            Ok(field)
+        } else {
+            Err(AppError::ValidationError(format!("Path '{}' is not patchable", pointer)))
+        }
+    }
+
+    fn removal_rejected(field: &str) -> AppError {
+        AppError::Unprocessable(format!("Field '{}' is not nullable and cannot be removed", field))
+    }
+
+    fn apply_operations(doc: &mut Map<String, Value>, ops: Vec<PatchOperation>) -> Result<(), AppError> {
+        for op in ops {
+            match op {
+                // Every patchable field always exists, so `add` behaves like `replace`
+                PatchOperation::Add { path, value } | PatchOperation::Replace { path, value } => {
+                    let field = field_from_pointer(&path)?;
+                    doc.insert(field.to_string(), value);
+                }
+                PatchOperation::Remove { path } => return Err(removal_rejected(field_from_pointer(&path)?)),
+                PatchOperation::Copy { from, path } => {
+                    let value = doc[field_from_pointer(&from)?].clone();
+                    doc.insert(field_from_pointer(&path)?.to_string(), value);
+                }
+                PatchOperation::Move { from, path } => {
+                    let source = field_from_pointer(&from)?;
+                    let target = field_from_pointer(&path)?;
+                    if source != target {
+                        return Err(removal_rejected(source));
+                    }
+                }
+                PatchOperation::Test { path, value } => {
+                    let field = field_from_pointer(&path)?;
+                    if doc[field] != value {
+                        return Err(AppError::PreconditionFailed(format!("Test failed for path '{}'", path)));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_merge(doc: &mut Map<String, Value>, patch: Map<String, Value>) -> Result<(), AppError> {
+        for (key, value) in patch {
+            let field = PATCHABLE_FIELDS
+                .iter()
+                .find(|f| **f == key)
+                .ok_or_else(|| AppError::ValidationError(format!("Field '{}' is not patchable", key)))?;
+            if value.is_null() {
+                return Err(removal_rejected(field));
+            }
+            doc.insert(key, value);
+        }
+        Ok(())
+    }
+
+    /// Applies `patch` to `current`, returning the complete set of new field values.
+    pub fn apply(current: &User, patch: PatchDocument) -> Result<UpdateUserPayload, AppError> {
+        let mut doc = Map::new();
+        doc.insert("email".to_string(), Value::String(current.email.clone()));
+        doc.insert("role".to_string(), serde_json::to_value(&current.role).map_err(|_| AppError::Repo(RepoError::Internal))?);
+        doc.insert("is_active".to_string(), Value::Bool(current.is_active));
+
+        match patch {
+            PatchDocument::Merge(map) => apply_merge(&mut doc, map)?,
+            PatchDocument::Json(ops) => apply_operations(&mut doc, ops)?,
+        }
+
+        let email: String = serde_json::from_value(doc.remove("email").unwrap_or(Value::Null))
+            .map_err(|_| AppError::ValidationError("'email' must be a string".to_string()))?;
+        if !email.contains('@') {
+            return Err(AppError::ValidationError("'email' must be a valid email address".to_string()));
+        }
+        let role: UserRole = serde_json::from_value(doc.remove("role").unwrap_or(Value::Null))
+            .map_err(|_| AppError::ValidationError("'role' must be one of ADMIN, USER".to_string()))?;
+        let is_active: bool = serde_json::from_value(doc.remove("is_active").unwrap_or(Value::Null))
+            .map_err(|_| AppError::ValidationError("'is_active' must be a boolean".to_string()))?;
+
+        Ok(UpdateUserPayload {
+            email: Some(email),
+            role: Some(role),
+            is_active: Some(is_active),
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+
+        fn user() -> User {
+            User {
+                id: Uuid::nil(),
+                email: "ada@example.com".to_string(),
+                password_hash: String::new(),
+                role: UserRole::ADMIN,
+                is_active: false,
+                created_at: Utc::now(),
+            }
+        }
+
+        fn status(content_type: &str, body: Value) -> StatusCode {
+            let body = serde_json::to_vec(&body).unwrap();
+            match PatchDocument::parse(content_type, &body).and_then(|doc| apply(&user(), doc)) {
+                Ok(_) => StatusCode::OK,
+                Err(e) => e.into_response().status(),
+            }
+        }
+
+        #[test]
+        fn json_patch_operations_map_to_statuses() {
+            let cases = [
+                (json!([{"op": "replace", "path": "/email", "value": "b@example.com"}]), StatusCode::OK),
+                (json!([{"op": "add", "path": "/is_active", "value": true}]), StatusCode::OK),
+                (json!([{"op": "remove", "path": "/role"}]), StatusCode::UNPROCESSABLE_ENTITY),
+                (json!([{"op": "remove", "path": "/is_active"}]), StatusCode::UNPROCESSABLE_ENTITY),
+                (json!([{"op": "remove", "path": "/email"}]), StatusCode::UNPROCESSABLE_ENTITY),
+                (json!([{"op": "remove", "path": "/password_hash"}]), StatusCode::BAD_REQUEST),
+                (json!([{"op": "move", "from": "/email", "path": "/role"}]), StatusCode::UNPROCESSABLE_ENTITY),
+                (json!([{"op": "move", "from": "/role", "path": "/role"}]), StatusCode::OK),
+                (json!([{"op": "replace", "path": "/role", "value": "USER"}, {"op": "replace", "path": "/is_active", "value": true}]), StatusCode::OK),
+                (json!([{"op": "copy", "from": "/email", "path": "/email"}]), StatusCode::OK),
+                (json!([{"op": "test", "path": "/role", "value": "ADMIN"}]), StatusCode::OK),
+                (json!([{"op": "test", "path": "/role", "value": "USER"}]), StatusCode::PRECONDITION_FAILED),
+                (json!([{"op": "replace", "path": "/password_hash", "value": "x"}]), StatusCode::BAD_REQUEST),
+                (json!([{"op": "replace", "path": "/is_active", "value": "yes"}]), StatusCode::BAD_REQUEST),
+                (json!({"op": "replace"}), StatusCode::BAD_REQUEST),
+            ];
+            for (body, expected) in cases {
+                assert_eq!(status(JSON_PATCH_CONTENT_TYPE, body.clone()), expected, "{}", body);
+            }
+        }
+
+        #[test]
+        fn merge_patch_fields_map_to_statuses() {
+            let cases = [
+                (json!({"email": "b@example.com"}), StatusCode::OK),
+                (json!({"role": "USER", "is_active": true}), StatusCode::OK),
+                (json!({"role": null}), StatusCode::UNPROCESSABLE_ENTITY),
+                (json!({"is_active": null}), StatusCode::UNPROCESSABLE_ENTITY),
+                (json!({"email": null}), StatusCode::UNPROCESSABLE_ENTITY),
+                (json!({"email": "not-an-address"}), StatusCode::BAD_REQUEST),
+                (json!({"role": "ROOT"}), StatusCode::BAD_REQUEST),
+                (json!({"id": "x"}), StatusCode::BAD_REQUEST),
+                (json!([]), StatusCode::BAD_REQUEST),
+            ];
+            for (body, expected) in cases {
+                assert_eq!(status(MERGE_PATCH_CONTENT_TYPE, body.clone()), expected, "{}", body);
+            }
+        }
+
+        #[test]
+        fn replaced_fields_are_applied_and_the_rest_kept() {
+            let body = br#"[{"op": "replace", "path": "/role", "value": "USER"}, {"op": "replace", "path": "/is_active", "value": true}]"#;
+            let payload = apply(&user(), PatchDocument::parse(JSON_PATCH_CONTENT_TYPE, body).unwrap()).unwrap();
+            assert_eq!(payload.role, Some(UserRole::USER));
+            assert_eq!(payload.is_active, Some(true));
+            assert_eq!(payload.email.as_deref(), Some("ada@example.com"));
+
+            let doc = PatchDocument::parse(MERGE_PATCH_CONTENT_TYPE, br#"{"is_active": true}"#).unwrap();
+            let payload = apply(&user(), doc).unwrap();
+            assert_eq!(payload.role, Some(UserRole::ADMIN));
+            assert_eq!(payload.is_active, Some(true));
+        }
+
+        #[test]
+        fn removing_a_field_names_it_in_the_error() {
+            let doc = PatchDocument::parse(JSON_PATCH_CONTENT_TYPE, br#"[{"op": "remove", "path": "/is_active"}]"#).unwrap();
+            match apply(&user(), doc) {
+                Err(AppError::Unprocessable(msg)) => assert!(msg.contains("'is_active'"), "{}", msg),
+                other => panic!("expected 422, got {:?}", other.map(|_| ())),
+            }
+        }
+
+        #[test]
+        fn unknown_content_type_is_unsupported() {
+            assert_eq!(status("text/plain", json!({})), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        }
+    }
 }
 
 // --- 3. Error Handling (errors.rs) ---
@@ -140,6 +638,23 @@ mod errors {
         Repo(#[from] RepoError),
         #[error("Validation error: {0}")]
         ValidationError(String),
+        #[error("Unauthorized")]
+        Unauthorized,
+        #[error("Forbidden")]
+        Forbidden,
+        #[error("Unsupported media type: {0}")]
+        UnsupportedMediaType(String),
+        #[error("Precondition failed: {0}")]
+        PreconditionFailed(String),
+        #[error("Unprocessable: {0}")]
+        Unprocessable(String),
+    }
+
+    /// JSON body of every error response.
+    #[derive(Serialize, ToSchema)]
+    #[schema(example = json!({"error": "Resource not found"}))]
+    pub struct ErrorBody {
+        pub error: String,
     }
 
     #[derive(Debug, Error)]
@@ -158,9 +673,17 @@ mod errors {
                 AppError::Repo(RepoError::NotFound) => (StatusCode::NOT_FOUND, "Resource not found".to_string()),
                 AppError::Repo(RepoError::Conflict(msg)) => (StatusCode::CONFLICT, msg),
                 AppError::ValidationError(msg) => (StatusCode::BAD_REQUEST, msg),
+                AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Authentication required".to_string()),
+                AppError::Forbidden => (StatusCode::FORBIDDEN, "Administrator role required".to_string()),
+                AppError::UnsupportedMediaType(media_type) => (
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    format!("Unsupported Content-Type '{}'", media_type),
+                ),
+                AppError::PreconditionFailed(msg) => (StatusCode::PRECONDITION_FAILED, msg),
+                AppError::Unprocessable(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
                 _ => (StatusCode::INTERNAL_SERVER_ERROR, "An internal error occurred".to_string()),
             };
-            (status, Json(serde_json::json!({ "error": error_message }))).into_response()
+            (status, Json(ErrorBody { error: error_message })).into_response()
         }
     }
 }
@@ -178,8 +701,12 @@ mod user_repository {
         async fn find_by_id(&self, id: Uuid) -> Result<User, RepoError>;
         async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepoError>;
         async fn find_all(&self, params: ListUsersParams) -> Result<Vec<User>, RepoError>;
+        async fn count(&self, params: &ListUsersParams) -> Result<usize, RepoError>;
         async fn update(&self, id: Uuid, payload: UpdateUserPayload) -> Result<User, RepoError>;
         async fn delete(&self, id: Uuid) -> Result<(), RepoError>;
+        async fn search(&self, filter: &AdminUserFilter) -> Result<Vec<User>, RepoError>;
+        /// Deactivates every existing user in `ids` atomically, returning the ids that were found.
+        async fn deactivate_many(&self, ids: &[Uuid]) -> Result<Vec<Uuid>, RepoError>;
     }
 
     type Db = Arc<RwLock<HashMap<Uuid, User>>>;
@@ -215,15 +742,28 @@ mod user_repository {
 
         async fn find_all(&self, params: ListUsersParams) -> Result<Vec<User>, RepoError> {
             let db = self.db.read().map_err(|_| RepoError::Internal)?;
-            let users = db
+            let mut users: Vec<User> = db
                 .values()
                 .filter(|user| params.role.as_ref().map_or(true, |role| &user.role == role))
                 .filter(|user| params.is_active.map_or(true, |is_active| user.is_active == is_active))
                 .cloned()
+                .collect();
+            // HashMap iteration order is arbitrary; pages (and their snapshots) must be stable
+            users.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+            Ok(users
+                .into_iter()
                 .skip(params.offset.unwrap_or(0))
                 .take(params.limit.unwrap_or(10))
-                .collect();
-            Ok(users)
+                .collect())
+        }
+
+        async fn count(&self, params: &ListUsersParams) -> Result<usize, RepoError> {
+            let db = self.db.read().map_err(|_| RepoError::Internal)?;
+            Ok(db
+                .values()
+                .filter(|user| params.role.as_ref().map_or(true, |role| &user.role == role))
+                .filter(|user| params.is_active.map_or(true, |is_active| user.is_active == is_active))
+                .count())
         }
 
         async fn update(&self, id: Uuid, payload: UpdateUserPayload) -> Result<User, RepoError> {
@@ -243,6 +783,73 @@ mod user_repository {
                 Err(RepoError::NotFound)
             }
         }
+
+        async fn search(&self, filter: &AdminUserFilter) -> Result<Vec<User>, RepoError> {
+            let db = self.db.read().map_err(|_| RepoError::Internal)?;
+            let needle = filter.email_contains.as_ref().map(|s| s.to_lowercase());
+            let mut users: Vec<User> = db
+                .values()
+                .filter(|user| filter.created_from.map_or(true, |from| user.created_at >= from))
+                .filter(|user| filter.created_to.map_or(true, |to| user.created_at <= to))
+                .filter(|user| needle.as_ref().map_or(true, |n| user.email.to_lowercase().contains(n)))
+                .filter(|user| filter.role.as_ref().map_or(true, |role| &user.role == role))
+                .filter(|user| filter.is_active.map_or(true, |is_active| user.is_active == is_active))
+                .cloned()
+                .collect();
+            // HashMap iteration order is arbitrary; keep exports and pages stable
+            users.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+            Ok(users
+                .into_iter()
+                .skip(filter.offset.unwrap_or(0))
+                .take(filter.limit.unwrap_or(usize::MAX))
+                .collect())
+        }
+
+        async fn deactivate_many(&self, ids: &[Uuid]) -> Result<Vec<Uuid>, RepoError> {
+            let mut db = self.db.write().map_err(|_| RepoError::Internal)?;
+            let mut deactivated = Vec::new();
+            for id in ids {
+                if let Some(user) = db.get_mut(id) {
+                    user.is_active = false;
+                    deactivated.push(*id);
+                }
+            }
+            Ok(deactivated)
+        }
+    }
+}
+
+// --- 4. Repository Layer (audit_repository.rs) ---
+mod audit_repository {
+    use super::errors::*;
+    use super::*;
+
+    #[derive(Debug, Serialize, Clone)]
+    pub struct AuditEntry {
+        pub id: Uuid,
+        pub actor_id: Uuid,
+        pub action: String,
+        pub target_user_id: Uuid,
+        pub at: DateTime<Utc>,
+    }
+
+    #[async_trait]
+    pub trait AuditRepository: Send + Sync {
+        async fn record_many(&self, entries: Vec<AuditEntry>) -> Result<(), RepoError>;
+    }
+
+    #[derive(Clone, Default)]
+    pub struct InMemoryAuditRepository {
+        entries: Arc<RwLock<Vec<AuditEntry>>>,
+    }
+
+    #[async_trait]
+    impl AuditRepository for InMemoryAuditRepository {
+        async fn record_many(&self, entries: Vec<AuditEntry>) -> Result<(), RepoError> {
+            let mut log = self.entries.write().map_err(|_| RepoError::Internal)?;
+            log.extend(entries);
+            Ok(())
+        }
     }
 }
 
@@ -287,57 +894,309 @@ mod user_service {
             self.repo.find_all(params).await.map_err(AppError::from)
         }
 
+        pub async fn count_users(&self, params: &ListUsersParams) -> Result<usize, AppError> {
+            self.repo.count(params).await.map_err(AppError::from)
+        }
+
         pub async fn update_user(&self, id: Uuid, payload: UpdateUserPayload) -> Result<User, AppError> {
             self.repo.update(id, payload).await.map_err(AppError::from)
         }
 
+        pub async fn patch_user(&self, id: Uuid, document: patch::PatchDocument) -> Result<User, AppError> {
+            let current = self.repo.find_by_id(id).await?;
+            let payload = patch::apply(&current, document)?;
+            if let Some(email) = payload.email.as_deref() {
+                if email != current.email {
+                    if let Some(other) = self.repo.find_by_email(email).await? {
+                        if other.id != id {
+                            return Err(AppError::Repo(RepoError::Conflict("Email already exists".to_string())));
+                        }
+                    }
+                }
+            }
+            self.repo.update(id, payload).await.map_err(AppError::from)
+        }
+
         pub async fn delete_user(&self, id: Uuid) -> Result<(), AppError> {
             self.repo.delete(id).await.map_err(AppError::from)
         }
     }
 }
 
+// --- 5. Service Layer (tokens.rs) ---
+pub(crate) mod tokens {
+    use super::errors::*;
+    use super::*;
+    use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+
+    /// Fixed key for the in-process harnesses, which mint their own tokens.
+    #[cfg(test)]
+    pub const HARNESS_SECRET: &[u8] = b"in-process-harness-only";
+
+    #[derive(Serialize, Deserialize)]
+    struct Claims {
+        sub: Uuid,
+        exp: usize,
+    }
+
+    /// Verifies HS256 bearer tokens. Only `sub` is trusted; role and active status
+    /// are still checked against the repository on every request.
+    #[derive(Clone)]
+    pub struct TokenKeys {
+        decoding: Arc<DecodingKey>,
+        #[cfg(test)]
+        encoding: Arc<jsonwebtoken::EncodingKey>,
+    }
+
+    impl TokenKeys {
+        pub fn from_secret(secret: &[u8]) -> Self {
+            Self {
+                decoding: Arc::new(DecodingKey::from_secret(secret)),
+                #[cfg(test)]
+                encoding: Arc::new(jsonwebtoken::EncodingKey::from_secret(secret)),
+            }
+        }
+
+        /// Reads `JWT_SECRET`; the server refuses to start without one.
+        pub fn from_env() -> Self {
+            let secret = std::env::var("JWT_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .expect("JWT_SECRET must be set");
+            Self::from_secret(secret.as_bytes())
+        }
+
+        #[cfg(test)]
+        pub fn issue(&self, user_id: Uuid) -> String {
+            let exp = (Utc::now() + chrono::Duration::hours(1)).timestamp() as usize;
+            jsonwebtoken::encode(&jsonwebtoken::Header::default(), &Claims { sub: user_id, exp }, &self.encoding)
+                .expect("HS256 signing cannot fail")
+        }
+
+        /// The user named by an `Authorization: Bearer <jwt>` header.
+        pub fn verify(&self, headers: &HeaderMap) -> Result<Uuid, AppError> {
+            let token = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .ok_or(AppError::Unauthorized)?;
+            decode::<Claims>(token, &self.decoding, &Validation::new(Algorithm::HS256))
+                .map(|data| data.claims.sub)
+                .map_err(|_| AppError::Unauthorized)
+        }
+    }
+}
+
+// --- 5. Service Layer (admin_service.rs) ---
+mod admin_service {
+    use super::audit_repository::*;
+    use super::domain::*;
+    use super::dtos::*;
+    use super::errors::*;
+    use super::tokens::TokenKeys;
+    use super::user_repository::*;
+    use super::*;
+
+    #[derive(Clone)]
+    pub struct AdminService {
+        users: Arc<dyn UserRepository>,
+        audit: Arc<dyn AuditRepository>,
+        tokens: TokenKeys,
+    }
+
+    impl AdminService {
+        pub fn new(users: Arc<dyn UserRepository>, audit: Arc<dyn AuditRepository>, tokens: TokenKeys) -> Self {
+            Self { users, audit, tokens }
+        }
+
+        /// Resolves the bearer-token caller and checks they are an active administrator.
+        pub async fn authorize_admin(&self, headers: &HeaderMap) -> Result<User, AppError> {
+            let user_id = self.tokens.verify(headers)?;
+            match self.users.find_by_id(user_id).await {
+                Ok(user) if user.role == UserRole::ADMIN && user.is_active => Ok(user),
+                Ok(_) => Err(AppError::Forbidden),
+                Err(RepoError::NotFound) => Err(AppError::Unauthorized),
+                Err(e) => Err(e.into()),
+            }
+        }
+
+        pub async fn search_users(&self, filter: &AdminUserFilter) -> Result<Vec<User>, AppError> {
+            if let (Some(from), Some(to)) = (filter.created_from, filter.created_to) {
+                if from > to {
+                    return Err(AppError::ValidationError("created_from must not be after created_to".to_string()));
+                }
+            }
+            self.users.search(filter).await.map_err(AppError::from)
+        }
+
+        pub async fn bulk_deactivate(&self, actor_id: Uuid, ids: Vec<Uuid>) -> Result<BulkDeactivateResponse, AppError> {
+            if ids.is_empty() {
+                return Err(AppError::ValidationError("ids must not be empty".to_string()));
+            }
+            let mut ids = ids;
+            ids.sort();
+            ids.dedup();
+
+            let deactivated = self.users.deactivate_many(&ids).await?;
+            let now = Utc::now();
+            let entries = deactivated
+                .iter()
+                .map(|target| AuditEntry {
+                    id: Uuid::new_v4(),
+                    actor_id,
+                    action: "user.deactivated".to_string(),
+                    target_user_id: *target,
+                    at: now,
+                })
+                .collect();
+            self.audit.record_many(entries).await?;
+
+            let not_found = ids.into_iter().filter(|id| !deactivated.contains(id)).collect();
+            Ok(BulkDeactivateResponse { deactivated, not_found })
+        }
+    }
+}
+
 // --- 6. Handler Layer (user_handlers.rs) ---
 mod user_handlers {
     use super::dtos::*;
+    use super::envelope::*;
     use super::errors::*;
+    use super::fields::*;
     use super::user_service::*;
     use super::*;
 
+    #[utoipa::path(
+        post,
+        path = "/users",
+        tag = "users",
+        request_body = CreateUserPayload,
+        params(("fields[user]" = Option<String>, Query, description = "Comma-separated user fields to return")),
+        responses(
+            (status = 201, description = "User created", body = UserEnvelope),
+            (status = 409, description = "Email already exists", body = ErrorBody),
+        )
+    )]
     pub async fn create_user(
         State(service): State<UserService>,
+        OriginalUri(uri): OriginalUri,
+        mask: FieldMask<UserResponse>,
         Json(payload): Json<CreateUserPayload>,
-    ) -> Result<(StatusCode, Json<UserResponse>), AppError> {
+    ) -> Result<(StatusCode, Json<ResponseEnvelope<Sparse<UserResponse>>>), AppError> {
         let user = service.create_user(payload).await?;
-        Ok((StatusCode::CREATED, Json(user.into())))
+        Ok((StatusCode::CREATED, Json(mask.apply(user.into()).into_envelope(uri.path()))))
     }
 
+    #[utoipa::path(
+        get,
+        path = "/users/{id}",
+        tag = "users",
+        params(
+            ("id" = Uuid, Path, description = "User id"),
+            ("fields[user]" = Option<String>, Query, description = "Comma-separated user fields to return"),
+        ),
+        responses(
+            (status = 200, description = "User found", body = UserEnvelope),
+            (status = 400, description = "Unknown field in fields[user]", body = ErrorBody),
+            (status = 404, description = "User not found", body = ErrorBody),
+        )
+    )]
     pub async fn get_user_by_id(
         State(service): State<UserService>,
+        OriginalUri(uri): OriginalUri,
         Path(id): Path<Uuid>,
-    ) -> Result<Json<UserResponse>, AppError> {
+        mask: FieldMask<UserResponse>,
+    ) -> Result<Json<ResponseEnvelope<Sparse<UserResponse>>>, AppError> {
         let user = service.get_user(id).await?;
-        Ok(Json(user.into()))
+        Ok(Json(mask.apply(user.into()).into_envelope(collection_of(&uri))))
     }
 
+    #[utoipa::path(
+        get,
+        path = "/users",
+        tag = "users",
+        params(
+            ListUsersParams,
+            ("fields[user]" = Option<String>, Query, description = "Comma-separated user fields to return"),
+        ),
+        responses(
+            (status = 200, description = "One page of users; navigation is also sent in the Link header", body = UserPageEnvelope),
+            (status = 400, description = "Unknown field in fields[user], or limit=0", body = ErrorBody),
+        )
+    )]
     pub async fn list_users(
         State(service): State<UserService>,
+        OriginalUri(uri): OriginalUri,
         Query(params): Query<ListUsersParams>,
-    ) -> Result<Json<Vec<UserResponse>>, AppError> {
+        mask: FieldMask<UserResponse>,
+    ) -> Result<Response, AppError> {
+        let offset = params.offset.unwrap_or(0);
+        let limit = params.limit.unwrap_or(10);
+        // An empty page would link `next` back to itself
+        if limit == 0 {
+            return Err(AppError::ValidationError("limit must be at least 1".to_string()));
+        }
+        let total = service.count_users(&params).await?;
         let users = service.list_users(params).await?;
-        let user_responses = users.into_iter().map(Into::into).collect();
-        Ok(Json(user_responses))
+        let user_responses: Vec<Sparse<UserResponse>> = users.into_iter().map(|user| mask.apply(user.into())).collect();
+
+        let envelope = ResponseEnvelope::page(user_responses, &uri, offset, limit, total);
+        let link_header = envelope.link_header();
+        let mut response = Json(envelope).into_response();
+        if let Some(link) = link_header {
+            response.headers_mut().insert(header::LINK, link);
+        }
+        Ok(response)
     }
 
+    /// Accepts `application/json-patch+json` (RFC 6902) and `application/merge-patch+json`
+    /// (RFC 7386); plain `application/json` bodies are treated as merge patches.
+    #[utoipa::path(
+        patch,
+        path = "/users/{id}",
+        tag = "users",
+        params(
+            ("id" = Uuid, Path, description = "User id"),
+            ("fields[user]" = Option<String>, Query, description = "Comma-separated user fields to return"),
+        ),
+        request_body(content = UpdateUserPayload, content_type = "application/merge-patch+json"),
+        responses(
+            (status = 200, description = "User updated", body = UserEnvelope),
+            (status = 400, description = "Invalid patch document", body = ErrorBody),
+            (status = 404, description = "User not found", body = ErrorBody),
+            (status = 409, description = "Email already exists", body = ErrorBody),
+            (status = 412, description = "A JSON Patch `test` operation failed", body = ErrorBody),
+            (status = 415, description = "Unsupported patch format", body = ErrorBody),
+            (status = 422, description = "Patch removes or nulls a field", body = ErrorBody),
+        )
+    )]
     pub async fn update_user(
         State(service): State<UserService>,
+        OriginalUri(uri): OriginalUri,
         Path(id): Path<Uuid>,
-        Json(payload): Json<UpdateUserPayload>,
-    ) -> Result<Json<UserResponse>, AppError> {
-        let user = service.update_user(id, payload).await?;
-        Ok(Json(user.into()))
+        mask: FieldMask<UserResponse>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Result<Json<ResponseEnvelope<Sparse<UserResponse>>>, AppError> {
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("application/json");
+        let document = patch::PatchDocument::parse(content_type, &body)?;
+        let user = service.patch_user(id, document).await?;
+        Ok(Json(mask.apply(user.into()).into_envelope(collection_of(&uri))))
     }
 
+    #[utoipa::path(
+        delete,
+        path = "/users/{id}",
+        tag = "users",
+        params(("id" = Uuid, Path, description = "User id")),
+        responses(
+            (status = 204, description = "User deleted"),
+            (status = 404, description = "User not found", body = ErrorBody),
+        )
+    )]
     pub async fn delete_user(
         State(service): State<UserService>,
         Path(id): Path<Uuid>,
@@ -347,8 +1206,802 @@ mod user_handlers {
     }
 }
 
-// --- 7. Main Application Setup (main.rs) ---
+// --- 6. Handler Layer (admin_handlers.rs) ---
+mod admin_handlers {
+    use super::admin_service::*;
+    use super::domain::*;
+    use super::dtos::*;
+    use super::errors::*;
+    use super::fields::*;
+    use super::*;
+
+    /// The administrator named by a verified bearer token.
+    pub struct AdminPrincipal(pub User);
+
+    #[async_trait]
+    impl FromRequestParts<AdminService> for AdminPrincipal {
+        type Rejection = AppError;
+
+        async fn from_request_parts(parts: &mut Parts, service: &AdminService) -> Result<Self, Self::Rejection> {
+            service.authorize_admin(&parts.headers).await.map(AdminPrincipal)
+        }
+    }
+
+    fn csv_field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    fn role_name(role: &UserRole) -> &'static str {
+        match role {
+            UserRole::ADMIN => "ADMIN",
+            UserRole::USER => "USER",
+        }
+    }
+
+    pub async fn list_users(
+        _admin: AdminPrincipal,
+        State(service): State<AdminService>,
+        Query(filter): Query<AdminUserFilter>,
+        mask: FieldMask<UserResponse>,
+    ) -> Result<Json<Vec<Sparse<UserResponse>>>, AppError> {
+        let users = service.search_users(&filter).await?;
+        Ok(Json(users.into_iter().map(|user| mask.apply(user.into())).collect()))
+    }
+
+    pub async fn export_users_csv(
+        _admin: AdminPrincipal,
+        State(service): State<AdminService>,
+        Query(filter): Query<AdminUserFilter>,
+    ) -> Result<Response, AppError> {
+        let users = service.search_users(&filter).await?;
+        let mut csv = String::from("id,email,role,is_active,created_at\n");
+        for user in users {
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                user.id,
+                csv_field(&user.email),
+                role_name(&user.role),
+                user.is_active,
+                user.created_at.to_rfc3339(),
+            ));
+        }
+        Ok((
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"users.csv\""),
+            ],
+            csv,
+        )
+            .into_response())
+    }
+
+    pub async fn bulk_deactivate(
+        AdminPrincipal(admin): AdminPrincipal,
+        State(service): State<AdminService>,
+        Json(payload): Json<BulkDeactivatePayload>,
+    ) -> Result<Json<BulkDeactivateResponse>, AppError> {
+        let result = service.bulk_deactivate(admin.id, payload.ids).await?;
+        Ok(Json(result))
+    }
+}
+
+// --- 7. OpenAPI Document (api_docs.rs) ---
+mod api_docs {
+    use super::domain::*;
+    use super::dtos::*;
+    use super::envelope::*;
+    use super::errors::*;
+    use super::*;
+
+    #[derive(OpenApi)]
+    #[openapi(
+        paths(
+            super::user_handlers::create_user,
+            super::user_handlers::list_users,
+            super::user_handlers::get_user_by_id,
+            super::user_handlers::update_user,
+            super::user_handlers::delete_user,
+        ),
+        components(schemas(
+            UserRole,
+            CreateUserPayload,
+            UpdateUserPayload,
+            UserResponse,
+            Links,
+            PageMeta,
+            UserEnvelope,
+            UserPageEnvelope,
+            ErrorBody,
+        )),
+        tags((name = "users", description = "User management"))
+    )]
+    pub struct ApiDoc;
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::Value;
+        use std::collections::BTreeSet;
+
+        fn document() -> Value {
+            serde_json::to_value(ApiDoc::openapi()).unwrap()
+        }
+
+        fn keys(value: &Value) -> BTreeSet<String> {
+            value.as_object().into_iter().flatten().map(|(key, _)| key.clone()).collect()
+        }
+
+        fn names(value: &Value) -> BTreeSet<String> {
+            value.as_array().into_iter().flatten().filter_map(Value::as_str).map(str::to_string).collect()
+        }
+
+        fn set(items: &[&str]) -> BTreeSet<String> {
+            items.iter().map(|item| item.to_string()).collect()
+        }
+
+        type ExampleParser = fn(Value) -> Result<(), serde_json::Error>;
+
+        // Request bodies whose examples must also parse into their serde type
+        const REQUEST_SCHEMAS: [(&str, ExampleParser); 2] = [
+            ("CreateUserPayload", |v| serde_json::from_value::<CreateUserPayload>(v).map(drop)),
+            ("UpdateUserPayload", |v| serde_json::from_value::<UpdateUserPayload>(v).map(drop)),
+        ];
+
+        /// Checks every schema example in the generated document: no fields the schema
+        /// doesn't declare, no missing required fields, and request examples must parse.
+        /// Returns how many examples were checked, or every problem found.
+        fn check_examples() -> Result<usize, Vec<String>> {
+            let doc = serde_json::to_value(ApiDoc::openapi()).expect("OpenAPI document serializes");
+            let mut problems = Vec::new();
+            let mut checked = 0;
+
+            for (name, schema) in doc["components"]["schemas"].as_object().into_iter().flatten() {
+                let Some(example) = schema.get("example") else { continue };
+                checked += 1;
+
+                if let (Some(properties), Some(fields)) = (schema.get("properties").and_then(Value::as_object), example.as_object()) {
+                    for key in fields.keys().filter(|key| !properties.contains_key(*key)) {
+                        problems.push(format!("{}: example has undeclared field '{}'", name, key));
+                    }
+                    let required = schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str);
+                    for key in required.filter(|key| !fields.contains_key(*key)) {
+                        problems.push(format!("{}: example is missing required field '{}'", name, key));
+                    }
+                }
+                if let Some((_, parse)) = REQUEST_SCHEMAS.iter().find(|(schema_name, _)| schema_name == name) {
+                    if let Err(e) = parse(example.clone()) {
+                        problems.push(format!("{}: example does not parse: {}", name, e));
+                    }
+                }
+            }
+
+            if problems.is_empty() {
+                Ok(checked)
+            } else {
+                Err(problems)
+            }
+        }
+
+        #[test]
+        fn examples_match_their_schemas() {
+            assert_eq!(check_examples(), Ok(4));
+        }
+
+        #[test]
+        fn response_schemas_match_what_the_dtos_serialize() {
+            let doc = document();
+            let schemas = &doc["components"]["schemas"];
+            let user = UserResponse::from(User {
+                id: Uuid::nil(),
+                email: "jane@example.com".to_string(),
+                password_hash: "hash".to_string(),
+                role: UserRole::USER,
+                is_active: true,
+                created_at: Utc::now(),
+            });
+            let page = ResponseEnvelope {
+                data: vec![serde_json::to_value(&user).unwrap()],
+                links: Links { self_link: "/users".to_string(), next: Some("/users?offset=10".to_string()), prev: Some("/users".to_string()) },
+                meta: Some(PageMeta { total: 11, offset: 0, limit: 10 }),
+            };
+            let page = serde_json::to_value(page).unwrap();
+
+            for (name, serialized) in [
+                ("UserResponse", serde_json::to_value(&user).unwrap()),
+                ("ErrorBody", serde_json::to_value(ErrorBody { error: "nope".to_string() }).unwrap()),
+                ("UserPageEnvelope", page.clone()),
+                ("Links", page["links"].clone()),
+                ("PageMeta", page["meta"].clone()),
+            ] {
+                assert_eq!(keys(&schemas[name]["properties"]), keys(&serialized), "{}", name);
+            }
+            assert_eq!(names(&schemas["UserResponse"]["required"]), set(&["id", "email", "role", "is_active", "created_at"]));
+            assert_eq!(names(&schemas["Links"]["required"]), set(&["self"]));
+            assert_eq!(names(&schemas["UserPageEnvelope"]["required"]), set(&["data", "links"]));
+
+            let roles: BTreeSet<String> = [UserRole::ADMIN, UserRole::USER]
+                .into_iter()
+                .map(|role| serde_json::to_value(role).unwrap().as_str().unwrap().to_string())
+                .collect();
+            assert_eq!(names(&schemas["UserRole"]["enum"]), roles);
+        }
+
+        #[test]
+        fn request_schemas_match_what_the_dtos_accept() {
+            let doc = document();
+            let schemas = &doc["components"]["schemas"];
+
+            let create = &schemas["CreateUserPayload"];
+            assert_eq!(keys(&create["properties"]), set(&["email", "password", "role"]));
+            assert_eq!(names(&create["required"]), set(&["email", "password", "role"]));
+            for missing in ["email", "password", "role"] {
+                let mut body = serde_json::json!({"email": "a@example.com", "password": "pw", "role": "USER"});
+                body.as_object_mut().unwrap().remove(missing);
+                assert!(serde_json::from_value::<CreateUserPayload>(body).is_err(), "{} is required", missing);
+            }
+
+            let update = &schemas["UpdateUserPayload"];
+            assert_eq!(keys(&update["properties"]), set(&["email", "role", "is_active"]));
+            assert!(names(&update["required"]).is_empty());
+            assert!(serde_json::from_value::<UpdateUserPayload>(serde_json::json!({})).is_ok());
+        }
+
+        #[test]
+        fn users_crud_pagination_and_errors_are_documented() {
+            let doc = document();
+            let paths = &doc["paths"];
+            assert_eq!(keys(&paths["/users"]), set(&["get", "post"]));
+            assert_eq!(keys(&paths["/users/{id}"]), set(&["get", "patch", "delete"]));
+
+            let list_params: BTreeSet<String> = paths["/users"]["get"]["parameters"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|param| param["in"] == "query")
+                .filter_map(|param| param["name"].as_str().map(str::to_string))
+                .collect();
+            for param in ["offset", "limit", "role", "is_active"] {
+                assert!(list_params.contains(param), "list_users is missing {}: {:?}", param, list_params);
+            }
+
+            for (path, operations) in paths.as_object().unwrap() {
+                for (method, operation) in operations.as_object().unwrap() {
+                    for (status, response) in operation["responses"].as_object().unwrap() {
+                        if status.starts_with('4') {
+                            let schema = &response["content"]["application/json"]["schema"]["$ref"];
+                            assert_eq!(schema, "#/components/schemas/ErrorBody", "{} {} {}", method, path, status);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// --- 8. Seed Data (fixtures.rs) ---
+/// Deterministic fixture builder: the same seed always yields the same users, down to
+/// ids and timestamps, so a seeded instance can be reproduced from its seed alone.
+mod fixtures {
+    use super::domain::*;
+    use super::*;
+
+    /// SplitMix64. Small and well distributed; not suitable for anything secret.
+    pub struct SeededRng(u64);
+
+    impl SeededRng {
+        pub fn new(seed: u64) -> Self {
+            Self(seed)
+        }
+
+        pub fn next_u64(&mut self) -> u64 {
+            self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = self.0;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+
+        pub fn below(&mut self, n: u64) -> u64 {
+            self.next_u64() % n
+        }
+
+        pub fn chance(&mut self, percent: u64) -> bool {
+            self.below(100) < percent
+        }
+
+        pub fn uuid(&mut self) -> Uuid {
+            let mut bytes = [0u8; 16];
+            bytes[..8].copy_from_slice(&self.next_u64().to_le_bytes());
+            bytes[8..].copy_from_slice(&self.next_u64().to_le_bytes());
+            uuid::Builder::from_random_bytes(bytes).into_uuid()
+        }
+    }
+
+    pub struct FixtureBuilder {
+        rng: SeededRng,
+        // Fixed rather than Utc::now() so created_at is reproducible too
+        epoch: DateTime<Utc>,
+        created: usize,
+    }
+
+    impl FixtureBuilder {
+        pub fn new(seed: u64) -> Self {
+            let epoch = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .expect("valid fixture epoch")
+                .with_timezone(&Utc);
+            Self { rng: SeededRng::new(seed), epoch, created: 0 }
+        }
+
+        /// A user with the given email and role; everything else is generated.
+        pub fn user_with(&mut self, email: &str, role: UserRole) -> User {
+            self.created += 1;
+            let offset_secs = self.rng.below(365 * 24 * 60 * 60) as i64;
+            User {
+                id: self.rng.uuid(),
+                email: email.to_string(),
+                password_hash: format!("hashed_fixture_{}", self.created),
+                role,
+                is_active: self.rng.chance(80),
+                created_at: self.epoch + chrono::Duration::seconds(offset_secs),
+            }
+        }
+
+        /// A valid user with a unique email, roughly 10% admins and 80% active.
+        pub fn user(&mut self) -> User {
+            let role = if self.rng.chance(10) { UserRole::ADMIN } else { UserRole::USER };
+            let email = format!("user{}.{:04x}@example.com", self.created + 1, self.rng.below(0x10000));
+            self.user_with(&email, role)
+        }
+
+        pub fn users(&mut self, count: usize) -> Vec<User> {
+            (0..count).map(|_| self.user()).collect()
+        }
+    }
+
+    /// Round-trip and filter invariants of the user repository, over users drawn both
+    /// from proptest strategies and from the seeded builder.
+    #[cfg(test)]
+    mod properties {
+        use super::super::dtos::*;
+        use super::super::user_repository::*;
+        use super::*;
+        use proptest::prelude::*;
+
+        fn block_on<F: std::future::Future>(future: F) -> F::Output {
+            tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+        }
+
+        fn repository(users: &[User]) -> InMemoryUserRepository {
+            let repo = InMemoryUserRepository::new(Arc::new(RwLock::new(HashMap::new())));
+            block_on(async {
+                for user in users {
+                    repo.create(user.clone()).await.unwrap();
+                }
+            });
+            repo
+        }
+
+        fn snapshot(user: &User) -> serde_json::Value {
+            serde_json::to_value(user).unwrap()
+        }
+
+        fn role() -> impl Strategy<Value = UserRole> {
+            prop_oneof![Just(UserRole::ADMIN), Just(UserRole::USER)]
+        }
+
+        // Valid users with distinct ids and emails
+        fn users() -> impl Strategy<Value = Vec<User>> {
+            prop::collection::vec((role(), any::<bool>(), 0i64..365 * 24 * 60 * 60, "[a-z]{1,12}"), 0..24).prop_map(
+                |rows| {
+                    let mut fixtures = FixtureBuilder::new(0);
+                    rows.into_iter()
+                        .enumerate()
+                        .map(|(n, (role, is_active, offset_secs, local))| {
+                            let mut user = fixtures.user_with(&format!("{}{}@example.com", local, n), role);
+                            user.is_active = is_active;
+                            user.created_at = fixtures.epoch + chrono::Duration::seconds(offset_secs);
+                            user
+                        })
+                        .collect()
+                },
+            )
+        }
+
+        proptest! {
+            #[test]
+            fn the_same_seed_builds_the_same_valid_users(seed in any::<u64>(), count in 0usize..64) {
+                let users = FixtureBuilder::new(seed).users(count);
+                let again = FixtureBuilder::new(seed).users(count);
+                prop_assert_eq!(users.iter().map(snapshot).collect::<Vec<_>>(), again.iter().map(snapshot).collect::<Vec<_>>());
+
+                let ids: std::collections::HashSet<_> = users.iter().map(|user| user.id).collect();
+                let emails: std::collections::HashSet<_> = users.iter().map(|user| user.email.as_str()).collect();
+                prop_assert_eq!(ids.len(), count);
+                prop_assert_eq!(emails.len(), count);
+                for user in &users {
+                    prop_assert!(user.email.contains('@'), "{}", user.email);
+                }
+            }
+
+            #[test]
+            fn created_users_are_found_unchanged(users in users()) {
+                let repo = repository(&users);
+                block_on(async {
+                    for user in &users {
+                        let by_id = repo.find_by_id(user.id).await.unwrap();
+                        assert_eq!(snapshot(&by_id), snapshot(user));
+                        let by_email = repo.find_by_email(&user.email).await.unwrap().unwrap();
+                        assert_eq!(snapshot(&by_email), snapshot(user));
+                    }
+                    assert_eq!(repo.count(&ListUsersParams::default()).await.unwrap(), users.len());
+                });
+            }
+
+            #[test]
+            fn list_filters_return_exactly_the_matching_users(
+                users in users(),
+                role in prop::option::of(role()),
+                is_active in prop::option::of(any::<bool>()),
+            ) {
+                let repo = repository(&users);
+                let mut expected: Vec<Uuid> = users
+                    .iter()
+                    .filter(|user| role.as_ref().is_none_or(|role| &user.role == role))
+                    .filter(|user| is_active.is_none_or(|is_active| user.is_active == is_active))
+                    .map(|user| user.id)
+                    .collect();
+                expected.sort();
+
+                let params = ListUsersParams { offset: None, limit: Some(usize::MAX), role: role.clone(), is_active };
+                let (mut listed, count) = block_on(async {
+                    let listed: Vec<Uuid> = repo.find_all(params).await.unwrap().iter().map(|user| user.id).collect();
+                    let count = repo
+                        .count(&ListUsersParams { role: role.clone(), is_active, ..Default::default() })
+                        .await
+                        .unwrap();
+                    (listed, count)
+                });
+                listed.sort();
+                prop_assert_eq!(&listed, &expected);
+                prop_assert_eq!(count, expected.len());
+            }
+
+            #[test]
+            fn pages_partition_the_filtered_list(users in users(), limit in 1usize..8, role in prop::option::of(role())) {
+                let repo = repository(&users);
+                let all = ListUsersParams { limit: Some(usize::MAX), role: role.clone(), ..Default::default() };
+                let (full, paged) = block_on(async {
+                    let full: Vec<Uuid> = repo.find_all(all).await.unwrap().iter().map(|user| user.id).collect();
+                    let mut paged = Vec::new();
+                    let mut offset = 0;
+                    loop {
+                        let params = ListUsersParams { offset: Some(offset), limit: Some(limit), role: role.clone(), is_active: None };
+                        let page = repo.find_all(params).await.unwrap();
+                        assert!(page.len() <= limit);
+                        if page.is_empty() {
+                            break;
+                        }
+                        offset += page.len();
+                        paged.extend(page.iter().map(|user| user.id));
+                    }
+                    (full, paged)
+                });
+                prop_assert_eq!(paged, full);
+            }
+
+            #[test]
+            fn search_filters_narrow_the_result(users in users(), needle in "[a-z]{0,2}", role in prop::option::of(role())) {
+                let repo = repository(&users);
+                let (everyone, matched) = block_on(async {
+                    let everyone = repo.search(&AdminUserFilter::default()).await.unwrap();
+                    let filter = AdminUserFilter { email_contains: Some(needle.clone()), role: role.clone(), ..Default::default() };
+                    (everyone, repo.search(&filter).await.unwrap())
+                });
+                prop_assert_eq!(everyone.len(), users.len());
+                let everyone: std::collections::HashSet<Uuid> = everyone.iter().map(|user| user.id).collect();
+                for user in &matched {
+                    prop_assert!(everyone.contains(&user.id));
+                    prop_assert!(user.email.contains(needle.as_str()));
+                    prop_assert!(role.as_ref().is_none_or(|role| &user.role == role));
+                }
+                let expected = users
+                    .iter()
+                    .filter(|user| user.email.contains(needle.as_str()))
+                    .filter(|user| role.as_ref().is_none_or(|role| &user.role == role))
+                    .count();
+                prop_assert_eq!(matched.len(), expected);
+            }
+
+            #[test]
+            fn updates_change_only_the_given_fields(
+                users in users().prop_filter("needs a user", |users| !users.is_empty()),
+                pick in any::<prop::sample::Index>(),
+                role in prop::option::of(role()),
+                is_active in prop::option::of(any::<bool>()),
+            ) {
+                let repo = repository(&users);
+                let before = pick.get(&users).clone();
+                let payload = UpdateUserPayload { email: None, role: role.clone(), is_active };
+                let after = block_on(repo.update(before.id, payload)).unwrap();
+
+                prop_assert_eq!(&after.email, &before.email);
+                prop_assert_eq!(&after.password_hash, &before.password_hash);
+                prop_assert_eq!(after.created_at, before.created_at);
+                prop_assert_eq!(&after.role, role.as_ref().unwrap_or(&before.role));
+                prop_assert_eq!(after.is_active, is_active.unwrap_or(before.is_active));
+                prop_assert_eq!(snapshot(&block_on(repo.find_by_id(before.id)).unwrap()), snapshot(&after));
+            }
+        }
+    }
+}
+
+// --- 9. Response Snapshots (snapshots.rs) ---
+/// Golden-file tests for the JSON (and CSV) the API sends.
+///
+/// Each case goes through a freshly seeded router, and its status, the headers clients
+/// rely on, and its body are compared against `snapshots/variation_2/<case>.snap` next to
+/// this file (or under `$SNAPSHOT_DIR`). Ids and timestamps are redacted so only the
+/// shape and the stable values are pinned. Run with `UPDATE_SNAPSHOTS=1` to rewrite the
+/// files after an intentional change. Any response that mentions a password hash fails
+/// regardless of what the snapshot says.
+#[cfg(test)]
+mod snapshots {
+    use super::domain::*;
+    use super::tokens::{TokenKeys, HARNESS_SECRET};
+    use super::*;
+    use axum::{body::Body, body::HttpBody, http::Request};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    const SEED: u64 = 42;
+    const MISSING_ID: &str = "00000000-0000-0000-0000-000000000000";
+    /// Never legitimately part of a response; the stored hashes all start with `hashed_`.
+    const LEAK_MARKERS: [&str; 2] = ["password_hash", "hashed_"];
+    const SNAPSHOT_HEADERS: [header::HeaderName; 3] = [header::CONTENT_TYPE, header::LINK, header::CONTENT_DISPOSITION];
+
+    struct Case {
+        name: &'static str,
+        request: fn(&[User]) -> Request<Body>,
+    }
+
+    fn get(uri: String) -> Request<Body> {
+        Request::get(uri).body(Body::empty()).expect("valid request")
+    }
+
+    fn with_body(method: &str, uri: String, content_type: &str, body: String) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .expect("valid request")
+    }
+
+    fn as_user(user: &User, mut request: Request<Body>) -> Request<Body> {
+        let token = TokenKeys::from_secret(HARNESS_SECRET).issue(user.id);
+        let value = format!("Bearer {}", token).parse().expect("token is a valid header value");
+        request.headers_mut().insert(header::AUTHORIZATION, value);
+        request
+    }
+
+    // Each case gets its own router, so none sees another's writes.
+    // Seeded users: users[0] is the active admin, users[1] an inactive user.
+    static CASES: [Case; 19] = [
+        Case {
+            name: "create_user_created",
+            request: |_| {
+                let body = serde_json::json!({"email": "new@example.com", "password": "secret", "role": "USER"});
+                with_body("POST", "/users".to_string(), "application/json", body.to_string())
+            },
+        },
+        Case {
+            name: "create_user_conflict",
+            request: |users| {
+                let body = serde_json::json!({"email": users[0].email, "password": "secret", "role": "USER"});
+                with_body("POST", "/users".to_string(), "application/json", body.to_string())
+            },
+        },
+        Case {
+            name: "create_user_malformed_body",
+            request: |_| with_body("POST", "/users".to_string(), "application/json", "{".to_string()),
+        },
+        Case { name: "get_user_ok", request: |users| get(format!("/users/{}", users[1].id)) },
+        Case { name: "get_user_sparse", request: |users| get(format!("/users/{}?fields[user]=email,role", users[1].id)) },
+        Case {
+            name: "get_user_unknown_field",
+            request: |users| get(format!("/users/{}?fields[user]=password", users[1].id)),
+        },
+        Case { name: "get_user_not_found", request: |_| get(format!("/users/{}", MISSING_ID)) },
+        Case { name: "get_user_invalid_id", request: |_| get("/users/not-a-uuid".to_string()) },
+        Case { name: "list_users_page", request: |_| get("/users?limit=1".to_string()) },
+        Case {
+            name: "update_user_ok",
+            request: |users| {
+                let body = serde_json::json!({"is_active": true});
+                with_body("PATCH", format!("/users/{}", users[1].id), "application/merge-patch+json", body.to_string())
+            },
+        },
+        Case {
+            name: "update_user_unsupported_media_type",
+            request: |users| with_body("PATCH", format!("/users/{}", users[1].id), "text/plain", "is_active=true".to_string()),
+        },
+        Case {
+            name: "update_user_not_found",
+            request: |_| with_body("PATCH", format!("/users/{}", MISSING_ID), "application/merge-patch+json", "{}".to_string()),
+        },
+        Case {
+            name: "delete_user_no_content",
+            request: |users| Request::delete(format!("/users/{}", users[1].id)).body(Body::empty()).expect("valid request"),
+        },
+        Case {
+            name: "delete_user_not_found",
+            request: |_| Request::delete(format!("/users/{}", MISSING_ID)).body(Body::empty()).expect("valid request"),
+        },
+        Case { name: "admin_list_users_ok", request: |users| as_user(&users[0], get("/admin/users".to_string())) },
+        Case { name: "admin_list_users_unauthorized", request: |_| get("/admin/users".to_string()) },
+        Case { name: "admin_list_users_forbidden", request: |users| as_user(&users[1], get("/admin/users".to_string())) },
+        Case { name: "admin_export_csv", request: |users| as_user(&users[0], get("/admin/users/export.csv".to_string())) },
+        Case {
+            name: "admin_bulk_deactivate",
+            request: |users| {
+                let body = serde_json::json!({"ids": [users[0].id, MISSING_ID]});
+                as_user(&users[0], with_body("POST", "/admin/users/bulk-deactivate".to_string(), "application/json", body.to_string()))
+            },
+        },
+    ];
+
+    fn is_uuid(s: &str) -> bool {
+        s.len() == 36
+            && s.bytes().enumerate().all(|(i, b)| if matches!(i, 8 | 13 | 18 | 23) { b == b'-' } else { b.is_ascii_hexdigit() })
+    }
+
+    fn redact_uuids(text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(ch) = rest.chars().next() {
+            if rest.get(..36).is_some_and(is_uuid) {
+                out.push_str("[uuid]");
+                rest = &rest[36..];
+            } else {
+                out.push(ch);
+                rest = &rest[ch.len_utf8()..];
+            }
+        }
+        out
+    }
+
+    fn redact_scalar(s: &str) -> String {
+        if DateTime::parse_from_rfc3339(s).is_ok() {
+            "[timestamp]".to_string()
+        } else {
+            redact_uuids(s)
+        }
+    }
+
+    fn redact_json(value: &mut Value) {
+        match value {
+            Value::String(s) => *s = redact_scalar(s),
+            Value::Array(items) => items.iter_mut().for_each(redact_json),
+            Value::Object(map) => map.values_mut().for_each(redact_json),
+            _ => {}
+        }
+    }
+
+    // CSV and plain-text bodies: redacted field by field
+    fn redact_text(body: &str) -> String {
+        body.lines()
+            .map(|line| line.split(',').map(redact_scalar).collect::<Vec<_>>().join(","))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    async fn render(response: Response) -> String {
+        let mut out = format!("status: {}\n", response.status().as_u16());
+        for name in &SNAPSHOT_HEADERS {
+            if let Some(value) = response.headers().get(name) {
+                out.push_str(&format!("{}: {}\n", name, redact_uuids(value.to_str().unwrap_or("<non-ascii>"))));
+            }
+        }
+        out.push('\n');
+
+        let mut body = response.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.expect("in-memory bodies do not fail"));
+        }
+        match serde_json::from_slice::<Value>(&bytes) {
+            Ok(mut json) => {
+                redact_json(&mut json);
+                out.push_str(&serde_json::to_string_pretty(&json).expect("JSON values serialize"));
+            }
+            Err(_) => out.push_str(&redact_text(&String::from_utf8_lossy(&bytes))),
+        }
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out
+    }
+
+    fn first_difference(expected: &str, actual: &str) -> String {
+        let (mut expected_lines, mut actual_lines) = (expected.lines(), actual.lines());
+        for line in 1.. {
+            match (expected_lines.next(), actual_lines.next()) {
+                (Some(e), Some(a)) if e == a => continue,
+                (None, None) => break,
+                (e, a) => {
+                    return format!("line {}: expected {:?}, got {:?}", line, e.unwrap_or("<end>"), a.unwrap_or("<end>"))
+                }
+            }
+        }
+        "trailing whitespace differs".to_string()
+    }
+
+    fn snapshot_dir() -> std::path::PathBuf {
+        match std::env::var_os("SNAPSHOT_DIR") {
+            Some(dir) => dir.into(),
+            None => std::path::Path::new(file!()).with_file_name("snapshots").join("variation_2"),
+        }
+    }
+
+    #[tokio::test]
+    async fn responses_match_their_snapshots() {
+        let dir = snapshot_dir();
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        if update {
+            std::fs::create_dir_all(&dir).unwrap();
+        }
+        let users = seed_users(SEED, 0);
+        let mut failures = Vec::new();
+        for case in &CASES {
+            let response = build_app(SEED, 0, TokenKeys::from_secret(HARNESS_SECRET)).oneshot((case.request)(&users)).await.unwrap();
+            let actual = render(response).await;
+            let path = dir.join(format!("{}.snap", case.name));
+
+            if let Some(marker) = LEAK_MARKERS.iter().find(|marker| actual.contains(*marker)) {
+                failures.push(format!("{}: response contains {:?}", case.name, marker));
+                continue;
+            }
+            if update {
+                std::fs::write(&path, &actual).unwrap();
+                continue;
+            }
+            match std::fs::read_to_string(&path) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => failures.push(format!("{}: {}", case.name, first_difference(&expected, &actual))),
+                Err(_) => failures.push(format!("{}: no snapshot at {}; rerun with UPDATE_SNAPSHOTS=1", case.name, path.display())),
+            }
+        }
+        assert!(failures.is_empty(), "{} of {} snapshots failed:\n{}", failures.len(), CASES.len(), failures.join("\n"));
+    }
+
+    #[test]
+    fn redaction_hides_ids_and_timestamps_only() {
+        let mut json = serde_json::json!({
+            "id": "6f1c2a4e-3b7d-4c1e-9a8f-2d5b6c7e8f90",
+            "created_at": "2024-01-01T00:00:00Z",
+            "self": "/users/6f1c2a4e-3b7d-4c1e-9a8f-2d5b6c7e8f90?fields[user]=email",
+            "email": "jane@example.com",
+        });
+        redact_json(&mut json);
+        assert_eq!(json, serde_json::json!({
+            "id": "[uuid]",
+            "created_at": "[timestamp]",
+            "self": "/users/[uuid]?fields[user]=email",
+            "email": "jane@example.com",
+        }));
+        assert_eq!(redact_text("id,created_at\n6f1c2a4e-3b7d-4c1e-9a8f-2d5b6c7e8f90,2024-01-01T00:00:00Z"), "id,created_at\n[uuid],[timestamp]");
+    }
+}
+
+// --- 10. Main Application Setup (main.rs) ---
+use admin_service::*;
+use audit_repository::*;
 use domain::*;
+use fixtures::FixtureBuilder;
 use user_repository::*;
 use user_service::*;
 use user_handlers::*;
@@ -360,14 +2013,42 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    // SEED picks the fixture data; SEED_USERS adds that many generated users
+    let seed = std::env::var("SEED").ok().and_then(|v| v.parse().ok()).unwrap_or(42);
+    let extra_users = std::env::var("SEED_USERS").ok().and_then(|v| v.parse().ok()).unwrap_or(0);
+
+    let app = build_app(seed, extra_users, tokens::TokenKeys::from_env()).layer(
+        TraceLayer::new_for_http()
+            .make_span_with(DefaultMakeSpan::default().include_headers(true)),
+    );
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    tracing::debug!("listening on {}", addr);
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await
+        .unwrap();
+}
+
+/// The whole API over a freshly seeded in-memory store, without tracing or a listener,
+/// so it can also be driven in-process (e.g. with `tower::ServiceExt::oneshot`).
+pub(crate) fn build_app(seed: u64, extra_users: usize, tokens: tokens::TokenKeys) -> Router {
     // --- Dependency Injection ---
     let db = Arc::new(RwLock::new(HashMap::new()));
-    populate_db(db.clone());
+    populate_db(db.clone(), seed, extra_users);
     let user_repo = Arc::new(InMemoryUserRepository::new(db.clone()));
-    let user_service = UserService::new(user_repo);
+    let audit_repo = Arc::new(InMemoryAuditRepository::default());
+    let user_service = UserService::new(user_repo.clone());
+    let admin_service = AdminService::new(user_repo, audit_repo, tokens);
+
+    let admin_routes = Router::new()
+        .route("/users", get(admin_handlers::list_users))
+        .route("/users/export.csv", get(admin_handlers::export_users_csv))
+        .route("/users/bulk-deactivate", post(admin_handlers::bulk_deactivate))
+        .with_state(admin_service);
 
     // --- Router Setup ---
-    let app = Router::new()
+    Router::new()
         .route("/users", post(create_user).get(list_users))
         .route(
             "/users/:id",
@@ -376,44 +2057,130 @@ async fn main() {
                 .delete(delete_user),
         )
         .with_state(user_service)
-        .layer(
-            TraceLayer::new_for_http()
-                .make_span_with(DefaultMakeSpan::default().include_headers(true)),
-        );
+        .nest("/admin", admin_routes)
+        // Serves the document at /api-docs/openapi.json and the UI at /swagger-ui
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", api_docs::ApiDoc::openapi()))
+}
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    tracing::debug!("listening on {}", addr);
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+/// The seeded users, admin@example.com first; the same arguments give the same users.
+pub(crate) fn seed_users(seed: u64, extra_users: usize) -> Vec<User> {
+    let mut fixtures = FixtureBuilder::new(seed);
+    let mut admin = fixtures.user_with("admin@example.com", UserRole::ADMIN);
+    admin.is_active = true;
+    let mut user = fixtures.user_with("user@example.com", UserRole::USER);
+    user.is_active = false;
+
+    let mut users = vec![admin, user];
+    users.extend(fixtures.users(extra_users));
+    users
 }
 
-fn populate_db(db: Arc<RwLock<HashMap<Uuid, User>>>) {
+fn populate_db(db: Arc<RwLock<HashMap<Uuid, User>>>, seed: u64, extra_users: usize) {
     let mut db_lock = db.write().unwrap();
-    let admin_id = Uuid::new_v4();
-    let user_id = Uuid::new_v4();
-
-    db_lock.insert(
-        admin_id,
-        User {
-            id: admin_id,
-            email: "admin@example.com".to_string(),
-            password_hash: "hashed_admin_pass".to_string(),
-            role: UserRole::ADMIN,
-            is_active: true,
-            created_at: Utc::now(),
-        },
-    );
-    db_lock.insert(
-        user_id,
-        User {
-            id: user_id,
-            email: "user@example.com".to_string(),
-            password_hash: "hashed_user_pass".to_string(),
-            role: UserRole::USER,
-            is_active: false,
-            created_at: Utc::now(),
-        },
-    );
-}
\ No newline at end of file
+    for user in seed_users(seed, extra_users) {
+        db_lock.insert(user.id, user);
+    }
+}
+
+// --- 11. End-to-End Tests (tests/e2e.rs) ---
+/// The users scenario shared with the Actix and Rocket REST variations: create,
+/// fetch, assign a role, list by role, delete. Runs in-process over `build_app`.
+#[cfg(test)]
+mod e2e {
+    use super::*;
+    use axum::body::{Body, HttpBody};
+    use axum::http::Request;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    async fn send(app: &Router, method: &str, uri: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let body = match body {
+            Some(body) => {
+                request = request.header(header::CONTENT_TYPE, "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+        let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+        let status = response.status();
+        let mut body = response.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        let json = if bytes.is_empty() { Value::Null } else { serde_json::from_slice(&bytes).unwrap() };
+        (status, json)
+    }
+
+    #[tokio::test]
+    async fn user_lifecycle() {
+        let tokens = tokens::TokenKeys::from_secret(tokens::HARNESS_SECRET);
+        let admin_id = seed_users(7, 0)[0].id;
+        let admin_token = tokens.issue(admin_id);
+        let app = build_app(7, 0, tokens);
+
+        let (status, created) = send(&app, "POST", "/users", None, Some(json!({
+            "email": "grace@example.com", "password": "s3cret!", "role": "USER"
+        }))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = created["data"]["id"].as_str().unwrap().to_string();
+        assert_eq!(created["data"]["role"], "USER");
+
+        let (status, _) = send(&app, "POST", "/users", None, Some(json!({
+            "email": "grace@example.com", "password": "again", "role": "USER"
+        }))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, fetched) = send(&app, "GET", &format!("/users/{}", id), None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(fetched["data"]["email"], "grace@example.com");
+
+        let (status, promoted) = send(&app, "PATCH", &format!("/users/{}", id), None, Some(json!({"role": "ADMIN"}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(promoted["data"]["role"], "ADMIN");
+
+        let (status, admins) = send(&app, "GET", "/users?role=ADMIN&limit=50", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let emails: Vec<&str> = admins["data"].as_array().unwrap().iter().filter_map(|u| u["email"].as_str()).collect();
+        assert!(emails.contains(&"grace@example.com"), "{:?}", emails);
+        assert!(emails.contains(&"admin@example.com"), "{:?}", emails);
+
+        let (status, _) = send(&app, "GET", "/admin/users", None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, "GET", "/admin/users", Some(&admin_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = send(&app, "DELETE", &format!("/users/{}", id), None, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&app, "GET", &format!("/users/{}", id), None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn links_follow_the_mount_point_and_empty_pages_are_rejected() {
+        let app = Router::new().nest("/api/v1", build_app(7, 3, tokens::TokenKeys::from_secret(tokens::HARNESS_SECRET)));
+
+        let (status, page) = send(&app, "GET", "/api/v1/users?role=USER&limit=1", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["links"]["self"], "/api/v1/users?role=USER&offset=0&limit=1");
+        assert_eq!(page["links"]["next"], "/api/v1/users?role=USER&offset=1&limit=1");
+
+        let id = page["data"][0]["id"].as_str().unwrap();
+        let (status, user) = send(&app, "GET", &format!("/api/v1/users/{}", id), None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(user["links"]["self"], format!("/api/v1/users/{}", id));
+
+        let (status, created) = send(&app, "POST", "/api/v1/users", None, Some(json!({
+            "email": "ada@example.com", "password": "s3cret!", "role": "USER"
+        }))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created["links"]["self"], format!("/api/v1/users/{}", created["data"]["id"].as_str().unwrap()));
+
+        let (status, _) = send(&app, "GET", "/api/v1/users?limit=0", None, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
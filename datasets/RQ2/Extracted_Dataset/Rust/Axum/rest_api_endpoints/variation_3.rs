@@ -23,16 +23,18 @@ tracing-subscriber = { version = "0.3", features = ["env-filter"] }
 tower-http = { version = "0.4", features = ["trace"] }
 */
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, Result};
 use axum::{
+    body::Bytes,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     routing::{delete, get, patch, post},
     Json, Router,
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::{
     collections::HashMap,
     net::SocketAddr,
@@ -129,6 +131,88 @@ impl IntoResponse for anyhow::Error {
     }
 }
 
+// --- PATCH DOCUMENTS ---
+
+// PATCH takes JSON Patch (RFC 6902) or Merge Patch (RFC 7396); plain JSON is a merge patch.
+// Fields can be replaced but never removed or nulled.
+const JSON_PATCH: &str = "application/json-patch+json";
+const MERGE_PATCH: &str = "application/merge-patch+json";
+const PATCHABLE_FIELDS: [&str; 3] = ["email", "role", "is_active"];
+
+type PatchError = (StatusCode, String);
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum PatchOp {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+fn field(pointer: &str) -> Result<&str, PatchError> {
+    pointer
+        .strip_prefix('/')
+        .filter(|f| PATCHABLE_FIELDS.contains(f))
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Path '{}' is not patchable", pointer)))
+}
+
+fn not_nullable(field: &str) -> PatchError {
+    (StatusCode::UNPROCESSABLE_ENTITY, format!("Field '{}' is not nullable and cannot be removed", field))
+}
+
+fn apply_patch(user: &User, content_type: &str, body: &[u8]) -> Result<UpdateUserPayload, PatchError> {
+    let bad_request = |e: serde_json::Error| (StatusCode::BAD_REQUEST, e.to_string());
+    let mut doc = Map::new();
+    doc.insert("email".into(), user.email.clone().into());
+    doc.insert("role".into(), serde_json::to_value(&user.role).map_err(bad_request)?);
+    doc.insert("is_active".into(), user.is_active.into());
+
+    match content_type.split(';').next().unwrap_or("").trim() {
+        JSON_PATCH => {
+            for op in serde_json::from_slice::<Vec<PatchOp>>(body).map_err(bad_request)? {
+                match op {
+                    PatchOp::Add { path, value } | PatchOp::Replace { path, value } => {
+                        doc.insert(field(&path)?.into(), value);
+                    }
+                    PatchOp::Remove { path } => return Err(not_nullable(field(&path)?)),
+                    PatchOp::Move { from, path } => {
+                        let source = field(&from)?;
+                        if source != field(&path)? {
+                            return Err(not_nullable(source));
+                        }
+                    }
+                    PatchOp::Copy { from, path } => {
+                        let value = doc[field(&from)?].clone();
+                        doc.insert(field(&path)?.into(), value);
+                    }
+                    PatchOp::Test { path, value } => {
+                        if doc[field(&path)?] != value {
+                            return Err((StatusCode::PRECONDITION_FAILED, format!("Test failed for path '{}'", path)));
+                        }
+                    }
+                }
+            }
+        }
+        MERGE_PATCH | "application/json" => {
+            for (key, value) in serde_json::from_slice::<Map<String, Value>>(body).map_err(bad_request)? {
+                let key = field(&format!("/{}", key))?.to_string();
+                if value.is_null() {
+                    return Err(not_nullable(&key));
+                }
+                doc.insert(key, value);
+            }
+        }
+        other => return Err((StatusCode::UNSUPPORTED_MEDIA_TYPE, format!("Unsupported patch format '{}'", other))),
+    }
+
+    let payload: UpdateUserPayload = serde_json::from_value(Value::Object(doc)).map_err(bad_request)?;
+    payload.validate().map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(payload)
+}
+
 // --- API HANDLERS ---
 
 async fn create_user(
@@ -170,11 +254,14 @@ async fn create_user(
 async fn update_user(
     State(db): State<AppState>,
     Path(id): Path<Uuid>,
-    Json(payload): Json<UpdateUserPayload>,
-) -> Result<Json<UserResponse>, anyhow::Error> {
-    payload.validate()?;
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<UserResponse>, Response> {
+    let content_type = headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or("application/json");
     let mut db_write = db.write().expect("Failed to acquire write lock");
-    let user = db_write.get_mut(&id).context("User not found")?;
+    let user = db_write.get_mut(&id).ok_or_else(|| (StatusCode::NOT_FOUND, "User not found").into_response())?;
+    let payload = apply_patch(user, content_type, &body)
+        .map_err(|(status, error)| (status, Json(serde_json::json!({ "error": error }))).into_response())?;
 
     if let Some(email) = payload.email { user.email = email; }
     if let Some(role) = payload.role { user.role = role; }
@@ -287,4 +374,57 @@ fn populate_db(db: AppState) {
             created_at: Utc::now(),
         },
     );
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user() -> User {
+        User {
+            id: Uuid::nil(),
+            email: "ada@example.com".to_string(),
+            password_hash: String::new(),
+            role: UserRole::ADMIN,
+            is_active: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn status(content_type: &str, body: Value) -> StatusCode {
+        match apply_patch(&user(), content_type, &serde_json::to_vec(&body).unwrap()) {
+            Ok(_) => StatusCode::OK,
+            Err((status, _)) => status,
+        }
+    }
+
+    #[test]
+    fn patch_documents_map_to_statuses() {
+        let cases = [
+            (JSON_PATCH, json!([{"op": "replace", "path": "/role", "value": "USER"}]), StatusCode::OK),
+            (JSON_PATCH, json!([{"op": "replace", "path": "/is_active", "value": true}]), StatusCode::OK),
+            (JSON_PATCH, json!([{"op": "remove", "path": "/role"}]), StatusCode::UNPROCESSABLE_ENTITY),
+            (JSON_PATCH, json!([{"op": "remove", "path": "/is_active"}]), StatusCode::UNPROCESSABLE_ENTITY),
+            (JSON_PATCH, json!([{"op": "move", "from": "/role", "path": "/email"}]), StatusCode::UNPROCESSABLE_ENTITY),
+            (JSON_PATCH, json!([{"op": "test", "path": "/is_active", "value": true}]), StatusCode::PRECONDITION_FAILED),
+            (JSON_PATCH, json!([{"op": "replace", "path": "/id", "value": "x"}]), StatusCode::BAD_REQUEST),
+            (MERGE_PATCH, json!({"role": "USER", "is_active": true}), StatusCode::OK),
+            (MERGE_PATCH, json!({"role": null}), StatusCode::UNPROCESSABLE_ENTITY),
+            (MERGE_PATCH, json!({"is_active": null}), StatusCode::UNPROCESSABLE_ENTITY),
+            (MERGE_PATCH, json!({"email": "not-an-address"}), StatusCode::BAD_REQUEST),
+            ("application/json", json!({"is_active": null}), StatusCode::UNPROCESSABLE_ENTITY),
+            ("text/plain", json!({}), StatusCode::UNSUPPORTED_MEDIA_TYPE),
+        ];
+        for (content_type, body, expected) in cases {
+            assert_eq!(status(content_type, body.clone()), expected, "{} {}", content_type, body);
+        }
+    }
+
+    #[test]
+    fn replaced_fields_are_applied_and_the_rest_kept() {
+        let payload = apply_patch(&user(), JSON_PATCH, br#"[{"op": "replace", "path": "/role", "value": "USER"}]"#).unwrap();
+        assert_eq!(payload.role, Some(UserRole::USER));
+        assert_eq!(payload.is_active, Some(false));
+        assert_eq!(payload.email.as_deref(), Some("ada@example.com"));
+    }
+}
@@ -20,7 +20,7 @@ chrono = { version = "0.4", features = ["serde"] }
 thiserror = "1"
 tracing = "0.1"
 tracing-subscriber = { version = "0.3", features = ["env-filter"] }
-tower-http = { version = "0.4", features = ["trace"] }
+tower-http = { version = "0.4", features = ["trace", "set-header"] }
 async-trait = "0.1"
 */
 
@@ -44,6 +44,14 @@ pub mod error {
         UserNotFound,
         #[error("Email already exists")]
         EmailConflict,
+        #[error("{0}")]
+        InvalidInput(String),
+        #[error("Unsupported patch format '{0}'")]
+        UnsupportedMediaType(String),
+        #[error("{0}")]
+        PreconditionFailed(String),
+        #[error("{0}")]
+        Unprocessable(String),
         #[error("An internal error occurred")]
         Internal,
     }
@@ -55,6 +63,10 @@ pub mod error {
             let (status, msg) = match self {
                 AppError::UserNotFound => (StatusCode::NOT_FOUND, self.to_string()),
                 AppError::EmailConflict => (StatusCode::CONFLICT, self.to_string()),
+                AppError::InvalidInput(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+                AppError::UnsupportedMediaType(_) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, self.to_string()),
+                AppError::PreconditionFailed(_) => (StatusCode::PRECONDITION_FAILED, self.to_string()),
+                AppError::Unprocessable(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
                 AppError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             };
             (status, Json(serde_json::json!({ "error": msg }))).into_response()
@@ -62,17 +74,118 @@ pub mod error {
     }
 }
 
+// --- Shared PATCH Documents (src/patch.rs) ---
+// Every version's PATCH accepts JSON Patch (RFC 6902) and Merge Patch (RFC 7396).
+// Plain `application/json` is read as a merge patch, as the endpoints did before.
+pub mod patch {
+    use super::error::{AppError, AppResult};
+    use axum::http::{header, HeaderMap};
+    use serde::{de::DeserializeOwned, Deserialize};
+    use serde_json::{Map, Value};
+
+    pub const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+    pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+    #[derive(Deserialize)]
+    #[serde(tag = "op", rename_all = "lowercase")]
+    enum PatchOperation {
+        Add { path: String, value: Value },
+        Remove { path: String },
+        Replace { path: String, value: Value },
+        Move { from: String, path: String },
+        Copy { from: String, path: String },
+        Test { path: String, value: Value },
+    }
+
+    pub fn content_type(headers: &HeaderMap) -> &str {
+        headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("application/json")
+    }
+
+    /// Applies the patch in `body` to `current` and deserializes the result into
+    /// the version's update payload. The keys of `current` are the patchable
+    /// fields; none of them is nullable, so removing one is rejected with 422.
+    pub fn apply<T: DeserializeOwned>(current: Map<String, Value>, content_type: &str, body: &[u8]) -> AppResult<T> {
+        let mut doc = current;
+        match content_type.split(';').next().unwrap_or("").trim() {
+            JSON_PATCH_CONTENT_TYPE => {
+                let ops: Vec<PatchOperation> = serde_json::from_slice(body)
+                    .map_err(|e| AppError::InvalidInput(format!("Invalid JSON Patch document: {}", e)))?;
+                for op in ops {
+                    apply_operation(&mut doc, op)?;
+                }
+            }
+            MERGE_PATCH_CONTENT_TYPE | "application/json" => {
+                let patch: Map<String, Value> = serde_json::from_slice(body)
+                    .map_err(|e| AppError::InvalidInput(format!("Merge patch must be a JSON object: {}", e)))?;
+                for (key, value) in patch {
+                    let field = field(&doc, &format!("/{}", key))?;
+                    if value.is_null() {
+                        return Err(not_nullable(&field));
+                    }
+                    doc.insert(field, value);
+                }
+            }
+            other => return Err(AppError::UnsupportedMediaType(other.to_string())),
+        }
+        serde_json::from_value(Value::Object(doc)).map_err(|e| AppError::InvalidInput(e.to_string()))
+    }
+
+    fn apply_operation(doc: &mut Map<String, Value>, op: PatchOperation) -> AppResult<()> {
+        match op {
+            // Every patchable field always exists, so `add` behaves like `replace`
+            PatchOperation::Add { path, value } | PatchOperation::Replace { path, value } => {
+                doc.insert(field(doc, &path)?, value);
+            }
+            PatchOperation::Remove { path } => return Err(not_nullable(&field(doc, &path)?)),
+            PatchOperation::Move { from, path } => {
+                let source = field(doc, &from)?;
+                if source != field(doc, &path)? {
+                    return Err(not_nullable(&source));
+                }
+            }
+            PatchOperation::Copy { from, path } => {
+                let value = doc[&field(doc, &from)?].clone();
+                doc.insert(field(doc, &path)?, value);
+            }
+            PatchOperation::Test { path, value } => {
+                if doc[&field(doc, &path)?] != value {
+                    return Err(AppError::PreconditionFailed(format!("Test failed for path '{}'", path)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn field(doc: &Map<String, Value>, pointer: &str) -> AppResult<String> {
+        match pointer.strip_prefix('/') {
+            Some(field) if doc.contains_key(field) => Ok(field.to_string()),
+            _ => Err(AppError::InvalidInput(format!("Path '{}' is not patchable", pointer))),
+        }
+    }
+
+    fn not_nullable(field: &str) -> AppError {
+        AppError::Unprocessable(format!("Field '{}' is not nullable and cannot be removed", field))
+    }
+}
+
 // --- User Feature Module (src/users/mod.rs) ---
 pub mod users {
     use super::error::{AppError, AppResult};
+    use super::patch;
     use axum::{
         async_trait,
+        body::Bytes,
         extract::{Path, Query, State},
-        routing::{delete, get, patch, post},
+        http::HeaderMap,
+        routing::{delete, get, post},
         Json, Router,
     };
     use chrono::{DateTime, Utc};
     use serde::{Deserialize, Serialize};
+    use serde_json::{Map, Value};
     use std::{
         collections::HashMap,
         sync::{Arc, RwLock},
@@ -117,17 +230,17 @@ pub mod users {
 
     #[derive(Deserialize, Default)]
     pub struct UpdateUserPayload {
-        email: Option<String>,
-        role: Option<UserRole>,
-        is_active: Option<bool>,
+        pub email: Option<String>,
+        pub role: Option<UserRole>,
+        pub is_active: Option<bool>,
     }
 
     #[derive(Deserialize, Debug, Default)]
     pub struct ListUsersParams {
-        offset: Option<usize>,
-        limit: Option<usize>,
-        role: Option<UserRole>,
-        is_active: Option<bool>,
+        pub offset: Option<usize>,
+        pub limit: Option<usize>,
+        pub role: Option<UserRole>,
+        pub is_active: Option<bool>,
     }
 
     #[derive(Serialize)]
@@ -216,6 +329,18 @@ pub mod users {
     // --- Handlers (src/users/handlers.rs) ---
     type UserStoreState = State<Arc<dyn UserStore>>;
 
+    /// Builds a new active user; shared by every API version's create handler.
+    pub fn new_user(email: String, password: &str, role: UserRole) -> User {
+        User {
+            id: Uuid::new_v4(),
+            email,
+            password_hash: format!("hashed_{}", password), // Hash properly
+            role,
+            is_active: true,
+            created_at: Utc::now(),
+        }
+    }
+
     async fn create_user(
         State(store): UserStoreState,
         Json(payload): Json<CreateUserPayload>,
@@ -223,14 +348,7 @@ pub mod users {
         if store.email_exists(&payload.email).await? {
             return Err(AppError::EmailConflict);
         }
-        let user = User {
-            id: Uuid::new_v4(),
-            email: payload.email,
-            password_hash: format!("hashed_{}", payload.password), // Hash properly
-            role: payload.role,
-            is_active: true,
-            created_at: Utc::now(),
-        };
+        let user = new_user(payload.email, &payload.password, payload.role);
         let created_user = store.create(user).await?;
         Ok((axum::http::StatusCode::CREATED, Json(created_user.into())))
     }
@@ -245,11 +363,26 @@ pub mod users {
         Ok(Json(users.into_iter().map(Into::into).collect()))
     }
 
+    /// The fields a v1 PATCH document may address: the response without `id` and `created_at`.
+    fn patchable_fields(user: User) -> AppResult<Map<String, Value>> {
+        match serde_json::to_value(UserResponse::from(user)).map_err(|_| AppError::Internal)? {
+            Value::Object(mut fields) => {
+                fields.remove("id");
+                fields.remove("created_at");
+                Ok(fields)
+            }
+            _ => Err(AppError::Internal),
+        }
+    }
+
     async fn update_user(
         State(store): UserStoreState,
         Path(id): Path<Uuid>,
-        Json(payload): Json<UpdateUserPayload>,
+        headers: HeaderMap,
+        body: Bytes,
     ) -> AppResult<Json<UserResponse>> {
+        let current = patchable_fields(store.get(id).await?)?;
+        let payload = patch::apply(current, patch::content_type(&headers), &body)?;
         let user = store.update(id, payload).await?;
         Ok(Json(user.into()))
     }
@@ -271,6 +404,352 @@ pub mod users {
             )
             .with_state(store)
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use axum::{http::header, response::IntoResponse};
+
+        async fn store_with_user() -> (Arc<dyn UserStore>, Uuid) {
+            let store: Arc<dyn UserStore> = Arc::new(InMemoryUserStore::new());
+            let mut user = new_user("ada@example.com".to_string(), "secret", UserRole::ADMIN);
+            user.is_active = false;
+            let id = store.create(user).await.unwrap().id;
+            (store, id)
+        }
+
+        fn headers(content_type: &str) -> HeaderMap {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+            headers
+        }
+
+        async fn patch_status(content_type: &str, body: Value) -> axum::http::StatusCode {
+            let (store, id) = store_with_user().await;
+            let body = Bytes::from(serde_json::to_vec(&body).unwrap());
+            match update_user(State(store), Path(id), headers(content_type), body).await {
+                Ok(_) => axum::http::StatusCode::OK,
+                Err(e) => e.into_response().status(),
+            }
+        }
+
+        #[tokio::test]
+        async fn patch_documents_map_to_statuses() {
+            use axum::http::StatusCode;
+            use patch::{JSON_PATCH_CONTENT_TYPE as JSON_PATCH, MERGE_PATCH_CONTENT_TYPE as MERGE_PATCH};
+            use serde_json::json;
+
+            let cases = [
+                (JSON_PATCH, json!([{"op": "replace", "path": "/role", "value": "USER"}]), StatusCode::OK),
+                (JSON_PATCH, json!([{"op": "replace", "path": "/is_active", "value": true}]), StatusCode::OK),
+                (JSON_PATCH, json!([{"op": "remove", "path": "/role"}]), StatusCode::UNPROCESSABLE_ENTITY),
+                (JSON_PATCH, json!([{"op": "remove", "path": "/is_active"}]), StatusCode::UNPROCESSABLE_ENTITY),
+                (JSON_PATCH, json!([{"op": "move", "from": "/role", "path": "/email"}]), StatusCode::UNPROCESSABLE_ENTITY),
+                (JSON_PATCH, json!([{"op": "move", "from": "/role", "path": "/role"}]), StatusCode::OK),
+                (JSON_PATCH, json!([{"op": "test", "path": "/role", "value": "USER"}]), StatusCode::PRECONDITION_FAILED),
+                (JSON_PATCH, json!([{"op": "remove", "path": "/id"}]), StatusCode::BAD_REQUEST),
+                (JSON_PATCH, json!([{"op": "replace", "path": "/is_active", "value": "yes"}]), StatusCode::BAD_REQUEST),
+                (MERGE_PATCH, json!({"role": "USER", "is_active": true}), StatusCode::OK),
+                (MERGE_PATCH, json!({"role": null}), StatusCode::UNPROCESSABLE_ENTITY),
+                (MERGE_PATCH, json!({"is_active": null}), StatusCode::UNPROCESSABLE_ENTITY),
+                (MERGE_PATCH, json!({"created_at": "2020-01-01T00:00:00Z"}), StatusCode::BAD_REQUEST),
+                ("application/json", json!({"is_active": null}), StatusCode::UNPROCESSABLE_ENTITY),
+                ("text/plain", json!({}), StatusCode::UNSUPPORTED_MEDIA_TYPE),
+            ];
+            for (content_type, body, expected) in cases {
+                assert_eq!(patch_status(content_type, body.clone()).await, expected, "{} {}", content_type, body);
+            }
+        }
+
+        #[tokio::test]
+        async fn replaced_fields_are_applied_and_the_rest_kept() {
+            let (store, id) = store_with_user().await;
+            let body = Bytes::from_static(br#"[{"op": "replace", "path": "/role", "value": "USER"}]"#);
+            let Json(user) = update_user(State(store), Path(id), headers(patch::JSON_PATCH_CONTENT_TYPE), body)
+                .await
+                .unwrap();
+            assert_eq!(user.role, UserRole::USER);
+            assert!(!user.is_active);
+            assert_eq!(user.email, "ada@example.com");
+        }
+    }
+}
+
+// --- User Feature Module, API v2 (src/users_v2/mod.rs) ---
+// v2 evolves the public DTOs while sharing the v1 storage layer and models.
+pub mod users_v2 {
+    use super::error::{AppError, AppResult};
+    use super::patch;
+    use super::users::{new_user, ListUsersParams, UpdateUserPayload, User, UserRole, UserStore};
+    use axum::{
+        body::Bytes,
+        extract::{Path, Query, State},
+        http::{HeaderMap, StatusCode},
+        routing::{get, post},
+        Json, Router,
+    };
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use serde_json::{Map, Value};
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    // --- Models (src/users_v2/models.rs) ---
+    #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum AccountStatus {
+        Active,
+        Inactive,
+    }
+
+    /// v2 replaces the `is_active` flag with an explicit `status` and exposes
+    /// roles as a list so multi-role accounts can be added without another version.
+    #[derive(Serialize)]
+    pub struct UserResponseV2 {
+        id: Uuid,
+        email: String,
+        roles: Vec<UserRole>,
+        status: AccountStatus,
+        created_at: DateTime<Utc>,
+    }
+
+    impl From<User> for UserResponseV2 {
+        fn from(user: User) -> Self {
+            Self {
+                id: user.id,
+                email: user.email,
+                roles: vec![user.role],
+                status: if user.is_active { AccountStatus::Active } else { AccountStatus::Inactive },
+                created_at: user.created_at,
+            }
+        }
+    }
+
+    #[derive(Deserialize, Debug, Default)]
+    pub struct ListUsersParamsV2 {
+        offset: Option<usize>,
+        limit: Option<usize>,
+        role: Option<UserRole>,
+        status: Option<AccountStatus>,
+    }
+
+    /// `roles` is a list on the wire, but storage still holds a single role.
+    #[derive(Deserialize)]
+    pub struct CreateUserPayloadV2 {
+        email: String,
+        password: String,
+        roles: Vec<UserRole>,
+    }
+
+    #[derive(Deserialize, Default)]
+    pub struct UpdateUserPayloadV2 {
+        email: Option<String>,
+        roles: Option<Vec<UserRole>>,
+        status: Option<AccountStatus>,
+    }
+
+    fn single_role(roles: Vec<UserRole>) -> AppResult<UserRole> {
+        let mut roles = roles.into_iter();
+        match (roles.next(), roles.next()) {
+            (Some(role), None) => Ok(role),
+            _ => Err(AppError::InvalidInput("Exactly one role is supported".to_string())),
+        }
+    }
+
+    impl From<ListUsersParamsV2> for ListUsersParams {
+        fn from(params: ListUsersParamsV2) -> Self {
+            Self {
+                offset: params.offset,
+                limit: params.limit,
+                role: params.role,
+                is_active: params.status.map(|s| s == AccountStatus::Active),
+            }
+        }
+    }
+
+    // --- Handlers (src/users_v2/handlers.rs) ---
+    type UserStoreState = State<Arc<dyn UserStore>>;
+
+    async fn get_user(State(store): UserStoreState, Path(id): Path<Uuid>) -> AppResult<Json<UserResponseV2>> {
+        let user = store.get(id).await?;
+        Ok(Json(user.into()))
+    }
+
+    async fn list_users(
+        State(store): UserStoreState,
+        Query(params): Query<ListUsersParamsV2>,
+    ) -> Result<Json<Vec<UserResponseV2>>, AppError> {
+        let users = store.list(params.into()).await?;
+        Ok(Json(users.into_iter().map(Into::into).collect()))
+    }
+
+    async fn create_user(
+        State(store): UserStoreState,
+        Json(payload): Json<CreateUserPayloadV2>,
+    ) -> AppResult<(StatusCode, Json<UserResponseV2>)> {
+        let role = single_role(payload.roles)?;
+        if store.email_exists(&payload.email).await? {
+            return Err(AppError::EmailConflict);
+        }
+        let created_user = store.create(new_user(payload.email, &payload.password, role)).await?;
+        Ok((StatusCode::CREATED, Json(created_user.into())))
+    }
+
+    /// The fields a v2 PATCH document may address: the v2 response without `id` and `created_at`.
+    fn patchable_fields(user: User) -> AppResult<Map<String, Value>> {
+        match serde_json::to_value(UserResponseV2::from(user)).map_err(|_| AppError::Internal)? {
+            Value::Object(mut fields) => {
+                fields.remove("id");
+                fields.remove("created_at");
+                Ok(fields)
+            }
+            _ => Err(AppError::Internal),
+        }
+    }
+
+    async fn update_user(
+        State(store): UserStoreState,
+        Path(id): Path<Uuid>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> AppResult<Json<UserResponseV2>> {
+        let current = patchable_fields(store.get(id).await?)?;
+        let payload: UpdateUserPayloadV2 = patch::apply(current, patch::content_type(&headers), &body)?;
+        let update = UpdateUserPayload {
+            email: payload.email,
+            role: payload.roles.map(single_role).transpose()?,
+            is_active: payload.status.map(|s| s == AccountStatus::Active),
+        };
+        let user = store.update(id, update).await?;
+        Ok(Json(user.into()))
+    }
+
+    async fn delete_user(State(store): UserStoreState, Path(id): Path<Uuid>) -> AppResult<StatusCode> {
+        store.delete(id).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    // --- Router (src/users_v2/routes.rs) ---
+    pub fn create_router(store: Arc<dyn UserStore>) -> Router {
+        Router::new()
+            .route("/", post(create_user).get(list_users))
+            .route("/:id", get(get_user).patch(update_user).delete(delete_user))
+            .with_state(store)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::users::InMemoryUserStore;
+        use axum::{http::header, response::IntoResponse};
+        use serde_json::json;
+
+        async fn patch_status(content_type: &str, body: Value) -> StatusCode {
+            let store: Arc<dyn UserStore> = Arc::new(InMemoryUserStore::new());
+            let id = store.create(new_user("ada@example.com".to_string(), "secret", UserRole::ADMIN)).await.unwrap().id;
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+            let body = Bytes::from(serde_json::to_vec(&body).unwrap());
+            match update_user(State(store), Path(id), headers, body).await {
+                Ok(_) => StatusCode::OK,
+                Err(e) => e.into_response().status(),
+            }
+        }
+
+        #[tokio::test]
+        async fn v2_fields_are_patchable_but_not_removable() {
+            let cases = [
+                (json!([{"op": "replace", "path": "/status", "value": "inactive"}]), StatusCode::OK),
+                (json!([{"op": "replace", "path": "/roles", "value": ["USER"]}]), StatusCode::OK),
+                (json!([{"op": "test", "path": "/roles", "value": ["ADMIN"]}]), StatusCode::OK),
+                (json!([{"op": "remove", "path": "/roles"}]), StatusCode::UNPROCESSABLE_ENTITY),
+                (json!([{"op": "remove", "path": "/status"}]), StatusCode::UNPROCESSABLE_ENTITY),
+                (json!([{"op": "replace", "path": "/roles", "value": []}]), StatusCode::BAD_REQUEST),
+                (json!([{"op": "replace", "path": "/is_active", "value": true}]), StatusCode::BAD_REQUEST),
+            ];
+            for (body, expected) in cases {
+                assert_eq!(patch_status(patch::JSON_PATCH_CONTENT_TYPE, body.clone()).await, expected, "{}", body);
+            }
+            assert_eq!(
+                patch_status(patch::MERGE_PATCH_CONTENT_TYPE, json!({"status": null})).await,
+                StatusCode::UNPROCESSABLE_ENTITY
+            );
+        }
+    }
+}
+
+// --- API Versioning (src/versioning.rs) ---
+pub mod versioning {
+    use axum::{
+        http::{HeaderName, HeaderValue},
+        Router,
+    };
+    use chrono::{DateTime, Utc};
+    use tower_http::set_header::SetResponseHeaderLayer;
+
+    /// Assembles feature routers under `/api/{version}` prefixes.
+    ///
+    /// ```ignore
+    /// VersionedRouter::new()
+    ///     .deprecated_version("v1", sunset, "/api/v2", Router::new().nest("/users", v1_users))
+    ///     .deprecated_unversioned(sunset, "/api/v2", Router::new().nest("/users", v1_users))
+    ///     .version("v2", Router::new().nest("/users", v2_users))
+    ///     .into_router();
+    /// ```
+    #[derive(Default)]
+    pub struct VersionedRouter {
+        router: Router,
+    }
+
+    impl VersionedRouter {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn version(self, version: &str, routes: Router) -> Self {
+            Self { router: self.router.nest(&format!("/api/{}", version), routes) }
+        }
+
+        /// Mounts a version whose responses announce its retirement via the
+        /// `Deprecation`, `Sunset` (RFC 8594) and `Link: rel="successor-version"` headers.
+        pub fn deprecated_version(self, version: &str, sunset: DateTime<Utc>, successor: &str, routes: Router) -> Self {
+            self.version(version, with_deprecation(routes, sunset, successor))
+        }
+
+        /// Keeps serving `routes` at their pre-versioning paths (e.g. `/users`) for
+        /// existing clients, with the same deprecation headers as a retired version.
+        pub fn deprecated_unversioned(self, sunset: DateTime<Utc>, successor: &str, routes: Router) -> Self {
+            Self { router: self.router.merge(with_deprecation(routes, sunset, successor)) }
+        }
+
+        pub fn into_router(self) -> Router {
+            self.router
+        }
+    }
+
+    fn with_deprecation(routes: Router, sunset: DateTime<Utc>, successor: &str) -> Router {
+        let sunset_value = HeaderValue::from_str(&sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+            .expect("formatted date is a valid header value");
+        let link_value = HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor))
+            .expect("successor path is a valid header value");
+
+        routes
+            .layer(SetResponseHeaderLayer::overriding(
+                HeaderName::from_static("deprecation"),
+                HeaderValue::from_static("true"),
+            ))
+            .layer(SetResponseHeaderLayer::overriding(HeaderName::from_static("sunset"), sunset_value))
+            .layer(SetResponseHeaderLayer::appending(HeaderName::from_static("link"), link_value))
+    }
+
+    /// Reads a retirement date (RFC 3339) from `key`, falling back to `default`.
+    /// An unparseable value stops startup rather than advertising a wrong date.
+    pub fn sunset_from_env(key: &str, default: &str) -> DateTime<Utc> {
+        let value = std::env::var(key).unwrap_or_else(|_| default.to_string());
+        DateTime::parse_from_rfc3339(&value)
+            .unwrap_or_else(|e| panic!("{} must be an RFC 3339 timestamp, got {:?}: {}", key, value, e))
+            .with_timezone(&Utc)
+    }
 }
 
 // --- Application State (src/state.rs) ---
@@ -294,12 +773,21 @@ async fn main() {
     let user_store: Arc<dyn users::UserStore> = Arc::new(users::InMemoryUserStore::with_data(in_memory_db));
 
     // --- Router Assembly ---
-    // Each feature module provides its own router.
-    let user_routes = users::create_router(user_store);
-
-    let app = Router::new()
-        .nest("/users", user_routes)
+    // Each feature module provides its own router, nested per API version.
+    // `API_V1_SUNSET` moves the v1 retirement date without a rebuild.
+    let v1_sunset = versioning::sunset_from_env("API_V1_SUNSET", "2027-06-30T23:59:59Z");
+    let v1_routes = Router::new()
+        .nest("/users", users::create_router(user_store.clone()));
         // .nest("/posts", posts::create_router(post_store)) // Other features would be added here
+    let v2_routes = Router::new()
+        .nest("/users", users_v2::create_router(user_store));
+
+    let app = versioning::VersionedRouter::new()
+        .deprecated_version("v1", v1_sunset, "/api/v2", v1_routes.clone())
+        // Clients from before versioning still call `/users`; it stays at v1 behaviour
+        .deprecated_unversioned(v1_sunset, "/api/v2", v1_routes)
+        .version("v2", v2_routes)
+        .into_router()
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(DefaultMakeSpan::default().include_headers(true)),
@@ -17,9 +17,9 @@ dashmap = "5.5"
 extern crate rocket;
 
 use rocket::{
-    http::{CookieJar, Status},
-    request::{FromRequest, Outcome, Request},
-    response::{Redirect, Flash},
+    http::{ContentType, CookieJar, Status},
+    request::{FromParam, FromRequest, Outcome, Request},
+    response::{self, Redirect, Flash, Responder},
     serde::json::{json, Json, Value},
     State,
 };
@@ -147,46 +147,170 @@ mod auth {
     }
 }
 
-// --- 4. REQUEST GUARDS ---
+// --- 4. CSRF PROTECTION (double-submit cookie) ---
+// Only cookie-authenticated requests need this: browsers attach the session cookie to
+// cross-site requests, but another origin cannot read the csrf cookie to echo it back.
+mod csrf {
+    use super::*;
+    use rocket::fairing::{Fairing, Info, Kind};
+    use rocket::http::{Cookie, Method, SameSite};
+    use rocket::Response;
+
+    pub const COOKIE: &str = "csrf_token";
+    pub const HEADER: &str = "X-CSRF-Token";
+
+    pub fn verify(req: &Request<'_>) -> Result<(), &'static str> {
+        if matches!(req.method(), Method::Get | Method::Head | Method::Options) {
+            return Ok(());
+        }
+        let cookie = req.cookies().get(COOKIE).map(|c| c.value().to_string());
+        match (cookie, req.headers().get_one(HEADER)) {
+            (Some(c), Some(h)) if constant_time_eq(c.as_bytes(), h.as_bytes()) => Ok(()),
+            (None, _) => Err("Missing CSRF cookie"),
+            _ => Err("Missing or invalid CSRF token"),
+        }
+    }
+
+    fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+
+    /// Issues the csrf cookie on the first GET that arrives without one.
+    pub struct CsrfCookie;
+
+    #[rocket::async_trait]
+    impl Fairing for CsrfCookie {
+        fn info(&self) -> Info {
+            Info { name: "CSRF Cookie Issuer", kind: Kind::Response }
+        }
+
+        async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+            if req.method() != Method::Get || req.cookies().get(COOKIE).is_some() {
+                return;
+            }
+            // Readable by page scripts on purpose: they copy it into the X-CSRF-Token header
+            let cookie = Cookie::build((COOKIE, CsrfToken::new_random().secret().clone()))
+                .path("/")
+                .same_site(SameSite::Strict)
+                .http_only(false)
+                .build();
+            res.adjoin_header(cookie);
+        }
+    }
+}
+
+// --- 5. REQUEST GUARDS ---
 mod guards {
     use super::{auth, db, models::*, AppState};
     use super::*;
 
+    // Guard errors
+    /// Why a guard rejected a request. Rocket only passes the status on to
+    /// catchers, so `reject` also leaves the error in the request-local cache for
+    /// `guard_problem` to render as problem+json.
+    #[derive(Debug, Clone)]
+    pub struct GuardError {
+        status: Status,
+        detail: String,
+    }
+
+    impl GuardError {
+        pub fn new(status: Status, detail: impl Into<String>) -> Self {
+            GuardError { status, detail: detail.into() }
+        }
+
+        /// A `State<T>` the guard depends on was never managed. That is a
+        /// deployment bug, so the type name goes to the log and the client only
+        /// sees a generic 500.
+        fn missing_state<T>() -> Self {
+            eprintln!("Request guard needs unmanaged state: {}", std::any::type_name::<T>());
+            GuardError::new(Status::InternalServerError, "The server is not configured to handle this request.")
+        }
+
+        pub fn reject<S>(self, req: &Request<'_>) -> Outcome<S, GuardError> {
+            req.local_cache(|| Some(self.clone()));
+            Outcome::Error((self.status, self))
+        }
+    }
+
+    /// Looks up managed state from inside a guard without panicking when it is absent.
+    pub async fn managed<'r, T: Send + Sync + 'static>(req: &'r Request<'_>) -> Result<&'r State<T>, GuardError> {
+        req.guard::<&State<T>>().await.succeeded().ok_or_else(GuardError::missing_state::<T>)
+    }
+
+    fn guard_problem(status: Status, req: &Request<'_>) -> Problem {
+        let detail = match req.local_cache(|| None::<GuardError>) {
+            Some(err) => err.detail.clone(),
+            None => "No further details are available.".to_string(),
+        };
+        Problem { status, title: status.reason().unwrap_or("Error"), detail, param: None }
+    }
+
+    #[catch(401)]
+    pub fn unauthorized(req: &Request<'_>) -> Problem {
+        guard_problem(Status::Unauthorized, req)
+    }
+
+    #[catch(403)]
+    pub fn forbidden(req: &Request<'_>) -> Problem {
+        guard_problem(Status::Forbidden, req)
+    }
+
+    #[catch(500)]
+    pub fn internal_error(req: &Request<'_>) -> Problem {
+        guard_problem(Status::InternalServerError, req)
+    }
+
+    pub const SESSION_COOKIE: &str = "session";
+
     pub struct AuthenticatedUser(pub models::User);
     pub struct AdminGuard(pub models::User);
 
     #[rocket::async_trait]
     impl<'r> FromRequest<'r> for AuthenticatedUser {
-        type Error = Value;
+        type Error = GuardError;
 
         async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-            let app_state = req.guard::<&State<AppState>>().await.unwrap();
+            let app_state = match managed::<AppState>(req).await {
+                Ok(state) => state,
+                Err(e) => return e.reject(req),
+            };
             
+            // API clients send a bearer token; browsers carry it in the private session
+            // cookie, and only then is a CSRF token required.
             let token = match req.headers().get_one("Authorization").and_then(|v| v.strip_prefix("Bearer ")) {
-                Some(token) => token,
-                None => return Outcome::Failure((Status::Unauthorized, json!({"error": "Missing token"}))),
+                Some(token) => token.to_string(),
+                None => match req.cookies().get_private(SESSION_COOKIE) {
+                    Some(cookie) => {
+                        if let Err(reason) = csrf::verify(req) {
+                            return GuardError::new(Status::Forbidden, reason).reject(req);
+                        }
+                        cookie.value().to_string()
+                    }
+                    None => return GuardError::new(Status::Unauthorized, "Missing token").reject(req),
+                },
             };
 
-            let claims = match auth::decode_jwt(token, &app_state.jwt_secret) {
+            let claims = match auth::decode_jwt(&token, &app_state.jwt_secret) {
                 Ok(c) => c,
-                Err(_) => return Outcome::Failure((Status::Unauthorized, json!({"error": "Invalid token"}))),
+                Err(_) => return GuardError::new(Status::Unauthorized, "Invalid token").reject(req),
             };
 
             let user_id = match Uuid::parse_str(&claims.sub) {
                 Ok(id) => id,
-                Err(_) => return Outcome::Failure((Status::Unauthorized, json!({"error": "Invalid user ID in token"}))),
+                Err(_) => return GuardError::new(Status::Unauthorized, "Invalid user ID in token").reject(req),
             };
 
             match db::MOCK_USERS.get(&user_id) {
                 Some(user) if user.is_active => Outcome::Success(AuthenticatedUser(user.clone())),
-                _ => Outcome::Failure((Status::Unauthorized, json!({"error": "User not found or inactive"}))),
+                _ => GuardError::new(Status::Unauthorized, "User not found or inactive").reject(req),
             }
         }
     }
     
     #[rocket::async_trait]
     impl<'r> FromRequest<'r> for AdminGuard {
-        type Error = Value;
+        type Error = GuardError;
 
         async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
             match AuthenticatedUser::from_request(req).await {
@@ -194,17 +318,70 @@ mod guards {
                     if user.role == models::Role::ADMIN {
                         Outcome::Success(AdminGuard(user))
                     } else {
-                        Outcome::Failure((Status::Forbidden, json!({"error": "Admin access required"})))
+                        GuardError::new(Status::Forbidden, "Admin access required").reject(req)
                     }
                 }
-                Outcome::Failure(e) => Outcome::Failure(e),
+                Outcome::Error(e) => Outcome::Error(e),
                 Outcome::Forward(f) => Outcome::Forward(f),
             }
         }
     }
+
+    // Path parameters
+    /// RFC 7807 problem details, sent as `application/problem+json`.
+    pub struct Problem {
+        pub status: Status,
+        pub title: &'static str,
+        pub detail: String,
+        /// The request parameter at fault, when there is one.
+        pub param: Option<&'static str>,
+    }
+
+    impl<'r> Responder<'r, 'static> for Problem {
+        fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+            let mut body = json!({
+                "type": "about:blank",
+                "title": self.title,
+                "status": self.status.code,
+                "detail": self.detail,
+            });
+            if let Some(name) = self.param {
+                body["invalid_params"] = json!([{ "name": name, "reason": "must be a UUID" }]);
+            }
+            (self.status, (ContentType::new("application", "problem+json"), body.to_string())).respond_to(req)
+        }
+    }
+
+    /// A UUID path segment. A bare `Uuid` parameter makes Rocket forward on a bad
+    /// value and the client sees an unrelated 404, so handlers take
+    /// `Result<ParsedUuid, InvalidUuid>` and hand it to `require`.
+    pub struct ParsedUuid(pub Uuid);
+
+    #[derive(Debug)]
+    pub struct InvalidUuid(String);
+
+    impl<'a> FromParam<'a> for ParsedUuid {
+        type Error = InvalidUuid;
+
+        fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+            Uuid::parse_str(param).map(ParsedUuid).map_err(|_| InvalidUuid(param.to_string()))
+        }
+    }
+
+    impl ParsedUuid {
+        /// `name` is the route's parameter name, echoed back in the problem body.
+        pub fn require(param: Result<Self, InvalidUuid>, name: &'static str) -> Result<Uuid, Problem> {
+            param.map(|ParsedUuid(id)| id).map_err(|InvalidUuid(value)| Problem {
+                status: Status::BadRequest,
+                title: "Invalid path parameter",
+                detail: format!("'{}' is not a valid UUID for path parameter '{}'", value, name),
+                param: Some(name),
+            })
+        }
+    }
 }
 
-// --- 5. ROUTE HANDLERS ---
+// --- 6. ROUTE HANDLERS ---
 mod routes {
     use super::{auth, db, guards::*, models::*, AppState};
     use super::*;
@@ -216,7 +393,11 @@ mod routes {
     }
 
     #[post("/login", data = "<login_request>")]
-    pub fn login(state: &State<AppState>, login_request: Json<LoginRequest<'_>>) -> Result<Value, (Status, Value)> {
+    pub fn login(
+        state: &State<AppState>,
+        cookies: &CookieJar<'_>,
+        login_request: Json<LoginRequest<'_>>,
+    ) -> Result<Value, (Status, Value)> {
         let user = db::MOCK_USERS
             .iter()
             .find(|entry| entry.value().email == login_request.email)
@@ -226,12 +407,20 @@ mod routes {
             Some(u) if auth::verify_password(login_request.password, &u.password_hash).unwrap_or(false) => {
                 let token = auth::create_jwt(u.id, &u.role, &state.jwt_secret)
                     .map_err(|_| (Status::InternalServerError, json!({"error": "Could not create token"})))?;
+                // Private cookies are encrypted, HttpOnly and SameSite=Strict by default
+                cookies.add_private((SESSION_COOKIE, token.clone()));
                 Ok(json!({ "token": token }))
             }
             _ => Err((Status::Unauthorized, json!({"error": "Invalid credentials"}))),
         }
     }
 
+    #[post("/logout")]
+    pub fn logout(_auth_user: AuthenticatedUser, cookies: &CookieJar<'_>) -> Status {
+        cookies.remove_private(SESSION_COOKIE);
+        Status::NoContent
+    }
+
     #[get("/me")]
     pub fn get_me(auth_user: AuthenticatedUser) -> Json<models::User> {
         Json(auth_user.0)
@@ -263,11 +452,12 @@ mod routes {
     }
 
     #[delete("/posts/<id>")]
-    pub fn delete_post(_admin: AdminGuard, id: Uuid) -> Status {
+    pub fn delete_post(_admin: AdminGuard, id: Result<ParsedUuid, InvalidUuid>) -> Result<Status, Problem> {
+        let id = ParsedUuid::require(id, "id")?;
         if db::MOCK_POSTS.remove(&id).is_some() {
-            Status::NoContent
+            Ok(Status::NoContent)
         } else {
-            Status::NotFound
+            Ok(Status::NotFound)
         }
     }
 
@@ -292,16 +482,16 @@ mod routes {
             .url();
         
         cookies.add(("oauth_csrf_state", csrf_state.secret().clone()));
-        Redirect::to(authorize_url)
+        Redirect::to(authorize_url.to_string())
     }
 
-    #[derive(Deserialize)]
+    #[derive(FromForm)]
     pub struct AuthCallbackQuery {
         code: String,
         state: String,
     }
 
-    #[get("/auth/google/callback?<query>")]
+    #[get("/auth/google/callback?<query..>")]
     pub async fn google_callback(
         state: &State<AppState>,
         cookies: &CookieJar<'_>,
@@ -332,7 +522,7 @@ mod routes {
     }
 }
 
-// --- 6. APPLICATION STATE & MAIN ---
+// --- 7. APPLICATION STATE & MAIN ---
 pub struct AppState {
     jwt_secret: String,
     oauth_client_id: String,
@@ -351,10 +541,13 @@ fn rocket() -> _ {
             oauth_client_id: std::env::var("GOOGLE_CLIENT_ID").unwrap_or_else(|_| "test_id".to_string()),
             oauth_client_secret: std::env::var("GOOGLE_CLIENT_SECRET").unwrap_or_else(|_| "test_secret".to_string()),
         })
+        .attach(csrf::CsrfCookie)
+        .register("/", catchers![guards::unauthorized, guards::forbidden, guards::internal_error])
         .mount(
             "/",
             routes![
                 routes::login,
+                routes::logout,
                 routes::get_me,
                 routes::create_post,
                 routes::list_posts,
@@ -363,4 +556,58 @@ fn rocket() -> _ {
                 routes::google_callback,
             ],
         )
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Header;
+    use rocket::local::blocking::Client;
+
+    fn problem_json() -> ContentType {
+        ContentType::new("application", "problem+json")
+    }
+
+    // Only routes whose state comes through the guards: a handler taking `&State`
+    // directly would stop an unmanaged rocket from launching at all.
+    #[test]
+    fn guards_without_managed_state_answer_500_problem_json() {
+        let rocket = rocket::build()
+            .register("/", catchers![guards::unauthorized, guards::forbidden, guards::internal_error])
+            .mount("/", routes![routes::get_me, routes::delete_post]);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let path = format!("/posts/{}", Uuid::new_v4());
+        for request in [client.get("/me"), client.delete(path.as_str())] {
+            let response = request.header(Header::new("Authorization", "Bearer whatever")).dispatch();
+            assert_eq!(response.status(), Status::InternalServerError);
+            assert_eq!(response.content_type(), Some(problem_json()));
+            let body: Value = response.into_json().expect("problem body");
+            assert_eq!(body["status"], 500);
+            assert_eq!(body["detail"], "The server is not configured to handle this request.");
+        }
+    }
+
+    #[test]
+    fn guard_errors_reach_the_catchers_as_problem_json() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+
+        let response = client.get("/me").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(response.content_type(), Some(problem_json()));
+        assert_eq!(response.into_json::<Value>().unwrap()["detail"], "Missing token");
+
+        let response = client.get("/me").header(Header::new("Authorization", "Bearer not-a-jwt")).dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(response.into_json::<Value>().unwrap()["detail"], "Invalid token");
+
+        let user = db::MOCK_USERS.iter().find(|u| u.email == "user@example.com").unwrap().clone();
+        let token = auth::create_jwt(user.id, &user.role, "a_very_secret_key_for_jwt_1").unwrap();
+        let response = client
+            .delete(format!("/posts/{}", Uuid::new_v4()))
+            .header(Header::new("Authorization", format!("Bearer {}", token)))
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        assert_eq!(response.into_json::<Value>().unwrap()["detail"], "Admin access required");
+    }
+}
@@ -8,19 +8,23 @@ chrono = { version = "0.4", features = ["serde"] }
 jsonwebtoken = "8.3"
 argon2 = "0.5"
 oauth2 = "4.4"
-reqwest = "0.11"
+reqwest = { version = "0.11", features = ["json"] }
 once_cell = "1.18"
 dashmap = "5.5"
 rand = "0.8"
+
+[features]
+# In-crate OAuth provider for exercising the login flow offline
+mock-oauth = []
 */
 
 #[macro_use]
 extern crate rocket;
 
 use rocket::{
-    http::{CookieJar, Status},
-    request::{FromRequest, Outcome, Request},
-    response::{Redirect, Flash},
+    http::{ContentType, CookieJar, Status},
+    request::{FromParam, FromRequest, Outcome, Request},
+    response::{self, Redirect, Flash, Responder},
     serde::json::{json, Json, Value},
     State,
 };
@@ -112,6 +116,7 @@ mod services {
                 is_active: true,
                 created_at: Utc::now(),
             });
+            drop(user_map);
 
             UserService { users }
         }
@@ -123,6 +128,26 @@ mod services {
         pub fn find_by_id(&self, id: Uuid) -> Option<User> {
             self.users.lock().unwrap().get(&id).cloned()
         }
+
+        /// Matches an OAuth login to an account by email, creating a USER on first
+        /// sign-in. Provisioned accounts get an empty hash, so password login never
+        /// succeeds for them.
+        pub fn find_or_provision(&self, email: &str) -> User {
+            let mut users = self.users.lock().unwrap();
+            if let Some(user) = users.values().find(|u| u.email.eq_ignore_ascii_case(email)) {
+                return user.clone();
+            }
+            let user = User {
+                id: Uuid::new_v4(),
+                email: email.to_string(),
+                password_hash: String::new(),
+                role: UserRole::USER,
+                is_active: true,
+                created_at: Utc::now(),
+            };
+            users.insert(user.id, user.clone());
+            user
+        }
     }
 
     // --- Auth Service ---
@@ -135,11 +160,11 @@ mod services {
 
     pub struct AuthService {
         jwt_secret: String,
-        user_service: UserService,
+        user_service: Arc<UserService>,
     }
 
     impl AuthService {
-        pub fn new(jwt_secret: String, user_service: UserService) -> Self {
+        pub fn new(jwt_secret: String, user_service: Arc<UserService>) -> Self {
             AuthService { jwt_secret, user_service }
         }
 
@@ -211,47 +236,165 @@ mod web {
     use super::*;
 
     // --- Guards ---
+    /// Why a guard rejected a request. Rocket only passes the status on to
+    /// catchers, so `reject` also leaves the error in the request-local cache for
+    /// `guard_problem` to render as problem+json.
+    #[derive(Debug, Clone)]
+    pub struct GuardError {
+        status: Status,
+        detail: String,
+    }
+
+    impl GuardError {
+        pub fn new(status: Status, detail: impl Into<String>) -> Self {
+            GuardError { status, detail: detail.into() }
+        }
+
+        /// A `State<T>` the guard depends on was never managed. That is a
+        /// deployment bug, so the type name goes to the log and the client only
+        /// sees a generic 500.
+        fn missing_state<T>() -> Self {
+            eprintln!("Request guard needs unmanaged state: {}", std::any::type_name::<T>());
+            GuardError::new(Status::InternalServerError, "The server is not configured to handle this request.")
+        }
+
+        pub fn reject<S>(self, req: &Request<'_>) -> Outcome<S, GuardError> {
+            req.local_cache(|| Some(self.clone()));
+            Outcome::Error((self.status, self))
+        }
+    }
+
+    /// Looks up managed state from inside a guard without panicking when it is absent.
+    pub async fn managed<'r, T: Send + Sync + 'static>(req: &'r Request<'_>) -> Result<&'r State<T>, GuardError> {
+        req.guard::<&State<T>>().await.succeeded().ok_or_else(GuardError::missing_state::<T>)
+    }
+
+    fn guard_problem(status: Status, req: &Request<'_>) -> Problem {
+        let detail = match req.local_cache(|| None::<GuardError>) {
+            Some(err) => err.detail.clone(),
+            None => "No further details are available.".to_string(),
+        };
+        Problem { status, title: status.reason().unwrap_or("Error"), detail, param: None }
+    }
+
+    #[catch(401)]
+    pub fn unauthorized(req: &Request<'_>) -> Problem {
+        guard_problem(Status::Unauthorized, req)
+    }
+
+    #[catch(403)]
+    pub fn forbidden(req: &Request<'_>) -> Problem {
+        guard_problem(Status::Forbidden, req)
+    }
+
+    #[catch(500)]
+    pub fn internal_error(req: &Request<'_>) -> Problem {
+        guard_problem(Status::InternalServerError, req)
+    }
+
     pub struct Authenticated(pub User);
     pub struct Admin(pub User);
 
     #[rocket::async_trait]
     impl<'r> FromRequest<'r> for Authenticated {
-        type Error = Value;
+        type Error = GuardError;
         async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-            let auth_svc = req.guard::<&State<Arc<AuthService>>>().await.unwrap();
-            let user_svc = req.guard::<&State<Arc<UserService>>>().await.unwrap();
+            let auth_svc = match managed::<Arc<AuthService>>(req).await {
+                Ok(state) => state,
+                Err(e) => return e.reject(req),
+            };
+            let user_svc = match managed::<Arc<UserService>>(req).await {
+                Ok(state) => state,
+                Err(e) => return e.reject(req),
+            };
 
             let token = match req.headers().get_one("Authorization").and_then(|v| v.strip_prefix("Bearer ")) {
                 Some(t) => t,
-                None => return Outcome::Failure((Status::Unauthorized, json!({"error": "Missing auth token"}))),
+                None => return GuardError::new(Status::Unauthorized, "Missing auth token").reject(req),
             };
 
             let claims = match auth_svc.validate_token(token) {
                 Ok(c) => c,
-                Err(_) => return Outcome::Failure((Status::Unauthorized, json!({"error": "Invalid auth token"}))),
+                Err(_) => return GuardError::new(Status::Unauthorized, "Invalid auth token").reject(req),
             };
 
-            let user_id = Uuid::parse_str(&claims.sub).unwrap();
+            let user_id = match Uuid::parse_str(&claims.sub) {
+                Ok(id) => id,
+                Err(_) => return GuardError::new(Status::Unauthorized, "Invalid user ID in auth token").reject(req),
+            };
             match user_svc.find_by_id(user_id) {
                 Some(user) if user.is_active => Outcome::Success(Authenticated(user)),
-                _ => Outcome::Failure((Status::Unauthorized, json!({"error": "User not found"}))),
+                _ => GuardError::new(Status::Unauthorized, "User not found").reject(req),
             }
         }
     }
 
     #[rocket::async_trait]
     impl<'r> FromRequest<'r> for Admin {
-        type Error = Value;
+        type Error = GuardError;
         async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
             match Authenticated::from_request(req).await {
                 Outcome::Success(Authenticated(user)) if user.role == UserRole::ADMIN => Outcome::Success(Admin(user)),
-                Outcome::Success(_) => Outcome::Failure((Status::Forbidden, json!({"error": "Requires admin privileges"}))),
-                Outcome::Failure(e) => Outcome::Failure(e),
+                Outcome::Success(_) => GuardError::new(Status::Forbidden, "Requires admin privileges").reject(req),
+                Outcome::Error(e) => Outcome::Error(e),
                 Outcome::Forward(f) => Outcome::Forward(f),
             }
         }
     }
 
+    // --- Path Parameters ---
+    /// RFC 7807 problem details, sent as `application/problem+json`.
+    pub struct Problem {
+        pub status: Status,
+        pub title: &'static str,
+        pub detail: String,
+        /// The request parameter at fault, when there is one.
+        pub param: Option<&'static str>,
+    }
+
+    impl<'r> Responder<'r, 'static> for Problem {
+        fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+            let mut body = json!({
+                "type": "about:blank",
+                "title": self.title,
+                "status": self.status.code,
+                "detail": self.detail,
+            });
+            if let Some(name) = self.param {
+                body["invalid_params"] = json!([{ "name": name, "reason": "must be a UUID" }]);
+            }
+            (self.status, (ContentType::new("application", "problem+json"), body.to_string())).respond_to(req)
+        }
+    }
+
+    /// A UUID path segment. A bare `Uuid` parameter makes Rocket forward on a bad
+    /// value and the client sees an unrelated 404, so handlers take
+    /// `Result<ParsedUuid, InvalidUuid>` and hand it to `require`.
+    pub struct ParsedUuid(pub Uuid);
+
+    #[derive(Debug)]
+    pub struct InvalidUuid(String);
+
+    impl<'a> FromParam<'a> for ParsedUuid {
+        type Error = InvalidUuid;
+
+        fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+            Uuid::parse_str(param).map(ParsedUuid).map_err(|_| InvalidUuid(param.to_string()))
+        }
+    }
+
+    impl ParsedUuid {
+        /// `name` is the route's parameter name, echoed back in the problem body.
+        pub fn require(param: Result<Self, InvalidUuid>, name: &'static str) -> Result<Uuid, Problem> {
+            param.map(|ParsedUuid(id)| id).map_err(|InvalidUuid(value)| Problem {
+                status: Status::BadRequest,
+                title: "Invalid path parameter",
+                detail: format!("'{}' is not a valid UUID for path parameter '{}'", value, name),
+                param: Some(name),
+            })
+        }
+    }
+
     // --- Handlers ---
     #[derive(Deserialize)]
     pub struct LoginPayload<'r> {
@@ -304,11 +447,16 @@ mod web {
     }
 
     #[delete("/posts/<id>")]
-    pub fn remove_post(_admin: Admin, post_svc: &State<Arc<PostService>>, id: Uuid) -> Status {
+    pub fn remove_post(
+        _admin: Admin,
+        post_svc: &State<Arc<PostService>>,
+        id: Result<ParsedUuid, InvalidUuid>,
+    ) -> Result<Status, Problem> {
+        let id = ParsedUuid::require(id, "id")?;
         if post_svc.delete(id) {
-            Status::NoContent
+            Ok(Status::NoContent)
         } else {
-            Status::NotFound
+            Ok(Status::NotFound)
         }
     }
 
@@ -316,16 +464,53 @@ mod web {
     pub struct OAuthConfig {
         pub client_id: String,
         pub client_secret: String,
+        pub auth_url: String,
+        pub token_url: String,
+        pub userinfo_url: String,
+        pub redirect_url: String,
+    }
+
+    impl OAuthConfig {
+        /// Google unless the `OAUTH_*_URL` variables point somewhere else.
+        pub fn from_env() -> Self {
+            let var = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+            OAuthConfig {
+                client_id: var("GOOGLE_CLIENT_ID", "test_id"),
+                client_secret: var("GOOGLE_CLIENT_SECRET", "test_secret"),
+                auth_url: var("OAUTH_AUTH_URL", "https://accounts.google.com/o/oauth2/v2/auth"),
+                token_url: var("OAUTH_TOKEN_URL", "https://www.googleapis.com/oauth2/v4/token"),
+                userinfo_url: var("OAUTH_USERINFO_URL", "https://openidconnect.googleapis.com/v1/userinfo"),
+                redirect_url: var("OAUTH_REDIRECT_URL", "http://localhost:8000/auth/google/callback"),
+            }
+        }
     }
 
-    fn get_oauth_client(config: &State<OAuthConfig>) -> BasicClient {
+    fn get_oauth_client(config: &OAuthConfig) -> BasicClient {
         BasicClient::new(
             ClientId::new(config.client_id.clone()),
             Some(ClientSecret::new(config.client_secret.clone())),
-            AuthUrl::new("https://accounts.google.com/o/oauth2/v2/auth".to_string()).unwrap(),
-            Some(TokenUrl::new("https://www.googleapis.com/oauth2/v4/token".to_string()).unwrap()),
+            AuthUrl::new(config.auth_url.clone()).expect("invalid OAuth authorize URL"),
+            Some(TokenUrl::new(config.token_url.clone()).expect("invalid OAuth token URL")),
         )
-        .set_redirect_uri(RedirectUrl::new("http://localhost:8000/auth/google/callback".to_string()).unwrap())
+        .set_redirect_uri(RedirectUrl::new(config.redirect_url.clone()).expect("invalid OAuth redirect URL"))
+    }
+
+    #[derive(Deserialize)]
+    struct UserInfo {
+        email: String,
+        #[serde(default)]
+        email_verified: bool,
+    }
+
+    async fn fetch_userinfo(url: &str, access_token: &str) -> Result<UserInfo, reqwest::Error> {
+        reqwest::Client::new()
+            .get(url)
+            .bearer_auth(access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
     }
 
     #[get("/auth/google")]
@@ -333,13 +518,13 @@ mod web {
         let client = get_oauth_client(config);
         let (auth_url, csrf_token) = client.authorize_url(CsrfToken::new_random).url();
         cookies.add(("oauth_csrf_token", csrf_token.secret().clone()));
-        Redirect::to(auth_url)
+        Redirect::to(auth_url.to_string())
     }
 
-    #[derive(Deserialize)]
+    #[derive(FromForm)]
     pub struct CallbackQuery { code: String, state: String }
 
-    #[get("/auth/google/callback?<query>")]
+    #[get("/auth/google/callback?<query..>")]
     pub async fn oauth_callback(
         config: &State<OAuthConfig>,
         cookies: &CookieJar<'_>,
@@ -354,16 +539,219 @@ mod web {
         cookies.remove("oauth_csrf_token");
 
         let client = get_oauth_client(config);
-        let token_result = client.exchange_code(oauth2::AuthorizationCode::new(query.code))
-            .request_async(oauth2::reqwest::async_http_client).await;
-
-        if token_result.is_ok() {
-            // Mock: In a real app, get user info from provider. Here, we log in the default user.
-            let user = user_svc.find_by_email("user@service.com").unwrap();
-            let jwt = auth_svc.generate_token(&user).unwrap();
-            Ok(json!({ "message": "OAuth login successful (mocked)", "token": jwt }))
-        } else {
-            Err(Flash::error(Redirect::to("/login"), "OAuth token exchange failed"))
+        let token = client.exchange_code(oauth2::AuthorizationCode::new(query.code))
+            .request_async(oauth2::reqwest::async_http_client).await
+            .map_err(|_| Flash::error(Redirect::to("/login"), "OAuth token exchange failed"))?;
+
+        let info = fetch_userinfo(&config.userinfo_url, token.access_token().secret()).await
+            .map_err(|_| Flash::error(Redirect::to("/login"), "Could not fetch OAuth user info"))?;
+        // An unverified address could belong to anyone, including an existing account
+        if !info.email_verified {
+            return Err(Flash::error(Redirect::to("/login"), "OAuth email address is not verified"));
+        }
+
+        let user = user_svc.find_or_provision(&info.email);
+        if !user.is_active {
+            return Err(Flash::error(Redirect::to("/login"), "Account is disabled"));
+        }
+        let jwt = auth_svc.generate_token(&user)
+            .map_err(|_| Flash::error(Redirect::to("/login"), "Token generation failed"))?;
+        Ok(json!({ "message": "OAuth login successful", "token": jwt }))
+    }
+}
+
+// --- MOCK IDENTITY PROVIDER (feature "mock-oauth") ---
+// Stands in for Google's authorize, token and userinfo endpoints on an ephemeral
+// localhost port, so the whole OAuth flow can run in CI without network access.
+#[cfg(feature = "mock-oauth")]
+mod mock_idp {
+    use super::web::OAuthConfig;
+    use super::CsrfToken;
+    use oauth2::url::{form_urlencoded, Url};
+    use rocket::serde::json::{json, Value};
+    use std::collections::HashMap;
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+
+    pub const CLIENT_ID: &str = "mock-client";
+    pub const CLIENT_SECRET: &str = "mock-secret";
+    const MAX_BODY_BYTES: usize = 64 * 1024;
+
+    #[derive(Debug, Clone)]
+    pub struct MockIdentity {
+        pub sub: String,
+        pub email: String,
+        pub email_verified: bool,
+    }
+
+    impl Default for MockIdentity {
+        fn default() -> Self {
+            MockIdentity {
+                sub: "mock-user-1".to_string(),
+                email: "oauth.user@service.com".to_string(),
+                email_verified: true,
+            }
+        }
+    }
+
+    // Codes remember the redirect_uri they were issued for, as a real provider checks
+    #[derive(Default)]
+    struct Grants {
+        codes: HashMap<String, (MockIdentity, String)>,
+        tokens: HashMap<String, MockIdentity>,
+    }
+
+    /// Every `/authorize` signs in as the identity given to `start`. The listener
+    /// thread lives as long as the process, whether or not this handle is kept.
+    pub struct MockIdp {
+        addr: SocketAddr,
+    }
+
+    impl MockIdp {
+        pub fn start(identity: MockIdentity) -> io::Result<Self> {
+            let listener = TcpListener::bind("127.0.0.1:0")?;
+            let addr = listener.local_addr()?;
+            std::thread::spawn(move || {
+                let mut grants = Grants::default();
+                for stream in listener.incoming().flatten() {
+                    if let Err(e) = handle(stream, &identity, &mut grants) {
+                        eprintln!("Mock IdP request failed: {}", e);
+                    }
+                }
+            });
+            Ok(MockIdp { addr })
+        }
+
+        pub fn base_url(&self) -> String {
+            format!("http://{}", self.addr)
+        }
+
+        pub fn oauth_config(&self, redirect_url: &str) -> OAuthConfig {
+            let base = self.base_url();
+            OAuthConfig {
+                client_id: CLIENT_ID.to_string(),
+                client_secret: CLIENT_SECRET.to_string(),
+                auth_url: format!("{}/authorize", base),
+                token_url: format!("{}/token", base),
+                userinfo_url: format!("{}/userinfo", base),
+                redirect_url: redirect_url.to_string(),
+            }
+        }
+    }
+
+    /// With `OAUTH_MOCK_IDP=1`, starts a mock provider and returns a config pointing
+    /// at it; otherwise returns `config` unchanged.
+    pub fn from_env_or(config: OAuthConfig) -> OAuthConfig {
+        if std::env::var("OAUTH_MOCK_IDP").map_or(true, |v| v != "1") {
+            return config;
+        }
+        let idp = MockIdp::start(MockIdentity::default()).expect("mock IdP could not bind to localhost");
+        println!("Mock OAuth provider listening at {}", idp.base_url());
+        idp.oauth_config(&config.redirect_url)
+    }
+
+    struct HttpRequest {
+        method: String,
+        path: String,
+        query: HashMap<String, String>,
+        headers: HashMap<String, String>,
+        form: HashMap<String, String>,
+    }
+
+    fn read_request(stream: &TcpStream) -> io::Result<HttpRequest> {
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or("/");
+        let url = Url::parse(&format!("http://mock-idp{}", target))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let mut headers = HashMap::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+            }
+        }
+        let length = headers.get("content-length").and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+        let mut body = vec![0; length.min(MAX_BODY_BYTES)];
+        reader.read_exact(&mut body)?;
+
+        Ok(HttpRequest {
+            method,
+            path: url.path().to_string(),
+            query: url.query_pairs().into_owned().collect(),
+            headers,
+            form: form_urlencoded::parse(&body).into_owned().collect(),
+        })
+    }
+
+    fn respond(mut stream: &TcpStream, status: &str, headers: &[(&str, &str)], body: &str) -> io::Result<()> {
+        let mut head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body.as_bytes())
+    }
+
+    fn respond_json(stream: &TcpStream, status: &str, body: Value) -> io::Result<()> {
+        respond(stream, status, &[("Content-Type", "application/json")], &body.to_string())
+    }
+
+    fn handle(stream: TcpStream, identity: &MockIdentity, grants: &mut Grants) -> io::Result<()> {
+        let req = read_request(&stream)?;
+        match (req.method.as_str(), req.path.as_str()) {
+            // No login page: the configured identity consents immediately
+            ("GET", "/authorize") => {
+                let (Some(redirect_uri), Some(state)) = (req.query.get("redirect_uri"), req.query.get("state")) else {
+                    return respond(&stream, "400 Bad Request", &[], "redirect_uri and state are required");
+                };
+                let Ok(mut location) = Url::parse(redirect_uri) else {
+                    return respond(&stream, "400 Bad Request", &[], "redirect_uri is not a valid URL");
+                };
+                let code = CsrfToken::new_random().secret().clone();
+                grants.codes.insert(code.clone(), (identity.clone(), redirect_uri.clone()));
+                location.query_pairs_mut().append_pair("code", &code).append_pair("state", state);
+                respond(&stream, "302 Found", &[("Location", location.as_str())], "")
+            }
+            ("POST", "/token") => {
+                let grant = req.form.get("code").and_then(|code| grants.codes.remove(code));
+                match grant {
+                    Some((identity, redirect_uri)) if req.form.get("redirect_uri") == Some(&redirect_uri) => {
+                        let access_token = CsrfToken::new_random().secret().clone();
+                        grants.tokens.insert(access_token.clone(), identity);
+                        respond_json(
+                            &stream,
+                            "200 OK",
+                            json!({ "access_token": access_token, "token_type": "bearer", "expires_in": 3600 }),
+                        )
+                    }
+                    _ => respond_json(&stream, "400 Bad Request", json!({ "error": "invalid_grant" })),
+                }
+            }
+            ("GET", "/userinfo") => {
+                let identity = req
+                    .headers
+                    .get("authorization")
+                    .and_then(|v| v.strip_prefix("Bearer "))
+                    .and_then(|token| grants.tokens.get(token));
+                match identity {
+                    Some(identity) => respond_json(
+                        &stream,
+                        "200 OK",
+                        json!({ "sub": identity.sub, "email": identity.email, "email_verified": identity.email_verified }),
+                    ),
+                    None => respond_json(&stream, "401 Unauthorized", json!({ "error": "invalid_token" })),
+                }
+            }
+            _ => respond(&stream, "404 Not Found", &[], ""),
         }
     }
 }
@@ -376,16 +764,16 @@ fn rocket() -> _ {
         user_service.clone(),
     ));
     let post_service = Arc::new(services::PostService::new());
-    let oauth_config = web::OAuthConfig {
-        client_id: std::env::var("GOOGLE_CLIENT_ID").unwrap_or_else(|_| "test_id".to_string()),
-        client_secret: std::env::var("GOOGLE_CLIENT_SECRET").unwrap_or_else(|_| "test_secret".to_string()),
-    };
+    let oauth_config = web::OAuthConfig::from_env();
+    #[cfg(feature = "mock-oauth")]
+    let oauth_config = mock_idp::from_env_or(oauth_config);
 
     rocket::build()
         .manage(user_service)
         .manage(auth_service)
         .manage(post_service)
         .manage(oauth_config)
+        .register("/", catchers![web::unauthorized, web::forbidden, web::internal_error])
         .mount("/", routes![
             web::login,
             web::current_user,
@@ -395,4 +783,152 @@ fn rocket() -> _ {
             web::oauth_redirect,
             web::oauth_callback,
         ])
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Header;
+    use rocket::local::blocking::Client;
+
+    fn client_with(rocket: rocket::Rocket<rocket::Build>) -> Client {
+        let rocket = rocket
+            .register("/", catchers![web::unauthorized, web::forbidden, web::internal_error])
+            .mount("/", routes![web::current_user]);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    fn assert_graceful_500(client: &Client) {
+        let response = client.get("/me").header(Header::new("Authorization", "Bearer whatever")).dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert_eq!(response.content_type(), Some(ContentType::new("application", "problem+json")));
+        let body: Value = response.into_json().expect("problem body");
+        assert_eq!(body["detail"], "The server is not configured to handle this request.");
+    }
+
+    #[test]
+    fn authenticated_guard_without_any_state_answers_500() {
+        assert_graceful_500(&client_with(rocket::build()));
+    }
+
+    #[test]
+    fn authenticated_guard_with_partial_state_answers_500() {
+        let users = Arc::new(services::UserService::new());
+        let auth = Arc::new(services::AuthService::new("secret".to_string(), users));
+        // The user service is never managed, only the auth service
+        assert_graceful_500(&client_with(rocket::build().manage(auth)));
+    }
+
+    #[test]
+    fn missing_token_is_a_401_problem() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client.get("/me").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(response.into_json::<Value>().unwrap()["detail"], "Missing auth token");
+    }
+
+    #[cfg(feature = "mock-oauth")]
+    mod oauth_flow {
+        use super::*;
+        use mock_idp::{MockIdentity, MockIdp};
+        use oauth2::url::Url;
+        use rocket::http::Header;
+        use rocket::local::blocking::Client;
+        use std::io::{Read, Write};
+
+        const REDIRECT_URL: &str = "http://localhost:8000/auth/google/callback";
+
+        struct Flow {
+            client: Client,
+            users: Arc<services::UserService>,
+        }
+
+        fn flow_for(identity: MockIdentity) -> Flow {
+            let idp = MockIdp::start(identity).expect("mock IdP binds");
+            let users = Arc::new(services::UserService::new());
+            let auth = Arc::new(services::AuthService::new("secret".to_string(), users.clone()));
+            let rocket = rocket::build()
+                .manage(users.clone())
+                .manage(auth)
+                .manage(idp.oauth_config(REDIRECT_URL))
+                .register("/", catchers![web::unauthorized, web::forbidden, web::internal_error])
+                .mount("/", routes![web::oauth_redirect, web::oauth_callback, web::current_user]);
+            Flow { client: Client::tracked(rocket).expect("valid rocket instance"), users }
+        }
+
+        // Plays the browser at the provider: a plain GET whose Location is the callback
+        fn authorize(url: &str) -> Url {
+            let url = Url::parse(url).unwrap();
+            let mut stream = std::net::TcpStream::connect((url.host_str().unwrap(), url.port().unwrap())).unwrap();
+            write!(stream, "GET {}?{} HTTP/1.1\r\nHost: localhost\r\n\r\n", url.path(), url.query().unwrap()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 302"), "{}", response);
+            let location = response.lines().find_map(|l| l.strip_prefix("Location: ")).expect("Location header");
+            Url::parse(location).unwrap()
+        }
+
+        fn callback_path(callback: &Url) -> String {
+            format!("{}?{}", callback.path(), callback.query().unwrap())
+        }
+
+        fn start_login(flow: &Flow) -> Url {
+            let response = flow.client.get("/auth/google").dispatch();
+            assert_eq!(response.status(), Status::SeeOther);
+            let authorize_url = response.headers().get_one("Location").expect("redirect to the IdP").to_string();
+            assert!(authorize_url.contains("client_id=mock-client"), "{}", authorize_url);
+            let callback = authorize(&authorize_url);
+            assert_eq!(format!("{}{}", callback.origin().ascii_serialization(), callback.path()), REDIRECT_URL);
+            callback
+        }
+
+        #[test]
+        fn first_login_provisions_the_user_and_returns_a_working_token() {
+            let identity = MockIdentity { email: "first.login@service.com".to_string(), ..MockIdentity::default() };
+            let flow = flow_for(identity);
+            assert!(flow.users.find_by_email("first.login@service.com").is_none());
+
+            let callback = start_login(&flow);
+            let response = flow.client.get(callback_path(&callback)).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            let body: Value = response.into_json().unwrap();
+            let token = body["token"].as_str().expect("token in the callback body").to_string();
+
+            let provisioned = flow.users.find_by_email("first.login@service.com").expect("user was provisioned");
+            assert_eq!(provisioned.role, domain::UserRole::USER);
+            let me = flow.client.get("/me").header(Header::new("Authorization", format!("Bearer {}", token))).dispatch();
+            assert_eq!(me.status(), Status::Ok);
+            assert_eq!(me.into_json::<Value>().unwrap()["id"], provisioned.id.to_string());
+
+            // Signing in again finds the same account rather than creating another
+            let again = start_login(&flow);
+            assert_eq!(flow.client.get(callback_path(&again)).dispatch().status(), Status::Ok);
+            assert_eq!(flow.users.find_by_email("first.login@service.com").unwrap().id, provisioned.id);
+        }
+
+        #[test]
+        fn unverified_emails_are_not_provisioned() {
+            let identity = MockIdentity {
+                email: "admin@service.com".to_string(),
+                email_verified: false,
+                ..MockIdentity::default()
+            };
+            let flow = flow_for(identity);
+            let callback = start_login(&flow);
+            let response = flow.client.get(callback_path(&callback)).dispatch();
+            assert_eq!(response.status(), Status::SeeOther);
+            assert_eq!(response.headers().get_one("Location"), Some("/login"));
+        }
+
+        #[test]
+        fn a_callback_with_a_foreign_state_is_rejected() {
+            let flow = flow_for(MockIdentity::default());
+            let mut callback = start_login(&flow);
+            let code = callback.query_pairs().find(|(k, _)| k == "code").unwrap().1.into_owned();
+            callback.query_pairs_mut().clear().append_pair("code", &code).append_pair("state", "forged");
+            let response = flow.client.get(callback_path(&callback)).dispatch();
+            assert_eq!(response.status(), Status::SeeOther);
+            assert!(flow.users.find_by_email(&MockIdentity::default().email).is_none());
+        }
+    }
+}
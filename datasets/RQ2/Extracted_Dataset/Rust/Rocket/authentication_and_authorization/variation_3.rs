@@ -17,9 +17,9 @@ dashmap = "5.5"
 extern crate rocket;
 
 use rocket::{
-    http::{CookieJar, Status},
-    request::{FromRequest, Outcome, Request},
-    response::{Redirect, Flash},
+    http::{ContentType, CookieJar, Status},
+    request::{FromParam, FromRequest, Outcome, Request},
+    response::{self, Redirect, Flash, Responder},
     serde::json::{json, Json, Value},
     State,
 };
@@ -40,7 +40,7 @@ use oauth2::{
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Role { USER, ADMIN }
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum Status { DRAFT, PUBLISHED }
+pub enum PostStatus { DRAFT, PUBLISHED }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
@@ -59,7 +59,7 @@ pub struct Post {
     pub user_id: Uuid,
     pub title: String,
     pub content: String,
-    pub status: Status,
+    pub status: PostStatus,
 }
 
 type Db<K, V> = Lazy<DashMap<K, V>>;
@@ -101,38 +101,153 @@ impl RoleCheck for UserRole { const MIN_ROLE: Role = Role::USER; }
 pub struct AdminRole;
 impl RoleCheck for AdminRole { const MIN_ROLE: Role = Role::ADMIN; }
 
+/// Why a guard rejected a request. Rocket only passes the status on to
+/// catchers, so `reject` also leaves the error in the request-local cache for
+/// `guard_problem` to render as problem+json.
+#[derive(Debug, Clone)]
+pub struct GuardError {
+    status: Status,
+    detail: String,
+}
+
+impl GuardError {
+    pub fn new(status: Status, detail: impl Into<String>) -> Self {
+        GuardError { status, detail: detail.into() }
+    }
+
+    /// A `State<T>` the guard depends on was never managed. That is a
+    /// deployment bug, so the type name goes to the log and the client only
+    /// sees a generic 500.
+    fn missing_state<T>() -> Self {
+        eprintln!("Request guard needs unmanaged state: {}", std::any::type_name::<T>());
+        GuardError::new(Status::InternalServerError, "The server is not configured to handle this request.")
+    }
+
+    pub fn reject<S>(self, req: &Request<'_>) -> Outcome<S, GuardError> {
+        req.local_cache(|| Some(self.clone()));
+        Outcome::Error((self.status, self))
+    }
+}
+
+/// Looks up managed state from inside a guard without panicking when it is absent.
+pub async fn managed<'r, T: Send + Sync + 'static>(req: &'r Request<'_>) -> Result<&'r State<T>, GuardError> {
+    req.guard::<&State<T>>().await.succeeded().ok_or_else(GuardError::missing_state::<T>)
+}
+
+fn guard_problem(status: Status, req: &Request<'_>) -> Problem {
+    let detail = match req.local_cache(|| None::<GuardError>) {
+        Some(err) => err.detail.clone(),
+        None => "No further details are available.".to_string(),
+    };
+    Problem { status, title: status.reason().unwrap_or("Error"), detail, param: None }
+}
+
+#[catch(401)]
+pub fn unauthorized(req: &Request<'_>) -> Problem {
+    guard_problem(Status::Unauthorized, req)
+}
+
+#[catch(403)]
+pub fn forbidden(req: &Request<'_>) -> Problem {
+    guard_problem(Status::Forbidden, req)
+}
+
+#[catch(500)]
+pub fn internal_error(req: &Request<'_>) -> Problem {
+    guard_problem(Status::InternalServerError, req)
+}
+
 // The actual request guard
 pub struct Auth<R: RoleCheck>(pub User, PhantomData<R>);
 
 #[rocket::async_trait]
 impl<'r, R: RoleCheck + Send + Sync> FromRequest<'r> for Auth<R> {
-    type Error = Value;
+    type Error = GuardError;
 
     async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        let secret = req.guard::<&State<AppConfig>>().await.unwrap().jwt_secret.clone();
+        let secret = match managed::<AppConfig>(req).await {
+            Ok(cfg) => cfg.jwt_secret.clone(),
+            Err(e) => return e.reject(req),
+        };
         
         let token = match req.headers().get_one("Authorization").and_then(|v| v.strip_prefix("Bearer ")) {
             Some(t) => t,
-            None => return Outcome::Failure((Status::Unauthorized, json!({"err": "missing_token"}))),
+            None => return GuardError::new(Status::Unauthorized, "missing_token").reject(req),
         };
 
         let claims = match auth_utils::decode_jwt(token, &secret) {
             Some(c) => c,
-            None => return Outcome::Failure((Status::Unauthorized, json!({"err": "invalid_token"}))),
+            None => return GuardError::new(Status::Unauthorized, "invalid_token").reject(req),
         };
 
         if claims.role < R::MIN_ROLE {
-            return Outcome::Failure((Status::Forbidden, json!({"err": "insufficient_permissions"})));
+            return GuardError::new(Status::Forbidden, "insufficient_permissions").reject(req);
         }
 
-        let user_id = Uuid::parse_str(&claims.sub).unwrap();
+        let user_id = match Uuid::parse_str(&claims.sub) {
+            Ok(id) => id,
+            Err(_) => return GuardError::new(Status::Unauthorized, "invalid_subject").reject(req),
+        };
         match USERS.get(&user_id) {
             Some(usr) if usr.is_active => Outcome::Success(Auth(usr.clone(), PhantomData)),
-            _ => Outcome::Failure((Status::Unauthorized, json!({"err": "user_not_found"}))),
+            _ => GuardError::new(Status::Unauthorized, "user_not_found").reject(req),
         }
     }
 }
 
+// --- PATH PARAMS ---
+/// RFC 7807 problem details, sent as `application/problem+json`.
+pub struct Problem {
+    pub status: Status,
+    pub title: &'static str,
+    pub detail: String,
+    /// The request parameter at fault, when there is one.
+    pub param: Option<&'static str>,
+}
+
+impl<'r> Responder<'r, 'static> for Problem {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let mut body = json!({
+            "type": "about:blank",
+            "title": self.title,
+            "status": self.status.code,
+            "detail": self.detail,
+        });
+        if let Some(name) = self.param {
+            body["invalid_params"] = json!([{ "name": name, "reason": "must be a UUID" }]);
+        }
+        (self.status, (ContentType::new("application", "problem+json"), body.to_string())).respond_to(req)
+    }
+}
+
+/// A UUID path segment. A bare `Uuid` parameter makes Rocket forward on a bad
+/// value and the client sees an unrelated 404, so handlers take
+/// `Result<ParsedUuid, InvalidUuid>` and hand it to `require`.
+pub struct ParsedUuid(pub Uuid);
+
+#[derive(Debug)]
+pub struct InvalidUuid(String);
+
+impl<'a> FromParam<'a> for ParsedUuid {
+    type Error = InvalidUuid;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        Uuid::parse_str(param).map(ParsedUuid).map_err(|_| InvalidUuid(param.to_string()))
+    }
+}
+
+impl ParsedUuid {
+    /// `name` is the route's parameter name, echoed back in the problem body.
+    pub fn require(param: Result<Self, InvalidUuid>, name: &'static str) -> Result<Uuid, Problem> {
+        param.map(|ParsedUuid(id)| id).map_err(|InvalidUuid(value)| Problem {
+            status: Status::BadRequest,
+            title: "Invalid path parameter",
+            detail: format!("'{}' is not a valid UUID for path parameter '{}'", value, name),
+            param: Some(name),
+        })
+    }
+}
+
 // --- API ENDPOINTS ---
 #[derive(Deserialize)]
 struct LoginData<'r> {
@@ -173,7 +288,7 @@ fn create_post(auth: Auth<UserRole>, data: Json<NewPost>) -> (Status, Json<Post>
         user_id: auth.0.id,
         title: data.title.clone(),
         content: data.content.clone(),
-        status: Status::DRAFT,
+        status: PostStatus::DRAFT,
     };
     POSTS.insert(post.id, post.clone());
     (Status::Created, Json(post))
@@ -185,11 +300,12 @@ fn get_posts(_auth: Auth<UserRole>) -> Json<Vec<Post>> {
 }
 
 #[delete("/posts/<id>")]
-fn delete_post(_auth: Auth<AdminRole>, id: Uuid) -> Status {
+fn delete_post(_auth: Auth<AdminRole>, id: Result<ParsedUuid, InvalidUuid>) -> Result<Status, Problem> {
+    let id = ParsedUuid::require(id, "id")?;
     if POSTS.remove(&id).is_some() {
-        Status::NoContent
+        Ok(Status::NoContent)
     } else {
-        Status::NotFound
+        Ok(Status::NotFound)
     }
 }
 
@@ -209,13 +325,13 @@ fn oauth_login(cfg: &State<AppConfig>, cookies: &CookieJar<'_>) -> Redirect {
     let client = get_oauth_client(cfg);
     let (url, state) = client.authorize_url(CsrfToken::new_random).url();
     cookies.add(("csrf", state.secret().clone()));
-    Redirect::to(url)
+    Redirect::to(url.to_string())
 }
 
-#[derive(Deserialize)]
+#[derive(FromForm)]
 struct CallbackParams { code: String, state: String }
 
-#[get("/auth/google/callback?<params>")]
+#[get("/auth/google/callback?<params..>")]
 async fn oauth_callback(cfg: &State<AppConfig>, cookies: &CookieJar<'_>, params: CallbackParams) -> Result<Value, Flash<Redirect>> {
     if cookies.get("csrf").map_or(true, |c| c.value() != params.state) {
         return Err(Flash::error(Redirect::to("/"), "CSRF failed."));
@@ -271,6 +387,7 @@ fn rocket() -> _ {
             oauth_client_id: std::env::var("GOOGLE_CLIENT_ID").unwrap_or_else(|_| "test_id".to_string()),
             oauth_client_secret: std::env::var("GOOGLE_CLIENT_SECRET").unwrap_or_else(|_| "test_secret".to_string()),
         })
+        .register("/", catchers![unauthorized, forbidden, internal_error])
         .mount("/", routes![
             login,
             me,
@@ -280,4 +397,46 @@ fn rocket() -> _ {
             oauth_login,
             oauth_callback,
         ])
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Header;
+    use rocket::local::blocking::Client;
+
+    #[test]
+    fn auth_guard_without_app_config_answers_500_problem_json() {
+        // `login` and the OAuth routes take `&State<AppConfig>` directly, which would
+        // make launch fail, so only the guard-protected routes are mounted
+        let rocket = rocket::build()
+            .register("/", catchers![unauthorized, forbidden, internal_error])
+            .mount("/", routes![me, get_posts, delete_post]);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let post = format!("/posts/{}", Uuid::new_v4());
+        for request in [client.get("/me"), client.get("/posts"), client.delete(post.as_str())] {
+            let response = request.header(Header::new("Authorization", "Bearer whatever")).dispatch();
+            assert_eq!(response.status(), Status::InternalServerError);
+            assert_eq!(response.content_type(), Some(ContentType::new("application", "problem+json")));
+            assert_eq!(
+                response.into_json::<Value>().unwrap()["detail"],
+                "The server is not configured to handle this request."
+            );
+        }
+    }
+
+    #[test]
+    fn user_tokens_are_forbidden_from_admin_routes() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let user = USERS.iter().find(|u| u.role == Role::USER).unwrap().clone();
+        let token = auth_utils::create_jwt(user.id, &user.role, "a_very_secret_key_for_jwt_3");
+
+        let response = client
+            .delete(format!("/posts/{}", Uuid::new_v4()))
+            .header(Header::new("Authorization", format!("Bearer {}", token)))
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        assert_eq!(response.into_json::<Value>().unwrap()["detail"], "insufficient_permissions");
+    }
+}
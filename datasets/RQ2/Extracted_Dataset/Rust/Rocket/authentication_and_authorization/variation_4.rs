@@ -17,9 +17,9 @@ tokio = { version = "1", features = ["sync"] }
 extern crate rocket;
 
 use rocket::{
-    http::{CookieJar, Status},
-    request::{FromRequest, Outcome, Request},
-    response::{Redirect, Flash},
+    http::{ContentType, CookieJar, Status},
+    request::{FromParam, FromRequest, Outcome, Request},
+    response::{self, Redirect, Flash, Responder},
     serde::json::{json, Json, Value},
     State,
 };
@@ -153,7 +153,7 @@ mod auth_provider {
 
     #[derive(Debug, Serialize, Deserialize)]
     pub struct Claims {
-        sub: String,
+        pub sub: String,
         role: Role,
         exp: i64,
     }
@@ -207,47 +207,165 @@ mod web {
     use super::*;
 
     // Guards
+    /// Why a guard rejected a request. Rocket only passes the status on to
+    /// catchers, so `reject` also leaves the error in the request-local cache for
+    /// `guard_problem` to render as problem+json.
+    #[derive(Debug, Clone)]
+    pub struct GuardError {
+        status: Status,
+        detail: String,
+    }
+
+    impl GuardError {
+        pub fn new(status: Status, detail: impl Into<String>) -> Self {
+            GuardError { status, detail: detail.into() }
+        }
+
+        /// A `State<T>` the guard depends on was never managed. That is a
+        /// deployment bug, so the type name goes to the log and the client only
+        /// sees a generic 500.
+        fn missing_state<T>() -> Self {
+            eprintln!("Request guard needs unmanaged state: {}", std::any::type_name::<T>());
+            GuardError::new(Status::InternalServerError, "The server is not configured to handle this request.")
+        }
+
+        pub fn reject<S>(self, req: &Request<'_>) -> Outcome<S, GuardError> {
+            req.local_cache(|| Some(self.clone()));
+            Outcome::Error((self.status, self))
+        }
+    }
+
+    /// Looks up managed state from inside a guard without panicking when it is absent.
+    pub async fn managed<'r, T: Send + Sync + 'static>(req: &'r Request<'_>) -> Result<&'r State<T>, GuardError> {
+        req.guard::<&State<T>>().await.succeeded().ok_or_else(GuardError::missing_state::<T>)
+    }
+
+    fn guard_problem(status: Status, req: &Request<'_>) -> Problem {
+        let detail = match req.local_cache(|| None::<GuardError>) {
+            Some(err) => err.detail.clone(),
+            None => "No further details are available.".to_string(),
+        };
+        Problem { status, title: status.reason().unwrap_or("Error"), detail, param: None }
+    }
+
+    #[catch(401)]
+    pub fn unauthorized(req: &Request<'_>) -> Problem {
+        guard_problem(Status::Unauthorized, req)
+    }
+
+    #[catch(403)]
+    pub fn forbidden(req: &Request<'_>) -> Problem {
+        guard_problem(Status::Forbidden, req)
+    }
+
+    #[catch(500)]
+    pub fn internal_error(req: &Request<'_>) -> Problem {
+        guard_problem(Status::InternalServerError, req)
+    }
+
     pub struct AuthenticatedUser(pub User);
     pub struct AdminUser(pub User);
 
     #[rocket::async_trait]
     impl<'r> FromRequest<'r> for AuthenticatedUser {
-        type Error = Value;
+        type Error = GuardError;
         async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-            let auth_provider = req.guard::<&State<Arc<dyn AuthProvider>>>().await.unwrap();
-            let user_repo = req.guard::<&State<Arc<dyn UserRepository>>>().await.unwrap();
+            let auth_provider = match managed::<Arc<dyn AuthProvider>>(req).await {
+                Ok(state) => state,
+                Err(e) => return e.reject(req),
+            };
+            let user_repo = match managed::<Arc<dyn UserRepository>>(req).await {
+                Ok(state) => state,
+                Err(e) => return e.reject(req),
+            };
 
             let token = match req.headers().get_one("Authorization").and_then(|v| v.strip_prefix("Bearer ")) {
                 Some(t) => t,
-                None => return Outcome::Failure((Status::Unauthorized, json!({"error": "missing token"}))),
+                None => return GuardError::new(Status::Unauthorized, "missing token").reject(req),
             };
 
             let claims = match auth_provider.validate_token(token).await {
                 Ok(c) => c,
-                Err(_) => return Outcome::Failure((Status::Unauthorized, json!({"error": "invalid token"}))),
+                Err(_) => return GuardError::new(Status::Unauthorized, "invalid token").reject(req),
             };
 
-            let user_id = Uuid::parse_str(&claims.sub).unwrap();
+            let user_id = match Uuid::parse_str(&claims.sub) {
+                Ok(id) => id,
+                Err(_) => return GuardError::new(Status::Unauthorized, "invalid user id in token").reject(req),
+            };
             match user_repo.find_by_id(user_id).await {
                 Some(user) if user.is_active => Outcome::Success(AuthenticatedUser(user)),
-                _ => Outcome::Failure((Status::Unauthorized, json!({"error": "user not found"}))),
+                _ => GuardError::new(Status::Unauthorized, "user not found").reject(req),
             }
         }
     }
 
     #[rocket::async_trait]
     impl<'r> FromRequest<'r> for AdminUser {
-        type Error = Value;
+        type Error = GuardError;
         async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
             match AuthenticatedUser::from_request(req).await {
                 Outcome::Success(AuthenticatedUser(user)) if user.role == Role::ADMIN => Outcome::Success(AdminUser(user)),
-                Outcome::Success(_) => Outcome::Failure((Status::Forbidden, json!({"error": "admin required"}))),
-                Outcome::Failure(e) => Outcome::Failure(e),
+                Outcome::Success(_) => GuardError::new(Status::Forbidden, "admin required").reject(req),
+                Outcome::Error(e) => Outcome::Error(e),
                 Outcome::Forward(f) => Outcome::Forward(f),
             }
         }
     }
 
+    // Path parameters
+    /// RFC 7807 problem details, sent as `application/problem+json`.
+    pub struct Problem {
+        pub status: Status,
+        pub title: &'static str,
+        pub detail: String,
+        /// The request parameter at fault, when there is one.
+        pub param: Option<&'static str>,
+    }
+
+    impl<'r> Responder<'r, 'static> for Problem {
+        fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+            let mut body = json!({
+                "type": "about:blank",
+                "title": self.title,
+                "status": self.status.code,
+                "detail": self.detail,
+            });
+            if let Some(name) = self.param {
+                body["invalid_params"] = json!([{ "name": name, "reason": "must be a UUID" }]);
+            }
+            (self.status, (ContentType::new("application", "problem+json"), body.to_string())).respond_to(req)
+        }
+    }
+
+    /// A UUID path segment. A bare `Uuid` parameter makes Rocket forward on a bad
+    /// value and the client sees an unrelated 404, so handlers take
+    /// `Result<ParsedUuid, InvalidUuid>` and hand it to `require`.
+    pub struct ParsedUuid(pub Uuid);
+
+    #[derive(Debug)]
+    pub struct InvalidUuid(String);
+
+    impl<'a> FromParam<'a> for ParsedUuid {
+        type Error = InvalidUuid;
+
+        fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+            Uuid::parse_str(param).map(ParsedUuid).map_err(|_| InvalidUuid(param.to_string()))
+        }
+    }
+
+    impl ParsedUuid {
+        /// `name` is the route's parameter name, echoed back in the problem body.
+        pub fn require(param: Result<Self, InvalidUuid>, name: &'static str) -> Result<Uuid, Problem> {
+            param.map(|ParsedUuid(id)| id).map_err(|InvalidUuid(value)| Problem {
+                status: Status::BadRequest,
+                title: "Invalid path parameter",
+                detail: format!("'{}' is not a valid UUID for path parameter '{}'", value, name),
+                param: Some(name),
+            })
+        }
+    }
+
     // Routes
     #[derive(Deserialize)]
     pub struct LoginRequest<'r> { email: &'r str, password: &'r str }
@@ -295,12 +413,17 @@ mod web {
     }
 
     #[delete("/posts/<id>")]
-    pub async fn delete_post(_admin: AdminUser, post_repo: &State<Arc<dyn PostRepository>>, id: Uuid) -> Status {
-        if post_repo.delete(id).await { Status::NoContent } else { Status::NotFound }
+    pub async fn delete_post(
+        _admin: AdminUser,
+        post_repo: &State<Arc<dyn PostRepository>>,
+        id: Result<ParsedUuid, InvalidUuid>,
+    ) -> Result<Status, Problem> {
+        let id = ParsedUuid::require(id, "id")?;
+        Ok(if post_repo.delete(id).await { Status::NoContent } else { Status::NotFound })
     }
     
     // OAuth2
-    pub struct OAuthConfig { client_id: String, client_secret: String }
+    pub struct OAuthConfig { pub client_id: String, pub client_secret: String }
     fn get_oauth_client(cfg: &State<OAuthConfig>) -> BasicClient {
         BasicClient::new(
             ClientId::new(cfg.client_id.clone()),
@@ -314,12 +437,12 @@ mod web {
     pub fn oauth_redirect(cfg: &State<OAuthConfig>, cookies: &CookieJar<'_>) -> Redirect {
         let (url, state) = get_oauth_client(cfg).authorize_url(CsrfToken::new_random).url();
         cookies.add(("oauth_csrf", state.secret().clone()));
-        Redirect::to(url)
+        Redirect::to(url.to_string())
     }
 
-    #[derive(Deserialize)]
+    #[derive(FromForm)]
     pub struct CallbackQuery { code: String, state: String }
-    #[get("/auth/google/callback?<q>")]
+    #[get("/auth/google/callback?<q..>")]
     pub async fn oauth_callback(
         cfg: &State<OAuthConfig>, cookies: &CookieJar<'_>, q: CallbackQuery,
         auth: &State<Arc<dyn AuthProvider>>, users: &State<Arc<dyn UserRepository>>,
@@ -354,6 +477,7 @@ async fn main() -> Result<(), rocket::Error> {
         .manage(post_repo)
         .manage(auth_provider)
         .manage(oauth_config)
+        .register("/", catchers![web::unauthorized, web::forbidden, web::internal_error])
         .mount("/", routes![
             web::login,
             web::get_me,
@@ -365,4 +489,37 @@ async fn main() -> Result<(), rocket::Error> {
         ])
         .launch()
         .await
-}
\ No newline at end of file
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Header;
+    use rocket::local::blocking::Client;
+
+    fn get_me(rocket: rocket::Rocket<rocket::Build>) -> (Status, Option<ContentType>, Value) {
+        let rocket = rocket
+            .register("/", catchers![web::unauthorized, web::forbidden, web::internal_error])
+            .mount("/", routes![web::get_me]);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+        let response = client.get("/me").header(Header::new("Authorization", "Bearer whatever")).dispatch();
+        (response.status(), response.content_type(), response.into_json().expect("problem body"))
+    }
+
+    #[test]
+    fn missing_auth_provider_is_a_graceful_500() {
+        let (status, content_type, body) = get_me(rocket::build());
+        assert_eq!(status, Status::InternalServerError);
+        assert_eq!(content_type, Some(ContentType::new("application", "problem+json")));
+        assert_eq!(body["detail"], "The server is not configured to handle this request.");
+    }
+
+    #[test]
+    fn missing_user_repository_is_a_graceful_500() {
+        let provider: Arc<dyn auth_provider::AuthProvider> = Arc::new(auth_provider::JwtAuthProvider::new("secret".to_string()));
+        let (status, _, body) = get_me(rocket::build().manage(provider));
+        assert_eq!(status, Status::InternalServerError);
+        assert_eq!(body["status"], 500);
+    }
+}
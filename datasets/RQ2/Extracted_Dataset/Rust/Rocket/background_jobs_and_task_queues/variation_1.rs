@@ -9,6 +9,8 @@ fang = { version = "0.10", features = ["asynk-postgres-queue"], default-features
 async-trait = "0.1"
 tokio = { version = "1", features = ["full"] }
 thiserror = "1.0"
+sqlx = { version = "0.7", features = ["runtime-tokio", "postgres", "uuid", "chrono", "json"] }
+serde_json = "1.0"
 */
 
 // V1: The "Service-Oriented" Developer
@@ -55,10 +57,149 @@ mod models {
     }
 }
 
-// --- 2. Background Task Definitions ---
+// --- 2. Persistence, Mail & Audit ---
+// Tasks are deserialized by the worker without access to Rocket state, so the
+// pool and mailer they need are process-wide.
+mod db {
+    use sqlx::postgres::{PgPool, PgPoolOptions};
+    use std::sync::OnceLock;
+
+    static POOL: OnceLock<PgPool> = OnceLock::new();
+
+    // Connections are opened on first use
+    pub fn init(database_url: &str) -> PgPool {
+        POOL.get_or_init(|| {
+            PgPoolOptions::new()
+                .max_connections(5)
+                .connect_lazy(database_url)
+                .expect("Invalid DATABASE_URL")
+        })
+        .clone()
+    }
+
+    pub fn pool() -> Option<&'static PgPool> {
+        POOL.get()
+    }
+
+    pub async fn migrate(pool: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS users (
+                id UUID PRIMARY KEY,
+                email TEXT NOT NULL UNIQUE,
+                password_hash TEXT NOT NULL,
+                role TEXT NOT NULL DEFAULT 'USER',
+                is_active BOOLEAN NOT NULL DEFAULT TRUE,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                last_login_at TIMESTAMPTZ,
+                inactivity_warned_at TIMESTAMPTZ
+            )",
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS audit_log (
+                id BIGSERIAL PRIMARY KEY,
+                actor TEXT NOT NULL,
+                action TEXT NOT NULL,
+                user_id UUID,
+                details JSONB NOT NULL DEFAULT '{}',
+                created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+        )
+        .execute(pool)
+        .await?;
+        // fang's own schema for the task queue (from its postgres migration)
+        sqlx::query(
+            "DO $$ BEGIN
+                CREATE TYPE fang_task_state AS ENUM ('new', 'in_progress', 'failed', 'finished', 'retried');
+             EXCEPTION WHEN duplicate_object THEN NULL;
+             END $$",
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS fang_tasks (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                metadata JSONB NOT NULL,
+                error_message TEXT,
+                state fang_task_state NOT NULL DEFAULT 'new',
+                task_type VARCHAR NOT NULL DEFAULT 'common',
+                uniq_hash CHAR(64),
+                retries INTEGER NOT NULL DEFAULT 0,
+                scheduled_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+        )
+        .execute(pool)
+        .await?;
+        for index in [
+            "CREATE INDEX IF NOT EXISTS fang_tasks_state_index ON fang_tasks(state)",
+            "CREATE INDEX IF NOT EXISTS fang_tasks_type_index ON fang_tasks(task_type)",
+            "CREATE INDEX IF NOT EXISTS fang_tasks_scheduled_at_index ON fang_tasks(scheduled_at)",
+            "CREATE INDEX IF NOT EXISTS fang_tasks_uniq_hash ON fang_tasks(uniq_hash)",
+        ] {
+            sqlx::query(index).execute(pool).await?;
+        }
+        Ok(())
+    }
+}
+
+mod mailer {
+    use async_trait::async_trait;
+    use std::sync::{Arc, OnceLock};
+
+    #[async_trait]
+    pub trait Mailer: Send + Sync {
+        async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String>;
+    }
+
+    // Stand-in transport; swap for SMTP or a provider API in production
+    pub struct LogMailer;
+
+    #[async_trait]
+    impl Mailer for LogMailer {
+        async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String> {
+            println!("MAILER: To: {} | Subject: {}\n{}", to, subject, body);
+            Ok(())
+        }
+    }
+
+    pub fn global() -> Arc<dyn Mailer> {
+        static MAILER: OnceLock<Arc<dyn Mailer>> = OnceLock::new();
+        MAILER.get_or_init(|| Arc::new(LogMailer)).clone()
+    }
+}
+
+mod audit {
+    use sqlx::{Postgres, Transaction};
+    use uuid::Uuid;
+
+    // Written in the caller's transaction so the entry commits with the change it describes
+    pub async fn record(
+        txn: &mut Transaction<'_, Postgres>,
+        actor: &str,
+        action: &str,
+        user_id: Option<Uuid>,
+        details: serde_json::Value,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO audit_log (actor, action, user_id, details) VALUES ($1, $2, $3, $4)")
+            .bind(actor)
+            .bind(action)
+            .bind(user_id)
+            .bind(details)
+            .execute(&mut **txn)
+            .await?;
+        Ok(())
+    }
+}
+
+// --- 3. Background Task Definitions ---
 mod tasks {
     use super::models::{Post, User};
+    use super::{audit, db, mailer};
     use async_trait::async_trait;
+    use chrono::{DateTime, Duration as ChronoDuration, Utc};
     use fang::{typetag, AsyncRunnable, FangError};
     use serde::{Deserialize, Serialize};
     use std::time::{Duration, SystemTime};
@@ -116,19 +257,156 @@ mod tasks {
         }
     }
 
+    const CLEANUP_ACTOR: &str = "system:cleanup-inactive-users";
+    // Users are warned this many days before their account is deactivated
+    const WARNING_LEAD_DAYS: i64 = 7;
+
+    fn db_error(context: &str, err: sqlx::Error) -> FangError {
+        FangError {
+            description: format!("{}: {}", context, err),
+        }
+    }
+
+    // A user's last activity is their last login, or signup if they never logged in
+    const LAST_ACTIVITY: &str = "COALESCE(last_login_at, created_at)";
+
     #[derive(Serialize, Deserialize)]
-    pub struct CleanupInactiveUsersTask;
+    pub struct CleanupInactiveUsersTask {
+        pub inactive_after_days: i64,
+        pub batch_size: i64,
+    }
+
+    impl CleanupInactiveUsersTask {
+        // INACTIVE_USER_DAYS (default 90) and INACTIVE_USER_BATCH_SIZE (default 100)
+        pub fn from_env() -> Self {
+            let env_or = |key: &str, default: i64| {
+                std::env::var(key).ok().and_then(|v| v.parse().ok()).filter(|n: &i64| *n > 0).unwrap_or(default)
+            };
+            Self {
+                inactive_after_days: env_or("INACTIVE_USER_DAYS", 90).max(WARNING_LEAD_DAYS + 1),
+                batch_size: env_or("INACTIVE_USER_BATCH_SIZE", 100),
+            }
+        }
+
+        /// Emails users who have been inactive for N-7 days and haven't been warned
+        /// since their last activity. Returns how many were warned.
+        async fn warn_batches(&self, pool: &sqlx::PgPool, now: DateTime<Utc>) -> Result<u64, FangError> {
+            let cutoff = now - ChronoDuration::days(self.inactive_after_days - WARNING_LEAD_DAYS);
+            let deactivate_on = |last_activity: DateTime<Utc>| last_activity + ChronoDuration::days(self.inactive_after_days);
+            let mailer = mailer::global();
+            let mut warned = 0;
+            // Keyset pagination so a user whose email fails doesn't stall the loop
+            let mut after_id = Uuid::nil();
+            loop {
+                let mut txn = pool.begin().await.map_err(|e| db_error("begin warning batch", e))?;
+                let batch: Vec<(Uuid, String, DateTime<Utc>)> = sqlx::query_as(&format!(
+                    "SELECT id, email, {activity} FROM users
+                     WHERE is_active AND id > $1 AND {activity} < $2
+                       AND (inactivity_warned_at IS NULL OR inactivity_warned_at < {activity})
+                     ORDER BY id LIMIT $3 FOR UPDATE SKIP LOCKED",
+                    activity = LAST_ACTIVITY
+                ))
+                .bind(after_id)
+                .bind(cutoff)
+                .bind(self.batch_size)
+                .fetch_all(&mut *txn)
+                .await
+                .map_err(|e| db_error("select users to warn", e))?;
+
+                for (user_id, email, last_activity) in &batch {
+                    let body = format!(
+                        "Your account has been inactive since {}. Log in before {} to keep it active.",
+                        last_activity.format("%Y-%m-%d"),
+                        deactivate_on(*last_activity).format("%Y-%m-%d")
+                    );
+                    if let Err(e) = mailer.send(email, "Your account will be deactivated soon", &body).await {
+                        eprintln!("PERIODIC TASK [CleanupUsers]: Warning email to {} failed: {}", user_id, e);
+                        continue;
+                    }
+                    sqlx::query("UPDATE users SET inactivity_warned_at = $1 WHERE id = $2")
+                        .bind(now)
+                        .bind(user_id)
+                        .execute(&mut *txn)
+                        .await
+                        .map_err(|e| db_error("mark user warned", e))?;
+                    audit::record(&mut txn, CLEANUP_ACTOR, "user.inactivity_warning_sent", Some(*user_id), serde_json::json!({
+                        "last_activity": last_activity,
+                        "deactivate_on": deactivate_on(*last_activity),
+                    }))
+                    .await
+                    .map_err(|e| db_error("audit warning", e))?;
+                    warned += 1;
+                }
+                txn.commit().await.map_err(|e| db_error("commit warning batch", e))?;
+
+                match batch.last() {
+                    Some((last_id, ..)) if batch.len() as i64 == self.batch_size => after_id = *last_id,
+                    _ => return Ok(warned),
+                }
+            }
+        }
+
+        /// Deactivates users inactive for N days, but only once they were warned at
+        /// least seven days ago and haven't been active since. Returns how many.
+        async fn deactivate_batches(&self, pool: &sqlx::PgPool, now: DateTime<Utc>) -> Result<u64, FangError> {
+            let cutoff = now - ChronoDuration::days(self.inactive_after_days);
+            let warned_before = now - ChronoDuration::days(WARNING_LEAD_DAYS);
+            let mut deactivated = 0;
+            loop {
+                let mut txn = pool.begin().await.map_err(|e| db_error("begin deactivation batch", e))?;
+                let batch: Vec<(Uuid, DateTime<Utc>)> = sqlx::query_as(&format!(
+                    "UPDATE users SET is_active = FALSE
+                     WHERE id IN (
+                         SELECT id FROM users
+                         WHERE is_active AND {activity} < $1
+                           AND inactivity_warned_at >= {activity} AND inactivity_warned_at <= $2
+                         ORDER BY id LIMIT $3 FOR UPDATE SKIP LOCKED
+                     )
+                     RETURNING id, {activity}",
+                    activity = LAST_ACTIVITY
+                ))
+                .bind(cutoff)
+                .bind(warned_before)
+                .bind(self.batch_size)
+                .fetch_all(&mut *txn)
+                .await
+                .map_err(|e| db_error("deactivate users", e))?;
+
+                for (user_id, last_activity) in &batch {
+                    audit::record(&mut txn, CLEANUP_ACTOR, "user.deactivated_inactive", Some(*user_id), serde_json::json!({
+                        "last_activity": last_activity,
+                        "inactive_after_days": self.inactive_after_days,
+                    }))
+                    .await
+                    .map_err(|e| db_error("audit deactivation", e))?;
+                }
+                txn.commit().await.map_err(|e| db_error("commit deactivation batch", e))?;
+                deactivated += batch.len() as u64;
+
+                // Deactivated rows drop out of the predicate, so the next batch starts fresh
+                if (batch.len() as i64) < self.batch_size {
+                    return Ok(deactivated);
+                }
+            }
+        }
+    }
 
     #[typetag::serde]
     #[async_trait]
     impl AsyncRunnable for CleanupInactiveUsersTask {
         async fn run(&self, _queue: &mut dyn fang::AsyncQueueable) -> Result<(), FangError> {
             println!("PERIODIC TASK [CleanupUsers]: Starting check for inactive users.");
-            // Simulate DB query
-            sleep(Duration::from_secs(1)).await;
-            println!("PERIODIC TASK [CleanupUsers]: Found 3 inactive users. Deactivating accounts.");
-            sleep(Duration::from_secs(2)).await;
-            println!("PERIODIC TASK [CleanupUsers]: Cleanup complete.");
+            let pool = db::pool().ok_or_else(|| FangError {
+                description: "database pool is not initialised".to_string(),
+            })?;
+            let now = Utc::now();
+            // Deactivate first so users warned in this run aren't counted against the same run
+            let deactivated = self.deactivate_batches(pool, now).await?;
+            let warned = self.warn_batches(pool, now).await?;
+            println!(
+                "PERIODIC TASK [CleanupUsers]: Cleanup complete. Warned {} user(s), deactivated {}.",
+                warned, deactivated
+            );
             Ok(())
         }
     }
@@ -178,63 +456,102 @@ mod tasks {
     }
 }
 
-// --- 3. Job Scheduling Service ---
+// --- 4. Job Scheduling Service ---
 mod services {
     use super::tasks::{
         CleanupInactiveUsersTask, ProcessPostImagePipelineTask, RetryableTask, SendWelcomeEmailTask,
     };
-    use fang::{AsyncQueue, FangError};
+    use chrono::{DateTime, Utc};
+    use fang::{AsyncQueue, AsyncQueueable, AsyncRunnable, FangError};
+    use serde::Serialize;
     use uuid::Uuid;
 
+    // A row of fang_tasks as reported to API clients
+    #[derive(Debug, Serialize, sqlx::FromRow)]
+    pub struct JobStatus {
+        pub job_id: Uuid,
+        pub task: Option<String>,
+        pub state: String,
+        pub retries: i32,
+        pub error_message: Option<String>,
+        pub scheduled_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
     // A service to abstract the queueing logic
     pub struct JobService {
         queue: AsyncQueue,
+        pool: sqlx::PgPool,
     }
 
     impl JobService {
-        pub fn new(queue: AsyncQueue) -> Self {
-            Self { queue }
+        pub fn new(queue: AsyncQueue, pool: sqlx::PgPool) -> Self {
+            Self { queue, pool }
+        }
+
+        // Persists the task in fang_tasks and returns its id
+        async fn insert(&self, task: &dyn AsyncRunnable) -> Result<Uuid, FangError> {
+            let mut queue = self.queue.clone();
+            let inserted = queue.insert_task(task).await.map_err(|e| FangError {
+                description: format!("Failed to insert task: {}", e),
+            })?;
+            Ok(inserted.id)
+        }
+
+        pub async fn job_status(&self, job_id: Uuid) -> Result<Option<JobStatus>, sqlx::Error> {
+            // typetag stores the task's type name under "type" in the metadata
+            sqlx::query_as(
+                "SELECT id AS job_id, metadata->>'type' AS task, state::text AS state, retries, error_message,
+                        scheduled_at, created_at, updated_at
+                 FROM fang_tasks WHERE id = $1",
+            )
+            .bind(job_id)
+            .fetch_optional(&self.pool)
+            .await
         }
 
         pub async fn enqueue_welcome_email(
             &self,
             user_id: Uuid,
             user_email: String,
-        ) -> Result<(), FangError> {
+        ) -> Result<Uuid, FangError> {
             let task = SendWelcomeEmailTask {
                 user_id,
                 user_email,
             };
-            self.queue.insert_task(&task).await
+            self.insert(&task).await
         }
 
         pub async fn enqueue_image_processing(
             &self,
             post_id: Uuid,
             image_url: String,
-        ) -> Result<(), FangError> {
+        ) -> Result<Uuid, FangError> {
             let task = ProcessPostImagePipelineTask { post_id, image_url };
-            self.queue.insert_task(&task).await
+            self.insert(&task).await
         }
 
-        pub async fn enqueue_retryable_task(&self) -> Result<(), FangError> {
+        pub async fn enqueue_retryable_task(&self) -> Result<Uuid, FangError> {
             let task = RetryableTask::new();
-            self.queue.insert_task(&task).await
+            self.insert(&task).await
         }
 
         // This would be called by a scheduler, not a web request.
-        pub async fn schedule_periodic_cleanup(&self) -> Result<(), FangError> {
-            let task = CleanupInactiveUsersTask;
-            self.queue.insert_task(&task).await
+        pub async fn schedule_periodic_cleanup(&self) -> Result<Uuid, FangError> {
+            let task = CleanupInactiveUsersTask::from_env();
+            self.insert(&task).await
         }
     }
 }
 
-// --- 4. Web Layer (Rocket Handlers) ---
+// --- 5. Web Layer (Rocket Handlers) ---
 mod web {
     use super::models::{Post, User, UserRole};
-    use super::services::JobService;
+    use super::services::{JobService, JobStatus};
     use chrono::Utc;
+    use rocket::http::Status;
+    use rocket::response::status::Custom;
     use rocket::serde::json::{json, Json, Value};
     use rocket::State;
     use uuid::Uuid;
@@ -297,47 +614,73 @@ mod web {
 
     #[rocket::post("/test/retry")]
     pub async fn test_retry_logic(job_service: &State<JobService>) -> Value {
-        if let Err(e) = job_service.enqueue_retryable_task().await {
-            eprintln!("API Error: Failed to enqueue retryable task: {}", e);
-            return json!({"status": "error", "reason": "Failed to schedule job"});
+        match job_service.enqueue_retryable_task().await {
+            Ok(job_id) => json!({
+                "status": "ok",
+                "job_id": job_id,
+                "message": "Retryable task enqueued. Check worker logs."
+            }),
+            Err(e) => {
+                eprintln!("API Error: Failed to enqueue retryable task: {}", e);
+                json!({"status": "error", "reason": "Failed to schedule job"})
+            }
         }
-        json!({"status": "ok", "message": "Retryable task enqueued. Check worker logs."})
     }
 
-    // In a real app, this would query the fang_tasks table.
-    // Here, we just return a mock status.
-    #[rocket::get("/jobs/<_job_id>")]
-    pub async fn get_job_status(_job_id: Uuid) -> Value {
-        println!("API: Checking status for job {}", _job_id);
-        json!({
-            "job_id": _job_id,
-            "status": "completed", // Mocked status
-            "updated_at": Utc::now()
-        })
+    // Reads the task's row in fang_tasks; unknown ids are a 404
+    #[rocket::get("/jobs/<job_id>")]
+    pub async fn get_job_status(
+        job_id: Uuid,
+        job_service: &State<JobService>,
+    ) -> Result<Json<JobStatus>, Custom<Value>> {
+        println!("API: Checking status for job {}", job_id);
+        match job_service.job_status(job_id).await {
+            Ok(Some(status)) => Ok(Json(status)),
+            Ok(None) => Err(Custom(
+                Status::NotFound,
+                json!({"status": "error", "reason": format!("Job {} not found", job_id)}),
+            )),
+            Err(e) => {
+                eprintln!("API Error: Failed to read job {}: {}", job_id, e);
+                Err(Custom(
+                    Status::InternalServerError,
+                    json!({"status": "error", "reason": "Failed to read job status"}),
+                ))
+            }
+        }
     }
 }
 
-// --- 5. Main Application Setup ---
-use fang::{AsyncQueue, AsyncWorkerPool};
+// --- 6. Main Application Setup ---
+use fang::{AsyncQueue, AsyncWorkerPool, NoTls, RetentionMode};
 use rocket::{Build, Rocket};
 use services::JobService;
 use std::time::Duration;
 use tokio::time::sleep;
 
-// Mock queue for demonstration without a real DB.
-// In production, you'd use `AsyncQueue::builder().uri("postgres://...").build()`
-fn setup_mock_queue() -> AsyncQueue {
-    AsyncQueue::builder().build()
+// Tasks live in the fang_tasks table of the application database
+async fn setup_queue(database_url: &str) -> AsyncQueue {
+    let mut queue = AsyncQueue::builder()
+        .uri(database_url)
+        .max_pool_size(5u32)
+        .build();
+    queue
+        .connect(NoTls)
+        .await
+        .expect("Failed to connect the job queue to DATABASE_URL");
+    queue
 }
 
-async fn run_mock_worker(queue: AsyncQueue) {
-    println!("Starting Mock Worker Pool...");
+async fn run_worker_pool(queue: AsyncQueue) {
+    println!("Starting Worker Pool...");
+    // Finished tasks are kept so GET /jobs/<id> can still report them
     let mut worker_pool = AsyncWorkerPool::builder()
         .queue(queue.clone())
+        .retention_mode(RetentionMode::KeepAll)
         .number_of_workers(2u32)
         .build();
     worker_pool.start().await;
-    println!("Mock Worker Pool Stopped.");
+    println!("Worker Pool Stopped.");
 }
 
 async fn run_periodic_scheduler(job_service: JobService) {
@@ -353,15 +696,20 @@ async fn run_periodic_scheduler(job_service: JobService) {
 }
 
 #[rocket::launch]
-fn rocket() -> Rocket<Build> {
-    let queue = setup_mock_queue();
-    let job_service = JobService::new(queue.clone());
+async fn rocket() -> Rocket<Build> {
+    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "postgres://localhost/app".to_string());
+    let pool = db::init(&database_url);
+    // The workers need fang_tasks before they start polling
+    db::migrate(&pool).await.expect("Failed to prepare database schema");
+
+    let queue = setup_queue(&database_url).await;
+    let job_service = JobService::new(queue.clone(), pool.clone());
 
     // Spawn the worker pool as a background task
-    tokio::spawn(run_mock_worker(queue.clone()));
+    tokio::spawn(run_worker_pool(queue.clone()));
 
     // Spawn the periodic task scheduler
-    let scheduler_service = JobService::new(queue.clone());
+    let scheduler_service = JobService::new(queue.clone(), pool);
     tokio::spawn(run_periodic_scheduler(scheduler_service));
 
     println!("Starting Rocket Server...");
@@ -38,6 +38,7 @@ fn rocket() -> _ {
             "/",
             routes![
                 routes::create_user,
+                routes::list_users,
                 routes::get_user_with_details,
                 routes::assign_role,
                 routes::create_user_and_post_transaction,
@@ -113,6 +114,7 @@ mod models {
     use diesel::prelude::*;
     use rocket_db_pools::Connection;
     use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
     use thiserror::Error;
     use uuid::Uuid;
     use validator::Validate;
@@ -175,25 +177,45 @@ mod models {
                 })
         }
 
-        pub async fn get_posts(&self, db: &mut Connection<DbConn>) -> ModelResult<Vec<Post>> {
-            let user_for_association = User { id: self.id, ..self.clone() };
-            db.run(move |conn| Post::belonging_to(&user_for_association).load(conn))
+        pub async fn find_all(db: &mut Connection<DbConn>) -> ModelResult<Vec<User>> {
+            db.run(|conn| users::table.order(users::created_at.asc()).load(conn))
                 .await
                 .map_err(ModelError::from)
         }
 
-        pub async fn get_roles(&self, db: &mut Connection<DbConn>) -> ModelResult<Vec<Role>> {
-            db.run(move |conn| {
-                user_roles::table
-                    .filter(user_roles::user_id.eq(self.id))
-                    .inner_join(roles::table)
-                    .select(Role::as_select())
-                    .load(conn)
-            })
-            .await
-            .map_err(ModelError::from)
+        /// Loads the posts of every user in `user_ids` with a single query, grouped by
+        /// author. Users without posts have no entry.
+        pub async fn find_posts_for_users(db: &mut Connection<DbConn>, user_ids: &[Uuid]) -> ModelResult<HashMap<Uuid, Vec<Post>>> {
+            let user_ids = user_ids.to_vec();
+            let posts: Vec<Post> = db
+                .run(move |conn| posts::table.filter(posts::user_id.eq_any(user_ids)).load(conn))
+                .await?;
+            let mut grouped: HashMap<Uuid, Vec<Post>> = HashMap::new();
+            for post in posts {
+                grouped.entry(post.user_id).or_default().push(post);
+            }
+            Ok(grouped)
         }
-        
+
+        /// Role counterpart of `find_posts_for_users`.
+        pub async fn find_roles_for_users(db: &mut Connection<DbConn>, user_ids: &[Uuid]) -> ModelResult<HashMap<Uuid, Vec<Role>>> {
+            let user_ids = user_ids.to_vec();
+            let rows: Vec<(Uuid, Role)> = db
+                .run(move |conn| {
+                    user_roles::table
+                        .inner_join(roles::table)
+                        .filter(user_roles::user_id.eq_any(user_ids))
+                        .select((user_roles::user_id, Role::as_select()))
+                        .load(conn)
+                })
+                .await?;
+            let mut grouped: HashMap<Uuid, Vec<Role>> = HashMap::new();
+            for (user_id, role) in rows {
+                grouped.entry(user_id).or_default().push(role);
+            }
+            Ok(grouped)
+        }
+
         pub async fn assign_role(&self, db: &mut Connection<DbConn>, role: &Role) -> ModelResult<()> {
             let new_user_role = NewUserRole { user_id: self.id, role_id: role.id };
             db.run(move |conn| {
@@ -320,11 +342,63 @@ mod models {
         pub posts: Vec<Post>,
         pub roles: Vec<Role>,
     }
+
+    /// Relations that `GET /users?include=` can embed.
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct UserIncludes {
+        pub posts: bool,
+        pub roles: bool,
+    }
+
+    impl UserIncludes {
+        pub fn parse(include: Option<&str>) -> Result<Self, String> {
+            let mut includes = Self::default();
+            for part in include.unwrap_or("").split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                match part {
+                    "posts" => includes.posts = true,
+                    "roles" => includes.roles = true,
+                    other => return Err(format!("Unknown include '{}'; expected posts or roles", other)),
+                }
+            }
+            Ok(includes)
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct UserListItem {
+        #[serde(flatten)]
+        pub user: User,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub posts: Option<Vec<Post>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub roles: Option<Vec<Role>>,
+    }
+
+    /// Attaches the requested relations to `users`, issuing one query per relation
+    /// regardless of how many users there are.
+    pub async fn load_user_relations(
+        db: &mut Connection<DbConn>,
+        users: Vec<User>,
+        includes: UserIncludes,
+    ) -> ModelResult<Vec<UserListItem>> {
+        let ids: Vec<Uuid> = users.iter().map(|u| u.id).collect();
+        let mut posts = if includes.posts { Some(User::find_posts_for_users(db, &ids).await?) } else { None };
+        let mut roles = if includes.roles { Some(User::find_roles_for_users(db, &ids).await?) } else { None };
+
+        Ok(users
+            .into_iter()
+            .map(|user| UserListItem {
+                posts: posts.as_mut().map(|p| p.remove(&user.id).unwrap_or_default()),
+                roles: roles.as_mut().map(|r| r.remove(&user.id).unwrap_or_default()),
+                user,
+            })
+            .collect())
+    }
 }
 
 // ============== ROUTES ==============
 mod routes {
-    use super::models::{self, AssignRolePayload, CreateUserPayload, ModelError, Post, PostStatus, Role, User, UserDetailsResponse};
+    use super::models::{self, AssignRolePayload, CreateUserPayload, ModelError, Post, PostStatus, Role, User, UserDetailsResponse, UserIncludes, UserListItem};
     use super::DbConn;
     use rocket::http::Status;
     use rocket::response::status;
@@ -363,16 +437,38 @@ mod routes {
             .map_err(to_api_error)
     }
 
+    #[get("/users?<include>")]
+    pub async fn list_users(
+        mut db: Connection<DbConn>,
+        include: Option<&str>,
+    ) -> Result<Json<Vec<UserListItem>>, status::Custom<String>> {
+        let includes = UserIncludes::parse(include).map_err(|e| status::Custom(Status::BadRequest, e))?;
+        let users = User::find_all(&mut db).await.map_err(to_api_error)?;
+        models::load_user_relations(&mut db, users, includes)
+            .await
+            .map(Json)
+            .map_err(to_api_error)
+    }
+
+    // Goes through the same batched loaders as the list endpoint
     #[get("/users/<id>")]
     pub async fn get_user_with_details(
         mut db: Connection<DbConn>,
         id: Uuid,
     ) -> Result<Json<UserDetailsResponse>, status::Custom<String>> {
         let user = User::find(&mut db, id).await.map_err(to_api_error)?;
-        let posts = user.get_posts(&mut db).await.map_err(to_api_error)?;
-        let roles = user.get_roles(&mut db).await.map_err(to_api_error)?;
-
-        Ok(Json(UserDetailsResponse { user, posts, roles }))
+        let includes = UserIncludes { posts: true, roles: true };
+        let item = models::load_user_relations(&mut db, vec![user], includes)
+            .await
+            .map_err(to_api_error)?
+            .pop()
+            .expect("one user in, one user out");
+
+        Ok(Json(UserDetailsResponse {
+            user: item.user,
+            posts: item.posts.unwrap_or_default(),
+            roles: item.roles.unwrap_or_default(),
+        }))
     }
 
     #[post("/users/roles", format = "json", data = "<body>")]
@@ -4,11 +4,14 @@ extern crate rocket;
 use async_trait::async_trait;
 use dashmap::DashMap;
 use rocket::fairing::{Fairing, Info, Kind};
-use rocket::http::{Header, Method, Status};
+use rocket::http::{uri::Origin, Header, Method, Status};
+use rocket::request::{self, FromRequest};
 use rocket::serde::json::{json, Json, Value};
 use rocket::{Request, Response, State};
 use serde::Serialize;
 use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
@@ -53,39 +56,180 @@ struct Post {
 
 // --- Middleware Implementation (Modular Struct-based Style) ---
 
-// Module 1: Request Logging
-pub struct RequestLogger;
+// Module 1: Access Logging
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccessLogFormat {
+    // Common Log Format, with the latency appended as a trailing field
+    Clf,
+    Json,
+}
+
+pub struct AccessLog {
+    format: AccessLogFormat,
+    // Only every Nth request to a `sampled_paths` entry is logged; errors always are
+    sample_every: u64,
+    sampled_paths: Vec<String>,
+    sample_counter: AtomicU64,
+}
+
+impl AccessLog {
+    pub fn from_env() -> Self {
+        let format = match std::env::var("ACCESS_LOG_FORMAT").as_deref() {
+            Ok("json") => AccessLogFormat::Json,
+            _ => AccessLogFormat::Clf,
+        };
+        let sample_every = std::env::var("ACCESS_LOG_SAMPLE_EVERY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(100);
+        let sampled_paths = std::env::var("ACCESS_LOG_SAMPLED_PATHS")
+            .unwrap_or_else(|_| "/health".to_string())
+            .split(',')
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+        AccessLog {
+            format,
+            sample_every,
+            sampled_paths,
+            sample_counter: AtomicU64::new(0),
+        }
+    }
+
+    fn should_log(&self, path: &str, status: Status) -> bool {
+        if status.class().is_error() || !self.sampled_paths.iter().any(|p| p == path) {
+            return true;
+        }
+        self.sample_counter.fetch_add(1, Ordering::Relaxed) % self.sample_every == 0
+    }
+}
+
+fn clf_timestamp() -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let now = rocket::time::OffsetDateTime::now_utc();
+    format!(
+        "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+        now.day(),
+        MONTHS[u8::from(now.month()) as usize - 1],
+        now.year(),
+        now.hour(),
+        now.minute(),
+        now.second()
+    )
+}
 
 #[async_trait]
-impl Fairing for RequestLogger {
+impl Fairing for AccessLog {
     fn info(&self) -> Info {
         Info {
-            name: "Request Logger",
+            name: "Access Logger",
             kind: Kind::Request | Kind::Response,
         }
     }
 
     async fn on_request(&self, request: &mut Request<'_>, _: &mut rocket::Data<'_>) {
         request.local_cache(|| Instant::now());
-        info_!("Request [{}]: {} {}", request.id(), request.method(), request.uri());
     }
 
     async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let path = request.uri().path().as_str();
+        if !self.should_log(path, response.status()) {
+            return;
+        }
+
         let start_time = request.local_cache(|| Instant::now());
-        let elapsed = start_time.elapsed();
-        info_!(
-            "Response [{}]: {} {} -> {} ({:.2?})",
-            request.id(),
-            request.method(),
-            request.uri(),
-            response.status(),
-            elapsed
-        );
+        let latency_us = start_time.elapsed().as_micros();
+        let client_ip = request
+            .client_ip()
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let bytes = response.body().preset_size();
+
+        match self.format {
+            AccessLogFormat::Clf => info_!(
+                "{} - - [{}] \"{} {} HTTP/1.1\" {} {} {}us",
+                client_ip,
+                clf_timestamp(),
+                request.method(),
+                request.uri(),
+                response.status().code,
+                bytes.map(|b| b.to_string()).unwrap_or_else(|| "-".to_string()),
+                latency_us
+            ),
+            AccessLogFormat::Json => info_!(
+                "{}",
+                json!({
+                    "clientIp": client_ip,
+                    "method": request.method().as_str(),
+                    "path": path,
+                    "status": response.status().code,
+                    "bytes": bytes,
+                    "latencyUs": latency_us as u64,
+                })
+            ),
+        }
     }
 }
 
 // Module 2: CORS Handling
-pub struct CorsHandler;
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>, // "*" allows any origin
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age: Option<Duration>, // preflight cache lifetime
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: vec!["*".to_string()],
+            allowed_methods: vec!["GET".to_string(), "POST".to_string(), "OPTIONS".to_string()],
+            allowed_headers: vec!["Content-Type".to_string()],
+            allow_credentials: false,
+            max_age: Some(Duration::from_secs(3600)),
+        }
+    }
+}
+
+impl CorsConfig {
+    // Comma-separated CORS_ALLOWED_ORIGINS / _METHODS / _HEADERS, CORS_ALLOW_CREDENTIALS
+    // and CORS_MAX_AGE_SECS override the defaults; credentials with a "*" origin are refused
+    pub fn from_env() -> Result<Self, String> {
+        fn list(key: &str) -> Option<Vec<String>> {
+            std::env::var(key)
+                .ok()
+                .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+        }
+        let defaults = CorsConfig::default();
+        let config = CorsConfig {
+            allowed_origins: list("CORS_ALLOWED_ORIGINS").unwrap_or(defaults.allowed_origins),
+            allowed_methods: list("CORS_ALLOWED_METHODS").unwrap_or(defaults.allowed_methods),
+            allowed_headers: list("CORS_ALLOWED_HEADERS").unwrap_or(defaults.allowed_headers),
+            allow_credentials: std::env::var("CORS_ALLOW_CREDENTIALS").map(|v| v == "true").unwrap_or(defaults.allow_credentials),
+            max_age: match std::env::var("CORS_MAX_AGE_SECS") {
+                Ok(v) => v.parse().ok().map(Duration::from_secs),
+                Err(_) => defaults.max_age,
+            },
+        };
+        if config.allow_credentials && config.allowed_origins.iter().any(|o| o == "*") {
+            return Err("set CORS_ALLOWED_ORIGINS to explicit origins when CORS_ALLOW_CREDENTIALS=true".to_string());
+        }
+        Ok(config)
+    }
+
+    fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins.iter().any(|o| o == "*" || o.eq_ignore_ascii_case(origin))
+    }
+}
+
+pub struct CorsHandler {
+    pub config: CorsConfig,
+}
 
 #[async_trait]
 impl Fairing for CorsHandler {
@@ -97,11 +241,36 @@ impl Fairing for CorsHandler {
     }
 
     async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
-        response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
-        response.set_header(Header::new("Access-Control-Allow-Methods", "GET, POST, OPTIONS"));
-        response.set_header(Header::new("Access-Control-Allow-Headers", "Content-Type"));
-        if request.method() == Method::Options {
-            response.set_status(Status::Ok);
+        let config = &self.config;
+        // Non-CORS requests and disallowed origins get no CORS headers; the browser enforces the rest
+        let Some(origin) = request.headers().get_one("Origin") else { return };
+        if !config.allows_origin(origin) {
+            return;
+        }
+
+        // from_env guarantees "*" never comes with credentials
+        if config.allowed_origins.iter().any(|o| o == "*") {
+            response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
+        } else {
+            // Credentialed responses must name the origin, and caches must key on it
+            response.set_header(Header::new("Access-Control-Allow-Origin", origin.to_string()));
+            response.set_header(Header::new("Vary", "Origin"));
+        }
+        if config.allow_credentials {
+            response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+        }
+
+        let is_preflight = request.method() == Method::Options
+            && request.headers().contains("Access-Control-Request-Method");
+        if is_preflight {
+            response.set_header(Header::new("Access-Control-Allow-Methods", config.allowed_methods.join(", ")));
+            response.set_header(Header::new("Access-Control-Allow-Headers", config.allowed_headers.join(", ")));
+            if let Some(max_age) = config.max_age {
+                response.set_header(Header::new("Access-Control-Max-Age", max_age.as_secs().to_string()));
+            }
+            // There are no OPTIONS routes, so replace the catcher's 404 with an empty 204
+            response.set_status(Status::NoContent);
+            response.set_sized_body(0, std::io::Cursor::new(""));
         }
     }
 }
@@ -170,6 +339,142 @@ impl Fairing for ApiResponseTransformer {
     }
 }
 
+// Module 5: Admin Guard
+// `Admin` is a request guard: it succeeds when the request carries
+// `Authorization: Bearer <ADMIN_TOKEN>`. No ADMIN_TOKEN means no admins.
+pub struct AdminToken(Option<String>);
+
+impl AdminToken {
+    pub fn from_env() -> Self {
+        AdminToken(std::env::var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty()))
+    }
+}
+
+pub struct Admin;
+
+#[async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let expected = request.rocket().state::<AdminToken>().and_then(|token| token.0.as_deref());
+        let presented = request.headers().get_one("Authorization").and_then(|v| v.strip_prefix("Bearer "));
+        match (expected, presented) {
+            (Some(expected), Some(presented)) if expected == presented => request::Outcome::Success(Admin),
+            _ => request::Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+// Module 6: Maintenance Mode
+// Driven by a flag file, so deploys flip it with `touch` / `rm` instead of a proxy
+// change or a restart.
+pub struct MaintenanceMode {
+    enabled: AtomicBool,
+    flag_file: PathBuf,
+    retry_after: Duration,
+    exempt_paths: Vec<String>, // prefixes open to everyone, e.g. health checks
+}
+
+impl MaintenanceMode {
+    // MAINTENANCE_FLAG_FILE, MAINTENANCE_RETRY_AFTER_SECS and comma-separated
+    // MAINTENANCE_EXEMPT_PATHS override the defaults
+    pub fn from_env() -> Self {
+        let mode = MaintenanceMode {
+            enabled: AtomicBool::new(false),
+            flag_file: std::env::var("MAINTENANCE_FLAG_FILE").unwrap_or_else(|_| "maintenance.flag".to_string()).into(),
+            retry_after: Duration::from_secs(
+                std::env::var("MAINTENANCE_RETRY_AFTER_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(120),
+            ),
+            exempt_paths: std::env::var("MAINTENANCE_EXEMPT_PATHS")
+                .unwrap_or_else(|_| "/health".to_string())
+                .split(',')
+                .map(|s| s.trim().trim_end_matches('/').to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+        };
+        mode.refresh();
+        mode
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn refresh(&self) {
+        let enabled = self.flag_file.exists();
+        if self.enabled.swap(enabled, Ordering::Relaxed) != enabled {
+            info_!("Maintenance mode {}", if enabled { "enabled" } else { "disabled" });
+        }
+    }
+
+    pub fn is_exempt(&self, path: &str) -> bool {
+        self.exempt_paths
+            .iter()
+            .any(|prefix| path == prefix || path.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.starts_with('/')))
+    }
+}
+
+const MAINTENANCE_ROUTE: &str = "/__maintenance";
+
+pub struct MaintenanceGate(pub Arc<MaintenanceMode>);
+
+#[async_trait]
+impl Fairing for MaintenanceGate {
+    fn info(&self) -> Info {
+        Info {
+            name: "Maintenance Gate",
+            kind: Kind::Liftoff | Kind::Request,
+        }
+    }
+
+    // Polls the flag file; cheap enough that a file-system notifier isn't worth the dependency
+    async fn on_liftoff(&self, _: &rocket::Rocket<rocket::Orbit>) {
+        let mode = self.0.clone();
+        rocket::tokio::spawn(async move {
+            let mut interval = rocket::tokio::time::interval(Duration::from_secs(2));
+            loop {
+                interval.tick().await;
+                mode.refresh();
+            }
+        });
+    }
+
+    // Fairings can't answer a request themselves, so blocked requests are rerouted to
+    // `maintenance_unavailable` before any handler runs
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut rocket::Data<'_>) {
+        // Preflights pass through so browsers can still read the 503
+        let blocked = self.0.is_enabled()
+            && request.method() != Method::Options
+            && !self.0.is_exempt(request.uri().path().as_str())
+            && request.guard::<Admin>().await.succeeded().is_none();
+        if blocked {
+            request.set_method(Method::Get);
+            request.set_uri(Origin::parse(MAINTENANCE_ROUTE).unwrap());
+        }
+    }
+}
+
+#[derive(Responder)]
+#[response(status = 503, content_type = "json")]
+struct MaintenanceResponse {
+    body: Value,
+    retry_after: Header<'static>,
+}
+
+#[get("/__maintenance")]
+fn maintenance_unavailable(mode: &State<Arc<MaintenanceMode>>) -> MaintenanceResponse {
+    MaintenanceResponse {
+        body: json!({
+            "error": {
+                "code": 503,
+                "message": "The service is down for maintenance"
+            }
+        }),
+        retry_after: Header::new("Retry-After", mode.retry_after.as_secs().to_string()),
+    }
+}
+
 // --- Error Handling ---
 #[catch(default)]
 fn default_catcher(status: Status, _req: &Request) -> Value {
@@ -182,6 +487,19 @@ fn default_catcher(status: Status, _req: &Request) -> Value {
 }
 
 // --- Mock API Routes ---
+#[get("/health")]
+fn health() -> Value {
+    json!({ "status": "ok" })
+}
+
+#[get("/admin/maintenance")]
+fn maintenance_status(_admin: Admin, mode: &State<Arc<MaintenanceMode>>) -> Value {
+    json!({
+        "enabled": mode.is_enabled(),
+        "retryAfterSecs": mode.retry_after.as_secs(),
+    })
+}
+
 #[get("/users/<id>")]
 fn get_user_by_id(id: Uuid) -> Json<User> {
     Json(User {
@@ -207,12 +525,17 @@ fn get_post_by_id(id: Uuid) -> Json<Post> {
 
 #[launch]
 fn rocket() -> _ {
+    let cors = CorsConfig::from_env().expect("Invalid CORS configuration");
+    let maintenance = Arc::new(MaintenanceMode::from_env());
     rocket::build()
         .manage(RateLimiterState(Arc::new(DashMap::new())))
-        .attach(RequestLogger)
-        .attach(CorsHandler)
+        .manage(maintenance.clone())
+        .manage(AdminToken::from_env())
+        .attach(AccessLog::from_env())
+        .attach(CorsHandler { config: cors })
+        .attach(MaintenanceGate(maintenance))
         .attach(RateLimiter)
         .attach(ApiResponseTransformer)
-        .mount("/", routes![get_user_by_id, get_post_by_id])
+        .mount("/", routes![get_user_by_id, get_post_by_id, health, maintenance_status, maintenance_unavailable])
         .register("/", catchers![default_catcher])
 }
\ No newline at end of file
@@ -13,6 +13,7 @@ use rocket::{Config, Request, Response, Rocket, Build};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
@@ -57,11 +58,23 @@ struct Post {
 
 // --- Middleware Implementation (Advanced, Configurable Style) ---
 
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum AccessLogFormat {
+    Clf,
+    Json,
+}
+
 #[derive(Deserialize, Debug)]
+#[serde(default)]
 struct FairingConfig {
     allowed_origin: String,
     rate_limit_count: u64,
     rate_limit_period_sec: u64,
+    access_log_format: AccessLogFormat,
+    // Requests to these paths are logged 1-in-N; error responses are always logged
+    access_log_sampled_paths: Vec<String>,
+    access_log_sample_every: u64,
 }
 
 impl Default for FairingConfig {
@@ -70,6 +83,9 @@ impl Default for FairingConfig {
             allowed_origin: "*".to_string(),
             rate_limit_count: 100,
             rate_limit_period_sec: 60,
+            access_log_format: AccessLogFormat::Clf,
+            access_log_sampled_paths: vec!["/health".to_string()],
+            access_log_sample_every: 100,
         }
     }
 }
@@ -84,6 +100,55 @@ struct RateLimitEntry {
 struct AppIntegrator {
     config: FairingConfig,
     rate_limit_map: Mutex<HashMap<IpAddr, RateLimitEntry>>,
+    access_log_counter: AtomicU64,
+}
+
+impl AppIntegrator {
+    fn should_log(&self, path: &str, status: Status) -> bool {
+        let sampled = self.config.access_log_sampled_paths.iter().any(|p| p == path);
+        if status.class().is_error() || !sampled {
+            return true;
+        }
+        let every = self.config.access_log_sample_every.max(1);
+        self.access_log_counter.fetch_add(1, Ordering::Relaxed) % every == 0
+    }
+
+    fn write_access_log(&self, req: &Request<'_>, res: &Response<'_>, latency: Duration) {
+        let path = req.uri().path().as_str();
+        if !self.should_log(path, res.status()) {
+            return;
+        }
+        let client_ip = req
+            .client_ip()
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let bytes = res.body().preset_size();
+
+        match self.config.access_log_format {
+            AccessLogFormat::Clf => println!(
+                "{} - - [{}] \"{} {} HTTP/1.1\" {} {} {}ms",
+                client_ip,
+                chrono::Utc::now().format("%d/%b/%Y:%H:%M:%S %z"),
+                req.method(),
+                req.uri(),
+                res.status().code,
+                bytes.map(|b| b.to_string()).unwrap_or_else(|| "-".to_string()),
+                latency.as_millis()
+            ),
+            AccessLogFormat::Json => println!(
+                "{}",
+                json!({
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                    "clientIp": client_ip,
+                    "method": req.method().as_str(),
+                    "path": path,
+                    "status": res.status().code,
+                    "bytes": bytes,
+                    "latencyMs": latency.as_millis() as u64,
+                })
+            ),
+        }
+    }
 }
 
 #[async_trait]
@@ -146,17 +211,12 @@ impl Fairing for AppIntegrator {
             return;
         }
 
-        // Request/Response Transformation & Logging
-        if let Some(start_time) = req.local_cache_get::<RequestStartTime>() {
-            let elapsed_ms = start_time.0.elapsed().as_millis();
-            res.set_header(Header::new("X-Response-Time-ms", elapsed_ms.to_string()));
-            println!(
-                "Request: {} {} -> {} ({}ms)",
-                req.method(),
-                req.uri(),
-                res.status(),
-                elapsed_ms
-            );
+        // Request/Response Transformation
+        let elapsed = req
+            .local_cache_get::<RequestStartTime>()
+            .map(|start_time| start_time.0.elapsed());
+        if let Some(elapsed) = elapsed {
+            res.set_header(Header::new("X-Response-Time-ms", elapsed.as_millis().to_string()));
         }
 
         // Error Handling Transformation
@@ -171,6 +231,11 @@ impl Fairing for AppIntegrator {
             res.set_header(rocket::http::ContentType::JSON);
             res.set_sized_body(error_body.len(), std::io::Cursor::new(error_body));
         }
+
+        // Access Logging (after the error body is in place so the byte count is final)
+        if let Some(elapsed) = elapsed {
+            self.write_access_log(req, res, elapsed);
+        }
     }
 }
 
@@ -221,12 +286,16 @@ fn rocket() -> _ {
             allowed_origin = "https://my-frontend.com"
             rate_limit_count = 50
             rate_limit_period_sec = 30
+            access_log_format = "json"
+            access_log_sampled_paths = ["/health"]
+            access_log_sample_every = 50
         "#).nested());
 
     rocket::custom(figment)
         .attach(AppIntegrator {
             config: FairingConfig::default(), // Will be re-read on_ignite
             rate_limit_map: Mutex::new(HashMap::new()),
+            access_log_counter: AtomicU64::new(0),
         })
         .mount("/", routes![get_user_handler, get_post_handler])
         .register("/", catchers![not_found_catcher])
@@ -6,7 +6,7 @@
 
 // --- Cargo.toml dependencies ---
 // [dependencies]
-// rocket = { version = "0.5.0", features = ["json"] }
+// rocket = { version = "0.5.0", features = ["json", "uuid"] }
 // serde = { version = "1.0", features = ["derive"] }
 // uuid = { version = "1.6", features = ["v4", "serde"] }
 // chrono = { version = "0.4", features = ["serde"] }
@@ -14,11 +14,15 @@
 #[macro_use]
 extern crate rocket;
 
-use rocket::serde::{json::Json, Deserialize, Serialize};
-use rocket::http::Status;
+use rocket::serde::{json::{self, Json}, Deserialize, Serialize};
+use rocket::data::{self, Data, FromData};
+use rocket::http::{ContentType, Status};
+use rocket::outcome::Outcome;
+use rocket::request::Request;
 use rocket::response::status;
 use rocket::State;
 use std::collections::HashMap;
+use std::ops::Deref;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
@@ -75,10 +79,82 @@ struct ListUsersQuery<'r> {
 
 type UserDb = Arc<Mutex<HashMap<Uuid, User>>>;
 
-// --- 5. API Routes / Handlers ---
+// --- 5. JSON Bodies & Error Catchers ---
+
+// Rocket's `Json` guard only hands a status to catchers. This wrapper also
+// keeps the serde error in the request-local cache, so `json_problem` can
+// answer with a problem+json body that points at the offending line/column.
+pub struct JsonBody<T>(pub T);
+
+impl<T> Deref for JsonBody<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+struct BodyError {
+    detail: String,
+    location: Option<(usize, usize)>,
+}
+
+#[rocket::async_trait]
+impl<'r, T: Deserialize<'r>> FromData<'r> for JsonBody<T> {
+    type Error = json::Error<'r>;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        match Json::<T>::from_data(req, data).await {
+            Outcome::Success(Json(value)) => Outcome::Success(JsonBody(value)),
+            Outcome::Error((status, err)) => {
+                let location = match &err {
+                    json::Error::Parse(_, e) => Some((e.line(), e.column())),
+                    json::Error::Io(_) => None,
+                };
+                req.local_cache(|| Some(BodyError { detail: err.to_string(), location }));
+                Outcome::Error((status, err))
+            }
+            Outcome::Forward(f) => Outcome::Forward(f),
+        }
+    }
+}
+
+fn json_problem(status: Status, req: &Request<'_>) -> (Status, (ContentType, String)) {
+    let mut body = json::json!({
+        "type": "about:blank",
+        "title": "Invalid request body",
+        "status": status.code,
+        "detail": status.reason().unwrap_or("The request body could not be read"),
+    });
+    if let Some(err) = req.local_cache(|| None::<BodyError>) {
+        body["detail"] = err.detail.clone().into();
+        if let Some((line, column)) = err.location {
+            body["line"] = line.into();
+            body["column"] = column.into();
+        }
+    }
+    (status, (ContentType::new("application", "problem+json"), body.to_string()))
+}
+
+#[catch(400)]
+fn bad_request(req: &Request<'_>) -> (Status, (ContentType, String)) {
+    json_problem(Status::BadRequest, req)
+}
+
+#[catch(413)]
+fn payload_too_large(req: &Request<'_>) -> (Status, (ContentType, String)) {
+    json_problem(Status::PayloadTooLarge, req)
+}
+
+#[catch(422)]
+fn unprocessable_entity(req: &Request<'_>) -> (Status, (ContentType, String)) {
+    json_problem(Status::UnprocessableEntity, req)
+}
+
+// --- 6. API Routes / Handlers ---
 
 #[post("/users", format = "json", data = "<payload>")]
-fn create_user(payload: Json<CreateUserPayload>, db: &State<UserDb>) -> status::Created<Json<User>> {
+fn create_user(payload: JsonBody<CreateUserPayload>, db: &State<UserDb>) -> status::Created<Json<User>> {
     let mut users = db.lock().unwrap();
     
     // Simple check for existing email
@@ -111,7 +187,7 @@ fn get_user_by_id(id: Uuid, db: &State<UserDb>) -> Option<Json<User>> {
 }
 
 #[patch("/users/<id>", format = "json", data = "<payload>")]
-fn update_user(id: Uuid, payload: Json<UpdateUserPayload>, db: &State<UserDb>) -> Option<Json<User>> {
+fn update_user(id: Uuid, payload: JsonBody<UpdateUserPayload>, db: &State<UserDb>) -> Option<Json<User>> {
     let mut users = db.lock().unwrap();
     if let Some(user) = users.get_mut(&id) {
         if let Some(email) = &payload.email {
@@ -139,7 +215,7 @@ fn delete_user(id: Uuid, db: &State<UserDb>) -> Status {
     }
 }
 
-#[get("/users")]
+#[get("/users?<query..>")]
 fn list_users(query: ListUsersQuery, db: &State<UserDb>) -> Json<Vec<User>> {
     let users = db.lock().unwrap();
     
@@ -159,7 +235,7 @@ fn list_users(query: ListUsersQuery, db: &State<UserDb>) -> Json<Vec<User>> {
     Json(paginated_users)
 }
 
-// --- 6. Main Application Setup ---
+// --- 7. Main Application Setup ---
 
 #[launch]
 fn rocket() -> _ {
@@ -182,6 +258,7 @@ fn rocket() -> _ {
 
     rocket::build()
         .manage(db)
+        .register("/", catchers![bad_request, payload_too_large, unprocessable_entity])
         .mount("/", routes![
             create_user,
             get_user_by_id,
@@ -189,4 +266,58 @@ fn rocket() -> _ {
             delete_user,
             list_users
         ])
-}
\ No newline at end of file
+}
+
+// --- 8. End-to-End Tests ---
+
+// The users scenario shared with the Axum and Actix REST variations: create,
+// fetch, assign a role, list, delete. This variation filters lists by email
+// rather than role, and a duplicate email panics (500), so that step is left out.
+#[cfg(test)]
+mod e2e {
+    use super::rocket;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::asynchronous::Client;
+    use rocket::serde::json::{json, Value};
+
+    #[rocket::async_test]
+    async fn user_lifecycle() {
+        let client = Client::tracked(rocket()).await.expect("valid rocket instance");
+
+        let response = client
+            .post("/users")
+            .header(ContentType::JSON)
+            .body(json!({"email": "grace@example.com", "password": "s3cret!"}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let created: Value = response.into_json().await.unwrap();
+        let id = created["id"].as_str().unwrap().to_string();
+        assert_eq!(created["role"], "USER");
+
+        let response = client.get(format!("/users/{}", id)).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let fetched: Value = response.into_json().await.unwrap();
+        assert_eq!(fetched["email"], "grace@example.com");
+
+        let response = client
+            .patch(format!("/users/{}", id))
+            .header(ContentType::JSON)
+            .body(json!({"role": "ADMIN"}).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let promoted: Value = response.into_json().await.unwrap();
+        assert_eq!(promoted["role"], "ADMIN");
+
+        let response = client.get("/users?email=grace&limit=50").dispatch().await;
+        let listed: Vec<Value> = response.into_json().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0]["role"], "ADMIN");
+
+        let response = client.delete(format!("/users/{}", id)).dispatch().await;
+        assert_eq!(response.status(), Status::NoContent);
+        let response = client.get(format!("/users/{}", id)).dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+}